@@ -0,0 +1,102 @@
+//! The `components.json` manifest describing a component library: each
+//! component's tag, props and slots, as `van pack` writes it next to the
+//! packed sources.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// The manifest of a packed component library.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentManifest {
+    /// The package name from `package.json` (e.g. `@van-ui/button`).
+    pub name: String,
+    pub version: String,
+    /// Components in path order.
+    pub components: Vec<ComponentInfo>,
+}
+
+/// One component of a [`ComponentManifest`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentInfo {
+    /// The kebab-case tag it is used as, from its file name (`card-header`).
+    pub tag: String,
+    /// Its path in the package, relative to the source directory
+    /// (`components/card-header.van`).
+    pub file: String,
+    pub props: Vec<PropInfo>,
+    /// Slot names in template order, `default` for the unnamed slot.
+    pub slots: Vec<String>,
+}
+
+/// A `defineProps` declaration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropInfo {
+    pub name: String,
+    /// `String`, `Number`, `Boolean`, `Array` or `Object`, when declared.
+    #[serde(rename = "type")]
+    pub prop_type: Option<String>,
+    pub required: bool,
+    /// The declared default, as written (a JS expression).
+    pub default: Option<String>,
+}
+
+impl ComponentInfo {
+    /// Describe the component at `file` from its source.
+    pub fn from_source(file: &str, source: &str) -> Self {
+        static SLOT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"<slot(?:\s+name="([\w-]+)")?[\s/>]"#).unwrap());
+        let blocks = van_parser::parse_blocks(source);
+        let stem = file.rsplit('/').next().unwrap_or(file).trim_end_matches(".van");
+        let mut slots: Vec<String> = Vec::new();
+        for cap in SLOT_RE.captures_iter(blocks.template.as_deref().unwrap_or_default()) {
+            let name = cap.get(1).map_or("default", |m| m.as_str());
+            if !slots.iter().any(|s| s == name) {
+                slots.push(name.to_string());
+            }
+        }
+        ComponentInfo {
+            tag: van_parser::pascal_to_kebab(stem),
+            file: file.to_string(),
+            props: blocks
+                .props
+                .into_iter()
+                .map(|p| PropInfo { name: p.name, prop_type: p.prop_type, required: p.required, default: p.default })
+                .collect(),
+            slots,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_component_info_from_source() {
+        let source = r#"<template>
+  <div class="card"><header><slot name="title">Untitled</slot></header><slot /><footer><slot name="actions"/></footer><slot name="title" /></div>
+</template>
+<script setup>
+defineProps({ title: { type: String, required: true }, size: { type: Number, default: 2 }, flat: Boolean })
+</script>"#;
+        let info = ComponentInfo::from_source("components/ui/CardPanel.van", source);
+        assert_eq!(info.tag, "card-panel");
+        assert_eq!(info.file, "components/ui/CardPanel.van");
+        assert_eq!(info.slots, vec!["title", "default", "actions"]);
+        let props: Vec<(&str, Option<&str>, bool, Option<&str>)> = info
+            .props
+            .iter()
+            .map(|p| (p.name.as_str(), p.prop_type.as_deref(), p.required, p.default.as_deref()))
+            .collect();
+        assert_eq!(
+            props,
+            vec![
+                ("title", Some("String"), true, None),
+                ("size", Some("Number"), false, Some("2")),
+                ("flat", Some("Boolean"), false, None),
+            ]
+        );
+        let json = serde_json::to_value(&info.props[0]).unwrap();
+        assert_eq!(json["type"], "String");
+    }
+}
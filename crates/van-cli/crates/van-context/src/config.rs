@@ -15,6 +15,99 @@ pub struct VanConfig {
     pub dev_dependencies: BTreeMap<String, String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub registry: Option<String>,
+    /// Van-specific settings from the optional `"van"` section.
+    #[serde(default, skip_serializing_if = "VanOptions::is_empty")]
+    pub van: VanOptions,
+}
+
+/// The `"van": { ... }` section of `package.json`.
+///
+/// Every field is optional and falls back to the conventional layout
+/// (`src/`, `src/pages/`, `dist/`, base `/`, port 3000).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VanOptions {
+    /// Source directory, relative to the project root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src_dir: Option<String>,
+    /// Output directory for generated files, relative to the project root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_dir: Option<String>,
+    /// Pages directory, relative to `srcDir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pages_dir: Option<String>,
+    /// URL prefix applied to asset paths and internal links (e.g. `/docs/`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Public origin of the site (e.g. `https://example.com`), used for
+    /// `sitemap.xml` and canonical links.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_url: Option<String>,
+    /// Output layout for `van generate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_style: Option<OutputStyle>,
+    /// Default port for `van dev`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev_port: Option<u16>,
+    /// Serve pages in `van dev` with CSS/JS as separate assets, as hosts do.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev_assets: Option<bool>,
+    /// Layout wrapped around pages that don't use one, e.g. `layouts/default.van`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_layout: Option<String>,
+    /// Global stylesheets emitted before any component style, relative to the
+    /// source directory (default: `styles/global.css` when it exists).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub css: Vec<String>,
+    /// Web component tags (or `prefix-*` patterns) used in templates, so they
+    /// are not reported as unknown components.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_elements: Vec<String>,
+    /// Bare module specifiers of script imports mapped to a URL, or to a
+    /// vendored file relative to the source directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub import_map: BTreeMap<String, String>,
+    /// Warn when a `v-if` / `v-show` condition relies on type coercion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict_coercion: Option<bool>,
+    /// Glob patterns for source files to skip (see [`crate::ignore::IgnoreRules`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Locale codes to generate (files in `locales/`); empty means every file found.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locales: Vec<String>,
+    /// Locale rendered at the site root and used for missing translations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_locale: Option<String>,
+}
+
+/// How `van generate` lays out page files and links between them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStyle {
+    /// `about.van` → `about/index.html`, linked as `/about/`.
+    #[default]
+    Nested,
+    /// `about.van` → `about.html`, linked as `/about.html`.
+    Flat,
+}
+
+impl std::str::FromStr for OutputStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nested" => Ok(Self::Nested),
+            "flat" => Ok(Self::Flat),
+            other => Err(format!("unknown output style `{other}` (expected `nested` or `flat`)")),
+        }
+    }
+}
+
+impl VanOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 impl VanConfig {
@@ -30,6 +123,7 @@ impl VanConfig {
             dependencies: BTreeMap::new(),
             dev_dependencies: BTreeMap::new(),
             registry: None,
+            van: VanOptions::default(),
         }
     }
 
@@ -37,3 +131,52 @@ impl VanConfig {
         Ok(serde_json::to_string_pretty(self)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_van_section_parses_with_unknown_keys() {
+        let raw = r#"{
+            "name": "site",
+            "version": "1.0.0",
+            "type": "module",
+            "van": {
+                "srcDir": "app",
+                "outDir": "public",
+                "pagesDir": "views",
+                "base": "/docs/",
+                "devPort": 4000,
+                "devAssets": true,
+                "autoLayout": "layouts/default.van",
+                "css": ["styles/reset.css", "styles/theme.css"],
+                "customElements": ["sl-*"],
+                "importMap": { "lodash-es": "vendor/lodash-es.js", "dayjs": "https://esm.sh/dayjs" },
+                "strictCoercion": true,
+                "somethingNew": true
+            }
+        }"#;
+        let config: VanConfig = serde_json::from_str(raw).unwrap();
+        assert_eq!(config.van.src_dir.as_deref(), Some("app"));
+        assert_eq!(config.van.out_dir.as_deref(), Some("public"));
+        assert_eq!(config.van.pages_dir.as_deref(), Some("views"));
+        assert_eq!(config.van.base.as_deref(), Some("/docs/"));
+        assert_eq!(config.van.dev_port, Some(4000));
+        assert_eq!(config.van.dev_assets, Some(true));
+        assert_eq!(config.van.auto_layout.as_deref(), Some("layouts/default.van"));
+        assert_eq!(config.van.css, vec!["styles/reset.css", "styles/theme.css"]);
+        assert_eq!(config.van.custom_elements, vec!["sl-*"]);
+        assert_eq!(config.van.import_map["lodash-es"], "vendor/lodash-es.js");
+        assert_eq!(config.van.import_map["dayjs"], "https://esm.sh/dayjs");
+        assert_eq!(config.van.strict_coercion, Some(true));
+    }
+
+    #[test]
+    fn test_van_section_is_optional() {
+        let config: VanConfig =
+            serde_json::from_str(r#"{"name": "site", "version": "1.0.0"}"#).unwrap();
+        assert!(config.van.is_empty());
+        assert!(!config.to_json_pretty().unwrap().contains("\"van\""));
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::path::Path;
+
+/// Patterns always ignored, in addition to hidden files.
+const DEFAULT_IGNORES: &[&str] = &["*.test.van"];
+
+/// Glob rules deciding which source files are left out of collection and watching.
+///
+/// Patterns follow `.gitignore` conventions loosely: a pattern without a `/`
+/// (like `*.bak`) matches the file name at any depth, while a pattern with one
+/// (like `**/drafts/**`) matches the path relative to the source directory.
+/// As in `.gitignore`, `*` and `?` never match a `/`, so `drafts/*` ignores
+/// `drafts/a.van` but not `drafts/a/b.van`; `**` spans directories.
+/// Hidden files and directories are always ignored.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl IgnoreRules {
+    /// Build rules from the defaults plus the given patterns.
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        let defaults = DEFAULT_IGNORES.iter().copied();
+        for pattern in defaults.chain(patterns.iter().map(|p| p.as_ref())) {
+            let pattern = pattern.trim();
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+            let pattern = pattern.trim_start_matches('/');
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid ignore pattern: {pattern}"))?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        Ok(Self {
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    /// Build rules from config patterns plus a `.vanignore` file in `root`, if present.
+    pub fn load<S: AsRef<str>>(root: &Path, config_patterns: &[S]) -> Result<Self> {
+        let mut patterns: Vec<String> = config_patterns
+            .iter()
+            .map(|p| p.as_ref().to_string())
+            .collect();
+        if let Ok(content) = fs::read_to_string(root.join(".vanignore")) {
+            patterns.extend(content.lines().map(str::to_string));
+        }
+        Self::new(&patterns)
+    }
+
+    /// Whether a path relative to the source directory (e.g. `pages/index.van`) is ignored.
+    pub fn is_ignored(&self, rel_path: &str) -> bool {
+        let rel_path = rel_path.trim_start_matches('/');
+        if rel_path.split('/').any(|segment| segment.starts_with('.')) {
+            return true;
+        }
+        let name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+        self.names.is_match(name) || self.paths.is_match(rel_path)
+    }
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self::new::<&str>(&[]).expect("default ignore patterns are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_ignores() {
+        let rules = IgnoreRules::default();
+        assert!(rules.is_ignored("components/button.test.van"));
+        assert!(rules.is_ignored(".hidden.van"));
+        assert!(rules.is_ignored("pages/.drafts/post.van"));
+        assert!(!rules.is_ignored("pages/index.van"));
+        assert!(!rules.is_ignored("utils/format.ts"));
+    }
+
+    #[test]
+    fn test_custom_patterns() {
+        let rules = IgnoreRules::new(&["**/drafts/**", "*.bak", "fixtures/*.van"]).unwrap();
+        assert!(rules.is_ignored("pages/drafts/post.van"));
+        assert!(rules.is_ignored("drafts/post.van"));
+        assert!(rules.is_ignored("pages/index.van.bak"));
+        assert!(rules.is_ignored("index.bak"));
+        assert!(rules.is_ignored("fixtures/card.van"));
+        assert!(!rules.is_ignored("pages/fixtures/card.van"));
+        assert!(!rules.is_ignored("pages/drafting.van"));
+        assert!(!rules.is_ignored("pages/index.van"));
+    }
+
+    #[test]
+    fn test_single_star_stays_in_its_directory() {
+        let rules = IgnoreRules::new(&["drafts/*", "pages/*/old.van"]).unwrap();
+        assert!(rules.is_ignored("drafts/a.van"));
+        assert!(!rules.is_ignored("drafts/a/b.van"));
+        assert!(rules.is_ignored("pages/blog/old.van"));
+        assert!(!rules.is_ignored("pages/blog/2024/old.van"));
+    }
+
+    #[test]
+    fn test_load_vanignore_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join(".vanignore"),
+            "# generated output\ngenerated/**\n\n*.orig\n",
+        )
+        .unwrap();
+        let rules = IgnoreRules::load(tmp.path(), &["*.bak"]).unwrap();
+        assert!(rules.is_ignored("generated/page.van"));
+        assert!(rules.is_ignored("pages/index.van.orig"));
+        assert!(rules.is_ignored("pages/index.van.bak"));
+        assert!(!rules.is_ignored("pages/index.van"));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(IgnoreRules::new(&["[unclosed"]).is_err());
+    }
+}
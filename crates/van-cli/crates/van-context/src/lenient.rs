@@ -0,0 +1,168 @@
+//! Lenient JSON for hand-maintained data files.
+//!
+//! Accepts `//` and `/* */` comments and trailing commas on top of plain JSON.
+//! Stripped characters are replaced with spaces (newlines are kept), so line and
+//! column numbers in parse errors still point into the original file.
+
+use serde_json::Value;
+
+/// Parse JSON that may contain comments and trailing commas.
+pub fn parse(source: &str) -> serde_json::Result<Value> {
+    serde_json::from_str(&strip_trailing_commas(&strip_comments(source)))
+}
+
+/// Describe a parse error as `line 3, column 3: expected ...`. serde's own
+/// message ends with the position, so that copy is dropped.
+pub fn describe(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    let message = message.strip_suffix(&position).unwrap_or(&message);
+    format!("line {}, column {}: {message}", error.line(), error.column())
+}
+
+/// Blank out comments that are not inside string literals.
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                out.push_str("  ");
+                chars.next();
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    out.push(blank(next));
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                out.push_str("  ");
+                chars.next();
+                while let Some(next) = chars.next() {
+                    if next == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        out.push_str("  ");
+                        break;
+                    }
+                    out.push(blank(next));
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Blank out commas directly followed (ignoring whitespace) by `]` or `}`.
+///
+/// Expects comments to have been stripped already.
+fn strip_trailing_commas(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut in_string = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                if let Some(&escaped) = chars.get(i + 1) {
+                    out.push(escaped);
+                    i += 1;
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if c == ',' {
+            let next = chars[i + 1..].iter().find(|ch| !ch.is_whitespace());
+            out.push(if matches!(next, Some(']' | '}')) { ' ' } else { c });
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Keep line structure intact when blanking out a character.
+fn blank(c: char) -> char {
+    if c == '\n' || c == '\r' {
+        c
+    } else {
+        ' '
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_comments() {
+        let source = r#"{
+  // TODO: real copy
+  "title": "Home", /* inline */
+  "url": "https://example.com/a//b",
+  "note": "not /* a comment */"
+}"#;
+        assert_eq!(
+            parse(source).unwrap(),
+            json!({
+                "title": "Home",
+                "url": "https://example.com/a//b",
+                "note": "not /* a comment */",
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_trailing_commas() {
+        let source = r#"{
+  "items": [1, 2, 3,],
+  "nested": { "a": "x,", },
+}"#;
+        assert_eq!(
+            parse(source).unwrap(),
+            json!({ "items": [1, 2, 3], "nested": { "a": "x," } })
+        );
+    }
+
+    #[test]
+    fn test_parse_escaped_quote_in_string() {
+        let source = r#"{ "q": "say \"hi\" // not a comment", }"#;
+        assert_eq!(parse(source).unwrap(), json!({ "q": "say \"hi\" // not a comment" }));
+    }
+
+    #[test]
+    fn test_error_position_is_preserved() {
+        let source = "{\n  // comment\n  \"a\": 1,\n  \"b\": oops\n}";
+        let err = parse(source).unwrap_err();
+        assert_eq!(err.line(), 4);
+        assert_eq!(err.column(), 8);
+        assert_eq!(describe(&err), "line 4, column 8: expected value");
+    }
+}
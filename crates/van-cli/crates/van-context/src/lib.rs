@@ -1,2 +1,6 @@
+pub mod catalog;
 pub mod config;
+pub mod ignore;
+pub mod lenient;
+pub mod locale;
 pub mod project;
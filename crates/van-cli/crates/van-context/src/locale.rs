@@ -0,0 +1,120 @@
+//! Locale message files (`locales/<code>.json`) and default-locale fallback.
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Read every `<code>.json` in `dir`, keyed by locale code. A missing directory is empty.
+pub fn load_dir(dir: &Path) -> Result<BTreeMap<String, Value>> {
+    let mut locales = BTreeMap::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(locales);
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(code) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let messages = crate::lenient::parse(&content).map_err(|e| {
+            anyhow::anyhow!("Failed to parse {} at {}", path.display(), crate::lenient::describe(&e))
+        })?;
+        locales.insert(code.to_string(), messages);
+    }
+    Ok(locales)
+}
+
+/// Fill keys missing from `messages` with those of `fallback` (recursively).
+///
+/// Returns the merged messages and the dotted keys that had to be taken from
+/// the fallback, so callers can warn about untranslated strings.
+pub fn with_fallback(messages: &Value, fallback: &Value) -> (Value, Vec<String>) {
+    let mut missing = Vec::new();
+    let merged = merge(messages, fallback, "", &mut missing);
+    (merged, missing)
+}
+
+/// Expose a locale to templates: `$i18n` holds the messages `$t()` reads, `$locale` the code.
+///
+/// Only object data is extended; anything else is left as it is.
+pub fn inject(data: &mut Value, code: &str, messages: Value) {
+    if let Value::Object(map) = data {
+        map.insert("$i18n".to_string(), messages);
+        map.insert("$locale".to_string(), Value::String(code.to_string()));
+    }
+}
+
+fn merge(messages: &Value, fallback: &Value, prefix: &str, missing: &mut Vec<String>) -> Value {
+    let (Value::Object(own), Value::Object(base)) = (messages, fallback) else {
+        return messages.clone();
+    };
+    let mut merged: Map<String, Value> = own.clone();
+    for (key, base_value) in base {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match own.get(key) {
+            Some(value) => {
+                merged.insert(key.clone(), merge(value, base_value, &path, missing));
+            }
+            None => {
+                collect_leaves(base_value, &path, missing);
+                merged.insert(key.clone(), base_value.clone());
+            }
+        }
+    }
+    Value::Object(merged)
+}
+
+fn collect_leaves(value: &Value, path: &str, out: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                collect_leaves(v, &format!("{path}.{key}"), out);
+            }
+        }
+        _ => out.push(path.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_with_fallback() {
+        let en = json!({ "nav": { "home": "Home", "about": "About" }, "footer": { "legal": "Legal" } });
+        let de = json!({ "nav": { "home": "Startseite" } });
+        let (merged, missing) = with_fallback(&de, &en);
+        assert_eq!(
+            merged,
+            json!({ "nav": { "home": "Startseite", "about": "About" }, "footer": { "legal": "Legal" } })
+        );
+        assert_eq!(missing, vec!["footer.legal", "nav.about"]);
+
+        let (merged, missing) = with_fallback(&en, &en);
+        assert_eq!(merged, en);
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("en.json"), r#"{ "hi": "Hello", }"#).unwrap();
+        fs::write(tmp.path().join("de.json"), r#"{ "hi": "Hallo" }"#).unwrap();
+        fs::write(tmp.path().join("README.md"), "ignored").unwrap();
+        let locales = load_dir(tmp.path()).unwrap();
+        assert_eq!(locales.keys().collect::<Vec<_>>(), vec!["de", "en"]);
+        assert_eq!(locales["en"], json!({ "hi": "Hello" }));
+        assert!(load_dir(&tmp.path().join("missing")).unwrap().is_empty());
+    }
+}
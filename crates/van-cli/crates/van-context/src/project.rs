@@ -1,12 +1,13 @@
-use crate::config::VanConfig;
+use crate::config::{OutputStyle, VanConfig};
+use crate::ignore::IgnoreRules;
 use anyhow::{bail, Context, Result};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 /// A loaded Van project, providing file collection and data utilities.
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct VanProject {
     pub root: PathBuf,
     pub config: VanConfig,
@@ -29,29 +30,72 @@ impl VanProject {
         })
     }
 
-    /// Load a Van project from the current working directory.
+    /// Load the Van project containing the current working directory.
+    ///
+    /// See [`VanProject::discover`].
     pub fn load_cwd() -> Result<Self> {
         let cwd = std::env::current_dir()?;
-        Self::load(&cwd)
+        Self::discover(&cwd)
+    }
+
+    /// Find the project containing `start` by walking up its ancestors.
+    ///
+    /// The first directory with a `package.json` that parses as a [`VanConfig`]
+    /// becomes the project root, so commands work from any subdirectory.
+    /// Unrelated `package.json` files (e.g. without a name) are skipped.
+    pub fn discover(start: &Path) -> Result<Self> {
+        let mut searched = Vec::new();
+        for dir in start.ancestors() {
+            let pkg_path = dir.join("package.json");
+            if let Ok(raw) = fs::read_to_string(&pkg_path) {
+                if let Ok(config) = serde_json::from_str::<VanConfig>(&raw) {
+                    return Ok(Self {
+                        root: dir.to_path_buf(),
+                        config,
+                    });
+                }
+            }
+            searched.push(dir.display().to_string());
+        }
+        bail!(
+            "No package.json found. Are you in a Van project directory?\n\
+             Searched:\n  {}",
+            searched.join("\n  ")
+        );
     }
 
-    /// Collect all source files (.van, .ts, .js) from `src/` and `node_modules/@scope/`.
+    /// Collect all source files (.van, .ts, .js, .md) from `src/` and `node_modules/@scope/`.
     ///
     /// Returns a HashMap keyed by relative path (e.g. `"pages/index.van"`).
+    /// Files matching the project's [ignore rules](Self::ignore_rules) are skipped.
     pub fn collect_files(&self) -> Result<HashMap<String, String>> {
+        self.collect_files_noted().map(|(files, _)| files)
+    }
+
+    /// Like [`collect_files`](Self::collect_files), also returning a note for
+    /// each file that was not valid UTF-8 and was read lossily, for the caller
+    /// to report.
+    pub fn collect_files_noted(&self) -> Result<(HashMap<String, String>, Vec<String>)> {
         let src_dir = self.src_dir();
         if !src_dir.exists() {
-            bail!("No src/ directory found.");
+            bail!("No {}/ directory found.", self.src_dir_name());
         }
+        let ignore = self.ignore_rules()?;
         let mut files = HashMap::new();
-        collect_files_recursive(&src_dir, &src_dir, &mut files)?;
+        let mut notes = Vec::new();
+        collect_files_recursive(&src_dir, &src_dir, &ignore, &mut files, &mut notes)?;
 
         let node_modules = self.root.join("node_modules");
         if node_modules.exists() {
-            collect_node_modules(&node_modules, &mut files)?;
+            collect_node_modules(&node_modules, &mut files, &mut notes)?;
         }
 
-        Ok(files)
+        Ok((files, notes))
+    }
+
+    /// Ignore rules from `van.ignore` in package.json plus a root `.vanignore` file.
+    pub fn ignore_rules(&self) -> Result<IgnoreRules> {
+        IgnoreRules::load(&self.root, &self.config.van.ignore)
     }
 
     /// Load page-specific data from `data/index.json`.
@@ -67,21 +111,196 @@ impl VanProject {
     }
 
     /// Load all data from `data/index.json`.
+    ///
+    /// The file may contain comments and trailing commas. If it still fails to
+    /// parse, a warning naming the position is printed and empty data is used.
     pub fn load_all_data(&self) -> Value {
         let data_path = self.root.join("data/index.json");
         let content = match fs::read_to_string(&data_path) {
             Ok(c) => c,
             Err(_) => return Value::Object(Default::default()),
         };
-        match serde_json::from_str(&content) {
+        match parse_data_file(&data_path, &content) {
             Ok(v) => v,
-            Err(_) => Value::Object(Default::default()),
+            Err(warning) => {
+                eprintln!("\x1b[33m  \u{26a0} {warning}\x1b[0m");
+                Value::Object(Default::default())
+            }
         }
     }
 
-    /// Find all page entries (files under `pages/` with `.van` extension).
+    /// Store `data` as the page-specific data of `page_key` in `data/index.json`,
+    /// keeping the other keys.
+    ///
+    /// The file is rewritten as plain JSON, so one that does not parse, or
+    /// that only parses leniently (comments, trailing commas), is left alone
+    /// and an error returned instead of losing its content.
+    pub fn save_data(&self, page_key: &str, data: Value) -> Result<()> {
+        let data_dir = self.root.join("data");
+        let data_path = data_dir.join("index.json");
+        let mut all = match fs::read_to_string(&data_path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(all) => all,
+                Err(_) => match crate::lenient::parse(&content) {
+                    Ok(_) => bail!(
+                        "{} has comments or trailing commas, which rewriting it would drop; edit it by hand",
+                        data_path.display()
+                    ),
+                    Err(e) => bail!(
+                        "Failed to parse {} at {}; not rewriting it",
+                        data_path.display(),
+                        crate::lenient::describe(&e)
+                    ),
+                },
+            },
+            Err(_) => Value::Object(Default::default()),
+        };
+        let Value::Object(map) = &mut all else {
+            bail!("data/index.json is not an object");
+        };
+        map.insert(page_key.to_string(), data);
+        fs::create_dir_all(&data_dir)
+            .with_context(|| format!("Failed to create {}", data_dir.display()))?;
+        fs::write(&data_path, serde_json::to_string_pretty(&all)? + "\n")
+            .with_context(|| format!("Failed to write {}", data_path.display()))
+    }
+
+    /// Find all page entries (`.van` files under the pages directory).
+    ///
+    /// Partials (any path segment starting with `_`, e.g. `pages/blog/_card.van`)
+    /// are not pages, though they remain in the files map for imports.
     pub fn page_entries(&self, files: &HashMap<String, String>) -> Vec<String> {
-        find_van_files(files, "pages/")
+        let mut entries = find_van_files(files, &self.pages_prefix());
+        entries.retain(|entry| !is_partial(entry));
+        entries
+    }
+
+    /// The URL route a page entry is served at.
+    ///
+    /// `pages/index.van` -> `/`, `pages/about.van` -> `/about`,
+    /// `pages/blog/index.van` -> `/blog`.
+    pub fn route_for_entry(&self, entry: &str) -> String {
+        route_for_entry(&self.pages_prefix(), entry)
+    }
+
+    /// Fail if two page entries map to the same route.
+    ///
+    /// Routes are compared case-insensitively, since `About.van` and `about.van`
+    /// overwrite each other on case-insensitive filesystems.
+    pub fn check_routes(&self, entries: &[String]) -> Result<()> {
+        let conflicts = route_conflicts(&self.pages_prefix(), entries);
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        let listing: Vec<String> = conflicts
+            .iter()
+            .map(|(route, files)| format!("  {route}: {}", files.join(", ")))
+            .collect();
+        bail!("Conflicting page routes:\n{}", listing.join("\n"))
+    }
+
+    /// The files-map key prefix for pages, e.g. `"pages/"`.
+    pub fn pages_prefix(&self) -> String {
+        let dir = self.config.van.pages_dir.as_deref().unwrap_or("pages");
+        format!("{}/", dir.trim_matches('/'))
+    }
+
+    /// The files-map key of a page by name, e.g. `"about"` -> `"pages/about.van"`.
+    pub fn page_entry(&self, name: &str) -> String {
+        format!("{}{name}.van", self.pages_prefix())
+    }
+
+    /// The URL prefix for generated links and assets, always with leading and trailing `/`.
+    pub fn base(&self) -> String {
+        let base = self.config.van.base.as_deref().unwrap_or("/").trim_matches('/');
+        if base.is_empty() {
+            "/".into()
+        } else {
+            format!("/{base}/")
+        }
+    }
+
+    /// The configured public site URL, if any.
+    pub fn site_url(&self) -> Option<&str> {
+        self.config.van.site_url.as_deref()
+    }
+
+    /// The configured output layout for `van generate`.
+    pub fn output_style(&self) -> OutputStyle {
+        self.config.van.output_style.unwrap_or_default()
+    }
+
+    /// The configured dev server port, if any.
+    pub fn dev_port(&self) -> Option<u16> {
+        self.config.van.dev_port
+    }
+
+    /// Whether `van dev` serves pages with separate CSS/JS assets.
+    pub fn dev_assets(&self) -> bool {
+        self.config.van.dev_assets.unwrap_or(false)
+    }
+
+    /// Layout to wrap pages in (`van.autoLayout`), relative to the source directory.
+    pub fn auto_layout(&self) -> Option<String> {
+        self.config.van.auto_layout.clone()
+    }
+
+    /// Web component tags not to report as unknown (`van.customElements`).
+    pub fn custom_elements(&self) -> Vec<String> {
+        self.config.van.custom_elements.clone()
+    }
+
+    /// Bare import specifiers mapped to URLs or vendored files (`van.importMap`).
+    pub fn import_map(&self) -> BTreeMap<String, String> {
+        self.config.van.import_map.clone()
+    }
+
+    /// Whether to warn about type coercion in conditions (`van.strictCoercion`).
+    pub fn strict_coercion(&self) -> bool {
+        self.config.van.strict_coercion.unwrap_or(false)
+    }
+
+    /// Build-time environment variables exposed to templates and scripts.
+    ///
+    /// Reads `VAN_`-prefixed entries from a root `.env` file; process
+    /// environment variables of the same name take precedence.
+    pub fn load_env(&self) -> HashMap<String, String> {
+        let mut env = fs::read_to_string(self.root.join(".env"))
+            .map(|content| parse_env_file(&content))
+            .unwrap_or_default();
+        env.extend(std::env::vars().filter(|(k, _)| k.starts_with(ENV_PREFIX)));
+        env
+    }
+
+    /// Locale messages from `locales/<code>.json`, limited to `van.locales` when set.
+    pub fn load_locales(&self) -> Result<BTreeMap<String, Value>> {
+        let mut locales = crate::locale::load_dir(&self.root.join("locales"))?;
+        let wanted = &self.config.van.locales;
+        if !wanted.is_empty() {
+            for code in wanted {
+                if !locales.contains_key(code) {
+                    bail!("Locale \"{code}\" is configured but locales/{code}.json does not exist");
+                }
+            }
+            locales.retain(|code, _| wanted.contains(code));
+        }
+        Ok(locales)
+    }
+
+    /// The default locale: `van.defaultLocale`, else the first configured locale, else `"en"`.
+    pub fn default_locale(&self) -> String {
+        let van = &self.config.van;
+        van.default_locale
+            .clone()
+            .or_else(|| van.locales.first().cloned())
+            .unwrap_or_else(|| "en".to_string())
+    }
+
+    /// Messages of the default locale, if `locales/` provides them.
+    pub fn default_messages(&self) -> Result<Option<(String, Value)>> {
+        let code = self.default_locale();
+        let mut locales = self.load_locales()?;
+        Ok(locales.remove(&code).map(|messages| (code, messages)))
     }
 
     /// Find all component entries (files under `components/` with `.van` extension).
@@ -89,40 +308,121 @@ impl VanProject {
         find_van_files(files, "components/")
     }
 
+    /// Global stylesheets (`van.css`), or `styles/global.css` when that is in
+    /// `files` and none are configured. Paths are relative to the source directory.
+    pub fn global_css(&self, files: &HashMap<String, String>) -> Vec<String> {
+        if !self.config.van.css.is_empty() {
+            return self.config.van.css.clone();
+        }
+        let default = "styles/global.css";
+        if files.contains_key(default) { vec![default.to_string()] } else { Vec::new() }
+    }
+
     pub fn src_dir(&self) -> PathBuf {
-        self.root.join("src")
+        self.root.join(self.src_dir_name())
     }
 
     pub fn pages_dir(&self) -> PathBuf {
-        self.root.join("src").join("pages")
+        self.src_dir().join(self.pages_prefix().trim_end_matches('/'))
     }
 
     pub fn dist_dir(&self) -> PathBuf {
-        self.root.join("dist")
+        self.root
+            .join(self.config.van.out_dir.as_deref().unwrap_or("dist"))
+    }
+
+    fn src_dir_name(&self) -> &str {
+        self.config.van.src_dir.as_deref().unwrap_or("src")
     }
 }
 
-/// Recursively collect source files (.van, .ts, .js) into the map.
+/// See [`VanProject::route_for_entry`].
+fn route_for_entry(prefix: &str, entry: &str) -> String {
+    let name = entry
+        .strip_prefix(prefix)
+        .unwrap_or(entry)
+        .trim_end_matches(".van");
+    let name = name
+        .strip_suffix("/index")
+        .unwrap_or(if name == "index" { "" } else { name });
+    format!("/{name}")
+}
+
+/// Routes claimed by more than one entry, with the entries claiming them.
+fn route_conflicts(prefix: &str, entries: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut by_route: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in entries {
+        by_route
+            .entry(route_for_entry(prefix, entry).to_lowercase())
+            .or_default()
+            .push(entry.clone());
+    }
+    by_route.into_iter().filter(|(_, files)| files.len() > 1).collect()
+}
+
+/// Only variables with this prefix are exposed, so secrets in `.env` stay out of pages.
+const ENV_PREFIX: &str = "VAN_";
+
+/// Parse `KEY=VALUE` lines, skipping blanks, `#` comments and non-`VAN_` keys.
+/// Values may be wrapped in single or double quotes.
+fn parse_env_file(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim().trim_start_matches("export ").trim();
+            if !key.starts_with(ENV_PREFIX) {
+                return None;
+            }
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+                .unwrap_or(value);
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Leniently parse a data file, describing where it broke on failure.
+fn parse_data_file(path: &Path, content: &str) -> std::result::Result<Value, String> {
+    crate::lenient::parse(content).map_err(|e| {
+        format!(
+            "Failed to parse {} at {}. Rendering with empty data.",
+            path.display(),
+            crate::lenient::describe(&e)
+        )
+    })
+}
+
+/// Recursively collect source files (.van, .ts, .js, .md) into the map.
 /// Keys are relative to `base` (e.g. `pages/index.van`).
 fn collect_files_recursive(
     dir: &Path,
     base: &Path,
+    ignore: &IgnoreRules,
     files: &mut HashMap<String, String>,
+    notes: &mut Vec<String>,
 ) -> Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
+        let rel = path
+            .strip_prefix(base)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if ignore.is_ignored(&rel) {
+            continue;
+        }
         if path.is_dir() {
-            collect_files_recursive(&path, base, files)?;
+            collect_files_recursive(&path, base, ignore, files, notes)?;
         } else if is_source_file(&path) {
-            let rel = path
-                .strip_prefix(base)
-                .unwrap_or(&path)
-                .to_string_lossy()
-                .replace('\\', "/");
-            let content = fs::read_to_string(&path)
-                .with_context(|| format!("Failed to read {}", path.display()))?;
-            files.insert(rel, content);
+            files.insert(rel, read_source(&path, notes)?);
         }
     }
     Ok(())
@@ -133,6 +433,7 @@ fn collect_files_recursive(
 fn collect_node_modules(
     node_modules: &Path,
     files: &mut HashMap<String, String>,
+    notes: &mut Vec<String>,
 ) -> Result<()> {
     for scope_entry in fs::read_dir(node_modules)? {
         let scope_entry = scope_entry?;
@@ -147,7 +448,7 @@ fn collect_node_modules(
             }
             let pkg_dir = pkg_entry.path();
             let pkg_name = pkg_dir.file_name().unwrap().to_string_lossy().to_string();
-            collect_scoped_package_recursive(&pkg_dir, &scope_name, &pkg_name, &pkg_dir, files)?;
+            collect_scoped_package_recursive(&pkg_dir, &scope_name, &pkg_name, &pkg_dir, files, notes)?;
         }
     }
     Ok(())
@@ -159,12 +460,13 @@ fn collect_scoped_package_recursive(
     pkg_name: &str,
     pkg_dir: &Path,
     files: &mut HashMap<String, String>,
+    notes: &mut Vec<String>,
 ) -> Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
         if path.is_dir() {
-            collect_scoped_package_recursive(&path, scope_name, pkg_name, pkg_dir, files)?;
+            collect_scoped_package_recursive(&path, scope_name, pkg_name, pkg_dir, files, notes)?;
         } else if is_source_file(&path) {
             let rel = path
                 .strip_prefix(pkg_dir)
@@ -172,17 +474,40 @@ fn collect_scoped_package_recursive(
                 .to_string_lossy()
                 .replace('\\', "/");
             let key = format!("{}/{}/{}", scope_name, pkg_name, rel);
-            let content = fs::read_to_string(&path)?;
-            files.insert(key, content);
+            files.insert(key, read_source(&path, notes)?);
         }
     }
     Ok(())
 }
 
+/// Read a source file normalized by [`van_parser::normalize_source`] (no BOM,
+/// `\n` line endings). Invalid UTF-8 is replaced with `U+FFFD` and a note is
+/// pushed to `notes`, so one bad byte doesn't fail the whole collection.
+fn read_source(path: &Path, notes: &mut Vec<String>) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => {
+            let valid = e.utf8_error().valid_up_to();
+            notes.push(format!(
+                "{} is not valid UTF-8 (byte {valid}); invalid bytes were replaced",
+                path.display()
+            ));
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    };
+    Ok(van_parser::normalize_source(&content))
+}
+
+/// Whether any segment of a files-map key starts with `_` (e.g. `pages/_parts/card.van`).
+fn is_partial(key: &str) -> bool {
+    key.split('/').any(|segment| segment.starts_with('_'))
+}
+
 fn is_source_file(path: &Path) -> bool {
     matches!(
         path.extension().and_then(|e| e.to_str()),
-        Some("van" | "ts" | "js")
+        Some("van" | "ts" | "js" | "md" | "css")
     )
 }
 
@@ -205,8 +530,250 @@ mod tests {
         assert!(is_source_file(Path::new("foo.van")));
         assert!(is_source_file(Path::new("bar.ts")));
         assert!(is_source_file(Path::new("baz.js")));
-        assert!(!is_source_file(Path::new("readme.md")));
-        assert!(!is_source_file(Path::new("style.css")));
+        assert!(is_source_file(Path::new("intro.md")));
+        assert!(is_source_file(Path::new("style.css")));
+        assert!(!is_source_file(Path::new("logo.png")));
+    }
+
+    #[test]
+    fn test_route_for_entry() {
+        assert_eq!(route_for_entry("pages/", "pages/index.van"), "/");
+        assert_eq!(route_for_entry("pages/", "pages/about.van"), "/about");
+        assert_eq!(route_for_entry("pages/", "pages/blog/index.van"), "/blog");
+        assert_eq!(route_for_entry("views/", "views/about.van"), "/about");
+    }
+
+    #[test]
+    fn test_route_conflicts_file_and_index() {
+        let entries = vec![
+            "pages/about.van".to_string(),
+            "pages/about/index.van".to_string(),
+            "pages/index.van".to_string(),
+        ];
+        assert_eq!(
+            route_conflicts("pages/", &entries),
+            vec![(
+                "/about".to_string(),
+                vec!["pages/about.van".to_string(), "pages/about/index.van".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_route_conflicts_case_insensitive() {
+        let entries = vec!["pages/About.van".to_string(), "pages/about.van".to_string()];
+        let conflicts = route_conflicts("pages/", &entries);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].1, entries);
+        assert!(route_conflicts("pages/", &entries[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_check_routes_lists_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(tmp.path(), r#"{"name": "app", "version": "0.1.0"}"#);
+        let project = VanProject::load(tmp.path()).unwrap();
+        let entries = vec!["pages/blog.van".to_string(), "pages/blog/index.van".to_string()];
+        let err = project.check_routes(&entries).unwrap_err().to_string();
+        assert!(err.contains("/blog: pages/blog.van, pages/blog/index.van"), "{err}");
+        assert!(project.check_routes(&entries[..1]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let env = parse_env_file(
+            "# site settings\n\
+             VAN_SITE_NAME=\"My Site\"\n\
+             export VAN_API_URL='https://api.example.com'\n\
+             VAN_EMPTY=\n\
+             SECRET_TOKEN=abc\n\
+             \n\
+             not a pair\n",
+        );
+        assert_eq!(env.len(), 3);
+        assert_eq!(env["VAN_SITE_NAME"], "My Site");
+        assert_eq!(env["VAN_API_URL"], "https://api.example.com");
+        assert_eq!(env["VAN_EMPTY"], "");
+        assert!(!env.contains_key("SECRET_TOKEN"));
+    }
+
+    fn write_package(dir: &Path, json: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("package.json"), json).unwrap();
+    }
+
+    #[test]
+    fn test_discover_from_nested_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("app");
+        write_package(&root, r#"{"name": "app", "version": "0.1.0"}"#);
+        let nested = root.join("src/pages");
+        fs::create_dir_all(&nested).unwrap();
+
+        let project = VanProject::discover(&nested).unwrap();
+        assert_eq!(project.root, root);
+        assert_eq!(project.config.name, "app");
+        assert_eq!(project.dist_dir(), root.join("dist"));
+    }
+
+    #[test]
+    fn test_discover_skips_non_van_package_json() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("app");
+        write_package(&root, r#"{"name": "app", "version": "0.1.0"}"#);
+        // A nested package.json without a name (e.g. `{"type": "module"}`) is not a project.
+        let nested = root.join("src/lib");
+        write_package(&nested, r#"{"type": "module"}"#);
+
+        let project = VanProject::discover(&nested).unwrap();
+        assert_eq!(project.root, root);
+    }
+
+    #[test]
+    fn test_discover_not_found_lists_searched_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let nested = tmp.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+
+        let err = VanProject::discover(&nested).unwrap_err().to_string();
+        assert!(err.contains("No package.json found"));
+        assert!(err.contains(&nested.display().to_string()));
+        assert!(err.contains(&tmp.path().display().to_string()));
+    }
+
+    #[test]
+    fn test_van_options_override_layout() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(
+            tmp.path(),
+            r#"{"name": "app", "version": "0.1.0",
+                "van": {"srcDir": "app", "outDir": "public", "pagesDir": "views", "base": "docs"}}"#,
+        );
+        let project = VanProject::load(tmp.path()).unwrap();
+        assert_eq!(project.src_dir(), tmp.path().join("app"));
+        assert_eq!(project.pages_dir(), tmp.path().join("app/views"));
+        assert_eq!(project.dist_dir(), tmp.path().join("public"));
+        assert_eq!(project.pages_prefix(), "views/");
+        assert_eq!(project.page_entry("about"), "views/about.van");
+        assert_eq!(project.base(), "/docs/");
+
+        let mut files = HashMap::new();
+        files.insert("views/index.van".into(), String::new());
+        files.insert("pages/old.van".into(), String::new());
+        assert_eq!(project.page_entries(&files), vec!["views/index.van"]);
+    }
+
+    #[test]
+    fn test_van_options_defaults() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(tmp.path(), r#"{"name": "app", "version": "0.1.0"}"#);
+        let project = VanProject::load(tmp.path()).unwrap();
+        assert_eq!(project.src_dir(), tmp.path().join("src"));
+        assert_eq!(project.pages_dir(), tmp.path().join("src/pages"));
+        assert_eq!(project.dist_dir(), tmp.path().join("dist"));
+        assert_eq!(project.base(), "/");
+        assert_eq!(project.dev_port(), None);
+        assert!(!project.dev_assets());
+        assert!(!project.strict_coercion());
+        assert_eq!(project.auto_layout(), None);
+        assert!(project.global_css(&HashMap::new()).is_empty());
+        let files = HashMap::from([("styles/global.css".to_string(), String::new())]);
+        assert_eq!(project.global_css(&files), vec!["styles/global.css"]);
+    }
+
+    #[test]
+    fn test_collect_files_applies_ignore_rules() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(
+            tmp.path(),
+            r#"{"name": "app", "version": "0.1.0", "van": {"ignore": ["**/drafts/**"]}}"#,
+        );
+        fs::write(tmp.path().join(".vanignore"), "*.bak.van\n").unwrap();
+        let src = tmp.path().join("src");
+        for file in [
+            "pages/index.van",
+            "pages/drafts/wip.van",
+            "pages/old.bak.van",
+            "pages/.index.van",
+            "components/button.test.van",
+            "components/button.van",
+        ] {
+            let path = src.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "<template></template>").unwrap();
+        }
+
+        let project = VanProject::load(tmp.path()).unwrap();
+        let mut keys: Vec<_> = project.collect_files().unwrap().into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["components/button.van", "pages/index.van"]);
+    }
+
+    #[test]
+    fn test_collect_files_normalizes_sources() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(tmp.path(), r#"{"name": "app", "version": "0.1.0"}"#);
+        let pages = tmp.path().join("src/pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("index.van"), "\u{feff}<template>\r\n  <p>Hi</p>\r\n</template>\r\n").unwrap();
+        fs::write(pages.join("latin1.van"), b"<template><p>Caf\xe9</p></template>\n").unwrap();
+
+        let (files, notes) = VanProject::load(tmp.path()).unwrap().collect_files_noted().unwrap();
+        assert_eq!(files["pages/index.van"], "<template>\n  <p>Hi</p>\n</template>\n");
+        assert_eq!(files["pages/latin1.van"], "<template><p>Caf\u{fffd}</p></template>\n");
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].ends_with("latin1.van is not valid UTF-8 (byte 16); invalid bytes were replaced"));
+    }
+
+    #[test]
+    fn test_load_all_data_lenient() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(tmp.path(), r#"{"name": "app", "version": "0.1.0"}"#);
+        fs::create_dir_all(tmp.path().join("data")).unwrap();
+        fs::write(
+            tmp.path().join("data/index.json"),
+            "{\n  // TODO: real title\n  \"pages/index\": { \"title\": \"Home\", },\n}",
+        )
+        .unwrap();
+        let project = VanProject::load(tmp.path()).unwrap();
+        assert_eq!(project.load_data("pages/index")["title"], "Home");
+    }
+
+    #[test]
+    fn test_save_data_keeps_other_pages() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(tmp.path(), r#"{"name": "app", "version": "0.1.0"}"#);
+        let project = VanProject::load(tmp.path()).unwrap();
+        project.save_data("pages/about", serde_json::json!({ "title": "About" })).unwrap();
+        project.save_data("pages/index", serde_json::json!({ "title": "Home" })).unwrap();
+        assert_eq!(project.load_data("pages/about")["title"], "About");
+        assert_eq!(project.load_data("pages/index")["title"], "Home");
+    }
+
+    #[test]
+    fn test_save_data_leaves_unparsable_or_commented_files_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(tmp.path(), r#"{"name": "app", "version": "0.1.0"}"#);
+        fs::create_dir_all(tmp.path().join("data")).unwrap();
+        let project = VanProject::load(tmp.path()).unwrap();
+        let index = tmp.path().join("data/index.json");
+        for content in [
+            "{ \"pages/index\": { \"title\": \"Home\" } \"oops\" }",
+            "{\n  // draft\n  \"pages/index\": { \"title\": \"Home\" },\n}",
+        ] {
+            fs::write(&index, content).unwrap();
+            assert!(project.save_data("pages/about", serde_json::json!({ "title": "About" })).is_err());
+            assert_eq!(fs::read_to_string(&index).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn test_parse_data_file_reports_position() {
+        let path = Path::new("data/index.json");
+        let warning = parse_data_file(path, "{\n  \"title\": \"Home\"\n  \"oops\": 1\n}").unwrap_err();
+        assert!(warning.contains("data/index.json"), "{warning}");
+        assert!(warning.contains(" at line 3, column 3: expected"), "{warning}");
+        assert_eq!(warning.matches("line 3").count(), 1, "{warning}");
     }
 
     #[test]
@@ -223,4 +790,22 @@ mod tests {
         let components = find_van_files(&files, "components/");
         assert_eq!(components, vec!["components/header.van"]);
     }
+
+    #[test]
+    fn test_page_entries_skip_partials() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_package(tmp.path(), r#"{"name": "app", "version": "0.1.0"}"#);
+        let project = VanProject::load(tmp.path()).unwrap();
+        let mut files = HashMap::new();
+        files.insert("pages/blog/index.van".into(), String::new());
+        files.insert("pages/blog/_post-card.van".into(), String::new());
+        files.insert("pages/_parts/hero.van".into(), String::new());
+        files.insert("components/_base.van".into(), String::new());
+
+        assert_eq!(project.page_entries(&files), vec!["pages/blog/index.van"]);
+        // Only pages have partials
+        assert_eq!(project.component_entries(&files), vec!["components/_base.van"]);
+        assert!(is_partial("pages/_parts/hero.van"));
+        assert!(!is_partial("pages/under_score.van"));
+    }
 }
@@ -0,0 +1,392 @@
+//! Machine-readable project info for editor tooling and the playground.
+//!
+//! Mounted under `/__van/api/`. Every response is JSON; failures are
+//! `{"error": "..."}` with an appropriate status code.
+
+use crate::server::{overlay_data, AppState};
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+pub(crate) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/pages", get(pages_handler))
+        .route("/components", get(components_handler))
+        .route("/file", get(file_handler))
+        .route("/data", get(data_handler).put(put_data_handler).delete(delete_data_handler))
+        .route("/inspect", get(inspect_handler))
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+fn collect_error(e: anyhow::Error) -> Response {
+    error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Failed to collect files: {e:#}"),
+    )
+}
+
+/// Normalize a `page` query value (`about`, `pages/about`, `pages/about.van`) to its data key.
+fn page_key(prefix: &str, page: &str) -> String {
+    let name = page
+        .strip_prefix(prefix)
+        .unwrap_or(page)
+        .trim_end_matches(".van");
+    format!("{prefix}{name}")
+}
+
+/// `GET /pages` — page entries, their routes, and whether mock data exists for them.
+async fn pages_handler(State(state): State<AppState>) -> Response {
+    let project = state.project();
+    let files = match project.collect_files() {
+        Ok(f) => f,
+        Err(e) => return collect_error(e),
+    };
+    let prefix = project.pages_prefix();
+    let all_data = project.load_all_data();
+    let pages: Vec<Value> = project
+        .page_entries(&files)
+        .into_iter()
+        .map(|entry| {
+            let key = page_key(&prefix, &entry);
+            json!({
+                "entry": entry,
+                "route": project.route_for_entry(&entry),
+                "hasData": all_data.get(&key).is_some(),
+            })
+        })
+        .collect();
+    Json(pages).into_response()
+}
+
+/// `GET /components` — component entries with their `defineProps` declarations.
+async fn components_handler(State(state): State<AppState>) -> Response {
+    let files = match state.project().collect_files() {
+        Ok(f) => f,
+        Err(e) => return collect_error(e),
+    };
+    let components: Vec<Value> = state
+        .project()
+        .component_entries(&files)
+        .into_iter()
+        .map(|entry| {
+            let blocks = van_parser::parse_blocks(&files[&entry]);
+            let props: Vec<Value> = blocks
+                .props
+                .iter()
+                .map(|p| json!({ "name": p.name, "type": p.prop_type, "required": p.required }))
+                .collect();
+            json!({ "entry": entry, "props": props })
+        })
+        .collect();
+    Json(components).into_response()
+}
+
+/// `GET /file?path=` — the source of a collected file.
+///
+/// Only keys of the collected files map are served, so paths outside the
+/// project sources (or traversal attempts) are simply not found.
+async fn file_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let Some(path) = query.get("path") else {
+        return error(StatusCode::BAD_REQUEST, "Missing `path` query parameter");
+    };
+    let mut files = match state.project().collect_files() {
+        Ok(f) => f,
+        Err(e) => return collect_error(e),
+    };
+    match files.remove(path) {
+        Some(content) => Json(json!({ "path": path, "content": content })).into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("File not found: {path}")),
+    }
+}
+
+/// `GET /inspect?path=` — a `.van` file's blocks, imports, props, reactive
+/// names and template bindings (see [`van_compiler::inspect`]).
+async fn inspect_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let Some(path) = query.get("path") else {
+        return error(StatusCode::BAD_REQUEST, "Missing `path` query parameter");
+    };
+    let files = match state.project().collect_files() {
+        Ok(f) => f,
+        Err(e) => return collect_error(e),
+    };
+    match files.get(path).filter(|_| path.ends_with(".van")) {
+        Some(source) => Json(van_compiler::inspect(source)).into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("File not found: {path}")),
+    }
+}
+
+/// The data key of the page named by the `page` query parameter, or the
+/// status and message to respond with when it is missing or names no page.
+fn data_page(state: &AppState, query: &HashMap<String, String>) -> Result<String, (StatusCode, String)> {
+    let Some(page) = query.get("page") else {
+        return Err((StatusCode::BAD_REQUEST, "Missing `page` query parameter".to_string()));
+    };
+    let project = state.project();
+    let files = project.collect_files().map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to collect files: {e:#}"))
+    })?;
+    let key = page_key(&project.pages_prefix(), page);
+    if !files.contains_key(&format!("{key}.van")) {
+        return Err((StatusCode::NOT_FOUND, format!("Page not found: {page}")));
+    }
+    Ok(key)
+}
+
+/// `GET /data?page=` — the mock data a page is rendered with.
+async fn data_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    match data_page(&state, &query) {
+        Ok(key) => Json(state.page_data(&state.project(), &key)).into_response(),
+        Err((status, message)) => error(status, message),
+    }
+}
+
+/// `PUT /data?page=` — layer the JSON body over a page's mock data and reload
+/// the tabs showing it. The overlay lives in memory until `DELETE /data`;
+/// with `&persist=1` the layered data is written to `data/index.json` instead.
+async fn put_data_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> Response {
+    let key = match data_page(&state, &query) {
+        Ok(key) => key,
+        Err((status, message)) => return error(status, message),
+    };
+    let overlay: Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => return error(StatusCode::BAD_REQUEST, format!("Invalid JSON body: {e}")),
+    };
+    let project = state.project();
+    if matches!(query.get("persist").map(String::as_str), Some("1" | "true")) {
+        // Only the page's own entry, not the whole-file fallback pages without one get
+        let mut own = project.load_all_data().get(&key).cloned().unwrap_or_else(|| json!({}));
+        overlay_data(&mut own, &overlay);
+        if let Err(e) = project.save_data(&key, own) {
+            return error(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}"));
+        }
+        state.set_data_overlay(&key, None);
+    } else {
+        state.set_data_overlay(&key, Some(overlay));
+    }
+    state.reload_page(&key);
+    Json(state.page_data(&project, &key)).into_response()
+}
+
+/// `DELETE /data?page=` — drop a page's overlay, back to `data/index.json`.
+async fn delete_data_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    match data_page(&state, &query) {
+        Ok(key) => {
+            state.set_data_overlay(&key, None);
+            state.reload_page(&key);
+            Json(state.page_data(&state.project(), &key)).into_response()
+        }
+        Err((status, message)) => error(status, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::build_router;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use std::fs;
+    use tower::ServiceExt;
+    use van_context::project::VanProject;
+
+    fn scaffold() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/pages/blog")).unwrap();
+        fs::create_dir_all(root.join("src/components")).unwrap();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"name": "demo", "version": "0.1.0"}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/index.van"),
+            "<template><h1>{{ title }}</h1></template>",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/blog/index.van"),
+            "<template><p>Blog</p></template>",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/components/button.van"),
+            "<template><button>{{ label }}</button></template>\n\
+             <script setup>\ndefineProps({ label: { type: String, required: true }, size: Number })\n</script>",
+        )
+        .unwrap();
+        fs::write(
+            root.join("data/index.json"),
+            r#"{"pages/index": {"title": "Home"}}"#,
+        )
+        .unwrap();
+        dir
+    }
+
+    async fn get_json(dir: &tempfile::TempDir, uri: &str) -> (StatusCode, Value) {
+        let project = VanProject::load(dir.path()).unwrap();
+        let app = build_router(AppState::new(project));
+        let resp = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = resp.status();
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "application/json",
+            "{uri} should respond with JSON"
+        );
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_api_pages() {
+        let dir = scaffold();
+        let (status, body) = get_json(&dir, "/__van/api/pages").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!([
+                { "entry": "pages/blog/index.van", "route": "/blog", "hasData": false },
+                { "entry": "pages/index.van", "route": "/", "hasData": true },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_components() {
+        let dir = scaffold();
+        let (status, body) = get_json(&dir, "/__van/api/components").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["entry"], "components/button.van");
+        assert_eq!(
+            body[0]["props"],
+            json!([
+                { "name": "label", "type": "String", "required": true },
+                { "name": "size", "type": "Number", "required": false },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_file() {
+        let dir = scaffold();
+        let (status, body) = get_json(&dir, "/__van/api/file?path=pages/index.van").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["content"], "<template><h1>{{ title }}</h1></template>");
+
+        let (status, body) = get_json(&dir, "/__van/api/file?path=../package.json").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body["error"].as_str().unwrap().contains("not found"));
+
+        let (status, _) = get_json(&dir, "/__van/api/file").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_api_data() {
+        let dir = scaffold();
+        let (status, body) = get_json(&dir, "/__van/api/data?page=index").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "title": "Home" }));
+
+        let (status, _) = get_json(&dir, "/__van/api/data?page=missing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    /// Status and body of a request through a shared router.
+    async fn send(app: &Router, method: &str, uri: &str, body: &str) -> (StatusCode, String) {
+        let req = Request::builder().method(method).uri(uri).body(Body::from(body.to_string())).unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        let status = resp.status();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_api_data_overlay() {
+        let dir = scaffold();
+        let app = build_router(AppState::new(VanProject::load(dir.path()).unwrap()));
+        let data = |body: String| serde_json::from_str::<Value>(&body).unwrap();
+
+        let (status, body) = send(&app, "PUT", "/__van/api/data?page=pages/index", r#"{"subtitle": "Draft"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(data(body), json!({ "title": "Home", "subtitle": "Draft" }));
+        let (status, body) = send(&app, "PUT", "/__van/api/data?page=pages/index", r#"{"title": "Draft"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(data(body), json!({ "title": "Draft" }), "the latest overlay replaces the previous one");
+
+        let (_, html) = send(&app, "GET", "/", "").await;
+        assert!(html.contains(">Draft</h1>"), "{html}");
+        // Editing an unrelated file keeps the overlay
+        fs::write(dir.path().join("src/pages/blog/index.van"), "<template><p>News</p></template>").unwrap();
+        let (_, html) = send(&app, "GET", "/", "").await;
+        assert!(html.contains(">Draft</h1>"), "{html}");
+        assert_eq!(fs::read_to_string(dir.path().join("data/index.json")).unwrap(), r#"{"pages/index": {"title": "Home"}}"#);
+
+        let (status, body) = send(&app, "DELETE", "/__van/api/data?page=index", "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(data(body), json!({ "title": "Home" }));
+        let (_, html) = send(&app, "GET", "/", "").await;
+        assert!(html.contains(">Home</h1>"), "{html}");
+
+        let (status, _) = send(&app, "PUT", "/__van/api/data?page=index", "{ not json").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(&app, "DELETE", "/__van/api/data?page=missing", "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_api_data_persist() {
+        let dir = scaffold();
+        let app = build_router(AppState::new(VanProject::load(dir.path()).unwrap()));
+        let (status, _) = send(&app, "PUT", "/__van/api/data?page=blog/index&persist=1", r#"{"title": "Blog"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        send(&app, "DELETE", "/__van/api/data?page=blog/index", "").await;
+        let (_, body) = get_json(&dir, "/__van/api/data?page=blog/index").await;
+        assert_eq!(body, json!({ "title": "Blog" }));
+        let (_, body) = get_json(&dir, "/__van/api/data?page=index").await;
+        assert_eq!(body, json!({ "title": "Home" }));
+    }
+
+    #[tokio::test]
+    async fn test_api_inspect() {
+        let dir = scaffold();
+        let (status, body) = get_json(&dir, "/__van/api/inspect?path=components/button.van").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["props"][0], json!({ "name": "label", "prop_type": "String", "required": true, "default": null }));
+        assert_eq!(body["bindings"]["texts"], json!([]));
+
+        let (status, _) = get_json(&dir, "/__van/api/inspect?path=data/index.json").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
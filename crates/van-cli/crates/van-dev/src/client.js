@@ -1,10 +1,68 @@
 (function() {
   var ws = new WebSocket('ws://' + location.host + '/__van/ws');
+  var overlay = null;
+  function showError(msg) {
+    hideError();
+    overlay = document.createElement('div');
+    overlay.setAttribute('style', 'position:fixed;inset:0;z-index:2147483647;background:rgba(0,0,0,.85);color:#fff;font:14px/1.5 monospace;padding:32px;overflow:auto');
+    var close = document.createElement('button');
+    close.textContent = '×';
+    close.setAttribute('style', 'position:absolute;top:12px;right:16px;background:none;border:0;color:#fff;font-size:24px;cursor:pointer');
+    close.onclick = hideError;
+    var title = document.createElement('div');
+    title.setAttribute('style', 'color:#ff5555;font-weight:bold;margin-bottom:8px');
+    title.textContent = 'Render Error' + (msg.file ? ' in ' + msg.file + (msg.line ? ':' + msg.line : '') : '');
+    var body = document.createElement('pre');
+    body.setAttribute('style', 'white-space:pre-wrap;margin:0');
+    body.textContent = msg.message;
+    overlay.appendChild(close);
+    overlay.appendChild(title);
+    overlay.appendChild(body);
+    document.body.appendChild(overlay);
+  }
+  function hideError() {
+    if (overlay) { overlay.remove(); overlay = null; }
+  }
+  window.__van = { showError: showError };
+  function baseName(url) {
+    return url.split('?')[0].split('/').pop();
+  }
+  function swapCss(msg) {
+    hideError();
+    if (msg.scope) {
+      var scoped = document.getElementsByClassName(msg.scope.from);
+      Array.prototype.slice.call(scoped).forEach(function(el) {
+        el.classList.replace(msg.scope.from, msg.scope.to);
+      });
+    }
+    if (msg.href) {
+      var links = document.querySelectorAll('link[rel="stylesheet"]');
+      Array.prototype.forEach.call(links, function(link) {
+        if (baseName(link.href) === msg.href) {
+          link.href = link.href.split('?')[0] + '?t=' + Date.now();
+        }
+      });
+    }
+    fetch(location.href).then(function(res) { return res.text(); }).then(function(html) {
+      var fresh = new DOMParser().parseFromString(html, 'text/html').querySelectorAll('style');
+      var current = document.querySelectorAll('style');
+      if (fresh.length !== current.length) { location.reload(); return; }
+      for (var i = 0; i < current.length; i++) current[i].textContent = fresh[i].textContent;
+    }).catch(function() { location.reload(); });
+  }
+  // Tell the server which page this is, so it only sends changes affecting it.
+  ws.onopen = function() {
+    ws.send(JSON.stringify({ type: 'hello', path: location.pathname }));
+  };
   ws.onmessage = function(e) {
     var msg = JSON.parse(e.data);
     if (msg.type === 'reload') location.reload();
+    else if (msg.type === 'css') swapCss(msg);
+    else if (msg.type === 'error') showError(msg);
   };
-  ws.onclose = function() {
+  ws.onclose = function(e) {
+    // 1001 "going away": the dev server was stopped on purpose, so don't retry.
+    if (e.code === 1001) return;
     setTimeout(function() { location.reload(); }, 1000);
   };
 })();
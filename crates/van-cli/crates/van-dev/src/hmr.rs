@@ -0,0 +1,473 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// A message pushed to connected browsers over the dev WebSocket.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DevEvent {
+    /// Full page reload.
+    Reload,
+    /// Style-only change: swap stylesheets in place, keeping client state.
+    ///
+    /// `href` names a changed `.css` file (for cache-busting `<link>` tags);
+    /// `scope` carries `(old, new)` scope ids when a scoped `<style>` changed.
+    Css {
+        href: Option<String>,
+        scope: Option<(String, String)>,
+    },
+    /// A page failed to render; show an overlay on top of the last good render.
+    Error {
+        message: String,
+        file: Option<String>,
+        line: Option<u32>,
+    },
+    /// The server is stopping; sockets are closed instead of sent a message.
+    Shutdown,
+}
+
+impl DevEvent {
+    /// Serialize to the JSON message understood by `client.js`.
+    pub(crate) fn to_message(&self) -> String {
+        match self {
+            DevEvent::Reload => serde_json::json!({ "type": "reload" }).to_string(),
+            DevEvent::Css { href, scope } => {
+                let scope = scope
+                    .as_ref()
+                    .map(|(from, to)| serde_json::json!({ "from": from, "to": to }));
+                serde_json::json!({ "type": "css", "href": href, "scope": scope }).to_string()
+            }
+            DevEvent::Error {
+                message,
+                file,
+                line,
+            } => serde_json::json!({
+                "type": "error",
+                "message": message,
+                "file": file,
+                "line": line,
+            })
+            .to_string(),
+            DevEvent::Shutdown => serde_json::json!({ "type": "shutdown" }).to_string(),
+        }
+    }
+
+    /// Build an error event from a compiler message.
+    ///
+    /// Compiler errors are plain strings, so the offending file is taken from the first
+    /// `.van` path mentioned in the message (optionally suffixed with `:line`), falling
+    /// back to the page entry that was being rendered.
+    pub(crate) fn error(message: &str, entry: &str) -> Self {
+        let located = message
+            .split(|c: char| c.is_whitespace() || matches!(c, '\'' | '"' | '(' | ')' | ','))
+            .find_map(|token| {
+                let (path, rest) = token.split_once(".van")?;
+                if path.is_empty() {
+                    return None;
+                }
+                let line = rest.strip_prefix(':').and_then(|l| {
+                    l.split(':').next().and_then(|n| n.parse().ok())
+                });
+                Some((format!("{path}.van"), line))
+            });
+        let (file, line) = match located {
+            Some((file, line)) => (file, line),
+            None => (entry.to_string(), None),
+        };
+        DevEvent::Error {
+            message: message.to_string(),
+            file: Some(file),
+            line,
+        }
+    }
+}
+
+/// A [`DevEvent`] on its way to the connected browsers.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Broadcast {
+    pub(crate) event: DevEvent,
+    /// Changed source files in file-map form (`components/card.van`), or
+    /// `None` when the event concerns every page.
+    pub(crate) changed: Option<Vec<String>>,
+    /// The one page the event is for (a failed rebuild's error), whatever changed.
+    pub(crate) only: Option<String>,
+    /// Pages kept from the event whatever changed (those whose rebuild
+    /// failed, which get their error instead).
+    pub(crate) except: Vec<String>,
+}
+
+impl Broadcast {
+    pub(crate) fn all(event: DevEvent) -> Self {
+        Self { event, changed: None, only: None, except: Vec::new() }
+    }
+
+    /// An event for the browsers viewing pages that depend on one of `changed`.
+    pub(crate) fn changed(event: DevEvent, changed: Option<Vec<String>>) -> Self {
+        Self { changed, ..Self::all(event) }
+    }
+
+    /// An event for the browsers viewing `page` only.
+    pub(crate) fn page(event: DevEvent, page: &str) -> Self {
+        Self { only: Some(page.to_string()), ..Self::all(event) }
+    }
+
+    /// Whether a browser viewing `page` should get this event, given the
+    /// source files each rendered page was built from.
+    ///
+    /// Anything unknown — the page (no hello yet, or not a rendered page) or
+    /// its dependencies (last render failed) — counts as concerned.
+    pub(crate) fn concerns(&self, page: Option<&str>, dependencies: &HashMap<String, Vec<String>>) -> bool {
+        if let (Some(only), Some(page)) = (&self.only, page) {
+            return only == page;
+        }
+        if page.is_some_and(|page| self.except.iter().any(|p| p == page)) {
+            return false;
+        }
+        let (Some(changed), Some(page)) = (&self.changed, page) else {
+            return true;
+        };
+        let Some(deps) = dependencies.get(page) else {
+            return true;
+        };
+        changed.iter().any(|path| deps.contains(path))
+    }
+}
+
+/// The page a `client.js` hello message (`{"type":"hello","path":"/blog/"}`)
+/// says the browser is viewing, keyed like the rendered pages (`blog`, `index`).
+pub(crate) fn hello_page(message: &str) -> Option<String> {
+    let msg: serde_json::Value = serde_json::from_str(message).ok()?;
+    if msg["type"] != "hello" {
+        return None;
+    }
+    let page = msg["path"].as_str()?.trim_matches('/');
+    Some(if page.is_empty() { "index".to_string() } else { page.to_string() })
+}
+
+/// One debounced batch of file changes from the watcher.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ChangeSet {
+    /// Every path touched in the batch, for targeted invalidation.
+    pub(crate) paths: Vec<PathBuf>,
+    /// The browser events the batch warrants, already merged.
+    pub(crate) events: Vec<DevEvent>,
+}
+
+impl ChangeSet {
+    /// The changed paths relative to `src_dir`, as in the compiler's file map,
+    /// or `None` if any lies outside it (`data/`, `locales/`, `package.json`),
+    /// since those can affect any page.
+    pub(crate) fn source_paths(&self, src_dir: &Path) -> Option<Vec<String>> {
+        self.paths
+            .iter()
+            .map(|path| {
+                let rel = path.strip_prefix(src_dir).ok()?;
+                let parts: Vec<String> = rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+                Some(parts.join("/"))
+            })
+            .collect()
+    }
+}
+
+/// The parts of a `.van` file relevant for deciding how to hot-update it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BlockSnapshot {
+    /// Hash of everything except the style content (template, scripts, style presence/scoping).
+    structure: u64,
+    /// The raw `<style>` content.
+    style: Option<String>,
+    scoped: bool,
+}
+
+impl BlockSnapshot {
+    pub(crate) fn from_source(source: &str) -> Self {
+        let blocks = van_parser::parse_blocks(source);
+        let mut hasher = DefaultHasher::new();
+        blocks.template.hash(&mut hasher);
+        blocks.script_setup.hash(&mut hasher);
+        blocks.script_server.hash(&mut hasher);
+        blocks.style.is_some().hash(&mut hasher);
+        blocks.style_scoped.hash(&mut hasher);
+        Self {
+            structure: hasher.finish(),
+            style: blocks.style,
+            scoped: blocks.style_scoped,
+        }
+    }
+
+    /// The scope id the compiler assigns to this file's elements, if its style is scoped.
+    fn scope_id(&self) -> Option<String> {
+        match (&self.style, self.scoped) {
+            (Some(css), true) => Some(van_parser::scope_id(css)),
+            _ => None,
+        }
+    }
+}
+
+/// How a changed `.van` file should be pushed to the browser.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Change {
+    Unchanged,
+    Style { scope: Option<(String, String)> },
+    Structural,
+}
+
+/// Compare a file's new snapshot against its previous one.
+///
+/// Files never seen before are treated as structural, since there is nothing to diff against.
+pub(crate) fn classify(prev: Option<&BlockSnapshot>, next: &BlockSnapshot) -> Change {
+    let Some(prev) = prev else {
+        return Change::Structural;
+    };
+    if prev.structure != next.structure {
+        return Change::Structural;
+    }
+    if prev.style == next.style {
+        return Change::Unchanged;
+    }
+    let scope = match (prev.scope_id(), next.scope_id()) {
+        (Some(from), Some(to)) if from != to => Some((from, to)),
+        _ => None,
+    };
+    Change::Style { scope }
+}
+
+/// Per-file block snapshots, used to classify the next change to each file.
+#[derive(Default)]
+pub(crate) struct SnapshotCache {
+    files: HashMap<PathBuf, BlockSnapshot>,
+}
+
+impl SnapshotCache {
+    /// Snapshot every `.van` file under `dir` so the first edit can already be diffed.
+    pub(crate) fn prime(&mut self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                self.prime(&path);
+            } else if path.extension().and_then(|e| e.to_str()) == Some("van") {
+                if let Ok(source) = fs::read_to_string(&path) {
+                    self.files.insert(path, BlockSnapshot::from_source(&source));
+                }
+            }
+        }
+    }
+
+    /// Re-read a changed file and decide which event (if any) it warrants.
+    pub(crate) fn event_for(&mut self, path: &Path) -> Option<DevEvent> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("van") => {
+                let Ok(source) = fs::read_to_string(path) else {
+                    // Deleted or unreadable — forget it and reload.
+                    self.files.remove(path);
+                    return Some(DevEvent::Reload);
+                };
+                let next = BlockSnapshot::from_source(&source);
+                let change = classify(self.files.get(path), &next);
+                self.files.insert(path.to_path_buf(), next);
+                match change {
+                    Change::Unchanged => None,
+                    Change::Style { scope } => Some(DevEvent::Css { href: None, scope }),
+                    Change::Structural => Some(DevEvent::Reload),
+                }
+            }
+            Some("css") => Some(DevEvent::Css {
+                href: path.file_name().map(|n| n.to_string_lossy().to_string()),
+                scope: None,
+            }),
+            _ => Some(DevEvent::Reload),
+        }
+    }
+}
+
+/// Merge the events produced by one batch of changed files.
+///
+/// Any structural change wins; otherwise style updates are sent individually.
+pub(crate) fn merge_events(events: Vec<DevEvent>) -> Vec<DevEvent> {
+    if events.contains(&DevEvent::Reload) {
+        vec![DevEvent::Reload]
+    } else {
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+<template>
+  <div class="card">{{ title }}</div>
+</template>
+
+<script setup>
+const count = ref(0)
+</script>
+
+<style scoped>
+.card { color: red; }
+</style>
+"#;
+
+    #[test]
+    fn test_classify_style_only_change() {
+        let prev = BlockSnapshot::from_source(BASE);
+        let next = BlockSnapshot::from_source(&BASE.replace("color: red", "color: blue"));
+        let Change::Style { scope } = classify(Some(&prev), &next) else {
+            panic!("expected a style change");
+        };
+        let (from, to) = scope.expect("scoped style should remap the scope id");
+        assert_eq!(from, van_parser::scope_id(".card { color: red; }"));
+        assert_eq!(to, van_parser::scope_id(".card { color: blue; }"));
+    }
+
+    #[test]
+    fn test_classify_unscoped_style_change() {
+        let base = BASE.replace("<style scoped>", "<style>");
+        let prev = BlockSnapshot::from_source(&base);
+        let next = BlockSnapshot::from_source(&base.replace("color: red", "color: blue"));
+        assert_eq!(classify(Some(&prev), &next), Change::Style { scope: None });
+    }
+
+    #[test]
+    fn test_classify_template_change_is_structural() {
+        let prev = BlockSnapshot::from_source(BASE);
+        let next = BlockSnapshot::from_source(&BASE.replace("<div class", "<section class"));
+        assert_eq!(classify(Some(&prev), &next), Change::Structural);
+    }
+
+    #[test]
+    fn test_classify_script_change_is_structural() {
+        let prev = BlockSnapshot::from_source(BASE);
+        let next = BlockSnapshot::from_source(&BASE.replace("ref(0)", "ref(1)"));
+        assert_eq!(classify(Some(&prev), &next), Change::Structural);
+    }
+
+    #[test]
+    fn test_classify_scoped_toggle_is_structural() {
+        let prev = BlockSnapshot::from_source(BASE);
+        let next = BlockSnapshot::from_source(&BASE.replace("<style scoped>", "<style>"));
+        assert_eq!(classify(Some(&prev), &next), Change::Structural);
+    }
+
+    #[test]
+    fn test_classify_unknown_and_unchanged() {
+        let snap = BlockSnapshot::from_source(BASE);
+        assert_eq!(classify(None, &snap), Change::Structural);
+        assert_eq!(classify(Some(&snap), &snap.clone()), Change::Unchanged);
+    }
+
+    #[test]
+    fn test_merge_events_reload_wins() {
+        let css = DevEvent::Css { href: None, scope: None };
+        assert_eq!(merge_events(vec![css.clone(), DevEvent::Reload]), vec![DevEvent::Reload]);
+        assert_eq!(merge_events(vec![css.clone()]), vec![css]);
+    }
+
+    #[test]
+    fn test_broadcast_routing() {
+        let deps = HashMap::from([
+            ("index".to_string(), vec!["pages/index.van".to_string(), "components/card.van".to_string()]),
+            ("about".to_string(), vec!["pages/about.van".to_string()]),
+        ]);
+        let card = Broadcast::changed(DevEvent::Reload, Some(vec!["components/card.van".to_string()]));
+        assert!(card.concerns(Some("index"), &deps));
+        assert!(!card.concerns(Some("about"), &deps));
+        // Unknown page, unknown dependencies, or changes outside the sources
+        assert!(card.concerns(None, &deps));
+        assert!(card.concerns(Some("blog"), &deps));
+        assert!(Broadcast::all(DevEvent::Reload).concerns(Some("about"), &deps));
+
+        // A failed page gets only its error; the others still get the batch
+        let error = Broadcast::page(DevEvent::error("boom", "pages/blog.van"), "blog");
+        assert!(error.concerns(Some("blog"), &deps));
+        assert!(!error.concerns(Some("index"), &deps));
+        let reload = Broadcast { except: vec!["blog".to_string()], ..Broadcast::all(DevEvent::Reload) };
+        assert!(!reload.concerns(Some("blog"), &deps));
+        assert!(reload.concerns(Some("about"), &deps));
+    }
+
+    #[test]
+    fn test_change_set_source_paths() {
+        let src = Path::new("/site/src");
+        let changes = ChangeSet {
+            paths: vec![src.join("components/card.van"), src.join("styles/global.css")],
+            events: vec![DevEvent::Reload],
+        };
+        assert_eq!(
+            changes.source_paths(src),
+            Some(vec!["components/card.van".to_string(), "styles/global.css".to_string()])
+        );
+        let changes = ChangeSet {
+            paths: vec![src.join("pages/index.van"), PathBuf::from("/site/data/index.json")],
+            events: vec![DevEvent::Reload],
+        };
+        assert_eq!(changes.source_paths(src), None);
+    }
+
+    #[test]
+    fn test_hello_page() {
+        assert_eq!(hello_page(r#"{"type":"hello","path":"/"}"#).as_deref(), Some("index"));
+        assert_eq!(hello_page(r#"{"type":"hello","path":"/blog/2024/recap/"}"#).as_deref(), Some("blog/2024/recap"));
+        assert_eq!(hello_page(r#"{"type":"ping"}"#), None);
+        assert_eq!(hello_page("not json"), None);
+    }
+
+    #[test]
+    fn test_dev_event_messages() {
+        assert_eq!(DevEvent::Reload.to_message(), r#"{"type":"reload"}"#);
+        let msg = DevEvent::Css {
+            href: Some("site.css".into()),
+            scope: Some(("aaaa1111".into(), "bbbb2222".into())),
+        }
+        .to_message();
+        let v: serde_json::Value = serde_json::from_str(&msg).unwrap();
+        assert_eq!(v["type"], "css");
+        assert_eq!(v["href"], "site.css");
+        assert_eq!(v["scope"]["from"], "aaaa1111");
+        assert_eq!(v["scope"]["to"], "bbbb2222");
+    }
+
+    #[test]
+    fn test_error_event_locates_file() {
+        let ev = DevEvent::error(
+            "Component not found: components/card.van (resolved from './card.van')",
+            "pages/index.van",
+        );
+        assert_eq!(
+            ev,
+            DevEvent::Error {
+                message: "Component not found: components/card.van (resolved from './card.van')".into(),
+                file: Some("components/card.van".into()),
+                line: None,
+            }
+        );
+
+        let DevEvent::Error { file, line, .. } =
+            DevEvent::error("Unexpected token at pages/about.van:12:4", "pages/about.van")
+        else {
+            unreachable!()
+        };
+        assert_eq!(file.as_deref(), Some("pages/about.van"));
+        assert_eq!(line, Some(12));
+
+        let DevEvent::Error { file, line, .. } =
+            DevEvent::error("Component nesting exceeded maximum depth of 10", "pages/index.van")
+        else {
+            unreachable!()
+        };
+        assert_eq!(file.as_deref(), Some("pages/index.van"));
+        assert_eq!(line, None);
+
+        let msg = DevEvent::error("boom", "pages/index.van").to_message();
+        let v: serde_json::Value = serde_json::from_str(&msg).unwrap();
+        assert_eq!(v["type"], "error");
+        assert_eq!(v["message"], "boom");
+        assert_eq!(v["file"], "pages/index.van");
+        assert!(v["line"].is_null());
+    }
+}
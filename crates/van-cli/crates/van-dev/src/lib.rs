@@ -1,7 +1,16 @@
+mod api;
+mod hmr;
 pub mod render;
 mod server;
 mod watcher;
 
-pub async fn start(port: u16) -> anyhow::Result<()> {
-    server::run(port).await
+pub use server::DevServer;
+
+/// Bind the dev server and start serving in the background.
+///
+/// Uses `port` if given, else the project's `van.devPort`, else 3000, moving to
+/// the next free port if that one is taken. Returns once bound, with the port
+/// in [`DevServer::port`]; [`DevServer::wait`] runs until Ctrl-C.
+pub async fn start(port: Option<u16>) -> anyhow::Result<DevServer> {
+    server::start(port).await
 }
@@ -1,13 +1,15 @@
+use crate::hmr::DevEvent;
 use anyhow::Result;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use van_compiler::render::escape_script;
 use van_parser::PropDef;
 
 const CLIENT_JS: &str = include_str!("client.js");
 
 /// Insert `content` before a closing tag (e.g. `</head>`, `</body>`),
 /// with indentation matching the surrounding HTML structure.
-fn inject_before_close(html: &mut String, close_tag: &str, content: &str) {
+pub fn inject_before_close(html: &mut String, close_tag: &str, content: &str) {
     if content.is_empty() {
         return;
     }
@@ -27,24 +29,148 @@ fn inject_before_close(html: &mut String, close_tag: &str, content: &str) {
     }
 }
 
+/// A page rendered for the dev server.
+#[derive(Debug, Clone)]
+pub struct RenderedPage {
+    pub html: String,
+    /// Source files the page was built from, in file-map form.
+    pub dependencies: Vec<String>,
+}
+
 /// Render a page from pre-collected files with live reload client and debug comments.
 ///
 /// Delegates compilation to `van_compiler`, then injects the WebSocket-based
-/// live reload `client.js` before `</body>`.
+/// live reload `client.js` before `</body>`. Compiler warnings (e.g. unset
+/// `env` variables) are printed to stderr. `options` are used with `debug` on.
 pub fn render_from_files(
     entry_path: &str,
     files: &HashMap<String, String>,
     data: &Value,
-    file_origins: &HashMap<String, String>,
-) -> Result<String> {
+    options: &van_compiler::CompileOptions,
+) -> Result<RenderedPage> {
+    let data_json = serde_json::to_string(data)?;
+    let options = van_compiler::CompileOptions {
+        debug: true,
+        ..options.clone()
+    };
+    let page = van_compiler::render_page(entry_path, files, &data_json, &options)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    print_warnings(&page.warnings);
+    let mut html = page.html;
+
+    inject_before_close(&mut html, "</body>", &client_script());
+    Ok(RenderedPage { html, dependencies: page.dependencies })
+}
+
+/// Like [`render_from_files`], but with CSS and JS split into assets under
+/// `asset_prefix` the way hosts serve pages, so asset-mode problems show up in
+/// development. Returns the page and the assets keyed by URL path.
+pub fn render_assets_from_files(
+    entry_path: &str,
+    files: &HashMap<String, String>,
+    data: &Value,
+    asset_prefix: &str,
+    options: &van_compiler::CompileOptions,
+) -> Result<(RenderedPage, BTreeMap<String, String>)> {
     let data_json = serde_json::to_string(data)?;
-    let mut html =
-        van_compiler::render_to_string_debug(entry_path, files, &data_json, file_origins)
-            .map_err(|e| anyhow::anyhow!("{e}"))?;
+    let options = van_compiler::CompileOptions {
+        debug: true,
+        ..options.clone()
+    };
+    let page = van_compiler::render_page_assets(entry_path, files, &data_json, asset_prefix, &options)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    print_warnings(&page.warnings);
+    let mut html = page.html;
 
-    let client_script = format!("<script>{CLIENT_JS}</script>");
-    inject_before_close(&mut html, "</body>", &client_script);
-    Ok(html)
+    inject_before_close(&mut html, "</body>", &client_script());
+    Ok((RenderedPage { html, dependencies: page.dependencies }, page.assets))
+}
+
+/// Render a single component standalone for the preview route.
+///
+/// `props` come from the query string and are coerced using the component's
+/// `defineProps` types. `options` carry the project's env and global styles.
+pub(crate) fn render_preview(
+    entry_path: &str,
+    files: &HashMap<String, String>,
+    query: &HashMap<String, String>,
+    options: &van_compiler::CompileOptions,
+) -> Result<String> {
+    let props = files
+        .get(entry_path)
+        .map(|source| van_parser::parse_blocks(source).props)
+        .unwrap_or_default();
+    let data = coerce_query_props(&props, query);
+    render_from_files(entry_path, files, &data, options).map(|page| page.html)
+}
+
+/// Build a props object from query parameters, coercing values by declared prop type.
+///
+/// `Number` and `Boolean` props are parsed (a bare `?flag` counts as `true`),
+/// `Array`/`Object` props accept JSON. Anything that fails to parse, and any
+/// undeclared parameter, is passed through as a string.
+pub(crate) fn coerce_query_props(props: &[PropDef], query: &HashMap<String, String>) -> Value {
+    let mut map = serde_json::Map::new();
+    for (key, raw) in query {
+        let prop_type = props
+            .iter()
+            .find(|p| &p.name == key)
+            .and_then(|p| p.prop_type.as_deref());
+        let value = match prop_type {
+            Some("Number") => raw
+                .parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| raw.parse::<f64>().map(Value::from))
+                .unwrap_or_else(|_| Value::String(raw.clone())),
+            Some("Boolean") => match raw.as_str() {
+                "" | "true" | "1" => Value::Bool(true),
+                "false" | "0" => Value::Bool(false),
+                _ => Value::String(raw.clone()),
+            },
+            Some("Array" | "Object") => {
+                serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.clone()))
+            }
+            _ => Value::String(raw.clone()),
+        };
+        map.insert(key.clone(), value);
+    }
+    Value::Object(map)
+}
+
+/// The live reload client as an inline `<script>`.
+fn client_script() -> String {
+    format!("<script>{}</script>", escape_script(CLIENT_JS))
+}
+
+/// Inject an error overlay into a previously rendered page.
+///
+/// The overlay is drawn by `client.js`, which must already be in the page;
+/// the injected call runs after it since both sit just before `</body>`.
+pub(crate) fn inject_error_overlay(html: &mut String, error: &DevEvent) {
+    // Keep `</script>` inside the message from closing the tag early.
+    let payload = escape_script(&error.to_message());
+    let script = format!("<script>window.__van && window.__van.showError({payload});</script>");
+    inject_before_close(html, "</body>", &script);
+}
+
+/// A bare error page for when a page has never rendered successfully.
+///
+/// Includes the live reload client so the page recovers once the error is fixed.
+pub(crate) fn error_page(error: &DevEvent) -> String {
+    let message = match error {
+        DevEvent::Error { message, .. } => message.as_str(),
+        _ => "",
+    };
+    let escaped = message
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<body>\n  <h1>Render Error</h1>\n  <pre>{escaped}</pre>\n</body>\n</html>"
+    );
+    inject_before_close(&mut html, "</body>", &client_script());
+    inject_error_overlay(&mut html, error);
+    html
 }
 
 /// Render a page from pre-collected files for static output (no live reload).
@@ -58,6 +184,13 @@ pub fn render_static_from_files(
         .map_err(|e| anyhow::anyhow!("{e}"))
 }
 
+/// Print non-fatal compiler warnings in yellow.
+pub(crate) fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("\x1b[33m  \u{26a0} {warning}\x1b[0m");
+    }
+}
+
 /// Validate data against `defineProps` declarations.
 ///
 /// Prints warnings to stderr for:
@@ -147,11 +280,13 @@ h1 { color: red; }
         let mut files = HashMap::new();
         files.insert("pages/index.van".to_string(), source.to_string());
         let data = json!({"title": "Hello"});
-        let html =
-            render_from_files("pages/index.van", &files, &data, &HashMap::new()).unwrap();
+        let page =
+            render_from_files("pages/index.van", &files, &data, &Default::default()).unwrap();
+        let html = &page.html;
         assert!(html.contains("Hello"), "Should contain interpolated title");
         assert!(html.contains("color: red"), "Should contain scoped CSS");
         assert!(html.contains("__van/ws"), "Should contain live reload client");
+        assert_eq!(page.dependencies, vec!["pages/index.van"]);
     }
 
     #[test]
@@ -169,13 +304,121 @@ h1 { color: red; }
         assert!(!html.contains("__van/ws"), "Static output should not have live reload");
     }
 
+    #[test]
+    fn test_inject_error_overlay() {
+        let mut html = "<html>\n<body>\n  <h1>Hi</h1>\n</body>\n</html>".to_string();
+        let error = DevEvent::error("bad </script> tag", "pages/index.van");
+        inject_error_overlay(&mut html, &error);
+        assert!(html.contains("window.__van.showError("));
+        assert!(html.contains(r#""file":"pages/index.van""#));
+        assert!(!html.contains("bad </script>"), "Message must not close the script tag");
+        let mut html = "<html>\n<body>\n</body>\n</html>".to_string();
+        inject_error_overlay(&mut html, &DevEvent::error("bad <!-- <script> tag", "pages/index.van"));
+        assert!(!html.contains("<!--"), "{html}");
+        assert!(html.find("showError").unwrap() < html.find("</body>").unwrap());
+    }
+
+    #[test]
+    fn test_error_page_has_live_reload() {
+        let html = error_page(&DevEvent::error("Entry <b>", "pages/index.van"));
+        assert!(html.contains("Entry &lt;b&gt;"));
+        assert!(html.contains("__van/ws"), "Error page should reload once fixed");
+        assert!(html.contains("showError"));
+    }
+
+    #[test]
+    fn test_coerce_query_props() {
+        let props = vec![
+            PropDef { name: "count".into(), prop_type: Some("Number".into()), required: false, default: None },
+            PropDef { name: "ratio".into(), prop_type: Some("Number".into()), required: false, default: None },
+            PropDef { name: "disabled".into(), prop_type: Some("Boolean".into()), required: false, default: None },
+            PropDef { name: "open".into(), prop_type: Some("Boolean".into()), required: false, default: None },
+            PropDef { name: "tags".into(), prop_type: Some("Array".into()), required: false, default: None },
+            PropDef { name: "label".into(), prop_type: Some("String".into()), required: false, default: None },
+        ];
+        let query: HashMap<String, String> = [
+            ("count", "3"),
+            ("ratio", "0.5"),
+            ("disabled", ""),
+            ("open", "false"),
+            ("tags", r#"["a","b"]"#),
+            ("label", "42"),
+            ("extra", "x"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let data = coerce_query_props(&props, &query);
+        assert_eq!(
+            data,
+            json!({
+                "count": 3,
+                "ratio": 0.5,
+                "disabled": true,
+                "open": false,
+                "tags": ["a", "b"],
+                "label": "42",
+                "extra": "x",
+            })
+        );
+    }
+
+    #[test]
+    fn test_coerce_query_props_invalid_number_stays_string() {
+        let props = vec![
+            PropDef { name: "count".into(), prop_type: Some("Number".into()), required: false, default: None },
+        ];
+        let query = HashMap::from([("count".to_string(), "many".to_string())]);
+        assert_eq!(coerce_query_props(&props, &query), json!({"count": "many"}));
+    }
+
+    #[test]
+    fn test_render_preview() {
+        let source = r#"
+<template>
+  <button :class="variant">{{ label }} ({{ count }})</button>
+</template>
+
+<script setup>
+defineProps({ label: String, count: Number, variant: String })
+</script>
+
+<style scoped>
+button { color: red; }
+</style>
+"#;
+        let files = HashMap::from([
+            ("components/button.van".to_string(), source.to_string()),
+            ("styles/global.css".to_string(), "body { margin: 0; }".to_string()),
+        ]);
+        let query = HashMap::from([
+            ("label".to_string(), "Save".to_string()),
+            ("count".to_string(), "2".to_string()),
+        ]);
+        let html = render_preview(
+            "components/button.van",
+            &files,
+            &query,
+            &van_compiler::CompileOptions {
+                global_css: vec!["styles/global.css".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(html.contains("Save (2)"));
+        assert!(html.contains("__van/ws"), "Preview should live reload");
+        let global = html.find("margin: 0").unwrap();
+        let scoped = html.find("color: red").unwrap();
+        assert!(global < scoped, "Global styles should come before component styles");
+    }
+
     // --- validate_data tests ---
 
     #[test]
     fn test_validate_all_good() {
         let props = vec![
-            PropDef { name: "title".into(), prop_type: Some("String".into()), required: true },
-            PropDef { name: "count".into(), prop_type: Some("Number".into()), required: false },
+            PropDef { name: "title".into(), prop_type: Some("String".into()), required: true, default: None },
+            PropDef { name: "count".into(), prop_type: Some("Number".into()), required: false, default: None },
         ];
         let data = json!({"title": "Hello", "count": 42});
         // Should produce no warnings (no panic)
@@ -185,7 +428,7 @@ h1 { color: red; }
     #[test]
     fn test_validate_missing_required() {
         let props = vec![
-            PropDef { name: "user".into(), prop_type: Some("Object".into()), required: true },
+            PropDef { name: "user".into(), prop_type: Some("Object".into()), required: true, default: None },
         ];
         let data = json!({});
         validate_data(&props, &data, "pages/index.van");
@@ -194,7 +437,7 @@ h1 { color: red; }
     #[test]
     fn test_validate_extra_keys() {
         let props = vec![
-            PropDef { name: "title".into(), prop_type: Some("String".into()), required: false },
+            PropDef { name: "title".into(), prop_type: Some("String".into()), required: false, default: None },
         ];
         let data = json!({"title": "Hi", "typo": "oops"});
         validate_data(&props, &data, "pages/index.van");
@@ -203,7 +446,7 @@ h1 { color: red; }
     #[test]
     fn test_validate_type_mismatch() {
         let props = vec![
-            PropDef { name: "count".into(), prop_type: Some("Number".into()), required: false },
+            PropDef { name: "count".into(), prop_type: Some("Number".into()), required: false, default: None },
         ];
         let data = json!({"count": "not a number"});
         validate_data(&props, &data, "pages/index.van");
@@ -1,124 +1,644 @@
-use crate::render::{render_from_files, validate_data};
+use crate::api;
+use crate::hmr::{hello_page, Broadcast, ChangeSet, DevEvent};
+use crate::render::{
+    error_page, inject_error_overlay, render_assets_from_files, render_from_files, render_preview,
+    validate_data, RenderedPage,
+};
 use crate::watcher;
 use anyhow::{Context, Result};
-use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{Path, State, WebSocketUpgrade};
-use axum::http::{header, StatusCode};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use axum::extract::{Path, Query, State, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, StatusCode, Uri};
 use axum::response::{Html, IntoResponse, Response};
 use axum::routing::get;
 use axum::Router;
 use futures_util::{SinkExt, StreamExt};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use van_compiler::render::escape_html;
+use van_context::catalog::ComponentInfo;
 use van_context::project::VanProject;
 
 const PLAYGROUND_HTML: &str = include_str!("playground.html");
 
 #[derive(Clone)]
-struct AppState {
-    project: VanProject,
-    reload_tx: broadcast::Sender<()>,
+pub(crate) struct AppState {
+    /// Reloaded in place when `package.json` changes.
+    project: Arc<RwLock<VanProject>>,
+    reload_tx: broadcast::Sender<Broadcast>,
+    /// Last successful render of each visited page, served with an overlay while broken.
+    last_good: Arc<Mutex<HashMap<String, String>>>,
+    /// Source files each visited page was last built from, so browsers only
+    /// reload for changes that affect the page they show.
+    page_deps: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    /// CSS/JS of pages rendered in assets mode, by URL path.
+    assets: Arc<Mutex<HashMap<String, String>>>,
+    /// Visited pages last rendered in assets mode, rebuilt the same way.
+    asset_pages: Arc<Mutex<HashSet<String>>>,
+    /// Mock data set through `PUT /__van/api/data`, by page key (`pages/index`),
+    /// layered over `data/index.json` until cleared.
+    data_overlays: Arc<Mutex<HashMap<String, Value>>>,
 }
 
-pub async fn run(port: u16) -> Result<()> {
+impl AppState {
+    pub(crate) fn new(project: VanProject) -> Self {
+        let (reload_tx, _) = broadcast::channel::<Broadcast>(16);
+        Self {
+            project: Arc::new(RwLock::new(project)),
+            reload_tx,
+            last_good: Arc::new(Mutex::new(HashMap::new())),
+            page_deps: Arc::new(Mutex::new(HashMap::new())),
+            assets: Arc::new(Mutex::new(HashMap::new())),
+            asset_pages: Arc::new(Mutex::new(HashSet::new())),
+            data_overlays: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// A snapshot of the current project.
+    pub(crate) fn project(&self) -> VanProject {
+        self.project.read().unwrap().clone()
+    }
+
+    /// The mock data the page `key` (`pages/index`) renders with, overlay included.
+    pub(crate) fn page_data(&self, project: &VanProject, key: &str) -> Value {
+        layered_data(project, key, &self.data_overlays)
+    }
+
+    /// Set (or with `None` clear) the data overlay of the page `key`.
+    pub(crate) fn set_data_overlay(&self, key: &str, overlay: Option<Value>) {
+        let mut overlays = self.data_overlays.lock().unwrap();
+        match overlay {
+            Some(overlay) => overlays.insert(key.to_string(), overlay),
+            None => overlays.remove(key),
+        };
+    }
+
+    /// Reload the tabs showing the page `key`, e.g. after its data changed.
+    pub(crate) fn reload_page(&self, key: &str) {
+        let _ = self.reload_tx.send(Broadcast::changed(DevEvent::Reload, Some(vec![format!("{key}.van")])));
+    }
+
+    /// Re-read `package.json` if it is among the changed paths.
+    ///
+    /// A config that fails to load is reported and the previous one is kept.
+    fn reload_config_if_changed(&self, paths: &[PathBuf]) {
+        let root = self.project.read().unwrap().root.clone();
+        let pkg_path = root.join("package.json");
+        if !paths.contains(&pkg_path) {
+            return;
+        }
+        match VanProject::load(&root) {
+            Ok(project) => {
+                *self.project.write().unwrap() = project;
+                eprintln!("  package.json changed, reloaded project config");
+            }
+            Err(e) => eprintln!(
+                "\x1b[33m  \u{26a0} package.json changed but could not be loaded: {e:#}\x1b[0m"
+            ),
+        }
+    }
+}
+
+/// All dev server routes, without the file watcher attached.
+pub(crate) fn build_router(state: AppState) -> Router {
+    Router::new()
+        .route("/__van/ws", get(ws_handler))
+        .route("/__van/playground", get(playground_handler))
+        .route("/__van/playground/{file}", get(playground_file_handler))
+        .route("/__van/preview/{*component}", get(preview_handler))
+        .route("/__van/components", get(catalog_handler))
+        .nest("/__van/api", api::router())
+        .route("/themes/{*path}", get(asset_handler))
+        .route("/", get(index_handler))
+        .route("/{*page}", get(page_handler))
+        .with_state(state)
+}
+
+/// How many consecutive ports to try when the requested one is taken.
+const PORT_ATTEMPTS: u16 = 10;
+
+/// Bind to `port`, falling back to the next few ports if it is already in use.
+///
+/// Returns the listener together with the port actually bound.
+pub(crate) async fn bind(port: u16) -> Result<(tokio::net::TcpListener, u16)> {
+    let last = port.saturating_add(PORT_ATTEMPTS - 1);
+    for candidate in port..=last {
+        let addr = format!("0.0.0.0:{candidate}");
+        match tokio::net::TcpListener::bind(&addr).await {
+            Ok(listener) => {
+                let bound = listener.local_addr()?.port();
+                if port != 0 && bound != port {
+                    eprintln!("  Port {port} is in use, using {bound} instead.");
+                }
+                return Ok((listener, bound));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to bind to {addr}")),
+        }
+    }
+    anyhow::bail!(
+        "Ports {port}-{last} are all in use.\n\
+         Stop the other server or pick a free port."
+    )
+}
+
+/// Resolve once the process receives Ctrl-C (or SIGTERM on Unix) or `stop`
+/// fires, then tell connected browsers the server is going away.
+async fn shutdown_signal(reload_tx: broadcast::Sender<Broadcast>, stop: oneshot::Receiver<()>) {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+        _ = stop => {}
+    }
+
+    eprintln!();
+    eprintln!("  Shutting down...");
+    let _ = reload_tx.send(Broadcast::all(DevEvent::Shutdown));
+}
+
+/// Default dev server port when neither the CLI nor `van.devPort` sets one.
+const DEFAULT_PORT: u16 = 3000;
+
+/// A dev server that is bound and serving in the background.
+pub struct DevServer {
+    port: u16,
+    stop: oneshot::Sender<()>,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl DevServer {
+    /// The port actually bound, which may differ from the one requested.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Serve until Ctrl-C or SIGTERM.
+    pub async fn wait(self) -> Result<()> {
+        self.task.await?
+    }
+
+    /// Stop serving and wait for in-flight requests to finish.
+    pub async fn shutdown(self) -> Result<()> {
+        let _ = self.stop.send(());
+        self.task.await?
+    }
+}
+
+/// Load the project in the current directory and start serving it.
+///
+/// An explicit `port` wins over the project's `van.devPort` setting.
+pub async fn start(port: Option<u16>) -> Result<DevServer> {
     let project = VanProject::load_cwd().context(
         "Failed to load project. Are you in a Van project?\n\
          Run `van init <name>` to create a new project.",
     )?;
+    serve(project, port).await
+}
+
+/// Bind, then serve `project` on a background task. Returns once the listener
+/// is bound, so the port is known before the first request arrives.
+pub(crate) async fn serve(project: VanProject, port: Option<u16>) -> Result<DevServer> {
+    let port = port.or(project.dev_port()).unwrap_or(DEFAULT_PORT);
 
-    let (reload_tx, _) = broadcast::channel::<()>(16);
+    let (change_tx, change_rx) = mpsc::unbounded_channel::<ChangeSet>();
     let version = Arc::new(AtomicU64::new(0));
 
-    // Start file watcher — must keep the watcher alive
-    let _watcher = watcher::start(&project.root, version, reload_tx.clone())
+    // Start file watcher — must keep the watcher alive while serving
+    let watcher = watcher::start(&project, version, change_tx, watcher::DEFAULT_DEBOUNCE)
         .context("Failed to start file watcher")?;
 
-    let state = AppState {
-        project,
-        reload_tx,
-    };
+    let (listener, port) = bind(port).await?;
 
-    let app = Router::new()
-        .route("/__van/ws", get(ws_handler))
-        .route("/__van/playground", get(playground_handler))
-        .route("/__van/playground/{file}", get(playground_file_handler))
-        .route("/", get(index_handler))
-        .route("/{page}", get(page_handler))
-        .with_state(state);
+    let state = AppState::new(project);
+    tokio::spawn(rebuild_loop(state.clone(), change_rx));
 
-    let addr = format!("0.0.0.0:{port}");
-    let listener = tokio::net::TcpListener::bind(&addr)
-        .await
-        .with_context(|| format!("Failed to bind to {addr}"))?;
+    let reload_tx = state.reload_tx.clone();
+    let app = build_router(state);
 
     eprintln!("  Van dev server running at http://localhost:{port}");
     eprintln!("  Playground at http://localhost:{port}/__van/playground");
+    eprintln!("  Project API at http://localhost:{port}/__van/api/pages");
+    eprintln!("  Component previews at http://localhost:{port}/__van/preview/<name>");
     eprintln!("  Watching for file changes...");
     eprintln!();
 
-    axum::serve(listener, app).await?;
+    let (stop, stopped) = oneshot::channel();
+    let task = tokio::spawn(async move {
+        let _watcher = watcher;
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown_signal(reload_tx, stopped))
+            .await?;
+        Ok(())
+    });
 
-    Ok(())
+    Ok(DevServer { port, stop, task })
 }
 
-async fn index_handler(State(state): State<AppState>) -> impl IntoResponse {
-    render_page(&state.project, "index")
+async fn index_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let assets = wants_assets(&state.project(), &query);
+    render_page(&state, "index", assets)
 }
 
 async fn page_handler(
     State(state): State<AppState>,
     Path(page): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    render_page(&state.project, &page)
+    let assets = wants_assets(&state.project(), &query);
+    render_page(&state, page.trim_end_matches('/'), assets)
 }
 
-fn render_page(project: &VanProject, page: &str) -> Html<String> {
-    let entry = format!("pages/{page}.van");
+/// Query parameter switching a page to assets mode (`?__van_assets=1`).
+const ASSETS_QUERY: &str = "__van_assets";
 
+/// Whether to render with separate CSS/JS assets: `?__van_assets=1` (or `=0`
+/// to opt out), else the project's `van.devAssets` setting.
+fn wants_assets(project: &VanProject, query: &HashMap<String, String>) -> bool {
+    match query.get(ASSETS_QUERY).map(String::as_str) {
+        Some("0" | "false") => false,
+        Some(_) => true,
+        None => project.dev_assets(),
+    }
+}
+
+fn render_page(state: &AppState, page: &str, assets: bool) -> Html<String> {
+    let project = state.project();
+    match try_render(&project, page, assets.then_some(&*state.assets), &state.data_overlays) {
+        Ok(Some(RenderedPage { html, dependencies })) => {
+            state
+                .last_good
+                .lock()
+                .unwrap()
+                .insert(page.to_string(), html.clone());
+            state.page_deps.lock().unwrap().insert(page.to_string(), dependencies);
+            let mut asset_pages = state.asset_pages.lock().unwrap();
+            if assets {
+                asset_pages.insert(page.to_string());
+            } else {
+                asset_pages.remove(page);
+            }
+            Html(html)
+        }
+        Ok(None) => Html(not_found_html(&project, page)),
+        Err(e) => {
+            state.page_deps.lock().unwrap().remove(page);
+            let error = DevEvent::error(&format!("{e:#}"), &project.page_entry(page));
+            match state.last_good.lock().unwrap().get(page) {
+                Some(good) => {
+                    let mut html = good.clone();
+                    inject_error_overlay(&mut html, &error);
+                    Html(html)
+                }
+                None => Html(error_page(&error)),
+            }
+        }
+    }
+}
+
+/// Render a page, returning `Ok(None)` if it does not exist.
+///
+/// `page` is the URL path: `blog/post` maps to `pages/blog/post.van`, falling
+/// back to `pages/blog/post/index.van`.
+///
+/// With an `assets` store the page is rendered in assets mode: its CSS and JS
+/// go into the store under `/themes/{name}/assets/...` and the HTML links them.
+/// Page data comes from `data/index.json` with `overlays` on top.
+fn try_render(
+    project: &VanProject,
+    page: &str,
+    assets: Option<&Mutex<HashMap<String, String>>>,
+    overlays: &Mutex<HashMap<String, Value>>,
+) -> Result<Option<RenderedPage>> {
     // Collect all source files from src/ and node_modules/
-    let files = match project.collect_files() {
-        Ok(f) => f,
-        Err(e) => return Html(error_html(&format!("Failed to collect files: {e}"))),
+    let (files, notes) = project
+        .collect_files_noted()
+        .context("Failed to collect files")?;
+    for note in &notes {
+        eprintln!("\x1b[33m  \u{26a0} {note}\x1b[0m");
+    }
+
+    // Partials (`_card.van`) live under pages/ but are not routes
+    let entries = project.page_entries(&files);
+    let Some(entry) = [page.to_string(), format!("{page}/index")]
+        .iter()
+        .map(|name| project.page_entry(name))
+        .find(|entry| entries.contains(entry))
+    else {
+        return Ok(None);
     };
+    project.check_routes(&entries)?;
 
-    if !files.contains_key(&entry) {
-        return Html(not_found_html(page));
+    let mut data = layered_data(project, entry.trim_end_matches(".van"), overlays);
+    if let Some((code, messages)) = project.default_messages()? {
+        van_context::locale::inject(&mut data, &code, messages);
     }
 
-    let data = project.load_data(&format!("pages/{page}"));
-
     // Validate data against defineProps (warning-only)
     if let Some(source) = files.get(&entry) {
         let blocks = van_parser::parse_blocks(source);
         if !blocks.props.is_empty() {
-            let label = format!("pages/{page}.van");
-            validate_data(&blocks.props, &data, &label);
+            validate_data(&blocks.props, &data, &entry);
         }
     }
 
-    match render_from_files(&entry, &files, &data, &HashMap::new()) {
-        Ok(html) => Html(html),
-        Err(e) => Html(error_html(&format!("{e:#}"))),
+    let options = van_compiler::CompileOptions {
+        env: project.load_env(),
+        auto_layout: project.auto_layout(),
+        global_css: project.global_css(&files),
+        custom_elements: project.custom_elements(),
+        import_map: project.import_map(),
+        strict_coercion: project.strict_coercion(),
+        ..Default::default()
+    };
+    match assets {
+        Some(store) => {
+            let prefix = format!("/themes/{}/assets", project.config.name);
+            let (page, page_assets) = render_assets_from_files(&entry, &files, &data, &prefix, &options)?;
+            store.lock().unwrap().extend(page_assets);
+            Ok(Some(page))
+        }
+        None => render_from_files(&entry, &files, &data, &options).map(Some),
+    }
+}
+
+/// `data/index.json`'s data for the page `key` with its overlay on top.
+fn layered_data(project: &VanProject, key: &str, overlays: &Mutex<HashMap<String, Value>>) -> Value {
+    let mut data = project.load_data(key);
+    if let Some(overlay) = overlays.lock().unwrap().get(key) {
+        overlay_data(&mut data, overlay);
+    }
+    data
+}
+
+/// Layer `overlay` over `data`: the overlay's keys replace the data's, and an
+/// overlay that is not an object replaces the data whole.
+pub(crate) fn overlay_data(data: &mut Value, overlay: &Value) {
+    match (data.as_object_mut(), overlay) {
+        (Some(fields), Value::Object(overlay)) => fields.extend(overlay.clone()),
+        _ => *data = overlay.clone(),
     }
 }
 
-fn not_found_html(page: &str) -> String {
+/// Serve a CSS/JS asset of a page rendered in assets mode.
+async fn asset_handler(State(state): State<AppState>, uri: Uri) -> Response {
+    let Some(content) = state.assets.lock().unwrap().get(uri.path()).cloned() else {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    };
+    let content_type = if uri.path().ends_with(".css") {
+        "text/css"
+    } else {
+        "application/javascript"
+    };
+    ([(header::CONTENT_TYPE, content_type)], content).into_response()
+}
+
+/// Re-render visited pages on every file change before telling browsers about it.
+///
+/// The browsers viewing a page that fails get its error event instead of the
+/// batch's events, so they keep showing the last good render; the next
+/// successful change reloads them as usual. The batch's events carry the
+/// changed source files, so each socket only passes them on when its page
+/// depends on one of them.
+async fn rebuild_loop(state: AppState, mut changes: mpsc::UnboundedReceiver<ChangeSet>) {
+    while let Some(batch) = changes.recv().await {
+        state.reload_config_if_changed(&batch.paths);
+        let pages: Vec<String> = state.last_good.lock().unwrap().keys().cloned().collect();
+        let mut failed = Vec::new();
+        for page in pages {
+            let project = state.project();
+            let assets = state.asset_pages.lock().unwrap().contains(&page);
+            match try_render(&project, &page, assets.then_some(&*state.assets), &state.data_overlays) {
+                Ok(Some(RenderedPage { html, dependencies })) => {
+                    state.page_deps.lock().unwrap().insert(page.clone(), dependencies);
+                    state.last_good.lock().unwrap().insert(page, html);
+                }
+                Ok(None) => {
+                    state.page_deps.lock().unwrap().remove(&page);
+                    state.last_good.lock().unwrap().remove(&page);
+                }
+                Err(e) => {
+                    state.page_deps.lock().unwrap().remove(&page);
+                    let error = DevEvent::error(&format!("{e:#}"), &project.page_entry(&page));
+                    let _ = state.reload_tx.send(Broadcast::page(error, &page));
+                    failed.push(page);
+                }
+            }
+        }
+        let changed = batch.source_paths(&state.project().src_dir());
+        for event in batch.events {
+            let broadcast = Broadcast { except: failed.clone(), ..Broadcast::changed(event, changed.clone()) };
+            let _ = state.reload_tx.send(broadcast);
+        }
+    }
+}
+
+/// Preview a single component, e.g. `/__van/preview/button?label=Save&variant=primary`.
+async fn preview_handler(
+    State(state): State<AppState>,
+    Path(component): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let files = match state.project().collect_files() {
+        Ok(f) => f,
+        Err(e) => {
+            let error = DevEvent::error(&format!("Failed to collect files: {e:#}"), "");
+            return Html(error_page(&error)).into_response();
+        }
+    };
+
+    let name = component.trim_end_matches(".van");
+    let entry = format!("components/{name}.van");
+    if !files.contains_key(&entry) {
+        let available = state.project().component_entries(&files);
+        return (
+            StatusCode::NOT_FOUND,
+            Html(component_not_found_html(name, &available)),
+        )
+            .into_response();
+    }
+
+    let project = state.project();
+    let options = van_compiler::CompileOptions {
+        env: project.load_env(),
+        global_css: project.global_css(&files),
+        custom_elements: project.custom_elements(),
+        import_map: project.import_map(),
+        strict_coercion: project.strict_coercion(),
+        ..Default::default()
+    };
+    match render_preview(&entry, &files, &query, &options) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => Html(error_page(&DevEvent::error(&format!("{e:#}"), &entry))).into_response(),
+    }
+}
+
+fn component_not_found_html(name: &str, available: &[String]) -> String {
+    let items: String = available
+        .iter()
+        .map(|entry| {
+            let name = escape_html(entry.trim_start_matches("components/").trim_end_matches(".van"));
+            format!(r#"<li><a href="/__van/preview/{name}">{name}</a></li>"#)
+        })
+        .collect();
     format!(
         r#"<!DOCTYPE html><html><body>
-        <h1>404 — Page not found</h1>
-        <p>Could not find <code>src/pages/{page}.van</code></p>
-        </body></html>"#
+        <h1>404 — Component not found</h1>
+        <p>Could not find <code>src/components/{name}.van</code></p>
+        <h2>Available components</h2>
+        <ul>{items}</ul>
+        <p><a href="/__van/components">Component catalog</a></p>
+        </body></html>"#,
+        name = escape_html(name),
+    )
+}
+
+/// A component as the catalog page shows it.
+#[derive(Debug)]
+struct CatalogEntry {
+    info: ComponentInfo,
+    /// The preview route, with example values for the props that have no default.
+    preview: String,
+}
+
+/// Every component of `files`, in path order, with its props, slots and a
+/// preview URL.
+fn component_catalog(project: &VanProject, files: &HashMap<String, String>) -> Vec<CatalogEntry> {
+    project
+        .component_entries(files)
+        .into_iter()
+        .map(|entry| {
+            let info = ComponentInfo::from_source(&entry, &files[&entry]);
+            let name = entry.trim_start_matches("components/").trim_end_matches(".van");
+            let query: Vec<String> = info
+                .props
+                .iter()
+                .filter(|prop| prop.default.is_none())
+                .map(|prop| {
+                    let example = match prop.prop_type.as_deref() {
+                        Some("Number") => "42",
+                        Some("Boolean") => "true",
+                        Some("Array") => "%5B%5D",
+                        Some("Object") => "%7B%7D",
+                        _ => "example",
+                    };
+                    format!("{}={example}", prop.name)
+                })
+                .collect();
+            let preview = if query.is_empty() {
+                format!("/__van/preview/{name}")
+            } else {
+                format!("/__van/preview/{name}?{}", query.join("&"))
+            };
+            CatalogEntry { info, preview }
+        })
+        .collect()
+}
+
+/// `/__van/components`: every component with its props, slots and a live preview.
+async fn catalog_handler(State(state): State<AppState>) -> Response {
+    let project = state.project();
+    let files = match project.collect_files() {
+        Ok(f) => f,
+        Err(e) => {
+            let error = DevEvent::error(&format!("Failed to collect files: {e:#}"), "");
+            return Html(error_page(&error)).into_response();
+        }
+    };
+    Html(catalog_html(&component_catalog(&project, &files))).into_response()
+}
+
+fn catalog_html(catalog: &[CatalogEntry]) -> String {
+    let sections: String = catalog
+        .iter()
+        .map(|entry| {
+            let props: String = entry
+                .info
+                .props
+                .iter()
+                .map(|prop| {
+                    format!(
+                        "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>",
+                        escape_html(&prop.name),
+                        escape_html(prop.prop_type.as_deref().unwrap_or("any")),
+                        if prop.required { "yes" } else { "" },
+                        escape_html(prop.default.as_deref().unwrap_or_default()),
+                    )
+                })
+                .collect();
+            let props = if props.is_empty() {
+                "<p>No props</p>".to_string()
+            } else {
+                format!("<table><tr><th>Prop</th><th>Type</th><th>Required</th><th>Default</th></tr>{props}</table>")
+            };
+            let slots = if entry.info.slots.is_empty() { "none".to_string() } else { entry.info.slots.join(", ") };
+            let preview = escape_html(&entry.preview);
+            format!(
+                r#"<section><h2>&lt;{tag}&gt; <small><code>{file}</code></small></h2>{props}<p>Slots: {slots}</p><p><a href="{preview}">Open preview</a></p><iframe src="{preview}" loading="lazy"></iframe></section>"#,
+                tag = escape_html(&entry.info.tag),
+                file = escape_html(&entry.info.file),
+                slots = escape_html(&slots),
+            )
+        })
+        .collect();
+    format!(
+        r#"<!DOCTYPE html><html><head><title>Components</title><style>
+        body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; }}
+        section {{ border-top: 1px solid #ddd; padding: 1rem 0; }}
+        table {{ border-collapse: collapse; }}
+        th, td {{ text-align: left; padding: 0.25rem 1rem 0.25rem 0; }}
+        iframe {{ width: 100%; height: 240px; border: 1px solid #ddd; }}
+        </style></head><body>
+        <h1>Components ({count})</h1>
+        {sections}
+        </body></html>"#,
+        count = catalog.len(),
     )
 }
 
-fn error_html(message: &str) -> String {
+fn not_found_html(project: &VanProject, page: &str) -> String {
+    let path = project.pages_dir().join(format!("{page}.van"));
+    let path = path.strip_prefix(&project.root).unwrap_or(&path).display();
+    let prefix = project.pages_prefix();
+    let items: String = project
+        .collect_files()
+        .map(|files| project.page_entries(&files))
+        .unwrap_or_default()
+        .iter()
+        .map(|entry| {
+            let name = entry.strip_prefix(&prefix).unwrap_or(entry).trim_end_matches(".van");
+            let href = if name == "index" { "" } else { name.strip_suffix("/index").unwrap_or(name) };
+            format!(r#"<li><a href="/{href}">{name}</a></li>"#)
+        })
+        .collect();
     format!(
         r#"<!DOCTYPE html><html><body>
-        <h1>Render Error</h1>
-        <pre>{message}</pre>
+        <h1>404 — Page not found</h1>
+        <p>Could not find <code>{path}</code></p>
+        <h2>Available pages</h2>
+        <ul>{items}</ul>
+        <p><a href="/__van/components">Component catalog</a></p>
         </body></html>"#
     )
 }
@@ -132,28 +652,43 @@ async fn playground_handler() -> Html<&'static str> {
 /// Discovery strategy for the pkg directory:
 /// 1. `VAN_WASM_PKG_DIR` environment variable
 /// 2. Relative to workspace root: `crates/van-compiler/pkg/`
-async fn playground_file_handler(Path(file): Path<String>) -> Response {
-    // Only allow specific file extensions
-    let content_type = if file.ends_with(".js") {
-        "application/javascript"
-    } else if file.ends_with(".wasm") {
-        "application/wasm"
-    } else {
-        return (StatusCode::NOT_FOUND, "Not found").into_response();
-    };
-
+async fn playground_file_handler(Path(file): Path<String>, headers: HeaderMap) -> Response {
     // Find the pkg directory
     let pkg_dir = if let Ok(dir) = std::env::var("VAN_WASM_PKG_DIR") {
         PathBuf::from(dir)
     } else {
         find_workspace_pkg_dir()
     };
+    serve_pkg_file(&pkg_dir, &file, &headers)
+}
 
-    let file_path = pkg_dir.join(&file);
+/// Respond with `file` from `pkg_dir`, revalidated through an `ETag` built
+/// from its modification time and size.
+///
+/// Only plain relative paths with known extensions are served, so a decoded
+/// `..%2f` in the route segment can never leave the pkg directory.
+fn serve_pkg_file(pkg_dir: &std::path::Path, file: &str, headers: &HeaderMap) -> Response {
+    let Some(content_type) = playground_content_type(file).filter(|_| is_plain_relative_path(file)) else {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    };
+    let file_path = pkg_dir.join(file);
+    let etag = std::fs::metadata(&file_path).ok().and_then(|meta| {
+        let mtime = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(format!("\"{:x}-{:x}-{:x}\"", mtime.as_secs(), mtime.subsec_nanos(), meta.len()))
+    });
+    if let Some(etag) = &etag {
+        if headers.get(header::IF_NONE_MATCH).is_some_and(|v| v.as_bytes() == etag.as_bytes()) {
+            return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag.clone())]).into_response();
+        }
+    }
     match std::fs::read(&file_path) {
         Ok(bytes) => (
             StatusCode::OK,
-            [(header::CONTENT_TYPE, content_type)],
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CACHE_CONTROL, "no-cache".to_string()),
+                (header::ETAG, etag.unwrap_or_default()),
+            ],
             bytes,
         )
             .into_response(),
@@ -168,6 +703,32 @@ async fn playground_file_handler(Path(file): Path<String>) -> Response {
     }
 }
 
+/// Content type of a wasm-pack output file the playground may load: the JS
+/// glue, the module, and the `.d.ts`/`.map` files devtools ask for.
+fn playground_content_type(file: &str) -> Option<&'static str> {
+    if file.ends_with(".js") {
+        Some("application/javascript")
+    } else if file.ends_with(".wasm") {
+        Some("application/wasm")
+    } else if file.ends_with(".map") {
+        Some("application/json")
+    } else if file.ends_with(".d.ts") {
+        Some("application/typescript")
+    } else {
+        None
+    }
+}
+
+/// Whether `file` only names entries below a directory: no `..`, `.`, root
+/// or drive components, and no backslashes that Windows would treat as separators.
+fn is_plain_relative_path(file: &str) -> bool {
+    !file.is_empty()
+        && !file.contains('\\')
+        && std::path::Path::new(file)
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
 /// Find the van-compiler/pkg directory by walking up from the current exe or cwd.
 fn find_workspace_pkg_dir() -> PathBuf {
     // Try relative to current exe
@@ -202,26 +763,50 @@ async fn ws_handler(
     State(state): State<AppState>,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_ws(socket, state.reload_tx))
+    ws.on_upgrade(move |socket| handle_ws(socket, state))
 }
 
-async fn handle_ws(socket: WebSocket, reload_tx: broadcast::Sender<()>) {
-    let mut rx = reload_tx.subscribe();
+async fn handle_ws(socket: WebSocket, state: AppState) {
+    let mut rx = state.reload_tx.subscribe();
     let (mut sender, mut receiver) = socket.split();
+    // The page this browser shows, from client.js's hello message
+    let page: Arc<Mutex<Option<String>>> = Arc::default();
 
-    // Spawn a task to forward reload signals to the WebSocket client
+    // Spawn a task to forward reload/css events meant for this page to the WebSocket client
+    let viewing = page.clone();
     let send_task = tokio::spawn(async move {
-        while rx.recv().await.is_ok() {
-            let msg = Message::Text("{\"type\":\"reload\"}".into());
+        while let Ok(notice) = rx.recv().await {
+            let page = viewing.lock().unwrap().clone();
+            if !notice.concerns(page.as_deref(), &state.page_deps.lock().unwrap()) {
+                continue;
+            }
+            let event = notice.event;
+            if event == DevEvent::Shutdown {
+                // "Going away" tells client.js not to retry the connection.
+                let _ = sender
+                    .send(Message::Close(Some(CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "dev server stopped".into(),
+                    })))
+                    .await;
+                break;
+            }
+            let msg = Message::Text(event.to_message().into());
             if sender.send(msg).await.is_err() {
                 break;
             }
         }
     });
 
-    // Drain incoming messages (we don't use them, but need to keep the connection alive)
+    // Read the hello message; drain the rest to keep the connection alive
     let recv_task = tokio::spawn(async move {
-        while let Some(Ok(_)) = receiver.next().await {}
+        while let Some(Ok(msg)) = receiver.next().await {
+            if let Message::Text(text) = msg {
+                if let Some(viewing) = hello_page(&text) {
+                    *page.lock().unwrap() = Some(viewing);
+                }
+            }
+        }
     });
 
     // When either task finishes, abort the other
@@ -230,3 +815,276 @@ async fn handle_ws(socket: WebSocket, reload_tx: broadcast::Sender<()>) {
         _ = recv_task => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    /// Status, content type and body of a GET through the router.
+    async fn get(app: &Router, uri: &str) -> (StatusCode, Option<String>, String) {
+        let resp = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = resp.status();
+        let content_type = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .map(|v| v.to_str().unwrap().to_string());
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, content_type, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_bind_falls_back_to_next_port() {
+        let taken = tokio::net::TcpListener::bind("0.0.0.0:0").await.unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let (_listener, bound) = bind(port).await.unwrap();
+        assert_ne!(bound, port);
+        assert!(bound > port && bound < port + PORT_ATTEMPTS);
+    }
+
+    #[tokio::test]
+    async fn test_serve_reports_bound_port() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/pages")).unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"name": "demo", "version": "0.1.0"}"#).unwrap();
+        std::fs::write(dir.path().join("src/pages/index.van"), "<template><p>Hi</p></template>").unwrap();
+
+        let server = serve(VanProject::load(dir.path()).unwrap(), Some(0)).await.unwrap();
+        let port = server.port();
+        assert_ne!(port, 0);
+        tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        server.shutdown().await.unwrap();
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_err());
+    }
+
+    #[test]
+    fn test_component_not_found_lists_available() {
+        let available = vec![
+            "components/button.van".to_string(),
+            "components/forms/input.van".to_string(),
+        ];
+        let html = component_not_found_html("buton", &available);
+        assert!(html.contains("src/components/buton.van"));
+        assert!(html.contains(r#"<a href="/__van/preview/button">button</a>"#));
+        assert!(html.contains(r#"<a href="/__van/preview/forms/input">forms/input</a>"#));
+    }
+
+    #[tokio::test]
+    async fn test_component_not_found_escapes_name() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/components")).unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"name": "demo", "version": "0.1.0"}"#).unwrap();
+        std::fs::write(dir.path().join("src/components/button.van"), "<template><button>Go</button></template>").unwrap();
+        let app = build_router(AppState::new(VanProject::load(dir.path()).unwrap()));
+
+        let (status, _, html) = get(&app, "/__van/preview/%3Cscript%3Ealert(1)%3C%2Fscript%3E").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(!html.contains("<script>alert(1)"), "{html}");
+        assert!(html.contains("src/components/&lt;script&gt;alert(1)&lt;/script&gt;.van"), "{html}");
+    }
+
+    #[test]
+    fn test_component_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let components = dir.path().join("src/components/forms");
+        std::fs::create_dir_all(&components).unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"name": "demo", "version": "0.1.0"}"#).unwrap();
+        std::fs::write(
+            components.join("input.van"),
+            r#"<template><label><slot name="label" /><input :value="value" /><slot name="hint" /></label></template>
+<script setup>
+defineProps({ value: String, size: { type: Number, required: true }, disabled: { type: Boolean, default: false }, options: Array })
+</script>"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/components/badge.van"), "<template><span><slot /></span></template>").unwrap();
+        let project = VanProject::load(dir.path()).unwrap();
+        let files = project.collect_files().unwrap();
+
+        let catalog = component_catalog(&project, &files);
+        let entries: Vec<(&str, &str)> = catalog.iter().map(|e| (e.info.tag.as_str(), e.info.file.as_str())).collect();
+        assert_eq!(entries, vec![("badge", "components/badge.van"), ("input", "components/forms/input.van")]);
+        assert_eq!(catalog[0].info.slots, vec!["default"]);
+        assert!(catalog[0].info.props.is_empty());
+        assert_eq!(catalog[0].preview, "/__van/preview/badge");
+
+        let input = &catalog[1];
+        assert_eq!(input.info.slots, vec!["label", "hint"]);
+        let props: Vec<(&str, Option<&str>, bool, Option<&str>)> = input
+            .info
+            .props
+            .iter()
+            .map(|p| (p.name.as_str(), p.prop_type.as_deref(), p.required, p.default.as_deref()))
+            .collect();
+        assert_eq!(
+            props,
+            vec![
+                ("value", Some("String"), false, None),
+                ("size", Some("Number"), true, None),
+                ("disabled", Some("Boolean"), false, Some("false")),
+                ("options", Some("Array"), false, None),
+            ]
+        );
+        assert_eq!(input.preview, "/__van/preview/forms/input?value=example&size=42&options=%5B%5D");
+    }
+
+    #[test]
+    fn test_partial_page_is_not_routable() {
+        let dir = tempfile::tempdir().unwrap();
+        let pages = dir.path().join("src/pages");
+        std::fs::create_dir_all(&pages).unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "demo", "version": "0.1.0"}"#,
+        )
+        .unwrap();
+        std::fs::write(pages.join("about.van"), "<template><p>About</p></template>").unwrap();
+        std::fs::write(pages.join("_card.van"), "<template><p>Card</p></template>").unwrap();
+        let project = VanProject::load(dir.path()).unwrap();
+
+        assert!(try_render(&project, "about", None, &Mutex::default()).unwrap().is_some());
+        assert!(try_render(&project, "_card", None, &Mutex::default()).unwrap().is_none());
+
+        let html = not_found_html(&project, "_card");
+        assert!(html.contains(r#"<a href="/about">about</a>"#));
+        assert!(!html.contains(r#"href="/_card""#));
+    }
+
+    #[test]
+    fn test_reload_config_if_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let pkg = dir.path().join("package.json");
+        std::fs::write(&pkg, r#"{"name": "before", "version": "0.1.0"}"#).unwrap();
+        let state = AppState::new(VanProject::load(dir.path()).unwrap());
+
+        std::fs::write(&pkg, r#"{"name": "after", "version": "0.1.0"}"#).unwrap();
+        state.reload_config_if_changed(&[dir.path().join("src/pages/index.van")]);
+        assert_eq!(state.project().config.name, "before", "unrelated change");
+
+        state.reload_config_if_changed(std::slice::from_ref(&pkg));
+        assert_eq!(state.project().config.name, "after");
+
+        std::fs::write(&pkg, "{ not json").unwrap();
+        state.reload_config_if_changed(&[pkg]);
+        assert_eq!(state.project().config.name, "after", "broken config keeps the old one");
+    }
+
+    #[tokio::test]
+    async fn test_assets_mode_serves_page_css_and_js() {
+        let dir = tempfile::tempdir().unwrap();
+        let pages = dir.path().join("src/pages");
+        std::fs::create_dir_all(&pages).unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "demo", "version": "0.1.0"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            pages.join("index.van"),
+            "<template><ClientOnly><button @click=\"count++\">{{ count }}</button></ClientOnly></template>\n\
+             <script setup>\nconst count = ref(0)\n</script>\n\
+             <style>\nbutton { color: red; }\n</style>",
+        )
+        .unwrap();
+        let app = build_router(AppState::new(VanProject::load(dir.path()).unwrap()));
+
+        let attr_after = |html: &str, marker: &str| {
+            let start = html.find(marker).unwrap_or_else(|| panic!("{marker} in {html}")) + marker.len();
+            html[start..start + html[start..].find('"').unwrap()].to_string()
+        };
+
+        let (_, _, inline) = get(&app, "/").await;
+        assert!(inline.contains("color: red"), "inline by default");
+
+        let (status, _, html) = get(&app, "/?__van_assets=1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(html.contains("/__van/ws"), "live reload still injected");
+        assert!(!html.contains("color: red"));
+
+        let css = attr_after(&html, r#"href="/themes/demo/assets/"#);
+        let (status, content_type, body) = get(&app, &format!("/themes/demo/assets/{css}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some("text/css"));
+        assert!(body.contains("color: red"));
+
+        let js = attr_after(&html, r#"src="/themes/demo/assets/js/pages/"#);
+        let js = format!("js/pages/{js}");
+        let (status, content_type, body) = get(&app, &format!("/themes/demo/assets/{js}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some("application/javascript"));
+        assert!(body.contains("count"));
+
+        let (status, _, _) = get(&app, "/themes/demo/assets/js/missing.js").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_nested_pages_are_routed() {
+        let dir = tempfile::tempdir().unwrap();
+        let pages = dir.path().join("src/pages");
+        std::fs::create_dir_all(pages.join("blog/2024")).unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "demo", "version": "0.1.0"}"#,
+        )
+        .unwrap();
+        std::fs::write(pages.join("blog/index.van"), "<template><p>Blog home</p></template>").unwrap();
+        std::fs::write(pages.join("blog/2024/recap.van"), "<template><p>Recap</p></template>").unwrap();
+        let app = build_router(AppState::new(VanProject::load(dir.path()).unwrap()));
+
+        let (_, _, html) = get(&app, "/blog/2024/recap").await;
+        assert!(html.contains("<p>Recap</p>"), "two-level page: {html}");
+
+        let (_, _, html) = get(&app, "/blog").await;
+        assert!(html.contains("<p>Blog home</p>"), "index fallback: {html}");
+        let (_, _, html) = get(&app, "/blog/").await;
+        assert!(html.contains("<p>Blog home</p>"), "trailing slash: {html}");
+
+        let (_, _, html) = get(&app, "/blog/2023/recap").await;
+        assert!(html.contains("404"));
+        assert!(html.contains(r#"<a href="/blog">blog/index</a>"#));
+        assert!(html.contains(r#"<a href="/blog/2024/recap">blog/2024/recap</a>"#));
+
+        let (status, content_type, _) = get(&app, "/__van/api/pages").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+    }
+
+    #[tokio::test]
+    async fn test_playground_file_rejects_traversal() {
+        for file in ["../Cargo.toml.js", "pkg/../../x.wasm", "/etc/passwd.js", "..\\x.js", "./van.js", "van.txt"] {
+            let resp = playground_file_handler(Path(file.to_string()), HeaderMap::new()).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{file}");
+            let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&bytes[..], b"Not found", "{file}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_playground_file_serves_maps_and_types() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("van_compiler.js.map"), "{}").unwrap();
+        std::fs::write(dir.path().join("van_compiler.d.ts"), "export {};").unwrap();
+
+        let resp = serve_pkg_file(dir.path(), "van_compiler.js.map", &HeaderMap::new());
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+        let etag = resp.headers()[header::ETAG].clone();
+
+        let resp = serve_pkg_file(dir.path(), "van_compiler.d.ts", &HeaderMap::new());
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/typescript");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag);
+        let resp = serve_pkg_file(dir.path(), "van_compiler.js.map", &headers);
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    }
+}
@@ -1,42 +1,233 @@
+use crate::hmr::{merge_events, ChangeSet, SnapshotCache};
 use anyhow::Result;
 use notify::{Event, RecursiveMode, Watcher};
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::sync::{mpsc as std_mpsc, Arc};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use van_context::ignore::IgnoreRules;
+use van_context::project::VanProject;
 
-/// Start watching the `src/` and `data/` directories for file changes.
+/// Default quiet period before a batch of file events is emitted.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// A batch is flushed after this many debounce windows even if events keep arriving
+/// (e.g. a long `git checkout`), so the browser is never starved of updates.
+const MAX_WAIT_WINDOWS: u32 = 10;
+
+/// Start watching the source, `data/` and `locales/` directories, plus the project's
+/// `package.json`, for file changes.
 ///
-/// When a `.van`, `.json`, or `.css` file changes, increments the version counter
-/// and sends a notification through the broadcast channel.
+/// Raw events are debounced: paths are collected until no new event arrives for
+/// `debounce`, then one [`ChangeSet`] is sent to the server, which re-renders before
+/// notifying browsers. Changes that only touch a `<style>` block are sent as CSS
+/// updates instead of full reloads.
 pub fn start(
-    project_dir: &Path,
+    project: &VanProject,
     version: Arc<AtomicU64>,
-    tx: broadcast::Sender<()>,
+    tx: mpsc::UnboundedSender<ChangeSet>,
+    debounce: Duration,
 ) -> Result<impl Watcher> {
-    let src_dir = project_dir.join("src");
+    let project_dir = project.root.as_path();
+    let src_dir = project.src_dir();
     let data_dir = project_dir.join("data");
+    let locales_dir = project_dir.join("locales");
+
+    let mut cache = SnapshotCache::default();
+    cache.prime(&src_dir);
 
+    let (raw_tx, raw_rx) = std_mpsc::channel::<PathBuf>();
+    let root = project_dir.to_path_buf();
+    let src = src_dir.clone();
+    let ignore = project.ignore_rules()?;
     let mut watcher =
         notify::recommended_watcher(move |res: std::result::Result<Event, notify::Error>| {
             if let Ok(event) = res {
-                let dominated = event.paths.iter().any(|p| {
-                    let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
-                    matches!(ext, "van" | "json" | "css")
-                });
-                if dominated {
-                    version.fetch_add(1, Ordering::SeqCst);
-                    let _ = tx.send(());
+                for path in event.paths {
+                    if is_watched(&root, &path) && !is_ignored(&ignore, &src, &path) {
+                        let _ = raw_tx.send(path);
+                    }
                 }
             }
         })?;
 
+    // The thread exits once the watcher (and with it `raw_tx`) is dropped.
+    std::thread::spawn(move || {
+        while let Some(paths) = next_batch(&raw_rx, debounce) {
+            let events = paths.iter().filter_map(|p| cache.event_for(p)).collect();
+            let events = merge_events(events);
+            if events.is_empty() {
+                continue;
+            }
+            version.fetch_add(1, Ordering::SeqCst);
+            let changes = ChangeSet {
+                paths: paths.into_iter().collect(),
+                events,
+            };
+            if tx.send(changes).is_err() {
+                break;
+            }
+        }
+    });
+
     if src_dir.exists() {
         watcher.watch(&src_dir, RecursiveMode::Recursive)?;
     }
-    if data_dir.exists() {
-        watcher.watch(&data_dir, RecursiveMode::Recursive)?;
+    for dir in [&data_dir, &locales_dir] {
+        if dir.exists() {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
     }
+    // Watch the root itself rather than the file, so editors that save by
+    // replacing `package.json` don't detach the watch.
+    watcher.watch(project_dir, RecursiveMode::NonRecursive)?;
 
     Ok(watcher)
 }
+
+/// Block until a path arrives, then keep collecting until `window` passes without
+/// a new one (or the batch has been open for too long).
+///
+/// Returns `None` once the sending side has hung up.
+fn next_batch(rx: &std_mpsc::Receiver<PathBuf>, window: Duration) -> Option<BTreeSet<PathBuf>> {
+    let first = rx.recv().ok()?;
+    let started = Instant::now();
+    let max_wait = window * MAX_WAIT_WINDOWS;
+    let mut paths = BTreeSet::from([first]);
+    while started.elapsed() < max_wait {
+        match rx.recv_timeout(window) {
+            Ok(path) => {
+                paths.insert(path);
+            }
+            Err(std_mpsc::RecvTimeoutError::Timeout) => break,
+            Err(std_mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    Some(paths)
+}
+
+/// Whether a changed path should trigger an update.
+///
+/// At the project root only `package.json` counts; below it, templates, data,
+/// styles, Markdown content and the `.ts`/`.js` modules that get inlined into signal JS.
+fn is_watched(root: &Path, path: &Path) -> bool {
+    if is_editor_temp(path) {
+        return false;
+    }
+    if path.parent() == Some(root) {
+        return path.file_name().is_some_and(|n| n == "package.json");
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    matches!(ext, "van" | "json" | "css" | "ts" | "js" | "md")
+}
+
+/// Whether a path under the source directory matches the project's ignore rules.
+fn is_ignored(ignore: &IgnoreRules, src_dir: &Path, path: &Path) -> bool {
+    match path.strip_prefix(src_dir) {
+        Ok(rel) => ignore.is_ignored(&rel.to_string_lossy().replace('\\', "/")),
+        Err(_) => false,
+    }
+}
+
+/// Swap/backup/lock files written by editors around a save.
+fn is_editor_temp(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return true;
+    };
+    name.starts_with('.')
+        || name.ends_with('~')
+        || matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("swp" | "swo" | "swx" | "tmp")
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_watched() {
+        let root = Path::new("/app");
+        let watched = |p: &str| is_watched(root, &root.join(p));
+        assert!(watched("src/pages/index.van"));
+        assert!(watched("data/index.json"));
+        assert!(watched("locales/de.json"));
+        assert!(watched("src/styles/global.css"));
+        assert!(watched("src/utils/format.ts"));
+        assert!(watched("src/utils/format.js"));
+        assert!(watched("package.json"));
+        assert!(!watched("tsconfig.json"));
+        assert!(!watched("README.md"));
+        assert!(!watched("src/pages/.index.van.swp"));
+        assert!(!watched("src/pages/index.van~"));
+        assert!(!watched("src/pages/.#index.van"));
+        assert!(!watched("src/pages/index.van.tmp"));
+        assert!(watched("src/pages/content/intro.md"));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let ignore = IgnoreRules::new(&["**/drafts/**"]).unwrap();
+        let src = Path::new("/app/src");
+        assert!(is_ignored(&ignore, src, &src.join("pages/drafts/wip.van")));
+        assert!(is_ignored(&ignore, src, &src.join("components/card.test.van")));
+        assert!(!is_ignored(&ignore, src, &src.join("pages/index.van")));
+        assert!(!is_ignored(&ignore, src, Path::new("/app/data/drafts/x.json")));
+    }
+
+    #[test]
+    fn test_next_batch_coalesces_burst() {
+        let (tx, rx) = std_mpsc::channel();
+        // A rename+truncate save: the same file reported several times.
+        for _ in 0..5 {
+            tx.send(PathBuf::from("src/pages/index.van")).unwrap();
+        }
+        tx.send(PathBuf::from("src/components/card.van")).unwrap();
+        let batch = next_batch(&rx, Duration::from_millis(20)).unwrap();
+        assert_eq!(
+            batch.into_iter().collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("src/components/card.van"),
+                PathBuf::from("src/pages/index.van"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next_batch_splits_on_quiet_period() {
+        let (tx, rx) = std_mpsc::channel();
+        let sender = std::thread::spawn(move || {
+            tx.send(PathBuf::from("a.van")).unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+            tx.send(PathBuf::from("b.van")).unwrap();
+        });
+        let first = next_batch(&rx, Duration::from_millis(20)).unwrap();
+        let second = next_batch(&rx, Duration::from_millis(20)).unwrap();
+        sender.join().unwrap();
+        assert_eq!(first, BTreeSet::from([PathBuf::from("a.van")]));
+        assert_eq!(second, BTreeSet::from([PathBuf::from("b.van")]));
+        assert_eq!(next_batch(&rx, Duration::from_millis(20)), None);
+    }
+
+    #[test]
+    fn test_next_batch_flushes_continuous_stream() {
+        let (tx, rx) = std_mpsc::channel();
+        let sender = std::thread::spawn(move || {
+            for i in 0..100 {
+                if tx.send(PathBuf::from(format!("{i}.van"))).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+        let started = Instant::now();
+        let batch = next_batch(&rx, Duration::from_millis(20)).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert!(batch.len() < 100, "a never-ending stream must still be flushed");
+        drop(rx);
+        sender.join().unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+//! Incremental `van generate`: pages whose sources and inputs are unchanged are
+//! not re-rendered.
+//!
+//! The cache lives in `.van/cache.json` and maps each page entry to a hash of its
+//! dependency closure (as reported by the compiler) plus everything else that
+//! affects its output, and to the files it emitted.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Cache location, relative to the project root.
+pub const CACHE_FILE: &str = ".van/cache.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildCache {
+    /// Toolchain version that wrote the cache; a different version invalidates it.
+    pub version: String,
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub hash: String,
+    /// Files the page was built from, as keys of the collected files map.
+    pub dependencies: Vec<String>,
+    /// Files written for the page, relative to the output directory.
+    pub outputs: Vec<String>,
+}
+
+impl BuildCache {
+    pub fn new() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Read the cache of a previous run. A missing, unreadable or outdated cache is `None`.
+    pub fn load(root: &Path) -> Option<Self> {
+        let content = fs::read_to_string(root.join(CACHE_FILE)).ok()?;
+        let cache: Self = serde_json::from_str(&content).ok()?;
+        (cache.version == env!("CARGO_PKG_VERSION")).then_some(cache)
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        let path = root.join(CACHE_FILE);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// The previous entry for `entry` if it can be reused as-is: its dependency closure
+    /// hashes the same under `salt` and every output it wrote still exists.
+    pub fn fresh(
+        &self,
+        entry: &str,
+        files: &HashMap<String, String>,
+        salt: &str,
+        out_dir: &Path,
+    ) -> Option<&CacheEntry> {
+        let cached = self.entries.get(entry)?;
+        let unchanged = closure_hash(&cached.dependencies, files, salt) == cached.hash;
+        let present = cached.outputs.iter().all(|o| out_dir.join(o).exists());
+        (unchanged && present).then_some(cached)
+    }
+
+    /// Outputs written by this cache's entries that `next` no longer produces.
+    pub fn stale_outputs(&self, next: &BuildCache, out_dir: &Path) -> Vec<PathBuf> {
+        let kept: Vec<&String> = next.entries.values().flat_map(|e| &e.outputs).collect();
+        self.entries
+            .values()
+            .flat_map(|e| &e.outputs)
+            .filter(|o| !kept.contains(o))
+            .map(|o| out_dir.join(o))
+            .collect()
+    }
+}
+
+/// Hash the contents of every dependency (missing files included) together with `salt`.
+///
+/// Uses 64-bit FNV-1a so the value is stable across toolchain versions and platforms.
+pub fn closure_hash(dependencies: &[String], files: &HashMap<String, String>, salt: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes.iter().chain(&[0]) {
+            hash ^= u64::from(*b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    feed(salt.as_bytes());
+    for dep in dependencies {
+        feed(dep.as_bytes());
+        match files.get(dep) {
+            Some(content) => feed(content.as_bytes()),
+            None => feed(b"\xff missing"),
+        }
+    }
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_closure_hash() {
+        let deps = vec!["pages/index.van".to_string(), "components/card.van".to_string()];
+        let a = files(&[("pages/index.van", "A"), ("components/card.van", "B")]);
+        let base = closure_hash(&deps, &a, "{}");
+        assert_eq!(base, closure_hash(&deps, &a, "{}"));
+
+        let changed = files(&[("pages/index.van", "A"), ("components/card.van", "C")]);
+        assert_ne!(base, closure_hash(&deps, &changed, "{}"));
+        let removed = files(&[("pages/index.van", "A")]);
+        assert_ne!(base, closure_hash(&deps, &removed, "{}"));
+        assert_ne!(base, closure_hash(&deps, &a, r#"{"x":1}"#));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::cmd::generate::{page_data, page_key, render_options, unused_mock_keys};
+use anyhow::{bail, Result};
+use van_context::project::VanProject;
+
+pub fn run() -> Result<()> {
+    let project = VanProject::load_cwd()?;
+    let (count, problems) = check(&project)?;
+    for problem in &problems {
+        eprintln!("\x1b[33m  \u{26a0} {problem}\x1b[0m");
+    }
+    println!("\nChecked {count} page(s): {} data problem(s)", problems.len());
+    Ok(())
+}
+
+/// Render every page with its data, writing nothing, and report where data
+/// and templates disagree: keys of a page's mock data that no template reads,
+/// and data paths its templates read that the data lacks. Returns the number
+/// of pages checked and one line per page and kind of problem.
+///
+/// Pages see the data `van generate` gives them in the default locale;
+/// `<script lang="data">` URLs come from the offline snapshot.
+fn check(project: &VanProject) -> Result<(usize, Vec<String>)> {
+    let (files, notes) = project.collect_files_noted()?;
+    for note in &notes {
+        eprintln!("\x1b[33m  \u{26a0} {note}\x1b[0m");
+    }
+    let page_entries = project.page_entries(&files);
+    if page_entries.is_empty() {
+        bail!("No pages found in {}", project.pages_dir().display());
+    }
+
+    let all_data = project.load_all_data();
+    let options = render_options(project, &files);
+    let default = project.default_locale();
+    let messages = project.load_locales()?.remove(&default);
+
+    let mut problems = Vec::new();
+    for entry in &page_entries {
+        let mut data = page_data(project, &all_data, &files, entry, false);
+        if let Some(messages) = &messages {
+            van_context::locale::inject(&mut data, &default, messages.clone());
+        }
+        let page = van_compiler::render_page(entry, &files, &serde_json::to_string(&data)?, &options)
+            .map_err(|e| anyhow::anyhow!("Failed to render {}: {}", entry, e))?;
+        problems.extend(unused_mock_keys(&all_data, &page_key(project, entry), entry, &page.data_used));
+        if !page.data_missing.is_empty() {
+            problems.push(format!("{entry}: templates read data the page lacks: {}", page.data_missing.join(", ")));
+        }
+    }
+    Ok((page_entries.len(), problems))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_check_reports_unused_and_missing_data() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages")).unwrap();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        fs::write(
+            root.join("src/pages/index.van"),
+            "<template><h1>{{ title }}</h1><p>{{ subtitle }}</p></template>",
+        )
+        .unwrap();
+        fs::write(
+            root.join("data/index.json"),
+            r#"{ "pages/index": { "title": "Home", "footer": "(c)" } }"#,
+        )
+        .unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        let (count, problems) = check(&project).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            problems,
+            [
+                "pages/index.van: mock data not read by any template: footer",
+                "pages/index.van: templates read data the page lacks: subtitle",
+            ]
+        );
+        assert!(!root.join("dist").exists());
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
 
-pub async fn run() -> Result<()> {
-    van_dev::start(3000).await
+pub async fn run(port: Option<u16>) -> Result<()> {
+    van_dev::start(port).await?.wait().await
 }
@@ -1,68 +1,1101 @@
+use crate::cache::{closure_hash, BuildCache, CacheEntry};
+use crate::page_data;
+use crate::report::{BuildReport, PageStats};
 use anyhow::{bail, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use van_context::config::OutputStyle;
 use van_context::project::VanProject;
 
-pub fn run() -> Result<()> {
+/// Flags for `van generate`.
+#[derive(Debug, Default)]
+pub struct GenerateOptions {
+    pub output_style: OutputStyle,
+    /// Ignore the build cache.
+    pub force: bool,
+    /// Fetch URLs declared in `<script lang="data">` blocks.
+    pub allow_network: bool,
+    /// Locales to generate besides the default one (`all`, or codes like `de,fr`).
+    pub locales: Option<String>,
+    /// Print per-phase compile timings for every rendered page.
+    pub timings: bool,
+    /// Make root-relative links page-relative, so the output works from disk
+    /// or under any subpath. Cannot be combined with `van.base`.
+    pub relative: bool,
+}
+
+/// One rendering of the site: the default locale at the output root, or another
+/// locale under `<code>/`.
+struct LocaleVariant {
+    /// `None` when the project has no `locales/` files.
+    code: Option<String>,
+    messages: Option<serde_json::Value>,
+    /// URL and output path prefix, `""` for the default locale.
+    prefix: String,
+}
+
+impl LocaleVariant {
+    /// Cache and report key of a page in this variant.
+    fn key(&self, entry: &str) -> String {
+        if self.prefix.is_empty() {
+            entry.to_string()
+        } else {
+            format!("{}/{entry}", self.prefix)
+        }
+    }
+
+    fn output(&self, file: &str) -> String {
+        if self.prefix.is_empty() {
+            file.to_string()
+        } else {
+            format!("{}/{file}", self.prefix)
+        }
+    }
+}
+
+pub fn run(
+    output_style: Option<OutputStyle>,
+    force: bool,
+    allow_network: bool,
+    locales: Option<String>,
+    timings: bool,
+    relative: bool,
+) -> Result<()> {
     let project = VanProject::load_cwd()?;
-    let files = project.collect_files()?;
+    let opts = GenerateOptions {
+        output_style: output_style.unwrap_or(project.output_style()),
+        force,
+        allow_network,
+        locales,
+        timings,
+        relative,
+    };
+    let count = generate(&project, &opts)?;
+    let out_dir = project.dist_dir();
+    println!(
+        "\nGenerated {} page(s) in {}/",
+        count,
+        out_dir.strip_prefix(&project.root).unwrap_or(&out_dir).display()
+    );
+    Ok(())
+}
+
+/// The compile options every page of `project` is rendered with.
+pub(crate) fn render_options(project: &VanProject, files: &HashMap<String, String>) -> van_compiler::CompileOptions {
+    van_compiler::CompileOptions {
+        env: project.load_env(),
+        auto_layout: project.auto_layout(),
+        global_css: project.global_css(files),
+        custom_elements: project.custom_elements(),
+        import_map: project.import_map(),
+        strict_coercion: project.strict_coercion(),
+        ..Default::default()
+    }
+}
+
+/// Key of a page entry in `data/index.json` (`pages/index.van` → `pages/index`).
+pub(crate) fn page_key(project: &VanProject, entry: &str) -> String {
+    let prefix = project.pages_prefix();
+    let stem = entry
+        .strip_prefix(&prefix)
+        .unwrap_or(entry)
+        .strip_suffix(".van")
+        .unwrap_or(entry);
+    format!("{prefix}{stem}")
+}
+
+/// The data a page renders with: its `data/index.json` entry, shallow-merged
+/// with its `<script lang="data">` block.
+pub(crate) fn page_data(
+    project: &VanProject,
+    all_data: &Value,
+    files: &HashMap<String, String>,
+    entry: &str,
+    allow_network: bool,
+) -> Value {
+    let page_data = match all_data.get(page_key(project, entry)) {
+        Some(pd) => pd.clone(),
+        // Fallback: use the entire data object (same as load_data())
+        None => all_data.clone(),
+    };
+    match van_parser::parse_blocks(&files[entry]).script_data {
+        Some(block) => {
+            let declared = page_data::resolve(&project.root, entry, &block, allow_network);
+            page_data::merge(page_data, declared)
+        }
+        None => page_data,
+    }
+}
+
+/// A warning listing the keys of the page's own mock data (its `page_key`
+/// entry in `data/index.json`) that no template read, if any.
+pub(crate) fn unused_mock_keys(all_data: &Value, page_key: &str, entry: &str, data_used: &[String]) -> Option<String> {
+    // Only a page's own mock data can drift from its templates
+    let unused = van_compiler::unused_keys(all_data.get(page_key)?, data_used);
+    (!unused.is_empty()).then(|| format!("{entry}: mock data not read by any template: {}", unused.join(", ")))
+}
+
+/// Render every page into the output directory, returning the number of pages written.
+///
+/// Pages unchanged since the last run (see [`crate::cache`]) are kept as they are
+/// unless `force` is set. Also writes `build-report.json` with per-page timings and sizes.
+/// With `locales`, every page is also rendered per locale under `<code>/`.
+fn generate(project: &VanProject, opts: &GenerateOptions) -> Result<usize> {
+    let started = Instant::now();
+    let style = opts.output_style;
+    let (files, notes) = project.collect_files_noted()?;
+    for note in &notes {
+        eprintln!("\x1b[33m  \u{26a0} {note}\x1b[0m");
+    }
     let page_entries = project.page_entries(&files);
 
     if page_entries.is_empty() {
-        bail!("No pages found in src/pages/");
+        bail!("No pages found in {}", project.pages_dir().display());
     }
+    project.check_routes(&page_entries)?;
 
     let all_data = project.load_all_data();
+    let base = project.base();
+    if opts.relative && base != "/" {
+        bail!("--relative cannot be combined with van.base ({base}): relative links work under any base");
+    }
+    let options = render_options(project, &files);
+
+    let routes: HashSet<String> = page_entries
+        .iter()
+        .map(|entry| project.route_for_entry(entry))
+        .collect();
+    let site_url = project.site_url();
+    let variants = locale_variants(project, opts.locales.as_deref())?;
 
-    // Create dist/ directory
+    // Without a usable cache, start from a clean output directory
     let dist_dir = project.dist_dir();
-    if dist_dir.exists() {
+    let cache = if opts.force { None } else { BuildCache::load(&project.root) };
+    if cache.is_none() && dist_dir.exists() {
         fs::remove_dir_all(&dist_dir)?;
     }
     fs::create_dir_all(&dist_dir)?;
 
-    let mut count = 0;
+    // Everything besides the sources and page data that shapes the output,
+    // the render options among it (their maps sorted, as a HashMap's order varies)
+    let unordered = van_compiler::CompileOptions { env: HashMap::new(), file_origins: HashMap::new(), ..options.clone() };
+    let settings = format!(
+        "{style:?}\0{base}\0{}\0{site_url:?}\0{:?}\0{:?}\0{:?}\0{unordered:?}",
+        opts.relative,
+        options.env.iter().collect::<BTreeMap<_, _>>(),
+        options.file_origins.iter().collect::<BTreeMap<_, _>>(),
+        routes.iter().collect::<BTreeSet<_>>(),
+    );
 
-    for entry in &page_entries {
-        // entry is like "pages/index.van"
-        let stem = entry
-            .strip_prefix("pages/")
-            .unwrap_or(entry)
-            .strip_suffix(".van")
-            .unwrap_or(entry);
+    let mut next_cache = BuildCache::new();
+    let mut sitemap = Vec::new();
+    let mut stats = Vec::new();
 
-        let page_key = format!("pages/{}", stem);
-        let page_data = if let Some(pd) = all_data.get(&page_key) {
-            pd.clone()
-        } else {
-            // Fallback: use the entire data object (same as load_data())
-            all_data.clone()
+    for variant in &variants {
+        for entry in &page_entries {
+            let page_started = Instant::now();
+            let key = variant.key(entry);
+            // entry is like "pages/index.van"
+            let page_key = page_key(project, entry);
+            let mut page_data = page_data(project, &all_data, &files, entry, opts.allow_network);
+            if let (Some(code), Some(messages)) = (&variant.code, &variant.messages) {
+                van_context::locale::inject(&mut page_data, code, messages.clone());
+            }
+            let data_json = serde_json::to_string(&page_data)?;
+            let salt = format!("{settings}\0{data_json}");
+
+            let route = project.route_for_entry(entry);
+            let output = variant.output(&output_file(&route, style));
+            let output_path = dist_dir.join(&output);
+            let href = format!("{}{}", link_prefix(variant), route_href(&route, style));
+            let canonical = site_url
+                .filter(|_| in_sitemap(&files[entry]))
+                .map(|site_url| page_url(site_url, &base, &href));
+            if let Some(url) = &canonical {
+                let source = project.src_dir().join(entry);
+                let lastmod = fs::metadata(&source).and_then(|m| m.modified()).ok();
+                sitemap.push((url.clone(), lastmod));
+            }
+
+            let cached = cache
+                .as_ref()
+                .and_then(|c| c.fresh(&key, &files, &salt, &dist_dir));
+            if let Some(cached) = cached {
+                let html = fs::read_to_string(&output_path)?;
+                println!("  {key} (unchanged)");
+                let mut page_stats = PageStats::new(&key, &output, page_started.elapsed(), &html);
+                page_stats.cached = true;
+                stats.push(page_stats);
+                next_cache.entries.insert(key, cached.clone());
+                continue;
+            }
+
+            let page = van_compiler::render_page(entry, &files, &data_json, &options)
+                .map_err(|e| anyhow::anyhow!("Failed to render {}: {}", key, e))?;
+            for warning in &page.warnings {
+                eprintln!("\x1b[33m  \u{26a0} {warning}\x1b[0m");
+            }
+            // Only a page's own mock data can drift from its templates
+            if let Some(unused) = unused_mock_keys(&all_data, &page_key, entry, &page.data_used) {
+                eprintln!("\x1b[33m  \u{26a0} {unused}\x1b[0m");
+            }
+            let html = rewrite_page_links(&page.html, &routes, style, &link_prefix(variant));
+            let page_base = if opts.relative { relative_base(&output) } else { base.clone() };
+            let mut html = apply_base(&html, &page_base);
+
+            if let Some(url) = &canonical {
+                let link = format!(r#"<link rel="canonical" href="{}" />"#, xml_escape(url));
+                van_dev::render::inject_before_close(&mut html, "</head>", &link);
+            }
+
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(&output_path, &html)?;
+            println!(
+                "  {} -> {}",
+                key,
+                output_path
+                    .strip_prefix(&project.root)
+                    .unwrap_or(&output_path)
+                    .display()
+            );
+            let mut page_stats = PageStats::new(&key, &output, page_started.elapsed(), &html);
+            page_stats.phases = Some(page.timings);
+            stats.push(page_stats);
+            next_cache.entries.insert(
+                key,
+                CacheEntry {
+                    hash: closure_hash(&page.dependencies, &files, &salt),
+                    dependencies: page.dependencies,
+                    outputs: vec![output],
+                },
+            );
+        }
+    }
+
+    // Only outputs of pages that are gone (or moved) are removed
+    if let Some(cache) = &cache {
+        for stale in cache.stale_outputs(&next_cache, &dist_dir) {
+            if stale.exists() {
+                fs::remove_file(&stale)?;
+                // Drop the page directory too if that left it empty (nested style)
+                if let Some(dir) = stale.parent().filter(|d| *d != dist_dir) {
+                    let _ = fs::remove_dir(dir);
+                }
+            }
+        }
+    }
+    next_cache.save(&project.root)?;
+
+    if site_url.is_some() {
+        fs::write(dist_dir.join("sitemap.xml"), sitemap_xml(&sitemap))?;
+        println!("  sitemap.xml ({} URL(s))", sitemap.len());
+    } else {
+        println!("  Skipping sitemap.xml: set \"van.siteUrl\" in package.json to generate it");
+    }
+
+    let report = BuildReport::new(started.elapsed(), stats);
+    report.write(&dist_dir)?;
+    report.print_summary();
+    if opts.timings {
+        report.print_timings();
+    }
+    Ok(report.pages.len())
+}
+
+/// The locales to render: the default one first, then those selected by `--locales`.
+///
+/// Without `locales/` files there is a single variant with no messages. Keys
+/// missing from a locale fall back to the default locale, with a warning each.
+fn locale_variants(project: &VanProject, selected: Option<&str>) -> Result<Vec<LocaleVariant>> {
+    let mut locales = project.load_locales()?;
+    if locales.is_empty() {
+        if selected.is_some() {
+            bail!("--locales was given but there are no files in locales/");
+        }
+        return Ok(vec![LocaleVariant {
+            code: None,
+            messages: None,
+            prefix: String::new(),
+        }]);
+    }
+
+    let default = project.default_locale();
+    let Some(default_messages) = locales.remove(&default) else {
+        bail!("Default locale \"{default}\" has no locales/{default}.json");
+    };
+    let codes: Vec<String> = match selected {
+        None => Vec::new(),
+        Some("all") => locales.keys().cloned().collect(),
+        Some(list) => {
+            let mut codes = Vec::new();
+            for code in list.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+                if code != default && !locales.contains_key(code) {
+                    bail!("Unknown locale \"{code}\" (no locales/{code}.json)");
+                }
+                if code != default && !codes.iter().any(|c| c == code) {
+                    codes.push(code.to_string());
+                }
+            }
+            codes
+        }
+    };
+
+    let mut variants = vec![LocaleVariant {
+        code: Some(default.clone()),
+        messages: Some(default_messages.clone()),
+        prefix: String::new(),
+    }];
+    for code in codes {
+        let (messages, missing) = van_context::locale::with_fallback(&locales[&code], &default_messages);
+        for key in missing {
+            eprintln!("\x1b[33m  \u{26a0} {code}: missing translation {key} (using {default})\x1b[0m");
+        }
+        variants.push(LocaleVariant {
+            code: Some(code.clone()),
+            messages: Some(messages),
+            prefix: code,
+        });
+    }
+    Ok(variants)
+}
+
+/// Root-relative prefix of a variant's links: `""` or `/de`.
+fn link_prefix(variant: &LocaleVariant) -> String {
+    if variant.prefix.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", variant.prefix)
+    }
+}
+
+/// Whether a page wants a sitemap entry and canonical link.
+///
+/// Pages opt out with `defineOptions({ sitemap: false })` in `<script setup>`.
+fn in_sitemap(source: &str) -> bool {
+    let Some(script) = van_parser::parse_blocks(source).script_setup else {
+        return true;
+    };
+    !van_parser::parse_define_options(&script)
+        .iter()
+        .any(|(key, value)| key == "sitemap" && value == "false")
+}
+
+/// Where a route is written, relative to the output directory.
+///
+/// The index page is always `index.html`; `/blog/intro` becomes
+/// `blog/intro/index.html` (nested) or `blog/intro.html` (flat).
+fn output_file(route: &str, style: OutputStyle) -> String {
+    let path = route.trim_matches('/');
+    match (path.is_empty(), style) {
+        (true, _) => "index.html".to_string(),
+        (false, OutputStyle::Nested) => format!("{path}/index.html"),
+        (false, OutputStyle::Flat) => format!("{path}.html"),
+    }
+}
+
+/// The root-relative URL a route is served at: `/about/` (nested) or `/about.html` (flat).
+fn route_href(route: &str, style: OutputStyle) -> String {
+    let path = route.trim_matches('/');
+    match (path.is_empty(), style) {
+        (true, _) => "/".to_string(),
+        (false, OutputStyle::Nested) => format!("/{path}/"),
+        (false, OutputStyle::Flat) => format!("/{path}.html"),
+    }
+}
+
+/// Rewrite root-relative `href`s that point at a page so they match the output style.
+///
+/// `/about`, `/about/`, `/about.html` and `/about/index.html` all name the `/about`
+/// route; query strings and fragments are kept. Links to anything that is not a
+/// page (assets, API paths) are left alone. Page links get `prefix` (e.g. `/de`)
+/// prepended so a locale's pages link to each other.
+fn rewrite_page_links(
+    html: &str,
+    routes: &HashSet<String>,
+    style: OutputStyle,
+    prefix: &str,
+) -> String {
+    let attr = "href=\"";
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(pos) = rest.find(attr) {
+        let value_start = pos + attr.len();
+        result.push_str(&rest[..value_start]);
+        rest = &rest[value_start..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        let value = &rest[..end];
+        match page_link_route(value, routes) {
+            Some((route, suffix)) => {
+                result.push_str(prefix);
+                result.push_str(&route_href(&route, style));
+                result.push_str(suffix);
+            }
+            None => result.push_str(value),
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The route a root-relative link points at (if it is a page), plus its `?query`/`#fragment`.
+fn page_link_route<'a>(href: &'a str, routes: &HashSet<String>) -> Option<(String, &'a str)> {
+    if !href.starts_with('/') || href.starts_with("//") {
+        return None;
+    }
+    let split = href.find(['?', '#']).unwrap_or(href.len());
+    let (path, suffix) = href.split_at(split);
+    let path = path
+        .strip_suffix("index.html")
+        .or_else(|| path.strip_suffix(".html"))
+        .unwrap_or(path);
+    let path = path.trim_end_matches('/');
+    let route = if path.is_empty() { "/" } else { path };
+    routes.contains(route).then(|| (route.to_string(), suffix))
+}
+
+/// The absolute URL of a page: `https://example.com` + `/docs/` + `/about/` -> `https://example.com/docs/about/`.
+fn page_url(site_url: &str, base: &str, href: &str) -> String {
+    join_url(site_url, &format!("{base}{}", href.trim_start_matches('/')))
+}
+
+/// Join an origin and a path with exactly one `/` between them.
+fn join_url(site_url: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        site_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+fn sitemap_xml(urls: &[(String, Option<SystemTime>)]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (url, lastmod) in urls {
+        xml.push_str("  <url>\n");
+        xml.push_str(&format!("    <loc>{}</loc>\n", xml_escape(url)));
+        if let Some(date) = lastmod.and_then(iso_date) {
+            xml.push_str(&format!("    <lastmod>{date}</lastmod>\n"));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Format a timestamp as a UTC `YYYY-MM-DD` date.
+fn iso_date(time: SystemTime) -> Option<String> {
+    let days = (time.duration_since(UNIX_EPOCH).ok()?.as_secs() / 86_400) as i64;
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    Some(format!("{year:04}-{month:02}-{day:02}"))
+}
+
+/// The `base` that turns root-relative URLs into ones relative to the page
+/// written at `output`: `./` for `index.html`, `../` for `about/index.html`.
+fn relative_base(output: &str) -> String {
+    match output.matches('/').count() {
+        0 => "./".to_string(),
+        depth => "../".repeat(depth),
+    }
+}
+
+/// Prefix root-relative `href`/`src` URLs (`/about`, `/css/site.css`) with `base`.
+///
+/// Only whole `href`/`src` attributes of tags are rewritten, so `data-src`,
+/// comments and the text of `<script>`/`<style>` are left alone.
+/// Protocol-relative (`//cdn...`) and absolute URLs are left alone, as is
+/// everything when `base` is `/`.
+fn apply_base(html: &str, base: &str) -> String {
+    if base == "/" {
+        return html.to_string();
+    }
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        out.push_str(&html[pos..start]);
+        let rest = &html[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map_or(html.len(), |e| start + 4 + e + 3);
+            out.push_str(&html[start..end]);
+            pos = end;
+            continue;
+        }
+        let name_len = rest[1..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(rest.len() - 1);
+        let Some(end) = (name_len > 0).then(|| van_parser::tag_end(rest)).flatten() else {
+            out.push('<');
+            pos = start + 1;
+            continue;
         };
-        let data_json = serde_json::to_string(&page_data)?;
+        out.push_str(&rebase_attrs(&rest[..end], base));
+        pos = start + end;
 
-        let html = van_compiler::render_to_string(entry, &files, &data_json)
-            .map_err(|e| anyhow::anyhow!("Failed to render {}: {}", entry, e))?;
+        let name = rest[1..1 + name_len].to_ascii_lowercase();
+        if name == "script" || name == "style" {
+            let close = format!("</{name}");
+            let body_end = html[pos..]
+                .to_ascii_lowercase()
+                .find(&close)
+                .map_or(html.len(), |c| pos + c);
+            out.push_str(&html[pos..body_end]);
+            pos = body_end;
+        }
+    }
+    out.push_str(&html[pos..]);
+    out
+}
 
-        // Write output: index.van -> dist/index.html, other.van -> dist/other/index.html
-        let output_path = if stem == "index" {
-            dist_dir.join("index.html")
-        } else {
-            let page_dir = dist_dir.join(stem);
-            fs::create_dir_all(&page_dir)?;
-            page_dir.join("index.html")
+/// Prefix the root-relative `href`/`src` values of one opening tag with `base`.
+fn rebase_attrs(tag: &str, base: &str) -> String {
+    let bytes = tag.as_bytes();
+    let mut out = String::with_capacity(tag.len() + base.len());
+    let mut copied = 0;
+    // Start after `<`; the tag name reads as an attribute without a value
+    let mut i = 1;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() || matches!(bytes[i], b'/' | b'>') {
+            i += 1;
+            continue;
+        }
+        let name_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !matches!(bytes[i], b'=' | b'/' | b'>') {
+            i += 1;
+        }
+        let name = &tag[name_start..i];
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if bytes.get(i) != Some(&b'=') {
+            continue;
+        }
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let (value_start, value_end) = match bytes.get(i) {
+            Some(&q @ (b'"' | b'\'')) => {
+                let end = tag[i + 1..].find(q as char).map_or(tag.len(), |e| i + 1 + e);
+                (i + 1, end)
+            }
+            _ => {
+                let end = tag[i..]
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .map_or(tag.len(), |e| i + e);
+                (i, end)
+            }
         };
+        let value = &tag[value_start..value_end];
+        let is_url = name.eq_ignore_ascii_case("href") || name.eq_ignore_ascii_case("src");
+        if is_url && value.starts_with('/') && !value.starts_with("//") {
+            out.push_str(&tag[copied..value_start]);
+            out.push_str(base);
+            copied = value_start + 1;
+        }
+        i = value_end + 1;
+    }
+    out.push_str(&tag[copied..]);
+    out
+}
 
-        fs::write(&output_path, &html)?;
-        println!(
-            "  {} -> {}",
-            entry,
-            output_path
-                .strip_prefix(&project.root)
-                .unwrap_or(&output_path)
-                .display()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_base() {
+        let html = r##"<link href="/css/site.css"><a href="/about">About</a><img src="/logo.png"><a href="//cdn.example.com/x.js"></a><a href="https://example.com/">x</a><a href="#top">top</a>"##;
+        assert_eq!(
+            apply_base(html, "/docs/"),
+            r##"<link href="/docs/css/site.css"><a href="/docs/about">About</a><img src="/docs/logo.png"><a href="//cdn.example.com/x.js"></a><a href="https://example.com/">x</a><a href="#top">top</a>"##
         );
-        count += 1;
+        assert_eq!(apply_base(html, "/"), html);
     }
 
-    println!("\nGenerated {} page(s) in dist/", count);
-    Ok(())
+    #[test]
+    fn test_apply_base_only_rewrites_url_attributes() {
+        assert_eq!(
+            apply_base(r#"<img data-src="/lazy.png" src="/logo.png"><a title="x href=&quot;/y" HREF='/z'>z</a>"#, "/docs/"),
+            r#"<img data-src="/lazy.png" src="/docs/logo.png"><a title="x href=&quot;/y" HREF='/docs/z'>z</a>"#
+        );
+        assert_eq!(
+            apply_base(r#"<p>Set href="/x" here</p><!-- <a href="/old"> -->"#, "/docs/"),
+            r#"<p>Set href="/x" here</p><!-- <a href="/old"> -->"#
+        );
+    }
+
+    #[test]
+    fn test_apply_base_skips_script_and_style_text() {
+        let html = r#"<script src="/app.js"></script><script>el.innerHTML = '<a href="/x">x</a>'; if (a<b) {}</script><style>a[href="/x"] { color: red }</style><a href="/y">y</a>"#;
+        assert_eq!(
+            apply_base(html, "../"),
+            r#"<script src="../app.js"></script><script>el.innerHTML = '<a href="/x">x</a>'; if (a<b) {}</script><style>a[href="/x"] { color: red }</style><a href="../y">y</a>"#
+        );
+    }
+
+    #[test]
+    fn test_join_url() {
+        assert_eq!(join_url("https://example.com", "/about/"), "https://example.com/about/");
+        assert_eq!(join_url("https://example.com/", "/about/"), "https://example.com/about/");
+        assert_eq!(join_url("https://example.com/", "about/"), "https://example.com/about/");
+        assert_eq!(join_url("https://example.com", "/"), "https://example.com/");
+    }
+
+    #[test]
+    fn test_page_url() {
+        assert_eq!(page_url("https://example.com/", "/", "/"), "https://example.com/");
+        assert_eq!(page_url("https://example.com", "/", "/about/"), "https://example.com/about/");
+        assert_eq!(
+            page_url("https://example.com/", "/docs/", "/blog/intro/"),
+            "https://example.com/docs/blog/intro/"
+        );
+        assert_eq!(
+            page_url("https://example.com/", "/", "/about.html"),
+            "https://example.com/about.html"
+        );
+        assert_eq!(page_url("https://example.com", "/docs/", "/"), "https://example.com/docs/");
+    }
+
+    #[test]
+    fn test_rewrite_page_links() {
+        let routes: HashSet<String> = ["/", "/about", "/blog/intro"].iter().map(|r| r.to_string()).collect();
+        let html = r##"<a href="/">Home</a><a href="/about">About</a><a href="/blog/intro/#top">Intro</a><a href="/about.html?x=1">A</a><link href="/css/site.css"><a href="/missing">M</a><a href="https://example.com/about">E</a>"##;
+        assert_eq!(
+            rewrite_page_links(html, &routes, OutputStyle::Flat, ""),
+            r##"<a href="/">Home</a><a href="/about.html">About</a><a href="/blog/intro.html#top">Intro</a><a href="/about.html?x=1">A</a><link href="/css/site.css"><a href="/missing">M</a><a href="https://example.com/about">E</a>"##
+        );
+        assert_eq!(
+            rewrite_page_links(html, &routes, OutputStyle::Nested, ""),
+            r##"<a href="/">Home</a><a href="/about/">About</a><a href="/blog/intro/#top">Intro</a><a href="/about/?x=1">A</a><link href="/css/site.css"><a href="/missing">M</a><a href="https://example.com/about">E</a>"##
+        );
+        assert_eq!(
+            rewrite_page_links(r#"<a href="/about">A</a><link href="/css/site.css">"#, &routes, OutputStyle::Nested, "/de"),
+            r#"<a href="/de/about/">A</a><link href="/css/site.css">"#
+        );
+    }
+
+    fn write_styled_site(root: &std::path::Path) {
+        fs::create_dir_all(root.join("src/pages/blog")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        fs::write(
+            root.join("src/pages/index.van"),
+            r#"<template><a href="/blog/intro">Intro</a></template>"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/blog/intro.van"),
+            r#"<template><a href="/">Home</a></template>"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_generate_flat_output() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_styled_site(root);
+
+        let project = VanProject::load(root).unwrap();
+        assert_eq!(generate(&project, &GenerateOptions { output_style: OutputStyle::Flat, ..Default::default() }).unwrap(), 2);
+        let index = fs::read_to_string(root.join("dist/index.html")).unwrap();
+        assert!(index.contains(r#"href="/blog/intro.html""#));
+        assert!(root.join("dist/blog/intro.html").exists());
+        assert!(!root.join("dist/blog/intro").exists());
+    }
+
+    #[test]
+    fn test_generate_nested_output() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_styled_site(root);
+
+        let project = VanProject::load(root).unwrap();
+        assert_eq!(generate(&project, &GenerateOptions::default()).unwrap(), 2);
+        let index = fs::read_to_string(root.join("dist/index.html")).unwrap();
+        assert!(index.contains(r#"href="/blog/intro/""#));
+        assert!(root.join("dist/blog/intro/index.html").exists());
+    }
+
+    fn write_localized_site(root: &std::path::Path) {
+        write_styled_site(root);
+        fs::create_dir_all(root.join("locales")).unwrap();
+        fs::write(
+            root.join("src/pages/index.van"),
+            r#"<template><h1>{{ $t('nav.home') }}</h1><p>{{ $t('nav.about') }}</p><span>{{ $locale }}</span><a href="/blog/intro">Intro</a></template>"#,
+        )
+        .unwrap();
+        fs::write(root.join("locales/en.json"), r#"{"nav": {"home": "Home", "about": "About"}}"#).unwrap();
+        fs::write(root.join("locales/de.json"), r#"{"nav": {"home": "Startseite"}}"#).unwrap();
+    }
+
+    #[test]
+    fn test_generate_default_locale_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_localized_site(root);
+
+        let project = VanProject::load(root).unwrap();
+        assert_eq!(generate(&project, &GenerateOptions::default()).unwrap(), 2);
+        let index = fs::read_to_string(root.join("dist/index.html")).unwrap();
+        assert!(index.contains("<h1>Home</h1>"));
+        assert!(index.contains("<span>en</span>"));
+        assert!(!root.join("dist/de").exists());
+    }
+
+    #[test]
+    fn test_generate_all_locales() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_localized_site(root);
+
+        let project = VanProject::load(root).unwrap();
+        let opts = GenerateOptions {
+            locales: Some("all".into()),
+            ..Default::default()
+        };
+        assert_eq!(generate(&project, &opts).unwrap(), 4);
+
+        let de = fs::read_to_string(root.join("dist/de/index.html")).unwrap();
+        assert!(de.contains("<h1>Startseite</h1>"));
+        // Missing in de.json: falls back to the default locale
+        assert!(de.contains("<p>About</p>"));
+        assert!(de.contains("<span>de</span>"));
+        assert!(de.contains(r#"href="/de/blog/intro/""#));
+        assert!(root.join("dist/de/blog/intro/index.html").exists());
+        assert!(root.join("dist/index.html").exists());
+
+        let report = fs::read_to_string(root.join("dist/build-report.json")).unwrap();
+        assert!(report.contains(r#""entry": "de/pages/index.van""#));
+    }
+
+    #[test]
+    fn test_generate_rejects_unknown_locale() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_localized_site(root);
+
+        let project = VanProject::load(root).unwrap();
+        let opts = GenerateOptions {
+            locales: Some("fr".into()),
+            ..Default::default()
+        };
+        let err = generate(&project, &opts).unwrap_err().to_string();
+        assert!(err.contains("Unknown locale \"fr\""), "{err}");
+    }
+
+    #[test]
+    fn test_generate_writes_build_report() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_styled_site(root);
+        fs::write(
+            root.join("src/pages/index.van"),
+            "<template><h1>Home</h1></template>\n<style>h1 { color: red; }</style>",
+        )
+        .unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        generate(&project, &GenerateOptions::default()).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(root.join("dist/build-report.json")).unwrap())
+                .unwrap();
+        assert_eq!(report["version"], env!("CARGO_PKG_VERSION"));
+        assert!(report["totalMs"].is_number());
+        let pages = report["pages"].as_array().unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0]["entry"], "pages/blog/intro.van");
+        assert_eq!(pages[0]["output"], "blog/intro/index.html");
+        let index = &pages[1];
+        assert_eq!(index["entry"], "pages/index.van");
+        assert_eq!(index["output"], "index.html");
+        for key in ["durationMs", "htmlBytes", "cssBytes", "jsBytes", "assetCount"] {
+            assert!(index[key].is_number(), "missing {key}");
+        }
+        assert!(index["cssBytes"].as_u64().unwrap() >= "h1 { color: red; }".len() as u64);
+        assert_eq!(
+            index["htmlBytes"].as_u64().unwrap(),
+            fs::read_to_string(root.join("dist/index.html")).unwrap().len() as u64
+        );
+    }
+
+    #[test]
+    fn test_generate_reuses_unchanged_pages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages")).unwrap();
+        fs::create_dir_all(root.join("src/components")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        fs::write(
+            root.join("src/pages/index.van"),
+            "<template><card /></template>\n<script setup>\nimport Card from '../components/card.van'\n</script>",
+        )
+        .unwrap();
+        fs::write(root.join("src/pages/about.van"), "<template><h1>About</h1></template>").unwrap();
+        fs::write(root.join("src/pages/old.van"), "<template><h1>Old</h1></template>").unwrap();
+        fs::write(root.join("src/components/card.van"), "<template><p>v1</p></template>").unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        generate(&project, &GenerateOptions::default()).unwrap();
+        assert!(root.join(".van/cache.json").exists());
+        let marker = root.join("dist/extra.txt");
+        fs::write(&marker, "kept").unwrap();
+
+        // Second run: one component changed.
+        fs::write(root.join("src/components/card.van"), "<template><p>v2</p></template>").unwrap();
+        assert_eq!(generate(&project, &GenerateOptions::default()).unwrap(), 3);
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(root.join("dist/build-report.json")).unwrap())
+                .unwrap();
+        let cached: Vec<(&str, bool)> = report["pages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| (p["entry"].as_str().unwrap(), p["cached"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(
+            cached,
+            vec![("pages/about.van", true), ("pages/index.van", false), ("pages/old.van", true)]
+        );
+        assert!(fs::read_to_string(root.join("dist/index.html")).unwrap().contains("v2"));
+        assert!(marker.exists(), "the output directory is no longer wiped");
+
+        // Third run: a removed page takes only its own output with it.
+        fs::remove_file(root.join("src/pages/old.van")).unwrap();
+        assert_eq!(generate(&project, &GenerateOptions::default()).unwrap(), 2);
+        assert!(!root.join("dist/old").exists());
+        assert!(root.join("dist/about/index.html").exists());
+        assert!(marker.exists());
+
+        // --force rebuilds everything from a clean directory.
+        generate(&project, &GenerateOptions { force: true, ..Default::default() }).unwrap();
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_generate_rerenders_after_render_options_change() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        fs::write(root.join("src/pages/index.van"), "<template><h1>Home</h1></template>").unwrap();
+        generate(&VanProject::load(root).unwrap(), &GenerateOptions::default()).unwrap();
+
+        fs::write(
+            root.join("package.json"),
+            r#"{"name": "site", "version": "0.1.0", "van": { "importMap": { "dayjs": "https://esm.sh/dayjs" } }}"#,
+        )
+        .unwrap();
+        generate(&VanProject::load(root).unwrap(), &GenerateOptions::default()).unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(root.join("dist/build-report.json")).unwrap())
+                .unwrap();
+        assert_eq!(report["pages"][0]["cached"], false);
+    }
+
+    #[test]
+    fn test_generate_with_data_block() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages")).unwrap();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        fs::write(
+            root.join("data/index.json"),
+            r#"{"pages/index": {"title": "Mock", "tagline": "From mock"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/index.van"),
+            "<template><h1>{{ title }}</h1><p>{{ tagline }}</p></template>\n\
+             <script lang=\"data\">\n{ \"title\": \"Inline\" }\n</script>",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/remote.van"),
+            "<template><h1>[{{ title }}]</h1></template>\n\
+             <script lang=\"data\">\nhttps://api.example.com/remote.json\n</script>",
+        )
+        .unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        generate(&project, &GenerateOptions::default()).unwrap();
+
+        let index = fs::read_to_string(root.join("dist/index.html")).unwrap();
+        assert!(index.contains("<h1>Inline</h1>"), "block data wins over mock data");
+        assert!(index.contains("<p>From mock</p>"), "other mock keys are kept");
+        assert!(root.join(".van/data-snapshot/pages/index.json").exists());
+
+        // Without --allow-network the URL is skipped and nothing provides `title`.
+        let remote = fs::read_to_string(root.join("dist/remote/index.html")).unwrap();
+        assert!(remote.contains("<h1>[{{title}}]</h1>"), "{remote}");
+        assert!(!root.join(".van/data-snapshot/pages/remote.json").exists());
+    }
+
+    #[test]
+    fn test_iso_date() {
+        assert_eq!(iso_date(UNIX_EPOCH).as_deref(), Some("1970-01-01"));
+        let t = UNIX_EPOCH + std::time::Duration::from_secs(1_709_164_800); // 2024-02-29
+        assert_eq!(iso_date(t).as_deref(), Some("2024-02-29"));
+    }
+
+    #[test]
+    fn test_generate_sitemap_and_canonical() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages")).unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"name": "site", "version": "0.1.0", "van": {"siteUrl": "https://example.com/"}}"#,
+        )
+        .unwrap();
+        fs::write(root.join("src/pages/index.van"), "<template><h1>Home</h1></template>").unwrap();
+        fs::write(
+            root.join("src/pages/draft.van"),
+            "<template><h1>Draft</h1></template>\n<script setup>\ndefineOptions({ sitemap: false })\n</script>",
+        )
+        .unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        assert_eq!(generate(&project, &GenerateOptions::default()).unwrap(), 2);
+
+        let sitemap = fs::read_to_string(root.join("dist/sitemap.xml")).unwrap();
+        assert!(sitemap.contains("<loc>https://example.com/</loc>"));
+        assert!(sitemap.contains("<lastmod>"));
+        assert!(!sitemap.contains("draft"));
+
+        let index = fs::read_to_string(root.join("dist/index.html")).unwrap();
+        assert!(index.contains(r#"<link rel="canonical" href="https://example.com/" />"#));
+        let draft = fs::read_to_string(root.join("dist/draft/index.html")).unwrap();
+        assert!(!draft.contains("canonical"));
+    }
+
+    #[test]
+    fn test_generate_skips_partials() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages/blog")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        fs::write(
+            root.join("src/pages/blog/index.van"),
+            r#"<template><post-card /></template>
+
+<script setup>
+import PostCard from './_post-card.van'
+</script>"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/blog/_post-card.van"),
+            "<template><article>Card</article></template>",
+        )
+        .unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        assert_eq!(generate(&project, &GenerateOptions::default()).unwrap(), 1);
+        let blog = fs::read_to_string(root.join("dist/blog/index.html")).unwrap();
+        assert!(blog.contains("<article>Card</article>"));
+        assert!(!root.join("dist/blog/_post-card").exists());
+        assert!(!root.join("dist/sitemap.xml").exists(), "no siteUrl, no sitemap");
+    }
+
+    #[test]
+    fn test_generate_rejects_conflicting_routes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages/about")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        fs::write(root.join("src/pages/about.van"), "<template><p>A</p></template>").unwrap();
+        fs::write(root.join("src/pages/about/index.van"), "<template><p>B</p></template>").unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        let err = generate(&project, &GenerateOptions::default()).unwrap_err().to_string();
+        assert!(err.contains("pages/about.van, pages/about/index.van"), "{err}");
+        assert!(!root.join("dist").exists());
+    }
+
+    #[test]
+    fn test_generate_with_out_dir_and_base() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages")).unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"name": "site", "version": "0.1.0", "van": {"outDir": "public", "base": "/docs/"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/index.van"),
+            r#"<template>
+  <html>
+  <head><link rel="stylesheet" href="/css/site.css" /></head>
+  <body><a href="/about">About</a></body>
+  </html>
+</template>"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/pages/about.van"),
+            "<template><h1>About</h1></template>",
+        )
+        .unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        assert_eq!(generate(&project, &GenerateOptions::default()).unwrap(), 2);
+        assert!(!root.join("dist").exists());
+        assert!(root.join("public/about/index.html").exists());
+
+        let index = fs::read_to_string(root.join("public/index.html")).unwrap();
+        assert!(index.contains(r#"href="/docs/css/site.css""#));
+        assert!(index.contains(r#"href="/docs/about/""#));
+    }
+
+    #[test]
+    fn test_generate_relative() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/pages/docs")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0"}"#).unwrap();
+        let page = r#"<template><html><head><link rel="stylesheet" href="/css/site.css" /></head><body><a href="/">Home</a></body></html></template>"#;
+        fs::write(root.join("src/pages/index.van"), page).unwrap();
+        fs::write(root.join("src/pages/docs/intro.van"), page).unwrap();
+
+        let project = VanProject::load(root).unwrap();
+        let opts = GenerateOptions { relative: true, ..Default::default() };
+        assert_eq!(generate(&project, &opts).unwrap(), 2);
+        let index = fs::read_to_string(root.join("dist/index.html")).unwrap();
+        assert!(index.contains(r#"href="./css/site.css""#), "{index}");
+        let intro = fs::read_to_string(root.join("dist/docs/intro/index.html")).unwrap();
+        assert!(intro.contains(r#"href="../../css/site.css""#), "{intro}");
+        assert!(intro.contains(r#"href="../../""#), "{intro}");
+
+        fs::write(root.join("package.json"), r#"{"name": "site", "version": "0.1.0", "van": {"base": "/docs/"}}"#).unwrap();
+        let project = VanProject::load(root).unwrap();
+        assert!(generate(&project, &opts).unwrap_err().to_string().contains("--relative"));
+    }
 }
@@ -1,3 +1,5 @@
+pub mod check;
 pub mod dev;
 pub mod generate;
 pub mod init;
+pub mod pack;
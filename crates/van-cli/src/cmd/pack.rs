@@ -0,0 +1,212 @@
+use anyhow::{bail, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use van_context::catalog::{ComponentInfo, ComponentManifest};
+use van_context::project::VanProject;
+
+/// Output directory of `van pack`, relative to the project root.
+const PACK_DIR: &str = "dist-pkg";
+
+pub fn run() -> Result<()> {
+    let project = VanProject::load_cwd()?;
+    let manifest = pack(&project)?;
+    println!("\nPacked {} component(s) into {PACK_DIR}/", manifest.components.len());
+    Ok(())
+}
+
+/// Check the project's components and write them to `dist-pkg/` for publishing.
+///
+/// Every component under `components/` must compile on its own, and every
+/// relative import reachable from one must name a file of the package. The
+/// components, the sources they import and a `components.json`
+/// [`ComponentManifest`] are written out, keeping their paths under the source
+/// directory; when anything is broken nothing is written and the error lists
+/// every problem.
+fn pack(project: &VanProject) -> Result<ComponentManifest> {
+    let (files, notes) = project.collect_files_noted()?;
+    for note in &notes {
+        eprintln!("\x1b[33m  \u{26a0} {note}\x1b[0m");
+    }
+    let entries = project.component_entries(&files);
+    if entries.is_empty() {
+        bail!("No components found in {}", project.src_dir().join("components").display());
+    }
+    let options = van_compiler::CompileOptions {
+        env: project.load_env(),
+        custom_elements: project.custom_elements(),
+        import_map: project.import_map(),
+        strict_coercion: project.strict_coercion(),
+        ..Default::default()
+    };
+
+    let mut problems = Vec::new();
+    for entry in &entries {
+        if let Err(e) = van_compiler::compile_page_assets(entry, &files, "/assets", &options) {
+            problems.push(format!("{entry}: {e}"));
+        }
+    }
+    let sources = package_sources(&entries, &files, &mut problems);
+    if !problems.is_empty() {
+        let list: Vec<String> = problems.iter().map(|p| format!("  - {p}")).collect();
+        bail!("Cannot pack {}: {} problem(s)\n{}", project.config.name, problems.len(), list.join("\n"));
+    }
+
+    let manifest = ComponentManifest {
+        name: project.config.name.clone(),
+        version: project.config.version.clone(),
+        components: entries.iter().map(|entry| ComponentInfo::from_source(entry, &files[entry])).collect(),
+    };
+    let out_dir = project.root.join(PACK_DIR);
+    if out_dir.exists() {
+        fs::remove_dir_all(&out_dir)?;
+    }
+    for path in &sources {
+        let target = out_dir.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, &files[path])?;
+        println!("  {path}");
+    }
+    fs::write(out_dir.join("components.json"), serde_json::to_string_pretty(&manifest)? + "\n")?;
+    Ok(manifest)
+}
+
+/// The package files `entries` need: themselves and every source reachable
+/// through their relative imports, sorted. Relative imports that leave the
+/// source directory or name a missing file, and scoped packages that are not
+/// installed, are added to `problems`.
+fn package_sources(entries: &[String], files: &HashMap<String, String>, problems: &mut Vec<String>) -> BTreeSet<String> {
+    let mut sources = BTreeSet::new();
+    let mut queue: Vec<String> = entries.to_vec();
+    while let Some(file) = queue.pop() {
+        if !sources.insert(file.clone()) {
+            continue;
+        }
+        let source = &files[&file];
+        let imports: Vec<String> = if file.ends_with(".van") {
+            let script = van_parser::parse_blocks(source).script_setup.unwrap_or_default();
+            van_parser::parse_imports(&script)
+                .into_iter()
+                .map(|imp| imp.path)
+                .chain(van_parser::parse_script_imports(&script).into_iter().map(|imp| imp.path))
+                .collect()
+        } else {
+            van_parser::parse_script_imports(source).into_iter().map(|imp| imp.path).collect()
+        };
+        for import in imports {
+            if import.starts_with('@') {
+                if !files.contains_key(&import) {
+                    problems.push(format!("{file}: imports {import}, which is not installed"));
+                }
+                continue;
+            }
+            if !import.starts_with('.') {
+                continue;
+            }
+            match join_relative(&file, &import) {
+                None => problems.push(format!("{file}: import {import} points outside the package")),
+                Some(path) if !files.contains_key(&path) => {
+                    problems.push(format!("{file}: import {import} not found ({path})"));
+                }
+                Some(path) => queue.push(path),
+            }
+        }
+    }
+    sources
+}
+
+/// The source path `import` names relative to `file`, or `None` when it
+/// climbs above the source directory.
+fn join_relative(file: &str, import: &str) -> Option<String> {
+    let mut parts: Vec<&str> = file.split('/').collect();
+    parts.pop();
+    for part in import.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_relative() {
+        assert_eq!(join_relative("components/card.van", "./icon.van").as_deref(), Some("components/icon.van"));
+        assert_eq!(join_relative("components/ui/card.van", "../../utils/format.ts").as_deref(), Some("utils/format.ts"));
+        assert_eq!(join_relative("components/card.van", "../../shared.ts"), None);
+    }
+
+    fn write_package(root: &std::path::Path) {
+        fs::create_dir_all(root.join("src/components")).unwrap();
+        fs::create_dir_all(root.join("src/utils")).unwrap();
+        fs::write(root.join("package.json"), r#"{"name": "@van-ui/kit", "version": "1.2.0"}"#).unwrap();
+        fs::write(
+            root.join("src/components/button.van"),
+            r#"<template><button :class="{ wide: block }"><slot name="icon" /><slot>{{ label }}</slot></button></template>
+<script setup>
+import { shout } from '../utils/format.ts'
+defineProps({ label: { type: String, required: true }, block: { type: Boolean, default: false } })
+</script>"#,
+        )
+        .unwrap();
+        fs::write(root.join("src/utils/format.ts"), "export const shout = (s: string) => s.toUpperCase()\n").unwrap();
+        fs::write(root.join("src/utils/unused.ts"), "export const x = 1\n").unwrap();
+        fs::write(
+            root.join("src/components/broken.van"),
+            r#"<template><missing-icon /><p>{{ text }}</p></template>
+<script setup>
+import MissingIcon from './missing-icon.van'
+import { theme } from '../../shared/theme.ts'
+</script>"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_pack_reports_broken_components() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_package(root);
+
+        let err = pack(&VanProject::load(root).unwrap()).unwrap_err().to_string();
+        assert!(err.starts_with("Cannot pack @van-ui/kit: 3 problem(s)"), "{err}");
+        assert!(err.contains("components/broken.van: Component not found: components/missing-icon.van"), "{err}");
+        assert!(err.contains("components/broken.van: import ./missing-icon.van not found (components/missing-icon.van)"), "{err}");
+        assert!(err.contains("components/broken.van: import ../../shared/theme.ts points outside the package"), "{err}");
+        assert!(!err.contains("button.van"), "{err}");
+        assert!(!root.join(PACK_DIR).exists());
+    }
+
+    #[test]
+    fn test_pack_writes_sources_and_manifest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write_package(root);
+        fs::remove_file(root.join("src/components/broken.van")).unwrap();
+
+        let manifest = pack(&VanProject::load(root).unwrap()).unwrap();
+        let out = root.join(PACK_DIR);
+        assert!(out.join("components/button.van").exists());
+        assert!(out.join("utils/format.ts").exists());
+        assert!(!out.join("utils/unused.ts").exists());
+
+        let written: ComponentManifest =
+            serde_json::from_str(&fs::read_to_string(out.join("components.json")).unwrap()).unwrap();
+        assert_eq!(written, manifest);
+        assert_eq!((written.name.as_str(), written.version.as_str()), ("@van-ui/kit", "1.2.0"));
+        let button = &written.components[0];
+        assert_eq!((button.tag.as_str(), button.file.as_str()), ("button", "components/button.van"));
+        assert_eq!(button.slots, vec!["icon", "default"]);
+        let props: Vec<(&str, bool, Option<&str>)> =
+            button.props.iter().map(|p| (p.name.as_str(), p.required, p.default.as_deref())).collect();
+        assert_eq!(props, vec![("label", true, None), ("block", false, Some("false"))]);
+    }
+}
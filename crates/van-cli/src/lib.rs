@@ -1,6 +1,10 @@
+mod cache;
 mod cmd;
+mod page_data;
+mod report;
 
 use clap::{Parser, Subcommand};
+use van_context::config::OutputStyle;
 
 #[derive(Parser)]
 #[command(name = "van", version, about = "Van - Vue-like template engine toolchain")]
@@ -17,9 +21,40 @@ enum Commands {
         name: Option<String>,
     },
     /// Start development server
-    Dev,
+    Dev {
+        /// Port to listen on (defaults to `van.devPort` in package.json, then 3000)
+        #[arg(long, short)]
+        port: Option<u16>,
+    },
     /// Generate static HTML pages
-    Generate,
+    Generate {
+        /// Output layout: `nested` (about/index.html) or `flat` (about.html).
+        /// Defaults to `van.outputStyle` in package.json, then `nested`
+        #[arg(long)]
+        output_style: Option<OutputStyle>,
+        /// Ignore the build cache and re-render every page
+        #[arg(long)]
+        force: bool,
+        /// Fetch URLs declared in `<script lang="data">` blocks
+        #[arg(long)]
+        allow_network: bool,
+        /// Also render these locales under `dist/<code>/`: `all` or e.g. `de,fr`
+        #[arg(long)]
+        locales: Option<String>,
+        /// Print per-page compile timings (parse, resolve, signals, render)
+        #[arg(long)]
+        timings: bool,
+        /// Link pages and assets relative to each page, for opening the output
+        /// from disk or hosting it under any subpath (not with `van.base`)
+        #[arg(long)]
+        relative: bool,
+    },
+    /// Render every page without writing it and report mock data keys no
+    /// template reads and data paths the templates read that the data lacks
+    Check,
+    /// Check the components under `src/components/` and assemble them, with
+    /// the sources they import and a `components.json` manifest, in `dist-pkg/`
+    Pack,
 }
 
 pub async fn run() {
@@ -27,8 +62,17 @@ pub async fn run() {
 
     let result = match cli.command {
         Commands::Init { name } => cmd::init::run(name),
-        Commands::Dev => cmd::dev::run().await,
-        Commands::Generate => cmd::generate::run(),
+        Commands::Dev { port } => cmd::dev::run(port).await,
+        Commands::Generate {
+            output_style,
+            force,
+            allow_network,
+            locales,
+            timings,
+            relative,
+        } => cmd::generate::run(output_style, force, allow_network, locales, timings, relative),
+        Commands::Check => cmd::check::run(),
+        Commands::Pack => cmd::pack::run(),
     };
 
     if let Err(e) = result {
@@ -0,0 +1,223 @@
+//! Build-time page data declared in a page's `<script lang="data">` block.
+//!
+//! The block holds either inline JSON (comments and trailing commas allowed) or a
+//! single `http(s)://` URL whose JSON response becomes the page data. Whatever is
+//! resolved is written to `.van/data-snapshot/`, which is used in place of the
+//! network when fetching is not allowed or fails, so builds stay reproducible offline.
+
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Snapshot location, relative to the project root.
+pub const SNAPSHOT_DIR: &str = ".van/data-snapshot";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, PartialEq)]
+pub enum DataSource {
+    Inline(Value),
+    Url(String),
+}
+
+/// Interpret the content of a `<script lang="data">` block.
+pub fn parse_source(block: &str) -> Result<DataSource, String> {
+    let block = block.trim();
+    if block.starts_with("http://") || block.starts_with("https://") {
+        return Ok(DataSource::Url(block.to_string()));
+    }
+    van_context::lenient::parse(block)
+        .map(DataSource::Inline)
+        .map_err(|e| format!("invalid JSON at {}", van_context::lenient::describe(&e)))
+}
+
+/// Resolve the data block of `entry`, warning and falling back instead of failing.
+///
+/// Order of fallbacks for a URL: the live response (only with `allow_network`),
+/// then the last snapshot, then `{}`.
+pub fn resolve(root: &Path, entry: &str, block: &str, allow_network: bool) -> Value {
+    let snapshot = snapshot_path(root, entry);
+    let resolved = match parse_source(block) {
+        Ok(DataSource::Inline(value)) => Ok(value),
+        Ok(DataSource::Url(url)) if allow_network => fetch_json(&url).map_err(|e| format!("failed to fetch {url}: {e}")),
+        Ok(DataSource::Url(url)) => Err(format!("{url} not fetched (pass --allow-network)")),
+        Err(e) => Err(e),
+    };
+    match resolved {
+        Ok(value) => {
+            if let Err(e) = write_snapshot(&snapshot, &value) {
+                warn(&format!("{entry}: could not write data snapshot: {e}"));
+            }
+            value
+        }
+        Err(e) => match read_snapshot(&snapshot) {
+            Some(value) => {
+                warn(&format!("{entry}: {e}; using snapshot {}", display(root, &snapshot)));
+                value
+            }
+            None => {
+                warn(&format!("{entry}: {e}; rendering with empty data"));
+                Value::Object(Default::default())
+            }
+        },
+    }
+}
+
+/// Overlay the resolved block data onto the mock data for the page.
+///
+/// Both are shallow-merged when they are objects, with the block's keys winning:
+/// it is the page's own declaration, while `data/index.json` is shared mock data.
+pub fn merge(mock: Value, block: Value) -> Value {
+    match (mock, block) {
+        (Value::Object(mut mock), Value::Object(block)) => {
+            mock.extend(block);
+            Value::Object(mock)
+        }
+        (_, block) => block,
+    }
+}
+
+/// `pages/blog/v1.2.van` → `.van/data-snapshot/pages/blog/v1.2.json`.
+fn snapshot_path(root: &Path, entry: &str) -> PathBuf {
+    root.join(SNAPSHOT_DIR)
+        .join(format!("{}.json", entry.strip_suffix(".van").unwrap_or(entry)))
+}
+
+fn write_snapshot(path: &Path, value: &Value) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)? + "\n")?;
+    Ok(())
+}
+
+fn read_snapshot(path: &Path) -> Option<Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// GET a URL and parse the body as JSON.
+///
+/// Runs on its own thread: the blocking client must not run inside the CLI's
+/// async runtime.
+fn fetch_json(url: &str) -> Result<Value, String> {
+    let url = url.to_string();
+    std::thread::spawn(move || {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let body = client
+            .get(&url)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|e| e.to_string())?;
+        serde_json::from_str(&body).map_err(|e| format!("invalid JSON response: {e}"))
+    })
+    .join()
+    .map_err(|_| "fetch thread panicked".to_string())?
+}
+
+fn warn(message: &str) {
+    eprintln!("\x1b[33m  \u{26a0} {message}\x1b[0m");
+}
+
+fn display(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            parse_source("  https://api.example.com/posts \n"),
+            Ok(DataSource::Url("https://api.example.com/posts".into()))
+        );
+        assert_eq!(
+            parse_source("{ \"title\": \"Hi\", // build-time\n }"),
+            Ok(DataSource::Inline(json!({ "title": "Hi" })))
+        );
+        assert!(parse_source("{ nope").unwrap_err().contains("line 1"));
+    }
+
+    #[test]
+    fn test_resolve_inline_writes_snapshot() {
+        let tmp = tempfile::tempdir().unwrap();
+        let value = resolve(tmp.path(), "pages/blog/index.van", r#"{"posts": [1, 2]}"#, false);
+        assert_eq!(value, json!({ "posts": [1, 2] }));
+        let snapshot = tmp.path().join(".van/data-snapshot/pages/blog/index.json");
+        assert_eq!(read_snapshot(&snapshot), Some(value));
+    }
+
+    #[test]
+    fn test_resolve_url_without_network_falls_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let url = "https://api.example.com/posts";
+        assert_eq!(resolve(tmp.path(), "pages/index.van", url, false), json!({}));
+
+        // A snapshot from an earlier run is preferred over empty data.
+        let snapshot = snapshot_path(tmp.path(), "pages/index.van");
+        write_snapshot(&snapshot, &json!({ "posts": ["cached"] })).unwrap();
+        assert_eq!(
+            resolve(tmp.path(), "pages/index.van", url, false),
+            json!({ "posts": ["cached"] })
+        );
+    }
+
+    #[test]
+    fn test_snapshot_path_keeps_dotted_names() {
+        let root = Path::new("/site");
+        assert_eq!(snapshot_path(root, "pages/v1.2.van"), root.join(".van/data-snapshot/pages/v1.2.json"));
+        assert_eq!(snapshot_path(root, "pages/index.van"), root.join(".van/data-snapshot/pages/index.json"));
+    }
+
+    /// Serve `responses` (raw HTTP) to one connection each on a local port,
+    /// returning the URL to fetch.
+    fn serve(responses: &[&'static str]) -> String {
+        use std::io::{BufRead, BufReader, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let responses = responses.to_vec();
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{addr}/data.json")
+    }
+
+    #[test]
+    fn test_resolve_failed_fetch_falls_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let url = serve(&[
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 15\r\nConnection: close\r\n\r\n{\"posts\": [1]}\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nnope\n",
+        ]);
+        assert_eq!(resolve(tmp.path(), "pages/index.van", &url, true), json!({}));
+
+        // A good response is snapshotted, and used when a later one is bad.
+        assert_eq!(resolve(tmp.path(), "pages/index.van", &url, true), json!({ "posts": [1] }));
+        assert_eq!(resolve(tmp.path(), "pages/index.van", &url, true), json!({ "posts": [1] }));
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(
+            merge(json!({ "title": "Mock", "nav": [] }), json!({ "title": "Live" })),
+            json!({ "title": "Live", "nav": [] })
+        );
+        assert_eq!(merge(json!({ "a": 1 }), json!([1])), json!([1]));
+    }
+}
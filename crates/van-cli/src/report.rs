@@ -0,0 +1,187 @@
+//! Machine-readable build statistics, written next to the generated pages.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// File name of the report inside the output directory.
+pub const REPORT_FILE: &str = "build-report.json";
+
+/// Per-page statistics collected while generating.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageStats {
+    pub entry: String,
+    /// Output file, relative to the output directory.
+    pub output: String,
+    pub duration_ms: f64,
+    pub html_bytes: usize,
+    /// Bytes of inline `<style>` content.
+    pub css_bytes: usize,
+    /// Bytes of inline `<script>` content.
+    pub js_bytes: usize,
+    /// Separate asset files emitted for the page.
+    pub asset_count: usize,
+    /// Whether the page was reused from the build cache instead of rendered.
+    pub cached: bool,
+    /// Compiler phase timings, for pages that were rendered.
+    #[serde(skip)]
+    pub phases: Option<van_compiler::Timings>,
+}
+
+impl PageStats {
+    pub fn new(entry: &str, output: &str, duration: Duration, html: &str) -> Self {
+        Self {
+            entry: entry.to_string(),
+            output: output.to_string(),
+            duration_ms: millis(duration),
+            html_bytes: html.len(),
+            css_bytes: inline_bytes(html, "style"),
+            js_bytes: inline_bytes(html, "script"),
+            asset_count: 0,
+            cached: false,
+            phases: None,
+        }
+    }
+}
+
+/// The whole report: toolchain version, wall time and every page.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildReport {
+    pub version: &'static str,
+    pub total_ms: f64,
+    pub pages: Vec<PageStats>,
+}
+
+impl BuildReport {
+    pub fn new(total: Duration, pages: Vec<PageStats>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            total_ms: millis(total),
+            pages,
+        }
+    }
+
+    /// Write the report as pretty JSON into `out_dir`.
+    pub fn write(&self, out_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(out_dir.join(REPORT_FILE), json + "\n")?;
+        Ok(())
+    }
+
+    /// Print a summary table of every page plus totals.
+    pub fn print_summary(&self) {
+        let width = self
+            .pages
+            .iter()
+            .map(|p| p.entry.len())
+            .max()
+            .unwrap_or(0)
+            .max("Page".len());
+        println!();
+        println!(
+            "  {:<width$}  {:>9}  {:>9}  {:>9}  {:>9}",
+            "Page", "Time", "HTML", "CSS", "JS"
+        );
+        for page in &self.pages {
+            println!(
+                "  {:<width$}  {:>9}  {:>9}  {:>9}  {:>9}",
+                page.entry,
+                format!("{:.1}ms", page.duration_ms),
+                format_size(page.html_bytes),
+                format_size(page.css_bytes),
+                format_size(page.js_bytes),
+            );
+        }
+        let html: usize = self.pages.iter().map(|p| p.html_bytes).sum();
+        println!(
+            "  {:<width$}  {:>9}  {:>9}",
+            "Total",
+            format!("{:.1}ms", self.total_ms),
+            format_size(html),
+        );
+    }
+}
+
+impl BuildReport {
+    /// Print where compile time went for every rendered page (`--timings`).
+    pub fn print_timings(&self) {
+        let rendered: Vec<(&str, &van_compiler::Timings)> = self
+            .pages
+            .iter()
+            .filter_map(|p| p.phases.as_ref().map(|t| (p.entry.as_str(), t)))
+            .collect();
+        if rendered.is_empty() {
+            return;
+        }
+        let width = rendered.iter().map(|(entry, _)| entry.len()).max().unwrap_or(0).max("Page".len());
+        println!();
+        println!(
+            "  {:<width$}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+            "Page", "Parse", "Resolve", "Signals", "Render", "Total"
+        );
+        for (entry, t) in rendered {
+            let ms = [t.parse_ms, t.resolve_ms, t.signal_gen_ms, t.render_ms, t.total_ms]
+                .map(|ms| format!("{ms:.1}ms"));
+            println!(
+                "  {:<width$}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+                entry, ms[0], ms[1], ms[2], ms[3], ms[4]
+            );
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 10_000.0).round() / 10.0
+}
+
+/// Sum the content lengths of every `<tag ...>...</tag>` element in `html`.
+fn inline_bytes(html: &str, tag: &str) -> usize {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut total = 0;
+    let mut rest = html;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start..];
+        let Some(content_start) = after.find('>') else {
+            break;
+        };
+        let body = &after[content_start + 1..];
+        let Some(end) = body.find(&close) else {
+            break;
+        };
+        total += end;
+        rest = &body[end + close.len()..];
+    }
+    total
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_bytes() {
+        let html = "<style>a{}</style><p>x</p><script src=\"/x.js\"></script><script>var a;</script>";
+        assert_eq!(inline_bytes(html, "style"), 3);
+        assert_eq!(inline_bytes(html, "script"), 6);
+        assert_eq!(inline_bytes("<p>none</p>", "style"), 0);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KB");
+    }
+}
@@ -1,96 +1,467 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, BufRead, Read, Write};
+use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
 
+/// Version of the JSON protocol, sent as `protocol` on every response.
+const PROTOCOL: u32 = 1;
+
+/// Optional request features this binary understands, reported by `hello`.
+const CAPABILITIES: &[&str] = &[
+    "render",
+    "assets",
+    "debug",
+    "origin_markers",
+    "html_format",
+    "runtime_url",
+    "sanitize_html",
+    "emit_teardown",
+    "limits",
+    "diagnostics",
+    "global_css",
+    "custom_elements",
+    "relative_assets",
+    "strict_coercion",
+    "strict_js",
+    "hydration",
+    "max_js_chunk_bytes",
+    "transition_css",
+    "element_names",
+    "import_map",
+    "warmup",
+];
+
+/// Per-request resource limits, set with `--max-request-bytes`, `--max-input-bytes`,
+/// `--max-file-bytes`, `--max-files` and `--max-output-bytes`.
+#[derive(Debug, Clone, PartialEq)]
+struct Limits {
+    /// Size of the raw JSON request, checked before it is parsed.
+    max_request_bytes: usize,
+    /// Total size of all file contents.
+    max_input_bytes: usize,
+    /// Size of any single file.
+    max_file_bytes: usize,
+    /// Number of entries in `files`.
+    max_files: usize,
+    /// Size of the HTML plus all emitted assets.
+    max_output_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_request_bytes: 128 << 20,
+            max_input_bytes: 64 << 20,
+            max_file_bytes: 8 << 20,
+            max_files: 10_000,
+            max_output_bytes: 64 << 20,
+        }
+    }
+}
+
+impl Limits {
+    /// Read `--max-*` flags (`--max-files 100` or `--max-files=100`).
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut limits = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            let slot = match flag {
+                "--max-request-bytes" => &mut limits.max_request_bytes,
+                "--max-input-bytes" => &mut limits.max_input_bytes,
+                "--max-file-bytes" => &mut limits.max_file_bytes,
+                "--max-files" => &mut limits.max_files,
+                "--max-output-bytes" => &mut limits.max_output_bytes,
+                _ => continue,
+            };
+            let value = inline.or_else(|| iter.next().cloned()).ok_or_else(|| format!("{flag} needs a value"))?;
+            *slot = value.parse().map_err(|_| format!("{flag}: invalid number \"{value}\""))?;
+        }
+        Ok(limits)
+    }
+
+    /// Reject a files map over the input limits before compiling anything,
+    /// returning the limit hit and a detail message.
+    fn check_input(&self, files: &HashMap<String, String>) -> Result<(), (&'static str, String)> {
+        if files.len() > self.max_files {
+            return Err(("max_files", format!("{} files, limit {}", files.len(), self.max_files)));
+        }
+        if let Some((path, content)) = files.iter().find(|(_, c)| c.len() > self.max_file_bytes) {
+            return Err((
+                "max_file_bytes",
+                format!("{path} is {} bytes, limit {}", content.len(), self.max_file_bytes),
+            ));
+        }
+        let total: usize = files.values().map(String::len).sum();
+        if total > self.max_input_bytes {
+            return Err(("max_input_bytes", format!("{total} bytes, limit {}", self.max_input_bytes)));
+        }
+        Ok(())
+    }
+
+    fn request_too_large(&self, len: usize) -> String {
+        to_json(&limit_error("max_request_bytes", format!("{len} bytes, limit {}", self.max_request_bytes)))
+    }
+
+    fn check_output(&self, resp: CompileResponse) -> CompileResponse {
+        let total = resp.output_bytes();
+        if total > self.max_output_bytes {
+            return limit_error("max_output_bytes", format!("{total} bytes, limit {}", self.max_output_bytes));
+        }
+        resp
+    }
+}
+
 #[derive(Deserialize)]
 struct CompileRequest {
     entry_path: String,
     files: HashMap<String, String>,
     data_json: Option<String>,
+    /// Emit CSS/JS as assets under this URL prefix (normalized to `/assets` form).
     #[serde(default)]
     asset_prefix: Option<String>,
     #[serde(default)]
     debug: bool,
-    /// Maps file paths to theme names (e.g. "components/header.van" → "van1").
+    /// Maps file paths to theme names (e.g. "components/header.van" → "van1"),
+    /// which also keeps each theme's scope ids and stylesheets apart.
     #[serde(default)]
     file_origins: HashMap<String, String>,
     /// Custom global name for the signal runtime (default: "Van").
     #[serde(default)]
     global_name: Option<String>,
+    /// Build-time environment variables (`env.VAN_*` / `import.meta.env.*`).
+    #[serde(default)]
+    env: HashMap<String, String>,
+    /// `preserve` (default), `pretty` or `minify`; applies when rendering with data.
+    #[serde(default)]
+    html_format: Option<String>,
+    /// With `debug`: `comments` (default) or `attributes` (`data-van-origin`).
+    #[serde(default)]
+    origin_markers: Option<String>,
+    /// Load the signal runtime from this URL instead of bundling it.
+    #[serde(default)]
+    runtime_url: Option<String>,
+    /// With `runtime_url`: `iife` (default) or `esm`.
+    #[serde(default)]
+    runtime_format: Option<String>,
+    /// `eager` (default) or `lazy`: wire each top-level element's bindings
+    /// once it scrolls into view or is interacted with.
+    #[serde(default)]
+    hydration: Option<String>,
+    /// With `asset_prefix`: split the page script into assets of about this
+    /// many bytes each.
+    #[serde(default)]
+    max_js_chunk_bytes: Option<usize>,
+    /// Generate a fade for `<Transition>` names no style defines instead of
+    /// warning about them.
+    #[serde(default)]
+    transition_css: bool,
+    /// `path` (default) or `ordinal`: how the signal script finds bound
+    /// elements, `ordinal` matching scripts compiled before paths.
+    #[serde(default)]
+    element_names: Option<String>,
+    /// Sanitize `{{{ }}}` and `v-html` output instead of warning about it.
+    #[serde(default)]
+    sanitize_html: bool,
+    /// Register `window.__van_pages[page].destroy()` in the signal script.
+    #[serde(default)]
+    emit_teardown: bool,
+    /// Wrap the entry's template in this layout unless it uses one already.
+    #[serde(default)]
+    auto_layout: Option<String>,
+    /// Stylesheets in `files` emitted before every component style.
+    #[serde(default)]
+    global_css: Vec<String>,
+    /// Web component tags (or `prefix-*` patterns) not to report as unknown.
+    #[serde(default)]
+    custom_elements: Vec<String>,
+    /// Bare import specifiers mapped to a URL (emitted as an import map) or
+    /// to a path in `files` (inlined).
+    #[serde(default)]
+    import_map: BTreeMap<String, String>,
+    /// Link assets relative to the page's output location.
+    #[serde(default)]
+    relative_assets: bool,
+    /// Warn when a `v-if` / `v-show` condition relies on type coercion.
+    #[serde(default)]
+    strict_coercion: bool,
+    /// Fail on `eval()`, `document.write` and other denied script constructs
+    /// instead of warning about them.
+    #[serde(default)]
+    strict_js: bool,
+    /// Include per-phase timings and sizes as `diagnostics` in the response.
+    #[serde(default)]
+    diagnostics: bool,
+}
+
+/// Where the time went for one request, sent when `diagnostics` is set.
+#[derive(Serialize)]
+struct Diagnostics {
+    parse_ms: f64,
+    resolve_ms: f64,
+    signal_gen_ms: f64,
+    render_ms: f64,
+    total_ms: f64,
+    files_count: usize,
+    output_bytes: usize,
+}
+
+/// Answer to `{"type":"hello"}`: what this binary supports.
+#[derive(Serialize)]
+struct HelloResponse {
+    ok: bool,
+    protocol: u32,
+    version: &'static str,
+    capabilities: &'static [&'static str],
+}
+
+/// Answer to `{"type":"warmup"}`: how many lazies were compiled, and how long
+/// it took.
+#[derive(Serialize)]
+struct WarmupResponse {
+    ok: bool,
+    protocol: u32,
+    warmed: usize,
+    warmup_ms: f64,
 }
 
 #[derive(Serialize)]
 struct CompileResponse {
     ok: bool,
+    protocol: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     html: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    assets: Option<HashMap<String, String>>,
+    assets: Option<BTreeMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    /// Machine-readable failure kind for protocol errors (`invalid_request`,
+    /// `unknown_type`, `limit_exceeded`); compile errors carry only `error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    /// With `limit_exceeded`: which limit, e.g. `max_file_bytes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<Diagnostics>,
+}
+
+impl CompileResponse {
+    /// Size of the HTML plus all assets.
+    fn output_bytes(&self) -> usize {
+        let assets: usize = self.assets.iter().flat_map(|a| a.values()).map(String::len).sum();
+        self.html.as_ref().map_or(0, String::len) + assets
+    }
+}
+
+/// Compile within `limits`. Component nesting deeper than the resolver allows
+/// is reported as the `max_depth` limit.
+fn compile(mut req: CompileRequest, limits: &Limits) -> CompileResponse {
+    if let Err((limit, detail)) = limits.check_input(&req.files) {
+        return limit_error(limit, detail);
+    }
+    // Files saved on Windows may start with a BOM and end lines with `\r\n`
+    for content in req.files.values_mut() {
+        *content = van_compiler::normalize_source(content);
+    }
+    let resp = compile_unchecked(req);
+    match resp.error {
+        Some(_) => resp,
+        None => limits.check_output(resp),
+    }
 }
 
-fn compile(req: CompileRequest) -> CompileResponse {
-    let global_name = req.global_name.as_deref().unwrap_or("Van");
+fn compile_unchecked(req: CompileRequest) -> CompileResponse {
+    let html_format = match req.html_format.as_deref().map(str::parse).transpose() {
+        Ok(format) => format.unwrap_or_default(),
+        Err(e) => return error_response(e),
+    };
+    let origin_markers = match req.origin_markers.as_deref().map(str::parse).transpose() {
+        Ok(markers) => markers.unwrap_or_default(),
+        Err(e) => return error_response(e),
+    };
+    let runtime = match req.runtime_url {
+        Some(url) => match req.runtime_format.as_deref().map(str::parse).transpose() {
+            Ok(format) => van_compiler::RuntimeSource::External { url, format: format.unwrap_or_default() },
+            Err(e) => return error_response(e),
+        },
+        None => van_compiler::RuntimeSource::Bundled,
+    };
+    let hydration = match req.hydration.as_deref().map(str::parse).transpose() {
+        Ok(hydration) => hydration.unwrap_or_default(),
+        Err(e) => return error_response(e),
+    };
+    let element_names = match req.element_names.as_deref().map(str::parse).transpose() {
+        Ok(names) => names.unwrap_or_default(),
+        Err(e) => return error_response(e),
+    };
+    let asset_prefix = match req.asset_prefix.as_deref().map(van_compiler::normalize_asset_prefix).transpose() {
+        Ok(prefix) => prefix,
+        Err(e) => return error_response(e),
+    };
+    let options = van_compiler::CompileOptions {
+        debug: req.debug,
+        origin_markers,
+        file_origins: req.file_origins,
+        global_name: req.global_name.unwrap_or_else(|| "Van".to_string()),
+        env: req.env,
+        html_format,
+        runtime,
+        sanitize_html: req.sanitize_html,
+        emit_teardown: req.emit_teardown,
+        auto_layout: req.auto_layout,
+        global_css: req.global_css,
+        custom_elements: req.custom_elements,
+        import_map: req.import_map,
+        relative_assets: req.relative_assets,
+        strict_coercion: req.strict_coercion,
+        strict_js: req.strict_js,
+        hydration,
+        max_js_chunk_bytes: req.max_js_chunk_bytes,
+        transition_css: req.transition_css,
+        element_names,
+    };
 
-    if let Some(ref prefix) = req.asset_prefix {
+    let result = if let Some(ref prefix) = asset_prefix {
         let result = if let Some(ref data_json) = req.data_json {
-            van_compiler::render_to_assets_full(
-                &req.entry_path, &req.files, data_json, prefix,
-                req.debug, &req.file_origins, global_name,
-            )
+            van_compiler::render_page_assets(&req.entry_path, &req.files, data_json, prefix, &options)
         } else {
-            van_compiler::compile_assets_full(
-                &req.entry_path, &req.files, prefix,
-                req.debug, &req.file_origins, global_name,
-            )
+            van_compiler::compile_page_assets(&req.entry_path, &req.files, prefix, &options)
         };
-        match result {
-            Ok(result) => CompileResponse {
+        result.map(|result| {
+            let resp = CompileResponse {
                 ok: true,
+                protocol: PROTOCOL,
                 html: Some(result.html),
                 assets: Some(result.assets),
                 error: None,
-            },
-            Err(e) => CompileResponse {
-                ok: false,
-                html: None,
-                assets: None,
-                error: Some(e),
-            },
-        }
+                code: None,
+                limit: None,
+                warnings: result.warnings,
+                diagnostics: None,
+            };
+            (resp, result.timings)
+        })
     } else {
         let result = if let Some(ref data_json) = req.data_json {
-            van_compiler::render_to_string_full(
-                &req.entry_path, &req.files, data_json,
-                req.debug, &req.file_origins, global_name,
-            )
+            van_compiler::render_page(&req.entry_path, &req.files, data_json, &options)
         } else {
-            van_compiler::compile_full(
-                &req.entry_path, &req.files,
-                req.debug, &req.file_origins, global_name,
-            )
+            van_compiler::compile_page(&req.entry_path, &req.files, &options)
         };
-        match result {
-            Ok(html) => CompileResponse {
+        result.map(|page| {
+            let resp = CompileResponse {
                 ok: true,
-                html: Some(html),
+                protocol: PROTOCOL,
+                html: Some(page.html),
                 assets: None,
                 error: None,
-            },
-            Err(e) => CompileResponse {
-                ok: false,
-                html: None,
-                assets: None,
-                error: Some(e),
-            },
+                code: None,
+                limit: None,
+                warnings: page.warnings,
+                diagnostics: None,
+            };
+            (resp, page.timings)
+        })
+    };
+
+    match result {
+        Ok((mut resp, timings)) => {
+            if req.diagnostics {
+                resp.diagnostics = Some(Diagnostics {
+                    parse_ms: timings.parse_ms,
+                    resolve_ms: timings.resolve_ms,
+                    signal_gen_ms: timings.signal_gen_ms,
+                    render_ms: timings.render_ms,
+                    total_ms: timings.total_ms,
+                    files_count: req.files.len(),
+                    output_bytes: resp.output_bytes(),
+                });
+            }
+            resp
         }
+        Err(van_compiler::PageError::MaxDepth(e)) => limit_error("max_depth", e),
+        Err(e) => error_response(e.to_string()),
+    }
+}
+
+fn error_response(error: String) -> CompileResponse {
+    CompileResponse {
+        ok: false,
+        protocol: PROTOCOL,
+        html: None,
+        assets: None,
+        error: Some(error),
+        code: None,
+        limit: None,
+        warnings: Vec::new(),
+        diagnostics: None,
+    }
+}
+
+fn limit_error(limit: &'static str, detail: String) -> CompileResponse {
+    CompileResponse {
+        limit: Some(limit),
+        ..protocol_error("limit_exceeded", format!("limit exceeded: {limit} ({detail})"))
+    }
+}
+
+fn protocol_error(code: &'static str, error: String) -> CompileResponse {
+    CompileResponse { code: Some(code), ..error_response(error) }
+}
+
+/// Compile the compiler's lazy regexes ahead of the first page.
+fn warmup() -> WarmupResponse {
+    let started = Instant::now();
+    let warmed = van_compiler::warmup();
+    WarmupResponse {
+        ok: true,
+        protocol: PROTOCOL,
+        warmed,
+        warmup_ms: started.elapsed().as_secs_f64() * 1000.0,
+    }
+}
+
+/// Answer one request. Requests without a `type` (or `"compile"`) compile a
+/// page; `"hello"` reports the version and capabilities, and `"warmup"`
+/// prepares for the first compile.
+fn handle(input: &str, limits: &Limits) -> String {
+    if input.len() > limits.max_request_bytes {
+        return limits.request_too_large(input.len());
+    }
+    let request: serde_json::Value = match serde_json::from_str(input) {
+        Ok(value) => value,
+        Err(e) => return to_json(&protocol_error("invalid_request", e.to_string())),
+    };
+    match request.get("type").map(|t| t.as_str()) {
+        None | Some(Some("compile")) => match serde_json::from_value::<CompileRequest>(request) {
+            Ok(req) => to_json(&compile(req, limits)),
+            Err(e) => to_json(&protocol_error("invalid_request", e.to_string())),
+        },
+        Some(Some("hello")) => to_json(&HelloResponse {
+            ok: true,
+            protocol: PROTOCOL,
+            version: env!("CARGO_PKG_VERSION"),
+            capabilities: CAPABILITIES,
+        }),
+        Some(Some("warmup")) => to_json(&warmup()),
+        Some(_) => to_json(&protocol_error(
+            "unknown_type",
+            format!("unknown request type {}", request["type"]),
+        )),
     }
 }
 
-fn write_response(resp: &CompileResponse) {
-    let out = serde_json::to_string(resp).unwrap();
+fn to_json(resp: &impl Serialize) -> String {
+    serde_json::to_string(resp).unwrap()
+}
+
+fn write_response(out: &str) {
     let stdout = io::stdout();
     let mut handle = stdout.lock();
     handle.write_all(out.as_bytes()).unwrap();
@@ -99,46 +470,317 @@ fn write_response(resp: &CompileResponse) {
 }
 
 fn main() {
-    let daemon = std::env::args().any(|a| a == "--daemon");
+    let started = Instant::now();
+    if std::env::args().any(|a| a == "--version") {
+        println!("van-compiler-wasi {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let daemon = args.iter().any(|a| a == "--daemon");
+    let limits = match Limits::from_args(&args) {
+        Ok(limits) => limits,
+        Err(e) => {
+            eprintln!("van-compiler-wasi: {e}");
+            std::process::exit(2);
+        }
+    };
+    let warmed = args.iter().any(|a| a == "--warmup").then(warmup);
+    if std::env::var("VAN_WASI_DEBUG").is_ok_and(|v| v == "1") {
+        let warmed = warmed.map_or_else(String::new, |w| format!(", {} lazies warmed in {:.2}ms", w.warmed, w.warmup_ms));
+        eprintln!("van-compiler-wasi: ready in {:.2}ms{warmed}", started.elapsed().as_secs_f64() * 1000.0);
+    }
 
     if daemon {
         // Daemon mode: read one JSON object per line (JSON Lines), compile, respond.
         // Exits when stdin reaches EOF.
-        let stdin = io::stdin();
-        for line in stdin.lock().lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(_) => break,
-            };
-            let line = line.trim().to_string();
-            if line.is_empty() {
-                continue;
+        let mut stdin = io::stdin().lock();
+        while let Ok(Some(line)) = read_line_capped(&mut stdin, limits.max_request_bytes) {
+            match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => write_response(&handle(line.trim(), &limits)),
+                Err(len) => write_response(&limits.request_too_large(len)),
             }
-            let resp = match serde_json::from_str::<CompileRequest>(&line) {
-                Ok(req) => compile(req),
-                Err(e) => CompileResponse {
-                    ok: false,
-                    html: None,
-                    assets: None,
-                    error: Some(e.to_string()),
-                },
-            };
-            write_response(&resp);
         }
     } else {
-        // Single-shot mode: read all of stdin, compile once, write response.
-        let mut input = String::new();
-        io::stdin().read_to_string(&mut input).unwrap();
-
-        let resp = match serde_json::from_str::<CompileRequest>(&input) {
-            Ok(req) => compile(req),
-            Err(e) => CompileResponse {
-                ok: false,
-                html: None,
-                assets: None,
-                error: Some(e.to_string()),
-            },
+        // Single-shot mode: read stdin up to the request limit, compile once, write response.
+        let mut stdin = io::stdin().lock();
+        let mut input = Vec::new();
+        (&mut stdin).take(limits.max_request_bytes as u64 + 1).read_to_end(&mut input).unwrap();
+        if input.len() > limits.max_request_bytes {
+            let rest = io::copy(&mut stdin, &mut io::sink()).unwrap_or(0) as usize;
+            write_response(&limits.request_too_large(input.len() + rest));
+            return;
+        }
+        write_response(&handle(&String::from_utf8_lossy(&input), &limits));
+    }
+}
+
+/// Read one line of at most `cap` bytes, without its newline. `None` at end of
+/// input; a longer line is skipped over and reported as `Err` with its length.
+fn read_line_capped(reader: &mut impl BufRead, cap: usize) -> io::Result<Option<Result<String, usize>>> {
+    let mut line = Vec::new();
+    let mut len = 0;
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            if len == 0 && line.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        let newline = chunk.iter().position(|&b| b == b'\n');
+        let part = &chunk[..newline.unwrap_or(chunk.len())];
+        len += part.len();
+        if len <= cap {
+            line.extend_from_slice(part);
+        }
+        let used = newline.map_or(chunk.len(), |at| at + 1);
+        reader.consume(used);
+        if newline.is_some() {
+            break;
+        }
+    }
+    if len > cap {
+        return Ok(Some(Err(len)));
+    }
+    Ok(Some(Ok(String::from_utf8_lossy(&line).into_owned())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn respond(request: Value) -> Value {
+        serde_json::from_str(&handle(&request.to_string(), &Limits::default())).unwrap()
+    }
+
+    #[test]
+    fn test_hello_handshake() {
+        let resp = respond(json!({ "type": "hello" }));
+        assert_eq!(resp["ok"], true);
+        assert_eq!(resp["protocol"], PROTOCOL);
+        assert_eq!(resp["version"], env!("CARGO_PKG_VERSION"));
+        assert!(resp["capabilities"].as_array().unwrap().contains(&json!("assets")));
+    }
+
+    #[test]
+    fn test_compile_without_type_keeps_working() {
+        let resp = respond(json!({
+            "entry_path": "pages/index.van",
+            "files": { "pages/index.van": "<template><p>Hi</p></template>" },
+        }));
+        assert_eq!(resp["ok"], true);
+        assert_eq!(resp["protocol"], PROTOCOL);
+        assert!(resp["html"].as_str().unwrap().contains("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn test_unknown_type_is_a_structured_error() {
+        let resp = respond(json!({ "type": "delta", "entry_path": "pages/index.van" }));
+        assert_eq!(resp["ok"], false);
+        assert_eq!(resp["protocol"], PROTOCOL);
+        assert_eq!(resp["code"], "unknown_type");
+        assert_eq!(resp["error"], "unknown request type \"delta\"");
+
+        let resp: Value = serde_json::from_str(&handle("{ not json", &Limits::default())).unwrap();
+        assert_eq!(resp["code"], "invalid_request");
+    }
+
+    #[test]
+    fn test_warmup_then_compile() {
+        let resp = respond(json!({ "type": "warmup" }));
+        assert_eq!(resp["ok"], true);
+        assert_eq!(resp["protocol"], PROTOCOL);
+        assert!(resp["warmed"].as_u64().unwrap() > 0);
+        assert!(resp["warmup_ms"].is_number());
+
+        let resp = respond(json!({
+            "entry_path": "pages/index.van",
+            "files": { "pages/index.van": "<template><p v-if=\"show\">{{ name }}</p></template>" },
+            "data_json": "{\"show\": true, \"name\": \"Ada\"}",
+        }));
+        assert_eq!(resp["ok"], true);
+        assert!(resp["html"].as_str().unwrap().contains(">Ada</p>"), "{resp}");
+    }
+
+    #[test]
+    fn test_windows_line_endings() {
+        let page = "\u{feff}<template>\r\n  <button @click=\"count++\">{{ count }}</button>\r\n</template>\r\n\r\n<script setup>\r\nconst count = ref(0)\r\nconst label = 'a\\\r\nb'\r\n</script>\r\n";
+        let resp = respond(json!({ "entry_path": "pages/index.van", "files": { "pages/index.van": page } }));
+        assert_eq!(resp["ok"], true, "{resp}");
+        let html = resp["html"].as_str().unwrap();
+        assert!(html.contains("<button>0</button>"), "{html}");
+        assert!(html.contains("var count = V.signal(0);"));
+        assert!(!html.contains('\r'));
+    }
+
+    #[test]
+    fn test_element_names() {
+        let files = json!({ "pages/index.van": "<template><main><p>{{ n }}</p></main></template>\n<script setup>\nconst n = ref(0)\n</script>" });
+        let resp = respond(json!({ "entry_path": "pages/index.van", "files": files }));
+        assert!(resp["html"].as_str().unwrap().contains("<!--v:0_0--><p>"), "{resp}");
+
+        let resp = respond(json!({ "entry_path": "pages/index.van", "files": files, "element_names": "ordinal" }));
+        assert!(resp["html"].as_str().unwrap().contains("<!--v:0--><p>"), "{resp}");
+
+        let resp = respond(json!({ "entry_path": "pages/index.van", "files": files, "element_names": "dfs" }));
+        assert_eq!(resp["ok"], false);
+        assert!(resp["error"].as_str().unwrap().contains("unknown element_names \"dfs\""));
+    }
+
+    fn compile_with(limits: &Limits, files: Value) -> Value {
+        let request = json!({ "entry_path": "pages/index.van", "files": files });
+        serde_json::from_str(&handle(&request.to_string(), limits)).unwrap()
+    }
+
+    #[test]
+    fn test_input_limits() {
+        let limits = Limits { max_input_bytes: 80, max_file_bytes: 60, max_files: 2, ..Limits::default() };
+        let page = "<template><p>Hi</p></template>";
+
+        let resp = compile_with(&limits, json!({ "pages/index.van": "x".repeat(61) }));
+        assert_eq!(resp["ok"], false);
+        assert_eq!(resp["code"], "limit_exceeded");
+        assert_eq!(resp["limit"], "max_file_bytes");
+        assert_eq!(resp["error"], "limit exceeded: max_file_bytes (pages/index.van is 61 bytes, limit 60)");
+
+        let resp = compile_with(&limits, json!({ "pages/index.van": page, "a.ts": "x".repeat(60) }));
+        assert_eq!(resp["limit"], "max_input_bytes");
+
+        let resp = compile_with(&limits, json!({ "pages/index.van": page, "a.ts": "", "b.ts": "" }));
+        assert_eq!(resp["limit"], "max_files");
+
+        assert_eq!(compile_with(&limits, json!({ "pages/index.van": page }))["ok"], true);
+    }
+
+    #[test]
+    fn test_request_limit() {
+        let limits = Limits { max_request_bytes: 100, ..Limits::default() };
+        let resp: Value = serde_json::from_str(&handle(&format!("{{\"entry_path\": \"{}\"}}", "x".repeat(100)), &limits)).unwrap();
+        assert_eq!(resp["code"], "limit_exceeded");
+        assert_eq!(resp["limit"], "max_request_bytes");
+        assert_eq!(resp["error"], "limit exceeded: max_request_bytes (118 bytes, limit 100)");
+
+        let long = "x".repeat(40);
+        let mut input = io::Cursor::new(format!("{{\"type\":\"hello\"}}\n{long}\n\nlast"));
+        assert_eq!(read_line_capped(&mut input, 20).unwrap(), Some(Ok(r#"{"type":"hello"}"#.to_string())));
+        assert_eq!(read_line_capped(&mut input, 20).unwrap(), Some(Err(40)));
+        assert_eq!(read_line_capped(&mut input, 20).unwrap(), Some(Ok(String::new())));
+        assert_eq!(read_line_capped(&mut input, 20).unwrap(), Some(Ok("last".to_string())));
+        assert_eq!(read_line_capped(&mut input, 20).unwrap(), None);
+    }
+
+    #[test]
+    fn test_output_and_depth_limits() {
+        let limits = Limits { max_output_bytes: 10, ..Limits::default() };
+        let resp = compile_with(&limits, json!({ "pages/index.van": "<template><p>Hello world</p></template>" }));
+        assert_eq!(resp["code"], "limit_exceeded");
+        assert_eq!(resp["limit"], "max_output_bytes");
+
+        // A chain of components nested deeper than the resolver allows
+        let mut files = serde_json::Map::new();
+        files.insert("pages/index.van".into(), json!("<template><C0 /></template>\n<script setup>\nimport C0 from '../components/c0.van'\n</script>"));
+        for i in 0..12 {
+            let next = i + 1;
+            let source = format!("<template><C{next} /></template>\n<script setup>\nimport C{next} from './c{next}.van'\n</script>");
+            files.insert(format!("components/c{i}.van"), json!(source));
+        }
+        files.insert("components/c12.van".into(), json!("<template><p>Deep</p></template>"));
+        let resp = compile_with(&Limits::default(), Value::Object(files));
+        assert_eq!(resp["code"], "limit_exceeded");
+        assert_eq!(resp["limit"], "max_depth");
+        assert!(resp["error"].as_str().unwrap().contains("Component nesting exceeded maximum depth"), "{resp}");
+    }
+
+    #[test]
+    fn test_limits_from_args() {
+        let args: Vec<String> = ["--daemon", "--max-files", "5", "--max-output-bytes=1024", "--max-request-bytes=4096"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        let limits = Limits::from_args(&args).unwrap();
+        assert_eq!(limits.max_files, 5);
+        assert_eq!(limits.max_output_bytes, 1024);
+        assert_eq!(limits.max_request_bytes, 4096);
+        assert_eq!(limits.max_file_bytes, Limits::default().max_file_bytes);
+        assert!(Limits::from_args(&["--max-files".to_string()]).is_err());
+        assert!(Limits::from_args(&["--max-files=lots".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let page = "<template><p>{{ count }}</p></template>\n<script setup>\nconst count = ref(0)\n</script>";
+        for asset_prefix in [None, Some("/assets")] {
+            let resp = respond(json!({
+                "entry_path": "pages/index.van",
+                "files": { "pages/index.van": page, "components/unused.van": "<template></template>" },
+                "data_json": "{}",
+                "asset_prefix": asset_prefix,
+                "diagnostics": true,
+            }));
+            let diagnostics = &resp["diagnostics"];
+            for field in ["parse_ms", "resolve_ms", "signal_gen_ms", "render_ms", "total_ms"] {
+                let ms = diagnostics[field].as_f64().unwrap_or_else(|| panic!("{field} in {resp}"));
+                assert!(ms >= 0.0, "{field}: {ms}");
+            }
+            assert_eq!(diagnostics["files_count"], 2);
+            let assets: usize = resp["assets"].as_object().map_or(0, |a| a.values().map(|v| v.as_str().unwrap().len()).sum());
+            assert_eq!(diagnostics["output_bytes"], resp["html"].as_str().unwrap().len() + assets);
+        }
+
+        let resp = respond(json!({ "entry_path": "pages/index.van", "files": { "pages/index.van": page } }));
+        assert!(resp.get("diagnostics").is_none(), "only on request");
+    }
+
+    #[test]
+    fn test_asset_prefix_is_normalized() {
+        let files = json!({ "index.van": "<template><p>Hi</p></template>\n<style>p { color: red; }</style>" });
+        let resp = respond(json!({ "entry_path": "index.van", "files": files, "asset_prefix": "themes\\site\\" }));
+        let paths: Vec<&String> = resp["assets"].as_object().unwrap().keys().collect();
+        assert!(paths.iter().all(|p| p.starts_with("/themes/site/css/")), "{paths:?}");
+
+        let resp = respond(json!({ "entry_path": "index.van", "files": files, "asset_prefix": "/my assets" }));
+        assert_eq!(resp["ok"], false);
+        assert!(resp["error"].as_str().unwrap().contains("Invalid asset prefix"));
+    }
+
+    #[test]
+    fn test_output_is_reproducible() {
+        let names = ["Alpha", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot"];
+        let compile_fixture = |asset_prefix: Option<&str>| {
+            let mut files = serde_json::Map::new();
+            let mut page = String::from("<template><main>");
+            let mut imports = String::new();
+            for name in names {
+                let lower = name.to_lowercase();
+                page.push_str(&format!("<{name} />"));
+                imports.push_str(&format!("import {name} from '../components/{lower}.van'\n"));
+                files.insert(
+                    format!("components/{lower}.van"),
+                    json!(format!(
+                        "<template><button @click=\"{lower}++\">{{{{ {lower} }}}}</button></template>\n\
+                         <script setup>\nconst {lower} = ref(0)\n</script>\n<style>.{lower} {{ color: red; }}</style>"
+                    )),
+                );
+            }
+            page.push_str(&format!("</main></template>\n<script setup>\n{imports}</script>"));
+            files.insert("pages/index.van".into(), json!(page));
+            let request = json!({
+                "entry_path": "pages/index.van",
+                "files": files,
+                "data_json": "{}",
+                "asset_prefix": asset_prefix,
+                "debug": true,
+            });
+            handle(&request.to_string(), &Limits::default())
         };
-        write_response(&resp);
+        for asset_prefix in [None, Some("/assets")] {
+            let first = compile_fixture(asset_prefix);
+            assert!(first.contains("\"ok\":true"), "{first}");
+            for _ in 0..3 {
+                assert_eq!(compile_fixture(asset_prefix), first);
+            }
+        }
     }
 }
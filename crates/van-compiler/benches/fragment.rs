@@ -0,0 +1,61 @@
+//! Per-render cost of a cached [`CompiledTemplate`] against compiling the
+//! template every time. Run with `cargo bench -p van-compiler`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use van_compiler::{compile_single, render_fragment, CompiledTemplate};
+
+const SOURCE: &str = r#"
+<template>
+  <section class="orders">
+    <h2>{{ title }}</h2>
+    <ul><li v-for="order in orders">#{{ order.id }} {{ order.customer }}: {{ order.total }}</li></ul>
+    <p v-if="empty">No orders</p>
+    <button @click="expanded = !expanded">Details</button>
+    <div v-show="expanded">Updated {{ updated }}</div>
+  </section>
+</template>
+
+<script setup>
+const expanded = ref(false)
+</script>
+
+<style scoped>
+.orders h2 { font-size: 1.25rem; }
+li { padding: 4px 0; }
+</style>
+"#;
+
+const ITERATIONS: u32 = 200;
+
+fn time(label: &str, mut f: impl FnMut()) -> Duration {
+    f();
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iter = started.elapsed() / ITERATIONS;
+    println!("{label:<28} {:>10.1} µs/iter", per_iter.as_secs_f64() * 1e6);
+    per_iter
+}
+
+fn main() {
+    let orders: Vec<_> = (0..50)
+        .map(|id| json!({ "id": id, "customer": format!("Customer {id}"), "total": id * 7 }))
+        .collect();
+    let data = json!({ "title": "Orders", "orders": orders, "empty": false, "updated": "today" });
+    let template = CompiledTemplate::compile(SOURCE).unwrap();
+
+    let compile = time("compile_single", || {
+        black_box(compile_single(black_box(SOURCE)).unwrap());
+    });
+    time("render_fragment", || {
+        black_box(render_fragment(black_box(SOURCE), &data).unwrap());
+    });
+    let render = time("CompiledTemplate::render", || {
+        black_box(template.render(black_box(&data)));
+    });
+    println!("cached render is {:.1}x faster than compile_single", compile.as_secs_f64() / render.as_secs_f64());
+}
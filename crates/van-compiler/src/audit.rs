@@ -0,0 +1,171 @@
+//! Denylist scan of page scripts, for hosts compiling untrusted themes.
+//!
+//! Matches are found in the code itself, not in comments or string literals
+//! (so `// evaluate(x)` and `"eval("` pass); `javascript:` URLs are the one
+//! thing looked for inside strings. Regex literals are read as code, so a
+//! quote inside one can hide the rest of its line.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// What to look for in code outside comments and strings, and how to name it.
+static CODE_PATTERNS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        (r"\bdocument\s*\.\s*write", "document.write"),
+        (r"(?:^|[^\w$])eval\s*\(", "eval()"),
+        (r"\bnew\s+Function\b", "new Function"),
+        (r"\binnerHTML\s*\+?=(?:[^=]|$)", "an innerHTML assignment"),
+    ]
+    .into_iter()
+    .map(|(pattern, what)| (Regex::new(pattern).unwrap(), what))
+    .collect()
+});
+
+/// Every denied construct in `code`, as `(line, what)` with 1-based lines, in
+/// source order.
+pub(crate) fn unsafe_js(code: &str) -> Vec<(usize, &'static str)> {
+    let (masked, strings) = mask(code);
+    let line_of = |offset: usize| masked[..offset].matches('\n').count() + 1;
+    let mut found: Vec<(usize, &'static str)> = CODE_PATTERNS
+        .iter()
+        .flat_map(|(re, what)| re.find_iter(&masked).map(|m| (line_of(m.end()), *what)))
+        .collect();
+    for (offset, content) in strings {
+        if content.to_ascii_lowercase().contains("javascript:") {
+            found.push((line_of(offset), "a javascript: URL"));
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// `code` with comments and string contents blanked out (line breaks kept),
+/// plus each string literal's content with the offset in the masked code it
+/// starts at. Template literal `${ }` parts are code.
+fn mask(code: &str) -> (String, Vec<(usize, String)>) {
+    enum State {
+        Code,
+        LineComment,
+        BlockComment,
+        Str(char),
+        Template,
+    }
+    let mut masked = String::with_capacity(code.len());
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    // Brace depth of code, and the depth each open `${` started at
+    let mut depth = 0usize;
+    let mut templates: Vec<usize> = Vec::new();
+    let mut state = State::Code;
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        let blank = |masked: &mut String, c: char| masked.push(if c == '\n' { '\n' } else { ' ' });
+        match state {
+            State::Code => {
+                match c {
+                    '/' if chars.peek() == Some(&'/') => state = State::LineComment,
+                    '/' if chars.peek() == Some(&'*') => {
+                        chars.next();
+                        masked.push(' ');
+                        state = State::BlockComment;
+                    }
+                    '\'' | '"' => state = State::Str(c),
+                    '`' => state = State::Template,
+                    '{' => depth += 1,
+                    '}' if templates.last() == Some(&depth) => {
+                        templates.pop();
+                        state = State::Template;
+                    }
+                    '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                if matches!(state, State::LineComment | State::BlockComment) {
+                    masked.push(' ');
+                } else {
+                    masked.push(c);
+                }
+            }
+            State::LineComment => {
+                blank(&mut masked, c);
+                if c == '\n' {
+                    state = State::Code;
+                }
+            }
+            State::BlockComment => {
+                blank(&mut masked, c);
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    masked.push(' ');
+                    state = State::Code;
+                }
+            }
+            State::Str(_) | State::Template => {
+                let closes = match state {
+                    State::Str(quote) => c == quote || c == '\n',
+                    _ => c == '`',
+                };
+                let opens_expr = matches!(state, State::Template) && c == '$' && chars.peek() == Some(&'{');
+                if closes || opens_expr {
+                    strings.push((masked.len() - current.chars().count(), std::mem::take(&mut current)));
+                }
+                if closes {
+                    masked.push(c);
+                    state = State::Code;
+                } else if opens_expr {
+                    chars.next();
+                    masked.push_str("${");
+                    templates.push(depth);
+                    state = State::Code;
+                } else {
+                    current.push(c);
+                    blank(&mut masked, c);
+                    if c == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            current.push(escaped);
+                            blank(&mut masked, escaped);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        strings.push((masked.len() - current.chars().count(), current));
+    }
+    (masked, strings)
+}
+
+/// Compile the denylist patterns now (see [`crate::warmup`]), returning how
+/// many there are.
+pub(crate) fn warmup() -> usize {
+    LazyLock::force(&CODE_PATTERNS).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsafe_js() {
+        let code = "const run = (src) => eval(src)\nel.innerHTML = html\nconst f = new Function('return 1')\nlocation.href = 'JavaScript:alert(1)'\ndocument.write(`<p>${ eval('x') }</p>`)";
+        assert_eq!(
+            unsafe_js(code),
+            vec![
+                (1, "eval()"),
+                (2, "an innerHTML assignment"),
+                (3, "new Function"),
+                (4, "a javascript: URL"),
+                (5, "document.write"),
+                (5, "eval()"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsafe_js_ignores_comments_and_strings() {
+        let code = "// evaluate(x) before document.write\nconst label = \"eval(\"\n/* new Function */ const ok = a.innerHTML == b\nfunction evaluate(x) { return retrieval(x) }\nconst t = `eval(${label})`";
+        assert_eq!(unsafe_js(code), vec![]);
+    }
+}
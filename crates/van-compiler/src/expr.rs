@@ -0,0 +1,395 @@
+//! Server-side evaluation of template expressions against page data: the
+//! conditions of `v-if` / `v-show`, and `{{ }}` expressions that are more than
+//! a data path.
+//!
+//! Supported: data paths (`user.name`, `items.length`), number literals
+//! (including negatives and floats, `-1.5`), string literals, `true`, `false`,
+//! `null`, `!`, `===`, `!==`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `&&`, `||` and
+//! parentheses. Anything else is not evaluated.
+//!
+//! Coercion is loose, and more forgiving than JS for CMS data where numbers
+//! often arrive as strings:
+//! - `<`, `<=`, `>`, `>=` compare numerically when both sides are numbers,
+//!   numeric strings (`"3"`, `" -0.5 "`), booleans (1/0) or `null` (0) — so
+//!   `"10" > "9"` is true, unlike JS. Two other strings compare by code point;
+//!   any other pairing is false.
+//! - `==` / `!=` compare a number and a numeric string as numbers; otherwise
+//!   values must have the same type (`true == "true"` is false). `===` / `!==`
+//!   never coerce.
+//! - Falsy: `false`, `null`, a missing path, `0`, `""`, and the strings `"0"`,
+//!   `"false"` and `"null"`. Everything else is truthy, arrays and objects
+//!   included.
+//!
+//! Every coercion (a string or boolean read as a number, a string read as
+//! false) is reported so `CompileOptions::strict_coercion` can warn about it.
+
+use serde_json::Value;
+
+use crate::usage::DataUsage;
+
+/// The outcome of evaluating an expression.
+pub(crate) struct Evaluated {
+    pub value: Value,
+    /// Whether a data path the expression reads is missing from the data.
+    pub missing: bool,
+    /// Each data path the expression read, and whether the data had it.
+    pub paths: Vec<(String, bool)>,
+    /// One line per type coercion the evaluation relied on.
+    pub coercions: Vec<String>,
+}
+
+/// Evaluate `expr` against `data`, or `None` when it isn't a supported expression.
+pub(crate) fn evaluate(expr: &str, data: &Value) -> Option<Evaluated> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser { tokens: &tokens, pos: 0, data, missing: false, paths: Vec::new(), coercions: Vec::new() };
+    let value = parser.or()?;
+    if parser.pos != tokens.len() {
+        return None;
+    }
+    Some(Evaluated { value, missing: parser.missing, paths: parser.paths, coercions: parser.coercions })
+}
+
+/// Whether a `v-if` / `v-show` condition holds, plus the coercions it relied
+/// on. The data paths it reads are recorded in `usage`.
+///
+/// Expressions that can't be evaluated (and strings still holding a `{{ }}`
+/// for the Java runtime) are false.
+pub(crate) fn condition(expr: &str, data: &Value, usage: &DataUsage) -> (bool, Vec<String>) {
+    let Some(Evaluated { value, paths, mut coercions, .. }) = evaluate(expr, data) else {
+        return (false, Vec::new());
+    };
+    for (path, found) in paths {
+        usage.record(&path, found);
+    }
+    let truthy = truthy(&value, &mut coercions);
+    let coercions = coercions.into_iter().map(|c| format!("`{}`: {c}", expr.trim())).collect();
+    (truthy, coercions)
+}
+
+/// Render an evaluated value as `{{ }}` output.
+pub(crate) fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn truthy(value: &Value, coercions: &mut Vec<String>) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0 && !n.is_nan()),
+        Value::String(s) if s.contains("{{") => false,
+        Value::String(s) if matches!(s.as_str(), "0" | "false" | "null") => {
+            coercions.push(format!("the string \"{s}\" counts as false"));
+            false
+        }
+        Value::String(s) => !s.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Path(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+/// Operators, longest first so `===` isn't read as `==` `=`.
+const OPERATORS: &[&str] = &["===", "!==", "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "-"];
+
+fn tokenize(expr: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            rest = &rest[1..];
+        } else if c == '\'' || c == '"' {
+            let end = rest[1..].find(c)? + 1;
+            tokens.push(Token::Str(rest[1..end].to_string()));
+            rest = &rest[end + 1..];
+        } else if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|d: char| d.is_ascii_digit())) {
+            let len = number_len(rest);
+            tokens.push(Token::Number(rest[..len].parse().ok()?));
+            rest = &rest[len..];
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Path(rest[..len].trim_end_matches('.').to_string()));
+            rest = &rest[len..];
+        } else {
+            let op = OPERATORS.iter().find(|op| rest.starts_with(**op))?;
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Some(tokens)
+}
+
+/// Length of the number literal `s` starts with: digits, a fraction and an exponent.
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = digits(0);
+    if bytes.get(i) == Some(&b'.') {
+        i = digits(i + 1);
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(i + 1), Some(b'+' | b'-')));
+        if bytes.get(i + 1 + sign).is_some_and(u8::is_ascii_digit) {
+            i = digits(i + 1 + sign);
+        }
+    }
+    i
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    data: &'a Value,
+    missing: bool,
+    paths: Vec<(String, bool)>,
+    coercions: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.tokens.get(self.pos), Some(Token::Op(o)) if *o == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Option<Value> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            let right = self.and()?;
+            if !truthy(&left, &mut self.coercions) {
+                left = right;
+            }
+        }
+        Some(left)
+    }
+
+    fn and(&mut self) -> Option<Value> {
+        let mut left = self.equality()?;
+        while self.eat("&&") {
+            let right = self.equality()?;
+            if truthy(&left, &mut self.coercions) {
+                left = right;
+            }
+        }
+        Some(left)
+    }
+
+    fn equality(&mut self) -> Option<Value> {
+        let mut left = self.relational()?;
+        loop {
+            let (strict, negate) = if self.eat("===") {
+                (true, false)
+            } else if self.eat("!==") {
+                (true, true)
+            } else if self.eat("==") {
+                (false, false)
+            } else if self.eat("!=") {
+                (false, true)
+            } else {
+                return Some(left);
+            };
+            let right = self.relational()?;
+            let equal = match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+                _ if strict => left == right,
+                _ => self.loose_equal(&left, &right),
+            };
+            left = Value::Bool(equal != negate);
+        }
+    }
+
+    fn loose_equal(&mut self, left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Number(_), Value::String(_)) | (Value::String(_), Value::Number(_)) => {
+                match (self.number(left), self.number(right)) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => false,
+                }
+            }
+            _ => left == right,
+        }
+    }
+
+    fn relational(&mut self) -> Option<Value> {
+        let mut left = self.unary()?;
+        loop {
+            let op = ["<=", ">=", "<", ">"].into_iter().find(|op| self.eat(op));
+            let Some(op) = op else {
+                return Some(left);
+            };
+            let right = self.unary()?;
+            let ordering = match (&left, &right) {
+                (Value::String(a), Value::String(b)) if a.trim().parse::<f64>().is_err() || b.trim().parse::<f64>().is_err() => {
+                    Some(a.cmp(b))
+                }
+                _ => match (self.number(&left), self.number(&right)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b),
+                    _ => None,
+                },
+            };
+            left = Value::Bool(ordering.is_some_and(|o| match op {
+                "<" => o.is_lt(),
+                "<=" => o.is_le(),
+                ">" => o.is_gt(),
+                _ => o.is_ge(),
+            }));
+        }
+    }
+
+    fn unary(&mut self) -> Option<Value> {
+        if self.eat("!") {
+            let value = self.unary()?;
+            return Some(Value::Bool(!truthy(&value, &mut self.coercions)));
+        }
+        if self.eat("-") {
+            let value = self.unary()?;
+            let n = self.number(&value)?;
+            return Some(number_value(-n));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Option<Value> {
+        let token = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Some(number_value(n)),
+            Token::Str(s) => Some(Value::String(s)),
+            Token::Path(path) => Some(match path.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" | "undefined" => Value::Null,
+                _ => self.lookup(&path),
+            }),
+            Token::Open => {
+                let value = self.or()?;
+                (self.tokens.get(self.pos) == Some(&Token::Close)).then(|| self.pos += 1)?;
+                Some(value)
+            }
+            Token::Close | Token::Op(_) => None,
+        }
+    }
+
+    fn lookup(&mut self, path: &str) -> Value {
+        let mut current = self.data;
+        for key in path.split('.') {
+            let length = match current {
+                Value::Array(items) if key == "length" => Some(items.len()),
+                Value::String(s) if key == "length" => Some(s.chars().count()),
+                _ => None,
+            };
+            if let Some(length) = length {
+                self.paths.push((path.to_string(), true));
+                return Value::from(length);
+            }
+            match current.get(key) {
+                Some(value) => current = value,
+                None => {
+                    self.missing = true;
+                    self.paths.push((path.to_string(), false));
+                    return Value::Null;
+                }
+            }
+        }
+        self.paths.push((path.to_string(), true));
+        current.clone()
+    }
+
+    /// `value` as a number, recording the coercion when it isn't one already.
+    fn number(&mut self, value: &Value) -> Option<f64> {
+        let (n, what) = match value {
+            Value::Number(n) => return n.as_f64(),
+            Value::String(s) => (s.trim().parse::<f64>().ok().filter(|_| !s.trim().is_empty())?, format!("the string \"{s}\"")),
+            Value::Bool(b) => (f64::from(u8::from(*b)), format!("the boolean {b}")),
+            Value::Null => (0.0, "null".to_string()),
+            _ => return None,
+        };
+        self.coercions.push(format!("{what} is compared as the number {n}"));
+        Some(n)
+    }
+}
+
+fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        Value::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn condition(expr: &str, data: &Value) -> (bool, Vec<String>) {
+        super::condition(expr, data, &DataUsage::default())
+    }
+
+    #[test]
+    fn test_string_number_coercion() {
+        let data = json!({ "stock": "3", "sold": "10", "code": "abc", "zero": "0" });
+        assert_eq!(condition("stock > 0", &data), (true, vec![
+            "`stock > 0`: the string \"3\" is compared as the number 3".to_string(),
+        ]));
+        assert!(condition("sold > stock", &data).0, "numeric, not lexicographic");
+        assert!(condition("stock == 3", &data).0);
+        assert!(!condition("stock === 3", &data).0);
+        assert!(!condition("code > 0", &data).0);
+        let (shown, coercions) = condition("zero", &data);
+        assert!(!shown);
+        assert_eq!(coercions, vec!["`zero`: the string \"0\" counts as false".to_string()]);
+    }
+
+    #[test]
+    fn test_negative_and_float_literals() {
+        let data = json!({ "rating": 4.25, "delta": -2, "temp": "-0.5" });
+        assert!(condition("rating >= 4.2", &data).0);
+        assert!(!condition("rating > 4.5", &data).0);
+        assert!(condition("delta < -1.5", &data).0);
+        assert!(condition("temp > -1 && temp < .5", &data).0);
+        assert!(condition("-delta === 2", &data).0);
+        assert!(condition("rating > 1e0", &data).0);
+        assert!(condition("rating * 2", &data) == (false, Vec::new()));
+        assert!(condition("delta === -2.0", &data).0);
+    }
+
+    #[test]
+    fn test_booleans_paths_and_logic() {
+        let data = json!({ "user": { "admin": false, "name": "Ada" }, "items": [1, 2] });
+        assert!(condition("!user.admin", &data).0);
+        assert!(condition("user.admin === false", &data).0);
+        assert!(condition("items.length > 1 && (user.name == 'Ada' || user.admin)", &data).0);
+        assert!(!condition("user.missing", &data).0);
+        assert!(!condition("user.name ? 1 : 0", &data).0, "unsupported syntax is false");
+
+        let evaluated = evaluate("user.nickname || user.name", &data).unwrap();
+        assert_eq!(display(&evaluated.value), "Ada");
+        assert!(evaluated.missing);
+        assert_eq!(evaluated.paths, vec![("user.nickname".to_string(), false), ("user.name".to_string(), true)]);
+        assert_eq!(display(&evaluate("items.length < 3", &data).unwrap().value), "true");
+    }
+}
@@ -0,0 +1,336 @@
+pub(crate) use van_parser::tag_end;
+
+/// How rendered HTML is laid out, applied as a final pass by `render_page`
+/// and `render_page_assets`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HtmlFormat {
+    /// Keep whatever whitespace resolution produced.
+    #[default]
+    Preserve,
+    /// Re-indent block elements, one per line.
+    Pretty,
+    /// Collapse whitespace between tags.
+    Minify,
+}
+
+impl std::str::FromStr for HtmlFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(Self::Preserve),
+            "pretty" => Ok(Self::Pretty),
+            "minify" => Ok(Self::Minify),
+            other => Err(format!(
+                "unknown HTML format \"{other}\" (expected preserve, pretty or minify)"
+            )),
+        }
+    }
+}
+
+/// Elements laid out on their own line (pretty) and whose surrounding
+/// whitespace is insignificant (minify).
+const BLOCK_TAGS: &[&str] = &[
+    "html", "head", "body", "title", "meta", "link", "base", "noscript",
+    "div", "p", "section", "article", "header", "footer", "nav", "main", "aside",
+    "h1", "h2", "h3", "h4", "h5", "h6", "hr", "address", "blockquote",
+    "ul", "ol", "li", "dl", "dt", "dd", "menu",
+    "table", "caption", "colgroup", "col", "thead", "tbody", "tfoot", "tr", "td", "th",
+    "form", "fieldset", "legend", "figure", "figcaption", "details", "summary", "dialog",
+    "template", "video", "audio", "canvas", "picture", "source", "option", "optgroup",
+];
+
+pub(crate) const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "source", "track", "wbr",
+];
+
+/// Elements whose content is emitted verbatim.
+const RAW_TAGS: &[&str] = &["pre", "textarea", "script", "style"];
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Text(&'a str),
+    Open { name: String, raw: &'a str },
+    Close { name: String, raw: &'a str },
+    /// Comments and the doctype.
+    Markup(&'a str),
+    /// A raw-text element, from its opening tag through its closing tag.
+    Raw { name: String, raw: &'a str },
+}
+
+impl Token<'_> {
+    /// Whether whitespace next to this token can be dropped.
+    fn is_block(&self) -> bool {
+        match self {
+            Token::Open { name, .. } | Token::Close { name, .. } => BLOCK_TAGS.contains(&name.as_str()),
+            Token::Raw { name, .. } => name != "textarea",
+            Token::Markup(raw) => raw.starts_with("<!") && !raw.starts_with("<!--"),
+            Token::Text(_) => false,
+        }
+    }
+}
+
+/// Apply `format` to a rendered page.
+pub(crate) fn format_html(html: &str, format: HtmlFormat) -> String {
+    match format {
+        HtmlFormat::Preserve => html.to_string(),
+        HtmlFormat::Pretty => pretty(&tokenize(html)),
+        HtmlFormat::Minify => minify(&tokenize(html)),
+    }
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    let mut text_start = 0;
+    let bytes = html.as_bytes();
+    while pos < html.len() {
+        if bytes[pos] != b'<' {
+            pos += 1;
+            continue;
+        }
+        let rest = &html[pos..];
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|e| e + 3)
+        } else if rest.starts_with("</") || rest.starts_with("<!") || rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            tag_end(rest)
+        } else {
+            None
+        };
+        let Some(end) = end else {
+            pos += 1;
+            continue;
+        };
+        if text_start < pos {
+            tokens.push(Token::Text(&html[text_start..pos]));
+        }
+        let raw = &rest[..end];
+        let mut next = pos + end;
+        if raw.starts_with("<!") {
+            tokens.push(Token::Markup(raw));
+        } else if let Some(name) = raw.strip_prefix("</") {
+            tokens.push(Token::Close { name: tag_name(name), raw });
+        } else {
+            let name = tag_name(&raw[1..]);
+            if RAW_TAGS.contains(&name.as_str()) {
+                let close = format!("</{name}");
+                let lower = html[next..].to_ascii_lowercase();
+                let close_end = lower
+                    .find(&close)
+                    .and_then(|c| lower[c..].find('>').map(|e| next + c + e + 1))
+                    .unwrap_or(html.len());
+                next = close_end;
+                tokens.push(Token::Raw { name, raw: &html[pos..close_end] });
+            } else {
+                tokens.push(Token::Open { name, raw });
+            }
+        }
+        pos = next;
+        text_start = next;
+    }
+    if text_start < html.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+fn tag_name(s: &str) -> String {
+    s.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+fn minify(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Text(text) => {
+                let mut text = collapse_whitespace(text);
+                let prev_block = i == 0 || tokens[i - 1].is_block();
+                let next_block = tokens.get(i + 1).is_none_or(Token::is_block);
+                if prev_block {
+                    text = text.trim_start().to_string();
+                }
+                if next_block {
+                    text = text.trim_end().to_string();
+                }
+                out.push_str(&text);
+            }
+            other => out.push_str(token_raw(other)),
+        }
+    }
+    out
+}
+
+fn pretty(tokens: &[Token]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut inline = String::new();
+    let mut depth = 0usize;
+
+    fn flush(lines: &mut Vec<String>, inline: &mut String, depth: usize) {
+        let content = inline.trim();
+        if !content.is_empty() {
+            lines.push(format!("{}{}", "  ".repeat(depth), content));
+        }
+        inline.clear();
+    }
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        match token {
+            Token::Text(text) => inline.push_str(&collapse_whitespace(text)),
+            Token::Open { name, raw } if token.is_block() => {
+                flush(&mut lines, &mut inline, depth);
+                if VOID_TAGS.contains(&name.as_str()) || raw.ends_with("/>") {
+                    lines.push(format!("{}{}", "  ".repeat(depth), raw));
+                    continue;
+                }
+                // A block holding only inline content stays on one line
+                match leaf_close(tokens, i, name) {
+                    Some(close) => {
+                        let mut line = raw.to_string();
+                        line.push_str(inline_content(&tokens[i..close]).trim());
+                        line.push_str(token_raw(&tokens[close]));
+                        lines.push(format!("{}{}", "  ".repeat(depth), line));
+                        i = close + 1;
+                    }
+                    None => {
+                        lines.push(format!("{}{}", "  ".repeat(depth), raw));
+                        depth += 1;
+                    }
+                }
+            }
+            Token::Close { raw, .. } if token.is_block() => {
+                flush(&mut lines, &mut inline, depth);
+                depth = depth.saturating_sub(1);
+                lines.push(format!("{}{}", "  ".repeat(depth), raw));
+            }
+            Token::Raw { raw, .. } | Token::Markup(raw) if token.is_block() => {
+                flush(&mut lines, &mut inline, depth);
+                lines.push(format!("{}{}", "  ".repeat(depth), raw));
+            }
+            _ => inline.push_str(token_raw(token)),
+        }
+    }
+    flush(&mut lines, &mut inline, depth);
+    lines.join("\n")
+}
+
+/// Index of the closing tag of `name` if no block token comes before it.
+fn leaf_close(tokens: &[Token], from: usize, name: &str) -> Option<usize> {
+    let offset = tokens[from..].iter().position(Token::is_block)?;
+    match &tokens[from + offset] {
+        Token::Close { name: close, .. } if close == name => Some(from + offset),
+        _ => None,
+    }
+}
+
+fn inline_content(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Text(text) => collapse_whitespace(text),
+            other => token_raw(other).to_string(),
+        })
+        .collect()
+}
+
+fn token_raw<'a>(token: &Token<'a>) -> &'a str {
+    match token {
+        Token::Text(raw)
+        | Token::Open { raw, .. }
+        | Token::Close { raw, .. }
+        | Token::Markup(raw)
+        | Token::Raw { raw, .. } => raw,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY: &str = "<!DOCTYPE html>\n<html>\n<head>\n      <title>Demo</title>\n</head>\n<body>\n\n\n\n    <div class=\"card\">\n<h1>  Hello,\n   <em>world</em> !</h1>\n\n\n\n        <ul><li>a</li>\n\n<li>b</li></ul>\n<pre>  keep\n    this  </pre>\n</div>\n</body>\n</html>\n";
+
+    #[test]
+    fn test_preserve() {
+        assert_eq!(format_html(MESSY, HtmlFormat::Preserve), MESSY);
+    }
+
+    #[test]
+    fn test_minify() {
+        assert_eq!(
+            format_html(MESSY, HtmlFormat::Minify),
+            "<!DOCTYPE html><html><head><title>Demo</title></head><body><div class=\"card\"><h1>Hello, <em>world</em> !</h1><ul><li>a</li><li>b</li></ul><pre>  keep\n    this  </pre></div></body></html>"
+        );
+    }
+
+    #[test]
+    fn test_minify_keeps_inline_spacing() {
+        assert_eq!(
+            format_html("<p>\n  <a href=\"/\">Home</a>\n  <span>About</span>\n</p>", HtmlFormat::Minify),
+            "<p><a href=\"/\">Home</a> <span>About</span></p>"
+        );
+        assert_eq!(
+            format_html("<div> <textarea>  a\n b </textarea> </div>", HtmlFormat::Minify),
+            "<div><textarea>  a\n b </textarea></div>"
+        );
+    }
+
+    #[test]
+    fn test_pretty() {
+        assert_eq!(
+            format_html(MESSY, HtmlFormat::Pretty),
+            "<!DOCTYPE html>
+<html>
+  <head>
+    <title>Demo</title>
+  </head>
+  <body>
+    <div class=\"card\">
+      <h1>Hello, <em>world</em> !</h1>
+      <ul>
+        <li>a</li>
+        <li>b</li>
+      </ul>
+      <pre>  keep
+    this  </pre>
+    </div>
+  </body>
+</html>"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_quoted_gt_and_raw_script() {
+        let tokens = tokenize("<a title=\"a>b\">x</a><script>if (a < b) {}</script>");
+        assert_eq!(
+            tokens[0],
+            Token::Open { name: "a".into(), raw: "<a title=\"a>b\">" }
+        );
+        assert_eq!(
+            tokens[3],
+            Token::Raw { name: "script".into(), raw: "<script>if (a < b) {}</script>" }
+        );
+    }
+}
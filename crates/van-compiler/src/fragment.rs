@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::render::{compile_body, fill_data};
+use crate::resolve::{self, expand_v_for};
+use crate::usage::DataUsage;
+use crate::CompileOptions;
+
+/// The virtual path a standalone template is compiled as.
+const ENTRY: &str = "main.van";
+
+/// A rendered template without the document around it, for embedding in a
+/// page the host application owns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fragment {
+    /// The template's markup.
+    pub html: String,
+    /// Its styles, scoped classes included.
+    pub css: String,
+    /// Its signal script, empty when nothing is reactive. It runs on the
+    /// signal runtime ([`crate::runtime_js`]), which the page loads once
+    /// before any fragment script.
+    pub js: String,
+}
+
+/// Why a template could not be compiled or rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError(pub String);
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<String> for CompileError {
+    fn from(message: String) -> Self {
+        CompileError(message)
+    }
+}
+
+/// Render a single `.van` source with data into a [`Fragment`].
+///
+/// Compiles the template on every call; for a template rendered once per
+/// request, compile it once into a [`CompiledTemplate`] instead.
+///
+/// ```
+/// use serde_json::json;
+///
+/// let source = r#"
+/// <template>
+///   <ul><li v-for="user in users">{{ user.name }}</li></ul>
+/// </template>
+/// <style scoped>li { color: teal; }</style>
+/// "#;
+/// let fragment = van_compiler::render_fragment(source, &json!({ "users": [{ "name": "Ada" }] }))?;
+/// assert!(fragment.html.contains(">Ada</li>"));
+/// assert!(fragment.css.contains("color: teal"));
+/// assert!(!fragment.html.contains("<html"));
+/// # Ok::<(), van_compiler::CompileError>(())
+/// ```
+pub fn render_fragment(source: &str, data: &Value) -> Result<Fragment, CompileError> {
+    let files = HashMap::from([(ENTRY.to_string(), source.to_string())]);
+    let options = CompileOptions::default();
+    let resolved = resolve::resolve_with_options(ENTRY, &files, data, &options)?;
+    let body = compile_body(&resolved, "main", &options);
+    Ok(Fragment {
+        html: fill_data(&body.html, data),
+        css: body.styles.join("\n"),
+        js: body.signal_js.unwrap_or_default(),
+    })
+}
+
+/// A `.van` template compiled once and rendered with different data many
+/// times.
+///
+/// Compiling does everything that doesn't depend on data — parsing, scoping
+/// styles, generating the signal script — the way the Java runtime's
+/// templates are compiled. Rendering only expands `v-for` and fills in
+/// `{{ }}`, `v-if` and `v-show`.
+///
+/// A typical embedding compiles its templates at startup and renders them
+/// from request handlers:
+///
+/// ```
+/// use std::sync::OnceLock;
+///
+/// use serde_json::{json, Value};
+/// use van_compiler::CompiledTemplate;
+///
+/// fn greeting() -> &'static CompiledTemplate {
+///     static TEMPLATE: OnceLock<CompiledTemplate> = OnceLock::new();
+///     TEMPLATE.get_or_init(|| {
+///         CompiledTemplate::compile(r#"<template><p class="hi">Hello, {{ name }}!</p></template>"#)
+///             .expect("greeting.van compiles")
+///     })
+/// }
+///
+/// fn handler(user: &Value) -> String {
+///     greeting().render(user).html
+/// }
+///
+/// assert_eq!(handler(&json!({ "name": "Ada" })).trim(), r#"<p class="hi">Hello, Ada!</p>"#);
+/// assert_eq!(handler(&json!({ "name": "Grace" })).trim(), r#"<p class="hi">Hello, Grace!</p>"#);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledTemplate {
+    html: String,
+    css: String,
+    js: String,
+    warnings: Vec<String>,
+}
+
+impl CompiledTemplate {
+    /// Compile a single `.van` source.
+    pub fn compile(source: &str) -> Result<Self, CompileError> {
+        let files = HashMap::from([(ENTRY.to_string(), source.to_string())]);
+        let options = CompileOptions::default();
+        let resolved = resolve::resolve_with_options(ENTRY, &files, &Value::Object(Default::default()), &options)?;
+        let body = compile_body(&resolved, "main", &options);
+        Ok(CompiledTemplate {
+            html: body.html,
+            css: body.styles.join("\n"),
+            js: body.signal_js.unwrap_or_default(),
+            warnings: resolved.warnings,
+        })
+    }
+
+    /// Render with `data`.
+    pub fn render(&self, data: &Value) -> Fragment {
+        Fragment {
+            html: fill_data(&expand_v_for(&self.html, data, false, None, &DataUsage::default()), data),
+            css: self.css.clone(),
+            js: self.js.clone(),
+        }
+    }
+
+    /// Non-fatal diagnostics from compiling the template.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const LIST: &str = r#"
+<template>
+  <section>
+    <h2>{{ title }}</h2>
+    <ul><li v-for="(item, i) in items">{{ i }}: {{ item.name }}</li></ul>
+    <p v-if="empty">Nothing yet</p>
+    <button @click="count++">Seen {{ count }}</button>
+  </section>
+</template>
+
+<script setup>
+const count = ref(0)
+</script>
+
+<style scoped>
+h2 { margin: 0; }
+</style>
+"#;
+
+    #[test]
+    fn test_render_fragment() {
+        let data = json!({ "title": "Todo", "items": [{ "name": "a" }, { "name": "b" }], "empty": false });
+        let fragment = render_fragment(LIST, &data).unwrap();
+        assert!(!fragment.html.contains("<html"));
+        assert!(!fragment.html.contains("<script"));
+        assert!(fragment.html.contains("<h2"));
+        assert!(fragment.html.contains(">Todo</h2>"));
+        assert!(fragment.html.contains(">0: a</li>") && fragment.html.contains(">1: b</li>"));
+        assert!(fragment.html.contains("Seen 0"));
+        assert!(fragment.css.contains("margin: 0"));
+        assert!(fragment.js.contains("signal(0)"));
+    }
+
+    #[test]
+    fn test_compiled_template_matches_render_fragment() {
+        let template = CompiledTemplate::compile(LIST).unwrap();
+        for data in [
+            json!({ "title": "Todo", "items": [{ "name": "a" }, { "name": "b" }], "empty": false }),
+            json!({ "title": "Done", "items": [], "empty": true }),
+        ] {
+            assert_eq!(template.render(&data), render_fragment(LIST, &data).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let source = "<template><missing-card /></template>\n<script setup>\nimport MissingCard from './missing-card.van'\n</script>";
+        let err = CompiledTemplate::compile(source).unwrap_err();
+        assert!(err.to_string().contains("Component not found"), "{err}");
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use van_parser::{parse_blocks, parse_imports, parse_script_imports, PropDef, ScriptImport, VanBlock, VanImport};
+use van_signal_gen::{analyze_script, walk_template, TemplateBindings};
+
+/// The compiler's view of a single `.van` file, for editor tooling.
+///
+/// Nothing is resolved: imports are listed as written and the bindings are
+/// those of this file's own template.
+#[derive(Debug, Serialize)]
+pub struct FileInspection {
+    pub blocks: VanBlock,
+    /// `.van` component imports.
+    pub imports: Vec<VanImport>,
+    /// Module and content imports (`.ts`, `.js`, `.md`).
+    pub script_imports: Vec<ScriptImport>,
+    pub props: Vec<PropDef>,
+    /// `ref()` and `computed()` names declared in `<script setup>`.
+    pub reactive_names: Vec<String>,
+    /// Directive and interpolation bindings of the template against `reactive_names`.
+    pub bindings: TemplateBindings,
+}
+
+/// Inspect a `.van` source without compiling a page.
+pub fn inspect(source: &str) -> FileInspection {
+    let blocks = parse_blocks(source);
+    let script = blocks.script_setup.as_deref().unwrap_or("");
+    let analysis = analyze_script(script);
+    let reactive_names: Vec<String> = analysis
+        .signals
+        .iter()
+        .map(|s| s.name.clone())
+        .chain(analysis.computeds.iter().map(|c| c.name.clone()))
+        .collect();
+    let reactive_refs: Vec<&str> = reactive_names.iter().map(String::as_str).collect();
+    let bindings = walk_template(blocks.template.as_deref().unwrap_or(""), &reactive_refs);
+    FileInspection {
+        imports: parse_imports(script),
+        script_imports: parse_script_imports(script),
+        props: blocks.props.clone(),
+        reactive_names,
+        bindings,
+        blocks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_signals_props_and_directives() {
+        let source = r#"<template>
+  <Card :title="title">
+    <p v-show="open" :class="{ active: open }">{{ count }} / {{ title }}</p>
+    <input v-model="query" />
+    <button @click="toggle">Toggle</button>
+    <div v-html="preview"></div>
+  </Card>
+</template>
+<script setup>
+import Card from '../components/card.van'
+import { formatDate } from '../utils/format.ts'
+defineProps({ title: { type: String, required: true } })
+const count = ref(0)
+const open = ref(false)
+const query = ref('')
+const preview = computed(() => '<b>' + query.value + '</b>')
+function toggle() { open.value = !open.value }
+</script>"#;
+        let info = inspect(source);
+        assert_eq!(info.imports.len(), 1);
+        assert_eq!(info.imports[0].tag_name, "card");
+        assert_eq!(info.script_imports.len(), 1);
+        assert_eq!(info.script_imports[0].path, "../utils/format.ts");
+        assert_eq!(info.props.len(), 1);
+        assert!(info.props[0].required);
+        assert_eq!(info.reactive_names, vec!["count", "open", "query", "preview"]);
+
+        let b = &info.bindings;
+        assert_eq!(b.events[0].handler, "toggle");
+        assert_eq!(b.shows[0].expr, "open");
+        assert_eq!(b.classes[0].expr, "{ active: open }");
+        assert_eq!(b.models[0].signal_name, "query");
+        assert_eq!(b.htmls[0].expr, "preview");
+        assert_eq!(b.texts[0].template, "{{ count }} / {{ title }}");
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["props"][0]["name"], "title");
+        assert_eq!(json["bindings"]["models"][0]["signal_name"], "query");
+        assert!(json["blocks"]["template"].as_str().unwrap().contains("<Card"));
+    }
+}
@@ -1,14 +1,224 @@
+mod audit;
+mod expr;
+mod format;
+mod fragment;
 mod i18n;
+mod inspect;
+mod markdown;
+mod raw;
 mod resolve;
 pub mod render;
+mod sanitize;
+mod usage;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
 
+use usage::DataUsage;
+
+pub use format::HtmlFormat;
+pub use fragment::{render_fragment, CompileError, CompiledTemplate, Fragment};
+pub use inspect::{inspect, FileInspection};
 pub use render::PageAssets;
+pub use render::RuntimeSource;
+pub use resolve::OriginMarkers;
 pub use resolve::ResolvedComponent;
 pub use resolve::resolve_single;
 pub use resolve::resolve_with_files;
 pub use resolve::resolve_with_files_debug;
+pub use resolve::resolve_with_options;
+pub use usage::unused_keys;
+pub use van_parser::normalize_source;
+pub use van_signal_gen::{runtime_js, ElementNames, Hydration, RuntimeFormat};
+pub use van_signal_gen::{
+    AttrTemplateBinding, ClassBinding, EventBinding, ForBinding, HtmlDirectiveBinding, ModelBinding, ShowBinding,
+    StyleBinding, TemplateBindings, TextBinding, TextDirectiveBinding, Transition,
+};
+
+/// Options shared by every compile/render entry point.
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    /// Mark component/slot boundaries in the output (see `origin_markers`), and
+    /// ship the signal bindings as a `<script type="application/json"
+    /// id="__van_bindings">` block next to the signal script.
+    pub debug: bool,
+    /// How boundaries are marked in debug mode: HTML comments or `data-van-origin` attributes.
+    pub origin_markers: OriginMarkers,
+    /// Maps file paths to theme names (e.g. `"components/header.van" → "van1"`):
+    /// shown in debug markers, mixed into scope ids, and the stylesheet
+    /// directory of each theme in assets mode.
+    pub file_origins: HashMap<String, String>,
+    /// Global name for the signal runtime.
+    pub global_name: String,
+    /// Build-time environment variables, exposed as `env.*` in templates and
+    /// `import.meta.env.*` in scripts.
+    pub env: HashMap<String, String>,
+    /// Final whitespace pass over rendered HTML (render mode only).
+    pub html_format: HtmlFormat,
+    /// Bundle the signal runtime or load it from a caller-provided URL.
+    pub runtime: RuntimeSource,
+    /// Strip scripts, event handlers and `javascript:` URLs from `{{{ }}}`
+    /// output and `v-html` values. When off, each use is reported as a warning.
+    pub sanitize_html: bool,
+    /// Let the signal script be torn down: it registers
+    /// `window.__van_pages[page].destroy()`, which removes its event listeners
+    /// and stops its effects, for hosts that swap pages without a reload.
+    pub emit_teardown: bool,
+    /// Layout wrapped around the entry's template (default slot), e.g.
+    /// `layouts/default.van`, unless the page already uses a component from
+    /// `layouts/`. Pages opt out with `defineOptions({ layout: false })` or pick
+    /// another with `layout: 'layouts/blank.van'`.
+    pub auto_layout: Option<String>,
+    /// Stylesheets (file-map paths, e.g. `styles/global.css`) emitted before
+    /// every component style: as the first `<style>` inline, and as their own
+    /// `css/global.{hash}.css` asset linked ahead of the page's in assets mode.
+    pub global_css: Vec<String>,
+    /// Custom element (web component) tags that are not Van components, so
+    /// they are not reported as unknown. A trailing `*` matches a prefix,
+    /// e.g. `sl-*`.
+    pub custom_elements: Vec<String>,
+    /// Bare module specifiers of `<script setup>` imports (`lodash-es`) mapped
+    /// to a URL (`https://…`, `/vendor/…`), which the page's
+    /// `<script type="importmap">` points the browser at, or to a files-map
+    /// path, which is inlined like any other module. Unmapped bare imports
+    /// are reported as warnings.
+    pub import_map: BTreeMap<String, String>,
+    /// Link assets relative to the page's output location instead of by
+    /// root-relative path (`../assets/css/...` from `about/index.html`), for
+    /// sites opened from disk or hosted under a subpath. Pages are assumed to
+    /// be written as `{name}/index.html` below `pages/`, as `van generate` does.
+    pub relative_assets: bool,
+    /// Warn, in render mode, whenever a `v-if` / `v-show` condition relies on
+    /// type coercion, such as the string `"3"` compared as a number in
+    /// `stock > 0` or the string `"0"` taken as false.
+    pub strict_coercion: bool,
+    /// Fail compilation when `<script setup>` or an imported `.ts`/`.js`
+    /// module uses `eval()`, `new Function`, `document.write`, an
+    /// `innerHTML` assignment or a `javascript:` URL, for hosts compiling
+    /// untrusted themes. These are warnings otherwise.
+    pub strict_js: bool,
+    /// When the page script wires bindings to the DOM: at load, or with
+    /// [`Hydration::Lazy`] per top-level element once it is seen or touched.
+    pub hydration: Hydration,
+    /// Split the signal script of [`render_page_assets`] into chunks of about
+    /// this many bytes, linked in order (see [`van_signal_gen::split_signals`]).
+    /// `None` keeps one script.
+    pub max_js_chunk_bytes: Option<usize>,
+    /// Append a baseline opacity fade (over the wrapper's `duration`, 300ms
+    /// without one) for each `<Transition>` name the page animates whose
+    /// `.{name}-enter-active` class no collected style defines. When off,
+    /// each such transition is reported as a warning.
+    pub transition_css: bool,
+    /// How the signal script finds bound elements: by their paths
+    /// (`<!--v:0_2_1-->`), so adding an element renames none outside its
+    /// parent, or with [`ElementNames::Ordinal`] by document-order numbers,
+    /// as scripts compiled before did.
+    pub element_names: ElementNames,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            origin_markers: OriginMarkers::Comments,
+            file_origins: HashMap::new(),
+            global_name: "Van".to_string(),
+            env: HashMap::new(),
+            html_format: HtmlFormat::Preserve,
+            runtime: RuntimeSource::Bundled,
+            sanitize_html: false,
+            emit_teardown: false,
+            auto_layout: None,
+            global_css: Vec::new(),
+            custom_elements: Vec::new(),
+            import_map: BTreeMap::new(),
+            relative_assets: false,
+            strict_coercion: false,
+            strict_js: false,
+            hydration: Hydration::Eager,
+            max_js_chunk_bytes: None,
+            transition_css: false,
+            element_names: ElementNames::Path,
+        }
+    }
+}
+
+/// A compiled or rendered page, plus any non-fatal warnings.
+#[derive(Debug, Clone)]
+pub struct PageOutput {
+    pub html: String,
+    pub warnings: Vec<String>,
+    /// Source files the page was built from (entry, components, modules), sorted.
+    pub dependencies: Vec<String>,
+    /// Page data paths the templates read (`user.name`), sorted. Empty in
+    /// compile mode.
+    pub data_used: Vec<String>,
+    /// Data paths the templates read that the data doesn't have, sorted; each
+    /// is also a warning naming the template. Empty in compile mode.
+    pub data_missing: Vec<String>,
+    /// Where the build time went.
+    pub timings: Timings,
+}
+
+/// Why a page could not be compiled or rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageError {
+    /// Components nest deeper than the resolver allows.
+    MaxDepth(String),
+    /// Anything else: a missing file, invalid data, a template error.
+    Invalid(String),
+}
+
+impl std::fmt::Display for PageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageError::MaxDepth(message) | PageError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for PageError {}
+
+impl From<String> for PageError {
+    fn from(message: String) -> Self {
+        PageError::Invalid(message)
+    }
+}
+
+impl From<PageError> for String {
+    fn from(error: PageError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Wall time of each build phase of one page, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timings {
+    /// Decoding the data JSON.
+    pub parse_ms: f64,
+    /// Parsing the `.van` sources and resolving imports, slots and props.
+    pub resolve_ms: f64,
+    /// Generating the signal script.
+    pub signal_gen_ms: f64,
+    /// Producing the HTML (and assets) around it: cleanup, data, formatting.
+    pub render_ms: f64,
+    /// The whole build, including checks not counted above.
+    pub total_ms: f64,
+}
+
+fn elapsed_ms(since: Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Modules with lazily compiled regexes, each forcing its own.
+const WARMUPS: &[fn() -> usize] = &[audit::warmup, render::warmup, resolve::warmup];
+
+/// Compile every lazily built regex now instead of on first use, returning
+/// how many, so the first page compiled doesn't pay for them. For hosts that
+/// care about first-request latency, like the WASI build's `--warmup`.
+pub fn warmup() -> usize {
+    WARMUPS.iter().map(|warmup| warmup()).sum()
+}
 
 // ── Compile (no data) ───────────────────────────────────────────
 // Produces HTML with v-for/v-if/:class/{{ }} preserved for Java runtime.
@@ -18,7 +228,7 @@ pub fn compile(
     entry_path: &str,
     files: &HashMap<String, String>,
 ) -> Result<String, String> {
-    build_page(entry_path, files, None, false, &HashMap::new(), "Van")
+    compile_page(entry_path, files, &CompileOptions::default()).map(|p| p.html).map_err(String::from)
 }
 
 /// Like `compile`, but with all options.
@@ -29,7 +239,17 @@ pub fn compile_full(
     file_origins: &HashMap<String, String>,
     global_name: &str,
 ) -> Result<String, String> {
-    build_page(entry_path, files, None, debug, file_origins, global_name)
+    let options = legacy_options(debug, file_origins, global_name);
+    compile_page(entry_path, files, &options).map(|p| p.html).map_err(String::from)
+}
+
+/// Compile with [`CompileOptions`], returning warnings alongside the HTML.
+pub fn compile_page(
+    entry_path: &str,
+    files: &HashMap<String, String>,
+    options: &CompileOptions,
+) -> Result<PageOutput, PageError> {
+    build_page(entry_path, files, None, options)
 }
 
 /// Compile with separated assets (no data binding).
@@ -38,7 +258,7 @@ pub fn compile_assets(
     files: &HashMap<String, String>,
     asset_prefix: &str,
 ) -> Result<PageAssets, String> {
-    build_page_assets(entry_path, files, None, asset_prefix, false, &HashMap::new(), "Van")
+    compile_page_assets(entry_path, files, asset_prefix, &CompileOptions::default()).map_err(String::from)
 }
 
 /// Like `compile_assets`, but with all options.
@@ -50,7 +270,18 @@ pub fn compile_assets_full(
     file_origins: &HashMap<String, String>,
     global_name: &str,
 ) -> Result<PageAssets, String> {
-    build_page_assets(entry_path, files, None, asset_prefix, debug, file_origins, global_name)
+    let options = legacy_options(debug, file_origins, global_name);
+    compile_page_assets(entry_path, files, asset_prefix, &options).map_err(String::from)
+}
+
+/// Compile with separated assets and [`CompileOptions`].
+pub fn compile_page_assets(
+    entry_path: &str,
+    files: &HashMap<String, String>,
+    asset_prefix: &str,
+    options: &CompileOptions,
+) -> Result<PageAssets, PageError> {
+    build_page_assets(entry_path, files, None, asset_prefix, options)
 }
 
 /// Compile a single `.van` file source (no data binding).
@@ -69,7 +300,7 @@ pub fn render_to_string(
     files: &HashMap<String, String>,
     data_json: &str,
 ) -> Result<String, String> {
-    build_page(entry_path, files, Some(data_json), false, &HashMap::new(), "Van")
+    render_page(entry_path, files, data_json, &CompileOptions::default()).map(|p| p.html).map_err(String::from)
 }
 
 /// Like `render_to_string`, but with debug HTML comments at component/slot boundaries.
@@ -79,7 +310,8 @@ pub fn render_to_string_debug(
     data_json: &str,
     file_origins: &HashMap<String, String>,
 ) -> Result<String, String> {
-    build_page(entry_path, files, Some(data_json), true, file_origins, "Van")
+    let options = legacy_options(true, file_origins, "Van");
+    render_page(entry_path, files, data_json, &options).map(|p| p.html).map_err(String::from)
 }
 
 /// Like `render_to_string`, but with all options.
@@ -91,7 +323,18 @@ pub fn render_to_string_full(
     file_origins: &HashMap<String, String>,
     global_name: &str,
 ) -> Result<String, String> {
-    build_page(entry_path, files, Some(data_json), debug, file_origins, global_name)
+    let options = legacy_options(debug, file_origins, global_name);
+    render_page(entry_path, files, data_json, &options).map(|p| p.html).map_err(String::from)
+}
+
+/// Render with [`CompileOptions`], returning warnings alongside the HTML.
+pub fn render_page(
+    entry_path: &str,
+    files: &HashMap<String, String>,
+    data_json: &str,
+    options: &CompileOptions,
+) -> Result<PageOutput, PageError> {
+    build_page(entry_path, files, Some(data_json), options)
 }
 
 /// Render with separated assets.
@@ -101,7 +344,7 @@ pub fn render_to_assets(
     data_json: &str,
     asset_prefix: &str,
 ) -> Result<PageAssets, String> {
-    build_page_assets(entry_path, files, Some(data_json), asset_prefix, false, &HashMap::new(), "Van")
+    render_page_assets(entry_path, files, data_json, asset_prefix, &CompileOptions::default()).map_err(String::from)
 }
 
 /// Like `render_to_assets`, but with all options.
@@ -114,7 +357,19 @@ pub fn render_to_assets_full(
     file_origins: &HashMap<String, String>,
     global_name: &str,
 ) -> Result<PageAssets, String> {
-    build_page_assets(entry_path, files, Some(data_json), asset_prefix, debug, file_origins, global_name)
+    let options = legacy_options(debug, file_origins, global_name);
+    render_page_assets(entry_path, files, data_json, asset_prefix, &options).map_err(String::from)
+}
+
+/// Render with separated assets and [`CompileOptions`].
+pub fn render_page_assets(
+    entry_path: &str,
+    files: &HashMap<String, String>,
+    data_json: &str,
+    asset_prefix: &str,
+    options: &CompileOptions,
+) -> Result<PageAssets, PageError> {
+    build_page_assets(entry_path, files, Some(data_json), asset_prefix, options)
 }
 
 /// Render a single `.van` file source with data.
@@ -126,28 +381,105 @@ pub fn render_single(source: &str, data_json: &str) -> Result<String, String> {
 
 // ── Internal shared implementation ──────────────────────────────
 
+fn legacy_options(
+    debug: bool,
+    file_origins: &HashMap<String, String>,
+    global_name: &str,
+) -> CompileOptions {
+    CompileOptions {
+        debug,
+        file_origins: file_origins.clone(),
+        global_name: global_name.to_string(),
+        ..CompileOptions::default()
+    }
+}
+
 fn build_page(
     entry_path: &str,
     files: &HashMap<String, String>,
     data_json: Option<&str>,
-    debug: bool,
-    file_origins: &HashMap<String, String>,
-    global_name: &str,
-) -> Result<String, String> {
+    options: &CompileOptions,
+) -> Result<PageOutput, PageError> {
+    let started = Instant::now();
+    let mut timings = Timings::default();
     let compile = data_json.is_none();
     let json_str = data_json.unwrap_or("{}");
     let data: serde_json::Value = serde_json::from_str(json_str)
         .map_err(|e| format!("Invalid JSON: {e}"))?;
-    let resolved = if debug {
-        resolve::resolve_with_files_debug(entry_path, files, &data, file_origins)?
-    } else {
-        resolve::resolve_with_files(entry_path, files, &data)?
+    timings.parse_ms = elapsed_ms(started);
+
+    let phase = Instant::now();
+    let usage = DataUsage::tracking();
+    let resolved = resolve::resolve_tracked(entry_path, files, &data, options, &usage)?;
+    timings.resolve_ms = elapsed_ms(phase);
+
+    let phase = Instant::now();
+    let page_name = entry_path.trim_end_matches(".van");
+    // Same as `render::render_to_string`, keeping the signal generation time
+    let (html, signal_gen_ms) = render::compile_timed(&resolved, page_name, options)?;
+    usage.enter(entry_path);
+    let (html, coercions) = if compile { (html, Vec::new()) } else { render::fill_data_checked(&html, &data, &usage) };
+    let coercions = if options.strict_coercion { coercions } else { Vec::new() };
+    let conflicts = render::binding_conflicts(&resolved);
+    let mismatches = render::anchor_mismatches(&resolved, &html, options.element_names);
+    let transitions = render::transition_warnings(&resolved, options);
+    let mut warnings = resolved.warnings;
+    warnings.extend(conflicts.into_iter().chain(mismatches).chain(transitions).chain(coercions).map(|m| format!("{entry_path}: {m}")));
+    let (data_used, data_missing) = if compile { Default::default() } else { data_usage(usage, &data, &mut warnings) };
+    let html = if compile { html } else { format::format_html(&html, options.html_format) };
+    timings.signal_gen_ms = signal_gen_ms;
+    timings.render_ms = elapsed_ms(phase) - signal_gen_ms;
+    timings.total_ms = elapsed_ms(started);
+    Ok(PageOutput {
+        html,
+        warnings,
+        dependencies: resolved.dependencies,
+        data_used,
+        data_missing,
+        timings,
+    })
+}
+
+/// The page data paths `usage` saw read, as `(used, missing)`, with a
+/// warning for each missing one.
+fn data_usage(usage: DataUsage, data: &serde_json::Value, warnings: &mut Vec<String>) -> (Vec<String>, Vec<String>) {
+    let (used, missing) = usage.finish(data);
+    let missing = missing
+        .into_iter()
+        .map(|(path, file)| {
+            warnings.push(format!("{file}: \"{path}\" is not in the page data"));
+            path
+        })
+        .collect();
+    (used, missing)
+}
+
+/// Normalize an asset prefix to a single leading slash, no trailing slash and
+/// forward slashes only (`assets/` and `\assets\` both become `/assets`; `/`
+/// becomes `""`). An absolute URL keeps its scheme and host.
+///
+/// Prefixes with whitespace, control characters or `..` segments are rejected.
+pub fn normalize_asset_prefix(prefix: &str) -> Result<String, String> {
+    if prefix.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("Invalid asset prefix {prefix:?}: contains whitespace"));
+    }
+    let prefix = prefix.replace('\\', "/");
+    let (origin, path) = match prefix.split_once("://") {
+        Some((scheme, rest)) => {
+            let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+            (format!("{scheme}://{host}"), path)
+        }
+        None => (String::new(), prefix.as_str()),
     };
-    if compile {
-        render::compile(&resolved, global_name)
-    } else {
-        render::render_to_string(&resolved, &data, global_name)
+    let mut normalized = origin;
+    for segment in path.split('/').filter(|s| !s.is_empty() && *s != ".") {
+        if segment == ".." {
+            return Err(format!("Invalid asset prefix {prefix:?}: contains `..`"));
+        }
+        normalized.push('/');
+        normalized.push_str(segment);
     }
+    Ok(normalized)
 }
 
 fn build_page_assets(
@@ -155,27 +487,49 @@ fn build_page_assets(
     files: &HashMap<String, String>,
     data_json: Option<&str>,
     asset_prefix: &str,
-    debug: bool,
-    file_origins: &HashMap<String, String>,
-    global_name: &str,
-) -> Result<PageAssets, String> {
+    options: &CompileOptions,
+) -> Result<PageAssets, PageError> {
+    let started = Instant::now();
+    let asset_prefix = &normalize_asset_prefix(asset_prefix)?;
     let compile = data_json.is_none();
     let json_str = data_json.unwrap_or("{}");
     let data: serde_json::Value = serde_json::from_str(json_str)
         .map_err(|e| format!("Invalid JSON: {e}"))?;
-    let resolved = if debug {
-        resolve::resolve_with_files_debug(entry_path, files, &data, file_origins)?
-    } else {
-        resolve::resolve_with_files(entry_path, files, &data)?
-    };
+    let parse_ms = elapsed_ms(started);
+
+    let phase = Instant::now();
+    let usage = DataUsage::tracking();
+    let resolved = resolve::resolve_tracked(entry_path, files, &data, options, &usage)?;
+    let resolve_ms = elapsed_ms(phase);
 
+    let phase = Instant::now();
     let page_name = entry_path.trim_end_matches(".van");
 
-    if compile {
-        render::compile_assets(&resolved, page_name, asset_prefix, global_name)
+    let mut assets = if compile {
+        render::compile_assets(&resolved, page_name, asset_prefix, options)?
     } else {
-        render::render_to_assets(&resolved, &data, page_name, asset_prefix, global_name)
+        usage.enter(entry_path);
+        render::render_to_assets_tracked(&resolved, &data, page_name, asset_prefix, options, &usage)?
+    };
+    let conflicts = render::binding_conflicts(&resolved);
+    let mismatches = render::client_only_mismatches(&resolved, &assets.html);
+    let transitions = render::transition_warnings(&resolved, options);
+    if !compile {
+        assets.html = format::format_html(&assets.html, options.html_format);
+    }
+    // The coercions were left behind in strict mode
+    let coercions = std::mem::replace(&mut assets.warnings, resolved.warnings);
+    assets.warnings.extend(conflicts.into_iter().chain(mismatches).chain(transitions).chain(coercions).map(|m| format!("{entry_path}: {m}")));
+    if !compile {
+        (assets.data_used, assets.data_missing) = data_usage(usage, &data, &mut assets.warnings);
     }
+    assets.dependencies = resolved.dependencies;
+    // `compile_assets` filled in `signal_gen_ms`
+    assets.timings.parse_ms = parse_ms;
+    assets.timings.resolve_ms = resolve_ms;
+    assets.timings.render_ms = elapsed_ms(phase) - assets.timings.signal_gen_ms;
+    assets.timings.total_ms = elapsed_ms(started);
+    Ok(assets)
 }
 
 #[cfg(feature = "wasm")]
@@ -200,7 +554,7 @@ pub fn compile_van(
         let content = val
             .as_str()
             .ok_or_else(|| JsValue::from_str(&format!("File '{}' content must be a string", key)))?;
-        files.insert(key.clone(), content.to_string());
+        files.insert(key.clone(), normalize_source(content));
     }
 
     // WASM: treat empty string as "{}" for backward compat
@@ -208,6 +562,13 @@ pub fn compile_van(
     render_to_string(entry_path, &files, data).map_err(|e| JsValue::from_str(&e))
 }
 
+/// [`inspect`] a `.van` source, returned as JSON.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn inspect_van(source: &str) -> Result<String, JsValue> {
+    serde_json::to_string(&inspect(source)).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -306,6 +667,9 @@ h1 { color: green; }
         let html = render_to_string("index.van", &files, data).unwrap();
         assert!(html.contains("<h1>Hello, Van!</h1>"));
         assert!(html.contains("color: green"));
+
+        let page = render_page("index.van", &files, data, &CompileOptions::default()).unwrap();
+        assert_eq!(page.dependencies, vec!["hello.van", "index.van"]);
     }
 
     #[test]
@@ -345,6 +709,382 @@ return { formatDate: formatDate };"#
         assert!(!html.contains("__mod_1"));
     }
 
+    #[test]
+    fn test_render_to_string_es_module_imports() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            r#"
+<template>
+  <p>{{ label }}</p>
+  <button @click="update">Go</button>
+</template>
+
+<script setup lang="ts">
+import { pad, SEP as separator } from './utils/format.ts'
+import greet from './utils/greet.js'
+const label = ref('')
+function update() { label.value = greet(pad(7) + separator) }
+</script>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "utils/format.ts".to_string(),
+            "export function pad(n: number): string { return String(n).padStart(2, '0'); }\nexport const SEP: string = ':';\n".to_string(),
+        );
+        files.insert(
+            "utils/greet.js".to_string(),
+            "export default function greet(s) { return 'hi ' + s; }\n".to_string(),
+        );
+
+        let html = render_to_string("index.van", &files, "{}").unwrap();
+        assert!(html.contains("return { pad: pad, SEP: SEP };"));
+        assert!(html.contains("return { default: greet };"));
+        assert!(html.contains("var pad = __mod_0.pad;"));
+        assert!(html.contains("var separator = __mod_0.SEP;"));
+        assert!(html.contains("var greet = __mod_1.default;"));
+        assert!(!html.contains("export "));
+    }
+
+    #[test]
+    fn test_render_to_string_shared_module_inlined_once() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"
+<template>
+  <p>{{ total }}</p>
+  <price-tag />
+</template>
+
+<script setup>
+import PriceTag from '../components/price-tag.van'
+import { currency } from '../utils/currency.ts'
+const total = ref(currency(3))
+</script>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "components/price-tag.van".to_string(),
+            r#"
+<template><button @click="show">{{ price }}</button></template>
+
+<script setup>
+import { currency as money } from './../utils/currency.ts'
+const price = ref('')
+function show() { price.value = money(5) }
+</script>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "utils/currency.ts".to_string(),
+            "export function currency(n: number): string { return '$' + n; }\n".to_string(),
+        );
+
+        let html = render_to_string("pages/index.van", &files, "{}").unwrap();
+        assert_eq!(html.matches("function currency(n)").count(), 1);
+        assert!(!html.contains("__mod_1"));
+        assert!(html.contains("var currency = __mod_0.currency;"));
+        assert!(html.contains("var money = __mod_0.currency;"));
+    }
+
+    fn counter_files() -> HashMap<String, String> {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            "<template><ClientOnly><button @click=\"count++\">{{ count }}</button></ClientOnly></template>\n<script setup>\nconst count = ref(0)\n</script>".to_string(),
+        );
+        files
+    }
+
+    #[test]
+    fn test_external_runtime_iife() {
+        let options = CompileOptions {
+            runtime: RuntimeSource::External { url: "https://cdn.example.com/van-runtime.js".into(), format: RuntimeFormat::Iife },
+            ..Default::default()
+        };
+        let html = render_page("index.van", &counter_files(), "{}", &options).unwrap().html;
+        assert!(html.contains(r#"<script src="https://cdn.example.com/van-runtime.js"></script>"#));
+        assert!(!html.contains("function signal("));
+        assert!(html.contains("var V = Van;"));
+
+        let assets = render_page_assets("index.van", &counter_files(), "{}", "/assets", &options).unwrap();
+        assert_eq!(assets.assets.len(), 1);
+        assert!(assets.assets.keys().all(|k| k.starts_with("/assets/js/index.")));
+        assert!(assets.html.contains(r#"<script src="https://cdn.example.com/van-runtime.js"></script>"#));
+    }
+
+    #[test]
+    fn test_external_runtime_es_module() {
+        let options = CompileOptions {
+            runtime: RuntimeSource::External { url: "/shared/van-runtime.mjs".into(), format: RuntimeFormat::EsModule },
+            ..Default::default()
+        };
+        let html = render_page("index.van", &counter_files(), "{}", &options).unwrap().html;
+        assert!(html.contains(r#"<script type="module">import * as V from "/shared/van-runtime.mjs";"#));
+        assert!(!html.contains("var V = Van;"));
+
+        let assets = render_page_assets("index.van", &counter_files(), "{}", "/assets", &options).unwrap();
+        let (path, js) = assets.assets.iter().find(|(k, _)| k.ends_with(".js")).unwrap();
+        assert!(js.starts_with("import * as V from \"/shared/van-runtime.mjs\";\n"));
+        assert!(assets.html.contains(&format!(r#"<script type="module" src="{path}"></script>"#)));
+        assert!(!assets.assets.keys().any(|k| k.contains("van-runtime")));
+    }
+
+    #[test]
+    fn test_max_js_chunk_bytes() {
+        let button = "<ClientOnly><button @click=\"count++\">{{ count }}</button></ClientOnly>";
+        let files = HashMap::from([(
+            "index.van".to_string(),
+            format!("<template><main>{}</main></template>\n<script setup>\nconst count = ref(0)\n</script>", button.repeat(30)),
+        )]);
+        let options = CompileOptions { max_js_chunk_bytes: Some(2000), ..Default::default() };
+        let assets = render_page_assets("index.van", &files, "{}", "/assets", &options).unwrap();
+        let scripts: Vec<&str> = assets.html.match_indices("<script src=\"").map(|(at, _)| &assets.html[at + 13..]).map(|rest| &rest[..rest.find('"').unwrap()]).collect();
+        assert_eq!(scripts.len(), 5, "the runtime, then four chunks");
+        assert!(scripts[0].starts_with("/assets/js/van-runtime."));
+        assert!(scripts[1].starts_with("/assets/js/index.") && scripts[1].matches('.').count() == 2);
+        for (i, path) in scripts[2..].iter().enumerate() {
+            assert!(path.starts_with(&format!("/assets/js/index.{}.", i + 1)), "{path}");
+        }
+        for path in &scripts[1..] {
+            assert!(assets.assets[*path].len() <= 2000, "{path}");
+        }
+        assert!(assets.assets[scripts[1]].contains("window.__van_chunks[\"index\"]"));
+
+        let single = render_page_assets("index.van", &files, "{}", "/assets", &CompileOptions::default()).unwrap();
+        assert_eq!(single.assets.keys().filter(|k| k.ends_with(".js")).count(), 2);
+    }
+
+    #[test]
+    fn test_theme_isolation() {
+        let component = "<template><p class=\"title\">{{ text }}</p></template>\n<style scoped>.title { color: red; }</style>";
+        let files = HashMap::from([
+            (
+                "index.van".to_string(),
+                "<template><main><site-header text=\"Head\" /><site-footer text=\"Foot\" /><plain-note text=\"Note\" /></main></template>\n<script setup>\nimport SiteHeader from './header.van'\nimport SiteFooter from './footer.van'\nimport PlainNote from './note.van'\n</script>".to_string(),
+            ),
+            ("header.van".to_string(), component.to_string()),
+            ("footer.van".to_string(), component.to_string()),
+            ("note.van".to_string(), component.to_string()),
+        ]);
+        let file_origins = HashMap::from([
+            ("header.van".to_string(), "van1".to_string()),
+            ("footer.van".to_string(), "van2".to_string()),
+        ]);
+        let options = CompileOptions { file_origins, ..Default::default() };
+        let page = render_page_assets("index.van", &files, "{}", "/assets", &options).unwrap();
+        let plain = van_parser::scope_id(".title { color: red; }");
+        let van1 = van_parser::scope_id("van1.title { color: red; }");
+        let van2 = van_parser::scope_id("van2.title { color: red; }");
+        assert!(plain != van1 && van1 != van2 && plain != van2);
+        for id in [&plain, &van1, &van2] {
+            assert!(page.html.contains(&format!("class=\"title {id}\"")), "{}", page.html);
+        }
+
+        let links: Vec<&str> = page.html.match_indices("<link rel=\"stylesheet\" href=\"").map(|(at, _)| &page.html[at + 29..]).map(|rest| &rest[..rest.find('"').unwrap()]).collect();
+        assert_eq!(links.len(), 3, "{links:?}");
+        // The entry has no origin, so the default stylesheet comes last
+        assert!(links[0].starts_with("/assets/van1/css/index."), "{links:?}");
+        assert!(links[1].starts_with("/assets/van2/css/index."), "{links:?}");
+        assert!(links[2].starts_with("/assets/css/index."), "{links:?}");
+        assert!(page.assets[links[0]].contains(&format!(".title.{van1}")) && !page.assets[links[0]].contains(&van2));
+        assert!(page.assets[links[1]].contains(&format!(".title.{van2}")));
+        assert!(page.assets[links[2]].contains(&format!(".title.{plain}")));
+
+        let single = render_page_assets("index.van", &files, "{}", "/assets", &CompileOptions::default()).unwrap();
+        assert_eq!(single.assets.keys().filter(|k| k.ends_with(".css")).count(), 1);
+    }
+
+    #[test]
+    fn test_import_map() {
+        let page = "<template><ClientOnly><input @input=\"search\" /><p>{{ query }}</p><time>{{ now }}</time></ClientOnly></template>\n<script setup>\nimport { debounce } from 'lodash-es'\nimport dayjs from 'dayjs'\nimport confetti from 'canvas-confetti'\nconst query = ref('')\nconst now = ref(dayjs().format('HH:mm'))\nconst search = debounce((e) => { query.value = e.target.value }, 200)\n</script>";
+        let files = HashMap::from([
+            ("index.van".to_string(), page.to_string()),
+            ("vendor/lodash-es.js".to_string(), "export function debounce(fn, ms) { return fn }".to_string()),
+        ]);
+        let options = CompileOptions {
+            import_map: BTreeMap::from([
+                ("lodash-es".to_string(), "vendor/lodash-es.js".to_string()),
+                ("dayjs".to_string(), "https://esm.sh/dayjs@1".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let page = render_page("index.van", &files, "{}", &options).unwrap();
+        // The vendored file is inlined like a relative module
+        assert!(page.html.contains("function debounce(fn, ms) { return fn }"));
+        assert!(page.html.contains("var debounce = __mod_0.debounce;"));
+        assert!(page.dependencies.contains(&"vendor/lodash-es.js".to_string()));
+        // The URL goes through the import map and a module script
+        assert!(page.html.contains(r#"<script type="importmap">{"imports":{"dayjs":"https://esm.sh/dayjs@1"}}</script>"#));
+        assert!(page.html.find("importmap").unwrap() < page.html.find("</head>").unwrap());
+        assert!(page.html.contains("<script type=\"module\">import * as __ext_0 from \"dayjs\";\n(function() {"));
+        assert!(page.html.contains("var dayjs = __mod_1.default;"));
+        assert_eq!(
+            page.warnings,
+            vec!["index.van: bare import \"canvas-confetti\" is not in the import map; its bindings are undefined in the page script"]
+        );
+
+        let assets = render_page_assets("index.van", &files, "{}", "/assets", &options).unwrap();
+        let (path, js) = assets.assets.iter().find(|(k, _)| k.starts_with("/assets/js/index.")).unwrap();
+        assert!(js.starts_with("import * as __ext_0 from \"dayjs\";\n"));
+        assert!(assets.html.contains(&format!(r#"<script type="module" src="{path}"></script>"#)));
+        assert!(assets.html.contains(r#"<script type="importmap">"#));
+    }
+
+    #[test]
+    fn test_render_page_markdown_import() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/about.van".to_string(),
+            r#"
+<template>
+  <h1>{{ title }}</h1>
+  <article>{{{ intro }}}</article>
+</template>
+
+<script setup>
+import intro from './content/intro.md'
+const count = ref(0)
+</script>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "pages/content/intro.md".to_string(),
+            "---\ntitle: About us\n---\n## Who\n\n- Ann\n- Bo\n".to_string(),
+        );
+
+        let page = render_page("pages/about.van", &files, "{}", &CompileOptions::default()).unwrap();
+        assert!(page.html.contains("<h1>About us</h1>"));
+        assert!(page.html.contains("<article><h2>Who</h2>\n<ul>\n<li>Ann</li>\n<li>Bo</li>\n</ul>\n</article>"));
+        assert!(!page.html.contains("intro.md"));
+        assert_eq!(page.dependencies, vec!["pages/about.van", "pages/content/intro.md"]);
+
+        // Page data takes precedence over front-matter
+        let page = render_page("pages/about.van", &files, r#"{"title": "Override"}"#, &CompileOptions::default()).unwrap();
+        assert!(page.html.contains("<h1>Override</h1>"));
+    }
+
+    #[test]
+    fn test_normalize_asset_prefix() {
+        assert_eq!(normalize_asset_prefix("/assets/").unwrap(), "/assets");
+        assert_eq!(normalize_asset_prefix("assets").unwrap(), "/assets");
+        assert_eq!(normalize_asset_prefix("\\themes\\site\\assets\\").unwrap(), "/themes/site/assets");
+        assert_eq!(normalize_asset_prefix("//a/./b//").unwrap(), "/a/b");
+        assert_eq!(normalize_asset_prefix("/").unwrap(), "");
+        assert_eq!(normalize_asset_prefix("https://cdn.example.com/v1/").unwrap(), "https://cdn.example.com/v1");
+        assert!(normalize_asset_prefix("/my assets").unwrap_err().contains("whitespace"));
+        assert!(normalize_asset_prefix("/assets/../secret").unwrap_err().contains(".."));
+
+        let mut files = HashMap::new();
+        files.insert("index.van".to_string(), "<template><p>Hi</p></template>\n<style>p { color: red; }</style>".to_string());
+        let page = compile_page_assets("index.van", &files, "assets/", &CompileOptions::default()).unwrap();
+        let (path, _) = page.assets.iter().next().unwrap();
+        assert!(path.starts_with("/assets/css/index."), "{path}");
+        assert!(page.html.contains(&format!("href=\"{path}\"")));
+    }
+
+    #[test]
+    fn test_relative_assets() {
+        let mut files = HashMap::new();
+        files.insert("pages/index.van".to_string(), "<template><p>Home</p></template>".to_string());
+        files.insert("pages/docs/intro.van".to_string(), "<template><p>Intro</p></template>".to_string());
+        files.insert("styles/global.css".to_string(), "body { margin: 0; }".to_string());
+        let options = CompileOptions {
+            global_css: vec!["styles/global.css".to_string()],
+            relative_assets: true,
+            ..Default::default()
+        };
+        let root = render_page_assets("pages/index.van", &files, "{}", "/assets", &options).unwrap();
+        let nested = render_page_assets("pages/docs/intro.van", &files, "{}", "/assets", &options).unwrap();
+        let path = root.assets.keys().next().unwrap();
+        assert!(path.starts_with("/assets/css/global."), "{path}");
+        assert_eq!(nested.assets.keys().next(), Some(path));
+        assert!(root.html.contains(&format!(r#"href="{}""#, &path[1..])), "{}", root.html);
+        assert!(nested.html.contains(&format!(r#"href="../../{}""#, &path[1..])), "{}", nested.html);
+    }
+
+    #[test]
+    fn test_page_assets_shared_component_css_is_stable() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/home.van".to_string(),
+            r#"
+<template><main><card /><van-button /></main></template>
+<script setup>
+import Card from '../components/card.van'
+import VanButton from '../components/button.van'
+</script>
+<style>main { padding: 0; }</style>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "pages/about.van".to_string(),
+            r#"
+<template><main><van-button /><card /><card /></main></template>
+<script setup>
+import Card from '../components/card.van'
+import VanButton from '../components/button.van'
+</script>
+<style>main { margin: 0; }</style>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "components/card.van".to_string(),
+            "<template><div class=\"card\">Card</div></template>\n<style scoped>.card { border: 1px solid; }</style>".to_string(),
+        );
+        files.insert(
+            "components/button.van".to_string(),
+            "<template><button class=\"btn\">Go</button></template>\n<style scoped>.btn { color: red; }</style>".to_string(),
+        );
+
+        let css = |entry: &str| {
+            let page = compile_page_assets(entry, &files, "/assets", &CompileOptions::default()).unwrap();
+            page.assets
+                .into_iter()
+                .find(|(path, _)| path.ends_with(".css"))
+                .map(|(_, css)| css)
+                .unwrap()
+        };
+        let home = css("pages/home.van");
+        let about = css("pages/about.van");
+
+        let shared = |css: &str| css[..css.find("main {").unwrap()].to_string();
+        assert_eq!(shared(&home), shared(&about));
+        assert!(shared(&home).find(".btn").unwrap() < shared(&home).find(".card").unwrap());
+        // The card is included twice on the about page but its CSS only once
+        assert_eq!(about.matches(".card").count(), 1);
+        assert!(home.ends_with("main { padding: 0; }"));
+        assert!(about.ends_with("main { margin: 0; }"));
+    }
+
+    #[test]
+    fn test_render_page_html_format() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            "<template>\n  <div>\n\n\n      <p>{{ title }}</p>\n<pre>  a\n   b</pre>\n  </div>\n</template>".to_string(),
+        );
+        let render = |html_format| {
+            let options = CompileOptions { html_format, ..Default::default() };
+            render_page("index.van", &files, r#"{"title": "Hi"}"#, &options).unwrap().html
+        };
+
+        let minified = render(HtmlFormat::Minify);
+        assert!(minified.contains("<body><div><p>Hi</p><pre>  a\n   b</pre></div>"), "{minified}");
+
+        let pretty = render(HtmlFormat::Pretty);
+        assert!(pretty.contains("\n    <div>\n      <p>Hi</p>\n      <pre>  a\n   b</pre>\n    </div>\n"), "{pretty}");
+
+        assert!(render(HtmlFormat::Preserve).contains("<div>\n\n\n      <p>Hi</p>"));
+    }
+
     #[test]
     fn test_render_single_i18n_basic() {
         let source = r#"
@@ -444,39 +1184,389 @@ const count = ref(0)
         assert!(html.contains("V.signal(0)"));
     }
 
-    // ── Compile tests (no data) ──
+    fn env_options(pairs: &[(&str, &str)]) -> CompileOptions {
+        CompileOptions {
+            env: pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..CompileOptions::default()
+        }
+    }
 
     #[test]
-    fn test_compile_preserves_v_for() {
-        let source = r#"
+    fn test_render_page_env_in_template() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            r#"
 <template>
-  <ul><li v-for="item in items">{{ item.name }}</li></ul>
+  <h1>{{ env.VAN_SITE_NAME }}</h1>
+  <p>{{ title }}</p>
 </template>
-"#;
-        let html = compile_single(source).unwrap();
-        assert!(html.contains("v-for=\"item in items\""), "v-for should be preserved in compile mode");
-        assert!(html.contains("{{item.name}}"), "{{ }} should be preserved in compile mode");
+"#
+            .to_string(),
+        );
+        let options = env_options(&[("VAN_SITE_NAME", "Tom & Jerry")]);
+        let page = render_page("index.van", &files, r#"{"title": "Hi"}"#, &options).unwrap();
+        assert!(page.html.contains("<h1>Tom &amp; Jerry</h1>"));
+        assert!(page.warnings.is_empty());
+
+        // Compile mode substitutes env too, while model bindings stay.
+        let page = compile_page("index.van", &files, &options).unwrap();
+        assert!(page.html.contains("<h1>Tom &amp; Jerry</h1>"));
+        assert!(page.html.contains("{{title}}"));
     }
 
     #[test]
-    fn test_compile_preserves_v_if() {
+    fn test_render_page_env_in_script() {
         let source = r#"
 <template>
-  <div v-if="visible">content</div>
+  <button @click="load">{{ count }}</button>
 </template>
+
+<script setup>
+const count = ref(0)
+function load() { fetch(import.meta.env.VAN_API_URL) }
+</script>
 "#;
-        let html = compile_single(source).unwrap();
-        assert!(html.contains("v-if=\"visible\""), "v-if should be preserved in compile mode");
+        let mut files = HashMap::new();
+        files.insert("main.van".to_string(), source.to_string());
+        let options = env_options(&[("VAN_API_URL", "https://api.example.com")]);
+        let page = render_page("main.van", &files, "{}", &options).unwrap();
+        assert!(page.html.contains(r#"fetch("https://api.example.com")"#));
+        assert!(!page.html.contains("import.meta.env"));
     }
 
     #[test]
-    fn test_compile_preserves_class_binding() {
-        let source = r#"
+    fn test_render_page_missing_env_warns() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            r#"
 <template>
-  <span :class="{ 'active': isActive }">text</span>
+  <card />
 </template>
-"#;
-        let html = compile_single(source).unwrap();
+
+<script setup>
+import Card from './card.van'
+</script>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "card.van".to_string(),
+            "<template><p>[{{ env.VAN_MISSING }}]</p></template>".to_string(),
+        );
+        let page = render_page("index.van", &files, r#"{"x": 1}"#, &CompileOptions::default())
+            .unwrap();
+        assert!(page.html.contains("<p>[]</p>"));
+        assert_eq!(
+            page.warnings,
+            vec!["card.van: environment variable VAN_MISSING is not set".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_define_options_ssr_interpolation_off() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            "<template><h1>{{ title }}</h1><widget /></template>\n<script setup>\nimport Widget from './widget.van'\n</script>".to_string(),
+        );
+        files.insert(
+            "widget.van".to_string(),
+            "<template><div id=\"app\" :title=\"x\">{{ message }} {{ title }}</div></template>\n<script setup>\ndefineOptions({ ssrInterpolation: false })\n</script>".to_string(),
+        );
+        let data = r#"{"title": "Home", "message": "server"}"#;
+        let page = render_page("index.van", &files, data, &CompileOptions::default()).unwrap();
+        assert!(page.html.contains("<h1>Home</h1>"));
+        assert!(page.html.contains("&#123;&#123; message }} &#123;&#123; title }}</div>"), "{}", page.html);
+        let compiled = compile_page("index.van", &files, &CompileOptions::default()).unwrap();
+        assert!(compiled.html.contains("<h1>{{title}}</h1>"));
+        assert!(compiled.html.contains("&#123;&#123; message }}"));
+    }
+
+    #[test]
+    fn test_strict_js() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            "<template><p>{{ total }}</p></template>\n\n<script setup>\nimport { run } from './utils/run.ts'\n// evaluate(total) on the server instead\nconst total = ref(run('1 + 1'))\n</script>".to_string(),
+        );
+        files.insert(
+            "utils/run.ts".to_string(),
+            "/** Evaluate a formula. */\nexport function run(src: string): number {\n  return eval(src)\n}\n".to_string(),
+        );
+        let page = compile_page("index.van", &files, &CompileOptions::default()).unwrap();
+        assert_eq!(page.warnings, vec!["utils/run.ts:3: script uses eval()".to_string()]);
+
+        let strict = CompileOptions { strict_js: true, ..Default::default() };
+        let err = compile_page("index.van", &files, &strict).unwrap_err();
+        assert_eq!(err, PageError::Invalid("utils/run.ts:3: eval() is not allowed (strict_js)".to_string()));
+
+        files.insert("utils/run.ts".to_string(), "export const run = (src: string) => Number(src)\n".to_string());
+        assert!(compile_page("index.van", &files, &strict).is_ok());
+        files.insert(
+            "index.van".to_string(),
+            "<template><a :href=\"link\">Go</a></template>\n<script setup>\nconst link = ref('#')\nlink.value = 'javascript:void(0)'\n</script>".to_string(),
+        );
+        let err = compile_page("index.van", &files, &strict).unwrap_err();
+        assert_eq!(err, PageError::Invalid("index.van:4: a javascript: URL is not allowed (strict_js)".to_string()));
+    }
+
+    #[test]
+    fn test_data_usage() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><h1>{{ title }}</h1><ul><li v-for=\"post in posts\">{{ post.name }}</li></ul><price-tag :amount=\"price\" /></template>\n<script setup>\nimport PriceTag from '../components/price-tag.van'\n</script>".to_string(),
+        );
+        files.insert(
+            "components/price-tag.van".to_string(),
+            "<template><span>{{ amount }} {{ currency }}</span></template>".to_string(),
+        );
+        let data = r#"{"title": "Shop", "posts": [{"name": "a"}], "price": 5, "footer": "unused"}"#;
+        let page = render_page("pages/index.van", &files, data, &CompileOptions::default()).unwrap();
+        assert!(page.html.contains("<li>a</li>") && page.html.contains("<span>5 {{currency}}</span>"), "{}", page.html);
+        assert_eq!(page.data_used, vec!["posts", "price", "title"]);
+        assert_eq!(page.data_missing, vec!["currency"]);
+        assert_eq!(page.warnings, vec![r#"components/price-tag.van: "currency" is not in the page data"#.to_string()]);
+        let data_value: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(unused_keys(&data_value, &page.data_used), vec!["footer"]);
+
+        let assets = render_page_assets("pages/index.van", &files, data, "/assets", &CompileOptions::default()).unwrap();
+        assert_eq!((assets.data_used, assets.data_missing), (page.data_used, page.data_missing));
+        let compiled = compile_page("pages/index.van", &files, &CompileOptions::default()).unwrap();
+        assert!(compiled.data_used.is_empty() && compiled.data_missing.is_empty() && compiled.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_condition_coercion() {
+        let source = r#"<template><p v-show="stock > 0">In stock</p><p v-if="rating >= 4.5">Top rated</p><p v-if="delta < -0.5">Falling</p></template>"#;
+        let mut files = HashMap::new();
+        files.insert("index.van".to_string(), source.to_string());
+        let data = r#"{"stock": "3", "rating": 4.25, "delta": -1}"#;
+
+        let page = render_page("index.van", &files, data, &CompileOptions::default()).unwrap();
+        assert!(page.html.contains("<p>In stock</p>"), "{}", page.html);
+        assert!(page.html.contains(r#"<p style="display:none">Top rated</p>"#));
+        assert!(page.html.contains("<p>Falling</p>"));
+        assert!(page.warnings.is_empty());
+
+        let options = CompileOptions { strict_coercion: true, ..Default::default() };
+        let page = render_page("index.van", &files, data, &options).unwrap();
+        assert_eq!(
+            page.warnings,
+            vec!["index.van: `stock > 0`: the string \"3\" is compared as the number 3".to_string()]
+        );
+        let assets = render_page_assets("index.van", &files, data, "/assets", &options).unwrap();
+        assert_eq!(assets.warnings, page.warnings);
+    }
+
+    #[test]
+    fn test_ref_initialized_from_prop() {
+        let collapsible = r#"
+<template>
+  <div><button @click="open = !open">Toggle</button><p v-show="open">Body</p></div>
+</template>
+
+<script setup>
+const props = defineProps({ defaultOpen: { type: Boolean, default: false }, label: String })
+const open = ref(props.defaultOpen)
+const text = ref(props.label)
+</script>
+"#;
+        let page_with = |attrs: &str| {
+            let mut files = HashMap::new();
+            files.insert(
+                "index.van".to_string(),
+                format!("<template><collapsible {attrs} /></template>\n<script setup>\nimport Collapsible from './collapsible.van'\n</script>"),
+            );
+            files.insert("collapsible.van".to_string(), collapsible.to_string());
+            render_page("index.van", &files, r#"{"faq": {"open": true, "label": "FAQ"}}"#, &CompileOptions::default()).unwrap()
+        };
+
+        let page = page_with(r#":defaultOpen="faq.open" :label="faq.label""#);
+        assert!(page.html.contains("signal(true)"), "{}", page.html);
+        assert!(page.html.contains(r#"signal("FAQ")"#), "{}", page.html);
+        assert!(!page.html.contains("props."));
+        assert!(page.warnings.is_empty(), "{:?}", page.warnings);
+
+        let page = page_with("");
+        assert!(page.html.contains("signal(false)"), "{}", page.html);
+        assert_eq!(
+            page.warnings,
+            vec![r#"collapsible.van: prop "label" initializes local state but is not passed and has no default"#.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_signal_script_escapes_closing_tag() {
+        // A `<script setup>` block can't hold `</script>` itself, but a module can
+        let source = "<template><p>{{ snippet }}</p><button @click=\"snippet = banner\">Show</button></template>\n<script setup>\nimport { banner } from './banner.js'\nconst snippet = ref('')\n</script>";
+        let files = HashMap::from([
+            ("index.van".to_string(), source.to_string()),
+            ("banner.js".to_string(), "export const banner = '<b>hi</b></script><script>alert(1)</SCRIPT><!--'".to_string()),
+        ]);
+        let html = render_page("index.van", &files, "{}", &CompileOptions::default()).unwrap().html;
+        assert!(html.contains(r#"'<b>hi</b><\/script><script>alert(1)<\/SCRIPT><\x21--'"#), "{html}");
+        // The runtime, the signal script and nothing else
+        assert_eq!(html.to_lowercase().matches("</script>").count(), 2, "{html}");
+    }
+
+    #[test]
+    fn test_props_object_in_signal_script() {
+        let share = r#"
+<template>
+  <button @click="share">Share ({{ count }})</button>
+</template>
+
+<script setup>
+const props = defineProps({ title: String, meta: Object })
+const count = ref(0)
+function share() { count.value++; navigator.share({ title: props.title, url: props.meta.url }) }
+</script>
+"#;
+        let files = HashMap::from([
+            (
+                "index.van".to_string(),
+                "<template><share-button :title=\"post.title\" :meta=\"post.meta\" /></template>\n<script setup>\nimport ShareButton from './share-button.van'\n</script>".to_string(),
+            ),
+            ("share-button.van".to_string(), share.to_string()),
+        ]);
+        let data = r#"{"post": {"title": "Hello </script>", "meta": {"url": "/posts/hello"}}}"#;
+        let page = render_page("index.van", &files, data, &CompileOptions::default()).unwrap();
+        assert!(
+            page.html.contains(r#"var props = {"meta":{"url":"/posts/hello"},"title":"Hello \u003c/script>"};"#),
+            "{}",
+            page.html
+        );
+        assert!(page.html.contains("navigator.share({ title: props.title, url: props.meta.url })"));
+        assert!(!page.html.contains("defineProps"));
+    }
+
+    #[test]
+    fn test_script_constants_interpolated() {
+        let source = r#"
+<template>
+  <h1>{{ siteName }}</h1>
+  <nav><a v-for="link in links" href="{{ link.href }}">{{ link.label }}</a></nav>
+  <ul><li v-for="item in items">{{ item }}</li></ul>
+  <button @click="count++">{{ count }}</button>
+</template>
+<script setup>
+const siteName = 'Acme'
+const links = [{ href: '/', label: 'Home' }, { href: '/about', label: 'About' }]
+const count = ref(0)
+</script>
+"#;
+        let html = render_single(source, r#"{"items": ["a"]}"#).unwrap();
+        assert!(html.contains("<h1>Acme</h1>"), "{html}");
+        assert!(html.contains(r#"<nav><a href="/">Home</a><a href="/about">About</a></nav>"#), "{html}");
+        assert!(html.contains("<li>a</li>"), "{html}");
+        assert!(!html.contains("siteName.value") && !html.contains("links.value"), "constants are not signals: {html}");
+
+        let html = render_single(source, r#"{"siteName": "From data"}"#).unwrap();
+        assert!(html.contains("<h1>From data</h1>"), "data wins: {html}");
+
+        let html = compile_single(source).unwrap();
+        assert!(html.contains("<h1>Acme</h1>"), "{html}");
+        assert!(html.contains(r#"<a href="/about">About</a>"#), "{html}");
+        assert!(html.contains(r#"v-for="item in items""#), "model loops stay for Java: {html}");
+    }
+
+    #[test]
+    fn test_v_raw_keeps_foreign_mustaches() {
+        let source = r#"
+<template>
+  <h1>{{ title }}</h1>
+  <script type="text/x-handlebars-template" id="row" v-raw>{{#each items}}<li>{{name}}</li>{{/each}}</script>
+  <div class="widget" v-raw><p>{{ title }} {{{ body }}}</p><Card /></div>
+  <button @click="count++">{{ count }}</button>
+</template>
+<script setup>
+import Card from './card.van'
+const count = ref(0)
+</script>
+"#;
+        let mut files = HashMap::new();
+        files.insert("index.van".to_string(), source.to_string());
+        files.insert("card.van".to_string(), "<template><p>card</p></template>".to_string());
+        let page = render_page("index.van", &files, r#"{"title": "Hi", "items": []}"#, &CompileOptions::default()).unwrap();
+        let html = page.html;
+        assert!(page.warnings.is_empty(), "{:?}", page.warnings);
+        assert!(html.contains("<h1>Hi</h1>"), "{html}");
+        assert!(
+            html.contains(r#"<script type="text/x-handlebars-template" id="row">{{#each items}}<li>{{name}}</li>{{/each}}</script>"#),
+            "{html}"
+        );
+        assert!(html.contains(r#"<div class="widget"><p>{{ title }} {{{ body }}}</p><Card /></div>"#), "{html}");
+        assert!(html.contains("<!--v:3--><button>0</button>"), "{html}");
+        assert!(!html.contains("v-raw"), "{html}");
+
+        let page = compile_page("index.van", &files, &CompileOptions::default()).unwrap();
+        assert!(page.warnings.is_empty(), "{:?}", page.warnings);
+        assert!(
+            page.html.contains(r#"<div class="widget" v-raw><p>{{ title }} {{{ body }}}</p><Card /></div>"#),
+            "compile output keeps v-raw for the Java runtime: {}",
+            page.html
+        );
+        assert!(!page.html.contains("<!--v-raw"), "{}", page.html);
+        assert!(page.html.contains("<!--v:3--><button>0</button>"), "{}", page.html);
+    }
+
+    #[test]
+    fn test_v_raw_template_groups_match_across_modes() {
+        let source = r#"<template><div v-raw><template v-if="show"><b>{{ y }}</b></template><template v-for="i in items"><i>{{ i }}</i></template><p v-if="show">x</p></div><p v-if="show">{{ y }}</p></template>"#;
+        let raw = r#"<template v-if="show"><b>{{ y }}</b></template><template v-for="i in items"><i>{{ i }}</i></template><p v-if="show">x</p>"#;
+        let data = r#"{"show": false, "items": [1, 2], "y": "Y"}"#;
+
+        let rendered = render_single(source, data).unwrap();
+        assert!(rendered.contains(&format!("<div>{raw}</div>")), "{rendered}");
+
+        let compiled = compile_single(source).unwrap();
+        assert!(compiled.contains(&format!("<div v-raw>{raw}</div>")), "{compiled}");
+        assert!(compiled.contains(r#"<p v-if="show">{{y}}</p>"#), "{compiled}");
+
+        let filled = render::fill_data(&compiled, &serde_json::from_str(data).unwrap());
+        assert!(filled.contains(&format!("<div>{raw}</div>")), "{filled}");
+        assert!(filled.contains(r#"<p style="display:none">Y</p>"#), "{filled}");
+    }
+
+    // ── Compile tests (no data) ──
+
+    #[test]
+    fn test_compile_preserves_v_for() {
+        let source = r#"
+<template>
+  <ul><li v-for="item in items">{{ item.name }}</li></ul>
+</template>
+"#;
+        let html = compile_single(source).unwrap();
+        assert!(html.contains("v-for=\"item in items\""), "v-for should be preserved in compile mode");
+        assert!(html.contains("{{item.name}}"), "{{ }} should be preserved in compile mode");
+    }
+
+    #[test]
+    fn test_compile_preserves_v_if() {
+        let source = r#"
+<template>
+  <div v-if="visible">content</div>
+</template>
+"#;
+        let html = compile_single(source).unwrap();
+        assert!(html.contains("v-if=\"visible\""), "v-if should be preserved in compile mode");
+    }
+
+    #[test]
+    fn test_compile_preserves_class_binding() {
+        let source = r#"
+<template>
+  <span :class="{ 'active': isActive }">text</span>
+</template>
+"#;
+        let html = compile_single(source).unwrap();
         assert!(html.contains(":class="), ":class should be preserved in compile mode");
     }
 
@@ -571,4 +1661,669 @@ defineProps({ title: String })
         assert!(result.contains("/style.css"), "Output should contain CSS link from Layout. Got:\n{}", result);
         assert!(!result.contains("Van Playground"), "Output should NOT use default shell. Got:\n{}", result);
     }
+
+    #[test]
+    fn test_binding_paths_match_shipped_html() {
+        let templates = [
+            r#"<div><Transition name="fade"><p v-show="open">Hi</p></Transition><button @click="open = !open">Toggle</button></div>"#,
+            r#"<ul><li v-for="item in items">{{ item }}</li></ul><section><TransitionGroup><p v-if="open">A</p></TransitionGroup><span>{{ count }}</span></section>"#,
+            r#"<h1>{{ title }}</h1><ClientOnly><div><h2>Static</h2><p>{{ count }}</p><button @click="count++">+</button></div></ClientOnly>"#,
+            r#"<header><Transition><nav v-show="open"><a href="/">Home</a></nav></Transition></header><main><input v-model="query"><p :class="{ on: open }">{{ query }}</p></main>"#,
+        ];
+        for template in templates {
+            let mut files = HashMap::new();
+            files.insert(
+                "index.van".to_string(),
+                format!("<template>{template}</template>\n<script setup>\nconst open = ref(false)\nconst count = ref(0)\nconst query = ref('')\n</script>"),
+            );
+            let data = r#"{"title": "T", "items": ["a", "b"]}"#;
+            let options = CompileOptions { transition_css: true, ..Default::default() };
+            let page = render_page("index.van", &files, data, &options).unwrap();
+            assert!(page.warnings.is_empty(), "{template}: {:?}", page.warnings);
+            let page = compile_page("index.van", &files, &options).unwrap();
+            assert!(page.warnings.is_empty(), "{template}: {:?}", page.warnings);
+            let assets = render_page_assets("index.van", &files, data, "/assets", &options).unwrap();
+            assert!(assets.warnings.is_empty(), "{template}: {:?}", assets.warnings);
+        }
+    }
+
+    fn fragment_files(page: &str) -> HashMap<String, String> {
+        let mut files = HashMap::new();
+        files.insert("pages/index.van".to_string(), page.to_string());
+        files.insert(
+            "components/layout.van".to_string(),
+            "<template>\n  <html>\n  <head></head>\n  <body><slot /></body>\n  </html>\n</template>".to_string(),
+        );
+        files.insert(
+            "components/split.van".to_string(),
+            r#"
+<template>
+  <header><button @click="count++">+</button></header>
+  <main><p>{{ count }}</p></main>
+</template>
+<script setup>
+const count = ref(0)
+</script>
+<style scoped>
+header { color: red; }
+</style>
+"#
+            .to_string(),
+        );
+        files
+    }
+
+    #[test]
+    fn test_mixed_case_directives_render() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><p V-Show=\"open\">Menu</p><p V-If=\"flag\">Flag</p><p V-Else>No flag</p></template>\n\
+             <script setup>\nconst open = ref(false)\n</script>"
+                .to_string(),
+        );
+        let html = render_to_string("pages/index.van", &files, r#"{"flag": true}"#).unwrap();
+        assert!(html.contains(r#"<p style="display:none">Menu</p>"#), "{html}");
+        assert!(html.contains("<p>Flag</p>"), "{html}");
+        assert!(html.contains(r#"<p style="display:none">No flag</p>"#), "{html}");
+        assert!(!html.to_ascii_lowercase().contains("v-show=") && !html.contains("V-If"), "{html}");
+        assert!(html.contains("style.display = open.value ? '' : 'none'"), "{html}");
+    }
+
+    #[test]
+    fn test_scoped_class_binding_with_gt() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><button :class=\"{ active: count > 1 }\" @click=\"count++\">b</button></template>\n\
+             <script setup>\nconst count = ref(0)\n</script>\n<style scoped>button { color: red; }</style>"
+                .to_string(),
+        );
+        let html = render_to_string("pages/index.van", &files, "{}").unwrap();
+        let id = van_parser::scope_id("button { color: red; }");
+        assert!(html.contains(&format!("<button class=\"{id}\">b</button>")), "{html}");
+        assert!(html.contains("classList.toggle('active', !!(count.value > 1))"), "{html}");
+    }
+
+    #[test]
+    fn test_fragment_component_standalone() {
+        let files = fragment_files(
+            "<template><Split /></template>\n<script setup>\nimport Split from '../components/split.van'\n</script>",
+        );
+        let html = render_to_string("pages/index.van", &files, "{}").unwrap();
+        let id = van_parser::scope_id("header { color: red; }");
+        assert!(html.contains(&format!("<header class=\"{id}\"><!--v:0_0--><button class=\"{id}\">+</button></header>")), "{html}");
+        assert!(html.contains(&format!("<main class=\"{id}\"><!--v:1_0--><p class=\"{id}\">0</p></main>")), "{html}");
+        assert!(html.contains("_ve['0_0'].addEventListener('click'"));
+        assert!(html.contains("_ve['1_0'].textContent = count.value"));
+    }
+
+    #[test]
+    fn test_fragment_component_in_layout() {
+        let files = fragment_files(
+            r#"
+<template>
+  <Layout>
+    <Split class="wide" />
+  </Layout>
+</template>
+<script setup>
+import Layout from '../components/layout.van'
+import Split from '../components/split.van'
+</script>
+"#,
+        );
+        let page = render_page("pages/index.van", &files, "{}", &CompileOptions::default()).unwrap();
+        assert!(page.html.contains("<!--v:0_0--><button"), "{}", page.html);
+        assert!(page.html.contains("<!--v:1_0--><p"), "{}", page.html);
+        assert!(!page.html.contains("wide"));
+        assert_eq!(
+            page.warnings,
+            vec!["pages/index.van -> components/split.van: attributes not applied (class): component renders 2 root nodes"]
+        );
+    }
+
+    #[test]
+    fn test_text_only_component_in_layout() {
+        let mut files = fragment_files(
+            r#"
+<template>
+  <Layout><Split /><Hello /></Layout>
+</template>
+<script setup>
+import Layout from '../components/layout.van'
+import Split from '../components/split.van'
+import Hello from '../components/hello.van'
+</script>
+"#,
+        );
+        files.insert("components/hello.van".to_string(), "<template>Hello {{ name }}</template>".to_string());
+        let html = render_to_string("pages/index.van", &files, r#"{"name": "Van"}"#).unwrap();
+        // Scripts are injected after the trailing text, not in front of it
+        let text = html.find("Hello Van").unwrap();
+        assert!(text < html.find("<script>").unwrap(), "{html}");
+        assert!(html.contains("</script>\n</body>"), "{html}");
+    }
+
+    #[test]
+    fn test_layout_missing_doctype_and_metas() {
+        let mut files = HashMap::new();
+        files.insert("pages/index.van".to_string(), r#"
+<template>
+  <html lang="de">
+  <head>
+    <title>Start</title>
+  </head>
+  <body><header>Top</header><h1>Hallo</h1></body>
+  </html>
+</template>
+"#.to_string());
+
+        let result = render_to_string("pages/index.van", &files, "{}").unwrap();
+        assert!(result.starts_with("<!DOCTYPE html>\n"), "Got:\n{result}");
+        let head = &result[result.find("<head>").unwrap()..result.find("</head>").unwrap()];
+        assert!(head.contains(r#"<meta charset="UTF-8" />"#), "Got:\n{result}");
+        assert!(head.contains(r#"<meta name="viewport""#), "Got:\n{result}");
+        assert!(head.contains("<title>Start</title>"));
+        assert!(!result.contains("Van App"));
+    }
+
+    #[test]
+    fn test_layout_existing_head_tags_kept() {
+        let mut files = HashMap::new();
+        files.insert("pages/index.van".to_string(), r#"
+<template>
+  <!DOCTYPE html>
+  <html>
+  <head><meta charset="utf-8"><meta name="viewport" content="width=1024"></head>
+  <body><p>Hi</p></body>
+  </html>
+</template>
+"#.to_string());
+
+        let result = render_to_string("pages/index.van", &files, "{}").unwrap();
+        assert_eq!(result.matches("<!DOCTYPE").count(), 1);
+        assert_eq!(result.to_ascii_lowercase().matches("<meta charset").count(), 1);
+        assert_eq!(result.matches("viewport").count(), 1);
+    }
+
+    #[test]
+    fn test_code_sample_with_html_tag_uses_default_shell() {
+        let mut files = HashMap::new();
+        files.insert("pages/index.van".to_string(), r#"
+<template>
+  <h1>Layouts</h1>
+  <pre><code><html lang="en"></code></pre>
+</template>
+"#.to_string());
+
+        let result = render_to_string("pages/index.van", &files, "{}").unwrap();
+        assert!(result.starts_with("<!DOCTYPE html>"));
+        assert!(result.contains("<title>Van App</title>"), "Got:\n{result}");
+        assert!(result.contains(r#"<pre><code><html lang="en"></code></pre>"#));
+    }
+
+    #[test]
+    fn test_attribute_mustaches() {
+        let mut files = HashMap::new();
+        files.insert("index.van".to_string(), r#"
+<template>
+  <a href="/users/{{ user.id }}" title="{{{ note }}}">Profile</a>
+  <img src="/img/{{ id }}.png" alt="{{ caption }} #{{ id + 1 }}">
+  <button @click="id++">Next</button>
+</template>
+<script setup>
+const id = ref(5)
+</script>
+"#.to_string());
+        let data = r#"{"user": {"id": 7}, "caption": "Fish & Chips", "note": "\"><script>"}"#;
+        let html = render_page("index.van", &files, data, &CompileOptions::default()).unwrap().html;
+
+        assert!(html.contains(r#"<a href="/users/7" title="&quot;&gt;&lt;script&gt;">"#), "Got:\n{html}");
+        // Signal with a plain initial value is filled in; the expression is left to the client
+        assert!(html.contains(r#"<img src="/img/5.png" alt="Fish &amp; Chips #">"#), "Got:\n{html}");
+        assert!(html.contains("setAttribute('src', '/img/' + id.value + '.png')"), "Got:\n{html}");
+        assert!(html.contains("setAttribute('alt', 'Fish & Chips #' + (id.value + 1))"), "Got:\n{html}");
+        assert!(!html.contains("setAttribute('href'"));
+    }
+
+    #[test]
+    fn test_attribute_values_escaped() {
+        let mut files = HashMap::new();
+        files.insert("index.van".to_string(), r#"
+<template>
+  <Card title="{{ post.title }}" :heading="post.title" />
+  <a href={{ post.url }}>Read</a>
+</template>
+<script setup>
+import Card from './card.van'
+</script>
+"#.to_string());
+        files.insert("card.van".to_string(), r#"
+<template>
+  <div class="card"><h2>{{ heading }}</h2></div>
+</template>
+<script setup>
+defineProps({ heading: String })
+</script>
+"#.to_string());
+        let data = r#"{"post": {"title": "say \"hi\" <b>", "url": "/x onmouseover=alert(1)"}}"#;
+        let html = render_page("index.van", &files, data, &CompileOptions::default()).unwrap().html;
+
+        assert!(
+            html.contains(r#"<div class="card" title="say &quot;hi&quot; &lt;b&gt;"><h2>say &quot;hi&quot; &lt;b&gt;</h2></div>"#),
+            "Got:\n{html}"
+        );
+        assert!(html.contains(r#"<a href="/x onmouseover=alert(1)">Read</a>"#), "Got:\n{html}");
+    }
+
+    fn raw_html_files() -> HashMap<String, String> {
+        let mut files = HashMap::new();
+        files.insert("index.van".to_string(), r#"
+<template>
+  <article>{{{ post.body }}}</article>
+  <div v-html="preview"></div>
+</template>
+<script setup>
+const preview = ref('<b>hi</b>')
+</script>
+"#.to_string());
+        files
+    }
+
+    const RAW_HTML_DATA: &str = r#"{"post": {"body": "<p onclick=\"steal()\">Hi</p><script>alert(1)</script>"}}"#;
+
+    #[test]
+    fn test_sanitize_html_option() {
+        let options = CompileOptions { sanitize_html: true, ..Default::default() };
+        let page = render_page("index.van", &raw_html_files(), RAW_HTML_DATA, &options).unwrap();
+
+        assert!(page.html.contains("<article><p>Hi</p></article>"), "Got:\n{}", page.html);
+        assert!(page.html.contains(".innerHTML = V.sanitize(preview.value);"), "Got:\n{}", page.html);
+        assert!(page.html.contains("function sanitize(html)"));
+        assert!(page.warnings.is_empty(), "{:?}", page.warnings);
+    }
+
+    #[test]
+    fn test_template_groups_render_without_wrapper() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            r#"<template><nav><template v-if="loggedIn"><a>Profile</a><a>Logout</a></template><a>Help</a></nav><dl><template v-for="t in terms"><dt>{{ t.name }}</dt><dd>{{ t.text }}</dd></template></dl></template>"#.to_string(),
+        );
+        let data = r#"{"loggedIn": false, "terms": [{"name": "a", "text": "1"}, {"name": "b", "text": "2"}]}"#;
+        let html = render_to_string("index.van", &files, data).unwrap();
+        assert!(!html.contains("<template"), "Got:\n{html}");
+        assert!(html.contains(r#"<nav><!--v:0_0--><a style="display:none">Profile</a><!--v:0_1--><a style="display:none">Logout</a><a>Help</a></nav>"#), "Got:\n{html}");
+        assert!(html.contains("<dl><dt>a</dt><dd>1</dd><dt>b</dt><dd>2</dd></dl>"), "Got:\n{html}");
+    }
+
+    #[test]
+    fn test_v_cloak_kept_until_uncloaked() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            "<template><div v-cloak><p v-show=\"open\">Menu</p></div></template>\n<script setup>\nconst open = ref(true)\n</script>".to_string(),
+        );
+        let html = render_to_string("index.van", &files, "{}").unwrap();
+        assert!(html.contains("<div v-cloak>"), "Got:\n{html}");
+        assert!(html.contains("<style>[v-cloak] { display: none; }</style>"));
+        assert!(html.contains(".removeAttribute('v-cloak');"));
+
+        // Without a signal script nothing would uncloak it, so no rule hides it
+        files.insert("index.van".to_string(), "<template><div v-cloak>Static</div></template>".to_string());
+        let html = render_to_string("index.van", &files, "{}").unwrap();
+        assert!(!html.contains("[v-cloak]"));
+    }
+
+    #[test]
+    fn test_emit_teardown_option() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><button @click=\"inc\">{{ n }}</button></template>\n<script setup>\nconst n = ref(0)\nfunction inc() { n.value++ }\n</script>".to_string(),
+        );
+        let options = CompileOptions { emit_teardown: true, ..Default::default() };
+        let page = compile_page("pages/index.van", &files, &options).unwrap();
+        assert!(page.html.contains("window.__van_pages[\"pages/index\"] = {"), "Got:\n{}", page.html);
+        assert!(page.html.contains(", 'click', inc);"));
+        assert!(!page.html.contains(".addEventListener('click'"));
+
+        let page = compile_page("pages/index.van", &files, &CompileOptions::default()).unwrap();
+        assert!(!page.html.contains("__van_pages"));
+    }
+
+    fn layout_files(page: &str) -> HashMap<String, String> {
+        let mut files = HashMap::new();
+        files.insert(
+            "layouts/default.van".to_string(),
+            "<template><div class=\"default\"><slot /></div></template>".to_string(),
+        );
+        files.insert(
+            "layouts/blank.van".to_string(),
+            "<template><div class=\"blank\"><slot /></div></template>".to_string(),
+        );
+        files.insert("pages/blog/post.van".to_string(), page.to_string());
+        files
+    }
+
+    fn render_with_auto_layout(page: &str) -> String {
+        let options = CompileOptions { auto_layout: Some("layouts/default.van".to_string()), ..Default::default() };
+        render_page("pages/blog/post.van", &layout_files(page), "{}", &options).unwrap().html
+    }
+
+    #[test]
+    fn test_auto_layout_wraps_page() {
+        let page = "<template><p>{{ msg }}</p></template>\n<script setup>\nconst msg = ref('Hi')\n</script>";
+        let html = render_with_auto_layout(page);
+        assert!(html.contains("<div class=\"default\"><!--v:0_0--><p>Hi</p></div>"), "{html}");
+
+        let html = render_with_auto_layout("<template><p>Bare</p></template>");
+        assert!(html.contains("<div class=\"default\"><p>Bare</p></div>"), "{html}");
+
+        let html = render_page("pages/blog/post.van", &layout_files(page), "{}", &CompileOptions::default()).unwrap().html;
+        assert!(!html.contains("class=\"default\""), "off unless configured");
+    }
+
+    #[test]
+    fn test_auto_layout_opt_out_and_override() {
+        let html = render_with_auto_layout(
+            "<template><p>Own</p></template>\n<script setup>\ndefineOptions({ layout: false })\n</script>",
+        );
+        assert!(!html.contains("class=\"default\""), "{html}");
+
+        let html = render_with_auto_layout(
+            "<template><p>Other</p></template>\n<script setup>\ndefineOptions({ layout: 'layouts/blank.van' })\n</script>",
+        );
+        assert!(html.contains("<div class=\"blank\"><p>Other</p></div>"), "{html}");
+        assert!(!html.contains("class=\"default\""));
+
+        let html = render_with_auto_layout(
+            "<template><BlankLayout><p>Manual</p></BlankLayout></template>\n<script setup>\nimport BlankLayout from '../../layouts/blank.van'\n</script>",
+        );
+        assert!(html.contains("<div class=\"blank\"><p>Manual</p></div>"), "{html}");
+        assert!(!html.contains("class=\"default\""), "already wrapped");
+    }
+
+    #[test]
+    fn test_auto_layout_named_slots_from_page() {
+        let mut files = layout_files("");
+        files.insert(
+            "layouts/default.van".to_string(),
+            "<template><aside><slot name=\"sidebar\">Default nav</slot></aside><main><slot>Empty</slot></main></template>"
+                .to_string(),
+        );
+        let options = CompileOptions { auto_layout: Some("layouts/default.van".to_string()), ..Default::default() };
+        let render = |files: &HashMap<String, String>| {
+            render_page("pages/blog/post.van", files, r#"{"title": "Intro"}"#, &options).unwrap().html
+        };
+
+        files.insert(
+            "pages/blog/post.van".to_string(),
+            "<template>\n  <template #sidebar><nav>Docs nav</nav></template>\n  <h1>{{ title }}</h1>\n</template>".to_string(),
+        );
+        let html = render(&files);
+        assert!(html.contains("<aside><nav>Docs nav</nav></aside>"), "{html}");
+        assert!(html.contains("<main><h1>Intro</h1></main>"), "{html}");
+        assert!(!html.contains("Default nav"));
+        let compiled = compile_page("pages/blog/post.van", &files, &options).unwrap().html;
+        assert!(compiled.contains("<aside><nav>Docs nav</nav></aside><main><h1>{{title}}</h1></main>"), "{compiled}");
+
+        files.insert("pages/blog/post.van".to_string(), "<template><h1>{{ title }}</h1></template>".to_string());
+        let html = render(&files);
+        assert!(html.contains("<aside>Default nav</aside>"), "fallback: {html}");
+        assert!(html.contains("<main><h1>Intro</h1></main>"), "{html}");
+    }
+
+    #[test]
+    fn test_teleport_moves_to_body_end() {
+        let mut files = fragment_files(
+            "<template><Layout><Card /></Layout></template>\n<script setup>\nimport Layout from '../components/layout.van'\nimport Card from '../components/card.van'\n</script>",
+        );
+        files.insert(
+            "components/card.van".to_string(),
+            "<template><section class=\"card\"><div><Modal /></div></section></template>\n<script setup>\nimport Modal from './modal.van'\n</script>".to_string(),
+        );
+        files.insert(
+            "components/modal.van".to_string(),
+            r#"
+<template>
+  <button @click="open = true">Open</button>
+  <Teleport to="body"><div class="modal" v-show="open">Hi</div></Teleport>
+</template>
+<script setup>
+const open = ref(false)
+</script>
+<style scoped>
+.modal { position: fixed; }
+</style>
+"#
+            .to_string(),
+        );
+        let options = CompileOptions::default();
+        let page = render_page("pages/index.van", &files, "{}", &options).unwrap();
+        let html = page.html;
+        assert!(page.warnings.is_empty(), "{:?}", page.warnings);
+        assert!(!html.contains("eleport"), "{html}");
+        let body_end = html.find("</body>").unwrap();
+        let modal = html.find("<div class=\"modal").unwrap();
+        assert!(html.find("</section>").unwrap() < modal && modal < body_end, "{html}");
+        let scope = html.split(".modal.").nth(1).and_then(|rest| rest.split(' ').next()).unwrap();
+        assert!(html[modal..].starts_with(&format!("<div class=\"modal {scope}\"")), "scoped class kept: {html}");
+        assert!(html[..modal].ends_with("</section><!--v:1-->"), "direct body child, anchored: {html}");
+        assert!(html.contains(".style.display"), "{html}");
+
+        let page = compile_page("pages/index.van", &files, &options).unwrap();
+        assert!(page.warnings.is_empty(), "{:?}", page.warnings);
+        let assets = render_page_assets("pages/index.van", &files, "{}", "/assets", &options).unwrap();
+        assert!(assets.warnings.is_empty(), "{:?}", assets.warnings);
+    }
+
+    #[test]
+    fn test_global_css_comes_first() {
+        let mut files = fragment_files(
+            "<template><Layout><Split /></Layout></template>\n<script setup>\nimport Layout from '../components/layout.van'\nimport Split from '../components/split.van'\n</script>",
+        );
+        files.insert("styles/global.css".to_string(), ":root { --brand: teal; }".to_string());
+        let options = CompileOptions { global_css: vec!["styles/global.css".to_string()], ..Default::default() };
+
+        let page = render_page("pages/index.van", &files, "{}", &options).unwrap();
+        let global = page.html.find("--brand: teal").unwrap();
+        assert!(global < page.html.find("color: red").unwrap(), "{}", page.html);
+        assert!(page.dependencies.contains(&"styles/global.css".to_string()));
+
+        let assets = render_page_assets("pages/index.van", &files, "{}", "/assets", &options).unwrap();
+        let global_path = assets.assets.keys().find(|k| k.starts_with("/assets/css/global.")).unwrap();
+        assert_eq!(assets.assets[global_path], ":root { --brand: teal; }");
+        let page_path = assets.assets.keys().find(|k| k.starts_with("/assets/css/pages/index.")).unwrap();
+        assert!(!assets.assets[page_path].contains("--brand"));
+        assert!(assets.html.find(global_path.as_str()).unwrap() < assets.html.find(page_path.as_str()).unwrap());
+
+        let options = CompileOptions { global_css: vec!["styles/missing.css".to_string()], ..Default::default() };
+        let page = render_page("pages/index.van", &files, "{}", &options).unwrap();
+        assert!(page.warnings.iter().any(|w| w.contains("styles/missing.css")), "{:?}", page.warnings);
+    }
+
+    #[test]
+    fn test_debug_binding_map() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            "<template><div><p>{{ count }}</p><button @click=\"count++\">+</button><span v-show=\"count > 1\">many</span></div></template>\n<script setup>\nconst count = ref(0)\n</script>".to_string(),
+        );
+        let block_start = r#"<script type="application/json" id="__van_bindings">"#;
+        let options = CompileOptions { debug: true, ..Default::default() };
+        let html = render_page("index.van", &files, "{}", &options).unwrap().html;
+        let start = html.find(block_start).unwrap() + block_start.len();
+        let json = &html[start..start + html[start..].find("</script>").unwrap()];
+        assert!(!json.contains('<'), "{json}");
+        let map: serde_json::Value = serde_json::from_str(json).unwrap();
+        let bindings = &map["bindings"];
+        assert_eq!(bindings["texts"].as_array().unwrap().len(), 1);
+        assert_eq!(bindings["events"][0]["path"], serde_json::json!([0, 1]));
+        assert_eq!(bindings["shows"].as_array().unwrap().len(), 1);
+        assert_eq!(map["anchors"].as_array().unwrap().len(), 3);
+        assert_eq!(map["anchors"][0]["anchor"], "<!--v:0_0-->");
+
+        let html = render_page("index.van", &files, "{}", &CompileOptions::default()).unwrap().html;
+        assert!(!html.contains("__van_bindings"));
+    }
+
+    #[test]
+    fn test_debug_element_ids() {
+        let files = HashMap::from([
+            (
+                "index.van".to_string(),
+                "<template><main><h1>Hi</h1><counter /></main></template>\n<script setup>\nimport Counter from './counter.van'\n</script>".to_string(),
+            ),
+            (
+                "counter.van".to_string(),
+                "<template><div class=\"counter\"><p>{{ count }}</p><button @click=\"count++\">+</button></div></template>\n<script setup>\nconst count = ref(0)\n</script>".to_string(),
+            ),
+        ]);
+        let script = |html: &str| html[html.rfind("<script>").unwrap()..].to_string();
+        let options = CompileOptions { debug: true, ..Default::default() };
+        let debug = render_page("index.van", &files, "{}", &options).unwrap().html;
+        assert!(debug.contains(r#"<div data-van-component="counter.van" class="counter">"#), "{debug}");
+        assert!(debug.contains(r#"<p data-van-path="0.1.0">"#), "{debug}");
+        assert!(debug.contains(r#"<button data-van-path="0.1.1">"#), "{debug}");
+        assert_eq!(render_page("index.van", &files, "{}", &options).unwrap().html, debug);
+
+        let plain = render_page("index.van", &files, "{}", &CompileOptions::default()).unwrap().html;
+        assert!(!plain.contains("data-van-path") && !plain.contains("data-van-component"), "{plain}");
+        assert_eq!(script(&plain), script(&debug));
+    }
+
+    #[test]
+    fn test_page_timings() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><p>{{ n }}</p></template>\n<script setup>\nconst n = ref(0)\n</script>".to_string(),
+        );
+        let page = render_page("pages/index.van", &files, "{}", &CompileOptions::default()).unwrap();
+        let assets = render_page_assets("pages/index.van", &files, "{}", "/assets", &CompileOptions::default()).unwrap();
+        for t in [page.timings, assets.timings] {
+            for ms in [t.parse_ms, t.resolve_ms, t.signal_gen_ms, t.render_ms] {
+                assert!(ms >= 0.0 && ms <= t.total_ms, "{t:?}");
+            }
+            assert!(t.total_ms > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_raw_html_warnings_without_sanitize() {
+        let page = render_page("index.van", &raw_html_files(), RAW_HTML_DATA, &CompileOptions::default()).unwrap();
+
+        assert!(page.html.contains(r#"<article><p onclick="steal()">Hi</p><script>alert(1)</script></article>"#));
+        assert!(page.html.contains(".innerHTML = preview.value;"));
+        assert_eq!(
+            page.warnings,
+            vec![
+                r#"index.van: v-html="preview" inserts unsanitized HTML"#.to_string(),
+                "index.van: {{{ post.body }}} inserts unsanitized HTML".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_competing_content_bindings_warn() {
+        let mut files = HashMap::new();
+        files.insert("index.van".to_string(), r#"
+<template>
+  <p v-text="msg">{{ msg }}</p>
+</template>
+<script setup>
+const msg = ref('hi')
+</script>
+"#.to_string());
+        let page = compile_page("index.van", &files, &CompileOptions::default()).unwrap();
+        assert_eq!(
+            page.warnings,
+            vec![r#"index.van: <p> v-text="msg" and {{ msg }} both set its text; the interpolation is ignored"#.to_string()]
+        );
+        assert_eq!(page.html.matches("_ve['0'].textContent").count(), 1, "Got:\n{}", page.html);
+    }
+
+    #[test]
+    fn test_store_shared_across_pages() {
+        let mut files = HashMap::new();
+        files.insert("stores/cart.ts".to_string(), r#"export const cart = defineStore('cart', () => {
+  const count: number = ref(2)
+  function add() { count.value++ }
+  return { count, add }
+})
+"#.to_string());
+        files.insert("components/site-header.van".to_string(), r#"
+<template>
+  <header><span class="badge">{{ cart.count }}</span><button @click="cart.add()">Add</button></header>
+</template>
+<script setup lang="ts">
+import { cart } from '../stores/cart.ts'
+</script>
+"#.to_string());
+        for page in ["pages/index.van", "pages/checkout.van"] {
+            files.insert(page.to_string(), r#"
+<template>
+  <site-header />
+  <main>{{ title }}</main>
+</template>
+<script setup>
+import SiteHeader from '../components/site-header.van'
+</script>
+"#.to_string());
+        }
+        for page in ["pages/index.van", "pages/checkout.van"] {
+            let html = render_to_string(page, &files, r#"{"title": "Shop"}"#).unwrap();
+            assert!(html.contains(">2</span>"), "Got:\n{html}");
+            assert!(html.contains(">Shop</main>"));
+            assert!(html.contains("var defineStore = V.store, ref = V.signal, computed = V.computed;"));
+            assert!(html.contains("var cart = __mod_0.cart;"));
+            assert!(html.contains("textContent = cart.count.value"), "Got:\n{html}");
+        }
+    }
+
+    #[test]
+    fn test_transition_css() {
+        let page = |style: &str| {
+            let mut files = HashMap::new();
+            files.insert("index.van".to_string(), format!(r#"
+<template>
+  <ClientOnly><div>
+    <Transition name="fade" :duration="{{ enter: 200, leave: 400 }}"><p v-show="open">Hi</p></Transition>
+    <Transition name="slide"><aside v-show="open">Menu</aside></Transition>
+    <button @click="open = !open">Toggle</button>
+  </div></ClientOnly>
+</template>
+<script setup>
+const open = ref(false)
+</script>
+<style>{style}</style>
+"#));
+            files
+        };
+        let files = page(".slide-enter-active { transition: transform 1s; }");
+
+        let page_out = render_page("index.van", &files, "{}", &CompileOptions::default()).unwrap();
+        assert_eq!(
+            page_out.warnings,
+            vec![r#"index.van: <Transition name="fade"> has no .fade-enter-active style, so it does not animate (transition_css generates a fade)"#.to_string()]
+        );
+        assert!(!page_out.html.contains(".fade-enter-active"));
+
+        let options = CompileOptions { transition_css: true, ..Default::default() };
+        let page_out = render_page("index.van", &files, "{}", &options).unwrap();
+        assert!(page_out.warnings.is_empty(), "{:?}", page_out.warnings);
+        assert!(page_out.html.contains(".fade-enter-active { transition: opacity 200ms ease; }\n.fade-leave-active { transition: opacity 400ms ease; }\n.fade-enter-from, .fade-leave-to { opacity: 0; }"));
+        assert!(!page_out.html.contains(".slide-enter-from"), "defined by the page, not generated");
+
+        let assets = render_page_assets("index.van", &files, "{}", "/assets", &options).unwrap();
+        assert!(assets.assets.values().any(|css| css.contains(".fade-leave-active { transition: opacity 400ms ease; }")));
+        assert!(!assets.assets.values().any(|css| css.contains(".slide-enter-from")));
+
+        let styled = page(".fade-enter-active, .slide-enter-active { transition: opacity 1s; }");
+        let page_out = render_page("index.van", &styled, "{}", &options).unwrap();
+        assert!(!page_out.html.contains("opacity: 0"));
+        assert!(render_page("index.van", &styled, "{}", &CompileOptions::default()).unwrap().warnings.is_empty());
+    }
 }
@@ -0,0 +1,124 @@
+use pulldown_cmark::{html, Options, Parser};
+use serde_json::{Map, Value};
+
+/// A Markdown content file split into its front-matter and rendered body.
+#[derive(Debug, PartialEq)]
+pub(crate) struct MarkdownDoc {
+    /// Front-matter fields (empty object when there is none).
+    pub front_matter: Value,
+    /// The body rendered to HTML.
+    pub html: String,
+}
+
+/// Render a `.md` file, extracting a leading `---` front-matter block.
+pub(crate) fn render_markdown(source: &str) -> MarkdownDoc {
+    let (front_matter, body) = split_front_matter(source);
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    let mut out = String::with_capacity(body.len() * 3 / 2);
+    html::push_html(&mut out, Parser::new_ext(body, options));
+    MarkdownDoc {
+        front_matter: front_matter.map(parse_front_matter).unwrap_or_else(|| Value::Object(Map::new())),
+        html: out,
+    }
+}
+
+/// Split `---\n...\n---` off the start of the source.
+fn split_front_matter(source: &str) -> (Option<&str>, &str) {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let Some(rest) = source.strip_prefix("---").and_then(|r| r.strip_prefix('\n').or_else(|| r.strip_prefix("\r\n"))) else {
+        return (None, source);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, source)
+}
+
+/// Parse flat `key: value` front-matter lines.
+///
+/// Values may be quoted strings, numbers, booleans, `null` or inline `[a, b]`
+/// lists; anything else is kept as a plain string. Comments and blank lines
+/// are skipped.
+fn parse_front_matter(block: &str) -> Value {
+    let mut map = Map::new();
+    for line in block.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            map.insert(key.trim().to_string(), parse_scalar(value.trim()));
+        }
+    }
+    Value::Object(map)
+}
+
+fn parse_scalar(value: &str) -> Value {
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return Value::Array(
+            items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(parse_scalar)
+                .collect(),
+        );
+    }
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return Value::String(inner.to_string());
+        }
+    }
+    match value {
+        "" | "null" | "~" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => value
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| value.parse::<f64>().map(Value::from))
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_headings_and_lists() {
+        let doc = render_markdown("# Intro\n\nSome *text*.\n\n- one\n- two\n");
+        assert_eq!(
+            doc.html,
+            "<h1>Intro</h1>\n<p>Some <em>text</em>.</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n"
+        );
+        assert_eq!(doc.front_matter, json!({}));
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let doc = render_markdown(
+            "---\ntitle: \"Hello: World\"\ndraft: false\norder: 3\ntags: [rust, 'web']\n---\n## Body\n",
+        );
+        assert_eq!(
+            doc.front_matter,
+            json!({ "title": "Hello: World", "draft": false, "order": 3, "tags": ["rust", "web"] })
+        );
+        assert_eq!(doc.html, "<h2>Body</h2>\n");
+    }
+
+    #[test]
+    fn test_unterminated_front_matter_is_content() {
+        let doc = render_markdown("---\ntitle: x\n");
+        assert_eq!(doc.front_matter, json!({}));
+        assert!(doc.html.contains("<hr />"));
+    }
+}
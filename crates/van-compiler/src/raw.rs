@@ -0,0 +1,123 @@
+use regex::Regex;
+
+use crate::format::tag_end;
+use crate::resolve::find_matching_close_tag;
+
+fn raw_attr_re() -> Regex {
+    Regex::new(r#"\sv-raw(?:=""|='')?(\s|/?>)"#).unwrap()
+}
+
+/// Whether an opening tag carries the `v-raw` attribute.
+pub(crate) fn has_raw_attr(open_tag: &str) -> bool {
+    raw_attr_re().is_match(open_tag)
+}
+
+/// Byte ranges of raw content: the content of each element with a `v-raw`
+/// attribute.
+fn raw_ranges(html: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &html[start..];
+        if rest.starts_with("<!--") {
+            pos = rest.find("-->").map_or(html.len(), |end| start + end + 3);
+            continue;
+        }
+        let Some(len) = tag_end(rest).filter(|_| rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())) else {
+            pos = start + 1;
+            continue;
+        };
+        let tag = &rest[..len];
+        pos = start + len;
+        if !tag.ends_with("/>") && has_raw_attr(tag) {
+            let name = &tag[1..tag.find(|c: char| c.is_whitespace()).unwrap_or(len - 1)];
+            let end = pos + find_matching_close_tag(&html[pos..], name);
+            ranges.push((pos, end));
+            pos = end;
+        }
+    }
+    ranges
+}
+
+/// Apply `f` to `html` with raw content held back: each raw range is swapped
+/// for a `<!--v-raw:N-->` placeholder while `f` runs, then put back.
+pub(crate) fn outside_raw(html: &str, f: impl FnOnce(&str) -> String) -> String {
+    if !html.contains("v-raw") {
+        return f(html);
+    }
+    let mut stashed = String::with_capacity(html.len());
+    let mut contents = Vec::new();
+    let mut pos = 0;
+    for (start, end) in raw_ranges(html) {
+        stashed.push_str(&html[pos..start]);
+        stashed.push_str(&format!("<!--v-raw:{}-->", contents.len()));
+        contents.push(&html[start..end]);
+        pos = end;
+    }
+    stashed.push_str(&html[pos..]);
+    let mut out = f(&stashed);
+    for (i, content) in contents.iter().enumerate() {
+        out = out.replacen(&format!("<!--v-raw:{i}-->"), content, 1);
+    }
+    out
+}
+
+/// Remove each `v-raw` attribute once its content no longer needs protecting.
+pub(crate) fn strip_raw_attrs(html: &str) -> String {
+    if !html.contains("v-raw") {
+        return html.to_string();
+    }
+    let re = raw_attr_re();
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &html[start..];
+        let Some(len) = tag_end(rest).filter(|_| !rest.starts_with("<!--")) else {
+            out.push_str(&html[pos..start + 1]);
+            pos = start + 1;
+            continue;
+        };
+        let tag = &rest[..len];
+        out.push_str(&html[pos..start]);
+        pos = start + len;
+        if !has_raw_attr(tag) {
+            out.push_str(tag);
+            continue;
+        }
+        out.push_str(&re.replace(tag, "$1"));
+        if tag.ends_with("/>") {
+            continue;
+        }
+        let name = &tag[1..tag.find(|c: char| c.is_whitespace()).unwrap_or(len - 1)];
+        let content_end = pos + find_matching_close_tag(&html[pos..], name);
+        out.push_str(&html[pos..content_end]);
+        pos = content_end;
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outside_raw_skips_raw_content() {
+        let html = r#"<p>{{ a }}</p><div class="x" v-raw>{{ b }}<div>{{ c }}</div></div><p>{{ e }}</p>"#;
+        assert_eq!(
+            outside_raw(html, |part| part.replace("{{", "[")),
+            r#"<p>[ a }}</p><div class="x" v-raw>{{ b }}<div>{{ c }}</div></div><p>[ e }}</p>"#
+        );
+    }
+
+    #[test]
+    fn test_strip_raw_attrs() {
+        assert_eq!(
+            strip_raw_attrs(r#"<ul><li v-raw class="a">{{ x }}<b v-raw>y</b></li><li>y</li><br v-raw /></ul>"#),
+            r#"<ul><li class="a">{{ x }}<b v-raw>y</b></li><li>y</li><br /></ul>"#
+        );
+        assert!(!has_raw_attr(r#"<p v-rawish="1">"#));
+    }
+}
@@ -1,24 +1,27 @@
-use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
-use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::LazyLock;
+use std::time::Instant;
 
 use regex::Regex;
+use serde::Serialize;
 use serde_json::Value;
+use van_parser::content_hash;
 use van_signal_gen::{
-    extract_initial_values, generate_signals, generate_signals_compile,
+    extract_initial_values, generate_signals_compile, store_fields,
     generate_signals_comment, inject_signal_comments, runtime_js,
-    analyze_script, walk_template,
+    analyze_script, walk_template, signal_module, verify_client_only_bindings,
+    split_signals, verify_signal_comments, with_teardown, ElementNames, ModuleCode, RuntimeFormat, SignalOptions, TemplateBindings,
+    Transition,
 };
 
+use crate::expr;
+use crate::format::{tag_end, VOID_TAGS};
 use crate::i18n;
-use crate::resolve::ResolvedComponent;
-
-/// Compute a short content hash (8 hex chars) for cache busting.
-fn content_hash(content: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    content.hash(&mut hasher);
-    format!("{:08x}", hasher.finish() as u32)
-}
+use crate::raw;
+use crate::{CompileOptions, Timings};
+use crate::resolve::{extract_reactive_names, find_matching_close_tag, ResolvedComponent};
+use crate::sanitize::sanitize_html;
+use crate::usage::DataUsage;
 
 /// Insert `content` before a closing tag (e.g. `</head>`, `</body>`),
 /// with indentation matching the surrounding HTML structure.
@@ -38,40 +41,166 @@ fn inject_before_close(html: &mut String, close_tag: &str, content: &str) {
             injection.push_str(line);
             injection.push('\n');
         }
-        html.insert_str(line_start, &injection);
+        if line_prefix.trim().is_empty() {
+            html.insert_str(line_start, &injection);
+        } else {
+            // Content shares the line with the close tag (e.g. a text-only
+            // body); keep it before the injection.
+            injection.insert(0, '\n');
+            html.insert_str(pos, &injection);
+        }
     }
 }
 
-/// Augment data with initial signal values from `<script setup>`.
+/// Whether the rendered page is a full document, i.e. its top-level element is `<html>`.
 ///
-/// This allows `cleanup_html()` to replace reactive `{{ count }}` with `0`
-/// instead of leaving raw mustache tags in the output (bad for SEO).
-fn augment_data_with_signals(data: &Value, script_setup: Option<&str>) -> Value {
-    let Some(script) = script_setup else {
-        return data.clone();
-    };
-    let initial_values = extract_initial_values(script);
-    if initial_values.is_empty() {
-        return data.clone();
-    }
-    let mut augmented = data.clone();
-    if let Value::Object(ref mut map) = augmented {
-        for (name, value) in initial_values {
-            // Don't override existing data (server data takes priority)
-            if !map.contains_key(&name) {
-                map.insert(name, Value::String(value));
-            }
+/// Leading whitespace, comments (such as debug markers) and a doctype are
+/// skipped; text that merely contains `<html` further in, like a code sample,
+/// does not count.
+fn is_document(html: &str) -> bool {
+    let mut rest = html.trim_start();
+    loop {
+        if rest.starts_with("<!--") {
+            let Some(end) = rest.find("-->") else {
+                return false;
+            };
+            rest = rest[end + 3..].trim_start();
+        } else if rest.get(..9).is_some_and(|p| p.eq_ignore_ascii_case("<!doctype")) {
+            let Some(end) = rest.find('>') else {
+                return false;
+            };
+            rest = rest[end + 1..].trim_start();
+        } else {
+            break;
+        }
+    }
+    rest.get(..5).is_some_and(|p| p.eq_ignore_ascii_case("<html"))
+        && matches!(rest.as_bytes().get(5), Some(b'>' | b'/') | Some(b' ' | b'\t' | b'\n' | b'\r'))
+}
+
+/// Fill in what a layout's own document shell leaves out: a doctype, a `<head>`,
+/// and the charset and viewport metas the default shell would provide.
+fn complete_document(html: &mut String) {
+    if open_tag_end(html, "head").is_none() {
+        if let Some(pos) = open_tag_end(html, "html") {
+            html.insert_str(pos, "\n<head>\n</head>");
         }
     }
-    augmented
+
+    let lower = html.to_ascii_lowercase();
+    let mut metas = Vec::new();
+    if !lower.contains("<meta charset") {
+        metas.push(r#"<meta charset="UTF-8" />"#);
+    }
+    if !lower.contains(r#"name="viewport""#) {
+        metas.push(r#"<meta name="viewport" content="width=device-width, initial-scale=1.0" />"#);
+    }
+    if let Some(pos) = open_tag_end(html, "head").filter(|_| !metas.is_empty()) {
+        let line_start = html[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = &html[line_start..pos];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let injection: String = metas.iter().map(|m| format!("\n{indent}  {m}")).collect();
+        html.insert_str(pos, &injection);
+    }
+
+    if !html.trim_start().get(..9).is_some_and(|p| p.eq_ignore_ascii_case("<!doctype")) {
+        html.insert_str(0, "<!DOCTYPE html>\n");
+    }
+}
+
+/// The resolved modules to inline into the signal script. A module the
+/// import map sends to a URL is bound from its namespace import (see
+/// [`external_imports`]) as if its code returned it.
+fn module_code(resolved: &ResolvedComponent) -> Vec<ModuleCode> {
+    let mut external = 0;
+    resolved
+        .module_imports
+        .iter()
+        .filter(|m| !m.is_type_only)
+        .map(|m| {
+            let code = match m.url {
+                Some(_) => {
+                    external += 1;
+                    format!("return __ext_{};", external - 1)
+                }
+                None => m.content.clone(),
+            };
+            ModuleCode { code, bindings: m.bindings.clone() }
+        })
+        .collect()
+}
+
+/// For modules the import map sends to URLs: the `import * as __ext_N`
+/// statements the signal script starts with, which make it an ES module, and
+/// the `<script type="importmap">` resolving their specifiers. Both empty
+/// when there are none.
+fn external_imports(resolved: &ResolvedComponent) -> (String, String) {
+    let external: Vec<(&str, &str)> = resolved
+        .module_imports
+        .iter()
+        .filter(|m| !m.is_type_only)
+        .filter_map(|m| Some((m.path.as_str(), m.url.as_deref()?)))
+        .collect();
+    if external.is_empty() {
+        return (String::new(), String::new());
+    }
+    let statements: String = external
+        .iter()
+        .enumerate()
+        .map(|(i, (specifier, _))| format!("import * as __ext_{i} from {};\n", serde_json::to_string(specifier).unwrap()))
+        .collect();
+    let imports: serde_json::Map<String, Value> =
+        external.iter().map(|(specifier, url)| (specifier.to_string(), Value::String(url.to_string()))).collect();
+    // `<` is escaped so neither `</script>` nor `<!--` ends the block early
+    let map = serde_json::json!({ "imports": imports }).to_string().replace('<', "\\u003c");
+    (statements, format!(r#"<script type="importmap">{map}</script>"#))
+}
+
+/// Byte offset just past the first `<name ...>` opening tag (case-insensitive),
+/// skipping longer names that share the prefix (`<header>` for `head`).
+fn open_tag_end(html: &str, name: &str) -> Option<usize> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{name}");
+    lower.match_indices(&open).find_map(|(pos, _)| {
+        let after = pos + open.len();
+        match lower.as_bytes().get(after) {
+            Some(b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r') => {
+                lower[after..].find('>').map(|e| after + e + 1)
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Where a page loads the signal runtime from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RuntimeSource {
+    /// Ship the runtime with the page: inlined, or as a hashed asset.
+    #[default]
+    Bundled,
+    /// Load a runtime hosted at `url` (e.g. one copy shared by many themes)
+    /// instead of bundling it. With [`RuntimeFormat::EsModule`] the signal
+    /// script becomes a module importing it.
+    External { url: String, format: RuntimeFormat },
 }
 
 /// Result of compiling a `.van` page with separated assets.
 pub struct PageAssets {
     /// HTML with external `<link>`/`<script src>` references (no inline CSS/JS)
     pub html: String,
-    /// Asset path → content (e.g. "/themes/van1/assets/js/pages/index.js" → "var Van=...")
-    pub assets: HashMap<String, String>,
+    /// Asset path → content (e.g. "/themes/van1/assets/js/pages/index.js" → "var Van=..."),
+    /// sorted by path so serialized output is reproducible.
+    pub assets: BTreeMap<String, String>,
+    /// Non-fatal diagnostics collected while resolving the page.
+    pub warnings: Vec<String>,
+    /// Source files the page was built from, sorted.
+    pub dependencies: Vec<String>,
+    /// Page data paths the templates read, sorted (see `PageOutput::data_used`).
+    pub data_used: Vec<String>,
+    /// Data paths read but missing from the data, sorted.
+    pub data_missing: Vec<String>,
+    /// Where the build time went (phases outside this module are left at zero here).
+    pub timings: Timings,
 }
 
 /// Render a resolved `.van` component into a full HTML page.
@@ -80,9 +209,14 @@ pub struct PageAssets {
 ///
 /// 1. `compile()` → compiled template (signals processed, model `{{ }}` preserved)
 /// 2. `fill_data()` → interpolate remaining `{{ }}` with data, evaluate model v-show/v-if
-pub fn render_to_string(resolved: &ResolvedComponent, data: &Value, global_name: &str) -> Result<String, String> {
+pub fn render_to_string(
+    resolved: &ResolvedComponent,
+    data: &Value,
+    page_name: &str,
+    options: &CompileOptions,
+) -> Result<String, String> {
     // Step 1: compile (same as Java SSR path)
-    let compiled = compile(resolved, global_name)?;
+    let compiled = compile(resolved, page_name, options)?;
 
     // Step 2: fill data into compiled template
     Ok(fill_data(&compiled, data))
@@ -91,89 +225,183 @@ pub fn render_to_string(resolved: &ResolvedComponent, data: &Value, global_name:
 /// Fill data into a compiled template: interpolate remaining `{{ }}` and evaluate model directives.
 /// This is the Rust equivalent of Java's `VanTemplate.evaluate(model)`.
 pub fn fill_data(compiled_html: &str, data: &Value) -> String {
-    let mut result = compiled_html.to_string();
+    fill_data_checked(compiled_html, data, &DataUsage::default()).0
+}
 
-    // Process remaining v-show (model-bound, preserved by compile)
-    let show_re = Regex::new(r#"\s*v-show="([^"]*)""#).unwrap();
-    result = show_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let expr = &caps[1];
-            let value = resolve_path(data, expr);
-            let is_falsy = value == "0"
-                || value == "false"
-                || value.is_empty()
-                || value == "null"
-                || value.contains("{{");
-            if is_falsy {
-                r#" style="display:none""#.to_string()
-            } else {
-                String::new()
-            }
-        })
-        .to_string();
+static SHOW_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\s*v-show="([^"]*)""#).unwrap());
+static VIF_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\s*v-if="([^"]*)""#).unwrap());
+static ELSE_IF_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\s*v-else-if="([^"]*)""#).unwrap());
+static ELSE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\s+v-else"#).unwrap());
+static VHTML_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\s*v-html="[^"]*""#).unwrap());
+static VTEXT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\s*v-text="[^"]*""#).unwrap());
+static BIND_CLASS_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\s*:class="[^"]*""#).unwrap());
+static BIND_STYLE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\s*:style="[^"]*""#).unwrap());
+
+/// [`fill_data`], also returning the type coercions its `v-show` / `v-if`
+/// conditions relied on (see [`crate::expr`]) and recording the data paths
+/// it reads in `usage`.
+pub(crate) fn fill_data_checked(compiled_html: &str, data: &Value, usage: &DataUsage) -> (String, Vec<String>) {
+    let mut coercions = Vec::new();
+    let filled = raw::outside_raw(compiled_html, |compiled_html| {
+        let mut result = compiled_html.to_string();
+
+        // Process remaining v-show (model-bound, preserved by compile)
+        result = SHOW_RE
+            .replace_all(&result, |caps: &regex::Captures| {
+                let (shown, used) = expr::condition(&caps[1], data, usage);
+                coercions.extend(used);
+                if shown {
+                    String::new()
+                } else {
+                    r#" style="display:none""#.to_string()
+                }
+            })
+            .to_string();
 
-    // Process remaining v-if (model-bound)
-    let vif_re = Regex::new(r#"\s*v-if="([^"]*)""#).unwrap();
-    result = vif_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let expr = &caps[1];
-            let value = resolve_path(data, expr);
-            let is_falsy = value == "0"
-                || value == "false"
-                || value.is_empty()
-                || value == "null"
-                || value.contains("{{");
-            if is_falsy {
-                r#" style="display:none""#.to_string()
-            } else {
-                String::new()
-            }
-        })
-        .to_string();
+        // Process remaining v-if / v-else-if / v-else chains (model-bound)
+        result = fill_conditionals(&result, data, usage, &mut coercions);
 
-    // Strip remaining v-else-if / v-else
-    let else_if_re = Regex::new(r#"\s*v-else-if="[^"]*""#).unwrap();
-    result = else_if_re.replace_all(&result, "").to_string();
-    let else_re = Regex::new(r#"\s+v-else"#).unwrap();
-    result = else_re.replace_all(&result, "").to_string();
+        // Strip remaining v-html / v-text
+        result = VHTML_RE.replace_all(&result, "").to_string();
+        result = VTEXT_RE.replace_all(&result, "").to_string();
 
-    // Strip remaining v-html / v-text
-    let vhtml_re = Regex::new(r#"\s*v-html="[^"]*""#).unwrap();
-    result = vhtml_re.replace_all(&result, "").to_string();
-    let vtext_re = Regex::new(r#"\s*v-text="[^"]*""#).unwrap();
-    result = vtext_re.replace_all(&result, "").to_string();
+        // Strip remaining :class / :style (model-bound, for static render we just strip)
+        result = BIND_CLASS_RE.replace_all(&result, "").to_string();
+        result = BIND_STYLE_RE.replace_all(&result, "").to_string();
 
-    // Strip remaining :class / :style (model-bound, for static render we just strip)
-    let bind_class_re = Regex::new(r#"\s*:class="[^"]*""#).unwrap();
-    result = bind_class_re.replace_all(&result, "").to_string();
-    let bind_style_re = Regex::new(r#"\s*:style="[^"]*""#).unwrap();
-    result = bind_style_re.replace_all(&result, "").to_string();
+        // Strip :key
+        result = strip_key_attrs(&result);
 
-    // Strip :key
-    let key_re = Regex::new(r#"\s*:key="[^"]*""#).unwrap();
-    result = key_re.replace_all(&result, "").to_string();
+        // Interpolate remaining {{ expr }} with data
+        result = interpolate_with(&result, data, false, usage);
 
-    // Interpolate remaining {{ expr }} with data
-    result = interpolate(&result, data);
+        result
+    });
+    (raw::strip_raw_attrs(&filled), coercions)
+}
 
+/// Evaluate the `v-if` / `v-else-if` / `v-else` chains of compiled HTML,
+/// removing the directives. A branch belongs to the nearest `v-if` before it
+/// among its siblings and shows only when no earlier branch of its chain
+/// did; hidden ones get `style="display:none"`.
+fn fill_conditionals(html: &str, data: &Value, usage: &DataUsage, coercions: &mut Vec<String>) -> String {
+    const HIDDEN: &str = r#" style="display:none""#;
+    // For each open element depth, whether the chain running among its
+    // children has shown a branch yet (`None` when no chain is running)
+    let mut chains: Vec<Option<bool>> = vec![None];
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(lt) = rest.find('<') {
+        result.push_str(&rest[..lt]);
+        rest = &rest[lt..];
+        let len = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| end + 3)
+        } else {
+            tag_end(rest)
+        };
+        let Some(len) = len else {
+            result.push('<');
+            rest = &rest[1..];
+            continue;
+        };
+        let (tag, after) = rest.split_at(len);
+        rest = after;
+        if tag.starts_with("</") {
+            if chains.len() > 1 {
+                chains.pop();
+            }
+            result.push_str(tag);
+            continue;
+        }
+        if !tag[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            result.push_str(tag);
+            continue;
+        }
+        let chain = chains.last_mut().unwrap();
+        let mut condition = |expr: &str| {
+            let (shown, used) = expr::condition(expr, data, usage);
+            coercions.extend(used);
+            shown
+        };
+        let tag = if let Some(caps) = ELSE_IF_RE.captures(tag) {
+            let shown = *chain != Some(true) && condition(&caps[1]);
+            *chain = Some(chain.unwrap_or(false) || shown);
+            ELSE_IF_RE.replace(tag, if shown { "" } else { HIDDEN }).into_owned()
+        } else if let Some(caps) = VIF_RE.captures(tag) {
+            let shown = condition(&caps[1]);
+            *chain = Some(shown);
+            VIF_RE.replace(tag, if shown { "" } else { HIDDEN }).into_owned()
+        } else if ELSE_RE.is_match(tag) {
+            let shown = *chain != Some(true);
+            *chain = None;
+            ELSE_RE.replace(tag, if shown { "" } else { HIDDEN }).into_owned()
+        } else {
+            *chain = None;
+            tag.to_string()
+        };
+        result.push_str(&tag);
+        let name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if tag.ends_with("/>") || VOID_TAGS.contains(&name.as_str()) {
+            continue;
+        }
+        if matches!(name.as_str(), "script" | "style" | "textarea") {
+            // Raw text: copy through to the closing tag, which closes the element
+            let end = rest.to_ascii_lowercase().find(&format!("</{name}")).unwrap_or(rest.len());
+            result.push_str(&rest[..end]);
+            rest = &rest[end..];
+        }
+        chains.push(None);
+    }
+    result.push_str(rest);
     result
 }
 
 /// Render a resolved `.van` component with separated assets.
 ///
 /// Pipeline: `compile_assets() + fill_data()` — shares compile step with Java SSR.
+///
+/// `data_used` and `data_missing` cover the data this fill reads; paths read
+/// while resolving (props, `v-for` sources) are only known to the resolver.
 pub fn render_to_assets(
     resolved: &ResolvedComponent,
     data: &Value,
     page_name: &str,
     asset_prefix: &str,
-    global_name: &str,
+    options: &CompileOptions,
+) -> Result<PageAssets, String> {
+    let usage = DataUsage::tracking();
+    if let Some(script) = &resolved.script_setup {
+        usage.ignore(&extract_reactive_names(script));
+    }
+    let mut assets = render_to_assets_tracked(resolved, data, page_name, asset_prefix, options, &usage)?;
+    let (used, missing) = usage.finish(data);
+    assets.data_used = used;
+    assets.data_missing = missing.into_iter().map(|(path, _)| path).collect();
+    Ok(assets)
+}
+
+/// [`render_to_assets`], recording the data the fill reads into `usage`.
+pub(crate) fn render_to_assets_tracked(
+    resolved: &ResolvedComponent,
+    data: &Value,
+    page_name: &str,
+    asset_prefix: &str,
+    options: &CompileOptions,
+    usage: &DataUsage,
 ) -> Result<PageAssets, String> {
     // Step 1: compile with separated assets
-    let mut compiled = compile_assets(resolved, page_name, asset_prefix, global_name)?;
+    let mut compiled = compile_assets(resolved, page_name, asset_prefix, options)?;
 
     // Step 2: fill data into compiled HTML
-    compiled.html = fill_data(&compiled.html, data);
+    let (html, coercions) = fill_data_checked(&compiled.html, data, usage);
+    compiled.html = html;
+    if options.strict_coercion {
+        compiled.warnings = coercions;
+    }
 
     Ok(compiled)
 }
@@ -184,80 +412,263 @@ pub fn render_to_assets(
 /// - Signal bindings (ref/computed): interpolate initial values + generate JS (same as render mode)
 /// - Model bindings: preserve for Java SSR (v-for, v-if, :class, {{ }})
 ///
-/// Uses comment anchors (`<!--v:N-->`) for position-independent signal element targeting.
-pub fn compile(resolved: &ResolvedComponent, global_name: &str) -> Result<String, String> {
-    let style_block: String = resolved
-        .styles
-        .iter()
-        .map(|css| format!("<style>{css}</style>"))
+/// Uses comment anchors (`<!--v:ID-->`) for position-independent signal element targeting.
+///
+/// With `sanitize_html`, `v-html` values go through `V.sanitize()` on the client;
+/// with `emit_teardown`, the signal script registers itself as `page_name` in
+/// `window.__van_pages`.
+pub fn compile(
+    resolved: &ResolvedComponent,
+    page_name: &str,
+    options: &CompileOptions,
+) -> Result<String, String> {
+    compile_timed(resolved, page_name, options).map(|(html, _)| html)
+}
+
+/// [`compile`], also returning the milliseconds spent generating the signal script.
+pub(crate) fn compile_timed(
+    resolved: &ResolvedComponent,
+    page_name: &str,
+    options: &CompileOptions,
+) -> Result<(String, f64), String> {
+    let global_name = options.global_name.as_str();
+    let body = compile_body(resolved, page_name, options);
+    let (imports, import_map) = external_imports(resolved);
+    let imports = escape_script(&imports);
+    let script_type = if imports.is_empty() { "" } else { r#" type="module""# };
+
+    let signal_scripts = match body.signal_js.as_deref().map(escape_script) {
+        Some(signal_js) => match &options.runtime {
+            RuntimeSource::Bundled => {
+                format!("<script>{}</script>\n<script{script_type}>{imports}{signal_js}</script>", runtime_js(global_name))
+            }
+            RuntimeSource::External { url, format: RuntimeFormat::Iife } => {
+                format!("<script src=\"{}\"></script>\n<script{script_type}>{imports}{signal_js}</script>", escape_html(url))
+            }
+            RuntimeSource::External { url, format: RuntimeFormat::EsModule } => {
+                format!("<script type=\"module\">{imports}{}</script>", signal_module(&signal_js, global_name, url))
+            }
+        },
+        None => String::new(),
+    };
+    let signal_scripts = match &body.binding_map {
+        Some(map) => format!("{map}\n{signal_scripts}"),
+        None => signal_scripts,
+    };
+
+    // The import map has to come before the module script using it
+    let style_block: String = (!import_map.is_empty() && body.signal_js.is_some())
+        .then_some(import_map)
+        .into_iter()
+        .chain(body.styles.iter().map(|css| format!("<style>{css}</style>")))
         .collect::<Vec<_>>()
         .join("\n");
 
-    let module_code: Vec<String> = resolved
-        .module_imports
-        .iter()
-        .filter(|m| !m.is_type_only)
-        .map(|m| m.content.clone())
-        .collect();
+    let clean_html = body.html;
+    if is_document(&clean_html) {
+        let mut html = clean_html;
+        complete_document(&mut html);
+        inject_before_close(&mut html, "</head>", &style_block);
+        inject_before_close(&mut html, "</body>", &signal_scripts);
+        Ok((html, body.signal_gen_ms))
+    } else {
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8" />
+<meta name="viewport" content="width=device-width, initial-scale=1.0" />
+<title>Van App</title>
+{style_block}
+</head>
+<body>
+{clean_html}
+{signal_scripts}
+</body>
+</html>"#
+        );
+        Ok((html, body.signal_gen_ms))
+    }
+}
+
+/// A compiled page before it is put into a document.
+pub(crate) struct CompiledBody {
+    /// Markup with signal bindings processed and model bindings preserved.
+    pub html: String,
+    /// Component styles, led by the `v-cloak` rule when the page needs it.
+    pub styles: Vec<String>,
+    /// The signal script, without the runtime it runs on.
+    pub signal_js: Option<String>,
+    /// The `__van_bindings` block, in debug mode.
+    pub binding_map: Option<String>,
+    pub signal_gen_ms: f64,
+}
+
+/// The document-independent part of [`compile`].
+pub(crate) fn compile_body(resolved: &ResolvedComponent, page_name: &str, options: &CompileOptions) -> CompiledBody {
+    let module_code = module_code(resolved);
 
-    // Step 1: Analyze script to get reactive names
+    // Step 1: Analyze script to get reactive names, imported store fields included
+    let stores = store_fields(&module_code);
     let reactive_names: Vec<String> = if let Some(ref script_setup) = resolved.script_setup {
         let analysis = analyze_script(script_setup);
         analysis.signals.iter().map(|s| s.name.clone())
             .chain(analysis.computeds.iter().map(|c| c.name.clone()))
+            .chain(stores.iter().map(|f| f.name.clone()))
             .collect()
     } else {
         Vec::new()
     };
 
     // Step 2: Generate signal JS from dirty HTML (before cleanup), using comment anchors
-    let signal_scripts = if let Some(ref script_setup) = resolved.script_setup {
-        if let Some(signal_js) = generate_signals_comment(script_setup, &resolved.html, &module_code, global_name) {
-            let runtime = runtime_js(global_name);
-            format!("<script>{runtime}</script>\n<script>{signal_js}</script>")
-        } else {
-            String::new()
-        }
-    } else {
-        String::new()
-    };
+    let signal_started = Instant::now();
+    let signal_js = resolved.script_setup.as_ref().and_then(|script_setup| {
+        generate_signals_comment(script_setup, &resolved.html, &module_code, &signal_options(options))
+            .map(|signal_js| teardown(signal_js, &module_code, page_name, options))
+    });
+    let signal_gen_ms = signal_started.elapsed().as_secs_f64() * 1000.0;
 
     // Step 3: Inject comment anchors before signal-bound elements
     let reactive_refs: Vec<&str> = reactive_names.iter().map(|s| s.as_str()).collect();
     let bindings = walk_template(&resolved.html, &reactive_refs);
     let binding_paths = collect_signal_binding_paths(&bindings);
-    let (html_with_comments, _) = inject_signal_comments(&resolved.html, &binding_paths);
+    let (mut html_with_comments, anchors) = inject_signal_comments(&resolved.html, &binding_paths, options.element_names);
+    let binding_map = (options.debug && signal_js.is_some()).then(|| binding_map_script(&bindings, &anchors));
+    if options.debug {
+        html_with_comments = stamp_binding_paths(&html_with_comments, &anchors);
+    }
+
+    let cloak_css = (signal_js.is_some() && !bindings.cloaks.is_empty()).then_some(CLOAK_CSS);
+    let transitions = match signal_js {
+        Some(_) if options.transition_css => unstyled_transitions(&bindings, &resolved.styles),
+        _ => Vec::new(),
+    };
+    let styles = cloak_css
+        .into_iter()
+        .chain(resolved.styles.iter().map(String::as_str))
+        .map(str::to_string)
+        .chain(transitions.iter().map(fade_css))
+        .collect();
 
     // Step 4: Get signal initial values and interpolate
-    let signal_initial_values: HashMap<String, String> = resolved.script_setup.as_ref()
+    let mut signal_initial_values: HashMap<String, String> = resolved.script_setup.as_ref()
         .map(|s| extract_initial_values(s))
         .unwrap_or_default();
+    signal_initial_values.extend(stores.into_iter().filter_map(|f| Some((f.name, f.initial?))));
 
     // Step 5: Cleanup HTML — signal bindings processed, model bindings preserved
-    let mut clean_html = cleanup_html_compile_smart(&html_with_comments, &reactive_names);
-    clean_html = interpolate_signals_only(&clean_html, &signal_initial_values);
+    let mut html = cleanup_html_compile_smart(&html_with_comments, &reactive_names);
+    html = interpolate_signals_only(&html, &signal_initial_values);
+    html = blank_signal_attr_mustaches(&html, &reactive_names);
 
-    if clean_html.contains("<html") {
-        let mut html = clean_html;
-        inject_before_close(&mut html, "</head>", &style_block);
-        inject_before_close(&mut html, "</body>", &signal_scripts);
-        Ok(html)
+    CompiledBody { html, styles, signal_js, binding_map, signal_gen_ms }
+}
+
+/// What the signal script was generated from, for cross-referencing positional
+/// paths against the DOM in devtools.
+#[derive(Serialize)]
+struct BindingMap<'a> {
+    bindings: &'a TemplateBindings,
+    /// Each bound element's path and the `<!--v:ID-->` anchor it is found by.
+    anchors: Vec<BindingAnchor>,
+}
+
+#[derive(Serialize)]
+struct BindingAnchor {
+    path: Vec<usize>,
+    anchor: String,
+}
+
+/// The `<script type="application/json" id="__van_bindings">` block emitted in
+/// debug mode.
+fn binding_map_script(bindings: &TemplateBindings, anchors: &HashMap<Vec<usize>, String>) -> String {
+    let mut anchors: Vec<(&Vec<usize>, &String)> = anchors.iter().collect();
+    anchors.sort();
+    let map = BindingMap {
+        bindings,
+        anchors: anchors
+            .into_iter()
+            .map(|(path, idx)| BindingAnchor { path: path.clone(), anchor: format!("<!--v:{idx}-->") })
+            .collect(),
+    };
+    // `<` is escaped so neither `</script>` nor `<!--` ends the block early
+    let json = serde_json::to_string(&map).unwrap_or_default().replace('<', "\\u003c");
+    format!(r#"<script type="application/json" id="__van_bindings">{json}</script>"#)
+}
+
+/// Add `data-van-path="0.2.1"` (the positional path) to each element the
+/// `<!--v:ID-->` anchors in `html` point at, for tools correlating the
+/// compiled markup, the signal script and the live DOM. Debug mode only; the
+/// paths were computed before, so the attributes don't shift them.
+fn stamp_binding_paths(html: &str, anchors: &HashMap<Vec<usize>, String>) -> String {
+    let mut result = html.to_string();
+    for (path, idx) in anchors {
+        let anchor = format!("<!--v:{idx}-->");
+        let Some(at) = result.find(&anchor) else {
+            continue;
+        };
+        let tag_start = at + anchor.len();
+        if !result[tag_start..].starts_with('<') {
+            continue;
+        }
+        let name_len = result[tag_start + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(result.len() - tag_start - 1);
+        let path: Vec<String> = path.iter().map(usize::to_string).collect();
+        result.insert_str(tag_start + 1 + name_len, &format!(" data-van-path=\"{}\"", path.join(".")));
+    }
+    result
+}
+
+/// Styles for a page's CSS asset, in a page-independent order, each with the
+/// file it came from.
+///
+/// Each component contributes its CSS once; shared components are sorted by
+/// virtual path and the entry's own styles come last (so they still win ties).
+/// A component therefore yields byte-identical, identically ordered CSS on
+/// every page that includes it. Global stylesheets are left out; they get
+/// an asset of their own.
+fn asset_styles<'a>(resolved: &'a ResolvedComponent, entry: &str, global_css: &[String]) -> Vec<(&'a str, &'a str)> {
+    let mut styles: Vec<(&str, &str)> = resolved
+        .styles
+        .iter()
+        .enumerate()
+        .map(|(i, css)| {
+            let source = resolved.style_sources.get(i).map(String::as_str).unwrap_or("");
+            (source, css.as_str())
+        })
+        .filter(|(source, _)| !global_css.iter().any(|path| path == source))
+        .collect();
+    styles.sort_by_key(|(source, _)| (*source == entry, *source));
+    let mut seen = HashSet::new();
+    styles.into_iter().filter(|style| seen.insert(*style)).collect()
+}
+
+/// A theme name as an asset directory: anything but ASCII letters, digits,
+/// `-` and `_` becomes `-`, so a name can't leave the asset prefix.
+fn theme_dir(theme: &str) -> String {
+    theme.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect()
+}
+
+/// Hides `v-cloak` elements until the signal script has wired them up.
+const CLOAK_CSS: &str = "[v-cloak] { display: none; }";
+
+/// The settings of `options` the signal generators take.
+fn signal_options(options: &CompileOptions) -> SignalOptions {
+    SignalOptions {
+        global_name: options.global_name.clone(),
+        sanitize_html: options.sanitize_html,
+        hydration: options.hydration,
+        element_names: options.element_names,
+    }
+}
+
+/// Add the `window.__van_pages` teardown hook when `emit_teardown` is on.
+fn teardown(signal_js: String, modules: &[ModuleCode], page_name: &str, options: &CompileOptions) -> String {
+    if options.emit_teardown {
+        with_teardown(&signal_js, modules, page_name)
     } else {
-        Ok(format!(
-            r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-<meta charset="UTF-8" />
-<meta name="viewport" content="width=device-width, initial-scale=1.0" />
-<title>Van App</title>
-{style_block}
-</head>
-<body>
-{clean_html}
-{signal_scripts}
-</body>
-</html>"#
-        ))
+        signal_js
     }
 }
 
@@ -266,51 +677,130 @@ pub fn compile_assets(
     resolved: &ResolvedComponent,
     page_name: &str,
     asset_prefix: &str,
-    global_name: &str,
+    options: &CompileOptions,
 ) -> Result<PageAssets, String> {
-    let mut assets = HashMap::new();
-
-    let css_ref = if !resolved.styles.is_empty() {
-        let css_content: String = resolved.styles.join("\n");
-        let hash = content_hash(&css_content);
-        let css_path = format!("{}/css/{}.{}.css", asset_prefix, page_name, hash);
-        assets.insert(css_path.clone(), css_content);
-        format!(r#"<link rel="stylesheet" href="{css_path}">"#)
-    } else {
-        String::new()
-    };
+    let global_name = options.global_name.as_str();
+    let mut assets = BTreeMap::new();
+    let href = |path: &str| asset_href(path, page_name, options.relative_assets);
 
-    let module_code: Vec<String> = resolved
-        .module_imports
-        .iter()
-        .filter(|m| !m.is_type_only)
-        .map(|m| m.content.clone())
-        .collect();
+    let module_code = module_code(resolved);
+    let (imports, import_map) = external_imports(resolved);
 
+    let signal_started = Instant::now();
     let js_ref = if let Some(ref script_setup) = resolved.script_setup {
-        if let Some(signal_js) = generate_signals_compile(script_setup, &resolved.html, &module_code, global_name) {
-            let runtime = runtime_js(global_name);
-            let runtime_hash = content_hash(&runtime);
-            let runtime_path = format!("{}/js/van-runtime.{}.js", asset_prefix, runtime_hash);
-            let js_hash = content_hash(&signal_js);
-            let js_path = format!("{}/js/{}.{}.js", asset_prefix, page_name, js_hash);
-            assets.insert(runtime_path.clone(), runtime);
-            assets.insert(js_path.clone(), signal_js);
-            format!(
-                r#"<script src="{runtime_path}"></script>
-<script src="{js_path}"></script>"#
-            )
+        if let Some(signal_js) = generate_signals_compile(script_setup, &resolved.html, &module_code, &signal_options(options)) {
+            let signal_js = teardown(signal_js, &module_code, page_name, options);
+            let mut chunks = match options.max_js_chunk_bytes {
+                Some(max_bytes) => split_signals(&signal_js, page_name, max_bytes),
+                None => vec![signal_js],
+            };
+            let (runtime_tag, mut script_type) = match &options.runtime {
+                RuntimeSource::Bundled => {
+                    let runtime = runtime_js(global_name);
+                    let runtime_hash = content_hash(&runtime);
+                    let runtime_path = format!("{}/js/van-runtime.{}.js", asset_prefix, runtime_hash);
+                    assets.insert(runtime_path.clone(), runtime);
+                    (format!("<script src=\"{}\"></script>\n", href(&runtime_path)), "")
+                }
+                RuntimeSource::External { url, format: RuntimeFormat::Iife } => {
+                    (format!("<script src=\"{}\"></script>\n", escape_html(url)), "")
+                }
+                RuntimeSource::External { url, format: RuntimeFormat::EsModule } => {
+                    // Later chunks read the runtime from the first one's scope
+                    chunks[0] = signal_module(&chunks[0], global_name, url);
+                    (String::new(), r#" type="module""#)
+                }
+            };
+            if !imports.is_empty() {
+                chunks[0].insert_str(0, &imports);
+                script_type = r#" type="module""#;
+            }
+            // Chunks after the first are numbered, and run in order after it
+            let tags: Vec<String> = chunks
+                .into_iter()
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let js_hash = content_hash(&chunk);
+                    let js_path = match i {
+                        0 => format!("{}/js/{}.{}.js", asset_prefix, page_name, js_hash),
+                        _ => format!("{}/js/{}.{}.{}.js", asset_prefix, page_name, i, js_hash),
+                    };
+                    let tag = format!(r#"<script{script_type} src="{}"></script>"#, href(&js_path));
+                    assets.insert(js_path, chunk);
+                    tag
+                })
+                .collect();
+            format!("{runtime_tag}{}", tags.join("\n"))
         } else {
             String::new()
         }
     } else {
         String::new()
     };
+    let signal_gen_ms = signal_started.elapsed().as_secs_f64() * 1000.0;
+
+    // Elements inside <ClientOnly> blocks are uncloaked by the signal script
+    let cloaked = !js_ref.is_empty() && !walk_template(&resolved.html, &[]).cloaks.is_empty();
+    let mut css_refs = Vec::new();
+    // The import map has to come before the module script using it
+    if !import_map.is_empty() && !js_ref.is_empty() {
+        css_refs.push(import_map);
+    }
+    // Global stylesheets are shared by every page, so they get their own file, linked first
+    let global_content: Vec<&str> = resolved
+        .styles
+        .iter()
+        .zip(&resolved.style_sources)
+        .filter(|(_, source)| options.global_css.contains(source))
+        .map(|(css, _)| css.as_str())
+        .collect();
+    if !global_content.is_empty() {
+        let css_content = global_content.join("\n");
+        let hash = content_hash(&css_content);
+        let css_path = format!("{}/css/global.{}.css", asset_prefix, hash);
+        css_refs.push(format!(r#"<link rel="stylesheet" href="{}">"#, href(&css_path)));
+        assets.insert(css_path, css_content);
+    }
+    // One stylesheet per theme of `file_origins`, under `{prefix}/{theme}/css/`;
+    // files without an origin (and the cloak rule) share the default one
+    let entry = format!("{page_name}.van");
+    let mut themes: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+    if cloaked {
+        themes.entry(None).or_default().push(CLOAK_CSS);
+    }
+    for (source, css) in asset_styles(resolved, &entry, &options.global_css) {
+        themes.entry(options.file_origins.get(source).map(String::as_str)).or_default().push(css);
+    }
+    let transitions: Vec<String> = match resolved.script_setup.as_deref() {
+        Some(script_setup) if options.transition_css && !js_ref.is_empty() => {
+            unstyled_transitions(&page_bindings(resolved, script_setup), &resolved.styles).iter().map(fade_css).collect()
+        }
+        _ => Vec::new(),
+    };
+    if !transitions.is_empty() {
+        themes.entry(None).or_default().extend(transitions.iter().map(String::as_str));
+    }
+    let entry_theme = options.file_origins.get(&entry).map(String::as_str);
+    let mut themes: Vec<_> = themes.into_iter().collect();
+    // The entry's stylesheet is linked last, so its styles still win ties
+    themes.sort_by_key(|(theme, _)| *theme == entry_theme);
+    for (theme, styles) in themes {
+        let css_content = styles.join("\n");
+        let hash = content_hash(&css_content);
+        let css_path = match theme {
+            Some(theme) => format!("{}/{}/css/{}.{}.css", asset_prefix, theme_dir(theme), page_name, hash),
+            None => format!("{}/css/{}.{}.css", asset_prefix, page_name, hash),
+        };
+        css_refs.push(format!(r#"<link rel="stylesheet" href="{}">"#, href(&css_path)));
+        assets.insert(css_path, css_content);
+    }
+    let css_ref = css_refs.join("\n");
 
     let clean_html = cleanup_html_compile(&resolved.html);
 
-    let html = if clean_html.contains("<html") {
+    let html = if is_document(&clean_html) {
         let mut html = clean_html;
+        complete_document(&mut html);
         inject_before_close(&mut html, "</head>", &css_ref);
         inject_before_close(&mut html, "</body>", &js_ref);
         html
@@ -332,31 +822,231 @@ pub fn compile_assets(
         )
     };
 
-    Ok(PageAssets { html, assets })
+    Ok(PageAssets {
+        html,
+        assets,
+        warnings: Vec::new(),
+        dependencies: Vec::new(),
+        data_used: Vec::new(),
+        data_missing: Vec::new(),
+        timings: Timings { signal_gen_ms, ..Timings::default() },
+    })
+}
+
+/// How a page links an asset: its root-relative `path` as is, or with
+/// `relative` a path from the page's output file, whose depth follows from
+/// `page_name` (`pages/index` → `index.html`, `pages/blog/post` →
+/// `blog/post/index.html`). Absolute URLs are never rewritten.
+fn asset_href(path: &str, page_name: &str, relative: bool) -> String {
+    let Some(rel) = path.strip_prefix('/').filter(|p| relative && !p.starts_with('/')) else {
+        return path.to_string();
+    };
+    let segments: Vec<&str> = page_name.strip_prefix("pages/").unwrap_or(page_name).split('/').collect();
+    let depth = segments.len() - usize::from(segments.last() == Some(&"index"));
+    format!("{}{rel}", "../".repeat(depth))
 }
 
 /// Compile cleanup: strip only @click/v-model events, keep runtime directives for Java.
 /// Preserves: v-for, v-if, v-else-if, v-else, v-show, :class, :style, :href, {{ }}, v-html, v-text
-/// Strips: @click, @input, v-model, <Transition>
+/// Strips: @click, @input, v-model, <Transition>, `<template v-if>` group tags
 fn cleanup_html_compile(html: &str) -> String {
-    let mut result = html.to_string();
+    raw::outside_raw(html, |html| {
+        let mut result = unwrap_template_groups(html);
 
-    // Strip @event="..." attributes
-    let event_re = Regex::new(r#"\s*@\w+="[^"]*""#).unwrap();
-    result = event_re.replace_all(&result, "").to_string();
+        // Strip @event="..." attributes
+        let event_re = Regex::new(r#"\s*@\w+="[^"]*""#).unwrap();
+        result = event_re.replace_all(&result, "").to_string();
 
-    // Strip <Transition> / </Transition> wrapper tags
-    let transition_re = Regex::new(r#"</?[Tt]ransition[^>]*>"#).unwrap();
-    result = transition_re.replace_all(&result, "").to_string();
+        // Strip <Transition> / <TransitionGroup> wrapper tags
+        result = strip_transition_tags(&result);
 
-    // Strip v-model="..." (client-only directive)
-    let model_re = Regex::new(r#"\s*v-model="[^"]*""#).unwrap();
-    result = model_re.replace_all(&result, "").to_string();
+        // Strip v-model="..." (client-only directive)
+        let model_re = Regex::new(r#"(?i)\s*v-model="[^"]*""#).unwrap();
+        result = model_re.replace_all(&result, "").to_string();
+
+        // Everything else (v-for, v-if, v-show, :class, :style, :href, {{ }}) is PRESERVED
+        result
+    })
+}
 
-    // Everything else (v-for, v-if, v-show, :class, :style, :href, {{ }}) is PRESERVED
+/// Unwrap `<template v-if>` groups (and `v-else-if`, `v-else`, `v-show`): the
+/// tag renders nothing, so its directives move onto each child element. Text
+/// directly inside a group is kept unconditionally.
+///
+/// `<template v-for>` is expanded during resolution in render mode and kept
+/// for the Java runtime in compile mode.
+fn unwrap_template_groups(html: &str) -> String {
+    let open_re = Regex::new(r#"<template(\s[^>]*?)\s*>"#).unwrap();
+    let directive_re = Regex::new(r#"(?i)(?:^|\s)v-(?:if|else-if|else|show)(?:[\s=]|$)"#).unwrap();
+    let mut result = html.to_string();
+    let mut from = 0;
+    while let Some(caps) = open_re.captures_at(&result, from) {
+        let (start, inner_start) = (caps.get(0).unwrap().start(), caps.get(0).unwrap().end());
+        let attrs = caps[1].trim().to_string();
+        if !directive_re.is_match(&attrs) {
+            from = inner_start;
+            continue;
+        }
+        let close = inner_start + find_matching_close_tag(&result[inner_start..], "template");
+        let inner = distribute_attrs(&result[inner_start..close], &attrs);
+        let end = (close + "</template>".len()).min(result.len());
+        result.replace_range(start..end, &inner);
+        from = start;
+    }
     result
 }
 
+/// Add `attrs` to every top-level element of `html`.
+fn distribute_attrs(html: &str, attrs: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        out.push_str(&html[pos..start]);
+        let rest = &html[start..];
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map_or(rest.len(), |e| e + 3)
+        } else {
+            tag_end(rest).unwrap_or(1)
+        };
+        let tag = &rest[..end];
+        if tag.starts_with("</") {
+            depth = depth.saturating_sub(1);
+            out.push_str(tag);
+        } else if tag[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let name_end = tag[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                .map_or(tag.len(), |n| n + 1);
+            if depth == 0 {
+                out.push_str(&format!("{} {}{}", &tag[..name_end], attrs, &tag[name_end..]));
+            } else {
+                out.push_str(tag);
+            }
+            let name = tag[1..name_end].to_ascii_lowercase();
+            if !tag.ends_with("/>") && !VOID_TAGS.contains(&name.as_str()) {
+                depth += 1;
+            }
+        } else {
+            out.push_str(tag);
+        }
+        pos = start + end;
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+static KEY_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\s+(?:v-bind)?:key="[^"]*""#).unwrap());
+
+/// Remove whole `:key="..."` / `v-bind:key="..."` attributes.
+///
+/// The attribute must start after whitespace, so names merely ending in
+/// `:key` (e.g. `xlink:key`) are left intact.
+fn strip_key_attrs(html: &str) -> String {
+    KEY_RE.replace_all(html, "").to_string()
+}
+
+/// Remove `<Transition>`, `<TransitionGroup>` (or `<transition-group>`) and
+/// any `<Teleport>` left after resolution, keeping their content: none
+/// renders an element of its own.
+fn strip_transition_tags(html: &str) -> String {
+    let re = Regex::new(r#"</?(?:[Tt]ransition(?:[Gg]roup|-group)?|[Tt]eleport)(?:\s[^>]*)?>"#).unwrap();
+    re.replace_all(html, "").to_string()
+}
+
+/// Signal bindings of a page built by [`compile`] whose `<!--v:ID-->` anchor
+/// no longer precedes the element it was computed for in the shipped `html`.
+pub(crate) fn anchor_mismatches(resolved: &ResolvedComponent, html: &str, element_names: ElementNames) -> Vec<String> {
+    let Some(ref script_setup) = resolved.script_setup else {
+        return Vec::new();
+    };
+    let bindings = page_bindings(resolved, script_setup);
+    verify_signal_comments(&resolved.html, &collect_signal_binding_paths(&bindings), html, element_names)
+}
+
+/// The signal bindings of a page, walked for the reactive names of
+/// `script_setup` and the store fields it imports.
+fn page_bindings(resolved: &ResolvedComponent, script_setup: &str) -> TemplateBindings {
+    let analysis = analyze_script(script_setup);
+    let stores = store_fields(&module_code(resolved));
+    let reactive_names: Vec<&str> = analysis.signals.iter().map(|s| s.name.as_str())
+        .chain(analysis.computeds.iter().map(|c| c.name.as_str()))
+        .chain(stores.iter().map(|f| f.name.as_str()))
+        .collect();
+    walk_template(&resolved.html, &reactive_names)
+}
+
+/// The `<Transition>`s of `bindings` (one per name) whose
+/// `.{name}-enter-active` class none of `styles` defines, so the runtime's
+/// classes would change nothing.
+fn unstyled_transitions(bindings: &TemplateBindings, styles: &[String]) -> Vec<Transition> {
+    let mut unstyled: Vec<Transition> = Vec::new();
+    for transition in bindings.shows.iter().filter_map(|show| show.transition.as_ref()) {
+        let class = format!(".{}-enter-active", transition.name);
+        if !unstyled.iter().any(|t| t.name == transition.name) && !styles.iter().any(|css| css.contains(&class)) {
+            unstyled.push(transition.clone());
+        }
+    }
+    unstyled
+}
+
+/// Baseline CSS for `transition`: an opacity fade over its `duration` (a
+/// number, or `{ enter, leave }` numbers), 300ms when it has none.
+fn fade_css(transition: &Transition) -> String {
+    let duration = transition.duration.as_deref().unwrap_or_default();
+    let ms = |key: &str| {
+        let re = Regex::new(&format!(r"\b{key}\s*:\s*(\d+(?:\.\d+)?)")).unwrap();
+        match duration.parse::<f64>() {
+            Ok(_) => duration.to_string(),
+            Err(_) => re.captures(duration).map_or("300".to_string(), |caps| caps[1].to_string()),
+        }
+    };
+    let name = &transition.name;
+    format!(
+        ".{name}-enter-active {{ transition: opacity {}ms ease; }}\n.{name}-leave-active {{ transition: opacity {}ms ease; }}\n.{name}-enter-from, .{name}-leave-to {{ opacity: 0; }}",
+        ms("enter"),
+        ms("leave")
+    )
+}
+
+/// `<Transition>`s the page animates without any styles for their classes,
+/// which show and hide instantly. Nothing when
+/// [`CompileOptions::transition_css`] generates their CSS.
+pub(crate) fn transition_warnings(resolved: &ResolvedComponent, options: &CompileOptions) -> Vec<String> {
+    let Some(script_setup) = resolved.script_setup.as_deref().filter(|_| !options.transition_css) else {
+        return Vec::new();
+    };
+    unstyled_transitions(&page_bindings(resolved, script_setup), &resolved.styles)
+        .into_iter()
+        .map(|t| {
+            format!(
+                "<Transition name=\"{0}\"> has no .{0}-enter-active style, so it does not animate (transition_css generates a fade)",
+                t.name
+            )
+        })
+        .collect()
+}
+
+/// Bindings of a page that set the same element content, of which only one
+/// takes effect.
+pub(crate) fn binding_conflicts(resolved: &ResolvedComponent) -> Vec<String> {
+    resolved
+        .script_setup
+        .as_deref()
+        .map(|script_setup| van_signal_gen::binding_conflicts(script_setup, &resolved.html))
+        .unwrap_or_default()
+}
+
+/// Signal bindings of a page built by [`compile_assets`] whose depth-first
+/// index no longer addresses the same element in the shipped `html`.
+pub(crate) fn client_only_mismatches(resolved: &ResolvedComponent, html: &str) -> Vec<String> {
+    resolved
+        .script_setup
+        .as_deref()
+        .map(|script_setup| verify_client_only_bindings(script_setup, &resolved.html, html))
+        .unwrap_or_default()
+}
+
 /// Collect all unique binding paths from TemplateBindings, sorted in DFS order.
 fn collect_signal_binding_paths(bindings: &van_signal_gen::TemplateBindings) -> Vec<Vec<usize>> {
     let mut paths = std::collections::BTreeSet::new();
@@ -368,13 +1058,15 @@ fn collect_signal_binding_paths(bindings: &van_signal_gen::TemplateBindings) ->
     for b in &bindings.classes { paths.insert(b.path.clone()); }
     for b in &bindings.styles { paths.insert(b.path.clone()); }
     for b in &bindings.models { paths.insert(b.path.clone()); }
+    for b in &bindings.attrs { paths.insert(b.path.clone()); }
+    for path in &bindings.cloaks { paths.insert(path.clone()); }
     paths.into_iter().collect()
 }
 
 /// Smart compile cleanup: process signal bindings like render mode, preserve model bindings for Java.
 ///
 /// Signal bindings (expr references reactive_names):
-/// - `{{ count }}` → interpolate with initial value
+/// - `{{ count }}` → left for `interpolate_signals_only`, which fills in initial values
 /// - `v-show="visible"` → evaluate initial value, add display:none
 /// - `@click`, `v-model` → strip (JS already generated)
 /// - `:class`/`:style` referencing signals → strip (JS already generated)
@@ -382,95 +1074,80 @@ fn collect_signal_binding_paths(bindings: &van_signal_gen::TemplateBindings) ->
 /// Model bindings (expr does NOT reference reactive_names):
 /// - `{{ ctx.title }}`, `v-for`, `v-if="ctx.xxx"`, `:class` → preserve for Java
 fn cleanup_html_compile_smart(html: &str, reactive_names: &[String]) -> String {
-    let mut result = html.to_string();
+    raw::outside_raw(html, |html| {
+        let mut result = unwrap_template_groups(html);
+
+        // 1. Strip ALL @event="..." (events are always client-side, JS already generated)
+        let event_re = Regex::new(r#"\s*@\w+="[^"]*""#).unwrap();
+        result = event_re.replace_all(&result, "").to_string();
 
-    // 1. Strip ALL @event="..." (events are always client-side, JS already generated)
-    let event_re = Regex::new(r#"\s*@\w+="[^"]*""#).unwrap();
-    result = event_re.replace_all(&result, "").to_string();
-
-    // 2. Strip <Transition> wrapper tags
-    let transition_re = Regex::new(r#"</?[Tt]ransition[^>]*>"#).unwrap();
-    result = transition_re.replace_all(&result, "").to_string();
-
-    // 3. Strip v-model="..." (always client-side)
-    let model_re = Regex::new(r#"\s*v-model="[^"]*""#).unwrap();
-    result = model_re.replace_all(&result, "").to_string();
-
-    // 4. Process v-show: signal-bound → evaluate initial value; model-bound → preserve
-    let show_re = Regex::new(r#"\s*v-show="([^"]*)""#).unwrap();
-    result = show_re.replace_all(&result, |caps: &regex::Captures| {
-        let expr = &caps[1];
-        if is_signal_expr(expr, reactive_names) {
-            // Signal-bound: evaluate with initial value (same as render mode)
-            // Initial value for ref(false) is "false", ref(true) is "true"
-            let is_falsy = expr == "false" || expr == "0";
-            if is_falsy {
-                r#" style="display:none""#.to_string()
+        // 2. Strip <Transition> / <TransitionGroup> wrapper tags
+        result = strip_transition_tags(&result);
+
+        // 3. Strip v-model="..." (always client-side)
+        let model_re = Regex::new(r#"(?i)\s*v-model="[^"]*""#).unwrap();
+        result = model_re.replace_all(&result, "").to_string();
+
+        // 4. Process v-show: signal-bound → evaluate initial value; model-bound → preserve
+        let show_re = Regex::new(r#"(?i)\s*v-show="([^"]*)""#).unwrap();
+        result = show_re.replace_all(&result, |caps: &regex::Captures| {
+            let expr = &caps[1];
+            if is_signal_expr(expr, reactive_names) {
+                // Signal-bound: evaluate with initial value (same as render mode)
+                // Initial value for ref(false) is "false", ref(true) is "true"
+                let is_falsy = expr == "false" || expr == "0";
+                if is_falsy {
+                    r#" style="display:none""#.to_string()
+                } else {
+                    // Default: initially hidden for safety (signal will show on client)
+                    // Check if the signal initial value is falsy
+                    r#" style="display:none""#.to_string()
+                }
             } else {
-                // Default: initially hidden for safety (signal will show on client)
-                // Check if the signal initial value is falsy
-                r#" style="display:none""#.to_string()
+                // Model-bound: preserve for Java
+                caps[0].to_string()
             }
-        } else {
-            // Model-bound: preserve for Java
-            caps[0].to_string()
-        }
-    }).to_string();
-
-    // 5. Process v-if: signal-bound → evaluate; model-bound → preserve
-    let vif_re = Regex::new(r#"\s*v-if="([^"]*)""#).unwrap();
-    result = vif_re.replace_all(&result, |caps: &regex::Captures| {
-        let expr = &caps[1];
-        if is_signal_expr(expr, reactive_names) {
-            String::new() // Signal-bound: strip (JS handles it)
-        } else {
-            caps[0].to_string() // Model-bound: preserve
-        }
-    }).to_string();
+        }).to_string();
 
-    // 6. Strip signal-bound :class/:style (JS handles them); preserve model-bound
-    let bind_class_re = Regex::new(r#"\s*:class="([^"]*)""#).unwrap();
-    result = bind_class_re.replace_all(&result, |caps: &regex::Captures| {
-        let expr = &caps[1];
-        if is_signal_expr(expr, reactive_names) {
-            String::new()
-        } else {
-            caps[0].to_string()
-        }
-    }).to_string();
+        // 5. Process v-if: signal-bound → evaluate; model-bound → preserve
+        let vif_re = Regex::new(r#"(?i)\s*v-if="([^"]*)""#).unwrap();
+        result = vif_re.replace_all(&result, |caps: &regex::Captures| {
+            let expr = &caps[1];
+            if is_signal_expr(expr, reactive_names) {
+                String::new() // Signal-bound: strip (JS handles it)
+            } else {
+                caps[0].to_string() // Model-bound: preserve
+            }
+        }).to_string();
 
-    let bind_style_re = Regex::new(r#"\s*:style="([^"]*)""#).unwrap();
-    result = bind_style_re.replace_all(&result, |caps: &regex::Captures| {
-        let expr = &caps[1];
-        if is_signal_expr(expr, reactive_names) {
-            String::new()
-        } else {
-            caps[0].to_string()
-        }
-    }).to_string();
+        // 6. Strip signal-bound :class/:style (JS handles them); preserve model-bound
+        let bind_class_re = Regex::new(r#"\s*:class="([^"]*)""#).unwrap();
+        result = bind_class_re.replace_all(&result, |caps: &regex::Captures| {
+            let expr = &caps[1];
+            if is_signal_expr(expr, reactive_names) {
+                String::new()
+            } else {
+                caps[0].to_string()
+            }
+        }).to_string();
 
-    // 7. Interpolate signal {{ }} with initial values; preserve model {{ }}
-    let initial_values = extract_signal_initial_values(&result, reactive_names);
-    result = interpolate_signals_only(&result, &initial_values);
+        let bind_style_re = Regex::new(r#"\s*:style="([^"]*)""#).unwrap();
+        result = bind_style_re.replace_all(&result, |caps: &regex::Captures| {
+            let expr = &caps[1];
+            if is_signal_expr(expr, reactive_names) {
+                String::new()
+            } else {
+                caps[0].to_string()
+            }
+        }).to_string();
 
-    result
+        result
+    })
 }
 
 /// Check if an expression references any signal name.
 fn is_signal_expr(expr: &str, reactive_names: &[String]) -> bool {
-    reactive_names.iter().any(|name| {
-        let re = Regex::new(&format!(r"\b{}\b", regex::escape(name))).unwrap();
-        re.is_match(expr)
-    })
-}
-
-/// Build a map of signal_name → initial display value from the HTML's script_setup context.
-/// Uses the extract_initial_values function from van-signal-gen.
-fn extract_signal_initial_values(_html: &str, _reactive_names: &[String]) -> HashMap<String, String> {
-    // This is called after inject_signal_comments, but we need the script to get initial values.
-    // The caller should pass initial values directly. For now return empty —
-    // the compile() function handles this via augment_data_with_signals pattern.
-    HashMap::new()
+    reactive_names.iter().any(|name| van_signal_gen::references_name(expr, name))
 }
 
 /// Replace only signal-bound {{ name }} with initial values, preserve model-bound {{ }}.
@@ -479,108 +1156,47 @@ fn interpolate_signals_only(html: &str, initial_values: &HashMap<String, String>
         return html.to_string();
     }
     let re = Regex::new(r"\{\{\s*([^}]+?)\s*\}\}").unwrap();
-    re.replace_all(html, |caps: &regex::Captures| {
-        let expr = caps[1].trim();
-        if let Some(val) = initial_values.get(expr) {
-            val.clone()
-        } else {
-            caps[0].to_string() // Not a signal → preserve for Java
-        }
-    }).to_string()
-}
-
-/// Clean up "dirty" resolved HTML by:
-/// 1. Stripping `@event="..."` attributes
-/// 2. Processing `v-show="expr"` / `v-if="expr"` → evaluate initial value, add
-///    `style="display:none"` if falsy, remove the directive attribute
-/// 3. Interpolating remaining `{{ expr }}` expressions
-fn cleanup_html(html: &str, data: &Value) -> String {
-    let mut result = html.to_string();
-
-    // 1. Strip @event="..." attributes
-    let event_re = Regex::new(r#"\s*@\w+="[^"]*""#).unwrap();
-    result = event_re.replace_all(&result, "").to_string();
-
-    // 1b. Strip <Transition> / </Transition> wrapper tags (keep inner content)
-    let transition_re = Regex::new(r#"</?[Tt]ransition[^>]*>"#).unwrap();
-    result = transition_re.replace_all(&result, "").to_string();
-
-    // 1c. Strip :key="..." attributes (from v-for)
-    let key_re = Regex::new(r#"\s*:key="[^"]*""#).unwrap();
-    result = key_re.replace_all(&result, "").to_string();
-
-    // 2. Process v-show/v-if: evaluate initial value, add display:none if falsy
-    let show_re = Regex::new(r#"\s*v-(?:show|if)="([^"]*)""#).unwrap();
-    result = show_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let expr = &caps[1];
-            let value = resolve_path(data, expr);
-            let is_falsy = value == "0"
-                || value == "false"
-                || value.is_empty()
-                || value == "null"
-                || value.contains("{{");
-            if is_falsy {
-                r#" style="display:none""#.to_string()
+    raw::outside_raw(html, |html| {
+        re.replace_all(html, |caps: &regex::Captures| {
+            let expr = caps[1].trim();
+            if let Some(val) = initial_values.get(expr) {
+                escape_in_context(&html[..caps.get(0).unwrap().start()], val, false)
             } else {
-                String::new()
+                caps[0].to_string() // Not a signal → preserve for Java
             }
-        })
-        .to_string();
+        }).to_string()
+    })
+}
 
-    // 2b. Process v-else-if="expr" (same as v-if)
-    let else_if_re = Regex::new(r#"\s*v-else-if="([^"]*)""#).unwrap();
-    result = else_if_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let expr = &caps[1];
-            let value = resolve_path(data, expr);
-            let is_falsy = value == "0"
-                || value == "false"
-                || value.is_empty()
-                || value == "null"
-                || value.contains("{{");
-            if is_falsy {
-                r#" style="display:none""#.to_string()
-            } else {
-                String::new()
-            }
-        })
-        .to_string();
-
-    // 2c. Strip v-else (unconditional — attribute with no value)
-    let else_re = Regex::new(r#"\s+v-else"#).unwrap();
-    result = else_re.replace_all(&result, "").to_string();
-
-    // 2d. Strip v-html="..." and v-text="..." attributes
-    let vhtml_re = Regex::new(r#"\s*v-html="[^"]*""#).unwrap();
-    result = vhtml_re.replace_all(&result, "").to_string();
-    let vtext_re = Regex::new(r#"\s*v-text="[^"]*""#).unwrap();
-    result = vtext_re.replace_all(&result, "").to_string();
-
-    // 2e. Strip :class="..." and :style="..." attributes
-    let bind_class_re = Regex::new(r#"\s*:class="[^"]*""#).unwrap();
-    result = bind_class_re.replace_all(&result, "").to_string();
-    let bind_style_re = Regex::new(r#"\s*:style="[^"]*""#).unwrap();
-    result = bind_style_re.replace_all(&result, "").to_string();
-
-    // 2f. Strip v-model="..." and optionally set initial value
-    let model_re = Regex::new(r#"\s*v-model="([^"]*)""#).unwrap();
-    result = model_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let expr = &caps[1];
-            let value = resolve_path(data, expr);
-            if value.contains("{{") {
-                String::new()
-            } else {
-                format!(r#" value="{}""#, value)
+/// Drop signal `{{ expr }}` left inside attribute values (those without a
+/// plain initial value, e.g. `{{ id + 1 }}`) so no literal braces ship; the
+/// attribute's `setAttribute` effect fills it in on the client.
+fn blank_signal_attr_mustaches(html: &str, reactive_names: &[String]) -> String {
+    let re = Regex::new(r"\{\{\s*([^}]+?)\s*\}\}").unwrap();
+    raw::outside_raw(html, |html| {
+        let mut result = String::with_capacity(html.len());
+        let mut last = 0;
+        for caps in re.captures_iter(html) {
+            let m = caps.get(0).unwrap();
+            result.push_str(&html[last..m.start()]);
+            if !(in_open_tag(&result) && is_signal_expr(&caps[1], reactive_names)) {
+                result.push_str(m.as_str());
             }
-        })
-        .to_string();
-
-    // 3. Interpolate remaining {{ expr }}
-    result = interpolate(&result, data);
+            last = m.end();
+        }
+        result.push_str(&html[last..]);
+        result
+    })
+}
 
-    result
+/// Whether the end of `html` lies inside an opening tag, i.e. a `{{ }}`
+/// appended there would be part of an attribute value.
+pub(crate) fn in_open_tag(html: &str) -> bool {
+    match (html.rfind('<'), html.rfind('>')) {
+        (Some(lt), Some(gt)) => lt > gt,
+        (Some(_), None) => true,
+        _ => false,
+    }
 }
 
 /// Escape HTML special characters in text content.
@@ -599,14 +1215,91 @@ pub fn escape_html(text: &str) -> String {
     result
 }
 
+/// Escape text for an attribute value: everything [`escape_html`] escapes
+/// plus the backtick, which some parsers treat as an attribute quote.
+pub fn escape_attr(text: &str) -> String {
+    escape_html(text).replace('`', "&#96;")
+}
+
+static SCRIPT_END: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)</(script)").unwrap());
+
+/// Make JS safe to inline in a `<script>` element. `</script` (in any case)
+/// would end the element early and `<!--` can make the HTML parser run past
+/// the real end tag, so they become `<\/script` and `<\x21--`, which read
+/// the same inside JS strings, template literals and regexes.
+///
+/// JSON meant for `JSON.parse` needs `<` as `\u003c` instead.
+pub fn escape_script(code: &str) -> String {
+    SCRIPT_END.replace_all(code, "<\\/$1").replace("<!--", "<\\x21--")
+}
+
+/// A `{{{ }}}` value, sanitized when the page opted in.
+pub(crate) fn raw_html(value: String, sanitize: bool) -> String {
+    if sanitize {
+        sanitize_html(&value)
+    } else {
+        value
+    }
+}
+
+/// Escape `value` for the position right after `before`.
+///
+/// Inside an opening tag the value is attribute-escaped (even for raw
+/// `{{{ }}}`), and quoted when it is the whole of an unquoted value, so
+/// `href={{ url }}` cannot smuggle in further attributes. Elsewhere `{{ }}`
+/// is HTML-escaped and raw values are kept as-is.
+pub(crate) fn escape_in_context(before: &str, value: &str, raw: bool) -> String {
+    if in_open_tag(before) {
+        if starts_unquoted_value(before) {
+            format!("\"{}\"", escape_attr(value))
+        } else {
+            escape_attr(value)
+        }
+    } else if raw {
+        value.to_string()
+    } else {
+        escape_html(value)
+    }
+}
+
+/// Whether `before`, which ends inside an opening tag, ends right after the
+/// `=` of an unquoted attribute (`href=`), rather than inside a quoted value
+/// (`href="/x?q=`) or partway through an unquoted one (`href=/x?q=`).
+fn starts_unquoted_value(before: &str) -> bool {
+    let tag = &before[before.rfind('<').unwrap_or(0)..];
+    let mut quote = None;
+    let mut token_start = 0;
+    for (i, c) in tag.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c.is_whitespace() => token_start = i + c.len_utf8(),
+            None => {}
+        }
+    }
+    let token = &tag[token_start..];
+    quote.is_none() && token.find('=') == Some(token.len().wrapping_sub(1))
+}
+
 /// Perform `{{ expr }}` / `{{{ expr }}}` interpolation with dot-path resolution.
 ///
 /// - `{{ expr }}` — HTML-escaped output (default, safe)
 /// - `{{{ expr }}}` — raw output (no escaping, for trusted HTML content)
 ///
 /// Supports paths like `user.name` which resolve to `data["user"]["name"]`.
-/// Unresolved expressions are left as-is.
+/// Unresolved expressions are left as-is, and so is `v-raw` content.
 pub fn interpolate(template: &str, data: &Value) -> String {
+    interpolate_with(template, data, false, &DataUsage::default())
+}
+
+/// [`interpolate`], optionally passing `{{{ }}}` output through
+/// [`sanitize_html`] first, and recording the data paths it reads in `usage`.
+pub(crate) fn interpolate_with(template: &str, data: &Value, sanitize: bool, usage: &DataUsage) -> String {
+    raw::outside_raw(template, |part| interpolate_part(part, data, sanitize, usage))
+}
+
+fn interpolate_part(template: &str, data: &Value, sanitize: bool, usage: &DataUsage) -> String {
     let mut result = String::with_capacity(template.len());
     let mut rest = template;
 
@@ -619,12 +1312,16 @@ pub fn interpolate(template: &str, data: &Value) -> String {
             if let Some(end) = after_open.find("}}}") {
                 let expr = after_open[..end].trim();
                 if let Some(translated) = try_resolve_t(expr, data) {
-                    result.push_str(&translated);
+                    let value = escape_in_context(&result, &raw_html(translated, sanitize), true);
+                    result.push_str(&value);
                 } else if expr.trim().starts_with("$t(") {
                     // $t() but no $i18n data — preserve for runtime resolution
                     result.push_str(&format!("{{{{{{{}}}}}}}", expr));
                 } else {
-                    result.push_str(&resolve_path(data, expr));
+                    usage.record_expr(expr, data);
+                    let value = raw_html(resolve_path(data, expr), sanitize);
+                    let value = escape_in_context(&result, &value, true);
+                    result.push_str(&value);
                 }
                 rest = &after_open[end + 3..];
             } else {
@@ -636,17 +1333,26 @@ pub fn interpolate(template: &str, data: &Value) -> String {
             if let Some(end) = after_open.find("}}") {
                 let expr = after_open[..end].trim();
                 if let Some(translated) = try_resolve_t(expr, data) {
-                    result.push_str(&escape_html(&translated));
+                    let value = escape_in_context(&result, &translated, false);
+                    result.push_str(&value);
                 } else if expr.trim().starts_with("$t(") {
                     // $t() but no $i18n data — preserve for runtime resolution
                     result.push_str(&format!("{{{{{}}}}}", expr));
                 } else {
-                    let value = resolve_path(data, expr);
+                    usage.record_expr(expr, data);
+                    let mut value = resolve_path(data, expr);
+                    if value.contains("{{") {
+                        // Not a data path: evaluate it when all the data it reads is there
+                        if let Some(evaluated) = expr::evaluate(expr, data).filter(|e| !e.missing) {
+                            value = expr::display(&evaluated.value);
+                        }
+                    }
                     if value.contains("{{") {
                         // Value is an unresolved or compile expression — preserve for Java
                         result.push_str(&value);
                     } else {
-                        result.push_str(&escape_html(&value));
+                        let value = escape_in_context(&result, &value, false);
+                        result.push_str(&value);
                     }
                 }
                 rest = &after_open[end + 2..];
@@ -719,11 +1425,53 @@ pub fn resolve_path(data: &Value, path: &str) -> String {
     }
 }
 
+/// Compile this module's lazy regexes now (see [`crate::warmup`]), returning
+/// how many there are.
+pub(crate) fn warmup() -> usize {
+    let lazies = [&SHOW_RE, &VIF_RE, &ELSE_IF_RE, &ELSE_RE, &VHTML_RE, &VTEXT_RE, &BIND_CLASS_RE, &BIND_STYLE_RE, &KEY_RE, &SCRIPT_END];
+    for lazy in lazies {
+        LazyLock::force(lazy);
+    }
+    lazies.len()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_asset_href() {
+        let css = "/assets/css/x.css";
+        assert_eq!(asset_href(css, "pages/about", false), css);
+        assert_eq!(asset_href(css, "pages/about", true), "../assets/css/x.css");
+        assert_eq!(asset_href(css, "pages/blog/index", true), "../assets/css/x.css");
+        assert_eq!(asset_href("https://cdn.example.com/x.css", "pages/about", true), "https://cdn.example.com/x.css");
+    }
+
+    #[test]
+    fn test_fill_data_else_chains() {
+        let html = r#"<div><p v-if="open">A</p><p v-else-if="count > 1">B</p><p v-else>C</p></div><i v-if="open"><b v-if="!open">x</b><b v-else>y</b></i><i v-else>z</i>"#;
+        let hidden = r#" style="display:none""#;
+        assert_eq!(
+            fill_data(html, &json!({ "open": true, "count": 2 })),
+            format!("<div><p>A</p><p{hidden}>B</p><p{hidden}>C</p></div><i><b{hidden}>x</b><b>y</b></i><i{hidden}>z</i>")
+        );
+        assert_eq!(
+            fill_data(html, &json!({ "open": false, "count": 2 })),
+            format!("<div><p{hidden}>A</p><p>B</p><p{hidden}>C</p></div><i{hidden}><b>x</b><b{hidden}>y</b></i><i>z</i>")
+        );
+        assert_eq!(
+            fill_data(html, &json!({ "open": false, "count": 0 })),
+            format!("<div><p{hidden}>A</p><p{hidden}>B</p><p>C</p></div><i{hidden}><b>x</b><b{hidden}>y</b></i><i>z</i>")
+        );
+        // A sibling without a directive ends the chain
+        assert_eq!(
+            fill_data(r#"<p v-if="open">A</p><hr><p v-else>C</p>"#, &json!({ "open": true })),
+            "<p>A</p><hr><p>C</p>"
+        );
+    }
+
     #[test]
     fn test_interpolate_simple() {
         let data = json!({"name": "World"});
@@ -743,41 +1491,72 @@ mod tests {
     }
 
     #[test]
-    fn test_cleanup_html_strips_events() {
+    fn test_cleanup_html_compile_strips_events() {
         let html = r#"<button @click="increment">+1</button>"#;
-        let data = json!({});
-        let clean = cleanup_html(html, &data);
-        assert_eq!(clean, "<button>+1</button>");
+        assert_eq!(cleanup_html_compile(html), "<button>+1</button>");
+        assert_eq!(cleanup_html_compile_smart(html, &[]), "<button>+1</button>");
     }
 
     #[test]
-    fn test_cleanup_html_v_show_falsy() {
+    fn test_cleanup_html_compile_v_show_falsy() {
         let html = r#"<p v-show="visible">Hello</p>"#;
-        let data = json!({"visible": false});
-        let clean = cleanup_html(html, &data);
+        let compiled = cleanup_html_compile(html);
+        assert_eq!(compiled, html, "model-bound v-show is kept for the SSR pass");
+        let clean = fill_data(&compiled, &json!({"visible": false}));
         assert!(!clean.contains("v-show"));
         assert!(clean.contains(r#"style="display:none""#));
     }
 
     #[test]
-    fn test_cleanup_html_v_show_truthy() {
+    fn test_cleanup_html_compile_v_show_truthy() {
         let html = r#"<p v-show="visible">Hello</p>"#;
-        let data = json!({"visible": true});
-        let clean = cleanup_html(html, &data);
-        assert!(!clean.contains("v-show"));
+        let clean = fill_data(&cleanup_html_compile(html), &json!({"visible": true}));
         assert_eq!(clean, "<p>Hello</p>");
     }
 
     #[test]
-    fn test_cleanup_html_strips_transition_tags() {
+    fn test_cleanup_html_compile_v_show_signal() {
+        let html = r#"<p v-show="open">Hi</p><p v-show="ctx.open">Hi</p>"#;
+        assert_eq!(
+            cleanup_html_compile_smart(html, &["open".to_string()]),
+            r#"<p style="display:none">Hi</p><p v-show="ctx.open">Hi</p>"#
+        );
+    }
+
+    #[test]
+    fn test_cleanup_html_compile_strips_transition_tags() {
         let html = r#"<div><Transition name="slide"><p v-show="open">Hi</p></Transition></div>"#;
-        let data = json!({"open": false});
-        let clean = cleanup_html(html, &data);
+        let clean = fill_data(&cleanup_html_compile(html), &json!({"open": false}));
         assert!(!clean.contains("Transition"));
         assert!(!clean.contains("transition"));
         assert!(clean.contains("<p"));
     }
 
+    #[test]
+    fn test_strip_key_attrs() {
+        assert_eq!(
+            strip_key_attrs(r#"<li :key="a" v-bind:key="b" xlink:key="c" data-key="d">x</li>"#),
+            r#"<li xlink:key="c" data-key="d">x</li>"#
+        );
+    }
+
+    #[test]
+    fn test_strip_transition_group_tags() {
+        assert_eq!(
+            strip_transition_tags(r#"<TransitionGroup name="list"><li>a</li></TransitionGroup><transition-group><li>b</li></transition-group><Teleport to=".toasts"><p>c</p></Teleport><transitioned-card />"#),
+            "<li>a</li><li>b</li><p>c</p><transitioned-card />"
+        );
+    }
+
+    #[test]
+    fn test_escape_script() {
+        assert_eq!(
+            escape_script(r#"var a = "</script><!-- x"; var b = '</SCRIPT >';"#),
+            r#"var a = "<\/script><\x21-- x"; var b = '<\/SCRIPT >';"#
+        );
+        assert_eq!(escape_script("if (a < b) { c </ d }"), "if (a < b) { c </ d }");
+    }
+
     #[test]
     fn test_interpolate_escapes_html() {
         let data = json!({"desc": "<script>alert('xss')</script>"});
@@ -793,6 +1572,50 @@ mod tests {
         assert_eq!(interpolate("{{{ html }}}", &data), "<b>bold</b>");
     }
 
+    #[test]
+    fn test_interpolate_triple_mustache_in_attribute_escaped() {
+        let data = json!({"html": "\"><b>"});
+        assert_eq!(
+            interpolate("<a title=\"{{{ html }}}\">{{{ html }}}</a>", &data),
+            "<a title=\"&quot;&gt;&lt;b&gt;\">\"><b></a>"
+        );
+    }
+
+    #[test]
+    fn test_escape_attr() {
+        assert_eq!(escape_attr(r#"a "b" 'c' <d> & `e`"#), "a &quot;b&quot; &#39;c&#39; &lt;d&gt; &amp; &#96;e&#96;");
+    }
+
+    #[test]
+    fn test_interpolate_unquoted_attribute_is_quoted() {
+        let data = json!({"url": "/x onmouseover=alert(1)"});
+        assert_eq!(
+            interpolate("<a href={{ url }}>{{ url }}</a>", &data),
+            "<a href=\"/x onmouseover=alert(1)\">/x onmouseover=alert(1)</a>"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_inside_attribute_value_is_not_quoted() {
+        let data = json!({"q": "a&b\"", "a": "x y", "b": 2});
+        assert_eq!(
+            interpolate(r#"<a href="/x?q={{ q }}">x</a>"#, &data),
+            r#"<a href="/x?q=a&amp;b&quot;">x</a>"#
+        );
+        assert_eq!(
+            interpolate(r#"<a href="/s?q={{a}}&p={{b}}" title='p={{ b }}'>s</a>"#, &data),
+            r#"<a href="/s?q=x y&p=2" title='p=2'>s</a>"#
+        );
+        assert_eq!(interpolate("<a href=/s?p={{ b }}>s</a>", &data), "<a href=/s?p=2>s</a>");
+        assert_eq!(interpolate("<a title=\"a=b\" href={{ b }}>s</a>", &data), "<a title=\"a=b\" href=\"2\">s</a>");
+    }
+
+    #[test]
+    fn test_cleanup_html_compile_strips_model() {
+        assert_eq!(cleanup_html_compile(r#"<input v-model="form.name">"#), "<input>");
+        assert_eq!(cleanup_html_compile_smart(r#"<input V-Model="form.name">"#, &[]), "<input>");
+    }
+
     #[test]
     fn test_interpolate_mixed_escaped_and_raw() {
         let data = json!({"safe": "<b>bold</b>", "text": "<em>hi</em>"});
@@ -875,16 +1698,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_document() {
+        assert!(is_document("\n  <html lang=\"en\"><body></body></html>"));
+        assert!(is_document("<!DOCTYPE html>\n<HTML>"));
+        assert!(is_document("<!-- START: layouts/default.van --><html>"));
+        assert!(!is_document("<h1>Docs</h1><pre><code><html></code></pre>"));
+        assert!(!is_document("<html-viewer></html-viewer>"));
+    }
+
+    #[test]
+    fn test_unwrap_template_groups() {
+        assert_eq!(
+            unwrap_template_groups(r#"<nav><template v-if="user.loggedIn"><a href="/me">Profile</a><!--v:0--><a><img src="x.png">Logout</a></template><template v-else><br/><a>Login</a></template><template id="row"><tr></tr></template></nav>"#),
+            r#"<nav><a v-if="user.loggedIn" href="/me">Profile</a><!--v:0--><a v-if="user.loggedIn"><img src="x.png">Logout</a><br v-else/><a v-else>Login</a><template id="row"><tr></tr></template></nav>"#
+        );
+    }
+
+    #[test]
+    fn test_compile_assets_hashed_names() {
+        let resolved = ResolvedComponent {
+            html: "<h1>Hello</h1>".to_string(),
+            styles: vec!["h1 { color: red; }".to_string()],
+            style_sources: vec!["index.van".to_string()],
+            script_setup: None,
+            module_imports: Vec::new(),
+            warnings: Vec::new(),
+            dependencies: Vec::new(),
+        };
+        let page = compile_assets(&resolved, "index", "/assets", &CompileOptions::default()).unwrap();
+        let css_path = format!("/assets/css/index.{}.css", content_hash("h1 { color: red; }"));
+        assert_eq!(css_path.len(), "/assets/css/index..css".len() + 12);
+        assert_eq!(page.assets[&css_path], "h1 { color: red; }");
+        assert!(page.html.contains(&css_path));
+    }
+
+    #[test]
+    fn test_render_to_assets_reports_data_usage() {
+        let resolved = ResolvedComponent {
+            html: "<h1>{{ title }}</h1><p>{{ subtitle }}</p><button @click=\"count++\">{{ count }}</button>".to_string(),
+            styles: Vec::new(),
+            style_sources: Vec::new(),
+            script_setup: Some("const count = ref(0)".to_string()),
+            module_imports: Vec::new(),
+            warnings: Vec::new(),
+            dependencies: Vec::new(),
+        };
+        let data = json!({"title": "Hi", "footer": "(c)"});
+        let page = render_to_assets(&resolved, &data, "index", "/assets", &CompileOptions::default()).unwrap();
+        assert!(page.html.contains("<h1>Hi</h1>"));
+        assert_eq!(page.data_used, ["title"]);
+        assert_eq!(page.data_missing, ["subtitle"]);
+    }
+
     #[test]
     fn test_render_to_string_basic() {
         let resolved = ResolvedComponent {
             html: "<h1>Hello</h1>".to_string(),
             styles: vec!["h1 { color: red; }".to_string()],
+            style_sources: vec!["index.van".to_string()],
             script_setup: None,
             module_imports: Vec::new(),
+            warnings: Vec::new(),
+            dependencies: Vec::new(),
         };
         let data = json!({});
-        let html = render_to_string(&resolved, &data, "Van").unwrap();
+        let html = render_to_string(&resolved, &data, "index", &CompileOptions::default()).unwrap();
         assert!(html.contains("<h1>Hello</h1>"));
         assert!(html.contains("h1 { color: red; }"));
         // Should NOT contain client.js WebSocket reload
@@ -1,9 +1,23 @@
 use regex::Regex;
-use serde_json::Value;
-use std::collections::HashMap;
-use van_parser::{add_scope_class, parse_blocks, parse_imports, parse_script_imports, scope_css, scope_id, VanImport};
-
-use crate::render::{escape_html, interpolate, resolve_path as resolve_json_path, try_resolve_t};
+use serde_json::{Map, Value};
+use std::cell::{Cell, RefCell};
+use std::sync::LazyLock;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use van_parser::{
+    add_scope_class, css_selectors, parse_blocks, parse_define_options, parse_bare_imports, parse_imports, parse_script_imports, pascal_to_kebab, scope_css, scope_id, PropDef,
+    VanBlock, VanImport,
+};
+
+use van_signal_gen::ModuleCode;
+
+use crate::audit;
+use crate::expr;
+use crate::format::tag_end;
+use crate::markdown::render_markdown;
+use crate::raw;
+use crate::render::{escape_html, escape_in_context, interpolate, interpolate_with, raw_html, resolve_path as resolve_json_path, try_resolve_t};
+use crate::usage::DataUsage;
+use crate::{CompileOptions, PageError};
 
 const MAX_DEPTH: usize = 10;
 
@@ -16,6 +30,12 @@ pub struct ResolvedModule {
     pub content: String,
     /// Whether this is a type-only import (should be erased).
     pub is_type_only: bool,
+    /// `(local, exported)` names bound by the scripts importing this module.
+    pub bindings: Vec<(String, String)>,
+    /// For a bare specifier the import map sends to a URL: that URL. The
+    /// browser loads the module, so `path` is the specifier and `content` is
+    /// empty.
+    pub url: Option<String>,
 }
 
 /// The result of resolving a `.van` file (with or without imports).
@@ -25,10 +45,107 @@ pub struct ResolvedComponent {
     pub html: String,
     /// Collected CSS styles from this component and all descendants.
     pub styles: Vec<String>,
+    /// Virtual path of the component each entry of `styles` came from.
+    pub style_sources: Vec<String>,
     /// The `<script setup>` content (for signal generation).
     pub script_setup: Option<String>,
-    /// Resolved non-component module imports (.ts/.js files).
+    /// Resolved non-component module imports (.ts/.js files), one entry per
+    /// normalized path even when several components import the same module.
     pub module_imports: Vec<ResolvedModule>,
+    /// Non-fatal diagnostics (e.g. unset environment variables), filled in at the top level.
+    pub warnings: Vec<String>,
+    /// Every file the result was built from (entry, components, modules), sorted.
+    /// Filled in at the top level.
+    pub dependencies: Vec<String>,
+}
+
+/// How debug mode marks where inserted components and slots came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OriginMarkers {
+    /// `<!-- START: components/card.van -->…<!-- END: components/card.van -->`
+    #[default]
+    Comments,
+    /// `data-van-origin="components/card.van"` on the fragment's first element,
+    /// for DOM-inspection tooling. Fragments without an element are left unmarked.
+    Attributes,
+}
+
+impl std::str::FromStr for OriginMarkers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "comments" => Ok(Self::Comments),
+            "attributes" => Ok(Self::Attributes),
+            other => Err(format!(
+                "unknown origin markers \"{other}\" (expected comments or attributes)"
+            )),
+        }
+    }
+}
+
+/// State shared across one recursive resolution.
+struct ResolveCtx<'a> {
+    files: &'a HashMap<String, String>,
+    /// Reactive names from every `.van` file, left alone during interpolation.
+    reactive_names: Vec<String>,
+    /// Origin markers to emit, `None` outside debug mode.
+    debug: Option<OriginMarkers>,
+    file_origins: &'a HashMap<String, String>,
+    env: &'a HashMap<String, String>,
+    /// Sanitize `{{{ }}}` output instead of warning about it.
+    sanitize_html: bool,
+    /// Custom element tags (or `prefix-*` patterns) not to report as unknown.
+    custom_elements: &'a [String],
+    /// Fail on denied script constructs instead of warning about them.
+    strict_js: bool,
+    /// Where the data paths the templates read are recorded.
+    usage: &'a DataUsage,
+    /// Bare module specifiers mapped to URLs or files-map paths.
+    import_map: &'a BTreeMap<String, String>,
+    warnings: RefCell<Vec<String>>,
+    dependencies: RefCell<BTreeSet<String>>,
+    /// Components being resolved, outermost first, with the data each got.
+    stack: RefCell<Vec<(String, String)>>,
+    /// Set when resolution failed for nesting past [`MAX_DEPTH`].
+    too_deep: Cell<bool>,
+}
+
+impl ResolveCtx<'_> {
+    /// Push a component onto the resolution stack.
+    ///
+    /// A component may appear inside itself (e.g. a tree node rendering its
+    /// children), but reaching it again with the very same data can only
+    /// repeat forever, so that is reported as a cycle.
+    fn enter(&self, path: &str, data: &Value) -> Result<(), String> {
+        let frame = (path.to_string(), data.to_string());
+        let mut stack = self.stack.borrow_mut();
+        if let Some(first) = stack.iter().position(|f| *f == frame) {
+            let chain: Vec<&str> = stack[first..]
+                .iter()
+                .map(|(p, _)| p.as_str())
+                .chain([path])
+                .collect();
+            return Err(format!("Component cycle detected: {}", chain.join(" -> ")));
+        }
+        stack.push(frame);
+        Ok(())
+    }
+
+    fn leave(&self) {
+        self.stack.borrow_mut().pop();
+    }
+
+    fn depend(&self, path: &str) {
+        self.dependencies.borrow_mut().insert(path.to_string());
+    }
+
+    fn warn(&self, message: String) {
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings.contains(&message) {
+            warnings.push(message);
+        }
+    }
 }
 
 // ─── Multi-file resolve (HashMap-based, no FS) ─────────────────────────
@@ -42,7 +159,7 @@ pub fn resolve_with_files(
     files: &HashMap<String, String>,
     data: &Value,
 ) -> Result<ResolvedComponent, String> {
-    resolve_with_files_inner(entry_path, files, data, false, &HashMap::new())
+    resolve_with_options(entry_path, files, data, &CompileOptions::default())
 }
 
 /// Like `resolve_with_files`, but with debug HTML comments showing component/slot boundaries.
@@ -55,16 +172,37 @@ pub fn resolve_with_files_debug(
     data: &Value,
     file_origins: &HashMap<String, String>,
 ) -> Result<ResolvedComponent, String> {
-    resolve_with_files_inner(entry_path, files, data, true, file_origins)
+    let options = CompileOptions {
+        debug: true,
+        file_origins: file_origins.clone(),
+        ..CompileOptions::default()
+    };
+    resolve_with_options(entry_path, files, data, &options)
 }
 
-fn resolve_with_files_inner(
+/// Like `resolve_with_files`, but honoring every field of [`CompileOptions`].
+///
+/// `options.env` is exposed as `env` in the data context, `{{ env.VAN_* }}`
+/// mustaches are replaced in every template, and `import.meta.env.*` references
+/// are inlined into scripts. Unset variables become empty strings with a warning.
+pub fn resolve_with_options(
     entry_path: &str,
     files: &HashMap<String, String>,
     data: &Value,
-    debug: bool,
-    file_origins: &HashMap<String, String>,
+    options: &CompileOptions,
 ) -> Result<ResolvedComponent, String> {
+    resolve_tracked(entry_path, files, data, options, &DataUsage::default()).map_err(String::from)
+}
+
+/// [`resolve_with_options`], recording the data paths the templates read in
+/// `usage`.
+pub(crate) fn resolve_tracked(
+    entry_path: &str,
+    files: &HashMap<String, String>,
+    data: &Value,
+    options: &CompileOptions,
+    usage: &DataUsage,
+) -> Result<ResolvedComponent, PageError> {
     let source = files
         .get(entry_path)
         .ok_or_else(|| format!("Entry file not found: {entry_path}"))?;
@@ -78,11 +216,345 @@ fn resolve_with_files_inner(
             let blk = parse_blocks(content);
             if let Some(ref script) = blk.script_setup {
                 reactive_names.extend(extract_reactive_names(script));
+                reactive_names.extend(store_names(path, script, files));
+            }
+        }
+    }
+
+    usage.ignore(&reactive_names);
+    let ctx = ResolveCtx {
+        files,
+        reactive_names,
+        debug: options.debug.then_some(options.origin_markers),
+        file_origins: &options.file_origins,
+        env: &options.env,
+        sanitize_html: options.sanitize_html,
+        custom_elements: &options.custom_elements,
+        strict_js: options.strict_js,
+        usage,
+        import_map: &options.import_map,
+        warnings: RefCell::new(Vec::new()),
+        dependencies: RefCell::new(BTreeSet::new()),
+        stack: RefCell::new(Vec::new()),
+        too_deep: Cell::new(false),
+    };
+    let data = with_env(data, &options.env);
+    let source = with_auto_layout(source, entry_path, options).unwrap_or_else(|| source.clone());
+    let mut resolved = resolve_recursive(&source, &data, entry_path, 0, &ctx).map_err(|e| {
+        if ctx.too_deep.get() { PageError::MaxDepth(e) } else { PageError::Invalid(e) }
+    })?;
+    // Nothing passes slots to the page itself
+    resolved.html = slot_conditions(&resolved.html, &SlotMap::new());
+    // Moved before bindings are computed, so their paths match the output
+    resolved.html = teleport(&resolved.html, entry_path, &ctx);
+    // Global stylesheets go first, whatever order the components resolved in
+    let mut global_css = Vec::new();
+    for path in &options.global_css {
+        match files.get(path) {
+            Some(css) => {
+                ctx.depend(path);
+                global_css.push((css.clone(), path.clone()));
+            }
+            None => ctx.warn(format!("{entry_path}: global stylesheet {path} not found")),
+        }
+    }
+    let (styles, sources): (Vec<_>, Vec<_>) = global_css.into_iter().unzip();
+    resolved.styles.splice(0..0, styles);
+    resolved.style_sources.splice(0..0, sources);
+    // Signal bindings locate elements by position, which an unclosed tag skews
+    if resolved.script_setup.is_some() {
+        for tag in van_signal_gen::unclosed_elements(&resolved.html) {
+            ctx.warn(format!("{entry_path}: <{tag}> has no closing tag and is treated as empty"));
+        }
+    }
+    resolved.warnings = ctx.warnings.into_inner();
+    resolved.dependencies = ctx.dependencies.into_inner().into_iter().collect();
+    Ok(resolved)
+}
+
+/// Tag the auto layout is imported as.
+const AUTO_LAYOUT_TAG: &str = "VanAutoLayout";
+
+/// The entry source with its template wrapped in `options.auto_layout`, or
+/// `None` when it opts out or already uses a component from `layouts/`.
+///
+/// The page's template becomes the layout tag's children, so its top-level
+/// `<template #sidebar>` blocks fill the layout's named slots and the rest
+/// its default slot, exactly as if the page had written the tag itself.
+fn with_auto_layout(source: &str, entry_path: &str, options: &CompileOptions) -> Option<String> {
+    let default_layout = options.auto_layout.as_deref()?;
+    let blocks = parse_blocks(source);
+    let script = blocks.script_setup.as_deref().unwrap_or("");
+    let layout = match parse_define_options(script).into_iter().find(|(key, _)| key == "layout") {
+        Some((_, value)) if value == "false" => return None,
+        Some((_, value)) => value.trim_matches(|c| c == '\'' || c == '"' || c == '`').to_string(),
+        None => default_layout.to_string(),
+    };
+    let has_layout = parse_imports(script)
+        .iter()
+        .any(|imp| resolve_virtual_path(entry_path, &imp.path).split('/').any(|dir| dir == "layouts"));
+    if has_layout {
+        return None;
+    }
+
+    // Import paths are relative to the entry, the layout to the files root
+    let import_path = if layout.starts_with('@') {
+        layout
+    } else {
+        format!("{}{layout}", "../".repeat(entry_path.matches('/').count()))
+    };
+    let import = format!("import {AUTO_LAYOUT_TAG} from '{import_path}'\n");
+    let template = blocks.template.as_deref()?;
+    let wrapped = format!("<{AUTO_LAYOUT_TAG}>{template}</{AUTO_LAYOUT_TAG}>");
+    let source = source.replacen(template, &wrapped, 1);
+    Some(match blocks.script_setup.as_deref() {
+        Some(script) if !script.is_empty() => source.replacen(script, &format!("{import}{script}"), 1),
+        _ => format!("{source}\n<script setup>\n{import}</script>\n"),
+    })
+}
+
+/// Move the content of every `<Teleport to="body">` to the end of `<body>`
+/// (or of the fragment), in document order, dropping the wrapper.
+///
+/// Content teleported out of a `<!--client-only-->` block is re-wrapped in
+/// its own block so asset-mode bindings still find it. Other targets and
+/// `disabled` teleports render their content in place.
+fn teleport(html: &str, entry_path: &str, ctx: &ResolveCtx) -> String {
+    if !html.contains("eleport") {
+        return html.to_string();
+    }
+    let open_re = Regex::new(r"<[Tt]eleport[\s/>]").unwrap();
+    let to_re = Regex::new(r#"\sto\s*=\s*"([^"]*)""#).unwrap();
+    let disabled_re = Regex::new(r"\sdisabled(?:[\s=/>]|$)").unwrap();
+    let mut html = html.to_string();
+    let mut moved = String::new();
+    while let Some(m) = open_re.find(&html) {
+        let start = m.start();
+        let Some(open_len) = tag_end(&html[start..]) else { break };
+        let open_tag = html[start..start + open_len].to_string();
+        if open_tag.ends_with("/>") {
+            html.replace_range(start..start + open_len, "");
+            continue;
+        }
+        let name = &open_tag[1..9];
+        let inner_start = start + open_len;
+        let inner_end = inner_start + find_matching_close_tag(&html[inner_start..], name);
+        let inner = html[inner_start..inner_end].to_string();
+        let end = (inner_end + name.len() + 3).min(html.len());
+        let target = to_re.captures(&open_tag).map(|caps| caps[1].to_string());
+        if disabled_re.is_match(&open_tag) {
+            html.replace_range(start..end, &inner);
+        } else if target.as_deref() == Some("body") {
+            let before = &html[..start];
+            if before.matches("<!--client-only-->").count() > before.matches("<!--/client-only-->").count() {
+                moved.push_str(&format!("<!--client-only-->{inner}<!--/client-only-->"));
+            } else {
+                moved.push_str(&inner);
+            }
+            html.replace_range(start..end, "");
+        } else {
+            let target = target.unwrap_or_default();
+            ctx.warn(format!(
+                "{entry_path}: <Teleport to=\"{target}\"> is not supported, only to=\"body\"; its content is rendered in place"
+            ));
+            html.replace_range(start..end, &inner);
+        }
+    }
+    match html.rfind("</body>") {
+        Some(pos) => html.insert_str(pos, &moved),
+        None => html.push_str(&moved),
+    }
+    html
+}
+
+/// Add `env` to a render-mode data context.
+///
+/// Compile mode (empty data) is left untouched so directives stay preserved;
+/// `{{ env.VAN_* }}` is substituted textually in both modes.
+fn with_env(data: &Value, env: &HashMap<String, String>) -> Value {
+    match data {
+        Value::Object(map) if !map.is_empty() && !env.is_empty() => {
+            let mut map = map.clone();
+            map.entry("env").or_insert_with(|| {
+                Value::Object(
+                    env.iter()
+                        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                        .collect(),
+                )
+            });
+            Value::Object(map)
+        }
+        _ => data.clone(),
+    }
+}
+
+/// Replace `{{ env.VAN_* }}` mustaches with their (escaped) values.
+fn substitute_env(template: &str, current_path: &str, ctx: &ResolveCtx) -> String {
+    if !template.contains("env.") {
+        return template.to_string();
+    }
+    let re = Regex::new(r"\{\{\s*env\.(VAN_[A-Za-z0-9_]*)\s*\}\}").unwrap();
+    raw::outside_raw(template, |part| {
+        re.replace_all(part, |caps: &regex::Captures| {
+            let name = &caps[1];
+            match ctx.env.get(name) {
+                Some(value) => escape_html(value),
+                None => {
+                    ctx.warn(format!("{current_path}: environment variable {name} is not set"));
+                    String::new()
+                }
+            }
+        })
+        .into_owned()
+    })
+}
+
+/// Selectors of a scoped style that need a class, id or tag appearing nowhere
+/// in `template`: warnings, or an error under `scopedStrict`.
+///
+/// Conservative: every word of a `:class` binding counts as a class, and
+/// selectors reaching outside the component (`:deep`, `:global`, slotted
+/// content), attribute selectors and `@keyframes` steps are not checked.
+fn unmatched_selectors(css: &str, template: &str) -> Vec<String> {
+    let comment_re = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    let ignored_re = Regex::new(r"\([^)]*\)|::?[\w-]+").unwrap();
+    let token_re = Regex::new(r"([.#]?)(-?[A-Za-z_][\w-]*)").unwrap();
+    let attr_re = Regex::new(r#"\s(?::|v-bind:)?(class|id)="([^"]*)""#).unwrap();
+    let word_re = Regex::new(r"[\w-]+").unwrap();
+    let tag_re = Regex::new(r"<([A-Za-z][\w-]*)").unwrap();
+
+    let mut classes = HashSet::new();
+    let mut ids = HashSet::new();
+    for caps in attr_re.captures_iter(template) {
+        let names = if &caps[1] == "class" { &mut classes } else { &mut ids };
+        names.extend(word_re.find_iter(caps.get(2).map_or("", |m| m.as_str())).map(|m| m.as_str().to_string()));
+    }
+    let tags: HashSet<String> = tag_re.captures_iter(template).map(|caps| caps[1].to_ascii_lowercase()).collect();
+
+    let css = comment_re.replace_all(css, "");
+    let mut unmatched = Vec::new();
+    for selector in css_selectors(&css) {
+        // Attribute selectors depend on runtime values, and the others match
+        // outside this template
+        let unchecked = [":deep", "::v-deep", ":global", "slotted", ">>>", "["].iter().any(|s| selector.contains(s));
+        if unchecked || selector.starts_with('@') || matches!(selector.as_str(), "from" | "to") || selector.ends_with('%') {
+            continue;
+        }
+        let simple = ignored_re.replace_all(&selector, " ");
+        let matches = token_re.captures_iter(&simple).all(|t| match &t[1] {
+            "." => classes.contains(&t[2]),
+            "#" => ids.contains(&t[2]),
+            _ => tags.contains(&t[2].to_ascii_lowercase()),
+        });
+        if !matches {
+            unmatched.push(selector);
+        }
+    }
+    unmatched
+}
+
+/// Warn about every `v-html` and `{{{ }}}` in a template, so pages that do
+/// not sanitize raw HTML can be audited.
+fn audit_raw_html(template: &str, current_path: &str, ctx: &ResolveCtx) {
+    let vhtml_re = Regex::new(r#"\sv-html="([^"]*)""#).unwrap();
+    let raw_re = Regex::new(r"\{\{\{\s*(.+?)\s*\}\}\}").unwrap();
+    // `v-raw` content is never interpolated, so only the rest is audited
+    raw::outside_raw(template, |part| {
+        for caps in vhtml_re.captures_iter(part) {
+            ctx.warn(format!("{current_path}: v-html=\"{}\" inserts unsanitized HTML", &caps[1]));
+        }
+        for caps in raw_re.captures_iter(part) {
+            ctx.warn(format!("{current_path}: {{{{{{ {} }}}}}} inserts unsanitized HTML", &caps[1]));
+        }
+        part.to_string()
+    });
+}
+
+/// Report the denied constructs in a script (see [`audit`]): an error with
+/// `strict_js`, warnings otherwise. `first_line` is the line of `path` the
+/// script starts on.
+fn audit_js(code: &str, path: &str, first_line: usize, ctx: &ResolveCtx) -> Result<(), String> {
+    let found = audit::unsafe_js(code);
+    if ctx.strict_js {
+        if let Some((line, what)) = found.first() {
+            return Err(format!("{path}:{}: {what} is not allowed (strict_js)", first_line + line - 1));
+        }
+    }
+    for (line, what) in found {
+        ctx.warn(format!("{path}:{}: script uses {what}", first_line + line - 1));
+    }
+    Ok(())
+}
+
+/// Render the `.md` imports of a script setup into a copy of `data`.
+///
+/// Each import's binding holds the rendered HTML (for `{{{ intro }}}`), and
+/// front-matter fields are added where `data` does not already define them.
+/// Returns `None` when there are no Markdown imports.
+fn markdown_data(
+    script: Option<&str>,
+    data: &Value,
+    current_path: &str,
+    ctx: &ResolveCtx,
+) -> Result<Option<Value>, String> {
+    let imports: Vec<_> = script
+        .map(parse_script_imports)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|imp| imp.is_markdown())
+        .collect();
+    if imports.is_empty() {
+        return Ok(None);
+    }
+
+    let mut map = match data {
+        Value::Object(map) => map.clone(),
+        _ => Map::new(),
+    };
+    for imp in imports {
+        let Some(name) = imp.default_name() else {
+            ctx.warn(format!("{current_path}: Markdown import '{}' needs a default binding", imp.path));
+            continue;
+        };
+        let resolved_key = resolve_virtual_path(current_path, &imp.path);
+        let source = ctx
+            .files
+            .get(&resolved_key)
+            .ok_or_else(|| format!("Markdown not found: {} (resolved from '{}')", resolved_key, imp.path))?;
+        ctx.depend(&resolved_key);
+        let doc = render_markdown(source);
+        if let Value::Object(front_matter) = doc.front_matter {
+            for (key, value) in front_matter {
+                map.entry(key).or_insert(value);
             }
         }
+        map.insert(name, Value::String(doc.html));
+    }
+    Ok(Some(Value::Object(map)))
+}
+
+/// Literal `const` declarations in a component's script setup.
+fn script_constants(blocks: &VanBlock) -> Vec<van_signal_gen::ConstDecl> {
+    match setup_script(blocks) {
+        Some(script) if script.contains("const") => van_signal_gen::analyze_script(&script).constants,
+        _ => Vec::new(),
     }
+}
 
-    resolve_recursive(source, data, entry_path, files, 0, &reactive_names, debug, file_origins)
+/// `data` with `constants` added under names it does not already have, or
+/// `None` when there is nothing to add.
+fn with_constants(data: &Value, constants: &[van_signal_gen::ConstDecl]) -> Option<Value> {
+    if constants.is_empty() {
+        return None;
+    }
+    let mut map = match data {
+        Value::Object(map) => map.clone(),
+        _ => Map::new(),
+    };
+    for constant in constants {
+        map.entry(constant.name.clone()).or_insert_with(|| constant.value.clone());
+    }
+    Some(Value::Object(map))
 }
 
 /// Recursively resolve component tags in a `.van` source using in-memory files.
@@ -90,13 +562,29 @@ fn resolve_recursive(
     source: &str,
     data: &Value,
     current_path: &str,
-    files: &HashMap<String, String>,
     depth: usize,
-    reactive_names: &[String],
-    debug: bool,
-    file_origins: &HashMap<String, String>,
+    ctx: &ResolveCtx,
+) -> Result<ResolvedComponent, String> {
+    ctx.enter(current_path, data)?;
+    let outer = ctx.usage.enter(current_path);
+    let resolved = resolve_component(source, data, current_path, depth, ctx);
+    ctx.usage.enter(&outer);
+    ctx.leave();
+    resolved
+}
+
+fn resolve_component(
+    source: &str,
+    data: &Value,
+    current_path: &str,
+    depth: usize,
+    ctx: &ResolveCtx,
 ) -> Result<ResolvedComponent, String> {
+    let ResolveCtx { files, debug, file_origins, .. } = *ctx;
+    let reactive_names = ctx.reactive_names.as_slice();
+    ctx.depend(current_path);
     if depth > MAX_DEPTH {
+        ctx.too_deep.set(true);
         return Err(format!(
             "Component nesting exceeded maximum depth of {MAX_DEPTH}"
         ));
@@ -105,17 +593,49 @@ fn resolve_recursive(
     let blocks = parse_blocks(source);
     let mut template = blocks
         .template
+        .as_deref()
+        .map(|t| substitute_env(t, current_path, ctx))
         .unwrap_or_else(|| "<p>No template block found.</p>".to_string());
+    if !ctx.sanitize_html {
+        audit_raw_html(&template, current_path, ctx);
+    }
+    if let Some(script) = &blocks.script_setup {
+        let start = source.find("<script setup").and_then(|open| source[open..].find(script.as_str()).map(|at| open + at));
+        let first_line = start.map_or(1, |at| source[..at].matches('\n').count() + 1);
+        audit_js(script, current_path, first_line, ctx)?;
+    }
+    for key in &blocks.options.unknown_keys {
+        ctx.warn(format!("{current_path}: unknown defineOptions key \"{key}\""));
+    }
+    if !blocks.options.ssr_interpolation {
+        // Entity-encoded braces still read as `{{` in the browser, but no
+        // server-side pass (nor the Java runtime) interpolates them
+        template = raw::outside_raw(&template, |t| t.replace("{{", "&#123;&#123;"));
+    }
 
     let mut styles: Vec<String> = Vec::new();
+    let mut style_sources: Vec<String> = Vec::new();
+    let mut scope = None;
     if let Some(css) = &blocks.style {
+        let unmatched = if blocks.style_scoped { unmatched_selectors(css, &template) } else { Vec::new() };
+        if blocks.options.scoped_strict && !unmatched.is_empty() {
+            return Err(format!(
+                "{current_path}: scoped style selectors match nothing in the template (scopedStrict): {}",
+                unmatched.join(", ")
+            ));
+        }
+        for selector in &unmatched {
+            ctx.warn(format!("{current_path}: scoped selector \"{selector}\" matches nothing in the template"));
+        }
         if blocks.style_scoped {
-            let id = scope_id(css);
+            let id = file_scope_id(current_path, css, file_origins);
             template = add_scope_class(&template, &id);
             styles.push(scope_css(css, &id));
+            scope = Some(id);
         } else {
             styles.push(css.clone());
         }
+        style_sources.push(current_path.to_string());
     }
 
     // Parse imports from script setup to build tag -> import mapping
@@ -130,11 +650,21 @@ fn resolve_recursive(
         .map(|imp| (imp.tag_name.clone(), imp))
         .collect();
 
+    warn_unknown_tags(&template, &import_map, current_path, ctx);
+
     // Expand v-for directives before component resolution
     // In compile mode (no data): preserve directives for Java runtime
     let compile = matches!(data, Value::Object(map) if map.is_empty());
+    // Literal `const`s from script setup fill in keys the data leaves out; in
+    // compile mode only v-for loops over them are expanded
+    let constants = script_constants(&blocks);
+    let const_data = with_constants(data, &constants);
+    let data = const_data.as_ref().unwrap_or(data);
     if !compile {
-        template = expand_v_for(&template, data);
+        template = expand_v_for(&template, data, ctx.sanitize_html, None, ctx.usage);
+    } else if !constants.is_empty() {
+        let names: Vec<String> = constants.into_iter().map(|c| c.name).collect();
+        template = expand_v_for(&template, data, ctx.sanitize_html, Some(&names), ctx.usage);
     }
 
     // In compile mode, replace <ClientOnly> tags with comment markers
@@ -142,6 +672,10 @@ fn resolve_recursive(
         template = replace_client_only_tags(&template);
     }
 
+    // Markdown imports become data for this template and its slot content
+    let content_data = markdown_data(blocks.script_setup.as_deref(), data, current_path, ctx)?;
+    let data = content_data.as_ref().unwrap_or(data);
+
     // Collect child script_setup and module_imports for merging
     let mut child_scripts: Vec<String> = Vec::new();
     let mut child_module_imports: Vec<ResolvedModule> = Vec::new();
@@ -161,8 +695,14 @@ fn resolve_recursive(
             .get(&resolved_key)
             .ok_or_else(|| format!("Component not found: {} (resolved from '{}')", resolved_key, imp.path))?;
 
-        // Parse props from the tag and build child data context
-        let child_data = parse_props(&tag_info.attrs, data);
+        // Parse props from the tag and build child data context, read as the
+        // child declares them
+        let child_blocks = parse_blocks(component_source);
+        let (child_data, unconverted) = parse_props(&tag_info.attrs, data, &child_blocks.props, ctx.usage);
+        for message in unconverted {
+            ctx.warn(format!("{current_path} -> {resolved_key}: {message}"));
+        }
+        validate_child_props(&tag_info.attrs, data, current_path, &resolved_key, component_source, ctx);
 
         // Parse slot content from children (using parent data + parent import_map)
         let slot_result = parse_slot_content(
@@ -170,11 +710,8 @@ fn resolve_recursive(
             data,
             &imports,
             current_path,
-            files,
             depth,
-            reactive_names,
-            debug,
-            file_origins,
+            ctx,
         )?;
 
         // Recursively resolve the child component
@@ -182,11 +719,8 @@ fn resolve_recursive(
             component_source,
             &child_data,
             &resolved_key,
-            files,
             depth + 1,
-            reactive_names,
-            debug,
-            file_origins,
+            ctx,
         )?;
 
         // Distribute slots into the child's rendered HTML
@@ -198,14 +732,24 @@ fn resolve_recursive(
             let theme = file_origins.get(&slot_key).unwrap_or(&file_theme);
             slot_themes.insert(slot_name.clone(), theme.clone());
         }
-        let with_slots = distribute_slots(&child_resolved.html, &slot_result.slots, debug, &slot_themes);
+        // `debugComments: false` leaves the component (and its slots) unmarked
+        let child_debug = debug.filter(|_| child_blocks.options.debug_comments);
+        let with_slots = distribute_slots(&child_resolved.html, &slot_result.slots, child_debug, &slot_themes);
+        let with_slots = apply_fallthrough(
+            &with_slots,
+            &tag_info.attrs,
+            &child_blocks.props,
+            scope.as_deref(),
+            &format!("{current_path} -> {resolved_key}"),
+            ctx,
+        );
 
         // Replace the component tag with the resolved content
-        let replacement = if debug {
+        let replacement = if let Some(markers) = child_debug {
             let theme_prefix = file_origins.get(&resolved_key)
                 .map(|t| format!("[{t}] "))
                 .unwrap_or_default();
-            format!("<!-- START: {theme_prefix}{resolved_key} -->{with_slots}<!-- END: {theme_prefix}{resolved_key} -->")
+            mark_origin(&mark_component(&with_slots, &resolved_key), &format!("{theme_prefix}{resolved_key}"), markers)
         } else {
             with_slots
         };
@@ -231,7 +775,9 @@ fn resolve_recursive(
 
         // Collect child styles and slot component styles
         styles.extend(child_resolved.styles);
+        style_sources.extend(child_resolved.style_sources);
         styles.extend(slot_result.styles);
+        style_sources.extend(slot_result.style_sources);
     }
 
     // Reactive-aware interpolation: leave reactive {{ expr }} as-is for
@@ -239,13 +785,30 @@ fn resolve_recursive(
     // In compile mode: props are injected as {{ expr }} by parse_props, and
     // interpolation preserves values containing {{ (skips escape_html).
     let html = if !reactive_names.is_empty() {
-        interpolate_skip_reactive(&template, data, reactive_names)
+        interpolate_skip_reactive(&template, data, reactive_names, ctx.sanitize_html, ctx.usage)
     } else {
-        interpolate(&template, data)
+        interpolate_with(&template, data, ctx.sanitize_html, ctx.usage)
     };
 
     // Merge this component's script_setup with collected child scripts
-    let mut script_setup = blocks.script_setup.clone();
+    let mut script_setup = setup_script(&blocks).map(|script| {
+        let (script, missing) = van_signal_gen::inline_env(&script, ctx.env);
+        for name in missing {
+            ctx.warn(format!("{current_path}: environment variable {name} is not set"));
+        }
+        let (script, missing) = van_signal_gen::inline_props(&script, |name| initial_prop_value(name, &blocks.props, data));
+        for name in missing {
+            let why = if data.get(&name).is_some() { "is only known at runtime" } else { "is not passed" };
+            ctx.warn(format!("{current_path}: prop \"{name}\" initializes local state but {why} and has no default"));
+        }
+        // Handlers read `props.x` on the client from the values known now
+        let values: Vec<(String, String)> = blocks
+            .props
+            .iter()
+            .filter_map(|p| Some((p.name.clone(), initial_prop_value(&p.name, &blocks.props, data)?)))
+            .collect();
+        van_signal_gen::props_object(&script, &values)
+    });
     if !child_scripts.is_empty() {
         let merged = child_scripts.join("\n");
         script_setup = Some(match script_setup {
@@ -254,37 +817,129 @@ fn resolve_recursive(
         });
     }
 
-    // Resolve module imports from this component and merge child module imports
-    let mut module_imports: Vec<ResolvedModule> = if let Some(ref script) = blocks.script_setup {
+    // Resolve module imports from this component; child module imports come
+    // first so the modules of nested components precede the ones using them
+    let own_module_imports: Vec<ResolvedModule> = if let Some(ref script) = blocks.script_setup {
         let script_imports = parse_script_imports(script);
         script_imports
             .into_iter()
             .filter_map(|imp| {
-                if imp.is_type_only {
-                    return None; // type-only imports are erased
+                if imp.is_type_only || imp.is_markdown() {
+                    return None; // type-only imports are erased, Markdown is already rendered
                 }
                 let resolved_key = resolve_virtual_path(current_path, &imp.path);
-                let content = files.get(&resolved_key)?;
-                Some(ResolvedModule {
-                    path: resolved_key,
-                    content: content.clone(),
-                    is_type_only: false,
-                })
+                local_module(resolved_key, imp.bindings(), ctx).transpose()
             })
-            .collect()
+            .chain(parse_bare_imports(script).into_iter().filter(|imp| !imp.is_type_only).filter_map(|imp| {
+                match ctx.import_map.get(&imp.path) {
+                    Some(url) if is_module_url(url) => Some(Ok(ResolvedModule {
+                        path: imp.path.clone(),
+                        content: String::new(),
+                        is_type_only: false,
+                        bindings: imp.bindings(),
+                        url: Some(url.clone()),
+                    })),
+                    Some(key) if files.contains_key(key) => local_module(key.clone(), imp.bindings(), ctx).transpose(),
+                    Some(key) => {
+                        ctx.warn(format!("{current_path}: import map entry \"{}\" points to {key}, which is not in the project", imp.path));
+                        None
+                    }
+                    None => {
+                        ctx.warn(format!("{current_path}: bare import \"{}\" is not in the import map; its bindings are undefined in the page script", imp.path));
+                        None
+                    }
+                }
+            }))
+            .collect::<Result<_, String>>()?
     } else {
         Vec::new()
     };
-    module_imports.extend(child_module_imports);
+    let module_imports = dedupe_modules(child_module_imports.into_iter().chain(own_module_imports));
 
     Ok(ResolvedComponent {
         html,
         styles,
+        style_sources,
         script_setup,
         module_imports,
+        warnings: Vec::new(),
+        dependencies: Vec::new(),
+    })
+}
+
+/// The `<script setup>` content, with types erased when it is `lang="ts"`.
+fn setup_script(blocks: &VanBlock) -> Option<String> {
+    let script = blocks.script_setup.as_deref()?;
+    Some(match blocks.script_setup_lang.as_deref() {
+        Some("ts") => van_signal_gen::strip_types(script),
+        _ => script.to_string(),
     })
 }
 
+/// The files-map module at `key`, audited and with types erased, or `None`
+/// when there is no such file.
+fn local_module(key: String, bindings: Vec<(String, String)>, ctx: &ResolveCtx) -> Result<Option<ResolvedModule>, String> {
+    let Some(content) = ctx.files.get(&key) else {
+        return Ok(None);
+    };
+    ctx.depend(&key);
+    audit_js(content, &key, 1, ctx)?;
+    let content = if key.ends_with(".ts") {
+        van_signal_gen::strip_types(content)
+    } else {
+        content.clone()
+    };
+    Ok(Some(ResolvedModule { path: key, content, is_type_only: false, bindings, url: None }))
+}
+
+/// The store fields (`cart.count`) the script of the component at `path`
+/// imports, which are reactive in its template like its own signals.
+fn store_names(path: &str, script: &str, files: &HashMap<String, String>) -> Vec<String> {
+    let modules: Vec<ModuleCode> = parse_script_imports(script)
+        .into_iter()
+        .filter(|imp| !imp.is_type_only)
+        .filter_map(|imp| {
+            let code = files.get(&resolve_virtual_path(path, &imp.path))?.clone();
+            Some(ModuleCode { code, bindings: imp.bindings() })
+        })
+        .collect();
+    van_signal_gen::store_fields(&modules).into_iter().map(|field| field.name).collect()
+}
+
+/// Whether an import map target is a URL for the browser to load rather
+/// than a path in the files map.
+fn is_module_url(target: &str) -> bool {
+    target.starts_with('/') || target.contains("://")
+}
+
+/// Keep the first occurrence of each module path, preserving order; the
+/// bindings of later occurrences are merged into it.
+fn dedupe_modules(modules: impl IntoIterator<Item = ResolvedModule>) -> Vec<ResolvedModule> {
+    let mut deduped: Vec<ResolvedModule> = Vec::new();
+    for module in modules {
+        match deduped.iter_mut().find(|m| m.path == module.path) {
+            Some(existing) => {
+                for binding in module.bindings {
+                    if !existing.bindings.contains(&binding) {
+                        existing.bindings.push(binding);
+                    }
+                }
+            }
+            None => deduped.push(module),
+        }
+    }
+    deduped
+}
+
+/// The scope id of `path`'s scoped `css`. Files from different themes never
+/// share an id, even for identical CSS.
+fn file_scope_id(path: &str, css: &str, file_origins: &HashMap<String, String>) -> String {
+    match file_origins.get(path) {
+        Some(theme) => scope_id(&format!("{theme}{css}")),
+        None => scope_id(css),
+    }
+}
+
 // ─── Single-file resolve (no imports, no FS) ────────────────────────────
 
 /// Resolve a single `.van` source into HTML + styles (no import resolution).
@@ -295,8 +950,9 @@ pub fn resolve_single(source: &str, data: &Value) -> Result<ResolvedComponent, S
 }
 
 /// Like `resolve_single`, but kept for API compatibility.
-pub fn resolve_single_with_path(source: &str, data: &Value, _path: &str) -> Result<ResolvedComponent, String> {
+pub fn resolve_single_with_path(source: &str, data: &Value, path: &str) -> Result<ResolvedComponent, String> {
     let blocks = parse_blocks(source);
+    let script_setup = setup_script(&blocks);
 
     let mut template = blocks
         .template
@@ -312,6 +968,7 @@ pub fn resolve_single_with_path(source: &str, data: &Value, _path: &str) -> Resu
             styles.push(css.clone());
         }
     }
+    let style_sources = vec![path.to_string(); styles.len()];
 
     // Extract reactive names from script setup
     let reactive_names = if let Some(ref script) = blocks.script_setup {
@@ -322,7 +979,7 @@ pub fn resolve_single_with_path(source: &str, data: &Value, _path: &str) -> Resu
 
     // Reactive-aware interpolation
     let html = if !reactive_names.is_empty() {
-        interpolate_skip_reactive(&template, data, &reactive_names)
+        interpolate_skip_reactive(&template, data, &reactive_names, false, &DataUsage::default())
     } else {
         interpolate(&template, data)
     };
@@ -330,8 +987,11 @@ pub fn resolve_single_with_path(source: &str, data: &Value, _path: &str) -> Resu
     Ok(ResolvedComponent {
         html,
         styles,
-        script_setup: blocks.script_setup.clone(),
+        style_sources,
+        script_setup,
         module_imports: Vec::new(),
+        warnings: Vec::new(),
+        dependencies: Vec::new(),
     })
 }
 
@@ -386,8 +1046,8 @@ fn normalize_virtual_path(path: &str) -> String {
 
 /// Extract reactive signal names from script setup (ref/computed declarations).
 pub fn extract_reactive_names(script: &str) -> Vec<String> {
-    let ref_re = Regex::new(r#"const\s+(\w+)\s*=\s*ref\("#).unwrap();
-    let computed_re = Regex::new(r#"const\s+(\w+)\s*=\s*computed\("#).unwrap();
+    let ref_re = Regex::new(r#"const\s+([\w$]+)\s*=\s*ref\("#).unwrap();
+    let computed_re = Regex::new(r#"const\s+([\w$]+)\s*=\s*computed\("#).unwrap();
     let mut names = Vec::new();
     for cap in ref_re.captures_iter(script) {
         names.push(cap[1].to_string());
@@ -401,31 +1061,19 @@ pub fn extract_reactive_names(script: &str) -> Vec<String> {
 /// Interpolate `{{ expr }}` / `{{{ expr }}}` but leave reactive expressions as-is.
 ///
 /// - `{{ expr }}` — HTML-escaped output (default, safe)
-/// - `{{{ expr }}}` — raw output (no escaping, for trusted HTML content)
-fn interpolate_skip_reactive(template: &str, data: &Value, reactive_names: &[String]) -> String {
+/// - `{{{ expr }}}` — raw output (no escaping, for trusted HTML content),
+///   sanitized when `sanitize` is set
+fn interpolate_skip_reactive(template: &str, data: &Value, reactive_names: &[String], sanitize: bool, usage: &DataUsage) -> String {
+    raw::outside_raw(template, |part| interpolate_part_skip_reactive(part, data, reactive_names, sanitize, usage))
+}
+
+fn interpolate_part_skip_reactive(template: &str, data: &Value, reactive_names: &[String], sanitize: bool, usage: &DataUsage) -> String {
     let mut result = String::with_capacity(template.len());
     let mut rest = template;
 
     // Closure: check if expression references any reactive signal
     let check_reactive = |expr: &str| -> bool {
-        reactive_names.iter().any(|name| {
-            let bytes = expr.as_bytes();
-            let name_bytes = name.as_bytes();
-            let name_len = name.len();
-            let mut i = 0;
-            while i + name_len <= bytes.len() {
-                if &bytes[i..i + name_len] == name_bytes {
-                    let before_ok = i == 0 || !(bytes[i - 1] as char).is_alphanumeric();
-                    let after_ok = i + name_len == bytes.len()
-                        || !(bytes[i + name_len] as char).is_alphanumeric();
-                    if before_ok && after_ok {
-                        return true;
-                    }
-                }
-                i += 1;
-            }
-            false
-        })
+        reactive_names.iter().any(|name| van_signal_gen::references_name(expr, name))
     };
 
     while let Some(start) = rest.find("{{") {
@@ -438,7 +1086,8 @@ fn interpolate_skip_reactive(template: &str, data: &Value, reactive_names: &[Str
                 let expr = after_open[..end].trim();
                 // $t() is always resolved immediately (never reactive)
                 if let Some(translated) = try_resolve_t(expr, data) {
-                    result.push_str(&translated);
+                    let value = escape_in_context(&result, &raw_html(translated, sanitize), true);
+                    result.push_str(&value);
                 } else if expr.trim().starts_with("$t(") {
                     // $t() but no $i18n data — preserve for runtime resolution
                     result.push_str(&format!("{{{{{{{}}}}}}}", expr));
@@ -446,8 +1095,10 @@ fn interpolate_skip_reactive(template: &str, data: &Value, reactive_names: &[Str
                     // Keep reactive as double-mustache for signal runtime
                     result.push_str(&format!("{{{{ {expr} }}}}"));
                 } else {
-                    let value = resolve_json_path(data, expr);
-                    result.push_str(&value); // Raw output — no escape for {{{ }}}
+                    usage.record_expr(expr, data);
+                    let value = raw_html(resolve_json_path(data, expr), sanitize);
+                    let value = escape_in_context(&result, &value, true);
+                    result.push_str(&value);
                 }
                 rest = &after_open[end + 3..];
             } else {
@@ -460,19 +1111,22 @@ fn interpolate_skip_reactive(template: &str, data: &Value, reactive_names: &[Str
                 let expr = after_open[..end].trim();
                 // $t() is always resolved immediately (never reactive)
                 if let Some(translated) = try_resolve_t(expr, data) {
-                    result.push_str(&escape_html(&translated));
+                    let value = escape_in_context(&result, &translated, false);
+                    result.push_str(&value);
                 } else if expr.trim().starts_with("$t(") {
                     // $t() but no $i18n data — preserve for runtime resolution
                     result.push_str(&format!("{{{{{}}}}}", expr));
                 } else if check_reactive(expr) {
                     result.push_str(&format!("{{{{ {expr} }}}}"));
                 } else {
+                    usage.record_expr(expr, data);
                     let value = resolve_json_path(data, expr);
                     if value.contains("{{") {
                         // Value is an unresolved or compile expression — preserve for Java
                         result.push_str(&value);
                     } else {
-                        result.push_str(&escape_html(&value));
+                        let value = escape_in_context(&result, &value, false);
+                        result.push_str(&value);
                     }
                 }
                 rest = &after_open[end + 2..];
@@ -527,54 +1181,237 @@ fn is_html_element(tag: &str) -> bool {
     )
 }
 
-/// Find the first component tag in the template that matches an import.
+/// Tags handled by Van itself rather than resolved from an import.
+const BUILTIN_TAGS: &[&str] = &[
+    "slot", "Transition", "transition", "TransitionGroup", "transition-group",
+    "Teleport", "teleport", "ClientOnly",
+];
+
+/// Lowercase HTML, SVG and MathML names missing from [`is_html_element`]
+/// (which only lists names that must never resolve as components), checked
+/// before reporting a plain lowercase tag as unknown.
+fn is_other_known_element(tag: &str) -> bool {
+    tag.starts_with("fe")
+        || matches!(tag,
+            "acronym" | "big" | "center" | "dir" | "font" | "frame" | "frameset" | "marquee"
+            | "nobr" | "noframes" | "param" | "strike" | "tt" | "xmp"
+            | "desc" | "filter" | "lineargradient" | "marker" | "metadata" | "mpath"
+            | "radialgradient" | "stop" | "switch" | "textpath" | "view" | "animatemotion"
+            | "math" | "mi" | "mn" | "mo" | "mrow" | "ms" | "msub" | "msup" | "mfrac" | "msqrt"
+            | "mtext" | "semantics" | "annotation"
+        )
+}
+
+/// Whether `tag` is on the custom element allowlist (`sl-button`, `sl-*`).
+fn is_custom_element(tag: &str, allowlist: &[String]) -> bool {
+    allowlist.iter().any(|allowed| match allowed.strip_suffix('*') {
+        Some(prefix) => tag.starts_with(prefix),
+        None => allowed == tag,
+    })
+}
+
+/// Warn about tags in a component's own template that look like components
+/// (PascalCase, or kebab-case custom elements) but match no import, with a
+/// suggestion from the imports and `components/` when one is close.
+///
+/// Plain lowercase tags that are not known elements are only reported when a
+/// suggestion exists, since the element lists here are not exhaustive.
+fn warn_unknown_tags(
+    template: &str,
+    import_map: &HashMap<String, &VanImport>,
+    current_path: &str,
+    ctx: &ResolveCtx,
+) {
+    let mut tags: Vec<&str> = Vec::new();
+    visit_open_tags(template, |_, name| {
+        if !tags.contains(&name) {
+            tags.push(name);
+        }
+        false
+    });
+    for tag in tags {
+        let pascal = tag.starts_with(|c: char| c.is_ascii_uppercase());
+        let kebab = pascal_to_kebab(tag);
+        let lower = tag.to_ascii_lowercase();
+        let known = import_map.contains_key(tag)
+            || (pascal && import_map.get(&kebab).is_some_and(|imp| imp.name == tag))
+            || BUILTIN_TAGS.contains(&tag)
+            || (!pascal && (is_html_element(&lower) || is_other_known_element(&lower)))
+            || is_custom_element(tag, ctx.custom_elements);
+        if known {
+            continue;
+        }
+        match suggest_component(tag, pascal, import_map, ctx.files) {
+            Some(suggestion) => ctx.warn(format!(
+                "{current_path}: unknown element <{tag}>, did you mean <{suggestion}>?"
+            )),
+            None if pascal || tag.contains('-') => ctx.warn(format!(
+                "{current_path}: unknown element <{tag}> is not an imported component"
+            )),
+            None => {}
+        }
+    }
+}
+
+/// The imported component, or failing that the `components/` file, whose
+/// name is closest to `tag`, if within a couple of edits.
+fn suggest_component(
+    tag: &str,
+    pascal: bool,
+    import_map: &HashMap<String, &VanImport>,
+    files: &HashMap<String, String>,
+) -> Option<String> {
+    let key = |name: &str| name.replace('-', "").to_ascii_lowercase();
+    let target = key(tag);
+    let max = (target.len() / 3).clamp(1, 2);
+    let closest = |names: Vec<String>| {
+        names
+            .into_iter()
+            .map(|name| (edit_distance(&key(&name), &target), name))
+            .filter(|(d, _)| *d <= max)
+            .min()
+            .map(|(_, name)| name)
+    };
+    let imported: Vec<String> = import_map
+        .iter()
+        .map(|(kebab, imp)| if pascal { imp.name.clone() } else { kebab.clone() })
+        .collect();
+    closest(imported).or_else(|| {
+        let listed = files
+            .keys()
+            .filter_map(|path| path.strip_prefix("components/")?.strip_suffix(".van"))
+            .filter_map(|stem| stem.rsplit('/').next())
+            .map(|stem| if pascal { kebab_to_pascal(stem) } else { stem.to_string() })
+            .collect();
+        closest(listed)
+    })
+}
+
+fn kebab_to_pascal(name: &str) -> String {
+    name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or(String::new(), |c| c.to_ascii_uppercase().to_string() + chars.as_str())
+        })
+        .collect()
+}
+
+/// Levenshtein distance between two ASCII-ish names.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Find the first component tag in the template (in document order) that
+/// matches an import, so components resolve — and collect their styles,
+/// scripts and warnings — in the same order on every run.
 /// Searches both kebab-case (`default-layout`) and PascalCase (`DefaultLayout`) forms.
 /// Kebab-case matching is skipped when the tag name collides with a known HTML/SVG element
 /// to prevent infinite loops (e.g. component `Header` → kebab `header` matching `<header>` HTML).
 fn find_component_tag(template: &str, import_map: &HashMap<String, &VanImport>) -> Option<TagInfo> {
+    let mut first: Option<TagInfo> = None;
     for (tag_name, imp) in import_map {
         // Try kebab-case (e.g. `<default-layout>`), but skip if it's a known HTML/SVG element.
         // e.g. import Header → kebab "header" matches <header> HTML element — skip.
-        if !is_html_element(tag_name) {
-            if let Some(info) = extract_component_tag(template, tag_name) {
-                return Some(info);
-            }
-        }
+        let kebab = (!is_html_element(tag_name))
+            .then(|| extract_component_tag(template, tag_name))
+            .flatten();
         // Try PascalCase (e.g. `<Header>`, `<DefaultLayout>`)
-        if imp.name != *tag_name {
-            if let Some(mut info) = extract_component_tag(template, &imp.name) {
+        let pascal = (imp.name != *tag_name)
+            .then(|| extract_component_tag(template, &imp.name))
+            .flatten()
+            .map(|mut info| {
                 info.tag_name = tag_name.clone(); // normalize to kebab for import_map lookup
-                return Some(info);
+                info
+            });
+        for info in kebab.into_iter().chain(pascal) {
+            if first.as_ref().is_none_or(|f| info.start < f.start) {
+                first = Some(info);
             }
         }
     }
-    None
+    first
 }
 
-/// Extract a component tag (self-closing or paired) from the template.
-fn extract_component_tag(template: &str, tag_name: &str) -> Option<TagInfo> {
-    let open_pattern = format!("<{}", tag_name);
+/// Elements whose content is shown as-is (code samples, raw text), so
+/// component tags written inside them are never resolved.
+const VERBATIM_ELEMENTS: &[&str] = &["pre", "code", "textarea", "script", "style"];
 
-    let start = template.find(&open_pattern)?;
+/// Offset of the first `<tag_name` opening tag that is real markup.
+///
+/// Comments, quoted attribute values and the content of verbatim and
+/// `v-raw` elements are skipped over, so a component mentioned there is left
+/// untouched.
+fn find_open_tag(template: &str, tag_name: &str) -> Option<usize> {
+    let mut found = None;
+    visit_open_tags(template, |start, name| {
+        found = (name == tag_name).then_some(start);
+        found.is_some()
+    });
+    found
+}
 
-    // Verify it's a complete tag name (next char must be space, /, or >)
-    let after_tag = start + open_pattern.len();
-    if after_tag < template.len() {
-        let next_ch = template.as_bytes()[after_tag] as char;
-        if next_ch != ' '
-            && next_ch != '/'
-            && next_ch != '>'
-            && next_ch != '\n'
-            && next_ch != '\r'
-            && next_ch != '\t'
-        {
-            return None;
+/// Call `visit` with the offset and name of each opening tag that is real
+/// markup (see [`find_open_tag`]), in document order, until it returns `true`.
+fn visit_open_tags<'t>(template: &'t str, mut visit: impl FnMut(usize, &'t str) -> bool) {
+    let mut pos = 0;
+    while let Some(offset) = template[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &template[start..];
+        if rest.starts_with("<!--") {
+            pos = rest.find("-->").map_or(template.len(), |end| start + end + 3);
+            continue;
+        }
+        let name_len = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(rest.len() - 1);
+        let name = &rest[1..1 + name_len];
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            pos = start + 1;
+            continue;
+        }
+        if visit(start, name) {
+            return;
+        }
+        let Some(tag_len) = tag_end(rest) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = start + tag_len;
+        if raw::has_raw_attr(&rest[..tag_len]) && !rest[..tag_len].ends_with("/>") {
+            pos += find_matching_close_tag(&template[pos..], name);
+            continue;
+        }
+        let name = name.to_ascii_lowercase();
+        if VERBATIM_ELEMENTS.contains(&name.as_str()) && !rest[..tag_len].ends_with("/>") {
+            let close = format!("</{name}");
+            pos = template[pos..]
+                .to_ascii_lowercase()
+                .find(&close)
+                .map_or(template.len(), |c| pos + c);
         }
     }
+}
+
+/// Extract a component tag (self-closing or paired) from the template.
+fn extract_component_tag(template: &str, tag_name: &str) -> Option<TagInfo> {
+    let open_pattern = format!("<{}", tag_name);
 
-    // Find the end of the opening tag '>'
+    let start = find_open_tag(template, tag_name)?;
+
+    // Find the end of the opening tag '>' (a '>' inside a quoted value doesn't count)
     let rest = &template[start..];
-    let gt_pos = rest.find('>')?;
+    let gt_pos = tag_end(rest)? - 1;
 
     // Check for self-closing: ends with />
     let is_self_closing = rest[..gt_pos].ends_with('/');
@@ -593,12 +1430,16 @@ fn extract_component_tag(template: &str, tag_name: &str) -> Option<TagInfo> {
         });
     }
 
-    // Paired tag: find matching closing tag </tag-name>
+    // Paired tag: find the matching closing tag </tag-name>, skipping nested
+    // uses of the same component
     let content_start = start + gt_pos + 1;
     let close_tag = format!("</{}>", tag_name);
 
     let remaining = &template[content_start..];
-    let close_pos = remaining.find(&close_tag)?;
+    let close_pos = find_matching_close_tag(remaining, tag_name);
+    if close_pos == remaining.len() {
+        return None;
+    }
 
     let attrs_raw = &rest[tag_name.len() + 1..gt_pos];
     let children = remaining[..close_pos].to_string();
@@ -615,10 +1456,16 @@ fn extract_component_tag(template: &str, tag_name: &str) -> Option<TagInfo> {
 // ─── Props ──────────────────────────────────────────────────────────────
 
 /// Parse `:prop="expr"` attributes and resolve them against parent data.
-fn parse_props(attrs: &str, parent_data: &Value) -> Value {
+///
+/// Values are handed down as strings, except for props `props` declares as
+/// `Number`, `Boolean`, `Array` or `Object`, which get the typed value (see
+/// [`coerce_prop`]). Also returns a message for each value that does not
+/// convert to its prop's type; it is passed on as it was.
+fn parse_props(attrs: &str, parent_data: &Value, props: &[PropDef], usage: &DataUsage) -> (Value, Vec<String>) {
     let compile = matches!(parent_data, Value::Object(m) if m.is_empty());
     let re = Regex::new(r#":(\w+)="([^"]*)""#).unwrap();
     let mut map = serde_json::Map::new();
+    let mut unconverted = Vec::new();
     for cap in re.captures_iter(attrs) {
         let key = &cap[1];
         let expr = &cap[2];
@@ -628,87 +1475,434 @@ fn parse_props(attrs: &str, parent_data: &Value) -> Value {
         } else if let Some(translated) = try_resolve_t(expr, parent_data) {
             translated
         } else {
+            usage.record_expr(expr, parent_data);
             resolve_json_path(parent_data, expr)
         };
-        map.insert(key.to_string(), Value::String(value_str));
+        let prop_type = props.iter().find(|p| p.name == key).and_then(|p| p.prop_type.as_deref());
+        let value = match prop_type {
+            Some(prop_type) if !compile && typed_prop(prop_type) => {
+                let value = resolve_path_value(parent_data, expr.trim()).cloned().unwrap_or(Value::String(value_str));
+                coerce_prop(value, prop_type).unwrap_or_else(|value| {
+                    unconverted.push(format!("prop \"{key}\" expects {prop_type}, could not convert {value}"));
+                    value
+                })
+            }
+            _ => Value::String(value_str),
+        };
+        map.insert(key.to_string(), value);
     }
 
-    // Inherit $i18n from parent so child components can use $t()
-    if let Some(i18n_data) = parent_data.get("$i18n") {
-        map.insert("$i18n".to_string(), i18n_data.clone());
+    // Inherit $i18n (and the active $locale) from parent so child components can use $t()
+    for key in ["$i18n", "$locale"] {
+        if let Some(value) = parent_data.get(key) {
+            map.insert(key.to_string(), value.clone());
+        }
     }
 
-    Value::Object(map)
+    (Value::Object(map), unconverted)
 }
 
-// ─── Slots ──────────────────────────────────────────────────────────────
+/// Whether props of `prop_type` are handed down typed rather than as strings.
+fn typed_prop(prop_type: &str) -> bool {
+    matches!(prop_type.to_lowercase().as_str(), "number" | "boolean" | "array" | "object")
+}
 
-/// Parsed slot content keyed by slot name ("default" for unnamed).
-type SlotMap = HashMap<String, String>;
+/// `value` read as a prop of `prop_type`: a string becomes a number for
+/// `Number`, `true`/`false` for `Boolean` and parsed JSON for `Array` /
+/// `Object`. Values already of the type, and props of other types, are kept.
+/// `Err` gives back a string that does not convert.
+fn coerce_prop(value: Value, prop_type: &str) -> Result<Value, Value> {
+    // Forwarded compile-mode expressions are only known at runtime
+    let Value::String(s) = &value else {
+        return Ok(value);
+    };
+    if s.starts_with("{{") {
+        return Ok(value);
+    }
+    let converted = match prop_type.to_lowercase().as_str() {
+        "number" => serde_json::from_str(s.trim()).ok().filter(Value::is_number),
+        "boolean" => match s.trim() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        "array" => serde_json::from_str(s).ok().filter(Value::is_array),
+        "object" => serde_json::from_str(s).ok().filter(Value::is_object),
+        _ => return Ok(value),
+    };
+    converted.ok_or(value)
+}
 
-/// Result of parsing slot content, including collected styles from resolved components.
-struct SlotResult {
-    slots: SlotMap,
-    styles: Vec<String>,
-    script_setup: Option<String>,
-    module_imports: Vec<ResolvedModule>,
+/// The JS literal a `ref(props.NAME)` initial value starts from: the value the
+/// parent passed, or the prop's declared default.
+///
+/// Props arrive as strings and are read back by their declared type, so
+/// `"true"` is `true` for a `Boolean` prop but `"true"` for a `String` one.
+/// Values only known at runtime (compile mode's `{{ expr }}`) use the default.
+fn initial_prop_value(name: &str, props: &[PropDef], data: &Value) -> Option<String> {
+    let def = props.iter().find(|p| p.name == name);
+    let passed = match data.get(name) {
+        Some(Value::String(s)) if s.starts_with("{{") => None,
+        Some(Value::String(s)) if def.and_then(|d| d.prop_type.as_deref()) == Some("String") => {
+            Some(Value::String(s.clone()))
+        }
+        Some(Value::String(s)) => Some(serde_json::from_str(s).unwrap_or_else(|_| Value::String(s.clone()))),
+        Some(other) => Some(other.clone()),
+        None => None,
+    };
+    match passed {
+        Some(value) => Some(value.to_string().replace('<', "\\u003c")),
+        None => def.and_then(|d| d.default.clone()),
+    }
 }
 
-/// Parse `<template #name>...</template>` blocks and default content from children.
-fn parse_slot_content(
-    children: &str,
+/// Check the props a parent passes against the child's `defineProps`.
+///
+/// Missing required props are reported in both modes; type mismatches only when
+/// rendering with data. Props are handed down as strings, so a string value is
+/// accepted for any type it parses as (e.g. `"42"` for `Number`). Expressions
+/// that don't resolve to a data path (loop variables, literals) are not checked.
+fn validate_child_props(
+    attrs: &str,
     parent_data: &Value,
-    parent_imports: &[VanImport],
-    current_path: &str,
-    files: &HashMap<String, String>,
-    depth: usize,
-    reactive_names: &[String],
-    debug: bool,
-    file_origins: &HashMap<String, String>,
-) -> Result<SlotResult, String> {
-    let mut slots = SlotMap::new();
-    let mut styles: Vec<String> = Vec::new();
-    let mut default_parts: Vec<String> = Vec::new();
-    let mut rest = children;
+    parent_path: &str,
+    child_path: &str,
+    child_source: &str,
+    ctx: &ResolveCtx,
+) {
+    let props = parse_blocks(child_source).props;
+    if props.is_empty() {
+        return;
+    }
+    let compile = matches!(parent_data, Value::Object(m) if m.is_empty());
+    let bound_re = Regex::new(r#":(\w+)="([^"]*)""#).unwrap();
+    let bound: HashMap<&str, &str> = bound_re
+        .captures_iter(attrs)
+        .map(|c| (c.get(1).unwrap().as_str(), c.get(2).unwrap().as_str()))
+        .collect();
+    let label = format!("{parent_path} -> {child_path}");
+
+    for prop in &props {
+        let Some(expr) = bound.get(prop.name.as_str()) else {
+            let static_re = Regex::new(&format!(r#"(?:^|\s){}="#, regex::escape(&prop.name))).unwrap();
+            if prop.required && !static_re.is_match(attrs) {
+                let type_hint = prop.prop_type.as_deref().unwrap_or("any");
+                ctx.warn(format!("{label}: missing required prop \"{}\" ({type_hint})", prop.name));
+            }
+            continue;
+        };
+        if compile {
+            continue;
+        }
+        let (Some(expected), Some(value)) = (&prop.prop_type, resolve_path_value(parent_data, expr)) else {
+            continue;
+        };
+        // Strings that don't convert were reported by `parse_props`
+        if value.is_string() && coerce_prop(value.clone(), expected).is_err() {
+            continue;
+        }
+        if let Some(actual) = prop_type_mismatch(expected, value) {
+            ctx.warn(format!("{label}: prop \"{}\" expects {expected}, got {actual}", prop.name));
+        }
+    }
+}
+
+/// The actual type name if `value` does not fit a declared prop type.
+fn prop_type_mismatch(expected: &str, value: &Value) -> Option<&'static str> {
+    let actual = match value {
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Null => return None,
+        // Forwarded compile-mode expressions are only known at runtime.
+        Value::String(s) if s.starts_with("{{") => return None,
+        Value::String(_) => "string",
+    };
+    let expected = expected.to_lowercase();
+    let fits = match (expected.as_str(), value) {
+        ("string" | "number" | "boolean" | "array" | "object", _) if expected == actual => true,
+        ("number", Value::String(s)) => s.trim().parse::<f64>().is_ok(),
+        ("boolean", Value::String(s)) => s == "true" || s == "false",
+        ("array", Value::String(s)) => matches!(serde_json::from_str(s), Ok(Value::Array(_))),
+        ("object", Value::String(s)) => matches!(serde_json::from_str(s), Ok(Value::Object(_))),
+        ("string" | "number" | "boolean" | "array" | "object", _) => false,
+        // Function, Date, custom classes...
+        _ => true,
+    };
+    if fits { None } else { Some(actual) }
+}
 
-    let named_slot_re = Regex::new(r#"<template\s+#(\w+)\s*>"#).unwrap();
+// ─── Fallthrough attributes ─────────────────────────────────────────────
 
-    loop {
-        let Some(cap) = named_slot_re.captures(rest) else {
-            let trimmed = rest.trim();
-            if !trimmed.is_empty() {
-                default_parts.push(trimmed.to_string());
+/// Plain attributes on a component tag that are not declared props, in
+/// source order (`None` for boolean attributes). Bindings, event handlers and
+/// directives are not passed on.
+fn fallthrough_attrs(attrs: &str, props: &[PropDef]) -> Vec<(String, Option<String>)> {
+    let attr_re = Regex::new(r#"([^\s=/>"']+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'))?"#).unwrap();
+    let is_prop = |name: &str| {
+        let name = name.replace('-', "").to_ascii_lowercase();
+        props.iter().any(|p| p.name.to_ascii_lowercase() == name)
+    };
+    attr_re
+        .captures_iter(attrs)
+        .filter_map(|c| {
+            let name = &c[1];
+            if !name.starts_with(|ch: char| ch.is_ascii_alphabetic()) || name.starts_with("v-") || is_prop(name) {
+                return None;
             }
-            break;
-        };
+            let value = c.get(2).or_else(|| c.get(3)).map(|m| m.as_str().to_string());
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
 
-        let full_match = cap.get(0).unwrap();
-        let slot_name = cap[1].to_string();
+/// Offset of the only top-level node of a fragment when it is an element,
+/// otherwise the number of top-level nodes (elements and text runs).
+///
+/// Whitespace, comments and the doctype are not nodes.
+fn single_root(html: &str) -> Result<usize, usize> {
+    let mut roots = Vec::new();
+    let mut pos = 0;
+    while let Some(c) = html[pos..].chars().next() {
+        let rest = &html[pos..];
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+        } else if rest.starts_with("<!--") {
+            pos = rest.find("-->").map_or(html.len(), |end| pos + end + 3);
+        } else if rest.starts_with("<!") {
+            pos = rest.find('>').map_or(html.len(), |end| pos + end + 1);
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            roots.push(pos);
+            let Some(open_len) = tag_end(rest) else {
+                break;
+            };
+            let name_len = rest[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                .unwrap_or(rest.len() - 1);
+            let name = &rest[1..1 + name_len];
+            pos += open_len;
+            if !rest[..open_len].ends_with("/>") && !VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str()) {
+                let close = find_matching_close_tag(&html[pos..], name);
+                pos = (pos + close + name.len() + 3).min(html.len());
+            }
+        } else {
+            roots.push(pos);
+            pos = rest[1..].find('<').map_or(html.len(), |next| pos + 1 + next);
+        }
+    }
+    match roots.as_slice() {
+        [start] if html[*start..].starts_with('<') => Ok(*start),
+        _ => Err(roots.len()),
+    }
+}
+
+/// Set an attribute on an opening tag. `class` and `style` are merged with
+/// the tag's own value; any other attribute replaces it.
+fn set_attr(tag: &str, name: &str, value: Option<&str>) -> String {
+    let attr_re = Regex::new(r#"([^\s=/>"']+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'))?"#).unwrap();
+    let name_end = 1 + tag[1..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(tag.len() - 1);
+    let render = |value: Option<&str>| match value {
+        Some(value) => format!("{name}=\"{value}\""),
+        None => name.to_string(),
+    };
+    let existing = attr_re
+        .captures_iter(&tag[name_end..])
+        .find(|c| c[1].eq_ignore_ascii_case(name));
+    let Some(existing) = existing else {
+        let end = tag.trim_end_matches('>').trim_end_matches('/').trim_end().len();
+        return format!("{} {}{}", &tag[..end], render(value), &tag[end..]);
+    };
+    let own = existing.get(2).or_else(|| existing.get(3)).map(|m| m.as_str());
+    let merged = match (name, own, value) {
+        ("class", Some(own), Some(value)) if !own.trim().is_empty() => {
+            // A root from the parent's slot content already has the parent's scope class
+            let mut classes: Vec<&str> = own.split_whitespace().collect();
+            for class in value.split_whitespace() {
+                if !classes.contains(&class) {
+                    classes.push(class);
+                }
+            }
+            Some(classes.join(" "))
+        }
+        ("style", Some(own), Some(value)) if !own.trim().is_empty() => {
+            Some(format!("{}; {value}", own.trim_end().trim_end_matches(';')))
+        }
+        _ => value.map(str::to_string),
+    };
+    let span = existing.get(0).unwrap();
+    format!(
+        "{}{}{}",
+        &tag[..name_end + span.start()],
+        render(merged.as_deref()),
+        &tag[name_end + span.end()..]
+    )
+}
+
+/// Pass a component tag's non-prop attributes on to the root element the
+/// component renders, as Vue's attribute fallthrough does.
+///
+/// A fragment (several root nodes) or a text-only component has no single
+/// element to receive them, so they are dropped with a warning. The parent's
+/// scope class, which a scoped template puts on every tag, is dropped silently.
+fn apply_fallthrough(
+    html: &str,
+    attrs: &str,
+    props: &[PropDef],
+    parent_scope: Option<&str>,
+    label: &str,
+    ctx: &ResolveCtx,
+) -> String {
+    let attrs = fallthrough_attrs(attrs, props);
+    if attrs.is_empty() {
+        return html.to_string();
+    }
+    match single_root(html) {
+        Ok(start) => {
+            let open_len = tag_end(&html[start..]).unwrap_or(html.len() - start);
+            let tag = attrs
+                .iter()
+                .fold(html[start..start + open_len].to_string(), |tag, (name, value)| {
+                    set_attr(&tag, name, value.as_deref())
+                });
+            format!("{}{}{}", &html[..start], tag, &html[start + open_len..])
+        }
+        Err(count) => {
+            let dropped: Vec<&str> = attrs
+                .iter()
+                .filter(|(name, value)| {
+                    name != "class" || value.as_deref().is_none_or(|v| v.split_whitespace().any(|c| Some(c) != parent_scope))
+                })
+                .map(|(name, _)| name.as_str())
+                .collect();
+            if !dropped.is_empty() {
+                let renders = if count == 0 { "no element".to_string() } else { format!("{count} root nodes") };
+                ctx.warn(format!(
+                    "{label}: attributes not applied ({}): component renders {renders}",
+                    dropped.join(", ")
+                ));
+            }
+            html.to_string()
+        }
+    }
+}
 
-        // Content before this named slot is default content
-        let before = rest[..full_match.start()].trim();
-        if !before.is_empty() {
-            default_parts.push(before.to_string());
+// ─── Slots ──────────────────────────────────────────────────────────────
+
+/// Parsed slot content keyed by slot name ("default" for unnamed).
+type SlotMap = HashMap<String, String>;
+
+/// Result of parsing slot content, including collected styles from resolved components.
+struct SlotResult {
+    slots: SlotMap,
+    styles: Vec<String>,
+    style_sources: Vec<String>,
+    script_setup: Option<String>,
+    module_imports: Vec<ResolvedModule>,
+}
+
+/// Elements without a closing tag, skipped when tracking nesting depth.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "source", "track", "wbr",
+];
+
+/// Split a component's children into named `<template #name>` blocks and the
+/// default content around them, in document order.
+///
+/// Only templates at the top level of `children` name a slot: one nested in
+/// another element belongs to that element (e.g. a nested component's own
+/// slot). A named block ends at its matching `</template>`, so templates
+/// inside it are kept whole.
+fn split_slot_templates(children: &str) -> (Vec<(String, String)>, Vec<String>) {
+    let tag_re = Regex::new(r#"<(/?)([A-Za-z][\w-]*)(?:[^>"']|"[^"]*"|'[^']*')*?(/?)>"#).unwrap();
+    let named_re = Regex::new(r#"^<template\s+#(\w+)\s*>"#).unwrap();
+    let mut named = Vec::new();
+    let mut default_parts = Vec::new();
+    let mut push_default = |part: &str| {
+        let part = part.trim();
+        if !part.is_empty() {
+            default_parts.push(part.to_string());
+        }
+    };
+
+    let mut depth = 0usize;
+    let mut segment_start = 0;
+    let mut pos = 0;
+    while let Some(caps) = tag_re.captures_at(children, pos) {
+        let tag = caps.get(0).unwrap();
+        pos = tag.end();
+        let name = caps[2].to_ascii_lowercase();
+        if &caps[1] == "/" {
+            depth = depth.saturating_sub(1);
+            continue;
         }
+        if depth == 0 {
+            if let Some(slot) = named_re.captures(&children[tag.start()..]) {
+                push_default(&children[segment_start..tag.start()]);
+                let (content_end, block_end) = matching_template_close(children, tag.end(), &tag_re);
+                named.push((slot[1].to_string(), children[tag.end()..content_end].trim().to_string()));
+                segment_start = block_end;
+                pos = block_end;
+                continue;
+            }
+        }
+        if &caps[3] != "/" && !VOID_ELEMENTS.contains(&name.as_str()) {
+            depth += 1;
+        }
+    }
+    push_default(&children[segment_start..]);
+    (named, default_parts)
+}
 
-        // Find closing </template>
-        let after_open = &rest[full_match.end()..];
-        let close_pos = after_open.find("</template>");
-        let slot_content = if let Some(pos) = close_pos {
-            let content = after_open[..pos].trim().to_string();
-            rest = &after_open[pos + "</template>".len()..];
-            content
+/// Find the `</template>` closing a template whose content starts at `from`.
+///
+/// Returns where the content ends and where the closing tag ends; an unclosed
+/// template runs to the end of the input.
+fn matching_template_close(html: &str, from: usize, tag_re: &Regex) -> (usize, usize) {
+    let mut depth = 0usize;
+    let mut pos = from;
+    while let Some(caps) = tag_re.captures_at(html, pos) {
+        let tag = caps.get(0).unwrap();
+        pos = tag.end();
+        if !caps[2].eq_ignore_ascii_case("template") || &caps[3] == "/" {
+            continue;
+        }
+        if &caps[1] == "/" {
+            if depth == 0 {
+                return (tag.start(), tag.end());
+            }
+            depth -= 1;
         } else {
-            let content = after_open.trim().to_string();
-            rest = "";
-            content
-        };
+            depth += 1;
+        }
+    }
+    (html.len(), html.len())
+}
+
+/// Parse `<template #name>...</template>` blocks and default content from children.
+fn parse_slot_content(
+    children: &str,
+    parent_data: &Value,
+    parent_imports: &[VanImport],
+    current_path: &str,
+    depth: usize,
+    ctx: &ResolveCtx,
+) -> Result<SlotResult, String> {
+    let reactive_names = ctx.reactive_names.as_slice();
+    let mut slots = SlotMap::new();
+    let mut styles: Vec<String> = Vec::new();
+    let mut style_sources: Vec<String> = Vec::new();
 
+    let (named, default_parts) = split_slot_templates(children);
+    for (slot_name, slot_content) in named {
         // Interpolate named slot content with parent data
         let interpolated = if !reactive_names.is_empty() {
-            interpolate_skip_reactive(&slot_content, parent_data, reactive_names)
+            interpolate_skip_reactive(&slot_content, parent_data, reactive_names, ctx.sanitize_html, ctx.usage)
         } else {
-            interpolate(&slot_content, parent_data)
+            interpolate_with(&slot_content, parent_data, ctx.sanitize_html, ctx.usage)
         };
         slots.insert(slot_name, interpolated);
     }
@@ -719,51 +1913,57 @@ fn parse_slot_content(
     if !default_parts.is_empty() {
         let default_content = default_parts.join("\n");
 
-        let parent_import_map: HashMap<String, &VanImport> = parent_imports
-            .iter()
-            .map(|imp| (imp.tag_name.clone(), imp))
-            .collect();
-
         let resolved = resolve_slot_components(
             &default_content,
             parent_data,
-            &parent_import_map,
+            parent_imports,
             current_path,
-            files,
             depth,
-            reactive_names,
-            debug,
-            file_origins,
+            ctx,
         )?;
 
         slots.insert("default".to_string(), resolved.html);
         styles.extend(resolved.styles);
+        style_sources.extend(resolved.style_sources);
         script_setup = resolved.script_setup;
         module_imports = resolved.module_imports;
     }
 
-    Ok(SlotResult { slots, styles, script_setup, module_imports })
+    Ok(SlotResult { slots, styles, style_sources, script_setup, module_imports })
 }
 
 /// Resolve component tags within slot content using the parent's import context.
+///
+/// Children of those components are slot content too, so a component nested
+/// inside another one's slot receives its own slots.
 fn resolve_slot_components(
     content: &str,
     data: &Value,
-    import_map: &HashMap<String, &VanImport>,
+    imports: &[VanImport],
     current_path: &str,
-    files: &HashMap<String, String>,
     depth: usize,
-    reactive_names: &[String],
-    debug: bool,
-    file_origins: &HashMap<String, String>,
+    ctx: &ResolveCtx,
 ) -> Result<ResolvedComponent, String> {
+    let ResolveCtx { files, debug, file_origins, .. } = *ctx;
+    let import_map: HashMap<String, &VanImport> = imports
+        .iter()
+        .map(|imp| (imp.tag_name.clone(), imp))
+        .collect();
+    let reactive_names = ctx.reactive_names.as_slice();
+    // Slot content belongs to the parent template, which carries its scope class
+    let scope = files
+        .get(current_path)
+        .map(|source| parse_blocks(source))
+        .filter(|blocks| blocks.style_scoped)
+        .and_then(|blocks| blocks.style.map(|css| file_scope_id(current_path, &css, file_origins)));
     let mut result = content.to_string();
     let mut styles: Vec<String> = Vec::new();
+    let mut style_sources: Vec<String> = Vec::new();
     let mut child_scripts: Vec<String> = Vec::new();
     let mut child_module_imports: Vec<ResolvedModule> = Vec::new();
 
     loop {
-        let tag_match = find_component_tag(&result, import_map);
+        let tag_match = find_component_tag(&result, &import_map);
         let Some(tag_info) = tag_match else {
             break;
         };
@@ -774,21 +1974,41 @@ fn resolve_slot_components(
             .get(&resolved_key)
             .ok_or_else(|| format!("Component not found: {} (resolved from '{}')", resolved_key, imp.path))?;
 
-        let child_data = parse_props(&tag_info.attrs, data);
+        let child_blocks = parse_blocks(component_source);
+        let (child_data, unconverted) = parse_props(&tag_info.attrs, data, &child_blocks.props, ctx.usage);
+        for message in unconverted {
+            ctx.warn(format!("{current_path} -> {resolved_key}: {message}"));
+        }
+        validate_child_props(&tag_info.attrs, data, current_path, &resolved_key, component_source, ctx);
+
+        let slot_result = parse_slot_content(&tag_info.children, data, imports, current_path, depth, ctx)?;
+        styles.extend(slot_result.styles);
+        style_sources.extend(slot_result.style_sources);
+        if let Some(cs) = slot_result.script_setup {
+            child_scripts.push(cs);
+        }
+        child_module_imports.extend(slot_result.module_imports);
 
         let child_resolved = resolve_recursive(
             component_source,
             &child_data,
             &resolved_key,
-            files,
             depth + 1,
-            reactive_names,
-            debug,
-            file_origins,
+            ctx,
         )?;
 
-        let with_slots = distribute_slots(&child_resolved.html, &HashMap::new(), debug, &HashMap::new());
+        let child_debug = debug.filter(|_| child_blocks.options.debug_comments);
+        let with_slots = distribute_slots(&child_resolved.html, &slot_result.slots, child_debug, &HashMap::new());
+        let with_slots = apply_fallthrough(
+            &with_slots,
+            &tag_info.attrs,
+            &child_blocks.props,
+            scope.as_deref(),
+            &format!("{current_path} -> {resolved_key}"),
+            ctx,
+        );
         styles.extend(child_resolved.styles);
+        style_sources.extend(child_resolved.style_sources);
 
         // Collect child script_setup and module_imports for merging
         if let Some(ref cs) = child_resolved.script_setup {
@@ -796,11 +2016,11 @@ fn resolve_slot_components(
         }
         child_module_imports.extend(child_resolved.module_imports);
 
-        let replacement = if debug {
+        let replacement = if let Some(markers) = child_debug {
             let theme_prefix = file_origins.get(&resolved_key)
                 .map(|t| format!("[{t}] "))
                 .unwrap_or_default();
-            format!("<!-- START: {theme_prefix}{resolved_key} -->{with_slots}<!-- END: {theme_prefix}{resolved_key} -->")
+            mark_origin(&mark_component(&with_slots, &resolved_key), &format!("{theme_prefix}{resolved_key}"), markers)
         } else {
             with_slots
         };
@@ -815,9 +2035,9 @@ fn resolve_slot_components(
 
     // Interpolate remaining {{ }} with parent data (reactive-aware)
     let html = if !reactive_names.is_empty() {
-        interpolate_skip_reactive(&result, data, reactive_names)
+        interpolate_skip_reactive(&result, data, reactive_names, ctx.sanitize_html, ctx.usage)
     } else {
-        interpolate(&result, data)
+        interpolate_with(&result, data, ctx.sanitize_html, ctx.usage)
     };
 
     // Merge collected child scripts
@@ -830,18 +2050,34 @@ fn resolve_slot_components(
     Ok(ResolvedComponent {
         html,
         styles,
+        style_sources,
         script_setup,
-        module_imports: child_module_imports,
+        module_imports: dedupe_modules(child_module_imports),
+        warnings: Vec::new(),
+        dependencies: Vec::new(),
     })
 }
 
 /// Replace `<slot />` and `<slot name="x">fallback</slot>` with provided content.
 ///
+/// Runs as a single pass over the component's own markup: inserted content is
+/// not scanned again. A `<slot>` inside provided content (a wrapper forwarding
+/// its caller's slot, e.g. `<template #title><slot name="title" /></template>`)
+/// therefore survives into the wrapper's output and is filled from the outer
+/// caller's slot map when the wrapper itself is distributed.
+///
+/// `$slots` conditions in the markup are settled first (see
+/// [`slot_conditions`]), against the slots this caller provides.
+///
 /// `slot_themes` maps slot_name → theme_name for debug comments.
 /// Only shown for explicitly provided slots, not for fallback defaults.
-fn distribute_slots(html: &str, slots: &SlotMap, debug: bool, slot_themes: &HashMap<String, String>) -> String {
-    let mut result = html.to_string();
-
+fn distribute_slots(
+    html: &str,
+    slots: &SlotMap,
+    debug: Option<OriginMarkers>,
+    slot_themes: &HashMap<String, String>,
+) -> String {
+    let html = slot_conditions(html, slots);
     // Helper: build theme prefix for a given slot
     let tp = |name: &str| -> String {
         slot_themes.get(name)
@@ -850,77 +2086,150 @@ fn distribute_slots(html: &str, slots: &SlotMap, debug: bool, slot_themes: &Hash
             .unwrap_or_default()
     };
 
-    // Handle named slots: <slot name="x">fallback</slot>
-    let named_re = Regex::new(r#"<slot\s+name="(\w+)">([\s\S]*?)</slot>"#).unwrap();
-    result = named_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let name = &caps[1];
-            let fallback = &caps[2];
+    // <slot />, <slot name="x" />, <slot>fallback</slot>, <slot name="x">fallback</slot>
+    let slot_re = Regex::new(r#"<slot(?:\s+name="(\w+)")?\s*(?:/>|>([\s\S]*?)</slot>)"#).unwrap();
+    slot_re
+        .replace_all(&html, |caps: &regex::Captures| {
+            let name = caps.get(1).map_or("default", |m| m.as_str());
+            let fallback = caps.get(2).map_or("", |m| m.as_str());
             let provided = slots.get(name);
             let content = provided
                 .cloned()
                 .unwrap_or_else(|| fallback.trim().to_string());
-            if debug {
+            if let Some(markers) = debug {
                 let p = if provided.is_some() { tp(name) } else { String::new() };
-                format!("<!-- START: {p}#{name} -->{content}<!-- END: {p}#{name} -->")
+                mark_origin(&content, &format!("{p}#{name}"), markers)
             } else {
                 content
             }
         })
-        .to_string();
+        .to_string()
+}
 
-    // Handle named self-closing slots: <slot name="x" />
-    let named_sc_re = Regex::new(r#"<slot\s+name="(\w+)"\s*/>"#).unwrap();
-    result = named_sc_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let name = &caps[1];
-            let provided = slots.get(name);
-            let content = provided.cloned().unwrap_or_default();
-            if debug {
-                let p = if provided.is_some() { tp(name) } else { String::new() };
-                format!("<!-- START: {p}#{name} -->{content}<!-- END: {p}#{name} -->")
-            } else {
-                content
-            }
-        })
-        .to_string();
-
-    // Handle default slot: <slot /> (self-closing)
-    let default_sc_re = Regex::new(r#"<slot\s*/>"#).unwrap();
-    result = default_sc_re
-        .replace_all(&result, |_: &regex::Captures| {
-            let provided = slots.get("default");
-            let content = provided.cloned().unwrap_or_default();
-            if debug {
-                let p = if provided.is_some() { tp("default") } else { String::new() };
-                format!("<!-- START: {p}#default -->{content}<!-- END: {p}#default -->")
-            } else {
-                content
-            }
-        })
-        .to_string();
-
-    // Handle default slot with fallback: <slot>fallback</slot>
-    let default_re = Regex::new(r#"<slot>([\s\S]*?)</slot>"#).unwrap();
-    result = default_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let fallback = &caps[1];
-            let provided = slots.get("default");
-            let content = provided
-                .cloned()
-                .unwrap_or_else(|| fallback.trim().to_string());
-            if debug {
-                let p = if provided.is_some() { tp("default") } else { String::new() };
-                format!("<!-- START: {p}#default -->{content}<!-- END: {p}#default -->")
-            } else {
-                content
-            }
-        })
-        .to_string();
+static SLOT_COND_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\s+(v-if|v-show)="([^"]*\$slots\.[^"]*)""#).unwrap());
 
+/// Settle `v-if` / `v-show` conditions on `$slots.<name>` against the names
+/// in `slots`: a false `v-if` removes its element, a false `v-show` hides it,
+/// and a true one just loses the directive.
+///
+/// Only conditions reading nothing but `$slots` are settled (`$slots.sidebar`,
+/// `!$slots.footer`, `$slots.a && $slots.b`); the rest are left for the data.
+fn slot_conditions(html: &str, slots: &SlotMap) -> String {
+    let provided: Map<String, Value> = slots.keys().map(|name| (name.clone(), Value::Bool(true))).collect();
+    let data = serde_json::json!({ "$slots": provided });
+    let mut result = html.to_string();
+    let mut pos = 0;
+    while let Some(caps) = SLOT_COND_RE.captures_at(&result, pos) {
+        let attr = caps.get(0).unwrap().range();
+        let directive = caps[1].to_string();
+        let settled = expr::evaluate(&caps[2], &data)
+            .filter(|e| e.paths.iter().all(|(path, _)| path.starts_with("$slots.")))
+            .map(|_| expr::condition(&caps[2], &data, &DataUsage::default()).0);
+        let Some(shown) = settled else {
+            pos = attr.end;
+            continue;
+        };
+        let tag_start = result[..attr.start].rfind('<').unwrap_or(0);
+        if shown || directive.eq_ignore_ascii_case("v-show") {
+            let replacement = if shown { "" } else { r#" style="display:none""# };
+            result.replace_range(attr.clone(), replacement);
+            pos = attr.start + replacement.len();
+            continue;
+        }
+        let open_end = result[attr.end..].find('>').map_or(result.len(), |gt| attr.end + gt + 1);
+        let element_end = if result[..open_end].ends_with("/>") {
+            open_end
+        } else {
+            let tag: String = result[tag_start + 1..].chars().take_while(|c| c.is_alphanumeric() || *c == '-').collect();
+            let close = format!("</{tag}>");
+            (open_end + find_matching_close_tag(&result[open_end..], &tag) + close.len()).min(result.len())
+        };
+        result.replace_range(tag_start..element_end, "");
+        pos = tag_start;
+    }
     result
 }
 
+/// Mark `content` with where it came from (`label` is e.g. `[van1] components/card.van`
+/// or `#title` for a slot).
+fn mark_origin(content: &str, label: &str, markers: OriginMarkers) -> String {
+    match markers {
+        OriginMarkers::Comments => {
+            let label = comment_safe(label);
+            format!("<!-- START: {label} -->{content}<!-- END: {label} -->")
+        }
+        OriginMarkers::Attributes => add_origin_attr(content, label),
+    }
+}
+
+/// Make text safe inside an HTML comment: no `--` (which would end it early
+/// in some parsers) and no `>`.
+fn comment_safe(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '>' => {}
+            '-' if out.ends_with('-') => out.push_str(" -"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Add `data-van-origin` to the first element of an HTML fragment.
+///
+/// An element that already carries one (from a nested component that renders
+/// it) keeps the innermost origin.
+fn add_origin_attr(content: &str, label: &str) -> String {
+    add_root_attr(content, "data-van-origin", label)
+}
+
+/// Mark a resolved component's markup with its virtual path, as a
+/// `data-van-component` attribute on its first element (debug mode only).
+/// Like origins, the innermost component rendering an element names it.
+fn mark_component(content: &str, path: &str) -> String {
+    add_root_attr(content, "data-van-component", path)
+}
+
+/// Add `attr="value"` to the first element of an HTML fragment, unless it
+/// already has `attr`.
+fn add_root_attr(content: &str, attr: &str, value: &str) -> String {
+    let mut pos = 0;
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &content[start..];
+        if rest.starts_with("<!--") {
+            match rest.find("-->") {
+                Some(end) => {
+                    pos = start + end + 3;
+                    continue;
+                }
+                None => break,
+            }
+        }
+        if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            pos = start + 1;
+            continue;
+        }
+        let name_len = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(rest.len() - 1);
+        let tag_end = rest.find('>').unwrap_or(rest.len());
+        if rest[..tag_end].contains(&format!("{attr}=")) {
+            break;
+        }
+        let insert_at = start + 1 + name_len;
+        return format!(
+            "{} {attr}=\"{}\"{}",
+            &content[..insert_at],
+            escape_html(value),
+            &content[insert_at..]
+        );
+    }
+    content.to_string()
+}
+
 /// Resolve a dot-separated path and return the raw JSON Value.
 fn resolve_path_value<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
     let mut current = data;
@@ -934,13 +2243,39 @@ fn resolve_path_value<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
     Some(current)
 }
 
+static VFOR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<(\w[\w-]*)([^>]*)\sv-for="([^"]*)"([^>]*)>"#).unwrap());
+
 /// Expand `v-for` directives by repeating elements for each array item.
-fn expand_v_for(template: &str, data: &Value) -> String {
-    let vfor_re = Regex::new(r#"<(\w[\w-]*)([^>]*)\sv-for="([^"]*)"([^>]*)>"#).unwrap();
+///
+/// A `v-empty` element right after a `v-for` one (only whitespace between)
+/// is the list's empty state: rendered, without the attribute, when there are
+/// no items, and dropped otherwise.
+///
+/// `sanitize` is passed on to the interpolation of each item. With `only`,
+/// loops over other names are left in place. `v-raw` content is left alone.
+pub(crate) fn expand_v_for(
+    template: &str,
+    data: &Value,
+    sanitize: bool,
+    only: Option<&[String]>,
+    usage: &DataUsage,
+) -> String {
+    raw::outside_raw(template, |part| expand_v_for_part(part, data, sanitize, only, usage))
+}
+
+fn expand_v_for_part(
+    template: &str,
+    data: &Value,
+    sanitize: bool,
+    only: Option<&[String]>,
+    usage: &DataUsage,
+) -> String {
     let mut result = template.to_string();
+    let mut from = 0;
 
     for _ in 0..20 {
-        let Some(cap) = vfor_re.captures(&result) else {
+        let Some(cap) = VFOR_RE.captures_at(&result, from) else {
             break;
         };
 
@@ -954,6 +2289,13 @@ fn expand_v_for(template: &str, data: &Value) -> String {
         let open_tag_no_vfor = format!("<{}{}{}>", tag_name, attrs_before, attrs_after);
         let match_start = full_match.start();
         let after_open = full_match.end();
+        let root = array_expr.split('.').next().unwrap_or("").trim();
+        if only.is_some_and(|names| !names.iter().any(|name| name == root)) {
+            from = after_open;
+            continue;
+        }
+        from = match_start;
+        usage.record_expr(&array_expr, data);
         let is_self_closing = result[match_start..after_open].trim_end_matches('>').ends_with('/');
 
         if is_self_closing {
@@ -961,17 +2303,19 @@ fn expand_v_for(template: &str, data: &Value) -> String {
             let array = resolve_path_value(data, &array_expr);
             let items = array.and_then(|v| v.as_array()).cloned().unwrap_or_default();
             let mut expanded = String::new();
+            // One copy of the data per loop; each item overwrites the loop variables
+            let mut item_data = data.clone();
             for (idx, item) in items.iter().enumerate() {
-                let mut item_data = data.clone();
                 if let Value::Object(ref mut map) = item_data {
                     map.insert(item_var.clone(), item.clone());
                     if let Some(ref idx_var) = index_var {
                         map.insert(idx_var.clone(), Value::Number(idx.into()));
                     }
                 }
-                expanded.push_str(&interpolate(&sc_tag, &item_data));
+                expanded.push_str(&interpolate_with(&sc_tag, &item_data, sanitize, usage));
             }
-            result = format!("{}{}{}", &result[..match_start], expanded, &result[after_open..]);
+            let (end, empty) = empty_state(&result, after_open, items.is_empty()).unwrap_or((after_open, String::new()));
+            result = format!("{}{}{}{}", &result[..match_start], expanded, empty, &result[end..]);
             continue;
         }
 
@@ -984,25 +2328,65 @@ fn expand_v_for(template: &str, data: &Value) -> String {
         let array = resolve_path_value(data, &array_expr);
         let items = array.and_then(|v| v.as_array()).cloned().unwrap_or_default();
         let mut expanded = String::new();
+        let mut item_data = data.clone();
         for (idx, item) in items.iter().enumerate() {
-            let mut item_data = data.clone();
             if let Value::Object(ref mut map) = item_data {
                 map.insert(item_var.clone(), item.clone());
                 if let Some(ref idx_var) = index_var {
                     map.insert(idx_var.clone(), Value::Number(idx.into()));
                 }
             }
-            let tag_interpolated = interpolate(&open_tag_no_vfor, &item_data);
-            let inner_interpolated = interpolate(&inner_content, &item_data);
-            expanded.push_str(&format!("{}{}</{}>", tag_interpolated, inner_interpolated, tag_name));
+            let tag_interpolated = interpolate_with(&open_tag_no_vfor, &item_data, sanitize, usage);
+            let inner_interpolated = interpolate_with(&inner_content, &item_data, sanitize, usage);
+            if tag_name == "template" {
+                // A `<template v-for>` group repeats its children, not itself
+                expanded.push_str(&inner_interpolated);
+            } else {
+                expanded.push_str(&format!("{}{}</{}>", tag_interpolated, inner_interpolated, tag_name));
+            }
         }
 
-        result = format!("{}{}{}", &result[..match_start], expanded, &result[element_end..]);
+        let (end, empty) = empty_state(&result, element_end, items.is_empty()).unwrap_or((element_end, String::new()));
+        result = format!("{}{}{}{}", &result[..match_start], expanded, empty, &result[end..]);
     }
 
     result
 }
 
+static EMPTY_OPEN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^\s*<(\w[\w-]*)([^>]*)>"#).unwrap());
+static EMPTY_ATTR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\s+v-empty(?:="[^"]*")?(\s|/|$)"#).unwrap());
+
+/// The `v-empty` element following a `v-for` one that ends at `end` in
+/// `html`: where it ends, and what replaces it from `end` on — itself without
+/// the attribute when the list is `empty`, nothing otherwise. `None` when the
+/// next element has no `v-empty`.
+fn empty_state(html: &str, end: usize, empty: bool) -> Option<(usize, String)> {
+    let rest = &html[end..];
+    let open = EMPTY_OPEN_RE.captures(rest)?;
+    let attrs = &open[2];
+    let directive = EMPTY_ATTR_RE.captures(attrs)?;
+    let open_end = open.get(0).unwrap().end();
+    let element_end = if attrs.trim_end().ends_with('/') {
+        open_end
+    } else {
+        let tag = &open[1];
+        open_end + find_matching_close_tag(&rest[open_end..], tag) + format!("</{tag}>").len()
+    };
+    let element_end = element_end.min(rest.len());
+    if !empty {
+        return Some((end + element_end, String::new()));
+    }
+    let attrs_start = open.get(2).unwrap().start();
+    let whole = directive.get(0).unwrap();
+    let kept = format!(
+        "{}{}{}",
+        &rest[..attrs_start + whole.start()],
+        &directive[1],
+        &rest[attrs_start + whole.end()..element_end]
+    );
+    Some((end + element_end, kept))
+}
+
 /// Replace `<ClientOnly>...</ClientOnly>` tags with `<!--client-only-->...<!--/client-only-->` markers.
 fn replace_client_only_tags(html: &str) -> String {
     let open_re = Regex::new(r"(?i)<ClientOnly\s*/?>").unwrap();
@@ -1029,13 +2413,19 @@ fn parse_vfor_expr(expr: &str) -> (String, Option<String>, String) {
     }
 }
 
-fn find_matching_close_tag(html: &str, tag_name: &str) -> usize {
+/// Position of the `</tag_name>` closing an element whose content starts at
+/// the beginning of `html`, or `html.len()` if it is never closed.
+///
+/// Nested elements with the same name are counted; self-closing ones are not.
+pub(crate) fn find_matching_close_tag(html: &str, tag_name: &str) -> usize {
     let open = format!("<{}", tag_name);
     let close = format!("</{}>", tag_name);
     let mut depth = 0;
     let mut pos = 0;
     while pos < html.len() {
-        if html[pos..].starts_with(&close) {
+        if html[pos..].starts_with("<!--") {
+            pos = html[pos..].find("-->").map_or(html.len(), |end| pos + end + 3);
+        } else if html[pos..].starts_with(&close) {
             if depth == 0 {
                 return pos;
             }
@@ -1045,7 +2435,10 @@ fn find_matching_close_tag(html: &str, tag_name: &str) -> usize {
             let after = pos + open.len();
             if after < html.len() {
                 let ch = html.as_bytes()[after] as char;
-                if ch == ' ' || ch == '>' || ch == '/' || ch == '\n' || ch == '\t' {
+                let self_closing = html[pos..]
+                    .find('>')
+                    .is_some_and(|gt| html[pos..pos + gt].ends_with('/'));
+                if matches!(ch, ' ' | '>' | '/' | '\n' | '\r' | '\t') && !self_closing {
                     depth += 1;
                 }
             }
@@ -1057,6 +2450,16 @@ fn find_matching_close_tag(html: &str, tag_name: &str) -> usize {
     html.len()
 }
 
+/// Compile this module's lazy regexes now (see [`crate::warmup`]), returning
+/// how many there are.
+pub(crate) fn warmup() -> usize {
+    let lazies = [&SLOT_COND_RE, &VFOR_RE, &EMPTY_OPEN_RE, &EMPTY_ATTR_RE];
+    for lazy in lazies {
+        LazyLock::force(lazy);
+    }
+    lazies.len()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1072,6 +2475,22 @@ const doubled = computed(() => count * 2)
         assert_eq!(names, vec!["count", "doubled"]);
     }
 
+    #[test]
+    fn test_dollar_reactive_name_kept_for_client() {
+        let source = r#"
+<template>
+  <p>{{ $query }} / {{ user.status }}</p>
+</template>
+<script setup>
+const $query = ref('')
+const status = ref('idle')
+</script>
+"#;
+        let data = json!({"$query": "server", "user": {"status": "active"}});
+        let resolved = resolve_single(source, &data).unwrap();
+        assert!(resolved.html.contains("<p>{{ $query }} / active</p>"), "Got:\n{}", resolved.html);
+    }
+
     #[test]
     fn test_resolve_single_basic() {
         let source = r#"
@@ -1400,55 +2819,785 @@ const count = ref(0)
     fn test_parse_props() {
         let data = json!({"title": "World", "count": 42});
         let attrs = r#":name="title" :num="count""#;
-        let result = parse_props(attrs, &data);
+        let (result, unconverted) = parse_props(attrs, &data, &[], &DataUsage::default());
         assert_eq!(result["name"], "World");
         assert_eq!(result["num"], "42");
+        assert!(unconverted.is_empty());
     }
 
     #[test]
-    fn test_distribute_slots_default() {
-        let html = r#"<div><slot /></div>"#;
-        let mut slots = HashMap::new();
-        slots.insert("default".to_string(), "Hello World".to_string());
-        let result = distribute_slots(html, &slots, false, &HashMap::new());
-        assert_eq!(result, "<div>Hello World</div>");
-    }
-
-    #[test]
-    fn test_distribute_slots_named() {
-        let html =
-            r#"<title><slot name="title">Fallback</slot></title><div><slot /></div>"#;
-        let mut slots = HashMap::new();
-        slots.insert("title".to_string(), "My Title".to_string());
-        slots.insert("default".to_string(), "Body".to_string());
-        let result = distribute_slots(html, &slots, false, &HashMap::new());
-        assert_eq!(result, "<title>My Title</title><div>Body</div>");
-    }
-
-    #[test]
-    fn test_distribute_slots_fallback() {
-        let html = r#"<title><slot name="title">Fallback Title</slot></title>"#;
-        let slots = HashMap::new();
-        let result = distribute_slots(html, &slots, false, &HashMap::new());
-        assert_eq!(result, "<title>Fallback Title</title>");
+    fn test_parse_props_coerces_declared_types() {
+        let prop = |name: &str, prop_type: &str| PropDef {
+            name: name.to_string(),
+            prop_type: Some(prop_type.to_string()),
+            required: false,
+            default: None,
+        };
+        let props = [
+            prop("count", "Number"),
+            prop("open", "Boolean"),
+            prop("tags", "Array"),
+            prop("user", "Object"),
+            prop("label", "String"),
+            prop("price", "Number"),
+        ];
+        let data = json!({
+            "count": "3",
+            "open": "false",
+            "tags": "[\"a\", \"b\"]",
+            "user": "{\"name\": \"Ada\"}",
+            "label": 7,
+            "price": 9.5,
+        });
+        let attrs = r#":count="count" :open="open" :tags="tags" :user="user" :label="label" :price="price""#;
+        let (result, unconverted) = parse_props(attrs, &data, &props, &DataUsage::default());
+        assert_eq!(result["count"], json!(3));
+        assert_eq!(result["open"], json!(false));
+        assert_eq!(result["tags"], json!(["a", "b"]));
+        assert_eq!(result["user"], json!({ "name": "Ada" }));
+        assert_eq!(result["label"], json!("7"), "String props stay strings");
+        assert_eq!(result["price"], json!(9.5), "typed values pass as they are");
+        assert!(unconverted.is_empty(), "{unconverted:?}");
+
+        let data = json!({ "count": "three", "open": "yes" });
+        let (result, unconverted) = parse_props(r#":count="count" :open="open""#, &data, &props, &DataUsage::default());
+        assert_eq!(result["count"], json!("three"));
+        assert_eq!(
+            unconverted,
+            vec![r#"prop "count" expects Number, could not convert "three""#, r#"prop "open" expects Boolean, could not convert "yes""#]
+        );
     }
 
     #[test]
-    fn test_expand_v_for_basic() {
-        let data = json!({"items": ["Alice", "Bob", "Charlie"]});
-        let template = r#"<ul><li v-for="item in items">{{ item }}</li></ul>"#;
-        let result = expand_v_for(template, &data);
-        assert!(result.contains("<li>Alice</li>"));
-        assert!(result.contains("<li>Bob</li>"));
-        assert!(result.contains("<li>Charlie</li>"));
-        assert!(!result.contains("v-for"));
+    fn test_coerced_props_in_child() {
+        let mut files = HashMap::new();
+        files.insert("pages/index.van".to_string(), r#"
+<template><stock-badge :count="stock" :tags="tags" /></template>
+<script setup>
+import StockBadge from '../components/stock-badge.van'
+</script>
+"#.to_string());
+        files.insert("components/stock-badge.van".to_string(), r#"
+<template><p>{{ count === 9 }}</p><ul><li v-for="tag in tags">{{ tag }}</li></ul></template>
+<script setup>
+defineProps({ count: Number, tags: Array })
+</script>
+"#.to_string());
+        let data = json!({ "stock": "9", "tags": "[\"new\", \"sale\"]" });
+        let resolved = resolve_with_files("pages/index.van", &files, &data).unwrap();
+        assert!(resolved.html.contains("<p>true</p>"), "{}", resolved.html);
+        assert!(resolved.html.contains("<li>new</li><li>sale</li>"), "{}", resolved.html);
+        assert!(resolved.warnings.is_empty(), "{:?}", resolved.warnings);
+    }
+
+    fn nested_prop_files(wrapper_template: &str) -> HashMap<String, String> {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"
+<template><wrapper :label="title" /></template>
+<script setup>
+import Wrapper from '../components/wrapper.van'
+</script>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "components/wrapper.van".to_string(),
+            format!(
+                "<template>{wrapper_template}</template>\n\
+                 <script setup>\n\
+                 import Counter from './counter.van'\n\
+                 defineProps({{ label: String }})\n\
+                 </script>"
+            ),
+        );
+        files.insert(
+            "components/counter.van".to_string(),
+            r#"
+<template><span>{{ text }}: {{ count }}</span></template>
+<script setup>
+defineProps({ count: Number, text: { type: String, required: true } })
+</script>
+"#
+            .to_string(),
+        );
+        files
+    }
+
+    #[test]
+    fn test_nested_prop_type_mismatch() {
+        let files = nested_prop_files(r#"<counter :count="label" text="Clicks" />"#);
+        let data = json!({"title": "Hello"});
+        let resolved = resolve_with_files("pages/index.van", &files, &data).unwrap();
+        assert_eq!(
+            resolved.warnings,
+            vec![r#"components/wrapper.van -> components/counter.van: prop "count" expects Number, could not convert "Hello""#]
+        );
+
+        // A numeric string is what a passed-through Number looks like, so it is accepted.
+        let data = json!({"title": "42"});
+        let resolved = resolve_with_files("pages/index.van", &files, &data).unwrap();
+        assert!(resolved.warnings.is_empty(), "{:?}", resolved.warnings);
+    }
+
+    #[test]
+    fn test_nested_missing_required_prop() {
+        let files = nested_prop_files(r#"<counter :count="label" />"#);
+        let data = json!({"title": "3"});
+        let resolved = resolve_with_files("pages/index.van", &files, &data).unwrap();
+        assert_eq!(
+            resolved.warnings,
+            vec![r#"components/wrapper.van -> components/counter.van: missing required prop "text" (String)"#]
+        );
+
+        // Missing required props are reported in compile mode too.
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+        assert_eq!(resolved.warnings.len(), 1, "{:?}", resolved.warnings);
+    }
+
+    #[test]
+    fn test_distribute_slots_default() {
+        let html = r#"<div><slot /></div>"#;
+        let mut slots = HashMap::new();
+        slots.insert("default".to_string(), "Hello World".to_string());
+        let result = distribute_slots(html, &slots, None, &HashMap::new());
+        assert_eq!(result, "<div>Hello World</div>");
+    }
+
+    #[test]
+    fn test_distribute_slots_named() {
+        let html =
+            r#"<title><slot name="title">Fallback</slot></title><div><slot /></div>"#;
+        let mut slots = HashMap::new();
+        slots.insert("title".to_string(), "My Title".to_string());
+        slots.insert("default".to_string(), "Body".to_string());
+        let result = distribute_slots(html, &slots, None, &HashMap::new());
+        assert_eq!(result, "<title>My Title</title><div>Body</div>");
+    }
+
+    #[test]
+    fn test_distribute_slots_fallback() {
+        let html = r#"<title><slot name="title">Fallback Title</slot></title>"#;
+        let slots = HashMap::new();
+        let result = distribute_slots(html, &slots, None, &HashMap::new());
+        assert_eq!(result, "<title>Fallback Title</title>");
+    }
+
+    #[test]
+    fn test_slot_conditions() {
+        let layout = r#"<template><main><slot /></main><aside v-if="$slots.sidebar" class="side"><div><slot name="sidebar" /></div></aside><p v-if="!$slots.sidebar">No sidebar</p><hr v-show="$slots.sidebar" /><p v-if="$slots.sidebar && open">x</p></template>"#;
+        let page = |children: &str| {
+            format!("<template><base-layout>{children}</base-layout></template>\n<script setup>\nimport BaseLayout from './layout.van'\n</script>")
+        };
+        let files = |children: &str| {
+            HashMap::from([
+                ("index.van".to_string(), page(children)),
+                ("layout.van".to_string(), layout.to_string()),
+            ])
+        };
+
+        let provided = files("<template #sidebar><nav>Links</nav></template><p>Body</p>");
+        let resolved = resolve_with_files("index.van", &provided, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html,
+            r#"<main><p>Body</p></main><aside class="side"><div><nav>Links</nav></div></aside><hr /><p v-if="$slots.sidebar && open">x</p>"#
+        );
+
+        let absent = files("<p>Body</p>");
+        let resolved = resolve_with_files("index.van", &absent, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html,
+            r#"<main><p>Body</p></main><p>No sidebar</p><hr style="display:none" /><p v-if="$slots.sidebar && open">x</p>"#
+        );
+
+        let own = "<template><div v-if=\"$slots.default\">Wrapped</div></template>";
+        let resolved = resolve_with_files("index.van", &HashMap::from([("index.van".to_string(), own.to_string())]), &json!({})).unwrap();
+        assert_eq!(resolved.html, "");
+    }
+
+    #[test]
+    fn test_named_slot_forwarded_through_wrapper() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><page-shell><template #title>Docs</template><p>Body</p></page-shell></template>
+<script setup>
+import PageShell from '../components/page-shell.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "components/page-shell.van".to_string(),
+            r#"<template><base-layout><template #title><slot name="title">Untitled</slot> | Site</template><nav>Menu</nav><slot /></base-layout></template>
+<script setup>
+import BaseLayout from '../layouts/base.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "layouts/base.van".to_string(),
+            r#"<template><header><h1><slot name="title" /></h1></header><main><slot /></main></template>"#.to_string(),
+        );
+
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html,
+            "<header><h1>Docs | Site</h1></header><main><nav>Menu</nav><p>Body</p></main>"
+        );
+    }
+
+    #[test]
+    fn test_default_slot_forwarded_into_named_slot() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><page-shell>Page title</page-shell></template>
+<script setup>
+import PageShell from '../components/page-shell.van'
+</script>"#
+                .to_string(),
+        );
+        // The wrapper forwards its default slot into the layout's title slot
+        files.insert(
+            "components/page-shell.van".to_string(),
+            r#"<template><base-layout><template #title><slot /></template><p>Shell body</p></base-layout></template>
+<script setup>
+import BaseLayout from '../layouts/base.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "layouts/base.van".to_string(),
+            r#"<template><h1><slot name="title" /></h1><main><slot /></main></template>"#.to_string(),
+        );
+
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+        assert_eq!(resolved.html, "<h1>Page title</h1><main><p>Shell body</p></main>");
+    }
+
+    #[test]
+    fn test_split_slot_templates() {
+        let (named, default) = split_slot_templates(
+            "<p>a</p>\n<template #x>X <template v-if=\"y\">Y</template></template>\n<p>b</p><card><template #title>T</template></card>",
+        );
+        assert_eq!(named, vec![("x".to_string(), "X <template v-if=\"y\">Y</template>".to_string())]);
+        assert_eq!(
+            default,
+            vec!["<p>a</p>".to_string(), "<p>b</p><card><template #title>T</template></card>".to_string()]
+        );
+
+        let (named, default) = split_slot_templates("<img src=\"a.png\"><br/><template #x>open");
+        assert_eq!(named, vec![("x".to_string(), "open".to_string())]);
+        assert_eq!(default, vec![r#"<img src="a.png"><br/>"#.to_string()]);
+    }
+
+    #[test]
+    fn test_named_slot_containing_component_slot_template() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><base-layout><template #header><card><template #title>Latest</template><p>News</p></card></template><p>Main</p></base-layout></template>
+<script setup>
+import BaseLayout from '../layouts/base.van'
+import Card from '../components/card.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "layouts/base.van".to_string(),
+            r#"<template><header><slot name="header" /></header><main><slot /></main></template>"#.to_string(),
+        );
+        files.insert(
+            "components/card.van".to_string(),
+            r#"<template><section><h2><slot name="title" /></h2><slot /></section></template>"#.to_string(),
+        );
+
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html,
+            "<header><section><h2>Latest</h2><p>News</p></section></header><main><p>Main</p></main>"
+        );
+    }
+
+    #[test]
+    fn test_same_component_nested() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><card><card>inner</card>outer</card><card /></template>
+<script setup>
+import Card from '../components/card.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "components/card.van".to_string(),
+            r#"<template><div class="card"><slot>empty</slot></div></template>"#.to_string(),
+        );
+
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html,
+            r#"<div class="card"><div class="card">inner</div>outer</div><div class="card">empty</div>"#
+        );
+    }
+
+    #[test]
+    fn test_nested_component_module_imports() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><price-tag /><price-tag /></template>
+<script setup>
+import PriceTag from '../components/price-tag.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "components/price-tag.van".to_string(),
+            r#"<template><button @click="show">Price</button></template>
+<script setup>
+import { currency } from '../utils/currency.ts'
+function show() { alert(currency(3)) }
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "utils/currency.ts".to_string(),
+            "function currency(n) { return '$' + n; }\nreturn { currency: currency };".to_string(),
+        );
+
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+        let paths: Vec<&str> = resolved.module_imports.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["utils/currency.ts"]);
+        assert!(resolved.module_imports[0].content.contains("function currency"));
+    }
+
+    #[test]
+    fn test_typescript_is_erased() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            r#"<template><p>{{ label }}</p></template>
+<script setup lang="ts">
+import { pad } from './utils/format.ts'
+interface Item { id: number }
+const count = ref<number>(0)
+function label(item: Item): string { return pad(item.id) }
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "utils/format.ts".to_string(),
+            "function pad(n: number, width?: number): string {\n  return String(n).padStart(width ?? 2, '0') as string;\n}\nreturn { pad: pad };".to_string(),
+        );
+
+        let resolved = resolve_with_files("index.van", &files, &json!({})).unwrap();
+        let script = resolved.script_setup.unwrap();
+        assert!(!script.contains("interface"));
+        assert!(script.contains("const count = ref(0)"));
+        assert!(script.contains("function label(item) { return pad(item.id) }"));
+        assert_eq!(
+            resolved.module_imports[0].content,
+            "function pad(n, width) {\n  return String(n).padStart(width ?? 2, '0');\n}\nreturn { pad: pad };"
+        );
+    }
+
+    #[test]
+    fn test_commented_component_tag_is_untouched() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            r#"<template><!-- TODO use <hello /> here --><p title="<hello />">x</p><hello /></template>
+<script setup>
+import Hello from './hello.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert("hello.van".to_string(), "<template><b>hi</b></template>".to_string());
+
+        let resolved = resolve_with_files("index.van", &files, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html,
+            r#"<!-- TODO use <hello /> here --><p title="<hello />">x</p><b>hi</b>"#
+        );
+    }
+
+    #[test]
+    fn test_component_usage_in_code_block_is_untouched() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            r#"<template><pre><code>&lt;hello&gt;
+<hello>slot</hello></code></pre><hello>real</hello></template>
+<script setup>
+import Hello from './hello.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert("hello.van".to_string(), "<template><b><slot /></b></template>".to_string());
+
+        let resolved = resolve_with_files("index.van", &files, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html,
+            "<pre><code>&lt;hello&gt;\n<hello>slot</hello></code></pre><b>real</b>"
+        );
+    }
+
+    #[test]
+    fn test_find_matching_close_tag_nested() {
+        let html = "<card /><card>a</card>b</card>rest";
+        assert_eq!(&html[find_matching_close_tag(html, "card")..], "</card>rest");
+        assert_eq!(find_matching_close_tag("<card>x</card>", "card"), "<card>x</card>".len());
+    }
+
+    #[test]
+    fn test_self_recursive_component_is_a_cycle() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><loop-item /></template>
+<script setup>
+import LoopItem from '../components/loop-item.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "components/loop-item.van".to_string(),
+            r#"<template><div><loop-item /></div></template>
+<script setup>
+import LoopItem from './loop-item.van'
+</script>"#
+                .to_string(),
+        );
+
+        let err = resolve_with_files("pages/index.van", &files, &json!({})).unwrap_err();
+        assert_eq!(
+            err,
+            "Component cycle detected: components/loop-item.van -> components/loop-item.van"
+        );
+    }
+
+    #[test]
+    fn test_comment_safe() {
+        assert_eq!(comment_safe("[my--theme] components/a--b.van"), "[my- -theme] components/a- -b.van");
+        assert_eq!(comment_safe("x--->y"), "x- - -y");
+        assert_eq!(comment_safe("components/card.van"), "components/card.van");
+    }
+
+    #[test]
+    fn test_debug_comments_sanitized() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><x-card /></template>
+<script setup>
+import XCard from '../components/x--card.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert("components/x--card.van".to_string(), "<template><p>Card</p></template>".to_string());
+        let origins = HashMap::from([("components/x--card.van".to_string(), "th--eme>".to_string())]);
+        let resolved = resolve_with_files_debug("pages/index.van", &files, &json!({}), &origins).unwrap();
+        assert_eq!(
+            resolved.html,
+            "<!-- START: [th- -eme] components/x- -card.van --><p data-van-component=\"components/x--card.van\">Card</p><!-- END: [th- -eme] components/x- -card.van -->"
+        );
+    }
+
+    #[test]
+    fn test_debug_origin_attributes() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><layout><h1>Hi</h1></layout></template>
+<script setup>
+import Layout from '../layouts/default.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "layouts/default.van".to_string(),
+            r#"<template><main class="page"><slot /></main></template>"#.to_string(),
+        );
+        let options = CompileOptions {
+            debug: true,
+            origin_markers: OriginMarkers::Attributes,
+            file_origins: HashMap::from([("layouts/default.van".to_string(), "van1".to_string())]),
+            ..CompileOptions::default()
+        };
+        let resolved = resolve_with_options("pages/index.van", &files, &json!({}), &options).unwrap();
+        assert_eq!(
+            resolved.html,
+            r##"<main data-van-origin="[van1] layouts/default.van" data-van-component="layouts/default.van" class="page"><h1 data-van-origin="#default">Hi</h1></main>"##
+        );
+        assert!(!resolved.html.contains("<!--"));
+    }
+
+    #[test]
+    fn test_define_options_debug_comments() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><card><b>Hi</b></card><icon /></template>
+<script setup>
+import Card from '../components/card.van'
+import Icon from '../components/icon.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert(
+            "components/card.van".to_string(),
+            "<template><div><slot /></div></template>\n<script setup>\ndefineOptions({ debugComments: false })\n</script>".to_string(),
+        );
+        files.insert("components/icon.van".to_string(), "<template><i>*</i></template>".to_string());
+        let resolved = resolve_with_files_debug("pages/index.van", &files, &json!({}), &HashMap::new()).unwrap();
+        assert_eq!(
+            resolved.html,
+            "<div><b>Hi</b></div><!-- START: components/icon.van --><i data-van-component=\"components/icon.van\">*</i><!-- END: components/icon.van -->"
+        );
+    }
+
+    #[test]
+    fn test_define_options_scoped_strict() {
+        let source = |css: &str| {
+            format!(
+                "<template><nav :class=\"{{ open: isOpen }}\"><a id=\"home\" class=\"link\">Home</a></nav></template>\n<script setup>\ndefineOptions({{ scopedStrict: true }})\n</script>\n<style scoped>{css}</style>"
+            )
+        };
+        let files = HashMap::from([(
+            "index.van".to_string(),
+            source("/* nav */ nav.open a:hover, #home, .link:not(.x) { color: red; } @media (min-width: 40em) { .link::after { content: ''; } } :deep(.inner) { margin: 0; }"),
+        )]);
+        assert!(resolve_with_files("index.van", &files, &json!({"isOpen": true})).is_ok());
+
+        let files = HashMap::from([("index.van".to_string(), source(".link, .btn, nav > ul { color: red; }"))]);
+        let err = resolve_with_files("index.van", &files, &json!({"isOpen": true})).unwrap_err();
+        assert_eq!(
+            err,
+            "index.van: scoped style selectors match nothing in the template (scopedStrict): .btn, nav > ul"
+        );
+    }
+
+    #[test]
+    fn test_unused_scoped_selector_warns() {
+        let files = HashMap::from([(
+            "components/card.van".to_string(),
+            r#"<template><div class="card"><h3>{{ title }}</h3></div></template>
+<style scoped>
+.card h3 { margin: 0; }
+.card-footer { padding: 1rem; }
+input[type="checkbox"], :global(.theme-dark) .card { color: white; }
+</style>"#
+                .to_string(),
+        )]);
+        let resolved = resolve_with_files("components/card.van", &files, &json!({"title": "Hi"})).unwrap();
+        assert_eq!(
+            resolved.warnings,
+            vec![r#"components/card.van: scoped selector ".card-footer" matches nothing in the template"#.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_define_options_unknown_key_warns() {
+        let files = HashMap::from([(
+            "index.van".to_string(),
+            "<template><p>Hi</p></template>\n<script setup>\ndefineOptions({ sitemap: false, lazyHydrate: true })\n</script>".to_string(),
+        )]);
+        let resolved = resolve_with_files("index.van", &files, &json!({"a": 1})).unwrap();
+        assert_eq!(resolved.warnings, vec![r#"index.van: unknown defineOptions key "lazyHydrate""#.to_string()]);
+    }
+
+    #[test]
+    fn test_add_origin_attr() {
+        assert_eq!(add_origin_attr("<!-- c --> <br/>", "a"), r#"<!-- c --> <br data-van-origin="a"/>"#);
+        assert_eq!(add_origin_attr("text only", "a"), "text only");
+        assert_eq!(
+            add_origin_attr(r#"<p data-van-origin="inner">x</p>"#, "outer"),
+            r#"<p data-van-origin="inner">x</p>"#
+        );
+        assert_eq!(add_origin_attr("<p>x</p>", "a&\"b"), r#"<p data-van-origin="a&amp;&quot;b">x</p>"#);
+    }
+
+    #[test]
+    fn test_unclosed_element_warning() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            "<template><div><my-widget><p>{{ count }}</p></div></template>\n<script setup>\nconst count = ref(0)\n</script>".to_string(),
+        );
+        let resolved = resolve_with_files("index.van", &files, &json!({"x": 1})).unwrap();
+        assert_eq!(
+            resolved.warnings,
+            vec![
+                "index.van: unknown element <my-widget> is not an imported component",
+                "index.van: <my-widget> has no closing tag and is treated as empty",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_root() {
+        assert_eq!(single_root("\n  <!-- c --><div><div>a</div><br></div>\n"), Ok(13));
+        assert_eq!(single_root("<img src=\"a.png\">"), Ok(0));
+        assert_eq!(single_root("<header></header>\n<main></main>"), Err(2));
+        assert_eq!(single_root("Hello <b>you</b>"), Err(2));
+        assert_eq!(single_root("just text"), Err(1));
+        assert_eq!(single_root("  "), Err(0));
+    }
+
+    #[test]
+    fn test_set_attr() {
+        assert_eq!(set_attr(r#"<div class="card">"#, "class", Some("wide")), r#"<div class="card wide">"#);
+        assert_eq!(set_attr(r#"<p style="color: red;">"#, "style", Some("margin: 0")), r#"<p style="color: red; margin: 0">"#);
+        assert_eq!(set_attr(r#"<a title="x class" id="a"/>"#, "id", Some("b")), r#"<a title="x class" id="b"/>"#);
+        assert_eq!(set_attr("<input>", "disabled", None), "<input disabled>");
+    }
+
+    #[test]
+    fn test_attribute_fallthrough() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"
+<template>
+  <card class="wide" id="main" data-role="x" title="Hi" :count="n" @click="go" />
+  <split class="wide" />
+</template>
+<script setup>
+import Card from '../components/card.van'
+import Split from '../components/split.van'
+</script>
+<style scoped>
+.wide { width: 100%; }
+</style>
+"#
+            .to_string(),
+        );
+        files.insert(
+            "components/card.van".to_string(),
+            r#"
+<template><div class="card"><h2>{{ title }}</h2></div></template>
+<script setup>
+defineProps({ title: String })
+</script>
+"#
+            .to_string(),
+        );
+        files.insert("components/split.van".to_string(), "<template><h1>A</h1><p>B</p></template>".to_string());
+
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({"n": 1})).unwrap();
+        let id = van_parser::scope_id(".wide { width: 100%; }");
+        assert!(
+            resolved.html.contains(&format!(r#"<div class="card wide {id}" id="main" data-role="x">"#)),
+            "{}",
+            resolved.html
+        );
+        // Neither root of the fragment receives the attributes
+        assert!(resolved.html.contains("<h1>A</h1><p>B</p>"), "{}", resolved.html);
+        assert_eq!(
+            resolved.warnings,
+            vec!["pages/index.van -> components/split.van: attributes not applied (class): component renders 2 root nodes"]
+        );
+    }
+
+    #[test]
+    fn test_fallthrough_onto_slot_content_root() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><wrapper class=\"wide\" id=\"main\"><section>Hi</section></wrapper><bare title=\"t\"><p>P</p></bare></template>\n<script setup>\nimport Wrapper from '../components/wrapper.van'\nimport Bare from '../components/bare.van'\n</script>".to_string(),
+        );
+        files.insert("components/wrapper.van".to_string(), "<template>\n  <slot />\n</template>".to_string());
+        files.insert("components/bare.van".to_string(), "<template><slot /></template>".to_string());
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({"x": 1})).unwrap();
+        assert_eq!(resolved.html, r#"<section class="wide" id="main">Hi</section><p title="t">P</p>"#);
+        assert!(resolved.warnings.is_empty(), "{:?}", resolved.warnings);
+
+        // With a scoped parent, the slot root and the component tag both carry its scope class
+        let page = files["pages/index.van"].clone() + "\n<style scoped>\n.wide { width: 100%; }\nsection, p { margin: 0; }\n</style>";
+        files.insert("pages/index.van".to_string(), page);
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({"x": 1})).unwrap();
+        let id = van_parser::scope_id(".wide { width: 100%; }\nsection, p { margin: 0; }");
+        assert_eq!(
+            resolved.html,
+            format!(r#"<section class="{id} wide" id="main">Hi</section><p class="{id}" title="t">P</p>"#)
+        );
+        assert!(resolved.warnings.is_empty(), "{:?}", resolved.warnings);
+    }
+
+    #[test]
+    fn test_fallthrough_in_themed_slot_content() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><layout><section><split /><card /></section></layout></template>\n<script setup>\nimport Layout from '../components/layout.van'\nimport Split from '../components/split.van'\nimport Card from '../components/card.van'\n</script>\n<style scoped>\nsection { margin: 0; }\n</style>".to_string(),
+        );
+        files.insert("components/layout.van".to_string(), "<template><main><slot /></main></template>".to_string());
+        files.insert("components/split.van".to_string(), "<template><h1>A</h1><p>B</p></template>".to_string());
+        files.insert("components/card.van".to_string(), "<template><div class=\"card\">C</div></template>".to_string());
+        let options = CompileOptions {
+            file_origins: HashMap::from([("pages/index.van".to_string(), "van1".to_string())]),
+            ..CompileOptions::default()
+        };
+        let resolved = resolve_with_options("pages/index.van", &files, &json!({"x": 1}), &options).unwrap();
+        let id = van_parser::scope_id("van1section { margin: 0; }");
+        assert!(resolved.html.contains(&format!(r#"<div class="card {id}">C</div>"#)), "{}", resolved.html);
+        // The themed scope class is recognised as the parent's, so the fragment is not reported
+        assert!(resolved.warnings.is_empty(), "{:?}", resolved.warnings);
+    }
+
+    #[test]
+    fn test_fragment_keeps_all_roots_scoped() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><section><split /></section></template>\n<script setup>\nimport Split from '../components/split.van'\n</script>\n<style scoped>\nsection { margin: 0; }\n</style>".to_string(),
+        );
+        files.insert(
+            "components/split.van".to_string(),
+            "<template>\n  <h1>A</h1>\n  <p>B</p>\n</template>\n<style scoped>\nh1, p { color: red; }\n</style>".to_string(),
+        );
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({"x": 1})).unwrap();
+        let id = van_parser::scope_id("h1, p { color: red; }");
+        assert!(resolved.html.contains(&format!(r#"<h1 class="{id}">A</h1>"#)), "{}", resolved.html);
+        assert!(resolved.html.contains(&format!(r#"<p class="{id}">B</p>"#)), "{}", resolved.html);
+        // The parent's scope class alone is not worth a warning
+        assert!(resolved.warnings.is_empty(), "{:?}", resolved.warnings);
+    }
+
+    #[test]
+    fn test_expand_v_for_basic() {
+        let data = json!({"items": ["Alice", "Bob", "Charlie"]});
+        let template = r#"<ul><li v-for="item in items">{{ item }}</li></ul>"#;
+        let result = expand_v_for(template, &data, false, None, &DataUsage::default());
+        assert!(result.contains("<li>Alice</li>"));
+        assert!(result.contains("<li>Bob</li>"));
+        assert!(result.contains("<li>Charlie</li>"));
+        assert!(!result.contains("v-for"));
+    }
+
+    #[test]
+    fn test_expand_v_for_empty_state() {
+        let template = "<ul><li v-for=\"item in items\">{{ item }}</li>\n  <li v-empty class=\"none\">No items</li></ul><br v-empty />";
+        let result = expand_v_for(template, &json!({"items": ["A", "B"]}), false, None, &DataUsage::default());
+        assert_eq!(result, "<ul><li>A</li><li>B</li></ul><br v-empty />");
+        let result = expand_v_for(template, &json!({"items": []}), false, None, &DataUsage::default());
+        assert_eq!(result, "<ul>\n  <li class=\"none\">No items</li></ul><br v-empty />");
+        let result = expand_v_for(template, &json!({}), false, None, &DataUsage::default());
+        assert!(result.contains(">No items</li>"));
+
+        let template = r#"<p v-for="x in items">{{ x }}</p><hr v-empty/>"#;
+        assert_eq!(expand_v_for(template, &json!({"items": []}), false, None, &DataUsage::default()), "<hr/>");
+        assert_eq!(expand_v_for(template, &json!({"items": [1]}), false, None, &DataUsage::default()), "<p>1</p>");
     }
 
     #[test]
     fn test_expand_v_for_with_index() {
         let data = json!({"items": ["A", "B"]});
         let template = r#"<ul><li v-for="(item, index) in items">{{ index }}: {{ item }}</li></ul>"#;
-        let result = expand_v_for(template, &data);
+        let result = expand_v_for(template, &data, false, None, &DataUsage::default());
         assert!(result.contains("0: A"));
         assert!(result.contains("1: B"));
     }
@@ -1457,7 +3606,7 @@ const count = ref(0)
     fn test_expand_v_for_nested_path() {
         let data = json!({"user": {"hobbies": ["coding", "reading"]}});
         let template = r#"<span v-for="h in user.hobbies">{{ h }}</span>"#;
-        let result = expand_v_for(template, &data);
+        let result = expand_v_for(template, &data, false, None, &DataUsage::default());
         assert!(result.contains("<span>coding</span>"));
         assert!(result.contains("<span>reading</span>"));
     }
@@ -1527,6 +3676,28 @@ import Card from './card.van'
         assert!(resolved.styles[0].contains(&format!(".card.{id}")));
     }
 
+    #[test]
+    fn test_scoped_layout_styles_reach_slots_only_via_slotted() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            "<template><default-layout><p>Body</p></default-layout></template>\n<script setup>\nimport DefaultLayout from '../layouts/default.van'\n</script>".to_string(),
+        );
+        let css = ".content p { color: gray; }\n.content ::v-slotted(p) { margin: 0; }";
+        files.insert(
+            "layouts/default.van".to_string(),
+            format!("<template><main class=\"content\"><p>Note</p><slot /></main></template>\n<style scoped>\n{css}\n</style>"),
+        );
+        let id = van_parser::scope_id(css);
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+
+        // The layout's own rule only matches the layout's own elements
+        assert!(resolved.html.contains(&format!("<p class=\"{id}\">Note</p><p>Body</p>")), "Got: {}", resolved.html);
+        assert!(resolved.styles[0].contains(&format!(".content p.{id} {{ color: gray; }}")));
+        // ::v-slotted deliberately reaches into the slot
+        assert!(resolved.styles[0].contains(&format!(".content.{id} p {{ margin: 0; }}")));
+    }
+
     #[test]
     fn test_resolve_unscoped_style_unchanged() {
         let source = r#"
@@ -1577,4 +3748,54 @@ import Header from './Header.van'
         assert!(resolved.html.contains("<header>"), "Should contain <header> HTML element");
         assert!(resolved.html.contains("<h1>My Site</h1>"), "Should interpolate title prop");
     }
+
+    #[test]
+    fn test_unknown_tag_warnings() {
+        let mut files = HashMap::new();
+        files.insert(
+            "pages/index.van".to_string(),
+            r#"<template><main><UserCrad /><user-crad /><hedaer /><sl-button>Go</sl-button><Teleport to="body"><svg><linearGradient /></svg></Teleport><pre><Nope /></pre></main></template>
+<script setup>
+import UserCard from '../components/user-card.van'
+</script>"#
+                .to_string(),
+        );
+        files.insert("components/user-card.van".to_string(), "<template><div>card</div></template>".to_string());
+        files.insert("components/site-header.van".to_string(), "<template><header>h</header></template>".to_string());
+        let resolved = resolve_with_files("pages/index.van", &files, &json!({})).unwrap();
+        assert_eq!(
+            resolved.warnings,
+            vec![
+                "pages/index.van: unknown element <UserCrad>, did you mean <UserCard>?",
+                "pages/index.van: unknown element <user-crad>, did you mean <user-card>?",
+                "pages/index.van: unknown element <sl-button> is not an imported component",
+            ]
+        );
+
+        let options = CompileOptions { custom_elements: vec!["sl-*".to_string()], ..Default::default() };
+        let resolved = resolve_with_options("pages/index.van", &files, &json!({}), &options).unwrap();
+        assert_eq!(resolved.warnings.len(), 2);
+        assert_eq!(suggest_component("SiteHeder", true, &HashMap::new(), &files).as_deref(), Some("SiteHeader"));
+        assert_eq!(edit_distance("hellp", "hello"), 1);
+    }
+
+    #[test]
+    fn test_teleport_targets() {
+        let mut files = HashMap::new();
+        files.insert(
+            "index.van".to_string(),
+            concat!(
+                "<template><main><ClientOnly><Teleport to=\"body\"><p>Toast</p></Teleport></ClientOnly>",
+                "<Teleport to=\"#dialogs\"><p>Stays</p></Teleport><Teleport to=\"body\" disabled><p>Off</p></Teleport></main></template>",
+            )
+            .to_string(),
+        );
+        let resolved = resolve_with_files("index.van", &files, &json!({})).unwrap();
+        assert_eq!(
+            resolved.html.trim(),
+            "<main><!--client-only--><!--/client-only--><p>Stays</p><p>Off</p></main><!--client-only--><p>Toast</p><!--/client-only-->"
+        );
+        assert_eq!(resolved.warnings.len(), 1);
+        assert!(resolved.warnings[0].contains("to=\"#dialogs\""), "{:?}", resolved.warnings);
+    }
 }
@@ -0,0 +1,104 @@
+use regex::Regex;
+
+use crate::format::tag_end;
+
+/// Attributes whose value is followed as a URL.
+const URL_ATTRS: &[&str] = &["href", "src", "action", "formaction", "xlink:href"];
+
+/// Strip the obvious script vectors from trusted-but-unchecked markup:
+/// `<script>` elements, `on*` event handler attributes and `javascript:` URLs.
+///
+/// A guardrail for `{{{ }}}` output when `sanitize_html` is on, not a full
+/// HTML sanitizer: it does not decode entities or restrict tags and styles.
+pub(crate) fn sanitize_html(html: &str) -> String {
+    let attr_re = Regex::new(r#"([^\s=/>"']+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+)))?"#).unwrap();
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        out.push_str(&html[pos..start]);
+        let rest = &html[start..];
+        let is_open = rest[1..].starts_with(|c: char| c.is_ascii_alphabetic());
+        let Some(end) = tag_end(rest).filter(|_| is_open) else {
+            out.push('<');
+            pos = start + 1;
+            continue;
+        };
+        let tag = &rest[..end];
+        let name_len = tag[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+            .map_or(tag.len() - 1, |n| n + 1);
+        let name = &tag[1..name_len];
+        if name.eq_ignore_ascii_case("script") {
+            let lower = html[start + end..].to_ascii_lowercase();
+            pos = lower
+                .find("</script")
+                .and_then(|c| lower[c..].find('>').map(|e| start + end + c + e + 1))
+                .unwrap_or(html.len());
+            continue;
+        }
+        let self_closing = tag.ends_with("/>");
+        let attrs = tag[name_len..].trim_end_matches('>').trim_end_matches('/');
+        out.push('<');
+        out.push_str(name);
+        for caps in attr_re.captures_iter(attrs) {
+            let attr = caps[1].to_ascii_lowercase();
+            let value = caps.get(2).or(caps.get(3)).or(caps.get(4)).map_or("", |m| m.as_str());
+            if attr.starts_with("on") || (URL_ATTRS.contains(&attr.as_str()) && is_script_url(value)) {
+                continue;
+            }
+            out.push(' ');
+            out.push_str(&caps[0]);
+        }
+        out.push_str(if self_closing { " />" } else { ">" });
+        pos = start + end;
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+/// Whether a URL runs script, ignoring the whitespace and control characters
+/// browsers skip inside the scheme.
+fn is_script_url(value: &str) -> bool {
+    let scheme: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take("javascript:".len())
+        .collect();
+    scheme.eq_ignore_ascii_case("javascript:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_script_elements() {
+        assert_eq!(
+            sanitize_html("<p>Hi</p><script>alert(1)</script><SCRIPT src=\"x.js\"></SCRIPT>!"),
+            "<p>Hi</p>!"
+        );
+    }
+
+    #[test]
+    fn test_strips_event_handlers() {
+        assert_eq!(
+            sanitize_html(r#"<button class="b" onclick="steal()" ONMOUSEOVER='x()'>Go</button>"#),
+            r#"<button class="b">Go</button>"#
+        );
+    }
+
+    #[test]
+    fn test_strips_javascript_urls() {
+        assert_eq!(
+            sanitize_html("<a href=\" java\tscript:x\" title=\"javascript: is text here\">a</a><a href=JavaScript:alert(1)>b</a><img src=\"/a.png\" />"),
+            "<a title=\"javascript: is text here\">a</a><a>b</a><img src=\"/a.png\" />"
+        );
+    }
+
+    #[test]
+    fn test_keeps_text_and_comments() {
+        let html = "<!-- note --><p>1 < 2 &amp; <em>ok</em></p>";
+        assert_eq!(sanitize_html(html), html);
+    }
+}
@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+
+use crate::expr;
+
+/// The data paths a render looked up, so pages can be checked against their
+/// mock data: keys no template reads, and paths no data provides.
+///
+/// Interpolation, `v-for`, props and `v-if` / `v-show` conditions record
+/// into it as they resolve data paths. Paths are recorded as written, so one
+/// read inside a loop or a child component (`item.name`, a prop) is relative
+/// to that scope rather than to the page data.
+///
+/// The default collector ignores everything, so renders that don't report
+/// usage don't pay for it.
+#[derive(Debug, Default)]
+pub(crate) struct DataUsage {
+    tracking: bool,
+    used: RefCell<BTreeSet<String>>,
+    /// Each missing path and the file that first referenced it.
+    missing: RefCell<BTreeMap<String, String>>,
+    /// The file being resolved, for `missing`.
+    file: RefCell<String>,
+    /// Names the page scripts declare, which are not read from the data.
+    reactive: RefCell<BTreeSet<String>>,
+}
+
+impl DataUsage {
+    /// A collector that records lookups.
+    pub(crate) fn tracking() -> Self {
+        DataUsage { tracking: true, ..Default::default() }
+    }
+
+    /// Record the data paths `expr` reads from `data` (none when it isn't an
+    /// expression [`expr::evaluate`] supports).
+    pub(crate) fn record_expr(&self, expr: &str, data: &Value) {
+        if !self.tracking {
+            return;
+        }
+        for (path, found) in expr::evaluate(expr, data).map(|e| e.paths).unwrap_or_default() {
+            self.record(&path, found);
+        }
+    }
+
+    /// Leave paths starting with one of `names`, the reactive names of the
+    /// page scripts (store fields like `cart.count` among them), unrecorded.
+    pub(crate) fn ignore(&self, names: &[String]) {
+        self.reactive.borrow_mut().extend(names.iter().cloned());
+    }
+
+    /// Record a lookup of the data path `path`.
+    pub(crate) fn record(&self, path: &str, found: bool) {
+        if !self.tracking {
+            return;
+        }
+        let reactive = self.reactive.borrow();
+        if path.match_indices('.').map(|(end, _)| &path[..end]).chain([path]).any(|prefix| reactive.contains(prefix)) {
+            return;
+        }
+        if found {
+            self.used.borrow_mut().insert(path.to_string());
+        } else {
+            let file = self.file.borrow().clone();
+            self.missing.borrow_mut().entry(path.to_string()).or_insert(file);
+        }
+    }
+
+    /// Attribute the lookups that follow to `file`, returning the file they
+    /// were attributed to before.
+    pub(crate) fn enter(&self, file: &str) -> String {
+        self.file.replace(file.to_string())
+    }
+
+    /// The paths that resolved and those that did not, each sorted, as
+    /// `(used, missing)`. A used path counts when it starts with a key of
+    /// `data`, the page data; missing ones come with the file referencing them.
+    pub(crate) fn finish(self, data: &Value) -> (Vec<String>, Vec<(String, String)>) {
+        let used = self
+            .used
+            .into_inner()
+            .into_iter()
+            .filter(|path| data.get(path.split('.').next().unwrap_or_default()).is_some())
+            .collect();
+        (used, self.missing.into_inner().into_iter().collect())
+    }
+}
+
+/// Top-level keys of `data` that no path in `used` starts with, skipping
+/// `$`-prefixed keys the compiler supplies (`$i18n`, `$env`, …).
+pub fn unused_keys(data: &Value, used: &[String]) -> Vec<String> {
+    let Value::Object(map) = data else {
+        return Vec::new();
+    };
+    let read: BTreeSet<&str> = used.iter().map(|path| path.split('.').next().unwrap_or_default()).collect();
+    map.keys().filter(|key| !key.starts_with('$') && !read.contains(key.as_str())).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_data_usage() {
+        let data = json!({ "title": "Hi", "user": { "name": "Ada" }, "items": [], "footer": "x", "$i18n": {} });
+        let usage = DataUsage::tracking();
+        usage.enter("pages/index.van");
+        usage.record_expr("title", &data);
+        usage.record_expr(" user.name ", &data);
+        usage.record_expr("items.length > 0 && subtitle", &data);
+        usage.record("item.id", true);
+        usage.record_expr("$t('hello')", &data);
+        usage.ignore(&["open".to_string(), "cart.count".to_string()]);
+        usage.record_expr("open && !footer && cart.count", &data);
+        usage.enter("components/card.van");
+        usage.record_expr("subtitle", &data);
+        usage.record_expr("price", &data);
+        let (used, missing) = usage.finish(&data);
+        assert_eq!(used, vec!["footer", "items.length", "title", "user.name"]);
+        assert_eq!(
+            missing,
+            vec![
+                ("price".to_string(), "components/card.van".to_string()),
+                ("subtitle".to_string(), "pages/index.van".to_string()),
+            ]
+        );
+        assert_eq!(unused_keys(&data, &["title".to_string()]), vec!["footer", "items", "user"]);
+
+        let ignored = DataUsage::default();
+        ignored.record_expr("title", &data);
+        assert_eq!(ignored.finish(&data), (Vec::new(), Vec::new()));
+    }
+}
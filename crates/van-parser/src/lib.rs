@@ -1,9 +1,9 @@
 use regex::Regex;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use serde::Serialize;
+use xxhash_rust::xxh3::xxh3_128;
 
-/// A non-component import from `<script setup>` (.ts/.js files).
-#[derive(Debug, Clone, PartialEq)]
+/// A non-component import from `<script setup>` (.ts/.js modules and .md content).
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ScriptImport {
     /// The full import statement as-is, e.g. `import { formatDate } from '../utils/format.ts'`
     pub raw: String,
@@ -13,12 +13,68 @@ pub struct ScriptImport {
     pub path: String,
 }
 
+impl ScriptImport {
+    /// The default-import binding, e.g. `intro` for `import intro from './intro.md'`.
+    pub fn default_name(&self) -> Option<String> {
+        let re = Regex::new(r"^import\s+([A-Za-z_$][\w$]*)\s+from\s").unwrap();
+        re.captures(&self.raw).map(|cap| cap[1].to_string())
+    }
+
+    /// The `(local, exported)` names this import binds.
+    ///
+    /// `import { a, b as c }` gives `(a, a)` and `(c, b)`, a default import
+    /// gives `(name, "default")` and `import * as ns` gives `(ns, "*")`.
+    /// Type-only imports and `type` specifiers bind nothing.
+    pub fn bindings(&self) -> Vec<(String, String)> {
+        if self.is_type_only {
+            return Vec::new();
+        }
+        let Some(clause) = self
+            .raw
+            .strip_prefix("import")
+            .and_then(|rest| rest.rsplit_once(" from ").map(|(clause, _)| clause.trim()))
+        else {
+            return Vec::new();
+        };
+        let mut bindings = Vec::new();
+        let (head, named) = match clause.split_once('{') {
+            Some((head, rest)) => (head, rest.split('}').next().unwrap_or("")),
+            None => (clause, ""),
+        };
+        for part in head.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.strip_prefix('*') {
+                Some(ns) => {
+                    if let Some(name) = ns.trim().strip_prefix("as") {
+                        bindings.push((name.trim().to_string(), "*".to_string()));
+                    }
+                }
+                None => bindings.push((part.to_string(), "default".to_string())),
+            }
+        }
+        for spec in named.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if spec.starts_with("type ") {
+                continue;
+            }
+            let (exported, local) = spec
+                .split_once(" as ")
+                .map_or((spec, spec), |(exported, local)| (exported.trim(), local.trim()));
+            bindings.push((local.to_string(), exported.to_string()));
+        }
+        bindings
+    }
+
+    /// Whether this imports a Markdown content file rather than a script module.
+    pub fn is_markdown(&self) -> bool {
+        self.path.ends_with(".md")
+    }
+}
+
 /// Parse non-.van imports from a script setup block.
-/// Returns imports from .ts, .js, .tsx, .jsx files.
+/// Returns imports from .ts, .js, .tsx, .jsx files and .md content files.
 /// Supports both relative paths and scoped packages (`@scope/pkg/file.ts`).
 /// Excludes: .van imports (handled by parse_imports), bare module imports like 'vue'.
 pub fn parse_script_imports(script_setup: &str) -> Vec<ScriptImport> {
-    let re = Regex::new(r#"(?m)^[ \t]*(import\s+(?:type\s+)?.*?\s+from\s+['"]([^'"]+\.(?:ts|js|tsx|jsx))['"].*)"#).unwrap();
+    let re = Regex::new(r#"(?m)^[ \t]*(import\s+(?:type\s+)?.*?\s+from\s+['"]([^'"]+\.(?:ts|js|tsx|jsx|md))['"].*)"#).unwrap();
     let type_re = Regex::new(r#"^import\s+type\s"#).unwrap();
     re.captures_iter(script_setup)
         .map(|cap| {
@@ -34,8 +90,32 @@ pub fn parse_script_imports(script_setup: &str) -> Vec<ScriptImport> {
         .collect()
 }
 
+/// Parse imports of bare module specifiers (`import { debounce } from 'lodash-es'`)
+/// from a script setup block: package names rather than paths to `.van`,
+/// `.ts`/`.js` or `.md` files. Imports from `vue`, whose `ref`/`computed` the
+/// compiler provides, and side-effect-only imports are left out.
+pub fn parse_bare_imports(script_setup: &str) -> Vec<ScriptImport> {
+    let re = Regex::new(r#"(?m)^[ \t]*(import\s+(?:type\s+)?.*?\s+from\s+['"]([^'"]+)['"].*)"#).unwrap();
+    let file_re = Regex::new(r"\.(?:van|ts|js|tsx|jsx|md)$").unwrap();
+    let type_re = Regex::new(r#"^import\s+type\s"#).unwrap();
+    re.captures_iter(script_setup)
+        .filter(|cap| {
+            let path = &cap[2];
+            !path.starts_with(['.', '/']) && !file_re.is_match(path) && path != "vue"
+        })
+        .map(|cap| {
+            let raw = cap[1].trim().to_string();
+            ScriptImport {
+                is_type_only: type_re.is_match(&raw),
+                raw,
+                path: cap[2].to_string(),
+            }
+        })
+        .collect()
+}
+
 /// Represents an import from a `<script setup>` block.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct VanImport {
     /// The imported identifier, e.g. `DefaultLayout`
     pub name: String,
@@ -80,23 +160,78 @@ pub fn pascal_to_kebab(s: &str) -> String {
 }
 
 /// A single prop declaration from `defineProps({ ... })`.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct PropDef {
     pub name: String,
     /// The declared type: "String", "Number", "Boolean", "Array", "Object", or None.
     pub prop_type: Option<String>,
     pub required: bool,
+    /// The `default:` of the object form, as written (a JS expression).
+    pub default: Option<String>,
 }
 
 /// Represents the extracted blocks from a `.van` file.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct VanBlock {
     pub template: Option<String>,
     pub script_setup: Option<String>,
+    /// The `lang` attribute of `<script setup>` (e.g. `ts`).
+    pub script_setup_lang: Option<String>,
     pub script_server: Option<String>,
+    /// Build-time page data from `<script lang="data">`: inline JSON or a URL.
+    pub script_data: Option<String>,
     pub style: Option<String>,
     pub style_scoped: bool,
     pub props: Vec<PropDef>,
+    /// Compile flags from `defineOptions({ ... })`.
+    pub options: FileOptions,
+}
+
+/// `defineOptions` keys read elsewhere (by the resolver and the CLI).
+const OTHER_OPTION_KEYS: &[&str] = &["layout", "sitemap"];
+
+/// Per-file compile flags set with `defineOptions({ ... })`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileOptions {
+    /// `ssrInterpolation: false`: leave the template's `{{ }}` for client-side
+    /// rendering instead of filling them in on the server.
+    pub ssr_interpolation: bool,
+    /// `scopedStrict: true`: a scoped style selector that matches nothing in
+    /// the template is an error.
+    pub scoped_strict: bool,
+    /// `debugComments: false`: no boundary markers around this component in
+    /// debug mode.
+    pub debug_comments: bool,
+    /// Keys `defineOptions` doesn't know, in source order.
+    pub unknown_keys: Vec<String>,
+}
+
+impl Default for FileOptions {
+    fn default() -> Self {
+        Self { ssr_interpolation: true, scoped_strict: false, debug_comments: true, unknown_keys: Vec::new() }
+    }
+}
+
+/// Read the compile flags out of a script's `defineOptions({ ... })`.
+///
+/// Flags take the literals `true` / `false`; other values leave the default.
+pub fn parse_file_options(script: &str) -> FileOptions {
+    let mut options = FileOptions::default();
+    for (key, value) in parse_define_options(script) {
+        let flag = match value.as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        match key.as_str() {
+            "ssrInterpolation" => options.ssr_interpolation = flag.unwrap_or(true),
+            "scopedStrict" => options.scoped_strict = flag.unwrap_or(false),
+            "debugComments" => options.debug_comments = flag.unwrap_or(true),
+            _ if OTHER_OPTION_KEYS.contains(&key.as_str()) => {}
+            _ => options.unknown_keys.push(key),
+        }
+    }
+    options
 }
 
 /// Extract blocks from a `.van` source file using simple tag matching.
@@ -112,13 +247,17 @@ pub fn parse_blocks(source: &str) -> VanBlock {
     } else {
         Vec::new()
     };
+    let options = script_setup.as_deref().map(parse_file_options).unwrap_or_default();
     VanBlock {
         template: extract_block(source, "template"),
         script_setup,
-        script_server: extract_script_server(source),
+        script_setup_lang: extract_script_setup_lang(source),
+        script_server: extract_script_lang(source, "java"),
+        script_data: extract_script_lang(source, "data"),
         style,
         style_scoped,
         props,
+        options,
     }
 }
 
@@ -126,7 +265,8 @@ pub fn parse_blocks(source: &str) -> VanBlock {
 ///
 /// Supports two forms per entry:
 /// - Simple: `name: Type` → `PropDef { name, prop_type: Some("Type"), required: false }`
-/// - Object: `name: { type: Type, required: true }` → extracts type and required flag
+/// - Object: `name: { type: Type, required: true, default: 0 }` → extracts type,
+///   required flag and default
 pub fn parse_define_props(script: &str) -> Vec<PropDef> {
     // Find `defineProps({` ... `})`
     let Some(start) = script.find("defineProps(") else {
@@ -172,8 +312,9 @@ pub fn parse_define_props(script: &str) -> Vec<PropDef> {
 
             let mut prop_type = None;
             let mut required = false;
+            let mut default = None;
 
-            for part in obj_inner.split(',') {
+            for part in split_respecting_braces(obj_inner) {
                 let part = part.trim();
                 if let Some(cp) = part.find(':') {
                     let key = part[..cp].trim();
@@ -182,6 +323,8 @@ pub fn parse_define_props(script: &str) -> Vec<PropDef> {
                         prop_type = Some(val.to_string());
                     } else if key == "required" {
                         required = val == "true";
+                    } else if key == "default" {
+                        default = Some(val.to_string());
                     }
                 }
             }
@@ -190,6 +333,7 @@ pub fn parse_define_props(script: &str) -> Vec<PropDef> {
                 name,
                 prop_type,
                 required,
+                default,
             });
         } else {
             // Simple form: `name: Type`
@@ -197,6 +341,7 @@ pub fn parse_define_props(script: &str) -> Vec<PropDef> {
                 name,
                 prop_type: Some(value.to_string()),
                 required: false,
+                default: None,
             });
         }
     }
@@ -204,6 +349,28 @@ pub fn parse_define_props(script: &str) -> Vec<PropDef> {
     props
 }
 
+/// Parse `defineOptions({ ... })` from a script setup block into raw `key → value` pairs.
+///
+/// Values are kept as written (e.g. `false`, `'weekly'`); callers interpret the keys
+/// they know about. Page-level options such as `sitemap: false` are read by the CLI.
+pub fn parse_define_options(script: &str) -> Vec<(String, String)> {
+    let Some(start) = script.find("defineOptions(") else {
+        return Vec::new();
+    };
+    let rest = &script[start + "defineOptions(".len()..];
+    let Some(inner) = extract_balanced_braces(rest) else {
+        return Vec::new();
+    };
+    split_respecting_braces(inner)
+        .into_iter()
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once(':')?;
+            let key = key.trim().trim_matches('\'').trim_matches('"');
+            Some((key.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
 /// Extract the content between balanced `{` and `}` from the start of the string.
 fn extract_balanced_braces(s: &str) -> Option<&str> {
     let s = s.trim();
@@ -226,15 +393,28 @@ fn extract_balanced_braces(s: &str) -> Option<&str> {
     None
 }
 
-/// Split a string by commas, but respect nested `{ ... }` blocks.
+/// Split a string by commas, but respect nested brackets and string literals.
 fn split_respecting_braces(s: &str) -> Vec<&str> {
     let mut result = Vec::new();
     let mut depth = 0;
     let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
     for (i, ch) in s.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
         match ch {
-            '{' => depth += 1,
-            '}' => depth -= 1,
+            '\'' | '"' | '`' => quote = Some(ch),
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth -= 1,
             ',' if depth == 0 => {
                 result.push(&s[start..i]);
                 start = i + 1;
@@ -286,12 +466,19 @@ fn extract_script_setup(source: &str) -> Option<String> {
     Some(source[content_start..end_idx].trim().to_string())
 }
 
-fn extract_script_server(source: &str) -> Option<String> {
-    // Look for <script lang="java">
-    let marker = "<script lang=\"java\">";
+fn extract_script_setup_lang(source: &str) -> Option<String> {
+    let start_idx = source.find("<script setup")?;
+    let open_tag = &source[start_idx..start_idx + source[start_idx..].find('>')?];
+    let re = Regex::new(r#"\slang\s*=\s*["']([^"']*)["']"#).unwrap();
+    re.captures(open_tag).map(|caps| caps[1].to_string())
+}
+
+fn extract_script_lang(source: &str, lang: &str) -> Option<String> {
+    // Look for <script lang="java"> / <script lang="data">
+    let marker = format!("<script lang=\"{lang}\">");
     let close = "</script>";
 
-    let start_idx = source.find(marker)?;
+    let start_idx = source.find(&marker)?;
     let content_start = start_idx + marker.len();
 
     // Find the closing </script> after this opening tag
@@ -334,14 +521,30 @@ fn extract_style(source: &str) -> (Option<String>, bool) {
     (Some(source[content_start..end_idx].trim().to_string()), is_scoped)
 }
 
+/// Source text the way the parser expects it: without a leading UTF-8 byte
+/// order mark, and with Windows `\r\n` line endings turned into `\n` so no
+/// `\r` ends up in extracted blocks or generated JS.
+pub fn normalize_source(source: &str) -> String {
+    source.strip_prefix('\u{feff}').unwrap_or(source).replace("\r\n", "\n")
+}
+
+/// Stable content hash as 12 hex chars (the leading 48 bits of XXH3-128).
+///
+/// XXH3 is a fixed, specified algorithm, so the value only changes when the
+/// content does — not across Rust releases or platforms, unlike `DefaultHasher`.
+/// Used for cache-busting asset names and (shortened) scope IDs.
+///
+/// Compatibility: hashes from releases before 0.1.33 (truncated SipHash) differ,
+/// so asset names and scope IDs change once on upgrade.
+pub fn content_hash(content: &str) -> String {
+    format!("{:032x}", xxh3_128(content.as_bytes()))[..12].to_string()
+}
+
 /// Generate a deterministic 8-hex-char scope ID from content (typically CSS).
 ///
-/// Uses `DefaultHasher` with fixed seed (SipHash keys 0,0) so the same
-/// content always produces the same ID, even across process restarts.
+/// A prefix of [`content_hash`], so the same content always produces the same ID.
 pub fn scope_id(content: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    content.hash(&mut hasher);
-    format!("{:08x}", hasher.finish() as u32)
+    content_hash(content)[..8].to_string()
 }
 
 /// Tags that should NOT receive a scope class.
@@ -377,8 +580,8 @@ pub fn add_scope_class(html: &str, id: &str) -> String {
             continue;
         }
 
-        // Opening tag — find '>'
-        let Some(gt) = rest.find('>') else {
+        // Opening tag — find its '>', skipping any inside quoted values
+        let Some(gt) = tag_end(rest).map(|len| len - 1) else {
             result.push_str(rest);
             return result;
         };
@@ -401,29 +604,31 @@ pub fn add_scope_class(html: &str, id: &str) -> String {
         let tag = &rest[..gt];
         let is_self_closing = tag.trim_end().ends_with('/');
 
-        if let Some(class_idx) = tag.find("class=\"") {
-            let after_quote = class_idx + 7;
-            if let Some(end_quote) = tag[after_quote..].find('"') {
-                let insert = after_quote + end_quote;
-                result.push_str(&rest[..insert]);
-                result.push(' ');
+        // One canonical form whatever the source spacing: the id ends the
+        // class list, or a new `class` follows the last attribute with one
+        // space, and nothing separates it from `>` / `/>`
+        match class_value(tag) {
+            Some((start, Some(end))) => {
+                let classes = tag[start..end].trim();
+                result.push_str(&rest[..start]);
+                result.push_str(classes);
+                if !classes.is_empty() {
+                    result.push(' ');
+                }
                 result.push_str(id);
-                result.push_str(&rest[insert..=gt]);
-            } else {
-                result.push_str(&rest[..=gt]);
+                let after = tag[end..].trim_end();
+                result.push_str(after.strip_suffix('/').filter(|_| is_self_closing).unwrap_or(after).trim_end());
+                result.push_str(if is_self_closing { "/>" } else { ">" });
+            }
+            // An unterminated class value: leave the tag alone
+            Some((_, None)) => result.push_str(&rest[..=gt]),
+            None => {
+                let attrs = if is_self_closing { &tag[..tag.rfind('/').unwrap()] } else { tag };
+                result.push_str(attrs.trim_end());
+                result.push_str(" class=\"");
+                result.push_str(id);
+                result.push_str(if is_self_closing { "\"/>" } else { "\">" });
             }
-        } else if is_self_closing {
-            let slash = tag.rfind('/').unwrap();
-            result.push_str(&rest[..slash]);
-            result.push_str("class=\"");
-            result.push_str(id);
-            result.push_str("\" ");
-            result.push_str(&rest[slash..=gt]);
-        } else {
-            result.push_str(&rest[..gt]);
-            result.push_str(" class=\"");
-            result.push_str(id);
-            result.push_str("\">");
         }
 
         rest = &rest[gt + 1..];
@@ -433,28 +638,100 @@ pub fn add_scope_class(html: &str, id: &str) -> String {
     result
 }
 
+/// Length of the tag at the start of `s`, honouring quoted attribute values.
+pub fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i + 1),
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Where the value of an opening tag's `class` attribute starts, and where it
+/// ends when its closing quote is there. Bound and prefixed attributes
+/// (`:class`, `data-class`) don't count.
+fn class_value(tag: &str) -> Option<(usize, Option<usize>)> {
+    let at = tag
+        .match_indices("class=\"")
+        .map(|(at, _)| at)
+        .find(|&at| tag[..at].ends_with(char::is_whitespace))?;
+    let start = at + "class=\"".len();
+    Some((start, tag[start..].find('"').map(|end| start + end)))
+}
+
 /// Scope CSS by inserting `.{id}` before any pseudo-class/pseudo-element
 /// on the last simple selector of each rule.
 ///
 /// Input: `.card { border: 1px solid; }  a:hover { color: navy; }`
 /// Output: `.card.a1b2c3d4 { border: 1px solid; }  a.a1b2c3d4:hover { color: navy; }`
+///
+/// Slot content keeps the scope of the component that wrote it, so these rules
+/// never reach it. `::v-slotted(X)` (or `:slotted(X)`) opts in: it matches `X`
+/// inside the scoped element before it, `.content ::v-slotted(p)` becoming
+/// `.content.a1b2c3d4 p` (or `.a1b2c3d4 p` on its own).
 pub fn scope_css(css: &str, id: &str) -> String {
     let suffix = format!(".{id}");
-    let rule_re = Regex::new(r"([^{}]+)\{([^{}]*)\}").unwrap();
 
-    rule_re.replace_all(css, |caps: &regex::Captures| {
+    css_rule_re().replace_all(css, |caps: &regex::Captures| {
         let selectors = caps[1].trim();
         let body = &caps[2];
 
         let scoped: Vec<String> = selectors
             .split(',')
-            .map(|s| insert_scope_suffix(s.trim(), &suffix))
+            .map(|s| scope_selector(s.trim(), &suffix))
             .collect();
 
         format!("{} {{{}}}", scoped.join(", "), body)
     }).to_string()
 }
 
+/// The selector list and body of each innermost CSS rule (so rules nested
+/// in `@media` are found, not the at-rule itself).
+fn css_rule_re() -> Regex {
+    Regex::new(r"([^{}]+)\{([^{}]*)\}").unwrap()
+}
+
+/// Every selector of every rule in `css`, trimmed, in source order.
+///
+/// Input: `h1, .title { margin: 0; } @media print { a:hover { color: black; } }`
+/// Output: `["h1", ".title", "a:hover"]`
+pub fn css_selectors(css: &str) -> Vec<String> {
+    css_rule_re()
+        .captures_iter(css)
+        .flat_map(|caps| caps[1].split(',').map(|s| s.trim().to_string()).collect::<Vec<_>>())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Scope one selector, rewriting a `::v-slotted(X)` in it.
+fn scope_selector(selector: &str, suffix: &str) -> String {
+    let slotted = ["::v-slotted(", ":slotted("]
+        .iter()
+        .find_map(|open| selector.find(open).map(|at| (at, at + open.len())));
+    let Some((at, inner_start)) = slotted else {
+        return insert_scope_suffix(selector, suffix);
+    };
+    let Some(close) = selector[inner_start..].find(')') else {
+        return insert_scope_suffix(selector, suffix);
+    };
+    let container = selector[..at].trim_end();
+    let inner = selector[inner_start..inner_start + close].trim();
+    let rest = &selector[inner_start + close + 1..];
+    let container = if container.is_empty() {
+        suffix.to_string()
+    } else {
+        insert_scope_suffix(container, suffix)
+    };
+    format!("{container} {inner}{rest}")
+}
+
 /// Insert a scope class suffix before any pseudo-class/pseudo-element
 /// at the end of a selector.
 ///
@@ -464,7 +741,7 @@ pub fn scope_css(css: &str, id: &str) -> String {
 fn insert_scope_suffix(selector: &str, suffix: &str) -> String {
     // Find the last simple selector (after space or combinator)
     let last_start = selector
-        .rfind(|c: char| c == ' ' || c == '>' || c == '+' || c == '~')
+        .rfind([' ', '>', '+', '~'])
         .map(|p| p + 1)
         .unwrap_or(0);
 
@@ -483,6 +760,30 @@ fn insert_scope_suffix(selector: &str, suffix: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_bare_imports() {
+        let script = "import { ref } from 'vue'\nimport { debounce } from 'lodash-es'\nimport type { Options } from '@floating-ui/dom'\nimport Card from './card.van'\nimport { fmt } from '../utils/format.ts'\nimport dayjs from \"dayjs\"";
+        let imports = parse_bare_imports(script);
+        let paths: Vec<&str> = imports.iter().map(|imp| imp.path.as_str()).collect();
+        assert_eq!(paths, vec!["lodash-es", "@floating-ui/dom", "dayjs"]);
+        assert_eq!(imports[0].bindings(), vec![("debounce".to_string(), "debounce".to_string())]);
+        assert!(imports[1].is_type_only);
+        assert_eq!(imports[2].default_name().as_deref(), Some("dayjs"));
+    }
+
+    #[test]
+    fn test_normalize_source() {
+        let source = normalize_source("\u{feff}<template>\r\n  <p>Hi</p>\r\n</template>\r\n");
+        assert_eq!(source, "<template>\n  <p>Hi</p>\n</template>\n");
+        assert_eq!(parse_blocks(&source).template.as_deref(), Some("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn test_css_selectors() {
+        let css = "h1, .title { margin: 0; }\n@media print { a:hover { color: black; } }\n@keyframes spin { from { opacity: 0; } }";
+        assert_eq!(css_selectors(css), vec!["h1", ".title", "a:hover", "from"]);
+    }
+
     #[test]
     fn test_parse_blocks_basic() {
         let source = r#"
@@ -499,6 +800,7 @@ import Hello from './hello.van'
 </style>
 "#;
         let blocks = parse_blocks(source);
+        assert_eq!(blocks.script_setup_lang.as_deref(), Some("ts"));
         assert!(blocks.template.is_some());
         assert!(blocks.template.unwrap().contains("Hello {{ name }}"));
         assert!(blocks.script_setup.is_some());
@@ -530,6 +832,21 @@ import Hello from './hello.van'
         assert!(blocks.script_server.unwrap().contains("java code"));
     }
 
+    #[test]
+    fn test_parse_blocks_script_data() {
+        let source = r#"
+<template><h1>{{ title }}</h1></template>
+
+<script lang="data">
+{ "title": "Hello" }
+</script>
+"#;
+        let blocks = parse_blocks(source);
+        assert_eq!(blocks.script_data.as_deref(), Some(r#"{ "title": "Hello" }"#));
+        assert!(blocks.script_setup.is_none());
+        assert!(blocks.script_server.is_none());
+    }
+
     #[test]
     fn test_parse_blocks_empty() {
         let blocks = parse_blocks("");
@@ -651,6 +968,16 @@ h1 { font-size: 2rem; }
         // Different content → different ID
         let id3 = scope_id("h1 { color: blue; }");
         assert_ne!(id1, id3);
+        assert!(content_hash(".card { color: red; }").starts_with(&id1));
+    }
+
+    #[test]
+    fn test_content_hash_stable() {
+        // Pinned values: these must never change between releases.
+        assert_eq!(content_hash("").len(), 12);
+        assert_eq!(content_hash("body { margin: 0; }"), "4f2debd6116d");
+        assert_ne!(content_hash("a"), content_hash("b"));
+        assert!(content_hash("x").chars().all(|c| c.is_ascii_hexdigit()));
     }
 
     #[test]
@@ -676,7 +1003,38 @@ h1 { font-size: 2rem; }
         let result = add_scope_class(html, "a1b2c3d4");
         assert_eq!(
             result,
-            r#"<div class="a1b2c3d4"><img src="x.png" class="a1b2c3d4" /><br class="a1b2c3d4" /></div>"#
+            r#"<div class="a1b2c3d4"><img src="x.png" class="a1b2c3d4"/><br class="a1b2c3d4"/></div>"#
+        );
+    }
+
+    #[test]
+    fn test_add_scope_class_canonical_spacing() {
+        let cases = [
+            (r#"<p class="a">x</p>"#, r#"<p class="a s1">x</p>"#),
+            (r#"<p id="x" class="  a  b " title="t">x</p>"#, r#"<p id="x" class="a  b s1" title="t">x</p>"#),
+            (r#"<p class="">x</p>"#, r#"<p class="s1">x</p>"#),
+            (r#"<p>x</p>"#, r#"<p class="s1">x</p>"#),
+            (r#"<p id="x"  >x</p>"#, r#"<p id="x" class="s1">x</p>"#),
+            (r#"<p :class="{ on: open }">x</p>"#, r#"<p :class="{ on: open }" class="s1">x</p>"#),
+            ("<input\n  type=\"text\"\n/>", "<input\n  type=\"text\" class=\"s1\"/>"),
+            (r#"<br/>"#, r#"<br class="s1"/>"#),
+            (r#"<img src="x.png"   />"#, r#"<img src="x.png" class="s1"/>"#),
+            (r#"<img class="a" />"#, r#"<img class="a s1"/>"#),
+        ];
+        for (html, expected) in cases {
+            assert_eq!(add_scope_class(html, "s1"), expected, "{html}");
+        }
+    }
+
+    #[test]
+    fn test_add_scope_class_quoted_gt() {
+        assert_eq!(
+            add_scope_class(r#"<button :class="{ active: count > 1 }">b</button>"#, "s1"),
+            r#"<button :class="{ active: count > 1 }" class="s1">b</button>"#
+        );
+        assert_eq!(
+            add_scope_class(r#"<a title='a > b' class="x">l</a>"#, "s1"),
+            r#"<a title='a > b' class="x s1">l</a>"#
         );
     }
 
@@ -747,6 +1105,18 @@ h1 { font-size: 2rem; }
         assert_eq!(result, ".item.a1b2c3d4::before { content: '-'; }");
     }
 
+    #[test]
+    fn test_scope_css_slotted() {
+        assert_eq!(
+            scope_css(".content ::v-slotted(p) { margin: 0; }", "a1b2c3d4"),
+            ".content.a1b2c3d4 p { margin: 0; }"
+        );
+        assert_eq!(
+            scope_css("::v-slotted(a.more):hover, :slotted(em) { color: red; }", "a1b2c3d4"),
+            ".a1b2c3d4 a.more:hover, .a1b2c3d4 em { color: red; }"
+        );
+    }
+
     #[test]
     fn test_scope_css_no_pseudo() {
         let css = "h1 { font-size: 2rem; }";
@@ -799,6 +1169,15 @@ h1 { font-size: 2rem; }
         assert!(!props[2].required);
     }
 
+    #[test]
+    fn test_parse_define_props_with_default() {
+        let script = "defineProps({ open: { type: Boolean, default: false }, label: { type: String, default: 'a, b' } })";
+        let props = parse_define_props(script);
+        assert_eq!(props[0].default.as_deref(), Some("false"));
+        assert_eq!(props[1].default.as_deref(), Some("'a, b'"));
+        assert_eq!(props[1].prop_type, Some("String".to_string()));
+    }
+
     #[test]
     fn test_parse_define_props_missing() {
         let script = "const count = ref(0)";
@@ -806,6 +1185,29 @@ h1 { font-size: 2rem; }
         assert!(props.is_empty());
     }
 
+    #[test]
+    fn test_parse_define_options() {
+        let script = "const x = ref(0)\ndefineOptions({ sitemap: false, title: 'Home' })";
+        assert_eq!(
+            parse_define_options(script),
+            vec![
+                ("sitemap".to_string(), "false".to_string()),
+                ("title".to_string(), "'Home'".to_string()),
+            ]
+        );
+        assert!(parse_define_options("defineProps({ a: String })").is_empty());
+    }
+
+    #[test]
+    fn test_parse_file_options() {
+        let options = parse_file_options("defineOptions({ ssrInterpolation: false, scopedStrict: true, layout: false, lazy: true })");
+        assert!(!options.ssr_interpolation);
+        assert!(options.scoped_strict);
+        assert!(options.debug_comments);
+        assert_eq!(options.unknown_keys, vec!["lazy"]);
+        assert_eq!(parse_file_options("const a = 1"), FileOptions::default());
+    }
+
     #[test]
     fn test_parse_define_props_empty() {
         let script = "defineProps({})";
@@ -860,6 +1262,24 @@ import { formatDate } from '../utils/format.ts'
         assert_eq!(imports[1].path, "../utils/format.ts");
     }
 
+    #[test]
+    fn test_script_import_bindings() {
+        let script = r#"
+import { formatDate, pad as padLeft, type Opts } from '../utils/format.ts'
+import money, { currency } from '../utils/money.ts'
+import * as math from '../utils/math.js'
+import type { User } from '../types.ts'
+"#;
+        let bindings: Vec<Vec<(String, String)>> = parse_script_imports(script).iter().map(ScriptImport::bindings).collect();
+        let pairs = |list: &[(&str, &str)]| -> Vec<(String, String)> {
+            list.iter().map(|(l, e)| (l.to_string(), e.to_string())).collect()
+        };
+        assert_eq!(bindings[0], pairs(&[("formatDate", "formatDate"), ("padLeft", "pad")]));
+        assert_eq!(bindings[1], pairs(&[("money", "default"), ("currency", "currency")]));
+        assert_eq!(bindings[2], pairs(&[("math", "*")]));
+        assert!(bindings[3].is_empty());
+    }
+
     #[test]
     fn test_parse_script_imports_js() {
         let script = r#"import foo from '../utils/helper.js'"#;
@@ -933,4 +1353,19 @@ import { helper } from '../lib/helper.jsx'
         assert_eq!(imports[0].path, "../lib/render.tsx");
         assert_eq!(imports[1].path, "../lib/helper.jsx");
     }
+
+    #[test]
+    fn test_parse_script_imports_markdown() {
+        let script = r#"
+import intro from './content/intro.md'
+import { formatDate } from '../utils/format.ts'
+"#;
+        let imports = parse_script_imports(script);
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].path, "./content/intro.md");
+        assert!(imports[0].is_markdown());
+        assert_eq!(imports[0].default_name().as_deref(), Some("intro"));
+        assert!(!imports[1].is_markdown());
+        assert_eq!(imports[1].default_name(), None);
+    }
 }
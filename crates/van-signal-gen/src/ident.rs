@@ -0,0 +1,154 @@
+/// Byte ranges of the identifiers in a JS expression that refer to variables.
+///
+/// Skipped: property names (`e.target.name`, `a?.status`), object literal
+/// keys (`{ status: 1 }`), string contents and numbers. `${ }` inside template
+/// literals is scanned like the surrounding expression.
+pub(crate) fn variable_refs(expr: &str) -> Vec<(usize, usize)> {
+    let bytes = expr.as_bytes();
+    let mut refs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => i = skip_string(bytes, i),
+            b'`' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'`' {
+                    if bytes[i] == b'\\' {
+                        i += 2;
+                    } else if bytes[i..].starts_with(b"${") {
+                        let start = i + 2;
+                        i = matching_brace(bytes, start);
+                        let inner = variable_refs(&expr[start..i]);
+                        refs.extend(inner.into_iter().map(|(s, e)| (s + start, e + start)));
+                        i += 1;
+                    } else {
+                        i += 1;
+                    }
+                }
+                i += 1;
+            }
+            c if c.is_ascii_digit() => {
+                while i < bytes.len() && (is_ident_byte(bytes[i]) || bytes[i] == b'.') {
+                    i += 1;
+                }
+            }
+            c if is_ident_byte(c) => {
+                let start = i;
+                while i < bytes.len() && is_ident_byte(bytes[i]) {
+                    i += 1;
+                }
+                if !is_property(bytes, start) && !is_object_key(bytes, start, i) {
+                    refs.push((start, i));
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    refs
+}
+
+/// Whether `expr` refers to the variable `name` (not merely a property or a
+/// string of that name). A dotted `name` (a store field, `cart.count`) is
+/// referred to by a variable followed by the rest of its path.
+pub fn references_name(expr: &str, name: &str) -> bool {
+    variable_refs(expr).into_iter().any(|span| name_end(expr, span, name).is_some())
+}
+
+/// Where `name` ends in `expr` when the variable reference at `start..end`
+/// is it: `end` itself, or for a dotted name the end of the whole path, which
+/// must not run on into a longer identifier (`cart.counter` is not
+/// `cart.count`).
+pub(crate) fn name_end(expr: &str, (start, end): (usize, usize), name: &str) -> Option<usize> {
+    let variable = &expr[start..end];
+    if variable == name {
+        return Some(end);
+    }
+    let path = name.strip_prefix(variable)?;
+    let after = end + path.len();
+    let follows = path.starts_with('.') && expr[end..].starts_with(path);
+    (follows && !expr.as_bytes().get(after).is_some_and(|b| is_ident_byte(*b))).then_some(after)
+}
+
+/// Identifier characters; non-ASCII bytes count so Unicode names stay whole.
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+/// Offset just past the string literal opening at `start`.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i] != quote {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    i + 1
+}
+
+/// Offset of the `}` closing a block whose content starts at `start`.
+fn matching_brace(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' | b'`' => {
+                i = skip_string(bytes, i);
+                continue;
+            }
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return i,
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// `.name` or `?.name`, but not a spread `...name`.
+fn is_property(bytes: &[u8], start: usize) -> bool {
+    let before = trim_end(&bytes[..start]);
+    before.ends_with(b".") && !before.ends_with(b"...")
+}
+
+/// `{ name: ... }` / `, name: ...` — a key, not a reference.
+fn is_object_key(bytes: &[u8], start: usize, end: usize) -> bool {
+    let before = trim_end(&bytes[..start]);
+    let after = bytes[end..].iter().find(|b| !b.is_ascii_whitespace());
+    matches!(before.last(), Some(b'{' | b',')) && after == Some(&b':')
+}
+
+fn trim_end(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|b| !b.is_ascii_whitespace()).map_or(0, |p| p + 1);
+    &bytes[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(expr: &str) -> Vec<&str> {
+        variable_refs(expr).into_iter().map(|(s, e)| &expr[s..e]).collect()
+    }
+
+    #[test]
+    fn test_skips_properties_keys_and_strings() {
+        assert_eq!(names("e.target.name + status"), vec!["e", "status"]);
+        assert_eq!(names("a?.top || ...rest"), vec!["a", "rest"]);
+        assert_eq!(names("emit('status', { status: status })"), vec!["emit", "status"]);
+        assert_eq!(names(r#""count" + 'it\'s count' + 1.5e3"#), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_dollar_identifiers_and_template_literals() {
+        assert_eq!(names("$query.length + count$ + _x"), vec!["$query", "count$", "_x"]);
+        assert_eq!(names("`${name}: ${ { a: n }.a }`"), vec!["name", "n"]);
+    }
+
+    #[test]
+    fn test_references_name() {
+        assert!(references_name("count2 + count", "count"));
+        assert!(!references_name("count2", "count"));
+        assert!(!references_name("user.id", "id"));
+        assert!(!references_name("$count", "count"));
+    }
+}
@@ -1,10 +1,19 @@
 use std::collections::HashMap;
 use regex::Regex;
+use serde::Serialize;
 use oxc_allocator::Allocator;
 use oxc_ast::ast::*;
 use oxc_parser::Parser;
 use oxc_span::{GetSpan, SourceType};
 
+mod ident;
+mod module;
+mod ts;
+
+pub use ident::references_name;
+pub use module::{store_fields, ModuleCode, StoreField};
+pub use ts::strip_types;
+
 /// The embedded signal runtime JS (~1KB) with `__VAN_NS__` placeholder.
 pub const RUNTIME_JS: &str = include_str!("runtime.js");
 
@@ -15,6 +24,366 @@ pub fn runtime_js(global_name: &str) -> String {
     RUNTIME_JS.replace(NAMESPACE_PLACEHOLDER, global_name)
 }
 
+/// How the signal runtime is packaged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RuntimeFormat {
+    /// A classic script that assigns the runtime to `window.<global_name>`.
+    #[default]
+    Iife,
+    /// An ES module exporting `signal`, `computed`, `effect`, `batch`,
+    /// `transition`, `watch`, `sanitize` and `hydrate`.
+    EsModule,
+}
+
+impl std::str::FromStr for RuntimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iife" => Ok(Self::Iife),
+            "esm" => Ok(Self::EsModule),
+            other => Err(format!("unknown runtime format \"{other}\" (expected iife or esm)")),
+        }
+    }
+}
+
+/// The runtime JS in the given format (`global_name` only applies to [`RuntimeFormat::Iife`]).
+pub fn runtime_js_for(format: RuntimeFormat, global_name: &str) -> String {
+    match format {
+        RuntimeFormat::Iife => runtime_js(global_name),
+        RuntimeFormat::EsModule => runtime_esm(),
+    }
+}
+
+/// Derive the ES module build from `runtime.js`: unwrap the IIFE and turn the
+/// `window.__VAN_NS__ = { ... }` assignment into an `export` list.
+fn runtime_esm() -> String {
+    let body = RUNTIME_JS
+        .trim_end()
+        .strip_prefix("(function() {\n")
+        .and_then(|body| body.strip_suffix("})();"))
+        .expect("runtime.js is wrapped in an IIFE");
+    let start = body
+        .find(&format!("  window.{NAMESPACE_PLACEHOLDER} = {{"))
+        .expect("runtime.js assigns its namespace object");
+    let end = start + body[start..].find("};").expect("namespace object is closed") + 2;
+    let names: Vec<&str> = body[start..end]
+        .lines()
+        .filter_map(|line| line.split_once(':').map(|(name, _)| name.trim()))
+        .collect();
+    format!("{}  export {{ {} }};\n", &body[..start], names.join(", "))
+}
+
+/// Turn generated signal JS into an ES module that imports the runtime from
+/// `runtime_url` instead of reading the `global_name` global.
+pub fn signal_module(signal_js: &str, global_name: &str, runtime_url: &str) -> String {
+    let body = signal_js.replacen(&format!("  var V = {global_name};\n"), "", 1);
+    format!("import * as V from {};\n{body}", js_string_literal(runtime_url))
+}
+
+/// Make generated signal JS removable: effects, watchers and computeds are
+/// recorded as they start, event listeners as they are added, and
+/// `window.__van_pages[page].destroy()` stops and removes them all. For hosts
+/// that swap page content without a full reload.
+///
+/// `modules` are the ones the JS was generated with; their inlined code is
+/// left as written.
+pub fn with_teardown(signal_js: &str, modules: &[ModuleCode], page: &str) -> String {
+    let modules_js = module::modules_js(modules);
+    let skip = Some(modules_js.as_str())
+        .filter(|m| !m.is_empty())
+        .and_then(|m| signal_js.find(m).map(|start| start..start + m.len()));
+    let effect_re = Regex::new(r"(^|[^\w$.])V\.(effect|watch|computed|hydrate)\(").unwrap();
+    let mut out = String::with_capacity(signal_js.len() + 1024);
+    let mut pos = 0;
+    for line in signal_js.split_inclusive('\n') {
+        let start = pos;
+        pos += line.len();
+        if skip.as_ref().is_some_and(|range| range.contains(&start)) {
+            out.push_str(line);
+            continue;
+        }
+        // `_t` starts effects like `V` does, but keeps their disposers
+        out.push_str(&track_listener(&effect_re.replace_all(line, "${1}_t.${2}(")));
+        if line.starts_with("  var V = ") {
+            out.push_str(TEARDOWN_PRELUDE);
+        }
+    }
+    let registry = format!(
+        "  window.__van_pages = window.__van_pages || {{}};\n  window.__van_pages[{}] = {{\n    destroy: function() {{\n      _ls.forEach(function(l) {{ l[0].removeEventListener(l[1], l[2]); }});\n      _fx.forEach(function(dispose) {{ dispose(); }});\n      _ls = []; _fx = [];\n    }}\n  }};\n",
+        js_string_literal(page)
+    );
+    if let Some(end) = out.rfind("})();") {
+        out.insert_str(end, &registry);
+    }
+    out
+}
+
+/// Bookkeeping declared at the top of a torn-down page's IIFE: `_t` starts
+/// effects (and lazy hydration gates) like `V` but records their disposers
+/// in `_fx`; `_on` records listeners in `_ls`.
+const TEARDOWN_PRELUDE: &str = "  var _fx = [], _ls = [];
+  var _t = {
+    effect: function(fn) { var d = V.effect(fn); _fx.push(d); return d; },
+    watch: function(s, fn) { var d = V.watch(s, fn); _fx.push(d); return d; },
+    computed: function(fn) { var c = V.computed(fn); _fx.push(c.dispose); return c; },
+    hydrate: function(el, fn) { var d = V.hydrate(el, fn); _fx.push(d); return d; }
+  };
+  function _on(el, type, fn) { el.addEventListener(type, fn); _ls.push([el, type, fn]); }
+";
+
+/// Turn a generated `  el.addEventListener(type, fn);` statement (at any
+/// indentation, as inside a lazy hydration gate) into `  _on(el, type, fn);`.
+/// Anything else is returned unchanged.
+fn track_listener(line: &str) -> String {
+    let indent = &line[..line.len() - line.trim_start_matches(' ').len()];
+    let Some(stmt) = line[indent.len()..].strip_suffix('\n').filter(|_| indent.len() >= 2) else {
+        return line.to_string();
+    };
+    let Some(at) = stmt.find(".addEventListener(") else {
+        return line.to_string();
+    };
+    let target = &stmt[..at];
+    let simple = !target.is_empty()
+        && target.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'$' | b'[' | b']' | b'\''));
+    match stmt[at + ".addEventListener(".len()..].strip_suffix(");") {
+        Some(args) if simple => format!("{indent}_on({target}, {args});\n"),
+        _ => line.to_string(),
+    }
+}
+
+/// Split compile-mode signal JS ([`generate_signals_compile`], after
+/// [`with_teardown`] when used) into scripts of at most `max_bytes` each, for
+/// hosts that cap asset size.
+///
+/// The first script declares the signals, computeds and functions, finds the
+/// `<ClientOnly>` blocks and shares that scope as `window.__van_chunks[page]`;
+/// each later one wires the bindings of a run of blocks from it, so the
+/// scripts must run in order. A block too big for one script is split between
+/// statements. The first script is never split, so it alone can be over
+/// `max_bytes`. JS that already fits is returned as the only script.
+pub fn split_signals(signal_js: &str, page: &str, max_bytes: usize) -> Vec<String> {
+    if signal_js.len() <= max_bytes {
+        return vec![signal_js.to_string()];
+    }
+    let lines: Vec<&str> = signal_js.split_inclusive('\n').collect();
+    // Wiring starts after the block discovery loop and ends at the teardown
+    // registry, or the end of the IIFE
+    let Some(wiring_start) = lines
+        .iter()
+        .position(|line| line.starts_with("  while (_n = _walker.nextNode())"))
+        .and_then(|at| lines[at..].iter().position(|line| *line == "  }\n").map(|end| at + end + 1))
+    else {
+        return vec![signal_js.to_string()];
+    };
+    let wiring_end = lines
+        .iter()
+        .rposition(|line| line.starts_with("  window.__van_pages = "))
+        .or_else(|| lines.iter().rposition(|line| line.starts_with("})();")))
+        .unwrap_or(lines.len());
+    if wiring_start >= wiring_end {
+        return vec![signal_js.to_string()];
+    }
+
+    // Top-level names of the first script, which the later ones read back
+    let decl_re = Regex::new(r"^  (?:(?:var|let|const)\s+|(?:async\s+)?function\s*\*?\s*)([\w$]+)").unwrap();
+    let mut names: Vec<&str> = Vec::new();
+    for line in &lines[..wiring_start] {
+        if let Some(name) = decl_re.captures(line).and_then(|c| c.get(1)) {
+            if !names.contains(&name.as_str()) && !BLOCK_DISCOVERY_NAMES.contains(&name.as_str()) {
+                names.push(name.as_str());
+            }
+        }
+    }
+    let key = js_string_literal(page);
+    let shared = names.iter().map(|name| format!("{name}: {name}")).collect::<Vec<_>>().join(", ");
+    let mut first: String = lines[..wiring_start].concat();
+    first.push_str("  window.__van_chunks = window.__van_chunks || {};\n");
+    first.push_str(&format!("  window.__van_chunks[{key}] = {{ {shared} }};\n"));
+    first.push_str(&lines[wiring_end..].concat());
+
+    let open = format!(
+        "(function(_s) {{\n  var {};\n",
+        names.iter().map(|name| format!("{name} = _s.{name}")).collect::<Vec<_>>().join(", ")
+    );
+    let close = format!("}})(window.__van_chunks[{key}]);\n");
+    let budget = max_bytes.saturating_sub(open.len() + close.len());
+
+    // Statements grouped by the block they wire, in order
+    let block_re = Regex::new(r"_blocks\[(\d+)\]").unwrap();
+    let mut groups: Vec<(Option<&str>, Vec<&str>)> = Vec::new();
+    for line in &lines[wiring_start..wiring_end] {
+        let block = block_re.captures(line).and_then(|c| c.get(1)).map(|m| m.as_str());
+        match groups.last_mut() {
+            Some((current, stmts)) if *current == block || block.is_none() => stmts.push(line),
+            _ => groups.push((block, vec![line])),
+        }
+    }
+
+    let mut chunks = vec![first];
+    let mut body = String::new();
+    let mut flush = |body: &mut String| {
+        if !body.is_empty() {
+            chunks.push(format!("{open}{body}{close}"));
+            body.clear();
+        }
+    };
+    for (_, stmts) in &groups {
+        let size: usize = stmts.iter().map(|stmt| stmt.len()).sum();
+        if body.len() + size > budget {
+            flush(&mut body);
+        }
+        for stmt in stmts {
+            if body.len() + stmt.len() > budget {
+                flush(&mut body);
+            }
+            body.push_str(stmt);
+        }
+    }
+    flush(&mut body);
+    chunks
+}
+
+/// Locals of the compile-mode block discovery, which wiring doesn't read.
+const BLOCK_DISCOVERY_NAMES: &[&str] = &["_collectEls", "_walker", "_n"];
+
+/// Replace `import.meta.env.NAME` references with string literals from `env`.
+///
+/// Runs on the raw script before analysis, so inlined values behave like any
+/// other constant. Names missing from `env` become `""` and are returned so
+/// the caller can warn about them.
+pub fn inline_env(script: &str, env: &HashMap<String, String>) -> (String, Vec<String>) {
+    let re = Regex::new(r"import\.meta\.env\.([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+    let mut missing = Vec::new();
+    let replaced = re.replace_all(script, |caps: &regex::Captures| {
+        let name = &caps[1];
+        match env.get(name) {
+            Some(value) => js_string_literal(value),
+            None => {
+                if !missing.iter().any(|m| m == name) {
+                    missing.push(name.to_string());
+                }
+                "\"\"".to_string()
+            }
+        }
+    });
+    (replaced.into_owned(), missing)
+}
+
+/// Replace `props.NAME` in the initial values of `ref()` signals with JS
+/// literals from `value_of`, so `ref(props.defaultOpen)` starts from the value
+/// the parent passed.
+///
+/// `props` is whatever name `defineProps()` is bound to; scripts that don't
+/// bind it are returned unchanged. Names `value_of` has no value for become
+/// `undefined` and are returned so the caller can warn about them.
+pub fn inline_props(script: &str, value_of: impl Fn(&str) -> Option<String>) -> (String, Vec<String>) {
+    let allocator = Allocator::default();
+    let source_type = SourceType::from_path("script.ts").unwrap_or_default();
+    let ret = Parser::new(&allocator, script, source_type).parse();
+
+    let declarators = || {
+        ret.program.body.iter().filter_map(|stmt| match stmt {
+            Statement::VariableDeclaration(decl) => Some(decl.declarations.iter()),
+            _ => None,
+        }).flatten()
+    };
+    let Some(props_name) = declarators()
+        .find(|d| matches!(&d.init, Some(Expression::CallExpression(call))
+            if matches!(callee_name(call), Some("defineProps" | "withDefaults"))))
+        .and_then(binding_name)
+    else {
+        return (script.to_string(), Vec::new());
+    };
+
+    let mut replacements = Vec::new();
+    let mut missing = Vec::new();
+    for declarator in declarators() {
+        let Some(Expression::CallExpression(call)) = &declarator.init else { continue };
+        let Some(arg) = call.arguments.first().filter(|_| callee_name(call) == Some("ref")) else { continue };
+        let span = arg.span();
+        let initial = span.source_text(script);
+        for (start, end) in ident::variable_refs(initial) {
+            let rest = &initial[end..];
+            if initial[start..end] != props_name || !rest.starts_with('.') {
+                continue;
+            }
+            let name_len = rest[1..].find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).unwrap_or(rest.len() - 1);
+            let name = &rest[1..1 + name_len];
+            if name.is_empty() {
+                continue;
+            }
+            let value = value_of(name).unwrap_or_else(|| {
+                if !missing.iter().any(|m| m == name) {
+                    missing.push(name.to_string());
+                }
+                "undefined".to_string()
+            });
+            let offset = span.start as usize;
+            replacements.push((offset + start, offset + end + 1 + name_len, value));
+        }
+    }
+
+    let mut out = script.to_string();
+    for (start, end, value) in replacements.into_iter().rev() {
+        out.replace_range(start..end, &value);
+    }
+    (out, missing)
+}
+
+/// Bind the name `defineProps()` is assigned to (`const props = defineProps(...)`)
+/// to an object literal of the prop values, so handlers and computeds can read
+/// `props.title` on the client. `values` are `(name, JS literal)` pairs.
+///
+/// Being a literal `const`, the object is declared at the top of the signal
+/// script with the other constants. Read-only by convention: nothing writes
+/// it back. Scripts that don't bind `defineProps()` are returned unchanged.
+pub fn props_object(script: &str, values: &[(String, String)]) -> String {
+    let allocator = Allocator::default();
+    let source_type = SourceType::from_path("script.ts").unwrap_or_default();
+    let ret = Parser::new(&allocator, script, source_type).parse();
+    let Some(init) = ret
+        .program
+        .body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::VariableDeclaration(decl) if decl.kind == VariableDeclarationKind::Const => Some(decl.declarations.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter(|d| binding_name(d).is_some())
+        .find_map(|d| match &d.init {
+            Some(init @ Expression::CallExpression(call)) if matches!(callee_name(call), Some("defineProps" | "withDefaults")) => Some(init),
+            _ => None,
+        })
+    else {
+        return script.to_string();
+    };
+    let fields: Vec<String> = values.iter().map(|(name, value)| format!("{}: {value}", js_string_literal(name))).collect();
+    let span = init.span();
+    let mut out = script.to_string();
+    out.replace_range(span.start as usize..span.end as usize, &format!("{{ {} }}", fields.join(", ")));
+    out
+}
+
+/// Quote a string as a double-quoted JS literal.
+fn js_string_literal(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '<' => out.push_str("\\u003c"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
 /// Extract initial values of `ref()` signals from a `<script setup>` block.
 ///
 /// Returns a map of `signal_name → display_value` suitable for HTML interpolation.
@@ -59,6 +428,9 @@ pub struct SignalDecl {
 pub struct ComputedDecl {
     pub name: String,
     pub body: String,
+    /// `body` holds the statements of a block (`() => { ... }` or
+    /// `function() { ... }`) rather than a single expression.
+    pub block: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -66,6 +438,16 @@ pub struct FunctionDecl {
     pub name: String,
     pub params: String,
     pub body: String,
+    /// Declared `async` (`async function f()` / `async () => ...`).
+    pub is_async: bool,
+}
+
+/// A `const` bound to a literal (`'Acme'`, `3`, `[{ href: '/' }]`), usable
+/// by server-side interpolation. Not reactive: no effects are generated for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstDecl {
+    pub name: String,
+    pub value: serde_json::Value,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -74,6 +456,20 @@ pub struct ScriptAnalysis {
     pub computeds: Vec<ComputedDecl>,
     pub functions: Vec<FunctionDecl>,
     pub watches: Vec<WatchDecl>,
+    pub constants: Vec<ConstDecl>,
+    /// Every other top-level statement (plain declarations as `var`, calls,
+    /// side effects), as written and in source order. Imports, type
+    /// declarations and compiler macros such as `defineProps` are not included.
+    pub residual: Vec<String>,
+}
+
+/// Compile-time macros of `<script setup>`, never emitted to the client.
+const COMPILER_MACROS: &[&str] = &[
+    "defineProps", "defineEmits", "defineOptions", "defineExpose", "defineSlots", "defineModel", "withDefaults",
+];
+
+fn is_macro_call(expr: &Expression) -> bool {
+    matches!(expr, Expression::CallExpression(call) if callee_name(call).is_some_and(|name| COMPILER_MACROS.contains(&name)))
 }
 
 /// Get the name of a call expression's callee (if it's a simple identifier).
@@ -111,35 +507,34 @@ fn body_inner_text(body: &FunctionBody, source: &str) -> String {
     }
 }
 
-/// Extract the body of a computed()'s callback argument.
-/// For `computed(() => expr)`, returns "expr".
+/// Extract the body of a computed()'s callback argument, and whether it is a
+/// block.
+/// For `computed(() => expr)`, returns `("expr", false)`.
 /// For `computed(() => { return expr; })`, returns the block inner text.
-fn extract_computed_body(call: &CallExpression, source: &str) -> String {
+fn extract_computed_body(call: &CallExpression, source: &str) -> (String, bool) {
     let Some(first_arg) = call.arguments.first() else {
-        return String::new();
+        return (String::new(), false);
     };
     match first_arg {
         Argument::ArrowFunctionExpression(arrow) => {
             if arrow.expression {
                 // Concise body: () => expr — get the expression text
-                if let Some(stmt) = arrow.body.statements.first() {
-                    if let Statement::ExpressionStatement(expr_stmt) = stmt {
-                        return expr_stmt.expression.span().source_text(source).trim().to_string();
-                    }
+                if let Some(Statement::ExpressionStatement(expr_stmt)) = arrow.body.statements.first() {
+                    return (expr_stmt.expression.span().source_text(source).trim().to_string(), false);
                 }
-                body_inner_text(&arrow.body, source)
+                (body_inner_text(&arrow.body, source), false)
             } else {
-                body_inner_text(&arrow.body, source)
+                (body_inner_text(&arrow.body, source), true)
             }
         }
         Argument::FunctionExpression(func) => {
             if let Some(ref body) = func.body {
-                body_inner_text(body, source)
+                (body_inner_text(body, source), true)
             } else {
-                String::new()
+                (String::new(), false)
             }
         }
-        _ => String::new(),
+        _ => (String::new(), false),
     }
 }
 
@@ -158,12 +553,8 @@ fn extract_watch_args(call: &CallExpression, source: &str) -> Option<(String, St
         Argument::ArrowFunctionExpression(arrow) => {
             let p = params_text(&arrow.params, source);
             let b = if arrow.expression {
-                if let Some(stmt) = arrow.body.statements.first() {
-                    if let Statement::ExpressionStatement(es) = stmt {
-                        es.expression.span().source_text(source).trim().to_string()
-                    } else {
-                        body_inner_text(&arrow.body, source)
-                    }
+                if let Some(Statement::ExpressionStatement(es)) = arrow.body.statements.first() {
+                    es.expression.span().source_text(source).trim().to_string()
                 } else {
                     body_inner_text(&arrow.body, source)
                 }
@@ -193,14 +584,20 @@ pub fn analyze_script(script: &str) -> ScriptAnalysis {
     let mut computeds = Vec::new();
     let mut functions = Vec::new();
     let mut watches = Vec::new();
+    let mut constants = Vec::new();
+    let mut residual = Vec::new();
 
     for stmt in &ret.program.body {
         match stmt {
             // const x = ref(...) / computed(...) / () => ... / function() { ... }
             Statement::VariableDeclaration(decl) => {
                 for declarator in &decl.declarations {
-                    let Some(name) = binding_name(declarator) else { continue };
-                    let Some(ref init) = declarator.init else { continue };
+                    // Emitted as `var`: scripts of several components share one scope
+                    let declaration = || format!("var {};", declarator.span.source_text(script));
+                    let (Some(name), Some(init)) = (binding_name(declarator), &declarator.init) else {
+                        residual.push(declaration());
+                        continue;
+                    };
                     match init {
                         Expression::CallExpression(call) => {
                             match callee_name(call) {
@@ -211,22 +608,19 @@ pub fn analyze_script(script: &str) -> ScriptAnalysis {
                                     signals.push(SignalDecl { name, initial_value: initial });
                                 }
                                 Some("computed") => {
-                                    let body = extract_computed_body(call, script);
-                                    computeds.push(ComputedDecl { name, body });
+                                    let (body, block) = extract_computed_body(call, script);
+                                    computeds.push(ComputedDecl { name, body, block });
                                 }
-                                _ => {}
+                                _ if is_macro_call(init) => {}
+                                _ => residual.push(declaration()),
                             }
                         }
                         Expression::ArrowFunctionExpression(arrow) => {
                             let params = params_text(&arrow.params, script);
                             let body = if arrow.expression {
                                 // Concise body: () => expr
-                                if let Some(stmt) = arrow.body.statements.first() {
-                                    if let Statement::ExpressionStatement(es) = stmt {
-                                        es.expression.span().source_text(script).trim().to_string()
-                                    } else {
-                                        body_inner_text(&arrow.body, script)
-                                    }
+                                if let Some(Statement::ExpressionStatement(es)) = arrow.body.statements.first() {
+                                    es.expression.span().source_text(script).trim().to_string()
                                 } else {
                                     body_inner_text(&arrow.body, script)
                                 }
@@ -234,16 +628,19 @@ pub fn analyze_script(script: &str) -> ScriptAnalysis {
                                 // Block body: () => { ... }
                                 body_inner_text(&arrow.body, script)
                             };
-                            functions.push(FunctionDecl { name, params, body });
+                            functions.push(FunctionDecl { name, params, body, is_async: arrow.r#async });
                         }
                         Expression::FunctionExpression(func) => {
                             let params = params_text(&func.params, script);
                             let body = func.body.as_ref()
                                 .map(|b| body_inner_text(b, script))
                                 .unwrap_or_default();
-                            functions.push(FunctionDecl { name, params, body });
+                            functions.push(FunctionDecl { name, params, body, is_async: func.r#async });
                         }
-                        _ => {}
+                        other => match literal_value(other).filter(|_| decl.kind == VariableDeclarationKind::Const) {
+                            Some(value) => constants.push(ConstDecl { name, value }),
+                            None => residual.push(declaration()),
+                        },
                     }
                 }
             }
@@ -255,28 +652,93 @@ pub fn analyze_script(script: &str) -> ScriptAnalysis {
                     let body = func.body.as_ref()
                         .map(|b| body_inner_text(b, script))
                         .unwrap_or_default();
-                    functions.push(FunctionDecl { name, params, body });
+                    functions.push(FunctionDecl { name, params, body, is_async: func.r#async });
                 }
             }
             // watch(source, callback)
             Statement::ExpressionStatement(expr_stmt) => {
-                if let Expression::CallExpression(call) = &expr_stmt.expression {
-                    if callee_name(call) == Some("watch") {
-                        if let Some((source_name, params, body)) = extract_watch_args(call, script) {
-                            watches.push(WatchDecl {
-                                source: source_name,
-                                params,
-                                body,
-                            });
-                        }
+                let watch = match &expr_stmt.expression {
+                    Expression::CallExpression(call) if callee_name(call) == Some("watch") => {
+                        extract_watch_args(call, script)
                     }
+                    _ => None,
+                };
+                if let Some((source_name, params, body)) = watch {
+                    watches.push(WatchDecl {
+                        source: source_name,
+                        params,
+                        body,
+                    });
+                } else if !is_macro_call(&expr_stmt.expression) {
+                    residual.push(stmt.span().source_text(script).to_string());
                 }
             }
-            _ => {}
+            Statement::ImportDeclaration(_)
+            | Statement::EmptyStatement(_)
+            | Statement::TSTypeAliasDeclaration(_)
+            | Statement::TSInterfaceDeclaration(_)
+            | Statement::TSModuleDeclaration(_)
+            | Statement::TSImportEqualsDeclaration(_) => {}
+            _ => residual.push(stmt.span().source_text(script).to_string()),
         }
     }
 
-    ScriptAnalysis { signals, computeds, functions, watches }
+    ScriptAnalysis { signals, computeds, functions, watches, constants, residual }
+}
+
+/// The JSON value of a literal expression: strings (including templates
+/// without `${}`), numbers, booleans, `null`, and arrays or objects of those.
+fn literal_value(expr: &Expression) -> Option<serde_json::Value> {
+    use serde_json::Value;
+    Some(match expr {
+        Expression::StringLiteral(s) => Value::String(s.value.to_string()),
+        Expression::TemplateLiteral(t) if t.expressions.is_empty() => {
+            Value::String(t.quasis.first()?.value.cooked?.to_string())
+        }
+        Expression::NumericLiteral(n) => number_value(n.value)?,
+        Expression::UnaryExpression(u) if u.operator == UnaryOperator::UnaryNegation => match &u.argument {
+            Expression::NumericLiteral(n) => number_value(-n.value)?,
+            _ => return None,
+        },
+        Expression::BooleanLiteral(b) => Value::Bool(b.value),
+        Expression::NullLiteral(_) => Value::Null,
+        Expression::ParenthesizedExpression(p) => literal_value(&p.expression)?,
+        Expression::TSAsExpression(e) => literal_value(&e.expression)?,
+        Expression::ArrayExpression(array) => Value::Array(
+            array
+                .elements
+                .iter()
+                .map(|element| element.as_expression().and_then(literal_value))
+                .collect::<Option<_>>()?,
+        ),
+        Expression::ObjectExpression(object) => {
+            let mut map = serde_json::Map::new();
+            for property in &object.properties {
+                let ObjectPropertyKind::ObjectProperty(property) = property else { return None };
+                if property.computed || property.method || property.kind != PropertyKind::Init {
+                    return None;
+                }
+                let key = match &property.key {
+                    PropertyKey::StaticIdentifier(id) => id.name.to_string(),
+                    PropertyKey::StringLiteral(s) => s.value.to_string(),
+                    PropertyKey::NumericLiteral(n) => n.value.to_string(),
+                    _ => return None,
+                };
+                map.insert(key, literal_value(&property.value)?);
+            }
+            Value::Object(map)
+        }
+        _ => return None,
+    })
+}
+
+/// Integers stay integers (`3`, not `3.0`) so they interpolate as written.
+fn number_value(n: f64) -> Option<serde_json::Value> {
+    if n.fract() == 0.0 && n.abs() < 9e15 {
+        Some(serde_json::Value::from(n as i64))
+    } else {
+        serde_json::Number::from_f64(n).map(serde_json::Value::Number)
+    }
 }
 
 // ── Stage B: HTML Tree Walker ───────────────────────────────────────────────
@@ -302,7 +764,7 @@ enum HtmlNode {
 }
 
 /// A binding for `@event="handler"` with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct EventBinding {
     pub path: Vec<usize>,
     pub event: String,
@@ -310,55 +772,124 @@ pub struct EventBinding {
 }
 
 /// A binding for `{{ reactiveExpr }}` text content with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct TextBinding {
     pub path: Vec<usize>,
     pub template: String,
 }
 
+/// A plain attribute whose value interpolates reactive `{{ expr }}`, e.g.
+/// `href="/users/{{ id }}"`, with its positional path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttrTemplateBinding {
+    pub path: Vec<usize>,
+    pub name: String,
+    pub template: String,
+}
+
 /// A binding for `v-show="expr"` or `v-if="expr"` with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ShowBinding {
     pub path: Vec<usize>,
     pub expr: String,
-    pub transition: Option<String>,
+    pub transition: Option<Transition>,
+}
+
+/// Settings from a `<Transition>` wrapper.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Transition {
+    /// Class prefix from `name` (default `v`).
+    pub name: String,
+    /// `appear`: also animate the initial mount.
+    pub appear: bool,
+    /// JS expression for `duration`/`:duration` (ms, or `{ enter, leave }`);
+    /// `None` waits for `transitionend`.
+    pub duration: Option<String>,
+}
+
+impl Transition {
+    fn from_attrs(attrs: &[(String, String)]) -> Self {
+        let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        let duration = match (attr(":duration"), attr("duration")) {
+            (Some(expr), _) => Some(expr.trim().to_string()),
+            (None, Some(ms)) if ms.trim().parse::<f64>().is_ok() => Some(ms.trim().to_string()),
+            _ => None,
+        };
+        Transition {
+            name: attr("name").unwrap_or("v").to_string(),
+            appear: attr("appear").is_some_and(|v| v != "false") || attr(":appear") == Some("true"),
+            duration,
+        }
+    }
+
+    /// A `V.effect(...)` statement showing or hiding `el` through this transition.
+    ///
+    /// The first run is `instant` (no animation) unless `appear` is set and
+    /// the element starts visible, so pages don't animate on load.
+    fn effect_js(&self, el: &str, expr: &str) -> String {
+        let mut opts = Vec::new();
+        if self.appear {
+            opts.push("appear: true".to_string());
+        }
+        if let Some(ref duration) = self.duration {
+            opts.push(format!("duration: {duration}"));
+        }
+        opts.push(if self.appear { "instant: first && !v" } else { "instant: first" }.to_string());
+        format!(
+            "  V.effect((function(first) {{ return function() {{ var v = {expr}; V.transition({el}, v, '{}', {{ {} }}); first = false; }}; }})(true));\n",
+            self.name,
+            opts.join(", ")
+        )
+    }
 }
 
 /// A binding for `v-html="expr"` with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct HtmlDirectiveBinding {
     pub path: Vec<usize>,
     pub expr: String,
 }
 
 /// A binding for `v-text="expr"` with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct TextDirectiveBinding {
     pub path: Vec<usize>,
     pub expr: String,
 }
 
 /// A binding for `:class="{ ... }"` with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ClassBinding {
     pub path: Vec<usize>,
     pub expr: String,
 }
 
 /// A binding for `:style="{ ... }"` with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct StyleBinding {
     pub path: Vec<usize>,
     pub expr: String,
 }
 
 /// A binding for `v-model="signalName"` with its positional path.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ModelBinding {
     pub path: Vec<usize>,
     pub signal_name: String,
 }
 
+/// A `v-for="item in items"` element left in the template (compile mode),
+/// with its `:key` expression when present.
+///
+/// The key identifies rows across re-renders so a client-side list update can
+/// reuse existing nodes instead of recreating them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForBinding {
+    pub path: Vec<usize>,
+    pub expr: String,
+    pub key: Option<String>,
+}
+
 /// A watch() declaration from script setup.
 #[derive(Debug, Clone, PartialEq)]
 pub struct WatchDecl {
@@ -367,7 +898,7 @@ pub struct WatchDecl {
     pub body: String,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct TemplateBindings {
     pub events: Vec<EventBinding>,
     pub texts: Vec<TextBinding>,
@@ -377,10 +908,39 @@ pub struct TemplateBindings {
     pub classes: Vec<ClassBinding>,
     pub styles: Vec<StyleBinding>,
     pub models: Vec<ModelBinding>,
+    pub fors: Vec<ForBinding>,
+    pub attrs: Vec<AttrTemplateBinding>,
+    /// Paths of `v-cloak` elements, uncloaked once their bindings are wired.
+    pub cloaks: Vec<Vec<usize>>,
+}
+
+/// Parser bookkeeping for recovering from unclosed elements.
+#[derive(Default)]
+struct ParseState {
+    /// Tag names of the elements currently being parsed, outermost first.
+    open: Vec<String>,
+    /// `(offset, tag)` of elements found to have no closing tag.
+    unclosed: Vec<(usize, String)>,
 }
 
 /// Parse HTML string into a list of HtmlNode.
 fn parse_html(html: &str) -> Vec<HtmlNode> {
+    parse_html_with(html, &mut ParseState::default())
+}
+
+/// Tag names of elements whose closing tag is missing, in document order.
+///
+/// The parser treats such an element as empty, so what follows it stays a
+/// sibling instead of being absorbed as its children (which would shift the
+/// positional paths of every bound element after it).
+pub fn unclosed_elements(html: &str) -> Vec<String> {
+    let mut state = ParseState::default();
+    parse_html_with(html, &mut state);
+    state.unclosed.sort();
+    state.unclosed.into_iter().map(|(_, tag)| tag).collect()
+}
+
+fn parse_html_with(html: &str, state: &mut ParseState) -> Vec<HtmlNode> {
     let mut nodes = Vec::new();
     let mut pos = 0;
     let bytes = html.as_bytes();
@@ -409,7 +969,7 @@ fn parse_html(html: &str) -> Vec<HtmlNode> {
             }
 
             // Opening tag — parse element
-            if let Some((elem, end_pos)) = parse_element(html, pos) {
+            if let Some((elem, end_pos)) = parse_element(html, pos, state) {
                 nodes.push(HtmlNode::Element(elem));
                 pos = end_pos;
             } else {
@@ -441,19 +1001,38 @@ const VOID_ELEMENTS: &[&str] = &[
     "link", "meta", "param", "source", "track", "wbr",
 ];
 
+/// Length of the tag at the start of `s`, honouring quoted attribute values
+/// (`:class="{ on: n > 1 }"`).
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
 /// Parse a single element starting at `pos` (which points to '<').
 /// Returns the element and the position after its closing tag.
-fn parse_element(html: &str, pos: usize) -> Option<(HtmlElement, usize)> {
+///
+/// An element whose closing tag never comes (before a parent closes or the
+/// input ends) is returned empty, ending right after its opening tag.
+fn parse_element(html: &str, pos: usize, state: &mut ParseState) -> Option<(HtmlElement, usize)> {
     let rest = &html[pos..];
     if !rest.starts_with('<') {
         return None;
     }
 
     // Find end of opening tag
-    let gt_pos = rest.find('>')?;
+    let gt_pos = tag_end(rest)? - 1;
     let tag_content = &rest[1..gt_pos];
 
-    // Self-closing?
+    // Self-closing? (`<my-widget/>`, SVG's `<path d="..." />`)
     let self_closing = tag_content.ends_with('/');
     let tag_content = if self_closing {
         &tag_content[..tag_content.len() - 1]
@@ -473,24 +1052,23 @@ fn parse_element(html: &str, pos: usize) -> Option<(HtmlElement, usize)> {
     let attrs = parse_attrs(&tag_content[tag_end..]);
 
     let after_open = pos + gt_pos + 1;
+    let empty = |tag_name: String, attrs| {
+        Some((HtmlElement { tag: tag_name, attrs, children: Vec::new(), source_start: pos }, after_open))
+    };
 
-    // Void elements and self-closing tags have no children
-    if self_closing || VOID_ELEMENTS.contains(&tag_name.as_str()) {
-        return Some((
-            HtmlElement {
-                tag: tag_name,
-                attrs,
-                children: Vec::new(),
-                source_start: pos,
-            },
-            after_open,
-        ));
+    // Void elements, self-closing tags and known unclosed elements have no children
+    if self_closing || VOID_ELEMENTS.contains(&tag_name.as_str()) || state.unclosed.iter().any(|(at, _)| *at == pos) {
+        return empty(tag_name, attrs);
     }
 
     // Parse children until we find the closing tag
-    let close_tag = format!("</{}>", tag_name);
-    let children = parse_children_until(html, after_open, &close_tag);
-    let (child_nodes, end_pos) = children;
+    state.open.push(tag_name.clone());
+    let (child_nodes, end_pos, closed) = parse_children_until(html, after_open, &tag_name, state);
+    state.open.pop();
+    if !closed {
+        state.unclosed.push((pos, tag_name.clone()));
+        return empty(tag_name, attrs);
+    }
 
     Some((
         HtmlElement {
@@ -503,19 +1081,23 @@ fn parse_element(html: &str, pos: usize) -> Option<(HtmlElement, usize)> {
     ))
 }
 
-/// Parse children from `start` until we encounter `close_tag`.
-/// Returns the children and position after the close tag.
-fn parse_children_until(html: &str, start: usize, close_tag: &str) -> (Vec<HtmlNode>, usize) {
+/// Parse children from `start` until the closing tag of `tag_name`.
+/// Returns the children, the position after the close tag, and whether it
+/// was found. Parsing stops without it at the end of input or at the closing
+/// tag of an enclosing element, which is left for that element.
+///
+/// Closing tags match case-insensitively (`</clipPath>` closes `<clipPath>`).
+fn parse_children_until(
+    html: &str,
+    start: usize,
+    tag_name: &str,
+    state: &mut ParseState,
+) -> (Vec<HtmlNode>, usize, bool) {
     let mut nodes = Vec::new();
     let mut pos = start;
     let bytes = html.as_bytes();
 
     while pos < bytes.len() {
-        // Check if we've reached the closing tag
-        if html[pos..].starts_with(close_tag) {
-            return (nodes, pos + close_tag.len());
-        }
-
         if bytes[pos] == b'<' {
             // Comment or doctype
             if pos + 1 < bytes.len() && bytes[pos + 1] == b'!' {
@@ -527,18 +1109,22 @@ fn parse_children_until(html: &str, start: usize, close_tag: &str) -> (Vec<HtmlN
                 continue;
             }
 
-            // Closing tag for something else? Skip it.
+            // Closing tag: ours, an enclosing element's, or a stray one to skip
             if pos + 1 < bytes.len() && bytes[pos + 1] == b'/' {
-                if let Some(end) = html[pos..].find('>') {
-                    pos = pos + end + 1;
-                } else {
-                    pos = bytes.len();
+                let end = html[pos..].find('>').map_or(bytes.len(), |end| pos + end + 1);
+                let name = html[pos + 2..end].trim_end_matches('>').trim().to_lowercase();
+                if name == tag_name {
+                    return (nodes, end, true);
+                }
+                if state.open.contains(&name) {
+                    return (nodes, pos, false);
                 }
+                pos = end;
                 continue;
             }
 
             // Opening tag — try to parse as child element
-            if let Some((elem, end_pos)) = parse_element(html, pos) {
+            if let Some((elem, end_pos)) = parse_element(html, pos, state) {
                 nodes.push(HtmlNode::Element(elem));
                 pos = end_pos;
             } else {
@@ -561,8 +1147,8 @@ fn parse_children_until(html: &str, start: usize, close_tag: &str) -> (Vec<HtmlN
         }
     }
 
-    // Ran out of input without finding close tag — return what we have
-    (nodes, pos)
+    // Ran out of input without finding close tag
+    (nodes, pos, false)
 }
 
 /// Parse attributes from a tag's attribute string.
@@ -591,15 +1177,22 @@ fn parse_attrs(attr_str: &str) -> Vec<(String, String)> {
         while pos < bytes.len() && bytes[pos] != b'=' && !(bytes[pos] as char).is_whitespace() && bytes[pos] != b'>' {
             pos += 1;
         }
-        let name = s[name_start..pos].to_string();
+        let name = normalize_attr_name(&s[name_start..pos]);
         if name.is_empty() {
             pos += 1;
             continue;
         }
 
-        // Check for = (attribute value)
-        if pos < bytes.len() && bytes[pos] == b'=' {
-            pos += 1; // skip '='
+        // Check for = (attribute value), allowing whitespace around it
+        let mut after_name = pos;
+        while after_name < bytes.len() && (bytes[after_name] as char).is_whitespace() {
+            after_name += 1;
+        }
+        if after_name < bytes.len() && bytes[after_name] == b'=' {
+            pos = after_name + 1; // skip '='
+            while pos < bytes.len() && (bytes[pos] as char).is_whitespace() {
+                pos += 1;
+            }
             if pos < bytes.len() && (bytes[pos] == b'"' || bytes[pos] == b'\'') {
                 let quote = bytes[pos];
                 pos += 1;
@@ -629,9 +1222,30 @@ fn parse_attrs(attr_str: &str) -> Vec<(String, String)> {
     attrs
 }
 
+/// `v-*` directive names match case-insensitively (`V-SHOW` is `v-show`), as
+/// HTML attribute names do. The argument after `:` or `.` keeps its case, and
+/// so does everything else: `@Click` listens for a `Click` event, not `click`.
+fn normalize_attr_name(name: &str) -> String {
+    if !name.get(..2).is_some_and(|p| p.eq_ignore_ascii_case("v-")) {
+        return name.to_string();
+    }
+    let end = name.find([':', '.']).unwrap_or(name.len());
+    format!("{}{}", name[..end].to_ascii_lowercase(), &name[end..])
+}
+
 /// Walk the HTML tree and collect bindings with positional paths.
 /// If the HTML contains `<body>`, paths are relative to body's children.
+///
+/// Bindings competing for the same element property are settled first (see
+/// [`settle_competing_bindings`]), so each property gets a single effect.
 pub fn walk_template(html: &str, reactive_names: &[&str]) -> TemplateBindings {
+    let mut bindings = walk_template_as_written(html, reactive_names);
+    settle_competing_bindings(&mut bindings);
+    bindings
+}
+
+/// [`walk_template`] without settling competing bindings.
+fn walk_template_as_written(html: &str, reactive_names: &[&str]) -> TemplateBindings {
     let nodes = parse_html(html);
 
     let mut bindings = TemplateBindings {
@@ -643,6 +1257,9 @@ pub fn walk_template(html: &str, reactive_names: &[&str]) -> TemplateBindings {
         classes: Vec::new(),
         styles: Vec::new(),
         models: Vec::new(),
+        fors: Vec::new(),
+        attrs: Vec::new(),
+        cloaks: Vec::new(),
     };
 
     // Check if there's a <body> element — if so, walk its children
@@ -686,8 +1303,24 @@ fn walk_children(
     walk_nodes(children, path, reactive_names, bindings, &mut element_index, None);
 }
 
+/// Whether `tag` (lowercased) is `<Transition>` or `<TransitionGroup>`, which
+/// render no element of their own.
+fn is_transition_wrapper(tag: &str) -> bool {
+    matches!(tag, "transition" | "transitiongroup" | "transition-group")
+}
+
+/// Whether `elem` is a `<template v-if>` / `<template v-for>` (or `v-else-if`,
+/// `v-else`, `v-show`) group: it renders only its children, and its
+/// directive applies to each of them.
+fn is_template_group(elem: &HtmlElement) -> bool {
+    elem.tag == "template"
+        && elem.attrs.iter().any(|(name, _)| {
+            matches!(name.as_str(), "v-if" | "v-else-if" | "v-else" | "v-show" | "v-for")
+        })
+}
+
 /// Internal walker that shares a mutable element index counter.
-/// `transition` carries the `name` attribute from a parent `<Transition>` wrapper.
+/// `transition` carries the settings of a parent `<Transition>` wrapper.
 /// When inside a `<Transition>`, child elements inherit the parent index counter
 /// and path — the `<Transition>` tag itself does NOT count as a DOM element.
 fn walk_nodes(
@@ -696,20 +1329,40 @@ fn walk_nodes(
     reactive_names: &[&str],
     bindings: &mut TemplateBindings,
     element_index: &mut usize,
-    transition: Option<&str>,
+    transition: Option<&Transition>,
 ) {
     for node in children {
         match node {
             HtmlNode::Element(elem) => {
-                if elem.tag == "transition" {
-                    // <Transition> is not a real DOM element — skip it in the path.
-                    // Extract the `name` attribute to pass to children; default to "v".
-                    let name = elem.attrs.iter()
-                        .find(|(k, _)| k == "name")
-                        .map(|(_, v)| v.as_str())
-                        .unwrap_or("v");
+                if is_transition_wrapper(&elem.tag) {
+                    // <Transition>/<TransitionGroup> is not a real DOM element — skip it
+                    // in the path. Its `name`, `appear` and `duration` apply to the children.
+                    let settings = Transition::from_attrs(&elem.attrs);
                     // Recurse into children, sharing the same index counter and path
-                    walk_nodes(&elem.children, path, reactive_names, bindings, element_index, Some(name));
+                    walk_nodes(&elem.children, path, reactive_names, bindings, element_index, Some(&settings));
+                    continue;
+                }
+                if is_template_group(elem) {
+                    // Likewise for <template v-if>/<template v-for>; each child
+                    // element takes the group's directives as its own
+                    let children: Vec<HtmlNode> = elem
+                        .children
+                        .iter()
+                        .map(|child| match child {
+                            HtmlNode::Element(child) => {
+                                let mut child = child.clone();
+                                child.attrs.splice(0..0, elem.attrs.iter().cloned());
+                                HtmlNode::Element(child)
+                            }
+                            text => text.clone(),
+                        })
+                        .collect();
+                    walk_nodes(&children, path, reactive_names, bindings, element_index, transition);
+                    continue;
+                }
+                if elem.attrs.iter().any(|(name, _)| name == "v-raw") {
+                    // Left to another templating system: no bindings on or inside it
+                    *element_index += 1;
                     continue;
                 }
 
@@ -729,14 +1382,24 @@ fn walk_nodes(
                         bindings.shows.push(ShowBinding {
                             path: current_path.clone(),
                             expr: value.clone(),
-                            transition: transition.map(|s| s.to_string()),
+                            transition: transition.cloned(),
                         });
                     }
                     if name == "v-else" {
                         bindings.shows.push(ShowBinding {
                             path: current_path.clone(),
                             expr: "true".to_string(),
-                            transition: transition.map(|s| s.to_string()),
+                            transition: transition.cloned(),
+                        });
+                    }
+                    if name == "v-for" {
+                        let key = elem.attrs.iter()
+                            .find(|(k, _)| k == ":key" || k == "v-bind:key")
+                            .map(|(_, v)| v.clone());
+                        bindings.fors.push(ForBinding {
+                            path: current_path.clone(),
+                            expr: value.clone(),
+                            key,
                         });
                     }
                     if name == "v-html" {
@@ -763,12 +1426,25 @@ fn walk_nodes(
                             expr: value.clone(),
                         });
                     }
+                    if name == "v-cloak" {
+                        bindings.cloaks.push(current_path.clone());
+                    }
                     if name == "v-model" {
                         bindings.models.push(ModelBinding {
                             path: current_path.clone(),
                             signal_name: value.clone(),
                         });
                     }
+                    if name.starts_with(|c: char| c.is_ascii_alphabetic())
+                        && !name.starts_with("v-")
+                        && has_reactive_mustache(value, reactive_names)
+                    {
+                        bindings.attrs.push(AttrTemplateBinding {
+                            path: current_path.clone(),
+                            name: name.clone(),
+                            template: value.clone(),
+                        });
+                    }
                 }
 
                 // Check if this element has text children with reactive {{ expr }}
@@ -814,14 +1490,7 @@ fn check_text_bindings(
         return;
     }
 
-    // Check if any {{ expr }} contains a reactive name
-    let re = Regex::new(r"\{\{\s*([^}]+?)\s*\}\}").unwrap();
-    let has_reactive = re.captures_iter(&full_text).any(|cap| {
-        let expr = cap[1].trim();
-        is_reactive_expr(expr, reactive_names)
-    });
-
-    if has_reactive {
+    if has_reactive_mustache(&full_text, reactive_names) {
         bindings.texts.push(TextBinding {
             path: path.to_vec(),
             template: full_text.trim().to_string(),
@@ -830,25 +1499,15 @@ fn check_text_bindings(
 }
 
 /// Check if an expression references any reactive name.
+/// Whether any `{{ expr }}` in `text` references a reactive name.
+fn has_reactive_mustache(text: &str, reactive_names: &[&str]) -> bool {
+    let re = Regex::new(r"\{\{\s*([^}]+?)\s*\}\}").unwrap();
+    let mut exprs = re.captures_iter(text);
+    exprs.any(|cap| is_reactive_expr(cap[1].trim(), reactive_names))
+}
+
 fn is_reactive_expr(expr: &str, reactive_names: &[&str]) -> bool {
-    reactive_names.iter().any(|name| {
-        let bytes = expr.as_bytes();
-        let name_bytes = name.as_bytes();
-        let name_len = name.len();
-        let mut i = 0;
-        while i + name_len <= bytes.len() {
-            if &bytes[i..i + name_len] == name_bytes {
-                let before_ok = i == 0 || !(bytes[i - 1] as char).is_alphanumeric() && bytes[i - 1] != b'_';
-                let after_ok = i + name_len == bytes.len()
-                    || !(bytes[i + name_len] as char).is_alphanumeric() && bytes[i + name_len] != b'_';
-                if before_ok && after_ok {
-                    return true;
-                }
-            }
-            i += 1;
-        }
-        false
-    })
+    reactive_names.iter().any(|name| references_name(expr, name))
 }
 
 // ── Stage C: Positional JS Code Generation ──────────────────────────────────
@@ -856,27 +1515,75 @@ fn is_reactive_expr(expr: &str, reactive_names: &[&str]) -> bool {
 /// Transform a script expression from Vue-style to signal JS.
 ///
 /// Converts `x` → `x.value` and `x.value` stays as-is for reactive names.
+/// Only variable references are rewritten: `e.target.name`, `{ name: 1 }` and
+/// `'name'` are left alone even when `name` is reactive.
 fn transform_expr(expr: &str, reactive_names: &[&str]) -> String {
-    let mut result = expr.to_string();
+    let mut result = String::with_capacity(expr.len());
+    let mut last = 0;
+    for span in ident::variable_refs(expr) {
+        let Some(end) = reactive_names.iter().filter_map(|name| ident::name_end(expr, span, name)).max() else {
+            continue;
+        };
+        let rest = &expr[end..];
+        let has_value = rest.starts_with(".value")
+            && !rest[".value".len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$');
+        if !has_value {
+            result.push_str(&expr[last..end]);
+            result.push_str(".value");
+            last = end;
+        }
+    }
+    result.push_str(&expr[last..]);
+    result
+}
+
+/// The `var name = V.computed(...)` line of a computed: an expression body is
+/// returned, a block body is emitted as written.
+fn computed_js(c: &ComputedDecl, reactive_names: &[&str]) -> String {
+    let body = transform_expr(&c.body, reactive_names);
+    if c.block {
+        format!("  var {} = V.computed(function() {{ {} }});\n", c.name, body)
+    } else {
+        format!("  var {} = V.computed(function() {{ return {}; }});\n", c.name, body)
+    }
+}
 
-    for name in reactive_names {
-        // Replace `name` with `name.value` but not if already `name.value`
-        let dot_value = format!("{}.value", name);
-        // First, temporarily replace existing .value references
-        let placeholder = format!("__PLACEHOLDER_{name}__");
-        result = result.replace(&dot_value, &placeholder);
+/// `var` declarations of literal constants, values written as JSON.
+fn constants_js(constants: &[ConstDecl]) -> String {
+    constants
+        .iter()
+        .map(|c| format!("  var {} = {};\n", c.name, c.value.to_string().replace('<', "\\u003c")))
+        .collect()
+}
 
-        // Now replace bare name references (word boundary)
-        let re = Regex::new(&format!(r"\b{}\b", regex::escape(name))).unwrap();
-        result = re.replace_all(&result, &dot_value).to_string();
+/// A function declaration, `async` when the source one was.
+fn function_js(f: &FunctionDecl, reactive_names: &[&str]) -> String {
+    let body = transform_expr(&f.body, reactive_names);
+    let keyword = if f.is_async { "async function" } else { "function" };
+    format!("  {} {}({}) {{ {} }}\n", keyword, f.name, f.params, body)
+}
 
-        // Restore the placeholders (they would have become name.value.value)
-        let double_value = format!("{}.value.value", name);
-        result = result.replace(&double_value, &dot_value);
-        result = result.replace(&placeholder, &dot_value);
+/// The listener for an event handler: a declared function by name, otherwise
+/// the inline statement wrapped in a function (an `async` one if it awaits).
+fn handler_js(handler: &str, analysis: &ScriptAnalysis, reactive_names: &[&str]) -> String {
+    if analysis.functions.iter().any(|f| f.name == handler) {
+        return handler.to_string();
     }
+    let body = transform_expr(handler, reactive_names);
+    if references_name(handler, "await") {
+        format!("async function() {{ {} }}", body)
+    } else {
+        format!("function() {{ {} }}", body)
+    }
+}
 
-    result
+/// The value assigned to `innerHTML` for a `v-html` expression.
+fn html_value(expr: String, sanitize_html: bool) -> String {
+    if sanitize_html {
+        format!("V.sanitize({expr})")
+    } else {
+        expr
+    }
 }
 
 /// Convert a text template like `"Count: {{ count }}"` to a JS expression
@@ -888,28 +1595,29 @@ fn template_to_js_expr(template: &str, reactive_names: &[&str]) -> String {
     while let Some(start) = rest.find("{{") {
         let before = &rest[..start];
         if !before.is_empty() {
-            // Escape single quotes in literal text
-            let escaped = before.replace('\\', "\\\\").replace('\'', "\\'");
-            parts.push(format!("'{}'", escaped));
+            parts.push(text_literal(before));
         }
         let after_open = &rest[start + 2..];
         if let Some(end) = after_open.find("}}") {
             let expr = after_open[..end].trim();
             let transformed = transform_expr(expr, reactive_names);
-            parts.push(transformed);
+            // Keep `{{ n + 1 }}` arithmetic from turning into string concatenation
+            if expr.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '.')) {
+                parts.push(transformed);
+            } else {
+                parts.push(format!("({transformed})"));
+            }
             rest = &after_open[end + 2..];
         } else {
             // No closing }} — treat rest as literal
-            let escaped = rest.replace('\\', "\\\\").replace('\'', "\\'");
-            parts.push(format!("'{}'", escaped));
+            parts.push(text_literal(rest));
             rest = "";
             break;
         }
     }
 
     if !rest.is_empty() {
-        let escaped = rest.replace('\\', "\\\\").replace('\'', "\\'");
-        parts.push(format!("'{}'", escaped));
+        parts.push(text_literal(rest));
     }
 
     if parts.is_empty() {
@@ -919,6 +1627,18 @@ fn template_to_js_expr(template: &str, reactive_names: &[&str]) -> String {
     }
 }
 
+/// A JS string literal for literal template text, with the HTML entities
+/// the source may contain (`&amp;`, `&quot;`...) decoded first.
+fn text_literal(text: &str) -> String {
+    let decoded = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    format!("'{}'", decoded.replace('\\', "\\\\").replace('\'', "\\'").replace('\n', "\\n"))
+}
+
 /// A single `:class` binding item: either a conditional toggle or a static class name.
 #[derive(Debug, Clone, PartialEq)]
 enum ClassItem {
@@ -928,22 +1648,140 @@ enum ClassItem {
     Static(String),
 }
 
-/// Parse a `:class` expression, dispatching based on syntax:
-/// - `{ ... }` → object syntax (existing)
-/// - `[{ ... }, 'static']` → array syntax (new)
-fn parse_class_expr(expr: &str) -> Vec<ClassItem> {
-    let trimmed = expr.trim();
-    if trimmed.starts_with('[') {
-        parse_class_array(trimmed)
-    } else if trimmed.starts_with('{') {
-        parse_class_object(trimmed)
-            .into_iter()
-            .map(|(name, cond)| ClassItem::Toggle(name, cond))
-            .collect()
-    } else {
-        Vec::new()
+/// Settle bindings that would otherwise emit effects overwriting each other:
+///
+/// - All `:class` items of an element are merged; the conditions of a class
+///   toggled more than once are OR'd together, and a class also listed
+///   statically stays on.
+/// - `{{ }}` text is dropped from an element whose content `v-text` or
+///   `v-html` sets ([`binding_conflicts`] reports it).
+fn settle_competing_bindings(bindings: &mut TemplateBindings) {
+    let mut classes: Vec<ClassBinding> = Vec::new();
+    for binding in std::mem::take(&mut bindings.classes) {
+        let Some(pos) = classes.iter().position(|b| b.path == binding.path) else {
+            classes.push(binding);
+            continue;
+        };
+        // Combined as an array; flattened by `merge_class_items` below
+        let mut items = parse_class_expr(&classes[pos].expr);
+        items.extend(parse_class_expr(&binding.expr));
+        classes[pos].expr = class_items_expr(&items);
     }
-}
+    for binding in &mut classes {
+        let items = parse_class_expr(&binding.expr);
+        let merged = merge_class_items(&items);
+        if merged != items {
+            binding.expr = class_items_expr(&merged);
+        }
+    }
+    bindings.classes = classes;
+
+    let content_paths: std::collections::HashSet<&Vec<usize>> = bindings
+        .text_directives
+        .iter()
+        .map(|b| &b.path)
+        .chain(bindings.htmls.iter().map(|b| &b.path))
+        .collect();
+    let texts = std::mem::take(&mut bindings.texts);
+    bindings.texts = texts.into_iter().filter(|t| !content_paths.contains(&t.path)).collect();
+}
+
+/// Fold repeated class names into one item, keeping first-seen order.
+fn merge_class_items(items: &[ClassItem]) -> Vec<ClassItem> {
+    let mut merged: Vec<ClassItem> = Vec::new();
+    for item in items {
+        let name = match item {
+            ClassItem::Toggle(name, _) | ClassItem::Static(name) => name,
+        };
+        let existing = merged.iter_mut().find(|m| matches!(m, ClassItem::Toggle(n, _) | ClassItem::Static(n) if n == name));
+        match (existing, item) {
+            (None, _) => merged.push(item.clone()),
+            (Some(ClassItem::Toggle(_, cond)), ClassItem::Toggle(_, other)) => {
+                *cond = format!("({cond}) || ({other})");
+            }
+            (Some(existing), ClassItem::Static(_)) => *existing = item.clone(),
+            (Some(ClassItem::Static(_)), ClassItem::Toggle(..)) => {}
+        }
+    }
+    merged
+}
+
+/// Write class items back as `:class` array syntax.
+fn class_items_expr(items: &[ClassItem]) -> String {
+    let parts: Vec<String> = items
+        .iter()
+        .map(|item| match item {
+            ClassItem::Toggle(name, cond) => format!("{{ '{name}': {cond} }}"),
+            ClassItem::Static(name) => format!("'{name}'"),
+        })
+        .collect();
+    format!("[{}]", parts.join(", "))
+}
+
+/// Bindings in `template_html` that set the same element content, e.g.
+/// `<p v-text="msg">{{ msg }}</p>`. [`walk_template`] keeps the directive and
+/// drops the interpolation; these are the warnings to show for it.
+pub fn binding_conflicts(script_setup: &str, template_html: &str) -> Vec<String> {
+    let analysis = analyze_script(script_setup);
+    let reactive_names: Vec<&str> = analysis
+        .signals
+        .iter()
+        .map(|s| s.name.as_str())
+        .chain(analysis.computeds.iter().map(|c| c.name.as_str()))
+        .collect();
+    let bindings = walk_template_as_written(template_html, &reactive_names);
+
+    let mut conflicts: Vec<(Vec<usize>, String)> = Vec::new();
+    for text in &bindings.texts {
+        for directive in bindings.text_directives.iter().filter(|d| d.path == text.path) {
+            conflicts.push((
+                text.path.clone(),
+                format!("v-text=\"{}\" and {} both set its text; the interpolation is ignored", directive.expr, text.template),
+            ));
+        }
+        for html in bindings.htmls.iter().filter(|h| h.path == text.path) {
+            conflicts.push((
+                text.path.clone(),
+                format!("v-html=\"{}\" and {} both set its content; the interpolation is ignored", html.expr, text.template),
+            ));
+        }
+    }
+    for directive in &bindings.text_directives {
+        for html in bindings.htmls.iter().filter(|h| h.path == directive.path) {
+            conflicts.push((
+                directive.path.clone(),
+                format!("v-text=\"{}\" and v-html=\"{}\" both set its content", directive.expr, html.expr),
+            ));
+        }
+    }
+
+    let paths: Vec<Vec<usize>> = conflicts.iter().map(|(path, _)| path.clone()).collect();
+    let offsets = find_element_offsets(template_html, &paths);
+    conflicts
+        .into_iter()
+        .map(|(path, msg)| match offsets.get(&path) {
+            Some(&offset) => format!("<{}> {msg}", tag_name_at(&template_html[offset..])),
+            None => msg,
+        })
+        .collect()
+}
+
+/// Parse a `:class` expression, dispatching based on syntax:
+/// - `{ ... }` → object syntax (existing)
+/// - `[{ ... }, 'static']` → array syntax (new)
+fn parse_class_expr(expr: &str) -> Vec<ClassItem> {
+    let trimmed = expr.trim();
+    if trimmed.starts_with('[') {
+        parse_class_array(trimmed)
+    } else if trimmed.starts_with('{') {
+        parse_class_object(trimmed)
+            .into_iter()
+            .map(|(name, cond)| ClassItem::Toggle(name, cond))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
 
 /// Parse a `:class` array expression like `[{ active: isActive }, 'bold']`.
 fn parse_class_array(expr: &str) -> Vec<ClassItem> {
@@ -1088,6 +1926,177 @@ fn parse_style_object(expr: &str) -> Vec<(String, String)> {
     pairs
 }
 
+/// When a page wires its bindings to the DOM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Hydration {
+    /// As soon as the page script runs.
+    #[default]
+    Eager,
+    /// Per top-level element (the first segment of a binding path), once it
+    /// scrolls into view or gets a pointer or focus event (`V.hydrate()`), so
+    /// below-the-fold widgets cost nothing at load. The server-rendered HTML
+    /// is the same either way.
+    Lazy,
+}
+
+/// How the signal generators name bound elements: the variables of
+/// [`generate_signals`], the `<!--v:ID-->` anchors of
+/// [`generate_signals_comment`] and the block lookups of
+/// [`generate_signals_compile`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ElementNames {
+    /// After the element's path, `_e0_2_1` for `[0, 2, 1]`, so adding or
+    /// removing a binding leaves the lines of unrelated ones unchanged.
+    #[default]
+    Path,
+    /// Numbered in document order (`_e0`, `_e1`, …), the earlier scheme: one
+    /// binding added near the top renames every variable after it.
+    Ordinal,
+}
+
+impl ElementNames {
+    /// The variable for the element at `path`, the `ordinal`-th one looked up.
+    fn var(self, path: &[usize], ordinal: usize) -> String {
+        match self {
+            ElementNames::Path => {
+                let segments: Vec<String> = path.iter().map(usize::to_string).collect();
+                format!("_e{}", segments.join("_"))
+            }
+            ElementNames::Ordinal => format!("_e{ordinal}"),
+        }
+    }
+
+    /// The id of the element at `path`, the `ordinal`-th one looked up:
+    /// `0_2_1` for `[0, 2, 1]`, or the ordinal itself.
+    fn key(self, path: &[usize], ordinal: usize) -> String {
+        match self {
+            ElementNames::Path => path.iter().map(usize::to_string).collect::<Vec<_>>().join("_"),
+            ElementNames::Ordinal => ordinal.to_string(),
+        }
+    }
+
+    /// [`Self::key`] as a JS property key, quoted unless it is a number.
+    fn index(self, path: &[usize], ordinal: usize) -> String {
+        match self {
+            ElementNames::Path => format!("'{}'", self.key(path, ordinal)),
+            ElementNames::Ordinal => ordinal.to_string(),
+        }
+    }
+}
+
+/// Settings shared by the signal generators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalOptions {
+    /// The runtime's global, bound to `V` in the page script.
+    pub global_name: String,
+    /// Pass `v-html` values through the runtime's `V.sanitize()` before they
+    /// reach `innerHTML`.
+    pub sanitize_html: bool,
+    /// When bindings are wired to the DOM.
+    pub hydration: Hydration,
+    /// How bound elements are named.
+    pub element_names: ElementNames,
+}
+
+impl Default for SignalOptions {
+    fn default() -> Self {
+        Self {
+            global_name: "Van".to_string(),
+            sanitize_html: false,
+            hydration: Hydration::default(),
+            element_names: ElementNames::default(),
+        }
+    }
+}
+
+impl std::str::FromStr for Hydration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eager" => Ok(Self::Eager),
+            "lazy" => Ok(Self::Lazy),
+            other => Err(format!("unknown hydration \"{other}\" (expected eager or lazy)")),
+        }
+    }
+}
+
+impl std::str::FromStr for ElementNames {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(Self::Path),
+            "ordinal" => Ok(Self::Ordinal),
+            other => Err(format!("unknown element_names \"{other}\" (expected path or ordinal)")),
+        }
+    }
+}
+
+/// The DOM wiring statements of a page script, each filed under the
+/// top-level element its binding sits in when hydrating lazily.
+struct Wiring {
+    lazy: bool,
+    eager: String,
+    groups: std::collections::BTreeMap<usize, String>,
+    /// `v-cloak` removals, run after everything else.
+    reveal: String,
+}
+
+impl Wiring {
+    fn new(hydration: Hydration) -> Self {
+        Wiring { lazy: hydration == Hydration::Lazy, eager: String::new(), groups: Default::default(), reveal: String::new() }
+    }
+
+    /// Where a statement for the element at `path` goes.
+    fn at(&mut self, path: &[usize]) -> &mut String {
+        match path.first() {
+            Some(&group) if self.lazy => self.groups.entry(group).or_default(),
+            _ => &mut self.eager,
+        }
+    }
+
+    /// Where a statement uncloaking an element goes.
+    fn reveal(&mut self) -> &mut String {
+        &mut self.reveal
+    }
+
+    /// The statements as page script: each group in a function that
+    /// `V.hydrate()` runs once the element `root(group)` names (or the
+    /// top-level element holding it) is reached, then the ungated ones, then
+    /// the uncloaking (see [`reveal_finally`]).
+    fn finish(self, root: impl Fn(usize) -> String) -> String {
+        let mut js = String::new();
+        for (group, body) in self.groups {
+            js.push_str(&format!("  V.hydrate({}, function() {{\n", root(group)));
+            for line in body.lines() {
+                js.push_str(&format!("  {line}\n"));
+            }
+            js.push_str("  });\n");
+        }
+        js.push_str(&self.eager);
+        reveal_finally(js, &self.reveal)
+    }
+}
+
+/// `wiring` with `reveal` (the `v-cloak` removals) in a `finally`, so a
+/// binding that throws can't leave its part of the page hidden.
+fn reveal_finally(wiring: String, reveal: &str) -> String {
+    if reveal.is_empty() {
+        return wiring;
+    }
+    let mut js = String::from("  try {\n");
+    for line in wiring.lines() {
+        js.push_str(&format!("  {line}\n"));
+    }
+    js.push_str("  } finally {\n");
+    for line in reveal.lines() {
+        js.push_str(&format!("  {line}\n"));
+    }
+    js.push_str("  }\n");
+    js
+}
+
 /// Collect all unique path prefixes that need JS variables.
 /// Returns a sorted, deduplicated list of paths that are either:
 /// - Direct binding targets (have an event, text, or show binding)
@@ -1106,6 +2115,8 @@ fn collect_required_paths(bindings: &TemplateBindings) -> Vec<Vec<usize>> {
         .chain(bindings.classes.iter().map(|b| &b.path))
         .chain(bindings.styles.iter().map(|b| &b.path))
         .chain(bindings.models.iter().map(|b| &b.path))
+        .chain(bindings.attrs.iter().map(|b| &b.path))
+        .chain(bindings.cloaks.iter())
         .collect();
 
     for path in &all_binding_paths {
@@ -1120,13 +2131,25 @@ fn collect_required_paths(bindings: &TemplateBindings) -> Vec<Vec<usize>> {
 
 /// Generate the signal JS for a page. Returns `None` if no reactive code found.
 ///
-/// `module_code` contains resolved .ts/.js content (already transpiled to JS) to be
-/// inlined before signal declarations. Each entry is wrapped in an IIFE.
-pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[String], global_name: &str) -> Option<String> {
+/// `modules` contains resolved .ts/.js content (already transpiled to JS) to be
+/// inlined before signal declarations. Each entry is wrapped in an IIFE whose
+/// exports are bound to the importers' local names.
+///
+/// See [`SignalOptions`] for the settings, which all generators share. With
+/// [`Hydration::Lazy`], the lookups and wiring under each top-level element
+/// wait in a `V.hydrate()` gate for it to be seen or touched.
+pub fn generate_signals(
+    script_setup: &str,
+    template_html: &str,
+    modules: &[ModuleCode],
+    options: &SignalOptions,
+) -> Option<String> {
+    let &SignalOptions { ref global_name, sanitize_html, hydration, element_names } = options;
     let analysis = analyze_script(script_setup);
 
     // If nothing reactive, skip
-    if analysis.signals.is_empty() && analysis.computeds.is_empty() {
+    let stores = module::store_fields(modules);
+    if analysis.signals.is_empty() && analysis.computeds.is_empty() && stores.is_empty() {
         return None;
     }
 
@@ -1135,6 +2158,7 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
         .iter()
         .map(|s| s.name.as_str())
         .chain(analysis.computeds.iter().map(|c| c.name.as_str()))
+        .chain(stores.iter().map(|f| f.name.as_str()))
         .collect();
 
     let bindings = walk_template(template_html, &reactive_names);
@@ -1146,14 +2170,11 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
     js.push_str("(function() {\n");
     js.push_str(&format!("  var V = {};\n", global_name));
 
-    // Inlined module code
-    for (i, code) in module_code.iter().enumerate() {
-        js.push_str(&format!(
-            "  var __mod_{} = (function() {{ {} }})();\n",
-            i,
-            code.trim()
-        ));
-    }
+    // Inlined module code and the importers' bindings
+    js.push_str(&module::modules_js(modules));
+
+    // Literal constants, which signal initial values may refer to
+    js.push_str(&constants_js(&analysis.constants));
 
     // Signals
     for s in &analysis.signals {
@@ -1165,20 +2186,17 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
 
     // Computeds
     for c in &analysis.computeds {
-        let body = transform_expr(&c.body, &reactive_names);
-        js.push_str(&format!(
-            "  var {} = V.computed(function() {{ return {}; }});\n",
-            c.name, body
-        ));
+        js.push_str(&computed_js(c, &reactive_names));
     }
 
     // Functions
     for f in &analysis.functions {
-        let body = transform_expr(&f.body, &reactive_names);
-        js.push_str(&format!(
-            "  function {}({}) {{ {} }}\n",
-            f.name, f.params, body
-        ));
+        js.push_str(&function_js(f, &reactive_names));
+    }
+
+    // Other top-level statements, in source order
+    for stmt in &analysis.residual {
+        js.push_str(&format!("  {}\n", transform_expr(stmt, &reactive_names)));
     }
 
     // Watch declarations
@@ -1192,17 +2210,16 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
 
     // Positional DOM element variables
     if !required_paths.is_empty() {
-        js.push_str("\n");
+        js.push('\n');
         // Build a map of path → variable name
         let mut path_vars: std::collections::HashMap<Vec<usize>, String> = std::collections::HashMap::new();
-        let mut var_counter = 0;
+        let mut wiring = Wiring::new(hydration);
 
         // Root is document.body
         js.push_str("  var _r = document.body;\n");
 
-        for path in &required_paths {
-            let var_name = format!("_e{}", var_counter);
-            var_counter += 1;
+        for (ordinal, path) in required_paths.iter().enumerate() {
+            let var_name = element_names.var(path, ordinal);
 
             // Parent variable
             let parent_var = if path.len() == 1 {
@@ -1213,7 +2230,14 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
             };
 
             let index = path[path.len() - 1];
-            js.push_str(&format!(
+            // Top-level elements are found up front, as the lazy gates watch
+            // them, and so are the ancestors of `v-cloak` elements, uncloaked
+            // outside the gates
+            let eager = path.len() == 1
+                || hydration == Hydration::Eager
+                || bindings.cloaks.iter().any(|cloak| cloak.starts_with(path));
+            let out = if eager { &mut js } else { wiring.at(path) };
+            out.push_str(&format!(
                 "  var {} = {}.children[{}];\n",
                 var_name, parent_var, index
             ));
@@ -1224,13 +2248,8 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
         // Event bindings
         for binding in &bindings.events {
             let var = path_vars.get(&binding.path).unwrap();
-            let handler_ref = if analysis.functions.iter().any(|f| f.name == binding.handler) {
-                binding.handler.clone()
-            } else {
-                let body = transform_expr(&binding.handler, &reactive_names);
-                format!("function() {{ {} }}", body)
-            };
-            js.push_str(&format!(
+            let handler_ref = handler_js(&binding.handler, &analysis, &reactive_names);
+            wiring.at(&binding.path).push_str(&format!(
                 "  {}.addEventListener('{}', {});\n",
                 var, binding.event, handler_ref
             ));
@@ -1240,7 +2259,7 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
         for binding in &bindings.texts {
             let var = path_vars.get(&binding.path).unwrap();
             let js_expr = template_to_js_expr(&binding.template, &reactive_names);
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ {}.textContent = {}; }});\n",
                 var, js_expr
             ));
@@ -1250,13 +2269,10 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
         for binding in &bindings.shows {
             let var = path_vars.get(&binding.path).unwrap();
             let transformed = transform_expr(&binding.expr, &reactive_names);
-            if let Some(ref name) = binding.transition {
-                js.push_str(&format!(
-                    "  V.effect(function() {{ V.transition({}, {}, '{}'); }});\n",
-                    var, transformed, name
-                ));
+            if let Some(ref transition) = binding.transition {
+                wiring.at(&binding.path).push_str(&transition.effect_js(var, &transformed));
             } else {
-                js.push_str(&format!(
+                wiring.at(&binding.path).push_str(&format!(
                     "  V.effect(function() {{ {}.style.display = {} ? '' : 'none'; }});\n",
                     var, transformed
                 ));
@@ -1266,8 +2282,8 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
         // v-html bindings
         for binding in &bindings.htmls {
             let var = path_vars.get(&binding.path).unwrap();
-            let transformed = transform_expr(&binding.expr, &reactive_names);
-            js.push_str(&format!(
+            let transformed = html_value(transform_expr(&binding.expr, &reactive_names), sanitize_html);
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ {}.innerHTML = {}; }});\n",
                 var, transformed
             ));
@@ -1277,7 +2293,7 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
         for binding in &bindings.text_directives {
             let var = path_vars.get(&binding.path).unwrap();
             let transformed = transform_expr(&binding.expr, &reactive_names);
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ {}.textContent = {}; }});\n",
                 var, transformed
             ));
@@ -1291,13 +2307,13 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
                 match item {
                     ClassItem::Toggle(class_name, cond_expr) => {
                         let transformed = transform_expr(cond_expr, &reactive_names);
-                        js.push_str(&format!(
-                            "  V.effect(function() {{ {}.classList.toggle('{}', !!{}); }});\n",
+                        wiring.at(&binding.path).push_str(&format!(
+                            "  V.effect(function() {{ {}.classList.toggle('{}', !!({})); }});\n",
                             var, class_name, transformed
                         ));
                     }
                     ClassItem::Static(class_name) => {
-                        js.push_str(&format!(
+                        wiring.at(&binding.path).push_str(&format!(
                             "  {}.classList.add('{}');\n",
                             var, class_name
                         ));
@@ -1312,26 +2328,46 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
             let pairs = parse_style_expr(&binding.expr);
             for (prop, val_expr) in &pairs {
                 let transformed = transform_expr(val_expr, &reactive_names);
-                js.push_str(&format!(
+                wiring.at(&binding.path).push_str(&format!(
                     "  V.effect(function() {{ {}.style.{} = {}; }});\n",
                     var, prop, transformed
                 ));
             }
         }
 
+        // Interpolated attribute bindings
+        for binding in &bindings.attrs {
+            let var = path_vars.get(&binding.path).unwrap();
+            wiring.at(&binding.path).push_str(&format!(
+                "  V.effect(function() {{ {}.setAttribute('{}', {}); }});\n",
+                var,
+                binding.name,
+                template_to_js_expr(&binding.template, &reactive_names)
+            ));
+        }
+
         // v-model bindings
         for binding in &bindings.models {
             let var = path_vars.get(&binding.path).unwrap();
             let signal = &binding.signal_name;
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ {}.value = {}.value; }});\n",
                 var, signal
             ));
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  {}.addEventListener('input', function(e) {{ {}.value = e.target.value; }});\n",
                 var, signal
             ));
         }
+
+        // v-cloak: reveal once everything above is wired, even if it threw
+        // (or, hydrating lazily, right away, since hidden content never
+        // scrolls into view)
+        for path in &bindings.cloaks {
+            let var = path_vars.get(path).unwrap();
+            wiring.reveal().push_str(&format!("  {}.removeAttribute('v-cloak');\n", var));
+        }
+        js.push_str(&wiring.finish(|group| path_vars[&vec![group]].clone()));
     }
 
     js.push_str("})();\n");
@@ -1340,16 +2376,22 @@ pub fn generate_signals(script_setup: &str, template_html: &str, module_code: &[
 }
 
 /// Compile mode: generate signal JS for ClientOnly blocks only.
-/// Uses comment anchor (`<!--client-only-->`) + DFS indexing instead of positional paths.
+/// Uses comment anchor (`<!--client-only-->`) and finds each bound element in
+/// its block by path (`_blocks[0]['0_2_1']`) or, with
+/// [`ElementNames::Ordinal`], by DFS index (`_blocks[0][5]`). With
+/// [`Hydration::Lazy`] the blocks are still collected up front; the wiring
+/// under each of a block's top-level elements is gated.
 pub fn generate_signals_compile(
     script_setup: &str,
     template_html: &str,
-    module_code: &[String],
-    global_name: &str,
+    modules: &[ModuleCode],
+    options: &SignalOptions,
 ) -> Option<String> {
+    let &SignalOptions { ref global_name, sanitize_html, hydration, element_names } = options;
     let analysis = analyze_script(script_setup);
 
-    if analysis.signals.is_empty() && analysis.computeds.is_empty() {
+    let stores = module::store_fields(modules);
+    if analysis.signals.is_empty() && analysis.computeds.is_empty() && stores.is_empty() {
         return None;
     }
 
@@ -1358,6 +2400,7 @@ pub fn generate_signals_compile(
         .iter()
         .map(|s| s.name.as_str())
         .chain(analysis.computeds.iter().map(|c| c.name.as_str()))
+        .chain(stores.iter().map(|f| f.name.as_str()))
         .collect();
 
     // Extract ClientOnly blocks from template
@@ -1383,6 +2426,8 @@ pub fn generate_signals_compile(
             || !b.classes.is_empty()
             || !b.styles.is_empty()
             || !b.models.is_empty()
+            || !b.attrs.is_empty()
+            || !b.cloaks.is_empty()
     });
 
     if !has_bindings {
@@ -1393,14 +2438,11 @@ pub fn generate_signals_compile(
     js.push_str("(function() {\n");
     js.push_str(&format!("  var V = {};\n", global_name));
 
-    // Inlined module code
-    for (i, code) in module_code.iter().enumerate() {
-        js.push_str(&format!(
-            "  var __mod_{} = (function() {{ {} }})();\n",
-            i,
-            code.trim()
-        ));
-    }
+    // Inlined module code and the importers' bindings
+    js.push_str(&module::modules_js(modules));
+
+    // Literal constants, which signal initial values may refer to
+    js.push_str(&constants_js(&analysis.constants));
 
     // Signals
     for s in &analysis.signals {
@@ -1412,20 +2454,17 @@ pub fn generate_signals_compile(
 
     // Computeds
     for c in &analysis.computeds {
-        let body = transform_expr(&c.body, &reactive_names);
-        js.push_str(&format!(
-            "  var {} = V.computed(function() {{ return {}; }});\n",
-            c.name, body
-        ));
+        js.push_str(&computed_js(c, &reactive_names));
     }
 
     // Functions
     for f in &analysis.functions {
-        let body = transform_expr(&f.body, &reactive_names);
-        js.push_str(&format!(
-            "  function {}({}) {{ {} }}\n",
-            f.name, f.params, body
-        ));
+        js.push_str(&function_js(f, &reactive_names));
+    }
+
+    // Other top-level statements, in source order
+    for stmt in &analysis.residual {
+        js.push_str(&format!("  {}\n", transform_expr(stmt, &reactive_names)));
     }
 
     // Watch declarations
@@ -1437,44 +2476,62 @@ pub fn generate_signals_compile(
         ));
     }
 
-    // DFS element collection helper + block discovery via comment anchors
-    js.push_str("\n");
-    js.push_str("  function _collectEls(el, out) {\n");
-    js.push_str("    out.push(el);\n");
-    js.push_str("    for (var i = 0; i < el.children.length; i++) _collectEls(el.children[i], out);\n");
+    // Element collection helper + block discovery via comment anchors: each
+    // block's elements in DFS order, or keyed by path (`'0_2_1'`)
+    js.push('\n');
+    match element_names {
+        ElementNames::Path => {
+            js.push_str("  function _collectEls(el, key, out) {\n");
+            js.push_str("    out[key] = el;\n");
+            js.push_str("    for (var i = 0; i < el.children.length; i++) _collectEls(el.children[i], key + '_' + i, out);\n");
+        }
+        ElementNames::Ordinal => {
+            js.push_str("  function _collectEls(el, out) {\n");
+            js.push_str("    out.push(el);\n");
+            js.push_str("    for (var i = 0; i < el.children.length; i++) _collectEls(el.children[i], out);\n");
+        }
+    }
     js.push_str("  }\n");
     js.push_str("  var _blocks = [];\n");
     js.push_str("  var _walker = document.createNodeIterator(document.body, NodeFilter.SHOW_COMMENT);\n");
     js.push_str("  var _n;\n");
     js.push_str("  while (_n = _walker.nextNode()) {\n");
     js.push_str("    if (_n.data === 'client-only') {\n");
-    js.push_str("      var _els = [], _sib = _n.nextSibling;\n");
+    match element_names {
+        ElementNames::Path => js.push_str("      var _els = {}, _i = 0, _sib = _n.nextSibling;\n"),
+        ElementNames::Ordinal => js.push_str("      var _els = [], _sib = _n.nextSibling;\n"),
+    }
     js.push_str("      while (_sib) {\n");
     js.push_str("        if (_sib.nodeType === 8 && _sib.data === '/client-only') break;\n");
-    js.push_str("        if (_sib.nodeType === 1) _collectEls(_sib, _els);\n");
+    match element_names {
+        ElementNames::Path => js.push_str("        if (_sib.nodeType === 1) _collectEls(_sib, '' + _i++, _els);\n"),
+        ElementNames::Ordinal => js.push_str("        if (_sib.nodeType === 1) _collectEls(_sib, _els);\n"),
+    }
     js.push_str("        _sib = _sib.nextSibling;\n");
     js.push_str("      }\n");
     js.push_str("      _blocks.push(_els);\n");
     js.push_str("    }\n");
     js.push_str("  }\n");
 
-    // Generate bindings per block using DFS indices
+    // Generate bindings per block
     for (block_idx, bindings) in all_block_bindings.iter().enumerate() {
         let b_var = format!("_blocks[{}]", block_idx);
 
-        // Convert positional paths to DFS indices within the block
-        let dfs_map = build_dfs_index_map(bindings, &blocks[block_idx]);
+        // Each bound element's key in its block, from its DFS index or path
+        let dfs_map: HashMap<Vec<usize>, String> = build_dfs_index_map(bindings, &blocks[block_idx])
+            .into_iter()
+            .map(|(path, index)| {
+                let key = element_names.index(&path, index);
+                (path, key)
+            })
+            .collect();
+        let mut wiring = Wiring::new(hydration);
 
         // Event bindings
         for binding in &bindings.events {
             if let Some(idx) = dfs_map.get(&binding.path) {
-                let handler_ref = if analysis.functions.iter().any(|f| f.name == binding.handler) {
-                    binding.handler.clone()
-                } else {
-                    let body = transform_expr(&binding.handler, &reactive_names);
-                    format!("function() {{ {} }}", body)
-                };
-                js.push_str(&format!(
+                let handler_ref = handler_js(&binding.handler, &analysis, &reactive_names);
+                wiring.at(&binding.path).push_str(&format!(
                     "  {}[{}].addEventListener('{}', {});\n",
                     b_var, idx, binding.event, handler_ref
                 ));
@@ -1485,24 +2542,32 @@ pub fn generate_signals_compile(
         for binding in &bindings.texts {
             if let Some(idx) = dfs_map.get(&binding.path) {
                 let js_expr = template_to_js_expr(&binding.template, &reactive_names);
-                js.push_str(&format!(
+                wiring.at(&binding.path).push_str(&format!(
                     "  V.effect(function() {{ {}[{}].textContent = {}; }});\n",
                     b_var, idx, js_expr
                 ));
             }
         }
 
+        // v-html bindings
+        for binding in &bindings.htmls {
+            if let Some(idx) = dfs_map.get(&binding.path) {
+                let transformed = html_value(transform_expr(&binding.expr, &reactive_names), sanitize_html);
+                wiring.at(&binding.path).push_str(&format!(
+                    "  V.effect(function() {{ {}[{}].innerHTML = {}; }});\n",
+                    b_var, idx, transformed
+                ));
+            }
+        }
+
         // Show bindings
         for binding in &bindings.shows {
             if let Some(idx) = dfs_map.get(&binding.path) {
                 let transformed = transform_expr(&binding.expr, &reactive_names);
-                if let Some(ref name) = binding.transition {
-                    js.push_str(&format!(
-                        "  V.effect(function() {{ V.transition({}[{}], {}, '{}'); }});\n",
-                        b_var, idx, transformed, name
-                    ));
+                if let Some(ref transition) = binding.transition {
+                    wiring.at(&binding.path).push_str(&transition.effect_js(&format!("{b_var}[{idx}]"), &transformed));
                 } else {
-                    js.push_str(&format!(
+                    wiring.at(&binding.path).push_str(&format!(
                         "  V.effect(function() {{ {}[{}].style.display = {} ? '' : 'none'; }});\n",
                         b_var, idx, transformed
                     ));
@@ -1518,13 +2583,13 @@ pub fn generate_signals_compile(
                     match item {
                         ClassItem::Toggle(class_name, cond_expr) => {
                             let transformed = transform_expr(cond_expr, &reactive_names);
-                            js.push_str(&format!(
-                                "  V.effect(function() {{ {}[{}].classList.toggle('{}', !!{}); }});\n",
+                            wiring.at(&binding.path).push_str(&format!(
+                                "  V.effect(function() {{ {}[{}].classList.toggle('{}', !!({})); }});\n",
                                 b_var, idx, class_name, transformed
                             ));
                         }
                         ClassItem::Static(class_name) => {
-                            js.push_str(&format!(
+                            wiring.at(&binding.path).push_str(&format!(
                                 "  {}[{}].classList.add('{}');\n",
                                 b_var, idx, class_name
                             ));
@@ -1534,20 +2599,46 @@ pub fn generate_signals_compile(
             }
         }
 
+        // Interpolated attribute bindings
+        for binding in &bindings.attrs {
+            if let Some(idx) = dfs_map.get(&binding.path) {
+                wiring.at(&binding.path).push_str(&format!(
+                    "  V.effect(function() {{ {}[{}].setAttribute('{}', {}); }});\n",
+                    b_var,
+                    idx,
+                    binding.name,
+                    template_to_js_expr(&binding.template, &reactive_names)
+                ));
+            }
+        }
+
         // v-model bindings
         for binding in &bindings.models {
             if let Some(idx) = dfs_map.get(&binding.path) {
                 let signal = &binding.signal_name;
-                js.push_str(&format!(
+                wiring.at(&binding.path).push_str(&format!(
                     "  V.effect(function() {{ {}[{}].value = {}.value; }});\n",
                     b_var, idx, signal
                 ));
-                js.push_str(&format!(
+                wiring.at(&binding.path).push_str(&format!(
                     "  {}[{}].addEventListener('input', function(e) {{ {}.value = e.target.value; }});\n",
                     b_var, idx, signal
                 ));
             }
         }
+
+        // v-cloak: reveal once everything above is wired, even if it threw
+        for path in &bindings.cloaks {
+            if let Some(idx) = dfs_map.get(path) {
+                wiring.reveal().push_str(&format!("  {}[{}].removeAttribute('v-cloak');\n", b_var, idx));
+            }
+        }
+        // A group waits on its first bound element; `V.hydrate()` climbs to
+        // the top-level element holding it
+        js.push_str(&wiring.finish(|group| {
+            let first = dfs_map.keys().filter(|path| path.first() == Some(&group)).min().unwrap();
+            format!("{b_var}[{}]", dfs_map[first])
+        }));
     }
 
     js.push_str("})();\n");
@@ -1574,39 +2665,103 @@ fn extract_client_only_blocks(html: &str) -> Vec<String> {
 }
 
 /// Build a mapping from positional path (as used by walk_template) to DFS index.
-/// DFS index is the order in which elements appear in a depth-first traversal.
+/// DFS index is the order in which elements appear in a depth-first traversal,
+/// which is how the generated `_collectEls` numbers a block's elements.
 fn build_dfs_index_map(
     bindings: &TemplateBindings,
-    _block_html: &str,
+    block_html: &str,
 ) -> std::collections::HashMap<Vec<usize>, usize> {
-    // Collect all unique paths from bindings
-    let mut all_paths: Vec<&Vec<usize>> = Vec::new();
-    for b in &bindings.events { all_paths.push(&b.path); }
-    for b in &bindings.texts { all_paths.push(&b.path); }
-    for b in &bindings.shows { all_paths.push(&b.path); }
-    for b in &bindings.htmls { all_paths.push(&b.path); }
-    for b in &bindings.text_directives { all_paths.push(&b.path); }
-    for b in &bindings.classes { all_paths.push(&b.path); }
-    for b in &bindings.styles { all_paths.push(&b.path); }
-    for b in &bindings.models { all_paths.push(&b.path); }
-
-    // Positional path [0, 2, 1] in children-based indexing maps to a DFS index.
-    // For a flat structure, the positional path correlates with DFS order.
-    // We sort all paths and assign sequential DFS indices.
-    let mut unique_paths: Vec<Vec<usize>> = all_paths.into_iter().cloned().collect();
-    unique_paths.sort();
-    unique_paths.dedup();
-
-    // Map each path to its DFS index
-    // This is an approximation: for the DFS traversal of the block HTML,
-    // positional path [a, b, c] maps to the element found by walking
-    // children[a].children[b].children[c] from root.
-    // In DFS order, we enumerate all elements and match paths.
-    let mut map = std::collections::HashMap::new();
-    for (i, path) in unique_paths.iter().enumerate() {
-        map.insert(path.clone(), i);
-    }
-    map
+    let bound: std::collections::HashSet<Vec<usize>> = collect_binding_paths(bindings).into_iter().collect();
+    let nodes = parse_html(block_html);
+    let mut elements = Vec::new();
+    collect_elements(&nodes, &[], &mut 0, &mut elements);
+    elements
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (path, _))| bound.contains(path))
+        .map(|(index, (path, _))| (path, index))
+        .collect()
+}
+
+/// Elements in depth-first order with their positional paths, numbered the
+/// way `walk_nodes` does: transition wrappers and template groups add neither
+/// an element nor a path step.
+fn collect_elements<'a>(
+    nodes: &'a [HtmlNode],
+    path: &[usize],
+    element_index: &mut usize,
+    out: &mut Vec<(Vec<usize>, &'a HtmlElement)>,
+) {
+    for node in nodes {
+        let HtmlNode::Element(elem) = node else {
+            continue;
+        };
+        if is_transition_wrapper(&elem.tag) || is_template_group(elem) {
+            collect_elements(&elem.children, path, element_index, out);
+            continue;
+        }
+        let mut current_path = path.to_vec();
+        current_path.push(*element_index);
+        out.push((current_path.clone(), elem));
+        collect_elements(&elem.children, &current_path, &mut 0, out);
+        *element_index += 1;
+    }
+}
+
+/// Tags of all elements in depth-first order, as the browser sees them.
+fn dfs_tags(nodes: &[HtmlNode], out: &mut Vec<String>) {
+    for node in nodes {
+        if let HtmlNode::Element(elem) = node {
+            out.push(elem.tag.clone());
+            dfs_tags(&elem.children, out);
+        }
+    }
+}
+
+/// Check that each bound element of every `<!--client-only-->` block in
+/// `shipped_html` sits at the depth-first index, and has the tag, computed
+/// from `template_html` by [`generate_signals_compile`].
+///
+/// Returns one message per mismatch (empty when every binding lines up).
+pub fn verify_client_only_bindings(script_setup: &str, template_html: &str, shipped_html: &str) -> Vec<String> {
+    let analysis = analyze_script(script_setup);
+    let reactive_names: Vec<&str> = analysis
+        .signals
+        .iter()
+        .map(|s| s.name.as_str())
+        .chain(analysis.computeds.iter().map(|c| c.name.as_str()))
+        .collect();
+    let shipped_blocks = extract_client_only_blocks(shipped_html);
+    let mut mismatches = Vec::new();
+    for (block_idx, block_html) in extract_client_only_blocks(template_html).iter().enumerate() {
+        let bindings = walk_template(block_html, &reactive_names);
+        let dfs_map = build_dfs_index_map(&bindings, block_html);
+        if dfs_map.is_empty() {
+            continue;
+        }
+        let nodes = parse_html(block_html);
+        let mut elements = Vec::new();
+        collect_elements(&nodes, &[], &mut 0, &mut elements);
+        let mut shipped = Vec::new();
+        if let Some(shipped_block) = shipped_blocks.get(block_idx) {
+            dfs_tags(&parse_html(shipped_block), &mut shipped);
+        }
+        let mut bound: Vec<(&Vec<usize>, &usize)> = dfs_map.iter().collect();
+        bound.sort();
+        for (path, &index) in bound {
+            let expected = &elements[index].1.tag;
+            match shipped.get(index) {
+                Some(found) if found == expected => {}
+                Some(found) => mismatches.push(format!(
+                    "client-only block {block_idx}: binding at {path:?} expects <{expected}> but element {index} is <{found}>"
+                )),
+                None => mismatches.push(format!(
+                    "client-only block {block_idx}: binding at {path:?} expects <{expected}> but element {index} is missing"
+                )),
+            }
+        }
+    }
+    mismatches
 }
 
 // ── Stage D: Comment-anchored signal generation (compile mode) ───────────
@@ -1622,6 +2777,8 @@ fn collect_binding_paths(bindings: &TemplateBindings) -> Vec<Vec<usize>> {
     for b in &bindings.classes { paths.insert(b.path.clone()); }
     for b in &bindings.styles { paths.insert(b.path.clone()); }
     for b in &bindings.models { paths.insert(b.path.clone()); }
+    for b in &bindings.attrs { paths.insert(b.path.clone()); }
+    for path in &bindings.cloaks { paths.insert(path.clone()); }
     paths.into_iter().collect()
 }
 
@@ -1631,90 +2788,132 @@ fn find_element_offsets(html: &str, target_paths: &[Vec<usize>]) -> HashMap<Vec<
     let target_set: std::collections::HashSet<Vec<usize>> = target_paths.iter().cloned().collect();
     let nodes = parse_html(html);
 
-    let mut offsets = HashMap::new();
-
     // Check for <body> — paths are relative to body's children (same as walk_template)
-    if let Some(body) = find_body(&nodes) {
-        collect_offsets_from_tree(&body.children, &[], &target_set, &mut offsets);
-    } else {
-        collect_offsets_from_tree(&nodes, &[], &target_set, &mut offsets);
-    }
-
-    offsets
-}
-
-/// Recursively collect source offsets from the parsed tree, matching target paths.
-fn collect_offsets_from_tree(
-    children: &[HtmlNode],
-    parent_path: &[usize],
-    targets: &std::collections::HashSet<Vec<usize>>,
-    offsets: &mut HashMap<Vec<usize>, usize>,
-) {
-    let mut element_index: usize = 0;
-    for node in children {
-        if let HtmlNode::Element(elem) = node {
-            // Skip <transition> — not a real DOM element
-            if elem.tag == "transition" {
-                collect_offsets_from_tree(&elem.children, parent_path, targets, offsets);
-                continue;
-            }
-
-            let mut current_path = parent_path.to_vec();
-            current_path.push(element_index);
-
-            if targets.contains(&current_path) {
-                offsets.insert(current_path.clone(), elem.source_start);
-            }
-
-            // Recurse into children
-            collect_offsets_from_tree(&elem.children, &current_path, targets, offsets);
-
-            element_index += 1;
-        }
-    }
+    let roots = find_body(&nodes).map_or(nodes.as_slice(), |body| body.children.as_slice());
+    let mut elements = Vec::new();
+    collect_elements(roots, &[], &mut 0, &mut elements);
+    elements
+        .into_iter()
+        .filter(|(path, _)| target_set.contains(path))
+        .map(|(path, elem)| (path, elem.source_start))
+        .collect()
 }
 
-/// Inject `<!--v:N-->` comment markers before signal-bound elements in HTML.
-/// `binding_paths` are sorted in DFS order; N is the index in this list.
-/// Returns the modified HTML and the mapping from path to comment index.
+/// Inject `<!--v:ID-->` comment markers before signal-bound elements in HTML.
+/// `binding_paths` are sorted in DFS order; the ID is the element's path
+/// (`0_2_1`) or, with [`ElementNames::Ordinal`], its index in this list.
+/// Returns the modified HTML and the mapping from path to comment ID.
 pub fn inject_signal_comments(
     html: &str,
     binding_paths: &[Vec<usize>],
-) -> (String, HashMap<Vec<usize>, usize>) {
+    element_names: ElementNames,
+) -> (String, HashMap<Vec<usize>, String>) {
     let offsets = find_element_offsets(html, binding_paths);
 
-    // Build (byte_offset, comment_index) pairs, sorted by offset descending for safe insertion
-    let mut insertions: Vec<(usize, usize)> = Vec::new();
-    let mut path_to_idx = HashMap::new();
+    // Build (byte_offset, comment_id) pairs, sorted by offset descending for safe insertion
+    let mut insertions: Vec<(usize, String)> = Vec::new();
+    let mut path_to_id = HashMap::new();
     for (idx, path) in binding_paths.iter().enumerate() {
         if let Some(&offset) = offsets.get(path) {
-            insertions.push((offset, idx));
-            path_to_idx.insert(path.clone(), idx);
+            let id = element_names.key(path, idx);
+            insertions.push((offset, id.clone()));
+            path_to_id.insert(path.clone(), id);
         }
     }
-    insertions.sort_by(|a, b| b.0.cmp(&a.0)); // reverse order
+    insertions.sort_by_key(|(offset, _)| std::cmp::Reverse(*offset));
 
     let mut result = html.to_string();
-    for (offset, idx) in &insertions {
-        let comment = format!("<!--v:{}-->", idx);
+    for (offset, id) in &insertions {
+        let comment = format!("<!--v:{}-->", id);
         result.insert_str(*offset, &comment);
     }
 
-    (result, path_to_idx)
+    (result, path_to_id)
+}
+
+/// Check the `<!--v:ID-->` anchors in `shipped_html` against the elements
+/// they were injected for by [`inject_signal_comments`] from `binding_paths`
+/// in `template_html`. Each anchor must be present and its next element
+/// sibling must have the same tag.
+///
+/// Returns one message per mismatch (empty when every binding lines up).
+pub fn verify_signal_comments(
+    template_html: &str,
+    binding_paths: &[Vec<usize>],
+    shipped_html: &str,
+    element_names: ElementNames,
+) -> Vec<String> {
+    let offsets = find_element_offsets(template_html, binding_paths);
+    let mut mismatches = Vec::new();
+    for (idx, path) in binding_paths.iter().enumerate() {
+        let Some(&offset) = offsets.get(path) else {
+            continue;
+        };
+        let expected = tag_name_at(&template_html[offset..]);
+        let idx = element_names.key(path, idx);
+        let anchor = format!("<!--v:{idx}-->");
+        let found = shipped_html
+            .find(&anchor)
+            .map(|at| next_element_tag(&shipped_html[at + anchor.len()..]));
+        match found {
+            Some(Some(found)) if found == expected => {}
+            Some(Some(found)) => mismatches.push(format!(
+                "signal binding v:{idx} at {path:?} expects <{expected}> but anchors <{found}>"
+            )),
+            Some(None) => mismatches.push(format!(
+                "signal binding v:{idx} at {path:?} expects <{expected}> but no element follows its anchor"
+            )),
+            None => mismatches.push(format!(
+                "signal binding v:{idx} at {path:?} expects <{expected}> but its anchor is missing"
+            )),
+        }
+    }
+    mismatches
+}
+
+/// Lowercased name of the tag opening at the start of `html`.
+fn tag_name_at(html: &str) -> String {
+    html.trim_start_matches('<')
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Tag of the next element sibling in `html` (skipping text and comments),
+/// or `None` when the parent closes first.
+fn next_element_tag(html: &str) -> Option<String> {
+    let mut rest = html;
+    loop {
+        rest = &rest[rest.find('<')?..];
+        if rest.starts_with("<!--") {
+            rest = &rest[rest.find("-->")? + 3..];
+        } else if rest.starts_with("</") {
+            return None;
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Some(tag_name_at(rest));
+        } else {
+            rest = &rest[1..];
+        }
+    }
 }
 
 /// Compile mode: generate signal JS for ALL signal bindings using comment anchors.
-/// Each signal-bound element has a `<!--v:N-->` comment before it.
+/// Each signal-bound element has a `<!--v:ID-->` comment before it (see
+/// [`inject_signal_comments`] for the IDs `element_names` gives).
 /// JS uses TreeWalker to collect these comments and locate elements via nextElementSibling.
+/// With [`Hydration::Lazy`] the walk still runs up front; only the wiring is gated.
 pub fn generate_signals_comment(
     script_setup: &str,
     template_html: &str,
-    module_code: &[String],
-    global_name: &str,
+    modules: &[ModuleCode],
+    options: &SignalOptions,
 ) -> Option<String> {
+    let &SignalOptions { ref global_name, sanitize_html, hydration, element_names } = options;
     let analysis = analyze_script(script_setup);
 
-    if analysis.signals.is_empty() && analysis.computeds.is_empty() {
+    let stores = module::store_fields(modules);
+    if analysis.signals.is_empty() && analysis.computeds.is_empty() && stores.is_empty() {
         return None;
     }
 
@@ -1723,6 +2922,7 @@ pub fn generate_signals_comment(
         .iter()
         .map(|s| s.name.as_str())
         .chain(analysis.computeds.iter().map(|c| c.name.as_str()))
+        .chain(stores.iter().map(|f| f.name.as_str()))
         .collect();
 
     let bindings = walk_template(template_html, &reactive_names);
@@ -1732,11 +2932,11 @@ pub fn generate_signals_comment(
         return None;
     }
 
-    // Build path → comment index mapping (same order as inject_signal_comments)
-    let path_to_idx: HashMap<Vec<usize>, usize> = binding_paths
+    // Build path → `_ve` key mapping (the ids inject_signal_comments anchors)
+    let path_to_idx: HashMap<Vec<usize>, String> = binding_paths
         .iter()
         .enumerate()
-        .map(|(i, p)| (p.clone(), i))
+        .map(|(i, p)| (p.clone(), element_names.index(p, i)))
         .collect();
 
     let total = binding_paths.len();
@@ -1745,13 +2945,11 @@ pub fn generate_signals_comment(
     js.push_str("(function() {\n");
     js.push_str(&format!("  var V = {};\n", global_name));
 
-    // Inlined module code
-    for (i, code) in module_code.iter().enumerate() {
-        js.push_str(&format!(
-            "  var __mod_{} = (function() {{ {} }})();\n",
-            i, code.trim()
-        ));
-    }
+    // Inlined module code and the importers' bindings
+    js.push_str(&module::modules_js(modules));
+
+    // Literal constants, which signal initial values may refer to
+    js.push_str(&constants_js(&analysis.constants));
 
     // Signals
     for s in &analysis.signals {
@@ -1760,17 +2958,17 @@ pub fn generate_signals_comment(
 
     // Computeds
     for c in &analysis.computeds {
-        let body = transform_expr(&c.body, &reactive_names);
-        js.push_str(&format!(
-            "  var {} = V.computed(function() {{ return {}; }});\n",
-            c.name, body
-        ));
+        js.push_str(&computed_js(c, &reactive_names));
     }
 
     // Functions
     for f in &analysis.functions {
-        let body = transform_expr(&f.body, &reactive_names);
-        js.push_str(&format!("  function {}({}) {{ {} }}\n", f.name, f.params, body));
+        js.push_str(&function_js(f, &reactive_names));
+    }
+
+    // Other top-level statements, in source order
+    for stmt in &analysis.residual {
+        js.push_str(&format!("  {}\n", transform_expr(stmt, &reactive_names)));
     }
 
     // Watch declarations
@@ -1783,27 +2981,30 @@ pub fn generate_signals_comment(
     }
 
     // Comment anchor walker — collect signal elements
-    js.push_str("\n");
-    js.push_str(&format!("  var _ve = new Array({});\n", total));
+    js.push('\n');
+    match element_names {
+        ElementNames::Path => js.push_str("  var _ve = {};\n"),
+        ElementNames::Ordinal => js.push_str(&format!("  var _ve = new Array({});\n", total)),
+    }
     js.push_str("  var _tw = document.createTreeWalker(document.body, NodeFilter.SHOW_COMMENT);\n");
     js.push_str("  var _tn;\n");
     js.push_str("  while (_tn = _tw.nextNode()) {\n");
     js.push_str("    var _td = _tn.data;\n");
     js.push_str("    if (_td.length > 2 && _td.charCodeAt(0) === 118 && _td.charCodeAt(1) === 58) {\n");
-    js.push_str("      _ve[parseInt(_td.substring(2))] = _tn.nextElementSibling;\n");
+    match element_names {
+        ElementNames::Path => js.push_str("      _ve[_td.substring(2)] = _tn.nextElementSibling;\n"),
+        ElementNames::Ordinal => js.push_str("      _ve[parseInt(_td.substring(2))] = _tn.nextElementSibling;\n"),
+    }
     js.push_str("    }\n");
     js.push_str("  }\n");
 
+    let mut wiring = Wiring::new(hydration);
+
     // Event bindings
     for binding in &bindings.events {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
-            let handler_ref = if analysis.functions.iter().any(|f| f.name == binding.handler) {
-                binding.handler.clone()
-            } else {
-                let body = transform_expr(&binding.handler, &reactive_names);
-                format!("function() {{ {} }}", body)
-            };
-            js.push_str(&format!(
+        if let Some(idx) = path_to_idx.get(&binding.path) {
+            let handler_ref = handler_js(&binding.handler, &analysis, &reactive_names);
+            wiring.at(&binding.path).push_str(&format!(
                 "  _ve[{}].addEventListener('{}', {});\n",
                 idx, binding.event, handler_ref
             ));
@@ -1812,9 +3013,9 @@ pub fn generate_signals_comment(
 
     // Text bindings
     for binding in &bindings.texts {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
+        if let Some(idx) = path_to_idx.get(&binding.path) {
             let js_expr = template_to_js_expr(&binding.template, &reactive_names);
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ _ve[{}].textContent = {}; }});\n",
                 idx, js_expr
             ));
@@ -1823,15 +3024,12 @@ pub fn generate_signals_comment(
 
     // Show bindings
     for binding in &bindings.shows {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
+        if let Some(idx) = path_to_idx.get(&binding.path) {
             let transformed = transform_expr(&binding.expr, &reactive_names);
-            if let Some(ref name) = binding.transition {
-                js.push_str(&format!(
-                    "  V.effect(function() {{ V.transition(_ve[{}], {}, '{}'); }});\n",
-                    idx, transformed, name
-                ));
+            if let Some(ref transition) = binding.transition {
+                wiring.at(&binding.path).push_str(&transition.effect_js(&format!("_ve[{idx}]"), &transformed));
             } else {
-                js.push_str(&format!(
+                wiring.at(&binding.path).push_str(&format!(
                     "  V.effect(function() {{ _ve[{}].style.display = {} ? '' : 'none'; }});\n",
                     idx, transformed
                 ));
@@ -1841,19 +3039,19 @@ pub fn generate_signals_comment(
 
     // :class bindings
     for binding in &bindings.classes {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
+        if let Some(idx) = path_to_idx.get(&binding.path) {
             let items = parse_class_expr(&binding.expr);
             for item in &items {
                 match item {
                     ClassItem::Toggle(class_name, cond_expr) => {
                         let transformed = transform_expr(cond_expr, &reactive_names);
-                        js.push_str(&format!(
-                            "  V.effect(function() {{ _ve[{}].classList.toggle('{}', !!{}); }});\n",
+                        wiring.at(&binding.path).push_str(&format!(
+                            "  V.effect(function() {{ _ve[{}].classList.toggle('{}', !!({})); }});\n",
                             idx, class_name, transformed
                         ));
                     }
                     ClassItem::Static(class_name) => {
-                        js.push_str(&format!("  _ve[{}].classList.add('{}');\n", idx, class_name));
+                        wiring.at(&binding.path).push_str(&format!("  _ve[{}].classList.add('{}');\n", idx, class_name));
                     }
                 }
             }
@@ -1862,11 +3060,11 @@ pub fn generate_signals_comment(
 
     // :style bindings
     for binding in &bindings.styles {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
+        if let Some(idx) = path_to_idx.get(&binding.path) {
             let pairs = parse_style_expr(&binding.expr);
             for (prop, val_expr) in &pairs {
                 let transformed = transform_expr(val_expr, &reactive_names);
-                js.push_str(&format!(
+                wiring.at(&binding.path).push_str(&format!(
                     "  V.effect(function() {{ _ve[{}].style.{} = {}; }});\n",
                     idx, prop, transformed
                 ));
@@ -1874,15 +3072,27 @@ pub fn generate_signals_comment(
         }
     }
 
+    // Interpolated attribute bindings
+    for binding in &bindings.attrs {
+        if let Some(idx) = path_to_idx.get(&binding.path) {
+            wiring.at(&binding.path).push_str(&format!(
+                "  V.effect(function() {{ _ve[{}].setAttribute('{}', {}); }});\n",
+                idx,
+                binding.name,
+                template_to_js_expr(&binding.template, &reactive_names)
+            ));
+        }
+    }
+
     // v-model bindings
     for binding in &bindings.models {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
+        if let Some(idx) = path_to_idx.get(&binding.path) {
             let signal = &binding.signal_name;
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ _ve[{}].value = {}.value; }});\n",
                 idx, signal
             ));
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  _ve[{}].addEventListener('input', function(e) {{ {}.value = e.target.value; }});\n",
                 idx, signal
             ));
@@ -1891,9 +3101,9 @@ pub fn generate_signals_comment(
 
     // v-html bindings
     for binding in &bindings.htmls {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
-            let transformed = transform_expr(&binding.expr, &reactive_names);
-            js.push_str(&format!(
+        if let Some(idx) = path_to_idx.get(&binding.path) {
+            let transformed = html_value(transform_expr(&binding.expr, &reactive_names), sanitize_html);
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ _ve[{}].innerHTML = {}; }});\n",
                 idx, transformed
             ));
@@ -1902,15 +3112,28 @@ pub fn generate_signals_comment(
 
     // v-text bindings
     for binding in &bindings.text_directives {
-        if let Some(&idx) = path_to_idx.get(&binding.path) {
+        if let Some(idx) = path_to_idx.get(&binding.path) {
             let transformed = transform_expr(&binding.expr, &reactive_names);
-            js.push_str(&format!(
+            wiring.at(&binding.path).push_str(&format!(
                 "  V.effect(function() {{ _ve[{}].textContent = {}; }});\n",
                 idx, transformed
             ));
         }
     }
 
+    // v-cloak: reveal once everything above is wired, even if it threw
+    // (right away when hydrating lazily)
+    for path in &bindings.cloaks {
+        if let Some(idx) = path_to_idx.get(path) {
+            wiring.reveal().push_str(&format!("  _ve[{}].removeAttribute('v-cloak');\n", idx));
+        }
+    }
+    // Each lazy group waits on the top-level element holding its first bound element
+    js.push_str(&wiring.finish(|group| {
+        let first = binding_paths.iter().find(|p| p.first() == Some(&group)).unwrap_or(&binding_paths[0]);
+        format!("_ve[{}]", path_to_idx[first])
+    }));
+
     js.push_str("})();\n");
     Some(js)
 }
@@ -1921,6 +3144,54 @@ pub fn generate_signals_comment(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_inline_env() {
+        let env = HashMap::from([("VAN_API_URL".to_string(), "https://api.example.com".to_string())]);
+        let script = "const api = import.meta.env.VAN_API_URL\nconst key = import.meta.env.VAN_KEY";
+        let (out, missing) = inline_env(script, &env);
+        assert_eq!(out, "const api = \"https://api.example.com\"\nconst key = \"\"");
+        assert_eq!(missing, vec!["VAN_KEY"]);
+    }
+
+    #[test]
+    fn test_inline_env_escapes_value() {
+        let env = HashMap::from([("VAN_MSG".to_string(), "say \"hi\"</script>".to_string())]);
+        let (out, _) = inline_env("const m = import.meta.env.VAN_MSG", &env);
+        assert_eq!(out, r#"const m = "say \"hi\"\u003c/script>""#);
+    }
+
+    #[test]
+    fn test_inline_props() {
+        let script = "const p = defineProps({ defaultOpen: Boolean })\nconst open = ref(p.defaultOpen)\nconst size = ref(p.size ?? 1)\nconst label = ref(other.p.x)\nconst shown = computed(() => p.defaultOpen)";
+        let (out, missing) = inline_props(script, |name| (name == "defaultOpen").then(|| "true".to_string()));
+        assert_eq!(
+            out,
+            "const p = defineProps({ defaultOpen: Boolean })\nconst open = ref(true)\nconst size = ref(undefined ?? 1)\nconst label = ref(other.p.x)\nconst shown = computed(() => p.defaultOpen)"
+        );
+        assert_eq!(missing, vec!["size"]);
+
+        let unbound = "defineProps({ a: Number })\nconst x = ref(props.a)";
+        assert_eq!(inline_props(unbound, |_| None), (unbound.to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn test_props_object() {
+        let script = "const props = withDefaults(defineProps<{ title: string }>(), { title: 'Hi' })\nfunction share() { navigator.share({ title: props.title, url: props.links.home }) }";
+        let values = vec![
+            ("title".to_string(), r#""Van \u003c/script>""#.to_string()),
+            ("links".to_string(), r#"{"home":"/"}"#.to_string()),
+        ];
+        let out = props_object(script, &values);
+        assert!(out.starts_with(r#"const props = { "title": "Van \u003c/script>", "links": {"home":"/"} }"#), "{out}");
+        let js = generate_signals(&format!("{out}\nconst n = ref(0)"), r#"<button @click="share">{{ n }}</button>"#, &[], &SignalOptions::default()).unwrap();
+        assert!(js.contains(r#"  var props = {"links":{"home":"/"},"title":"Van \u003c/script>"};"#), "{js}");
+        assert!(js.find("var props").unwrap() < js.find("function share").unwrap());
+        assert!(js.contains("navigator.share({ title: props.title, url: props.links.home })"));
+
+        let unbound = "defineProps({ a: Number })";
+        assert_eq!(props_object(unbound, &values), unbound);
+    }
+
     #[test]
     fn test_analyze_script_ref() {
         let script = r#"
@@ -1942,6 +3213,34 @@ const name = ref('hello')
         assert_eq!(analysis.computeds.len(), 1);
         assert_eq!(analysis.computeds[0].name, "doubled");
         assert_eq!(analysis.computeds[0].body, "count * 2");
+        assert!(!analysis.computeds[0].block);
+    }
+
+    #[test]
+    fn test_computed_block_body_early_return() {
+        let script = "const count = ref(12)\nconst label = computed(() => { if (count.value > 9) { return '9+' } return String(count.value) })";
+        let analysis = analyze_script(script);
+        assert_eq!(analysis.computeds[0].body, "if (count.value > 9) { return '9+' } return String(count.value)");
+        assert!(analysis.computeds[0].block);
+
+        let js = generate_signals(script, "<span>{{ label }}</span>", &[], &SignalOptions::default()).unwrap();
+        assert!(
+            js.contains("var label = V.computed(function() { if (count.value > 9) { return '9+' } return String(count.value) });"),
+            "Got:\n{js}"
+        );
+        assert!(js.contains("textContent = label.value;"));
+    }
+
+    #[test]
+    fn test_computed_function_body_with_object_literal() {
+        let script = "const size = ref('sm')\nconst styles = computed(function() { const map = { sm: { px: 8 }, lg: { px: 16 } }; return map[size].px + 'px' })";
+        let analysis = analyze_script(script);
+        assert!(analysis.computeds[0].block);
+        let js = generate_signals(script, "<p>{{ styles }}</p>", &[], &SignalOptions::default()).unwrap();
+        assert!(
+            js.contains("var styles = V.computed(function() { const map = { sm: { px: 8 }, lg: { px: 16 } }; return map[size.value].px + 'px' });"),
+            "Got:\n{js}"
+        );
     }
 
     #[test]
@@ -1953,6 +3252,37 @@ const name = ref('hello')
         assert_eq!(analysis.functions[0].body, "count.value++");
     }
 
+    #[test]
+    fn test_async_function_declaration() {
+        let script = "const items = ref([])\nasync function load() { const r = await fetch('/api/items?limit=10'); items.value = await r.json() }";
+        let analysis = analyze_script(script);
+        assert!(analysis.functions[0].is_async);
+        assert_eq!(analysis.functions[0].body, "const r = await fetch('/api/items?limit=10'); items.value = await r.json()");
+
+        let js = generate_signals(script, r#"<button @click="load">Load</button><p>{{ items }}</p>"#, &[], &SignalOptions::default()).unwrap();
+        assert!(
+            js.contains("  async function load() { const r = await fetch('/api/items?limit=10'); items.value = await r.json() }\n"),
+            "Got:\n{js}"
+        );
+        assert!(js.contains(".addEventListener('click', load);"), "Got:\n{js}");
+    }
+
+    #[test]
+    fn test_async_arrow_function() {
+        let script = "const items = ref([])\nconst load = async (url) => { const r = await fetch(url); items = await r.json() }";
+        let analysis = analyze_script(script);
+        assert!(analysis.functions[0].is_async);
+
+        let html = r#"<button @click="load">Load</button><button @click="await load('/more')">More</button>"#;
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        assert!(
+            js.contains("  async function load(url) { const r = await fetch(url); items.value = await r.json() }\n"),
+            "Got:\n{js}"
+        );
+        assert!(js.contains(".addEventListener('click', load);"), "Got:\n{js}");
+        assert!(js.contains(".addEventListener('click', async function() { await load('/more') });"), "Got:\n{js}");
+    }
+
     #[test]
     fn test_analyze_script_full() {
         let script = r#"
@@ -1997,6 +3327,34 @@ function decrement() { count.value-- }
         assert_eq!(attrs[2], ("v-show".to_string(), "visible".to_string()));
     }
 
+    #[test]
+    fn test_parse_attrs_multi_line_and_case() {
+        let attrs = parse_attrs(" \n\tclass=\"btn\"\n\t@myEvent = \"onMine\"\n\tV-SHOW\n\t\t=\n\t\t'visible'\n\tv-Bind:innerHTML=html\n\tdisabled\n");
+        assert_eq!(
+            attrs,
+            vec![
+                ("class".to_string(), "btn".to_string()),
+                ("@myEvent".to_string(), "onMine".to_string()),
+                ("v-show".to_string(), "visible".to_string()),
+                ("v-bind:innerHTML".to_string(), "html".to_string()),
+                ("disabled".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_template_prettier_formatted_tag() {
+        let html = "<div>\n  <button\n    class=\"btn\"\n    @click=\"inc\"\n    V-Show=\"open\"\n    :class=\"{ active: open }\"\n  >\n    {{ count }}\n  </button>\n</div>";
+        let bindings = walk_template(html, &["count", "open"]);
+        assert_eq!(bindings.events.len(), 1);
+        assert_eq!(bindings.events[0].event, "click");
+        assert_eq!(bindings.events[0].handler, "inc");
+        assert_eq!(bindings.shows.len(), 1);
+        assert_eq!(bindings.shows[0].expr, "open");
+        assert_eq!(bindings.classes.len(), 1);
+        assert_eq!(bindings.texts.len(), 1);
+    }
+
     #[test]
     fn test_walk_template_events() {
         let html = r#"<div><button @click="increment">+1</button></div>"#;
@@ -2016,6 +3374,43 @@ function decrement() { count.value-- }
         assert_eq!(bindings.texts[0].path, vec![0, 0]); // div.children[0] = p
     }
 
+    #[test]
+    fn test_walk_template_attr_binding() {
+        let html = r#"<div><img src="/img/{{ id }}.png" alt="static" :title="id"></div>"#;
+        let bindings = walk_template(html, &["id"]);
+        assert_eq!(bindings.attrs.len(), 1);
+        assert_eq!(bindings.attrs[0].name, "src");
+        assert_eq!(bindings.attrs[0].template, "/img/{{ id }}.png");
+        assert_eq!(bindings.attrs[0].path, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_walk_template_merges_class_toggles() {
+        let html = r#"<div :class="{ active: a }" :class="[{ active: b }, 'big', { big: c }]"></div>"#;
+        let bindings = walk_template(html, &["a", "b", "c"]);
+        assert_eq!(bindings.classes.len(), 1);
+        assert_eq!(bindings.classes[0].expr, "[{ 'active': (a) || (b) }, 'big']");
+
+        let js = generate_signals("const a = ref(false)\nconst b = ref(true)\nconst c = ref(false)", html, &[], &SignalOptions::default()).unwrap();
+        assert_eq!(js.matches("classList.toggle('active'").count(), 1, "Got:\n{js}");
+        assert!(js.contains("classList.toggle('active', !!((a.value) || (b.value)));"), "Got:\n{js}");
+        assert!(!js.contains("classList.toggle('big'"), "Got:\n{js}");
+    }
+
+    #[test]
+    fn test_v_text_with_interpolation_conflict() {
+        let script = "const msg = ref('hi')";
+        let html = r#"<div><p v-text="msg">{{ msg }}!</p></div>"#;
+        let bindings = walk_template(html, &["msg"]);
+        assert!(bindings.texts.is_empty());
+        assert_eq!(bindings.text_directives.len(), 1);
+        assert_eq!(
+            binding_conflicts(script, html),
+            vec![r#"<p> v-text="msg" and {{ msg }}! both set its text; the interpolation is ignored"#.to_string()]
+        );
+        assert!(binding_conflicts(script, "<p>{{ msg }}</p><p v-text=\"msg\"></p>").is_empty());
+    }
+
     #[test]
     fn test_walk_template_show() {
         let html = r#"<div><p v-show="visible">Hello</p></div>"#;
@@ -2035,7 +3430,68 @@ function decrement() { count.value-- }
         // div.children: [0]=p, [1]=div(drawer), [2]=p — Transition skipped
         assert_eq!(bindings.shows[0].path, vec![0, 1]);
         assert_eq!(bindings.shows[0].expr, "open");
-        assert_eq!(bindings.shows[0].transition, Some("slide".to_string()));
+        assert_eq!(bindings.shows[0].transition.as_ref().map(|t| t.name.as_str()), Some("slide"));
+    }
+
+    #[test]
+    fn test_walk_template_transition_group_skips_path() {
+        let html = r#"<div><TransitionGroup name="list"><p v-show="a">A</p><p>B</p></TransitionGroup><button @click="go">Go</button></div>"#;
+        let bindings = walk_template(html, &["a"]);
+        assert_eq!(bindings.shows[0].path, vec![0, 0]);
+        assert_eq!(bindings.shows[0].transition.as_ref().map(|t| t.name.as_str()), Some("list"));
+        // The button after the group is the div's third element child
+        assert_eq!(bindings.events[0].path, vec![0, 2]);
+
+        let kebab = walk_template(r#"<div><transition-group><p>A</p></transition-group><button @click="go">Go</button></div>"#, &[]);
+        assert_eq!(kebab.events[0].path, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_walk_template_for_key() {
+        let html = r#"<ul><li v-for="row in rows" :key="row.id">{{ row.name }}</li><li v-for="x in xs">x</li></ul>"#;
+        let bindings = walk_template(html, &[]);
+        assert_eq!(bindings.fors.len(), 2);
+        assert_eq!(bindings.fors[0].expr, "row in rows");
+        assert_eq!(bindings.fors[0].key.as_deref(), Some("row.id"));
+        assert_eq!(bindings.fors[1].key, None);
+    }
+
+    #[test]
+    fn test_walk_template_template_v_if_group() {
+        let html = r#"<div><template v-if="loggedIn"><a>Profile</a><a @click="logout">Logout</a></template><p>{{ count }}</p></div>"#;
+        let bindings = walk_template(html, &["count", "loggedIn"]);
+        // The <template> adds no path step; each child takes its v-if
+        let shows: Vec<_> = bindings.shows.iter().map(|s| (s.path.clone(), s.expr.as_str())).collect();
+        assert_eq!(shows, vec![(vec![0, 0], "loggedIn"), (vec![0, 1], "loggedIn")]);
+        assert_eq!(bindings.events[0].path, vec![0, 1]);
+        assert_eq!(bindings.texts[0].path, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_walk_template_template_v_for_group() {
+        let html = r#"<dl><template v-for="term in terms" :key="term.id"><dt>{{ term.name }}</dt><dd>{{ term.text }}</dd></template><dt @click="more">More</dt></dl>"#;
+        let bindings = walk_template(html, &[]);
+        let fors: Vec<_> = bindings.fors.iter().map(|f| (f.path.clone(), f.key.as_deref())).collect();
+        assert_eq!(fors, vec![(vec![0, 0], Some("term.id")), (vec![0, 1], Some("term.id"))]);
+        assert_eq!(bindings.events[0].path, vec![0, 2]);
+
+        let (marked, _) = inject_signal_comments(html, &[vec![0, 2]], ElementNames::Path);
+        assert!(marked.contains("</template><!--v:0_2--><dt @click"));
+    }
+
+    #[test]
+    fn test_generate_signals_v_cloak() {
+        let script = "const open = ref(false)";
+        let html = r#"<div><section v-cloak><p v-show="open">Hi</p></section></div>"#;
+        let bindings = walk_template(html, &["open"]);
+        assert_eq!(bindings.cloaks, vec![vec![0, 0]]);
+
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        let uncloak = js.find("  _e0_0.removeAttribute('v-cloak');").expect("uncloaks the section");
+        assert!(uncloak > js.find("style.display").unwrap(), "uncloaked after bindings are wired");
+
+        let js = generate_signals_comment(script, html, &[], &SignalOptions::default()).unwrap();
+        assert!(js.contains("  _ve['0_0'].removeAttribute('v-cloak');"));
     }
 
     #[test]
@@ -2046,7 +3502,7 @@ function decrement() { count.value-- }
         assert_eq!(bindings.shows.len(), 1);
         assert_eq!(bindings.shows[0].path, vec![0, 0]); // div.children[0] = p
         // No name attr → defaults to "v"
-        assert_eq!(bindings.shows[0].transition, Some("v".to_string()));
+        assert_eq!(bindings.shows[0].transition.as_ref().map(|t| t.name.as_str()), Some("v"));
     }
 
     #[test]
@@ -2060,6 +3516,100 @@ function decrement() { count.value-- }
         assert_eq!(bindings.events[0].path, vec![1, 1]); // body.children[1]=main, main.children[1]=button
     }
 
+    #[test]
+    fn test_walk_template_multi_root_body() {
+        // A fragment page: two roots with text and comments between them
+        let html = "<body>\n  <header><button @click=\"inc\">+</button></header>\n  hello <!-- note -->\n  <main><p>{{ count }}</p></main>\n</body>";
+        let bindings = walk_template(html, &["count"]);
+        assert_eq!(bindings.events[0].path, vec![0, 0]); // body.children[0]=header
+        assert_eq!(bindings.texts[0].path, vec![1, 0]); // body.children[1]=main
+
+        // Without a <body>, the fragment's roots are the top level
+        let bindings = walk_template("<header><button @click=\"inc\">+</button></header><main><p>{{ count }}</p></main>", &["count"]);
+        assert_eq!(bindings.events[0].path, vec![0, 0]);
+        assert_eq!(bindings.texts[0].path, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_walk_template_unclosed_custom_element() {
+        // <my-widget> never closes; the paragraph and button stay its siblings
+        let html = r#"<div><my-widget><p>{{ count }}</p><button @click="inc">+</button></div><button @click="dec">-</button>"#;
+        let bindings = walk_template(html, &["count"]);
+        assert_eq!(bindings.texts[0].path, vec![0, 1]); // div.children[1] = p
+        assert_eq!(bindings.events[0].path, vec![0, 2]);
+        assert_eq!(bindings.events[1].path, vec![1]);
+        assert_eq!(unclosed_elements(html), vec!["my-widget"]);
+
+        // Unclosed at the end of the input
+        let html = r#"<my-widget><p>{{ count }}</p>"#;
+        assert_eq!(walk_template(html, &["count"]).texts[0].path, vec![1]);
+        assert_eq!(unclosed_elements(html), vec!["my-widget"]);
+    }
+
+    #[test]
+    fn test_walk_template_nested_unclosed_elements() {
+        let html = r#"<ul><li>a<li>b</ul><p>{{ count }}</p>"#;
+        assert_eq!(walk_template(html, &["count"]).texts[0].path, vec![1]);
+        assert_eq!(unclosed_elements(html), vec!["li", "li"]);
+    }
+
+    #[test]
+    fn test_walk_template_svg_self_closing() {
+        let html = r#"<svg><defs><linearGradient id="g"><stop offset="0"/></linearGradient><clipPath id="c"><rect width="1" height="1" /></clipPath></defs><path d="M0 0"/></svg><p>{{ count }}</p>"#;
+        assert_eq!(walk_template(html, &["count"]).texts[0].path, vec![1]);
+        assert!(unclosed_elements(html).is_empty());
+    }
+
+    #[test]
+    fn test_dfs_index_map_counts_unbound_elements() {
+        let block = r#"<div><h2>Title</h2><p>{{ count }}</p><Transition><button @click="inc">+</button></Transition></div>"#;
+        let bindings = walk_template(block, &["count"]);
+        let map = build_dfs_index_map(&bindings, block);
+        assert_eq!(map[&vec![0, 1]], 2); // div, h2, p
+        assert_eq!(map[&vec![0, 2]], 3); // the <Transition> wrapper is not an element
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_verify_client_only_bindings() {
+        let script = "const count = ref(0)";
+        let template = r#"<!--client-only--><div><p>{{ count }}</p></div><!--/client-only-->"#;
+        assert!(verify_client_only_bindings(script, template, template).is_empty());
+        // An element inserted before the binding shifts its DFS index
+        let shipped = r#"<!--client-only--><div><span>x</span><p>0</p></div><!--/client-only-->"#;
+        assert_eq!(
+            verify_client_only_bindings(script, template, shipped),
+            vec!["client-only block 0: binding at [0, 0] expects <p> but element 1 is <span>"]
+        );
+        let shipped = r#"<!--client-only--><div></div><!--/client-only-->"#;
+        assert_eq!(
+            verify_client_only_bindings(script, template, shipped),
+            vec!["client-only block 0: binding at [0, 0] expects <p> but element 1 is missing"]
+        );
+    }
+
+    #[test]
+    fn test_verify_signal_comments() {
+        let template = r#"<div><p>{{ count }}</p><button @click="inc">+</button></div>"#;
+        let paths = vec![vec![0, 0], vec![0, 1]];
+        let (shipped, _) = inject_signal_comments(template, &paths, ElementNames::Path);
+        assert!(shipped.contains("<!--v:0_1--><button"));
+        assert!(verify_signal_comments(template, &paths, &shipped, ElementNames::Path).is_empty());
+
+        let shipped = "<div><!--v:0-->\n  <span>0</span></div>";
+        assert_eq!(
+            verify_signal_comments(template, &paths, shipped, ElementNames::Ordinal),
+            vec![
+                "signal binding v:0 at [0, 0] expects <p> but anchors <span>",
+                "signal binding v:1 at [0, 1] expects <button> but its anchor is missing",
+            ]
+        );
+        assert_eq!(
+            verify_signal_comments(template, &paths[..1], "<div><!--v:0_0--></div>", ElementNames::Path),
+            vec!["signal binding v:0_0 at [0, 0] expects <p> but no element follows its anchor"]
+        );
+    }
+
     #[test]
     fn test_walk_template_no_reactive_text() {
         let html = r#"<div><p>Hello {{ name }}</p></div>"#;
@@ -2096,7 +3646,7 @@ function decrement() { count.value-- }
         // Simulate resolved body content
         let html = r#"<body><nav>nav</nav><main><h1>Title</h1><div class="counter"><p>Count: {{ count }}</p><button @click="increment">+1</button><button @click="decrement">-1</button></div></main></body>"#;
 
-        let js = generate_signals(script, html, &[], "Van").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
 
         // Should use positional paths, NOT querySelectorAll
         assert!(!js.contains("querySelectorAll"));
@@ -2123,7 +3673,7 @@ function decrement() { count.value-- }
 defineProps({ title: String })
 "#;
         let html = r#"<div><h1>Hello</h1></div>"#;
-        assert!(generate_signals(script, html, &[], "Van").is_none());
+        assert!(generate_signals(script, html, &[], &SignalOptions::default()).is_none());
     }
 
     #[test]
@@ -2140,6 +3690,74 @@ defineProps({ title: String })
         );
     }
 
+    #[test]
+    fn test_transform_expr_dollar_names() {
+        let names = vec!["$query", "count"];
+        assert_eq!(transform_expr("$query.trim() + count2", &names), "$query.value.trim() + count2");
+        assert_eq!(transform_expr("$query.value = ''", &names), "$query.value = ''");
+    }
+
+    #[test]
+    fn test_transform_expr_store_fields() {
+        let names = vec!["cart.count", "cart"];
+        assert_eq!(transform_expr("cart.count + 1", &names), "cart.count.value + 1");
+        assert_eq!(transform_expr("cart.count.value++", &names), "cart.count.value++");
+        assert_eq!(transform_expr("cart.counter", &names), "cart.value.counter");
+        assert_eq!(transform_expr("cart.add()", &["cart.count"]), "cart.add()");
+        assert!(references_name("cart.count > 0", "cart.count"));
+        assert!(!references_name("cart.counter", "cart.count"));
+    }
+
+    #[test]
+    fn test_transform_expr_skips_property_access() {
+        let names = vec!["status", "name"];
+        assert_eq!(
+            transform_expr("name = e.target.name; status = res.status || status", &names),
+            "name.value = e.target.name; status.value = res.status || status.value"
+        );
+        assert_eq!(
+            transform_expr("emit('status', { status: status })", &names),
+            "emit('status', { status: status.value })"
+        );
+    }
+
+    #[test]
+    fn test_generate_signals_handler_with_dom_property() {
+        let script = "const $query = ref('')\nconst status = ref('idle')\nfunction onInput(e) { $query = e.target.value; status = e.target.name }";
+        let html = r#"<input @input="onInput"><p>{{ status }}: {{ $query }}</p>"#;
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        assert!(js.contains("$query.value = e.target.value; status.value = e.target.name"), "Got:\n{js}");
+        assert!(js.contains("textContent = status.value + ': ' + $query.value;"), "Got:\n{js}");
+    }
+
+    #[test]
+    fn test_generate_signals_keeps_other_statements() {
+        let script = r#"import Card from './card.van'
+const API = 'https://api.example.com'
+const items = ref([])
+const props = defineProps({ title: String })
+let retries = count()
+async function load() { items = await fetch(API).then(r => r.json()) }
+load()
+document.title = `${items.length} items`
+watch(items, (v) => { retries = 0 })"#;
+        let analysis = analyze_script(script);
+        assert_eq!(
+            analysis.residual,
+            vec!["var retries = count();", "load()", "document.title = `${items.length} items`"]
+        );
+
+        let html = r#"<button @click="load">Reload</button><p>{{ items.length }}</p>"#;
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        let constant = js.find(r#"var API = "https://api.example.com";"#).expect(&js);
+        let function = js.find("async function load()").expect(&js);
+        let call = js.find("  load()\n").expect(&js);
+        assert!(constant < js.find("V.signal([])").unwrap(), "Got:\n{js}");
+        assert!(function < call && call < js.find("V.watch").unwrap(), "Got:\n{js}");
+        assert!(js.contains("document.title = `${items.value.length} items`"), "Got:\n{js}");
+        assert!(!js.contains("defineProps"), "Got:\n{js}");
+    }
+
     #[test]
     fn test_runtime_js_included() {
         assert!(RUNTIME_JS.contains("__VAN_NS__"));
@@ -2148,6 +3766,66 @@ defineProps({ title: String })
         assert!(RUNTIME_JS.contains("computed"));
     }
 
+    /// Run `script` under node after the runtime (as `Van`), with stubs for
+    /// the browser globals it touches, returning what the script logs. The
+    /// tests using it are ignored by default since they need node on `PATH`.
+    fn run_runtime(script: &str) -> String {
+        let prelude = "globalThis.window = globalThis;\n\
+            var storage = {};\n\
+            Object.defineProperty(globalThis, 'localStorage', { configurable: true, value: {\n\
+              getItem: function(k) { return k in storage ? storage[k] : null; },\n\
+              setItem: function(k, v) { storage[k] = String(v); }\n\
+            } });\n\
+            globalThis.addEventListener = function() {};\n";
+        let output = std::process::Command::new("node")
+            .arg("-e")
+            .arg(format!("{prelude}{}\n{script}", runtime_js("Van")))
+            .output()
+            .expect("node not found on PATH");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs node"]
+    fn test_runtime_store_persists_only_when_asked() {
+        let script = r#"
+storage['van:store:cart'] = '{"count":5}';
+storage['van:store:prefs'] = '{"theme":"dark"}';
+var prefs = Van.store('prefs', function() { return { theme: Van.signal('light') }; });
+var cart = Van.store('cart', function() { return { count: Van.signal(0) }; }, { persist: 'cart' });
+console.log(prefs.theme.value, cart.count.value);
+prefs.theme.value = 'blue';
+cart.count.value = 6;
+console.log(storage['van:store:prefs'], storage['van:store:cart']);
+"#;
+        let out = run_runtime(script);
+        assert_eq!(out, "light 5\n{\"theme\":\"dark\"} {\"count\":6}\n");
+    }
+
+    #[test]
+    #[ignore = "needs node"]
+    fn test_v_cloak_removed_when_an_effect_throws() {
+        let js = generate_signals(
+            "const label = ref(null)",
+            r#"<div v-cloak><p>{{ label.toUpperCase() }}</p></div>"#,
+            &[],
+            &SignalOptions::default(),
+        )
+        .unwrap();
+        let script = format!(
+            "function el(children) {{\n\
+              return {{ children: children || [], cloaked: true, removeAttribute: function(n) {{ if (n === 'v-cloak') this.cloaked = false; }} }};\n\
+            }}\n\
+            var div = el([el()]);\n\
+            globalThis.document = {{ body: el([div]) }};\n\
+            try {{\n{js}}} catch (e) {{ console.log(e.name); }}\n\
+            console.log(div.cloaked);\n"
+        );
+        let out = run_runtime(&script);
+        assert_eq!(out, "TypeError\nfalse\n");
+    }
+
     #[test]
     fn test_runtime_js_default_name() {
         let js = runtime_js("Van");
@@ -2170,7 +3848,7 @@ const count = ref(0)
 function increment() { count.value++ }
 "#;
         let html = r#"<div><p>{{ count }}</p><button @click="increment">+</button></div>"#;
-        let js = generate_signals(script, html, &[], "MyApp").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions { global_name: "MyApp".into(), ..Default::default() }).unwrap();
         assert!(js.contains("var V = MyApp;"));
         assert!(!js.contains("var V = Van;"));
     }
@@ -2191,6 +3869,9 @@ function increment() { count.value++ }
             classes: vec![],
             styles: vec![],
             models: vec![],
+            fors: vec![],
+            attrs: vec![],
+            cloaks: vec![],
         };
         let paths = collect_required_paths(&bindings);
         // Should have: [1], [1,2], [1,2,0], [1,2,1]
@@ -2208,7 +3889,7 @@ const open = ref(false)
 function toggle() { open.value = !open.value }
 "#;
         let html = r#"<div><button @click="toggle">Toggle</button><Transition name="fade"><div v-show="open">Content</div></Transition></div>"#;
-        let js = generate_signals(script, html, &[], "Van").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
         // Should use V.transition() instead of style.display
         assert!(js.contains("V.transition("));
         assert!(js.contains("'fade'"));
@@ -2216,6 +3897,188 @@ function toggle() { open.value = !open.value }
         assert!(!js.contains("style.display"));
     }
 
+    #[test]
+    fn test_generate_signals_transition_options() {
+        let script = "const open = ref(false)";
+        let html = r#"<div><Transition name="fade" appear duration="300"><div v-show="open">A</div></Transition><Transition :duration="{ enter: 200, leave: 400 }"><p v-show="open">B</p></Transition></div>"#;
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        assert!(js.contains("V.transition(_e0_0, v, 'fade', { appear: true, duration: 300, instant: first && !v });"));
+        assert!(js.contains("V.transition(_e0_1, v, 'v', { duration: { enter: 200, leave: 400 }, instant: first });"));
+    }
+
+    #[test]
+    fn test_generate_signals_transition_first_run_is_instant() {
+        let script = "const open = ref(true)";
+        let html = r#"<div><Transition name="fade"><div v-show="open">A</div></Transition></div>"#;
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        assert!(js.contains("(function(first) { return function() { var v = open.value; V.transition(_e0_0, v, 'fade', { instant: first }); first = false; }; })(true)"));
+        assert!(!js.contains("appear"));
+    }
+
+    #[test]
+    fn test_runtime_formats() {
+        let iife = runtime_js_for(RuntimeFormat::Iife, "MyVan");
+        assert!(iife.contains("window.MyVan = {"));
+        assert!(!iife.contains("export"));
+
+        let esm = runtime_js_for(RuntimeFormat::EsModule, "MyVan");
+        assert!(esm.contains("export { signal, computed, effect, batch, transition, watch, sanitize, hydrate, store };"));
+        assert!(esm.contains("function signal(value)"));
+        assert!(!esm.contains("window."));
+        assert!(!esm.contains("})();"));
+    }
+
+    #[test]
+    fn test_signal_module() {
+        let js = generate_signals("const n = ref(0)", "<p>{{ n }}</p>", &[], &SignalOptions::default()).unwrap();
+        let module = signal_module(&js, "Van", "/js/van-runtime.mjs");
+        assert!(module.starts_with("import * as V from \"/js/van-runtime.mjs\";\n(function() {\n"));
+        assert!(!module.contains("var V = Van;"));
+        assert!(module.contains("V.signal(0)"));
+    }
+
+    #[test]
+    fn test_with_teardown() {
+        let script = "const n = ref(0)\nconst double = computed(() => n.value * 2)\nfunction inc() { n.value++ }";
+        let html = r#"<div><p>{{ double }}</p><button @click="inc">+</button><input v-model="n" /></div>"#;
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        let torn = with_teardown(&js, &[], "blog/post");
+        assert!(torn.starts_with("(function() {\n  var V = Van;\n  var _fx = [], _ls = [];\n"));
+        assert!(torn.contains("  var double = _t.computed(function() { return n.value * 2; });\n"));
+        assert!(torn.contains("_t.effect(function() {"));
+        assert_eq!(torn.matches("V.effect(").count(), 1, "only `_t` calls the runtime directly");
+        assert!(torn.contains("  _on(_e0_1, 'click', inc);\n"));
+        assert!(torn.contains("  _on(_e0_2, 'input', function(e) { n.value = e.target.value; });\n"));
+        assert_eq!(torn.matches(".addEventListener(").count(), 1, "only the `_on` helper adds listeners");
+        assert!(torn.contains("  window.__van_pages[\"blog/post\"] = {\n    destroy: function() {\n"));
+        assert!(torn.contains("l[0].removeEventListener(l[1], l[2]);"));
+        assert!(torn.ends_with("  };\n})();\n"));
+    }
+
+    #[test]
+    fn test_element_names_survive_unrelated_bindings() {
+        let script = "const n = ref(0)\nfunction inc() { n.value++ }";
+        let before = r#"<header><p>{{ n }}</p></header><main><div><button @click="inc">+</button></div></main>"#;
+        let after = r#"<header><p>{{ n }}</p><span>{{ n + 1 }}</span></header><main><div><button @click="inc">+</button></div></main>"#;
+        let generate = |html, names| generate_signals(script, html, &[], &SignalOptions { element_names: names, ..Default::default() }).unwrap();
+
+        let (old, new) = (generate(before, ElementNames::Path), generate(after, ElementNames::Path));
+        assert!(new.contains("  var _e0_1 = _e0.children[1];\n"));
+        for line in old.lines() {
+            assert!(new.lines().any(|l| l == line), "{line:?} changed:\n{new}");
+        }
+        assert!(new.contains("  _e1_0_0.addEventListener('click', inc);\n"));
+
+        let (old, new) = (generate(before, ElementNames::Ordinal), generate(after, ElementNames::Ordinal));
+        assert!(old.contains("  _e4.addEventListener('click', inc);\n"));
+        assert!(new.contains("  _e5.addEventListener('click', inc);\n"), "one binding earlier renames the rest");
+    }
+
+    #[test]
+    fn test_element_names_survive_unrelated_bindings_comment_and_compile() {
+        let script = "const n = ref(0)\nfunction inc() { n.value++ }";
+        let before = r#"<header><p>{{ n }}</p></header><main><div><button @click="inc">+</button></div></main>"#;
+        let after = r#"<header><p>{{ n }}</p><span>{{ n + 1 }}</span></header><main><div><button @click="inc">+</button></div></main>"#;
+
+        let comment = |html, names| generate_signals_comment(script, html, &[], &SignalOptions { element_names: names, ..Default::default() }).unwrap();
+        let (old, new) = (comment(before, ElementNames::Path), comment(after, ElementNames::Path));
+        for js in [&old, &new] {
+            assert!(js.contains("_ve['0_0'].textContent"), "{js}");
+            assert!(js.contains("_ve['1_0_0'].addEventListener('click', inc)"), "{js}");
+        }
+        assert!(new.contains("_ve['0_1'].textContent"));
+        let (old, new) = (comment(before, ElementNames::Ordinal), comment(after, ElementNames::Ordinal));
+        assert!(old.contains("_ve[1].addEventListener('click', inc)"), "{old}");
+        assert!(new.contains("_ve[2].addEventListener('click', inc)"), "{new}");
+
+        let (marked, _) = inject_signal_comments(after, &[vec![0, 0], vec![0, 1], vec![1, 0, 0]], ElementNames::Path);
+        assert!(marked.contains("<!--v:0_0--><p>") && marked.contains("<!--v:0_1--><span>") && marked.contains("<!--v:1_0_0--><button"));
+
+        let compile = |html| {
+            let block = format!("<!--client-only-->{html}<!--/client-only-->");
+            generate_signals_compile(script, &block, &[], &SignalOptions::default()).unwrap()
+        };
+        let (old, new) = (compile(before), compile(after));
+        for js in [&old, &new] {
+            assert!(js.contains("_blocks[0]['1_0_0'].addEventListener('click', inc)"), "{js}");
+        }
+        assert!(new.contains("_blocks[0]['0_1']"));
+    }
+
+    #[test]
+    fn test_lazy_hydration() {
+        let script = "const n = ref(0)\nfunction inc() { n.value++ }";
+        let html = r#"<header><p>{{ n }}</p></header><main v-cloak><div><button @click="inc">+</button></div></main>"#;
+        let js = generate_signals(script, html, &[], &SignalOptions { hydration: Hydration::Lazy, ..Default::default() }).unwrap();
+        assert!(js.contains("  var _e0 = _r.children[0];\n  var _e1 = _r.children[1];\n"), "roots and cloaked elements are found up front");
+        assert!(js.contains("  try {\n    V.hydrate(_e0, function() {\n      var _e0_0 = _e0.children[0];\n      V.effect(function() { _e0_0.textContent = n.value; });\n    });\n"));
+        assert!(js.contains("    V.hydrate(_e1, function() {\n      var _e1_0 = _e1.children[0];\n      var _e1_0_0 = _e1_0.children[0];\n      _e1_0_0.addEventListener('click', inc);\n    });\n"));
+        assert!(js.contains("    });\n  } finally {\n    _e1.removeAttribute('v-cloak');\n  }\n"), "uncloaked without waiting for the gate");
+
+        let torn = with_teardown(&js, &[], "index");
+        assert!(torn.contains("  _t.hydrate(_e0, function() {\n"));
+        assert!(torn.contains("    _on(_e1_0_0, 'click', inc);\n"));
+        assert!(torn.contains("    _t.effect(function() { _e0_0.textContent = n.value; });\n"));
+
+        let js = generate_signals_comment(script, html, &[], &SignalOptions { hydration: Hydration::Lazy, element_names: ElementNames::Ordinal, ..Default::default() }).unwrap();
+        assert!(js.contains("    V.hydrate(_ve[0], function() {\n      V.effect(function() { _ve[0].textContent = n.value; });\n    });\n"));
+        assert!(js.contains("    V.hydrate(_ve[1], function() {\n      _ve[2].addEventListener('click', inc);\n    });\n"));
+
+        let block = format!("<!--client-only-->{html}<!--/client-only-->");
+        let js = generate_signals_compile(script, &block, &[], &SignalOptions { hydration: Hydration::Lazy, ..Default::default() }).unwrap();
+        assert!(js.contains("    V.hydrate(_blocks[0]['0_0'], function() {\n      V.effect(function() { _blocks[0]['0_0'].textContent = n.value; });\n    });\n"), "{js}");
+        assert!(js.contains("    V.hydrate(_blocks[0]['1'], function() {\n      _blocks[0]['1_0_0'].addEventListener('click', inc);\n    });\n  } finally {\n    _blocks[0]['1'].removeAttribute('v-cloak');\n"));
+
+        let eager = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
+        assert!(!eager.contains("V.hydrate("));
+        assert_eq!("lazy".parse(), Ok(Hydration::Lazy));
+        assert!("later".parse::<Hydration>().is_err());
+    }
+
+    #[test]
+    fn test_split_signals() {
+        let script = "const n = ref(0)\nfunction inc() { n.value++ }";
+        let block = r#"<!--client-only--><div><p>{{ n }}</p><button @click="inc">+</button></div><!--/client-only-->"#;
+        let html = block.repeat(40);
+        let js = generate_signals_compile(script, &html, &[], &SignalOptions::default()).unwrap();
+        assert_eq!(split_signals(&js, "index", js.len()), vec![js.clone()]);
+
+        let chunks = split_signals(&js, "index", 1500);
+        assert_eq!(chunks.len(), 5);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 1500));
+        assert!(chunks[0].contains("  var n = V.signal(0);\n"));
+        assert!(chunks[0].contains("  window.__van_chunks[\"index\"] = { V: V, n: n, inc: inc, _blocks: _blocks };\n})();\n"));
+        assert!(!chunks[0].contains("addEventListener"));
+        for chunk in &chunks[1..] {
+            assert!(chunk.starts_with("(function(_s) {\n  var V = _s.V, n = _s.n, inc = _s.inc, _blocks = _s._blocks;\n"));
+            assert!(chunk.ends_with("})(window.__van_chunks[\"index\"]);\n"));
+        }
+        for i in 0..40 {
+            let wired = format!("_blocks[{i}]['0_1'].addEventListener('click', inc);");
+            assert_eq!(chunks.iter().filter(|chunk| chunk.contains(&wired)).count(), 1);
+            let block = format!("_blocks[{i}][");
+            assert_eq!(chunks.iter().filter(|chunk| chunk.contains(&block)).count(), 1, "block {i} stays in one chunk");
+        }
+
+        let torn = with_teardown(&js, &[], "index");
+        let chunks = split_signals(&torn, "index", 1500);
+        assert!(chunks[0].contains("_t: _t, _on: _on"));
+        assert!(chunks[0].contains("window.__van_pages[\"index\"] = {"));
+        assert!(chunks[1].contains("_on(_blocks[0]['0_1'], 'click', inc);"));
+    }
+
+    #[test]
+    fn test_with_teardown_leaves_modules_alone() {
+        let modules = vec![ModuleCode {
+            code: "export function onResize(fn) {\n  window.addEventListener('resize', fn);\n}".to_string(),
+            bindings: vec![("onResize".to_string(), "onResize".to_string())],
+        }];
+        let js = generate_signals("const n = ref(0)", "<p>{{ n }}</p>", &modules, &SignalOptions::default()).unwrap();
+        let torn = with_teardown(&js, &modules, "index");
+        assert!(torn.contains("  window.addEventListener('resize', fn);\n"));
+        assert!(torn.contains("var onResize = __mod_0.onResize;"));
+    }
+
     #[test]
     fn test_runtime_js_has_transition() {
         assert!(RUNTIME_JS.contains("transition"));
@@ -2284,13 +4147,25 @@ function toggle() { open.value = !open.value }
         assert_eq!(pairs[1], ("fontSize".into(), "s".into()));
     }
 
+    #[test]
+    fn test_generate_signals_sanitized_v_html() {
+        let script = "const html = ref('')";
+        let template = r#"<div v-html="html"></div>"#;
+        let js = generate_signals(script, template, &[], &SignalOptions { sanitize_html: true, ..Default::default() }).unwrap();
+        assert!(js.contains("_e0.innerHTML = V.sanitize(html.value);"), "Got:\n{js}");
+        let js = generate_signals(script, template, &[], &SignalOptions::default()).unwrap();
+        assert!(js.contains("_e0.innerHTML = html.value;"), "Got:\n{js}");
+        let js = generate_signals_comment(script, template, &[], &SignalOptions { sanitize_html: true, ..Default::default() }).unwrap();
+        assert!(js.contains("_ve['0'].innerHTML = V.sanitize(html.value);"), "Got:\n{js}");
+    }
+
     #[test]
     fn test_generate_signals_class_binding() {
         let script = r#"
 const isActive = ref(true)
 "#;
         let html = r#"<div :class="[{ active: isActive }, 'base']"><p>Hello</p></div>"#;
-        let js = generate_signals(script, html, &[], "Van").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
         // Should have classList.toggle for object item
         assert!(js.contains("classList.toggle('active'"));
         // Should have classList.add for static item
@@ -2304,7 +4179,7 @@ const textColor = ref('red')
 const size = ref('16px')
 "#;
         let html = r#"<div :style="[{ color: textColor }, { fontSize: size }]">Hello</div>"#;
-        let js = generate_signals(script, html, &[], "Van").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
         assert!(js.contains("style.color"));
         assert!(js.contains("style.fontSize"));
         assert!(js.contains("textColor.value"));
@@ -2319,13 +4194,15 @@ const count = ref(0)
 function increment() { count.value++ }
 "#;
         let html = r#"<body><div><p>Count: {{ count }}</p><button @click="increment">+1</button></div></body>"#;
-        let modules = vec![
-            "function formatDate(d) { return d.toISOString(); }\nreturn { formatDate: formatDate };".to_string(),
-        ];
-        let js = generate_signals(script, html, &modules, "Van").unwrap();
+        let modules = vec![ModuleCode {
+            code: "export function formatDate(d) { return d.toISOString(); }".to_string(),
+            bindings: vec![("formatDate".to_string(), "formatDate".to_string())],
+        }];
+        let js = generate_signals(script, html, &modules, &SignalOptions::default()).unwrap();
         // Should have module IIFE
         assert!(js.contains("var __mod_0 = (function()"));
-        assert!(js.contains("formatDate"));
+        assert!(js.contains("return { formatDate: formatDate };"));
+        assert!(js.contains("var formatDate = __mod_0.formatDate;"));
         // Should still have signal code
         assert!(js.contains("V.signal(0)"));
         // Import line should be stripped — not cause issues
@@ -2340,7 +4217,7 @@ import type { User } from '../types.ts'
 const count = ref(0)
 "#;
         let html = r#"<div><p>{{ count }}</p></div>"#;
-        let js = generate_signals(script, html, &[], "Van").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
         assert!(js.contains("V.signal(0)"));
     }
 
@@ -2397,6 +4274,44 @@ const config = ref({ theme: 'dark' })
         assert!(analysis.signals[1].initial_value.contains("theme"));
     }
 
+    #[test]
+    fn test_walk_template_skips_v_raw() {
+        let html = r#"<div v-raw @click="x"><p>{{ count }}</p></div><p>{{ count }}</p>"#;
+        let bindings = walk_template(html, &["count"]);
+        assert!(bindings.events.is_empty());
+        assert_eq!(bindings.texts.len(), 1);
+        assert_eq!(bindings.texts[0].path, vec![1]);
+    }
+
+    #[test]
+    fn test_analyze_literal_constants() {
+        let script = r#"
+const siteName = 'Acme'
+const links = [{ href: '/', label: `Home` }, { 'href': '/about', order: -2.5 }]
+const max = 10, debug = false
+let mutable = 'x'
+const user = getUser()
+const count = ref(0)
+const mixed = [1, max]
+"#;
+        let analysis = analyze_script(script);
+        let constants: Vec<(&str, String)> = analysis
+            .constants
+            .iter()
+            .map(|c| (c.name.as_str(), c.value.to_string()))
+            .collect();
+        assert_eq!(
+            constants,
+            vec![
+                ("siteName", r#""Acme""#.to_string()),
+                ("links", r#"[{"href":"/","label":"Home"},{"href":"/about","order":-2.5}]"#.to_string()),
+                ("max", "10".to_string()),
+                ("debug", "false".to_string()),
+            ]
+        );
+        assert_eq!(analysis.signals.len(), 1);
+    }
+
     #[test]
     fn test_generate_signals_arrow_click_handler() {
         // THE critical test: @click with arrow function handler
@@ -2405,7 +4320,7 @@ const count = ref(0)
 const increment = () => count.value++
 "#;
         let html = r#"<body><p>{{ count }}</p><button @click="increment">+1</button></body>"#;
-        let js = generate_signals(script, html, &[], "Van").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
         // Arrow function should be declared in the IIFE
         assert!(js.contains("function increment("));
         // Event handler should reference the function directly
@@ -2421,7 +4336,7 @@ const count = ref(0)
 const add = (n) => { count.value += n }
 "#;
         let html = r#"<body><p>{{ count }}</p><button @click="add(5)">+5</button></body>"#;
-        let js = generate_signals(script, html, &[], "Van").unwrap();
+        let js = generate_signals(script, html, &[], &SignalOptions::default()).unwrap();
         assert!(js.contains("function add(n)"));
     }
 }
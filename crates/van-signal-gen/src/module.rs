@@ -0,0 +1,221 @@
+use regex::Regex;
+
+use crate::{analyze_script, js_literal_to_display};
+
+/// A `.ts`/`.js` module to inline into the signal script.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleCode {
+    /// Module source (types already erased).
+    pub code: String,
+    /// `(local, exported)` names the importing scripts bind, e.g.
+    /// `("fmt", "formatDate")` for `import { formatDate as fmt }`.
+    /// The exported name is `default` for default imports and `*` for
+    /// namespace imports.
+    pub bindings: Vec<(String, String)>,
+}
+
+/// A reactive field of a store an importing script binds, e.g. `cart.count`
+/// for `import { cart } from './stores/cart.ts'`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreField {
+    /// `local.field`, the name templates bind.
+    pub name: String,
+    /// Display value of the field's `ref()` initial value; `None` for
+    /// computeds.
+    pub initial: Option<String>,
+}
+
+/// The store fields `modules` hand to their importers.
+///
+/// A store is an `export const cart = defineStore('cart', () => { ... })`
+/// whose setup function ends in `return { ... }`; its fields are the returned
+/// names declared there with `ref()` or `computed()`.
+pub fn store_fields(modules: &[ModuleCode]) -> Vec<StoreField> {
+    let store_re =
+        Regex::new(r#"(?m)^[ \t]*export[ \t]+const[ \t]+([A-Za-z_$][\w$]*)[ \t]*=[ \t]*defineStore\(\s*['"][^'"]*['"]"#).unwrap();
+    let return_re = Regex::new(r"return\s*\{([^}]*)\}").unwrap();
+    let mut fields = Vec::new();
+    for module in modules {
+        for caps in store_re.captures_iter(&module.code) {
+            let start = caps.get(0).unwrap().end();
+            let setup = &module.code[start..call_end(&module.code, start)];
+            // The setup function's statements, up to its `return { ... }`
+            let body = setup.find('{').map_or("", |open| &setup[open + 1..]);
+            let Some(returned) = return_re.captures_iter(body).last() else {
+                continue;
+            };
+            let analysis = analyze_script(&body[..returned.get(0).unwrap().start()]);
+            let locals: Vec<&str> = module.bindings.iter().filter(|(_, e)| *e == caps[1]).map(|(l, _)| l.as_str()).collect();
+            for entry in returned[1].split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let (key, value) = entry.split_once(':').map_or((entry, entry), |(k, v)| (k.trim(), v.trim()));
+                let initial = match analysis.signals.iter().find(|s| s.name == value) {
+                    Some(signal) => Some(js_literal_to_display(&signal.initial_value)),
+                    None if analysis.computeds.iter().any(|c| c.name == value) => None,
+                    None => continue,
+                };
+                for local in &locals {
+                    fields.push(StoreField { name: format!("{local}.{key}"), initial: initial.clone() });
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// The end of the call whose arguments run from `start`: the offset of its
+/// closing `)`, skipping nested brackets and string literals.
+fn call_end(code: &str, start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut chars = code[start..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(' | '{' | '[') => depth += 1,
+            (None, ')') if depth == 0 => return start + i,
+            (None, ')' | '}' | ']') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+/// Emit the inlined modules: one IIFE per module, then the importers' bindings.
+/// Modules defining stores get `defineStore`, `ref` and `computed` from the
+/// runtime.
+pub(crate) fn modules_js(modules: &[ModuleCode]) -> String {
+    let mut js = String::new();
+    for (i, module) in modules.iter().enumerate() {
+        let prelude = if module.code.contains("defineStore(") {
+            "var defineStore = V.store, ref = V.signal, computed = V.computed; "
+        } else {
+            ""
+        };
+        js.push_str(&format!(
+            "  var __mod_{} = (function() {{ {}{} }})();\n",
+            i,
+            prelude,
+            module_body(&module.code).trim()
+        ));
+    }
+    for (i, module) in modules.iter().enumerate() {
+        for (local, exported) in &module.bindings {
+            if exported == "*" {
+                js.push_str(&format!("  var {local} = __mod_{i};\n"));
+            } else {
+                js.push_str(&format!("  var {local} = __mod_{i}.{exported};\n"));
+            }
+        }
+    }
+    js
+}
+
+/// Turn ES module exports into plain declarations plus a trailing
+/// `return { ... }` of the exported names.
+///
+/// Modules without `export` statements are kept as-is, so files that end with
+/// their own `return { ... }` keep working.
+fn module_body(code: &str) -> String {
+    let decl_re = Regex::new(
+        r"(?m)^([ \t]*)export[ \t]+(default[ \t]+)?((?:async[ \t]+)?function\*?|const|let|var|class)[ \t]+([A-Za-z_$][\w$]*)",
+    )
+    .unwrap();
+    let default_re = Regex::new(r"(?m)^([ \t]*)export[ \t]+default[ \t]+").unwrap();
+    let list_re = Regex::new(r"(?m)^[ \t]*export[ \t]*\{([^}]*)\}[ \t]*;?[ \t]*\n?").unwrap();
+
+    let mut exports: Vec<(String, String)> = Vec::new();
+    let body = decl_re.replace_all(code, |caps: &regex::Captures| {
+        let name = caps[4].to_string();
+        let exported = if caps.get(2).is_some() { "default".to_string() } else { name.clone() };
+        exports.push((exported, name));
+        format!("{}{} {}", &caps[1], &caps[3], &caps[4])
+    });
+    let body = default_re.replace_all(&body, |caps: &regex::Captures| {
+        exports.push(("default".to_string(), "__default".to_string()));
+        format!("{}var __default = ", &caps[1])
+    });
+    let body = list_re.replace_all(&body, |caps: &regex::Captures| {
+        for spec in caps[1].split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (local, exported) = spec.split_once(" as ").map_or((spec, spec), |(l, e)| (l.trim(), e.trim()));
+            exports.push((exported.to_string(), local.to_string()));
+        }
+        String::new()
+    });
+
+    if exports.is_empty() {
+        return code.to_string();
+    }
+    let fields: Vec<String> = exports.iter().map(|(exported, local)| format!("{exported}: {local}")).collect();
+    format!("{}\nreturn {{ {} }};", body.trim_end(), fields.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_body_named_exports() {
+        let code = "export function pad(n) { return String(n).padStart(2, '0'); }\nexport const SEP = ':';\nfunction helper() {}\nexport { helper as util };\n";
+        assert_eq!(
+            module_body(code),
+            "function pad(n) { return String(n).padStart(2, '0'); }\nconst SEP = ':';\nfunction helper() {}\nreturn { pad: pad, SEP: SEP, util: helper };"
+        );
+    }
+
+    #[test]
+    fn test_module_body_default_exports() {
+        assert_eq!(
+            module_body("export default function format(d) { return d; }"),
+            "function format(d) { return d; }\nreturn { default: format };"
+        );
+        assert_eq!(
+            module_body("export default { a: 1 };"),
+            "var __default = { a: 1 };\nreturn { default: __default };"
+        );
+    }
+
+    #[test]
+    fn test_module_body_without_exports_is_unchanged() {
+        let code = "function f() {}\nreturn { f: f };";
+        assert_eq!(module_body(code), code);
+    }
+
+    #[test]
+    fn test_modules_js_bindings() {
+        let modules = vec![ModuleCode {
+            code: "export function formatDate(d) { return d; }".to_string(),
+            bindings: vec![
+                ("formatDate".to_string(), "formatDate".to_string()),
+                ("fmt".to_string(), "default".to_string()),
+                ("utils".to_string(), "*".to_string()),
+            ],
+        }];
+        assert_eq!(
+            modules_js(&modules),
+            "  var __mod_0 = (function() { function formatDate(d) { return d; }\nreturn { formatDate: formatDate }; })();\n  var formatDate = __mod_0.formatDate;\n  var fmt = __mod_0.default;\n  var utils = __mod_0;\n"
+        );
+    }
+
+    #[test]
+    fn test_store_fields() {
+        let code = "export const cart = defineStore('cart', () => {\n  const count = ref(0)\n  const owner = ref('guest')\n  const empty = computed(() => count.value === 0)\n  function add() { count.value++ }\n  return { count, owner: owner, empty, add }\n}, { persist: 'cart' })\nexport function unrelated() { return { count: 1 } }\n";
+        let modules = vec![ModuleCode {
+            code: code.to_string(),
+            bindings: vec![("basket".to_string(), "cart".to_string()), ("unrelated".to_string(), "unrelated".to_string())],
+        }];
+        assert_eq!(
+            store_fields(&modules),
+            vec![
+                StoreField { name: "basket.count".to_string(), initial: Some("0".to_string()) },
+                StoreField { name: "basket.owner".to_string(), initial: Some("guest".to_string()) },
+                StoreField { name: "basket.empty".to_string(), initial: None },
+            ]
+        );
+        assert!(modules_js(&modules).starts_with("  var __mod_0 = (function() { var defineStore = V.store, ref = V.signal, computed = V.computed; const cart = defineStore("));
+    }
+}
@@ -7,7 +7,10 @@
     var subs = [];
     var s = {
       get value() {
-        if (context && subs.indexOf(context) === -1) subs.push(context);
+        if (context && subs.indexOf(context) === -1) {
+          subs.push(context);
+          context.deps.push(subs);
+        }
         return value;
       },
       set value(v) {
@@ -23,17 +26,29 @@
 
   function computed(fn) {
     var s = signal(undefined);
-    effect(function() { s.value = fn(); });
-    return { get value() { return s.value; }, peek: function() { return s.peek(); } };
+    var dispose = effect(function() { s.value = fn(); });
+    return { get value() { return s.value; }, peek: function() { return s.peek(); }, dispose: dispose };
   }
 
+  // Returns a disposer that unsubscribes the effect from every signal it read.
   function effect(fn) {
+    var disposed = false;
     var run = function() {
+      if (disposed) return;
       var prev = context;
       context = run;
       try { fn(); } finally { context = prev; }
     };
+    run.deps = [];
     run();
+    return function dispose() {
+      disposed = true;
+      for (var i = 0; i < run.deps.length; i++) {
+        var at = run.deps[i].indexOf(run);
+        if (at !== -1) run.deps[i].splice(at, 1);
+      }
+      run.deps = [];
+    };
   }
 
   var batchQueue = null;
@@ -50,40 +65,42 @@
     }
   }
 
-  function transition(el, show, name) {
+  function transition(el, show, name, opts) {
     var p = name || 'v';
-    if (!el.__van_t) { el.__van_t = true; el.style.display = show ? '' : 'none'; return; }
-    if (show) {
-      el.style.display = '';
-      el.classList.add(p + '-enter-from', p + '-enter-active');
-      requestAnimationFrame(function() { requestAnimationFrame(function() {
-        el.classList.remove(p + '-enter-from');
-        el.classList.add(p + '-enter-to');
-        var done = function() {
-          el.classList.remove(p + '-enter-active', p + '-enter-to');
-          el.removeEventListener('transitionend', done);
-        };
-        el.addEventListener('transitionend', done);
-      }); });
-    } else {
-      el.classList.add(p + '-leave-from', p + '-leave-active');
-      requestAnimationFrame(function() { requestAnimationFrame(function() {
-        el.classList.remove(p + '-leave-from');
-        el.classList.add(p + '-leave-to');
-        var done = function() {
-          el.classList.remove(p + '-leave-active', p + '-leave-to');
-          el.style.display = 'none';
-          el.removeEventListener('transitionend', done);
-        };
-        el.addEventListener('transitionend', done);
-      }); });
-    }
+    var cls = show
+      ? [p + '-enter-from', p + '-enter-active', p + '-enter-to']
+      : [p + '-leave-from', p + '-leave-active', p + '-leave-to'];
+    var d = opts && opts.duration;
+    var ms = d != null && typeof d === 'object' ? d[show ? 'enter' : 'leave'] : d;
+    if (el.__van_t) el.__van_t();
+    if (opts && opts.instant) { el.style.display = show ? '' : 'none'; return; }
+    var frame, timer;
+    var cancel = function() {
+      cancelAnimationFrame(frame);
+      clearTimeout(timer);
+      el.removeEventListener('transitionend', done);
+      el.classList.remove(cls[0], cls[1], cls[2]);
+      el.__van_t = null;
+    };
+    var done = function() {
+      cancel();
+      if (!show) el.style.display = 'none';
+    };
+    el.__van_t = cancel;
+    if (show) el.style.display = '';
+    el.classList.add(cls[0], cls[1]);
+    frame = requestAnimationFrame(function() { frame = requestAnimationFrame(function() {
+      el.classList.remove(cls[0]);
+      el.classList.add(cls[2]);
+      if (ms != null) timer = setTimeout(done, ms);
+      else el.addEventListener('transitionend', done);
+    }); });
   }
 
   function watch(source, fn) {
     var prev;
     var first = true;
-    effect(function() {
+    return effect(function() {
       var val = typeof source === 'function' ? source() : source.value;
       if (!first) { fn(val, prev); }
       prev = val;
@@ -91,12 +108,112 @@
     });
   }
 
+  // Lazy hydration: run `wire` once the top-level element holding `el`
+  // scrolls into view, is pointed at or takes focus (right away when it
+  // can't be observed or isn't displayed). Returns a function that cancels
+  // the wait.
+  function hydrate(el, wire) {
+    while (el.parentElement && el.parentElement !== document.body) el = el.parentElement;
+    var io = null;
+    function stop() {
+      if (io) io.disconnect();
+      el.removeEventListener('pointerover', run);
+      el.removeEventListener('focusin', run);
+      io = null;
+      wire = null;
+    }
+    function run() {
+      var fn = wire;
+      stop();
+      if (fn) fn();
+    }
+    if (typeof IntersectionObserver === 'undefined' || !el.getClientRects().length) {
+      run();
+      return stop;
+    }
+    io = new IntersectionObserver(function(entries) {
+      for (var i = 0; i < entries.length; i++) if (entries[i].isIntersecting) return run();
+    });
+    io.observe(el);
+    el.addEventListener('pointerover', run);
+    el.addEventListener('focusin', run);
+    return stop;
+  }
+
+  var stores = {};
+
+  // defineStore(name, setup, options): the first call runs setup, later ones
+  // (other components on the page) get the same object. With
+  // { persist: 'cart' }, writable signals it returns are saved to localStorage
+  // under 'van:store:cart', restored on the next page, and follow changes made
+  // in other tabs.
+  function store(name, setup, options) {
+    if (stores[name]) return stores[name];
+    var state = stores[name] = setup();
+    if (options && options.persist) persist(state, 'van:store:' + options.persist);
+    return state;
+  }
+
+  function persist(state, key) {
+    var fields = Object.keys(state).filter(function(k) {
+      var s = state[k];
+      return s && typeof s.peek === 'function' && !s.dispose;
+    });
+    function restore(json) {
+      var saved;
+      try { saved = JSON.parse(json); } catch (e) { return; }
+      if (!saved) return;
+      batch(function() {
+        for (var i = 0; i < fields.length; i++) {
+          if (fields[i] in saved) state[fields[i]].value = saved[fields[i]];
+        }
+      });
+    }
+    try { restore(localStorage.getItem(key)); } catch (e) {}
+    effect(function() {
+      var saved = {};
+      for (var i = 0; i < fields.length; i++) saved[fields[i]] = state[fields[i]].value;
+      try { localStorage.setItem(key, JSON.stringify(saved)); } catch (e) {}
+    });
+    addEventListener('storage', function(e) {
+      if (e.key === key) restore(e.newValue);
+    });
+  }
+
+  var URL_ATTRS = ['href', 'src', 'action', 'formaction', 'xlink:href'];
+
+  // Drop <script> elements, on* handlers and javascript: URLs from markup
+  // bound with v-html (only emitted when the page opts into sanitizing).
+  function sanitize(html) {
+    var tpl = document.createElement('template');
+    tpl.innerHTML = html == null ? '' : String(html);
+    var els = tpl.content.querySelectorAll('*');
+    for (var i = 0; i < els.length; i++) {
+      var el = els[i];
+      if (el.tagName.toLowerCase() === 'script') {
+        if (el.parentNode) el.parentNode.removeChild(el);
+        continue;
+      }
+      for (var j = el.attributes.length - 1; j >= 0; j--) {
+        var name = el.attributes[j].name.toLowerCase();
+        var value = el.attributes[j].value.replace(/[\s\u0000-\u001f]/g, '').toLowerCase();
+        if (name.indexOf('on') === 0 || (URL_ATTRS.indexOf(name) !== -1 && value.indexOf('javascript:') === 0)) {
+          el.removeAttribute(el.attributes[j].name);
+        }
+      }
+    }
+    return tpl.innerHTML;
+  }
+
   window.__VAN_NS__ = {
     signal: signal,
     computed: computed,
     effect: effect,
     batch: batch,
     transition: transition,
-    watch: watch
+    watch: watch,
+    sanitize: sanitize,
+    hydrate: hydrate,
+    store: store
   };
 })();
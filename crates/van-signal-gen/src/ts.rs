@@ -0,0 +1,628 @@
+//! Conservative TypeScript type erasure for inlined scripts.
+//!
+//! Handles the syntax that commonly shows up in utility modules and
+//! `<script setup lang="ts">` blocks: parameter, return and variable
+//! annotations, `interface`/`type` declarations, `as`/`satisfies` casts,
+//! non-null assertions and generic parameters on function declarations.
+//! Strings, template literals, regexes and comments are never touched.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Space,
+    Comment,
+    /// Strings, template literals, regexes and numbers.
+    Literal,
+    Ident,
+    Punct,
+}
+
+#[derive(Debug)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+}
+
+/// Remove TypeScript type syntax, leaving plain JavaScript.
+pub fn strip_types(source: &str) -> String {
+    let tokens = tokenize(source);
+    let sig: Vec<usize> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !matches!(t.kind, Kind::Space | Kind::Comment))
+        .map(|(i, _)| i)
+        .collect();
+    let mut eraser = Eraser { drop: vec![false; tokens.len()], eat_line: vec![false; tokens.len()], tokens, sig };
+    eraser.run();
+
+    let mut out = String::with_capacity(source.len());
+    for (i, token) in eraser.tokens.iter().enumerate() {
+        if eraser.drop[i] {
+            continue;
+        }
+        if eraser.eat_line[i] {
+            out.push_str(token.text.split_once('\n').map_or("", |(_, rest)| rest));
+        } else {
+            out.push_str(token.text);
+        }
+    }
+    out
+}
+
+const NOT_METHODS: &[&str] = &["if", "for", "while", "switch", "with", "return", "typeof", "await", "yield", "new"];
+
+struct Eraser<'a> {
+    tokens: Vec<Token<'a>>,
+    /// Indices of the significant (non-space, non-comment) tokens.
+    sig: Vec<usize>,
+    drop: Vec<bool>,
+    /// Emit only what follows the first newline of this (space) token.
+    eat_line: Vec<bool>,
+}
+
+impl Eraser<'_> {
+    fn text(&self, p: usize) -> &str {
+        self.sig.get(p).map_or("", |&i| self.tokens[i].text)
+    }
+
+    fn kind(&self, p: usize) -> Option<Kind> {
+        self.sig.get(p).map(|&i| self.tokens[i].kind)
+    }
+
+    fn is_punct(&self, p: usize, s: &str) -> bool {
+        self.kind(p) == Some(Kind::Punct) && self.text(p) == s
+    }
+
+    fn is_word(&self, p: usize, s: &str) -> bool {
+        self.kind(p) == Some(Kind::Ident) && self.text(p) == s
+    }
+
+    fn gap(&self, p: usize) -> std::ops::Range<usize> {
+        let from = if p == 0 { 0 } else { self.sig[p - 1] + 1 };
+        from..self.sig.get(p).copied().unwrap_or(self.tokens.len())
+    }
+
+    fn newline_before(&self, p: usize) -> bool {
+        self.gap(p).any(|i| self.tokens[i].text.contains('\n'))
+    }
+
+    fn at_statement_start(&self, p: usize) -> bool {
+        p == 0 || matches!(self.text(p - 1), ";" | "{" | "}") || self.newline_before(p)
+    }
+
+    /// Drop significant tokens `from..to` and everything between them.
+    fn drop_range(&mut self, from: usize, to: usize) {
+        if from >= to {
+            return;
+        }
+        for i in self.sig[from]..=self.sig[to - 1] {
+            self.drop[i] = true;
+        }
+    }
+
+    /// Drop a whole declaration, along with its line if nothing else is on it.
+    fn drop_statement(&mut self, from: usize, mut to: usize) {
+        if self.is_punct(to, ";") {
+            to += 1;
+        }
+        self.drop_range(from, to);
+        let own_line = from == 0 || self.newline_before(from);
+        let next = self.sig.get(to - 1).map_or(self.tokens.len(), |&i| i + 1);
+        if own_line && self.tokens.get(next).is_some_and(|t| t.kind == Kind::Space && t.text.contains('\n')) {
+            self.eat_line[next] = true;
+        }
+    }
+
+    /// Index just past the bracket group opened at `p`.
+    fn balanced(&self, p: usize) -> Option<usize> {
+        let open = self.text(p).to_string();
+        let close = match open.as_str() {
+            "(" => ")",
+            "[" => "]",
+            "{" => "}",
+            "<" => ">",
+            _ => return None,
+        };
+        let mut depth = 0;
+        for q in p..self.sig.len() {
+            if self.kind(q) != Some(Kind::Punct) {
+                continue;
+            }
+            if self.text(q) == open {
+                depth += 1;
+            } else if self.text(q) == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(q + 1);
+                }
+            }
+        }
+        None
+    }
+
+    /// Index just past a type expression starting at `p`.
+    fn ty(&self, mut p: usize) -> Option<usize> {
+        if self.is_punct(p, "|") || self.is_punct(p, "&") {
+            p += 1;
+        }
+        p = self.ty_operand(p)?;
+        while self.is_punct(p, "|") || self.is_punct(p, "&") {
+            p = self.ty_operand(p + 1)?;
+        }
+        Some(p)
+    }
+
+    fn ty_operand(&self, mut p: usize) -> Option<usize> {
+        while ["keyof", "typeof", "readonly", "unique", "infer"].iter().any(|w| self.is_word(p, w)) {
+            p += 1;
+        }
+        if self.is_punct(p, "-") && self.kind(p + 1) == Some(Kind::Literal) {
+            p += 1;
+        }
+        p = match (self.kind(p)?, self.text(p)) {
+            (Kind::Ident, _) => {
+                p += 1;
+                while self.is_punct(p, ".") && self.kind(p + 1) == Some(Kind::Ident) {
+                    p += 2;
+                }
+                if self.is_punct(p, "<") {
+                    p = self.balanced(p)?;
+                }
+                if self.is_word(p, "is") {
+                    p = self.ty(p + 1)?;
+                }
+                p
+            }
+            (Kind::Literal, _) => p + 1,
+            (Kind::Punct, "{" | "[") => self.balanced(p)?,
+            (Kind::Punct, "(") => {
+                let end = self.balanced(p)?;
+                if self.is_punct(end, "=>") {
+                    self.ty(end + 1)?
+                } else {
+                    end
+                }
+            }
+            _ => return None,
+        };
+        while self.is_punct(p, "[") {
+            p = self.balanced(p)?;
+        }
+        Some(p)
+    }
+
+    /// Skip to just past the module specifier of an `import`/`export ... from` statement.
+    fn skip_module_clause(&self, mut p: usize) -> usize {
+        while p < self.sig.len() && !self.is_punct(p, ";") {
+            if self.kind(p) == Some(Kind::Literal) {
+                return p + 1;
+            }
+            if self.is_punct(p, "{") {
+                match self.balanced(p) {
+                    Some(end) if self.is_word(end, "from") => p = end,
+                    Some(end) => return end,
+                    None => return self.sig.len(),
+                }
+                continue;
+            }
+            p += 1;
+        }
+        p
+    }
+
+    fn run(&mut self) {
+        let mut p = 0;
+        while p < self.sig.len() {
+            let text = self.text(p).to_string();
+            let kind = self.kind(p);
+            let next = self.text(p + 1).to_string();
+            if kind == Some(Kind::Ident) {
+                match text.as_str() {
+                    "import" if next != "(" && next != "." => {
+                        p = self.skip_module_clause(p + 1);
+                        continue;
+                    }
+                    "export" if next == "{" || next == "*" => {
+                        p = self.skip_module_clause(p + 1);
+                        continue;
+                    }
+                    "interface" | "type" | "declare" if self.declaration_start(p) => {
+                        if let Some(end) = self.declaration_end(p) {
+                            let from = if p > 0 && self.is_word(p - 1, "export") { p - 1 } else { p };
+                            self.drop_statement(from, end);
+                            p = end;
+                            continue;
+                        }
+                    }
+                    "function" => {
+                        let mut q = p + 1;
+                        if self.is_punct(q, "*") {
+                            q += 1;
+                        }
+                        if self.kind(q) == Some(Kind::Ident) {
+                            q += 1;
+                        }
+                        if self.is_punct(q, "<") {
+                            if let Some(end) = self.balanced(q) {
+                                self.drop_range(q, end);
+                                q = end;
+                            }
+                        }
+                        if self.is_punct(q, "(") {
+                            self.erase_function(q);
+                        }
+                    }
+                    "let" | "const" | "var" => self.erase_declarator(p + 1),
+                    "as" | "satisfies" if self.casts_previous(p) => {
+                        if let Some(end) = self.ty(p + 1) {
+                            self.drop_range(p, end);
+                            for i in self.gap(p) {
+                                self.drop[i] = true; // the space before `as`
+                            }
+                            p = end;
+                            continue;
+                        }
+                    }
+                    _ => {
+                        if let Some(end) = self.call_type_args(p) {
+                            self.drop_range(p + 1, end);
+                            p = end;
+                            continue;
+                        }
+                    }
+                }
+            } else if text == "(" && kind == Some(Kind::Punct) {
+                self.erase_function_like(p);
+            } else if text == "!" && kind == Some(Kind::Punct) && self.is_non_null(p) {
+                self.drop_range(p, p + 1);
+            }
+            p += 1;
+        }
+    }
+
+    fn declaration_start(&self, p: usize) -> bool {
+        let start = if p > 0 && self.is_word(p - 1, "export") { p - 1 } else { p };
+        self.at_statement_start(start) && self.kind(p + 1) == Some(Kind::Ident)
+    }
+
+    /// End of an `interface`, `type` alias or `declare` statement at `p`.
+    fn declaration_end(&self, p: usize) -> Option<usize> {
+        match self.text(p) {
+            "interface" => {
+                let mut q = p + 2;
+                while q < self.sig.len() && !self.is_punct(q, "{") {
+                    q += 1;
+                }
+                self.balanced(q)
+            }
+            "type" => {
+                let mut q = p + 2;
+                if self.is_punct(q, "<") {
+                    q = self.balanced(q)?;
+                }
+                if !self.is_punct(q, "=") {
+                    return None;
+                }
+                self.ty(q + 1)
+            }
+            _ => {
+                // `declare const x: T;`, `declare function f(): T;`, ...
+                let mut q = p + 1;
+                while q < self.sig.len() && !self.is_punct(q, ";") {
+                    if self.is_punct(q, "{") {
+                        return self.balanced(q);
+                    }
+                    if q > p + 1 && self.newline_before(q) {
+                        return Some(q);
+                    }
+                    q += 1;
+                }
+                Some(q)
+            }
+        }
+    }
+
+    /// Whether the `as`/`satisfies` at `p` follows an expression.
+    fn casts_previous(&self, p: usize) -> bool {
+        if p == 0 || self.newline_before(p) {
+            return false;
+        }
+        match self.kind(p - 1) {
+            Some(Kind::Literal) => true,
+            Some(Kind::Ident) => !matches!(self.text(p - 1), "let" | "const" | "var" | "function" | "return"),
+            Some(Kind::Punct) => matches!(self.text(p - 1), ")" | "]" | "}"),
+            _ => false,
+        }
+    }
+
+    /// End of the type arguments in a generic call like `ref<number>(0)`.
+    ///
+    /// Only matches when nothing separates the name, the `<...>` and the `(`,
+    /// which keeps comparisons like `a < b > (c)` alone.
+    fn call_type_args(&self, p: usize) -> Option<usize> {
+        if !self.is_punct(p + 1, "<") || !self.gap(p + 1).is_empty() {
+            return None;
+        }
+        let mut q = p + 2;
+        loop {
+            q = self.ty(q)?;
+            if !self.is_punct(q, ",") {
+                break;
+            }
+            q += 1;
+        }
+        (self.is_punct(q, ">") && self.is_punct(q + 1, "(") && self.gap(q + 1).is_empty()).then_some(q + 1)
+    }
+
+    /// `x!` followed by member access, a call or the end of an expression.
+    fn is_non_null(&self, p: usize) -> bool {
+        p > 0
+            && self.gap(p).is_empty()
+            && (self.kind(p - 1) == Some(Kind::Ident) || matches!(self.text(p - 1), ")" | "]"))
+            && matches!(self.text(p + 1), "." | "?." | "[" | "(" | ")" | "]" | "," | ";" | "")
+    }
+
+    /// Annotation on a `let`/`const`/`var` binding starting at `p`.
+    fn erase_declarator(&mut self, p: usize) {
+        let after = match (self.kind(p), self.text(p)) {
+            (Some(Kind::Ident), _) => p + 1,
+            (Some(Kind::Punct), "{" | "[") => match self.balanced(p) {
+                Some(end) => end,
+                None => return,
+            },
+            _ => return,
+        };
+        let colon = if self.is_punct(after, "!") { after + 1 } else { after };
+        if self.is_punct(colon, ":") {
+            if let Some(end) = self.ty(colon + 1) {
+                self.drop_range(after, end);
+            }
+        }
+    }
+
+    /// Parameters and return type of `function name(...)`.
+    fn erase_function(&mut self, open: usize) {
+        let Some(close) = self.balanced(open) else {
+            return;
+        };
+        self.erase_params(open, close);
+        if self.is_punct(close, ":") {
+            if let Some(end) = self.ty(close + 1) {
+                self.drop_range(close, end);
+            }
+        }
+    }
+
+    /// Methods (`name(...) {`) and arrow functions (`(...) =>`).
+    fn erase_function_like(&mut self, open: usize) {
+        if open > 0 && self.is_word(open - 1, "function")
+            || open > 1 && self.kind(open - 1) == Some(Kind::Ident) && self.is_word(open - 2, "function")
+        {
+            return; // handled with the `function` keyword
+        }
+        let Some(close) = self.balanced(open) else {
+            return;
+        };
+        let method = open > 0
+            && self.kind(open - 1) == Some(Kind::Ident)
+            && !NOT_METHODS.contains(&self.text(open - 1));
+        if self.is_punct(close, "=>") || method && self.is_punct(close, "{") {
+            self.erase_params(open, close);
+        } else if self.is_punct(close, ":") {
+            let Some(end) = self.ty(close + 1) else {
+                return;
+            };
+            if self.is_punct(end, "=>") || method && self.is_punct(end, "{") {
+                self.erase_params(open, close);
+                self.drop_range(close, end);
+            }
+        }
+    }
+
+    /// Annotations and `?` markers on the parameters between `open` and `close - 1`.
+    fn erase_params(&mut self, open: usize, close: usize) {
+        let last = close - 1;
+        let mut q = open + 1;
+        while q < last {
+            if self.is_punct(q, "...") {
+                q += 1;
+            }
+            let after = match (self.kind(q), self.text(q)) {
+                (Some(Kind::Ident), _) => Some(q + 1),
+                (Some(Kind::Punct), "{" | "[") => self.balanced(q),
+                _ => None,
+            };
+            if let Some(mut after) = after {
+                if self.is_punct(after, "?") && self.is_punct(after + 1, ":") {
+                    self.drop_range(after, after + 1);
+                    after += 1;
+                }
+                if self.is_punct(after, ":") {
+                    if let Some(end) = self.ty(after + 1).filter(|&end| end <= last) {
+                        self.drop_range(after, end);
+                        after = end;
+                    }
+                }
+                q = after;
+            }
+            // Skip the default value to the next parameter
+            let mut depth = 0;
+            while q < last {
+                match self.text(q) {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth -= 1,
+                    "," if depth == 0 => break,
+                    _ => {}
+                }
+                q += 1;
+            }
+            q += 1;
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut pos = 0;
+    while pos < source.len() {
+        let c = source[pos..].chars().next().unwrap();
+        let rest = &source[pos..];
+        let (kind, len) = if c.is_whitespace() {
+            (Kind::Space, rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len()))
+        } else if rest.starts_with("//") {
+            (Kind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            (Kind::Comment, body.find("*/").map_or(rest.len(), |e| e + 4))
+        } else if c == '"' || c == '\'' {
+            (Kind::Literal, quoted_len(rest, c))
+        } else if c == '`' {
+            (Kind::Literal, template_len(rest))
+        } else if c == '/' && regex_allowed(&tokens) {
+            (Kind::Literal, regex_len(rest))
+        } else if c.is_ascii_digit() || c == '.' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit) {
+            (Kind::Literal, rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_')).unwrap_or(rest.len()))
+        } else if c == '_' || c == '$' || c.is_alphabetic() {
+            (
+                Kind::Ident,
+                rest.find(|c: char| !(c == '_' || c == '$' || c.is_alphanumeric())).unwrap_or(rest.len()),
+            )
+        } else {
+            let op = ["===", "!==", "...", "=>", "==", "!=", "&&", "||", "??", "?."]
+                .iter()
+                .find(|op| rest.starts_with(**op) && !(**op == "?." && bytes.get(pos + 2).is_some_and(u8::is_ascii_digit)));
+            (Kind::Punct, op.map_or(c.len_utf8(), |op| op.len()))
+        };
+        tokens.push(Token { kind, text: &source[pos..pos + len] });
+        pos += len;
+    }
+    tokens
+}
+
+/// Whether a `/` after these tokens starts a regex rather than a division.
+fn regex_allowed(tokens: &[Token]) -> bool {
+    let Some(prev) = tokens.iter().rev().find(|t| !matches!(t.kind, Kind::Space | Kind::Comment)) else {
+        return true;
+    };
+    match prev.kind {
+        Kind::Literal => false,
+        Kind::Ident => matches!(
+            prev.text,
+            "return" | "typeof" | "case" | "do" | "else" | "in" | "of" | "new" | "delete" | "void" | "throw" | "yield" | "await"
+        ),
+        _ => !matches!(prev.text, ")" | "]" | "}"),
+    }
+}
+
+fn quoted_len(s: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\n' => return i,
+            c if c == quote => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+fn template_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'`' => return i + 1,
+            b'$' if bytes.get(i + 1) == Some(&b'{') => {
+                let mut depth = 0;
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'{' => depth += 1,
+                        b'}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        b'`' => i += template_len(&s[i..]) - 1,
+                        q @ (b'"' | b'\'') => i += quoted_len(&s[i..], q as char) - 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    s.len()
+}
+
+fn regex_len(s: &str) -> usize {
+    let mut in_class = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '\n' => return i,
+            '/' if !in_class => {
+                let flags = s[i + 1..].find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(s.len() - i - 1);
+                return i + 1 + flags;
+            }
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_annotated_functions() {
+        let ts = "function pad(n: number, width?: number): string {\n  return String(n).padStart(width ?? 2, '0');\n}\nconst double = (x: number): number => x * 2;\nlet total: number = 0;\n";
+        assert_eq!(
+            strip_types(ts),
+            "function pad(n, width) {\n  return String(n).padStart(width ?? 2, '0');\n}\nconst double = (x) => x * 2;\nlet total = 0;\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_interface_and_type_alias() {
+        let ts = "interface Opts {\n  width: number;\n  fill?: string;\n}\nexport type Mode = 'a' | 'b';\nfunction format(value: unknown, opts: Opts = { width: 2 }): string {\n  return String(value);\n}\n";
+        assert_eq!(
+            strip_types(ts),
+            "function format(value, opts = { width: 2 }) {\n  return String(value);\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_generics_casts_and_non_null() {
+        let ts = "function first<T extends object>(items: Array<T>): T | undefined {\n  return items[0] as T;\n}\nconst el = document.getElementById('app')!.textContent as string;\nconst cfg = { a: 1 } as const;\n";
+        assert_eq!(
+            strip_types(ts),
+            "function first(items) {\n  return items[0];\n}\nconst el = document.getElementById('app').textContent;\nconst cfg = { a: 1 };\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_keeps_plain_js() {
+        let js = "const msg = 'a: number' + `x ${y ? 1 : 2}` // as string\nconst obj = { a: 1, b: c ? d : e };\nconst re = /as: T/g;\nimport { a as b } from './x.js'\nfunction f(a, b = { x: 1 }) { return a / b.x; }\nconst lt = a<b && c>(d);\n";
+        assert_eq!(strip_types(js), js);
+    }
+
+    #[test]
+    fn test_strip_methods_and_callbacks() {
+        let ts = "const count = ref<number>(0);\nconst map = new Map<string, Array<number>>();\nconst api = {\n  load(id: string, cb: (err: Error | null) => void): void {\n    cb(null);\n  },\n};\nitems.map((item: Item, i: number) => item.id + i);\n";
+        assert_eq!(
+            strip_types(ts),
+            "const count = ref(0);\nconst map = new Map();\nconst api = {\n  load(id, cb) {\n    cb(null);\n  },\n};\nitems.map((item, i) => item.id + i);\n"
+        );
+    }
+}